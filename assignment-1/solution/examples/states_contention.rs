@@ -0,0 +1,151 @@
+//! Compara la espera de los dispensers por el lock de `ContainersStates` cuando un lector que reporta periodicamente toma el
+//! lock para leer los estados, contra cuando lee la copia publicada (`ContainersStates::snapshots`).
+//!
+//! El lector reporta cada `REPORT_INTERVAL` escribiendo una linea por contenedor en un archivo, como el logger del SYSTEM-ALERT.
+//! Cada dispenser toma un contenedor, espera el tiempo de aplicacion y lo libera, actualizando los estados cada vez.
+//!
+//! ```txt
+//! cargo run --release --example states_contention -- <dispensers> <pedidos por dispenser> <microsegundos de aplicacion>
+//! ```
+use std::{
+    env,
+    fs::File,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use tp1::{
+    conteiners_states::{ContainersSnapshot, ContainersStates},
+    enums::{IngredientType, StateOfConteiner},
+    lock_contention::{LockContention, LockedResource},
+};
+
+const INGREDIENTS: [IngredientType; 4] = [
+    IngredientType::Agua,
+    IngredientType::CafeMolido,
+    IngredientType::EspumaLeche,
+    IngredientType::Cacao,
+];
+
+/// Tiempo entre reportes del lector
+const REPORT_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Forma en que el lector accede a los estados de los contenedores
+#[derive(Debug, Clone, Copy)]
+enum Reader {
+    /// Toma el lock de `ContainersStates` mientras arma el reporte, como antes de publicar las copias
+    Lock,
+    /// Arma el reporte con la ultima copia publicada, sin tomar el lock
+    Snapshot,
+}
+
+/// Escribe las lineas que reporta el SYSTEM-ALERT para los contenedores, de a una como el logger
+fn write_report(log: &mut File, states: &ContainersSnapshot) {
+    for (tipo, (state, quantity)) in &states.principal_conteiners {
+        let _ = log.write_all(format!("{:?} -> {:?} {:?}\n", tipo, state, quantity).as_bytes());
+    }
+    for (tipo, quantity) in &states.quantity_to_recharge {
+        let _ = log.write_all(format!("{:?} -> {:?}\n", tipo, quantity).as_bytes());
+    }
+}
+
+/// Ejecuta los dispensers, que toman y liberan los contenedores `orders` veces cada uno, mientras el lector reporta
+fn run(reader: Reader, dispensers: usize, orders: usize, apply: Duration) -> LockContention {
+    let pair_conteiners_states = Arc::new((Mutex::new(ContainersStates::default()), Condvar::new()));
+    let contention = Arc::new(LockContention::default());
+    let done = Arc::new(AtomicBool::new(false));
+
+    let reporter = {
+        let (pair_conteiners_states, done) = (pair_conteiners_states.clone(), done.clone());
+        thread::spawn(move || {
+            let mut log = File::create(env::temp_dir().join("tp1_states_contention.log")).unwrap();
+            let snapshots = pair_conteiners_states.0.lock().unwrap().snapshots();
+            let mut reports = 0;
+            while !done.load(Ordering::Relaxed) {
+                match reader {
+                    Reader::Lock => {
+                        let states = pair_conteiners_states.0.lock().unwrap();
+                        let copy = ContainersSnapshot {
+                            principal_conteiners: states.principal_conteiners.clone(),
+                            quantity_to_recharge: states.quantity_to_recharge.clone(),
+                        };
+                        write_report(&mut log, &copy);
+                    }
+                    Reader::Snapshot => write_report(&mut log, &snapshots.load()),
+                }
+                reports += 1;
+                thread::sleep(REPORT_INTERVAL);
+            }
+            reports
+        })
+    };
+
+    let handles = (0..dispensers)
+        .map(|id| {
+            let (pair_conteiners_states, contention) =
+                (pair_conteiners_states.clone(), contention.clone());
+            thread::Builder::new()
+                .name(format!("[ DISPENSER#{} ]", id))
+                .spawn(move || {
+                    let tipo = INGREDIENTS[id % INGREDIENTS.len()];
+                    for i in 0..orders {
+                        let lock = &pair_conteiners_states.0;
+                        let mut states = contention
+                            .lock(LockedResource::ContainersStates, lock)
+                            .unwrap();
+                        states.set_state(i as f32, StateOfConteiner::Taken, &tipo);
+                        drop(states);
+                        thread::sleep(apply);
+
+                        let mut states = contention
+                            .lock(LockedResource::ContainersStates, lock)
+                            .unwrap();
+                        states.set_state(i as f32, StateOfConteiner::Free, &tipo);
+                        states.notify_waiters_of(&tipo);
+                    }
+                })
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    handles
+        .into_iter()
+        .for_each(|handle| handle.join().unwrap());
+    done.store(true, Ordering::Relaxed);
+    let reports = reporter.join().unwrap();
+    println!("{:?}: {} reports", reader, reports);
+
+    Arc::try_unwrap(contention).unwrap_or_default()
+}
+
+fn main() {
+    let args: Vec<usize> = env::args().skip(1).filter_map(|x| x.parse().ok()).collect();
+    let dispensers = args.first().copied().unwrap_or(32);
+    let orders = args.get(1).copied().unwrap_or(2000);
+    let apply = Duration::from_micros(args.get(2).copied().unwrap_or(100) as u64);
+
+    println!(
+        "{} dispensers, {} orders each applied in {:?}. Wait for the ContainersStates lock (total, mean, max):",
+        dispensers, orders, apply
+    );
+    for reader in [Reader::Lock, Reader::Snapshot] {
+        let start = Instant::now();
+        let contention = run(reader, dispensers, orders, apply);
+        for (_, stats) in contention.by_resource().unwrap() {
+            println!(
+                "    {:?} -> {:?}, {:?}, {:?} in {} acquisitions ({:?} elapsed)",
+                reader,
+                stats.total_wait,
+                stats.mean_wait(),
+                stats.max_wait,
+                stats.acquisitions,
+                start.elapsed()
+            );
+        }
+    }
+}
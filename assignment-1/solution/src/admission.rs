@@ -0,0 +1,174 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    enums::IngredientType,
+    error_dispenser::ErrorCafeteria,
+    order::Order,
+    sync::{Arc, Condvar, Mutex},
+    utils::Consts,
+};
+
+/// Control de admision de los pedidos: limita cuantos pedidos pueden estar procesandose a la vez con cada ingrediente, asi
+/// una rafaga de pedidos que requieren un mismo ingrediente (por ejemplo cacao) no ocupa todos los dispensers esperando por
+/// ese contenedor mientras los pedidos que solo requieren otros ingredientes esperan en la cola.
+///
+/// Los dispensers toman de la cola el pedido mas antiguo que puede admitirse (ver `Dispenser::wait_admitted_pedido`), y al
+/// finalizarlo lo liberan (`release`) para que se puedan admitir los pedidos que estaban esperando.
+#[derive(Debug, Default)]
+pub struct AdmissionControl {
+    /// Cantidad maxima de pedidos en proceso con cada ingrediente. Los ingredientes que no estan no tienen limite
+    limits: HashMap<IngredientType, usize>,
+
+    /// Cantidad de pedidos en proceso con cada ingrediente limitado
+    in_flight: Mutex<HashMap<IngredientType, usize>>,
+}
+
+impl AdmissionControl {
+    /// Crea el control de admision a partir de las variables de entorno `K_LIMITE_AGUA`, `K_LIMITE_CAFE_MOLIDO`,
+    /// `K_LIMITE_ESPUMA_LECHE` y `K_LIMITE_CACAO`.
+    pub fn from_env() -> Self {
+        AdmissionControl::default()
+            .with_limit(IngredientType::Agua, Consts::k_limite_agua())
+            .with_limit(IngredientType::CafeMolido, Consts::k_limite_cafe_molido())
+            .with_limit(IngredientType::EspumaLeche, Consts::k_limite_espuma_leche())
+            .with_limit(IngredientType::Cacao, Consts::k_limite_cacao())
+    }
+
+    /// Limita la cantidad de pedidos en proceso con el ingrediente. Con limite 0 el ingrediente no tiene limite.
+    pub fn with_limit(mut self, tipo: IngredientType, limit: usize) -> Self {
+        if limit > 0 {
+            self.limits.insert(tipo, limit);
+        } else {
+            self.limits.remove(&tipo);
+        }
+        self
+    }
+
+    /// Saca de la cola el pedido mas antiguo que puede admitirse sin superar ningun limite, y lo cuenta como en proceso.
+    ///
+    /// Debe llamarse con el lock de la cola de pedidos tomado, asi `release` no puede liberar lugar entre que se revisa la
+    /// cola y el dispenser se pone a esperar en su condvar.
+    ///
+    /// # Returns
+    /// * `Result<Option<(Order, Vec<IngredientType>)>, ErrorCafeteria>`:
+    ///    * Si es Ok(Some), el pedido admitido y los ingredientes limitados que se contaron, para pasarselos a `release`.
+    ///    * Si es Ok(None), ningun pedido de la cola puede admitirse.
+    ///    * Si es Err, hubo un error al tomar el lock de los pedidos en proceso.
+    pub fn admit_from(
+        &self,
+        orders: &mut VecDeque<Order>,
+    ) -> Result<Option<(Order, Vec<IngredientType>)>, ErrorCafeteria> {
+        let mut in_flight = self
+            .in_flight
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+
+        let limited_ingredients = |order: &Order| -> Vec<IngredientType> {
+            self.limits
+                .keys()
+                .filter(|tipo| order.requiere(tipo))
+                .copied()
+                .collect()
+        };
+
+        // los pedidos se insertan adelante y se toman de atras, asi que el mas antiguo es el ultimo
+        let admitted = (0..orders.len()).rev().find(|i| {
+            limited_ingredients(&orders[*i])
+                .iter()
+                .all(|tipo| in_flight.get(tipo).copied().unwrap_or(0) < self.limits[tipo])
+        });
+
+        Ok(admitted.and_then(|i| orders.remove(i)).map(|order| {
+            let counted = limited_ingredients(&order);
+            counted
+                .iter()
+                .for_each(|tipo| *in_flight.entry(*tipo).or_default() += 1);
+            (order, counted)
+        }))
+    }
+
+    /// Libera los ingredientes de un pedido que finalizo y notifica a los dispensers que esperan en la cola de pedidos,
+    /// ya que puede haber pedidos que ahora pueden admitirse.
+    ///
+    /// # Arguments
+    ///  * `counted` - Ingredientes que se contaron al admitir el pedido (ver `admit_from`).
+    ///  * `pair_vecdeque_orders` - Par de VecDeque y Condvar que representa la cola de pedidos.
+    pub fn release(
+        &self,
+        counted: &[IngredientType],
+        pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+    ) -> Result<(), ErrorCafeteria> {
+        if counted.is_empty() {
+            return Ok(());
+        }
+
+        {
+            let mut in_flight = self
+                .in_flight
+                .lock()
+                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+            counted.iter().for_each(|tipo| {
+                if let Some(count) = in_flight.get_mut(tipo) {
+                    *count = count.saturating_sub(1);
+                }
+            });
+        }
+
+        // se toma el lock de la cola para que ningun dispenser quede esperando sin ver el lugar liberado
+        let (lock, cvar) = &**pair_vecdeque_orders;
+        let _guard = lock
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+        cvar.notify_all();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_orders_over_the_limit_wait_while_other_orders_are_admitted() {
+        let admission = AdmissionControl::default().with_limit(IngredientType::Cacao, 1);
+        let pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)> =
+            Arc::new((Mutex::new(Some(VecDeque::new())), Condvar::new()));
+
+        // el mas antiguo queda al final de la cola
+        let mut orders = VecDeque::from([
+            Order::new_with_id(2, 0.0, 0.0, 0.0, 5.0),
+            Order::new_with_id(1, 0.0, 0.0, 1.0, 5.0),
+            Order::new_with_id(0, 0.0, 0.0, 1.0, 0.0),
+        ]);
+
+        let (first, counted) = admission.admit_from(&mut orders).unwrap().unwrap();
+        assert_eq!(first.id.into_inner(), 0);
+        assert_eq!(counted, vec![IngredientType::Cacao]);
+
+        // el pedido 1 tambien requiere cacao, asi que se admite el 2 que solo requiere agua
+        let (second, counted_second) = admission.admit_from(&mut orders).unwrap().unwrap();
+        assert_eq!(second.id.into_inner(), 2);
+        assert!(counted_second.is_empty());
+        assert!(admission.admit_from(&mut orders).unwrap().is_none());
+
+        admission.release(&counted, &pair_vecdeque_orders).unwrap();
+        let (third, _) = admission.admit_from(&mut orders).unwrap().unwrap();
+        assert_eq!(third.id.into_inner(), 1);
+        assert!(orders.is_empty());
+    }
+
+    #[test]
+    fn test2_without_limits_every_order_is_admitted_in_order() {
+        let admission = AdmissionControl::default().with_limit(IngredientType::Cacao, 0);
+        let mut orders = VecDeque::from([
+            Order::new_with_id(1, 0.0, 0.0, 1.0, 0.0),
+            Order::new_with_id(0, 0.0, 0.0, 1.0, 0.0),
+        ]);
+
+        for id in 0..2 {
+            let (order, counted) = admission.admit_from(&mut orders).unwrap().unwrap();
+            assert_eq!(order.id.into_inner(), id);
+            assert!(counted.is_empty());
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+use crate::{
+    conteiners_states::ContainersSnapshot, enums::IngredientType, error_dispenser::ErrorCafeteria,
+    sync::reloj,
+};
+
+/// Ingredientes de los contenedores principales, en el orden de las columnas del CSV
+const PRINCIPAL_COLUMNS: [IngredientType; 4] = [
+    IngredientType::Agua,
+    IngredientType::CafeMolido,
+    IngredientType::EspumaLeche,
+    IngredientType::Cacao,
+];
+
+/// Ingredientes de los contenedores de recarga, en el orden de las columnas del CSV
+const RECHARGE_COLUMNS: [IngredientType; 2] =
+    [IngredientType::LecheFria, IngredientType::GranosCafe];
+
+/// Encabezado del CSV de las alertas periodicas
+pub const CSV_HEADER: &str =
+    "segundos,agua,cafe_molido,espuma_leche,cacao,leche_fria,granos_cafe,procesados,completados";
+
+/// Serie de tiempo de las alertas periodicas: agrega al final de un archivo CSV una fila por cada muestra del SYSTEM-ALERT,
+/// con los segundos desde que se abrio el archivo, los gramos de cada contenedor y la cantidad de pedidos procesados y
+/// completados, para poder graficar como se vacian los contenedores luego de la ejecucion.
+#[derive(Debug)]
+pub struct AlertCsv {
+    /// Archivo CSV en el que se agregan las muestras
+    writer: BufWriter<File>,
+
+    /// Instante desde el que se miden los segundos de cada muestra
+    start: Instant,
+}
+
+impl AlertCsv {
+    /// Abre (o crea) el archivo CSV para agregar muestras al final. Si el archivo esta vacio, escribe el encabezado.
+    ///
+    /// # Arguments
+    ///  * `path` - Ruta del archivo CSV.
+    /// # Returns
+    /// * `Result<AlertCsv, ErrorCafeteria>`:
+    ///    * Si es Ok, el CSV listo para agregar muestras.
+    ///    * Si es Err, no se pudo abrir el archivo o escribir el encabezado.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ErrorCafeteria> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+        let empty = file
+            .metadata()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .len()
+            == 0;
+
+        let mut csv = AlertCsv {
+            writer: BufWriter::new(file),
+            start: reloj().ahora(),
+        };
+        if empty {
+            csv.write_line(CSV_HEADER)?;
+        }
+        Ok(csv)
+    }
+
+    /// Agrega una muestra al final del CSV. Los contenedores que no estan en los estados se escriben vacios.
+    ///
+    /// # Arguments
+    ///  * `states` - Copia de los estados de los contenedores, con la cantidad actual de cada uno.
+    ///  * `processed` - Cantidad de pedidos procesados hasta el momento.
+    ///  * `completed` - Cantidad de pedidos completados hasta el momento.
+    pub fn append(
+        &mut self,
+        states: &ContainersSnapshot,
+        processed: usize,
+        completed: usize,
+    ) -> Result<(), ErrorCafeteria> {
+        let seconds = reloj()
+            .ahora()
+            .saturating_duration_since(self.start)
+            .as_secs_f64();
+        let principal = PRINCIPAL_COLUMNS.iter().map(|tipo| {
+            states
+                .principal_conteiners
+                .get(tipo)
+                .map(|(_, quantity)| quantity.to_string())
+        });
+        let recharge = RECHARGE_COLUMNS.iter().map(|tipo| {
+            states
+                .quantity_to_recharge
+                .get(tipo)
+                .map(|quantity| quantity.to_string())
+        });
+
+        let mut row = vec![format!("{:.3}", seconds)];
+        row.extend(principal.chain(recharge).map(Option::unwrap_or_default));
+        row.push(processed.to_string());
+        row.push(completed.to_string());
+        self.write_line(&row.join(","))
+    }
+
+    /// Escribe una linea y la baja al archivo, asi el CSV queda completo aunque la cafeteria termine abruptamente
+    fn write_line(&mut self, line: &str) -> Result<(), ErrorCafeteria> {
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        conteiners_states::ContainersStates, enums::StateOfConteiner, sync::sleep, utils::Consts,
+    };
+
+    #[test]
+    fn test1_samples_are_appended_after_the_header() {
+        let path = std::env::temp_dir().join("tp1_alert_csv_test1.csv");
+        let _ = fs::remove_file(&path);
+        let mut states = ContainersStates::default();
+
+        let mut csv = AlertCsv::open(&path).unwrap();
+        csv.append(&states.snapshot(), 0, 0).unwrap();
+        sleep(std::time::Duration::from_secs(3));
+        states.set_state(12.5, StateOfConteiner::Free, &IngredientType::Cacao);
+        csv.append(&states.snapshot(), 2, 1).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        let (seconds, first) = lines[1].split_once(',').unwrap();
+        assert!(seconds.parse::<f64>().is_ok());
+        assert_eq!(
+            first,
+            format!(
+                "{},{},{},{},{},{},0,0",
+                Consts::a_agua_caliente(),
+                Consts::m_granos_molidos(),
+                Consts::e_espuma_leche(),
+                Consts::c_cacao(),
+                Consts::l_leche_fria(),
+                Consts::g_granos()
+            )
+        );
+        let second: Vec<&str> = lines[2].split(',').collect();
+        assert!(second[0].parse::<f64>().unwrap() >= seconds.parse::<f64>().unwrap() + 3.0);
+        assert_eq!(second[4], "12.5");
+        assert_eq!(&second[7..], &["2", "1"]);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test2_reopening_an_existing_csv_does_not_repeat_the_header() {
+        let path = std::env::temp_dir().join("tp1_alert_csv_test2.csv");
+        let _ = fs::remove_file(&path);
+        let states = ContainersStates::default().snapshot();
+
+        AlertCsv::open(&path)
+            .unwrap()
+            .append(&states, 1, 1)
+            .unwrap();
+        AlertCsv::open(&path)
+            .unwrap()
+            .append(&states, 2, 2)
+            .unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().filter(|l| *l == CSV_HEADER).count(), 1);
+        assert_eq!(content.lines().count(), 3);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,248 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use log::{error, info};
+
+use crate::{
+    conteiners_states::ContainersSnapshot,
+    enums::{IngredientType, StateOfConteiner},
+    rejections::RejectionHistogram,
+    sync::{Arc, Mutex},
+    utils::Consts,
+};
+
+/// Muestra periodica del estado del sistema que toma el SYSTEM-ALERT.
+///
+/// Es una copia de los estados de los contenedores y de los pedidos finalizados, asi el destino puede tardar (por ejemplo
+/// enviandola por un socket) sin retener los locks que usan los dispensers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlertSample {
+    /// Estado y cantidad actual de cada contenedor principal (ver `ContainersStates::principal_conteiners`)
+    pub principal_conteiners: HashMap<IngredientType, (StateOfConteiner, f32)>,
+
+    /// Cantidad disponible para recargar de cada contenedor de recarga (ver `ContainersStates::quantity_to_recharge`)
+    pub quantity_to_recharge: HashMap<IngredientType, f32>,
+
+    /// Cantidad de pedidos procesados hasta el momento
+    pub processed: usize,
+
+    /// Cantidad de pedidos completados hasta el momento
+    pub completed: usize,
+
+    /// Pedidos cancelados por falta de recursos hasta el momento
+    pub rejections: RejectionHistogram,
+}
+
+impl AlertSample {
+    /// Toma la muestra con la ultima copia publicada de los estados de los contenedores
+    ///
+    /// # Arguments
+    ///  * `states` - Copia de los estados de los contenedores.
+    ///  * `processed` - Cantidad de pedidos procesados hasta el momento.
+    ///  * `completed` - Cantidad de pedidos completados hasta el momento.
+    ///  * `rejections` - Pedidos cancelados por falta de recursos hasta el momento.
+    pub fn new(
+        states: &ContainersSnapshot,
+        processed: usize,
+        completed: usize,
+        rejections: RejectionHistogram,
+    ) -> Self {
+        AlertSample {
+            principal_conteiners: states.principal_conteiners.clone(),
+            quantity_to_recharge: states.quantity_to_recharge.clone(),
+            processed,
+            completed,
+            rejections,
+        }
+    }
+}
+
+/// Destino de las muestras periodicas del SYSTEM-ALERT.
+///
+/// Permite llevar las muestras a otro lado ademas del log (por ejemplo a un socket, a una base de datos o al cluster del TP2),
+/// registrando la implementacion con `Cafeteria::with_alert_sink` o al crear el SYSTEM-ALERT con `create_and_run_system_alert`.
+pub trait AlertSink: Send {
+    /// Recibe una muestra del estado del sistema. Se llama desde el thread del SYSTEM-ALERT, sin ningun lock tomado
+    ///
+    /// # Arguments
+    /// * `sample` - Muestra del estado del sistema
+    fn report(&mut self, sample: &AlertSample);
+}
+
+/// Destino por defecto de las muestras: las reporta en el log con `info!`, con el nivel de cada contenedor como porcentaje
+/// de su capacidad.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogAlertSink {
+    /// Capacidad total de cada contenedor, principal o de recarga
+    capacities: HashMap<IngredientType, f32>,
+}
+
+impl LogAlertSink {
+    /// Crea el destino con las capacidades de los contenedores de `Consts`
+    pub fn new() -> Self {
+        LogAlertSink {
+            capacities: HashMap::from([
+                (IngredientType::Agua, Consts::a_agua_caliente()),
+                (IngredientType::CafeMolido, Consts::m_granos_molidos()),
+                (IngredientType::EspumaLeche, Consts::e_espuma_leche()),
+                (IngredientType::Cacao, Consts::c_cacao()),
+                (IngredientType::LecheFria, Consts::l_leche_fria()),
+                (IngredientType::GranosCafe, Consts::g_granos()),
+            ]),
+        }
+    }
+
+    /// Porcentaje de la capacidad del contenedor que representa la cantidad. Los contenedores sin capacidad conocida son 0
+    fn percentage(&self, tipo: &IngredientType, quantity: f32) -> f32 {
+        match self.capacities.get(tipo) {
+            Some(capacity) => quantity / capacity * 100.0,
+            None => 0.0,
+        }
+    }
+}
+
+impl Default for LogAlertSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlertSink for LogAlertSink {
+    fn report(&mut self, sample: &AlertSample) {
+        info!("[ SYSTEM ALERT ]: Level of conteiners:");
+        sample
+            .principal_conteiners
+            .iter()
+            .for_each(|(key, (_, quantity))| {
+                info!(
+                    "                    {:?} -> {:?}% para usar",
+                    key,
+                    self.percentage(key, *quantity)
+                );
+            });
+        sample
+            .quantity_to_recharge
+            .iter()
+            .for_each(|(key, quantity)| {
+                info!(
+                    "                    {:?} -> {:?}% para recargar",
+                    key,
+                    self.percentage(key, *quantity)
+                );
+            });
+
+        info!(
+            "[ SYSTEM ALERT ]: Cantidad pedidos totales procesados. {:?}",
+            sample.processed
+        );
+        info!(
+            "[ SYSTEM ALERT ]: Cantidad pedidos completados. {:?}/{:?}",
+            sample.completed, sample.processed
+        );
+        if sample.rejections.rejected > 0 {
+            sample.rejections.report();
+        }
+    }
+}
+
+/// Destino registrado en la Cafetería, compartido entre sus ejecuciones: cada SYSTEM-ALERT recibe un `Box` que reenvia las
+/// muestras al mismo destino.
+#[derive(Clone)]
+pub(crate) struct SharedAlertSink(Arc<Mutex<Box<dyn AlertSink>>>);
+
+impl SharedAlertSink {
+    /// Comparte el destino entre las ejecuciones
+    pub(crate) fn new(sink: Box<dyn AlertSink>) -> Self {
+        SharedAlertSink(Arc::new(Mutex::new(sink)))
+    }
+}
+
+impl AlertSink for SharedAlertSink {
+    fn report(&mut self, sample: &AlertSample) {
+        match self.0.lock() {
+            Ok(mut sink) => sink.report(sample),
+            Err(error) => error!("[ SYSTEM ALERT ]: {}", error),
+        }
+    }
+}
+
+impl Debug for SharedAlertSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedAlertSink").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::{
+        conteiners_states::ContainersStates,
+        enums::OrderState,
+        order::Order,
+        periodic_alert::create_and_run_system_alert,
+        sync::{mpsc, Condvar},
+    };
+
+    /// Destino que envia las muestras por un canal
+    struct ChannelSink(mpsc::Sender<AlertSample>);
+
+    impl AlertSink for ChannelSink {
+        fn report(&mut self, sample: &AlertSample) {
+            let _ = self.0.send(sample.clone());
+        }
+    }
+
+    #[test]
+    fn test1_samples_are_reported_to_the_registered_sink() {
+        let mut completed = Order::new_with_id(0, 0.0, 0.0, 0.0, 10.0);
+        completed.status = OrderState::Completed;
+        let mut rejected = Order::new_with_id(1, 0.0, 0.0, 5.0, 10.0);
+        rejected.status = OrderState::NoEnoughResourceContainer;
+        let pair_vecdeque_system_alert = Arc::new((
+            Mutex::new(VecDeque::from([completed, rejected])),
+            Condvar::new(),
+        ));
+        let pair_conteiners_states =
+            Arc::new((Mutex::new(ContainersStates::default()), Condvar::new()));
+        let (sender, receiver) = mpsc::channel();
+
+        let system_alert = create_and_run_system_alert(
+            pair_vecdeque_system_alert,
+            pair_conteiners_states,
+            2,
+            None,
+            Box::new(ChannelSink(sender)),
+        );
+        system_alert.handle.unwrap().join().unwrap().unwrap();
+
+        let samples: Vec<AlertSample> = receiver.try_iter().collect();
+        let last = samples.last().unwrap();
+        assert_eq!(last.processed, 2);
+        assert_eq!(last.completed, 1);
+        assert_eq!(last.rejections.rejected, 1);
+        assert_eq!(
+            last.principal_conteiners[&IngredientType::Cacao].1,
+            Consts::c_cacao()
+        );
+        assert_eq!(
+            last.quantity_to_recharge[&IngredientType::GranosCafe],
+            Consts::g_granos()
+        );
+    }
+
+    #[test]
+    fn test2_the_shared_sink_forwards_to_the_same_sink() {
+        let (sender, receiver) = mpsc::channel();
+        let shared = SharedAlertSink::new(Box::new(ChannelSink(sender)));
+        let sample = AlertSample {
+            processed: 3,
+            ..AlertSample::default()
+        };
+
+        shared.clone().report(&sample);
+        shared.clone().report(&sample);
+
+        assert_eq!(receiver.try_iter().count(), 2);
+    }
+}
@@ -1,15 +1,33 @@
+use crate::alert_sink::{AlertSink, LogAlertSink, SharedAlertSink};
 use crate::conteiners::Conteiners;
 use crate::conteiners_states::ContainersStates;
-use crate::dispenser::{create_and_run_dispensers, send_signal_poweroff_to_dispensers};
+use crate::dispenser::send_signal_poweroff_to_dispensers;
+use crate::enums::{OrderState, Scheduler};
 use crate::error_dispenser::ErrorCafeteria;
 use crate::file_orders;
-use crate::order::{insert_orders, Order};
-use crate::periodic_alert::create_and_run_system_alert;
-use crate::sync::{Arc, Condvar, Mutex};
+use crate::order::{Order, OrderIds};
+use crate::periodic_alert::{create_and_run_system_alert, TotalOrders};
+use crate::pipeline::create_and_run_pipeline;
+use crate::queue_monitor::QueueMonitor;
+use crate::recipes::RecipeStats;
+use crate::rejections::RejectionHistogram;
+use crate::replay::{Replay, ReplayOutcome};
+use crate::respawn::create_and_run_monitored_dispensers;
+use crate::shutdown::ShutdownWatchdog;
+use crate::sources::insert_source_orders;
+use crate::sync::{mpsc::Sender, Arc, Condvar, Mutex};
+use crate::traits::OrderSource;
+use crate::utils::Consts;
+use crate::warm_up::WarmUp;
 use log::{debug, error, info};
-use std::{collections::VecDeque, path::Path};
-
-/// Comenzar la ejecución de la Cafetería
+use std::{
+    collections::{HashMap, VecDeque},
+    path::Path,
+    time::Duration,
+};
+
+/// Comenzar la ejecución de la Cafetería, con el calentamiento configurado por las variables de entorno
+/// (idem a `Cafeteria::new().start(path)`)
 /// # Arguments
 /// * `file` - Ruta del archivo de pedidos a procesar
 /// # Returns
@@ -21,48 +39,349 @@ pub fn start<P>(path: P) -> Result<(), ErrorCafeteria>
 where
     P: AsRef<Path>,
 {
-    ///////// INIT CONDVARS, ORDERS, ETCS /////////
-    let (
-        orders_to_process,
-        pair_vecdeque_orders,
-        pair_vecdeque_system_alert,
-        pair_conteiners_states,
-        arc_containers,
-    ) = init_elements(path)?;
+    Cafeteria::new().start(path)
+}
 
-    //////// THREADS DISPENSERS ////////
-    let dispensers = create_and_run_dispensers(
-        &pair_vecdeque_orders,
-        &pair_vecdeque_system_alert,
-        &pair_conteiners_states,
-        arc_containers,
-    );
+/// Comenzar la ejecución de la Cafetería con los pedidos de una fuente cualquiera (por ejemplo la entrada estandar, el generador
+/// o varias fuentes combinadas con `Sources`) (idem a `Cafeteria::new().start_with_source(source)`)
+/// # Arguments
+/// * `source` - Fuente de los pedidos a procesar
+/// # Returns
+/// * `Ok()` - Si se procesaron todos los pedidos correctamente
+/// * `Err(ErrorCafeteria)` - Si la fuente termino con un error (ver `OrderSource::finish`)
+pub fn start_with_source<S: OrderSource + Send>(source: S) -> Result<(), ErrorCafeteria> {
+    Cafeteria::new().start_with_source(source)
+}
 
-    //////// THREAD SYSTEM ALERT ////////
-    let system_alert = create_and_run_system_alert(
-        pair_vecdeque_system_alert,
-        pair_conteiners_states,
-        orders_to_process.len(),
-    );
+/// Comenzar la ejecución determinista de la Cafetería en un unico thread (ver `Replay`), con las decisiones de las variables de
+/// entorno (`J_SEMILLA` o `J_REPRODUCIR_DECISIONES`). Si esta definido `J_GRABAR_DECISIONES`, se graban las decisiones tomadas
+/// para repetir la ejecución
+/// # Arguments
+/// * `file` - Ruta del archivo de pedidos a procesar
+/// # Returns
+/// * `Ok(ReplayOutcome)` - Las decisiones tomadas y el resultado de la ejecución
+/// * `Err(ErrorCafeteria)` - Si ocurrió alguno de los siguientes errores:
+///    * Error al leer el archivo de pedidos o el de decisiones
+///    * Alguna decision grabada no corresponde a los pedidos
+///    * Error al grabar las decisiones
+pub fn start_replay<P>(path: P) -> Result<ReplayOutcome, ErrorCafeteria>
+where
+    P: AsRef<Path>,
+{
+    let outcome = Replay::from_env()?.run(file_orders::read_orders(path)?)?;
+    outcome.report();
+    if let Some(path) = Consts::j_grabar_decisiones() {
+        outcome.save_decisions(path)?;
+    }
+    Ok(outcome)
+}
+
+/// Configuracion de la ejecución de la Cafetería, se arma encadenando los `with_*`
+#[derive(Debug, Clone, Default)]
+pub struct Cafeteria {
+    /// Calentamiento de la maquina que se ejecuta antes de insertar los pedidos
+    warm_up: WarmUp,
+
+    /// Forma en que se reparten los pedidos entre los threads que los procesan
+    scheduler: Scheduler,
+
+    /// Canal por el que el SYSTEM-ALERT envia cada pedido apenas finaliza (completado o cancelado)
+    completed_orders: Option<Sender<Order>>,
+
+    /// Plazo para que los dispensers terminen los pedidos luego de la señal de apagado. Si es None no hay plazo
+    shutdown_deadline: Option<Duration>,
+
+    /// Tiempo que la cola de pedidos puede estar llena antes de alertar que los dispensers estan saturados. Si es None no se alerta
+    queue_saturation: Option<Duration>,
 
-    //////// INSERTION ORDERS ////////
-    if let Err(error) = insert_orders(orders_to_process, &pair_vecdeque_orders) {
-        error!("[ SYSTEM-ALERT ] Error insertion orders: {:?}", error);
+    /// Contenedores de los ingredientes. Si es None cada ejecución usa `Conteiners::default()`
+    containers: Option<Arc<Conteiners>>,
+
+    /// Destino de las muestras periodicas del SYSTEM-ALERT. Si es None cada ejecución las reporta en el log (ver `LogAlertSink`)
+    alert_sink: Option<SharedAlertSink>,
+
+    /// Contador de los ids de los pedidos creados con `Order::new` para esta Cafetería
+    order_ids: OrderIds,
+}
+
+impl Cafeteria {
+    /// Crea la Cafetería con el calentamiento, el scheduler y el plazo de apagado configurados por las variables de entorno
+    /// (ver `WarmUp::from_env`, `Consts::s_scheduler` y `Consts::f_plazo_apagado`)
+    pub fn new() -> Self {
+        Cafeteria {
+            warm_up: WarmUp::from_env(),
+            scheduler: Consts::s_scheduler(),
+            completed_orders: None,
+            shutdown_deadline: Some(Duration::from_secs_f32(Consts::f_plazo_apagado().max(0.0)))
+                .filter(|deadline| !deadline.is_zero()),
+            queue_saturation: Some(Duration::from_secs_f32(
+                Consts::u_saturacion_cola().max(0.0),
+            ))
+            .filter(|threshold| !threshold.is_zero()),
+            containers: None,
+            alert_sink: None,
+            order_ids: OrderIds::new(),
+        }
     }
 
-    //////// SEND SIGNAL "None" IN CODVAR for DISPENSERS OF END OF ORDERS ////////
-    if let Err(error) = send_signal_poweroff_to_dispensers(pair_vecdeque_orders) {
-        error!(
-            "[ SYSTEM-ALERT ] Error send signal for poweroff : {:?}",
-            error
-        );
+    /// Reemplaza el calentamiento de la maquina
+    pub fn with_warm_up(mut self, warm_up: WarmUp) -> Self {
+        self.warm_up = warm_up;
+        self
+    }
+
+    /// Comienza a aceptar pedidos sin calentar la maquina, por ejemplo en los tests
+    pub fn without_warm_up(self) -> Self {
+        self.with_warm_up(WarmUp::default())
+    }
+
+    /// Reemplaza la forma en que se reparten los pedidos
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Aborta los pedidos que los dispensers no terminaron dentro de `deadline` luego de la señal de apagado
+    /// (ver `ShutdownWatchdog`)
+    pub fn with_shutdown_deadline(mut self, deadline: Duration) -> Self {
+        self.shutdown_deadline = Some(deadline);
+        self
+    }
+
+    /// Alerta que los dispensers estan saturados cuando la cola de pedidos esta llena mas tiempo que `threshold`
+    /// (ver `QueueMonitor`)
+    pub fn with_queue_saturation(mut self, threshold: Duration) -> Self {
+        self.queue_saturation = Some(threshold);
+        self
+    }
+
+    /// Envia por `sender` cada pedido apenas finaliza, para consumirlos mientras la Cafetería sigue procesando
+    /// en lugar de esperar al join del SYSTEM-ALERT.
+    ///
+    /// Como la Cafetería conserva el `sender`, el `Receiver` deja de recibir (por ejemplo, termina de iterarse) cuando
+    /// se descarta la Cafetería.
+    pub fn with_completed_orders(mut self, sender: Sender<Order>) -> Self {
+        self.completed_orders = Some(sender);
+        self
+    }
+
+    /// Reemplaza los contenedores de los ingredientes, por ejemplo con contenedores propios registrados con
+    /// `Conteiners::with_container`.
+    ///
+    /// Los contenedores se comparten entre las ejecuciones de la Cafetería, asi que cada ejecución continua con las
+    /// cantidades que dejo la anterior.
+    pub fn with_containers(mut self, containers: Conteiners) -> Self {
+        self.containers = Some(Arc::new(containers));
+        self
     }
 
-    //////// JOIN THREADS ////////
-    join_dispensers(dispensers);
-    join_system_alert(system_alert);
+    /// Reemplaza el destino de las muestras periodicas del SYSTEM-ALERT, que por defecto se reportan en el log.
+    ///
+    /// El destino se comparte entre las ejecuciones de la Cafetería, asi que recibe las muestras de todas ellas.
+    pub fn with_alert_sink<S: AlertSink + 'static>(mut self, sink: S) -> Self {
+        self.alert_sink = Some(SharedAlertSink::new(Box::new(sink)));
+        self
+    }
+
+    /// Contador de los ids de esta Cafetería, para crear con `Order::new` los pedidos que se le envian por un `OrderSender`.
+    /// Los clones de la Cafetería comparten el contador
+    pub fn order_ids(&self) -> OrderIds {
+        self.order_ids.clone()
+    }
+
+    /// Comenzar la ejecución de la Cafetería
+    ///
+    /// Los pedidos se leen del archivo a medida que se insertan en la cola (ver `stream_orders`), asi que no se conoce su
+    /// cantidad total hasta terminar de leerlo. Si una linea tiene un error se dejan de leer pedidos, se terminan de procesar
+    /// los ya insertados y se retorna el error.
+    /// # Arguments
+    /// * `file` - Ruta del archivo de pedidos a procesar
+    /// # Returns
+    /// * `Ok()` - Si se procesaron todos los pedidos correctamente
+    /// * `Err(ErrorCafeteria)` - Si ocurrió alguno de los siguientes errores:
+    ///    * Error al leer el archivo de pedidos
+    ///    * Error al insertar los pedidos en la cola de pedidos
+    pub fn start<P>(&self, path: P) -> Result<(), ErrorCafeteria>
+    where
+        P: AsRef<Path>,
+    {
+        self.start_with_source(file_orders::stream_orders(path)?)
+    }
+
+    /// Comenzar la ejecución de la Cafetería con los pedidos de una fuente cualquiera (ver `OrderSource`), por ejemplo el
+    /// archivo de ordenes, la entrada estandar, un archivo que se sigue escribiendo, un socket TCP, el generador, el canal de
+    /// `order_channel` o varias de ellas combinadas con `Sources`.
+    ///
+    /// Cada pedido se inserta en la cola apenas finalizan los pedidos de los que depende, aunque la fuente siga esperando el
+    /// proximo (ver `insert_source_orders`). La señal de apagado recien se envia a los dispensers cuando la fuente ya no tiene
+    /// mas pedidos, y si la fuente no conoce de antemano la cantidad total (ver `OrderSource::total_orders`) recien ahi se le
+    /// informa al SYSTEM-ALERT. Si la fuente termina con un error, se terminan de procesar los pedidos ya insertados y se
+    /// retorna el error.
+    /// # Arguments
+    /// * `source` - Fuente de los pedidos a procesar
+    /// # Returns
+    /// * `Ok()` - Si se procesaron todos los pedidos correctamente
+    /// * `Err(ErrorCafeteria)` - Si la fuente termino con un error (ver `OrderSource::finish`)
+    pub fn start_with_source<S: OrderSource + Send>(
+        &self,
+        mut source: S,
+    ) -> Result<(), ErrorCafeteria> {
+        ///////// INIT CONDVARS, ETCS /////////
+        let total_orders = source
+            .total_orders()
+            .map_or_else(TotalOrders::unknown, TotalOrders::from);
+
+        self.run(
+            init_elements_with_orders(Vec::new()),
+            total_orders,
+            |_, pair_vecdeque_orders, pair_finished_orders| {
+                insert_source_orders(&mut source, pair_vecdeque_orders, pair_finished_orders)
+            },
+        )?;
+
+        source.finish()
+    }
+
+    /// Ejecuta los dispensers y el SYSTEM-ALERT, calienta la maquina, inserta los pedidos con `insert` y espera a que terminen todos
+    /// # Arguments
+    /// * `elements` - Elementos inicializados de la Cafetería
+    /// * `total_orders` - Cantidad de pedidos que se van a insertar, para que el SYSTEM-ALERT sepa cuando terminar. Si no se conoce,
+    ///   se settea con la cantidad de pedidos que inserto `insert`
+    /// * `insert` - Thread principal productor que inserta los pedidos en la cola de pedidos, luego del calentamiento, y retorna
+    ///   la cantidad de pedidos que inserto
+    fn run<F>(
+        &self,
+        elements: InitElements,
+        total_orders: TotalOrders,
+        insert: F,
+    ) -> Result<(), ErrorCafeteria>
+    where
+        F: FnOnce(
+            Vec<Order>,
+            &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+            &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+        ) -> Result<usize, ErrorCafeteria>,
+    {
+        let (
+            orders_to_process,
+            pair_vecdeque_orders,
+            pair_vecdeque_system_alert,
+            pair_conteiners_states,
+            arc_containers,
+            pair_finished_orders,
+        ) = elements;
+
+        let arc_containers = self.containers.clone().unwrap_or(arc_containers);
+        let containers = arc_containers.clone();
+        let conteiners_states = pair_conteiners_states.clone();
+
+        //////// THREADS DISPENSERS ////////
+        let create_and_run = match self.scheduler {
+            Scheduler::Dispensers => create_and_run_monitored_dispensers,
+            Scheduler::Pipeline => create_and_run_pipeline,
+        };
+        let dispensers = create_and_run(
+            &pair_vecdeque_orders,
+            &pair_vecdeque_system_alert,
+            &pair_finished_orders,
+            &pair_conteiners_states,
+            arc_containers,
+        );
+
+        //////// THREAD SYSTEM ALERT ////////
+        let system_alert = create_and_run_system_alert(
+            pair_vecdeque_system_alert.clone(),
+            pair_conteiners_states,
+            total_orders.clone(),
+            self.completed_orders.clone(),
+            match self.alert_sink.clone() {
+                Some(sink) => Box::new(sink),
+                None => Box::new(LogAlertSink::new()),
+            },
+        );
+
+        //////// THREAD QUEUE MONITOR ////////
+        let queue_monitor = QueueMonitor::start(
+            self.queue_saturation,
+            pair_vecdeque_orders.clone(),
+            pair_vecdeque_system_alert.clone(),
+            conteiners_states.clone(),
+        )
+        .map_err(|error| error!("[ SYSTEM-ALERT ] Error queue monitor: {:?}", error))
+        .ok();
+
+        //////// WARM-UP ////////
+        if let Err(error) = self.warm_up.run(&containers) {
+            error!("[ SYSTEM-ALERT ] Error warm-up: {:?}", error);
+        }
+
+        //////// INSERTION ORDERS ////////
+        match insert(
+            orders_to_process,
+            &pair_vecdeque_orders,
+            &pair_finished_orders,
+        ) {
+            Ok(inserted) if total_orders.get().is_none() => {
+                if let Err(error) = total_orders.set(inserted, &pair_vecdeque_system_alert) {
+                    error!("[ SYSTEM-ALERT ] Error set total orders: {:?}", error);
+                }
+            }
+            Ok(_) => {}
+            Err(error) => error!("[ SYSTEM-ALERT ] Error insertion orders: {:?}", error),
+        }
+
+        //////// SHUTDOWN DEADLINE ////////
+        let watchdog = self.shutdown_deadline.and_then(|deadline| {
+            ShutdownWatchdog::start(
+                deadline,
+                pair_vecdeque_orders.clone(),
+                conteiners_states.clone(),
+                pair_vecdeque_system_alert.clone(),
+                pair_finished_orders.clone(),
+            )
+            .map_err(|error| error!("[ SYSTEM-ALERT ] Error shutdown watchdog: {:?}", error))
+            .ok()
+        });
+
+        //////// SEND SIGNAL "None" IN CODVAR for DISPENSERS OF END OF ORDERS ////////
+        if let Err(error) = send_signal_poweroff_to_dispensers(pair_vecdeque_orders) {
+            error!(
+                "[ SYSTEM-ALERT ] Error send signal for poweroff : {:?}",
+                error
+            );
+        }
+
+        //////// JOIN THREADS ////////
+        join_dispensers(dispensers);
+        let queue_stats = queue_monitor.map(QueueMonitor::finish);
+        join_system_alert(system_alert);
+        if let Some(Err(error)) = watchdog.map(ShutdownWatchdog::finish) {
+            error!("[ SYSTEM-ALERT ] Error shutdown watchdog: {:?}", error);
+        }
+
+        //////// REPORT QUEUE DEPTHS ////////
+        match queue_stats {
+            Some(Ok(stats)) => stats.report(),
+            Some(Err(error)) => error!("[ SYSTEM-ALERT ] Error queue monitor: {:?}", error),
+            None => {}
+        }
+
+        //////// REPORT LOCK CONTENTION ////////
+        if let Err(error) = containers.contention.report() {
+            error!("[ SYSTEM-ALERT ] Error report lock contention: {:?}", error);
+        }
 
-    Ok(())
+        //////// REPORT DISPENSER WAKEUPS ////////
+        match conteiners_states.0.lock() {
+            Ok(states) => states.wakeups.report(),
+            Err(error) => error!(
+                "[ SYSTEM-ALERT ] Error report dispenser wakeups: {:?}",
+                error
+            ),
+        }
+
+        Ok(())
+    }
 }
 
 /// Tupla de elementos necesarios para la ejecución de la Cafetería
@@ -72,6 +391,7 @@ pub type InitElements = (
     Arc<(Mutex<VecDeque<Order>>, Condvar)>,
     Arc<(Mutex<ContainersStates>, Condvar)>,
     Arc<Conteiners>,
+    Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
 );
 
 /// Inicializa los elementos necesarios para la ejecución de la Cafetería
@@ -89,16 +409,23 @@ pub type InitElements = (
 ///     * `Arc<(Mutex<VecDeque<Order>>, Condvar)>`: Sirve para implementar modelo productor-consumidor entre los
 ///         threads `N_DISPENSERS` y el thread `SYSTEM-ALERT`. Los threads productores envian los pedidos procesados a la cola para que el
 ///         SYSTEM-ALERT consumidor los tome y reporte estadisticas periodicas de los pedidos procesados.
-///     * `Arc<(Mutex<ContainersStates>, Condvar)>`: Sirve para notificar y esperar (mediante la condivar) y acceder (con el mutex) a los
-///         diferentes estados de los contenedores mediante `ContainersStates`.
+///     * `Arc<(Mutex<ContainersStates>, Condvar)>`: Sirve para acceder (con el mutex) a los diferentes estados de los contenedores
+///       mediante `ContainersStates`. Los dispensers esperan los cambios de estado en sus propios Condvar, registrados en
+///       `ContainersStates` segun los contenedores que requiere cada pedido.
 ///     * `Arc<Conteiners>`: Sirve para compartir los contenedores entre los diferentes dispensers.
+///     * `Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>`: Los dispensers registran el estado final de cada pedido que terminan
+///       de procesar segun su id, y notifican mediante la condvar al thread principal para que inserte los pedidos que dependian de el.
 /// * `Err(ErrorCafeteria)` - Si ocurrió alguno de los siguientes errores:
 ///     * Error al leer el archivo de pedidos
 pub fn init_elements<P>(file: P) -> Result<InitElements, ErrorCafeteria>
 where
     P: AsRef<Path>,
 {
-    let orders = file_orders::read_orders(file)?;
+    Ok(init_elements_with_orders(file_orders::read_orders(file)?))
+}
+
+/// Idem a `init_elements` pero con los pedidos ya cargados
+pub fn init_elements_with_orders(orders: Vec<Order>) -> InitElements {
     let orders_to_process = VecDeque::new();
     let pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)> =
         Arc::new((Mutex::new(Some(orders_to_process)), Condvar::new()));
@@ -110,18 +437,22 @@ where
         Arc::new((Mutex::new(states), Condvar::new()));
     let containers = Conteiners::default();
     let arc_containers = Arc::new(containers);
-    Ok((
+    let pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)> =
+        Arc::new((Mutex::new(HashMap::new()), Condvar::new()));
+    (
         orders,
         pair_vecdeque_orders,
         pair_vecdeque_system_alert,
         pair_conteiners_states,
         arc_containers,
-    ))
+        pair_finished_orders,
+    )
 }
 
 /// Thread principal productor encargado de hacer join del thread `SYSTEM-ALERT`.
 /// Ademas se reporta las ordenes totales procesadas en el sistema segun su (id, status).
-/// Donde su status puede ser `OrderState::NoEnoughResourceContainer` o `OrderState::Completed`.
+/// Donde su status puede ser `OrderState::NoEnoughResourceContainer`, `OrderState::ExceedsCapacity`, `OrderState::Aborted`
+/// o `OrderState::Completed`.
 ///
 /// En caso de que alguno de los threads dispensers haya terminado su ejecucion con error
 /// se lo reporta en el log mediante la macro `error!`.
@@ -131,6 +462,8 @@ pub fn join_system_alert(system_alert: crate::periodic_alert::PeriodicAlert) {
             if let Ok(result) = handle.join() {
                 match result {
                     Ok(orders) => {
+                        RejectionHistogram::from_orders(orders.iter()).report();
+                        RecipeStats::from_orders(orders.iter()).report();
                         info!(
                             "[ SYSTEM-ALERT ] Orders processed (id, status): {:?}",
                             orders
@@ -170,7 +503,8 @@ pub fn join_dispensers(dispensers: Vec<crate::dispenser::Dispenser>) {
 #[cfg(test)]
 mod tests1 {
     use crate::{
-        enums::{IngredientType, OrderState},
+        dispenser::create_and_run_dispensers,
+        enums::{ErrorType, IngredientType, OrderState, StateOfConteiner},
         order::insert_orders,
         periodic_alert::create_and_run_system_alert,
     };
@@ -211,12 +545,14 @@ mod tests1 {
                 pair_vecdeque_system_alert,
                 pair_conteiners_states,
                 arc_containers,
+                pair_finished_orders,
             ) = init_elements(Path::new("test1.txt")).unwrap();
 
             //////// THREADS DISPENSERS ////////
             let dispensers = create_and_run_dispensers(
                 &pair_vecdeque_orders,
                 &pair_vecdeque_system_alert,
+                &pair_finished_orders,
                 &pair_conteiners_states,
                 arc_containers,
             );
@@ -226,6 +562,8 @@ mod tests1 {
                 pair_vecdeque_system_alert,
                 pair_conteiners_states,
                 orders.len(),
+                None,
+                Box::new(LogAlertSink::new()),
             );
 
             //////// INSERTION ORDERS ////////
@@ -300,12 +638,14 @@ mod tests1 {
                 pair_vecdeque_system_alert,
                 pair_conteiners_states,
                 arc_containers,
+                pair_finished_orders,
             ) = init_elements(Path::new("test2.txt")).unwrap();
 
             //////// THREADS DISPENSERS ////////
             let dispensers = create_and_run_dispensers(
                 &pair_vecdeque_orders,
                 &pair_vecdeque_system_alert,
+                &pair_finished_orders,
                 &pair_conteiners_states,
                 arc_containers,
             );
@@ -315,6 +655,8 @@ mod tests1 {
                 pair_vecdeque_system_alert,
                 pair_conteiners_states,
                 orders.len(),
+                None,
+                Box::new(LogAlertSink::new()),
             );
 
             //////// INSERTION ORDERS ////////
@@ -376,12 +718,14 @@ mod tests1 {
             pair_vecdeque_system_alert,
             pair_conteiners_states,
             arc_containers,
+            pair_finished_orders,
         ) = init_elements(Path::new("test3.txt")).unwrap();
 
         //////// THREADS DISPENSERS ////////
         let dispensers = create_and_run_dispensers(
             &pair_vecdeque_orders,
             &pair_vecdeque_system_alert,
+            &pair_finished_orders,
             &pair_conteiners_states,
             arc_containers.clone(),
         );
@@ -391,6 +735,8 @@ mod tests1 {
             pair_vecdeque_system_alert,
             pair_conteiners_states,
             orders.len(),
+            None,
+            Box::new(LogAlertSink::new()),
         );
 
         //////// INSERTION ORDERS ////////
@@ -508,12 +854,14 @@ mod tests1 {
             pair_vecdeque_system_alert,
             pair_conteiners_states,
             arc_containers,
+            pair_finished_orders,
         ) = init_elements(Path::new("test4.txt")).unwrap();
 
         //////// THREADS DISPENSERS ////////
         let dispensers = create_and_run_dispensers(
             &pair_vecdeque_orders,
             &pair_vecdeque_system_alert,
+            &pair_finished_orders,
             &pair_conteiners_states,
             arc_containers.clone(),
         );
@@ -523,6 +871,8 @@ mod tests1 {
             pair_vecdeque_system_alert,
             pair_conteiners_states,
             orders.len(),
+            None,
+            Box::new(LogAlertSink::new()),
         );
 
         //////// INSERTION ORDERS ////////
@@ -608,4 +958,212 @@ mod tests1 {
         std::fs::remove_file("test4.env").unwrap();
         std::fs::remove_file("test4.txt").unwrap();
     }
+
+    #[test]
+    fn test5_completed_orders_are_received_while_the_cafeteria_is_running() {
+        let orders_content = "A5 M2\nA5 E1\nA10 M1 E2";
+        let mut orders_file = File::create("test5.txt").unwrap();
+        orders_file.write_all(orders_content.as_bytes()).unwrap();
+
+        let (sender, receiver) = crate::sync::mpsc::channel();
+        let cafeteria = Cafeteria::default().with_completed_orders(sender);
+        let running = std::thread::spawn(move || cafeteria.start(Path::new("test5.txt")));
+
+        // el Receiver termina de iterarse cuando la Cafeteria se descarta al finalizar el thread
+        let received = receiver
+            .iter()
+            .map(|order| (order.id.load(Ordering::SeqCst), order.status))
+            .collect::<Vec<_>>();
+        running.join().unwrap().unwrap();
+
+        assert_eq!(received.len(), 3);
+        assert!(received
+            .iter()
+            .all(|(_, status)| *status == OrderState::Completed));
+        assert!(received.iter().map(|(id, _)| id).all_unique());
+
+        std::fs::remove_file("test5.txt").unwrap();
+    }
+
+    #[test]
+    fn test6_orders_sent_while_running_are_processed_until_every_sender_is_dropped() {
+        let (order_sender, order_receiver) = crate::order_sender::order_channel();
+        let (sender, receiver) = crate::sync::mpsc::channel();
+        let cafeteria = Cafeteria::default().with_completed_orders(sender);
+        let ids = cafeteria.order_ids();
+        let running = std::thread::spawn(move || cafeteria.start_with_source(order_receiver));
+
+        for _ in 0..6 {
+            order_sender
+                .send(Order::new(&ids, 1.0, 0.0, 0.0, 1.0))
+                .unwrap();
+        }
+        drop(order_sender);
+
+        running.join().unwrap().unwrap();
+        assert_eq!(
+            receiver
+                .iter()
+                .filter(|order| order.status == OrderState::Completed)
+                .count(),
+            6
+        );
+    }
+
+    #[test]
+    fn test7_order_exceeding_the_capacity_of_a_container_is_rejected_without_taking_containers() {
+        let orders_content = format!("A{} M1\nA5 M1", Consts::a_agua_caliente() + 1.0);
+        let mut orders_file = File::create("test7.txt").unwrap();
+        orders_file.write_all(orders_content.as_bytes()).unwrap();
+
+        let (sender, receiver) = crate::sync::mpsc::channel();
+        let cafeteria = Cafeteria::default().with_completed_orders(sender);
+        cafeteria.start(Path::new("test7.txt")).unwrap();
+        drop(cafeteria);
+
+        let mut received = receiver
+            .iter()
+            .map(|order| (order.id.load(Ordering::SeqCst), order.status))
+            .collect::<Vec<_>>();
+        received.sort_by_key(|(id, _)| *id);
+
+        assert_eq!(
+            received,
+            vec![(0, OrderState::ExceedsCapacity), (1, OrderState::Completed)]
+        );
+        std::fs::remove_file("test7.txt").unwrap();
+    }
+
+    /// Contenedor de cacao sin limite que cuenta cuantas veces se aplico
+    struct CountingCacao {
+        applied: Arc<Mutex<usize>>,
+    }
+
+    impl crate::traits::ApplyContainer for CountingCacao {
+        fn apply_ingredient(&mut self, order: &mut Order) {
+            order.apply(IngredientType::Cacao);
+            *self.applied.lock().unwrap() += 1;
+        }
+
+        fn update_and_notify_state(
+            &mut self,
+            mut states: crate::sync::MutexGuard<ContainersStates>,
+        ) {
+            states.set_state(1.0, StateOfConteiner::Free, &IngredientType::Cacao);
+            states.notify_waiters_of(&IngredientType::Cacao);
+        }
+
+        fn set_taken_state(&mut self, mut states: crate::sync::MutexGuard<ContainersStates>) {
+            states.set_state(1.0, StateOfConteiner::Taken, &IngredientType::Cacao);
+        }
+
+        fn get_statistic(&self, _: IngredientType) -> Option<f32> {
+            None
+        }
+    }
+
+    #[test]
+    fn test8_orders_are_processed_with_a_registered_custom_container() {
+        let applied = Arc::new(Mutex::new(0));
+        let containers = Conteiners::default()
+            .with_container(
+                IngredientType::Cacao,
+                Box::new(CountingCacao {
+                    applied: applied.clone(),
+                }),
+            )
+            .unwrap();
+        assert!(Conteiners::default()
+            .with_container(
+                IngredientType::GranosCafe,
+                Box::new(CountingCacao {
+                    applied: applied.clone(),
+                }),
+            )
+            .is_err());
+
+        let (order_sender, order_receiver) = crate::order_sender::order_channel();
+        let (sender, receiver) = crate::sync::mpsc::channel();
+        let cafeteria = Cafeteria::default()
+            .with_containers(containers)
+            .with_completed_orders(sender);
+        let ids = cafeteria.order_ids();
+        let running = std::thread::spawn(move || cafeteria.start_with_source(order_receiver));
+
+        for _ in 0..4 {
+            order_sender
+                .send(Order::new(&ids, 0.0, 0.0, 5.0, 1.0))
+                .unwrap();
+        }
+        drop(order_sender);
+
+        running.join().unwrap().unwrap();
+        assert_eq!(
+            receiver
+                .iter()
+                .filter(|order| order.status == OrderState::Completed)
+                .count(),
+            4
+        );
+        assert_eq!(*applied.lock().unwrap(), 4);
+    }
+
+    #[test]
+    fn test9_orders_read_before_an_incorrect_line_are_processed_and_the_error_is_returned() {
+        let mut orders_file = File::create("test9.txt").unwrap();
+        orders_file
+            .write_all("A5 M1\nA5 M1\nA5 D7\nA5 M1".as_bytes())
+            .unwrap();
+
+        let (sender, receiver) = crate::sync::mpsc::channel();
+        let cafeteria = Cafeteria::default().with_completed_orders(sender);
+        let error = cafeteria.start(Path::new("test9.txt")).unwrap_err();
+        drop(cafeteria);
+
+        assert_eq!(error.type_error, ErrorType::IncorrectOrderFile);
+        let mut received = receiver
+            .iter()
+            .map(|order| order.id.load(Ordering::SeqCst))
+            .collect::<Vec<_>>();
+        received.sort();
+        assert_eq!(received, vec![0, 1]);
+        std::fs::remove_file("test9.txt").unwrap();
+    }
+
+    #[test]
+    fn test10_orders_received_by_tcp_through_the_order_source_trait_wait_for_their_dependencies() {
+        let tcp = crate::tcp_orders::TcpOrders::bind("127.0.0.1:0")
+            .unwrap()
+            .with_end_marker("FIN")
+            .with_poll_interval(Duration::from_millis(10));
+        let address = tcp.local_addr().unwrap();
+
+        let (sender, receiver) = crate::sync::mpsc::channel();
+        let cafeteria = Cafeteria::default().with_completed_orders(sender);
+        let running = std::thread::spawn(move || cafeteria.start_with_source(tcp));
+
+        // dos conexiones, la segunda con la marca de fin
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        stream.write_all(b"A5 M1\nA5 D0\n").unwrap();
+        drop(stream);
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        stream.write_all(b"A5 D1\nFIN\n").unwrap();
+        drop(stream);
+
+        // el Receiver termina de iterarse cuando la Cafeteria se descarta al finalizar el thread
+        let received = receiver
+            .iter()
+            .map(|order| (order.id.load(Ordering::SeqCst), order.status))
+            .collect::<Vec<_>>();
+        running.join().unwrap().unwrap();
+
+        assert_eq!(
+            received,
+            vec![
+                (0, OrderState::Completed),
+                (1, OrderState::Completed),
+                (2, OrderState::Completed)
+            ]
+        );
+    }
 }
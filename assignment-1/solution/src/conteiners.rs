@@ -1,9 +1,16 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use crate::admission::AdmissionControl;
+use crate::conteiners_states::ContainersStates;
 use crate::enums::IngredientType;
 use crate::error_dispenser::ErrorCafeteria;
+use crate::lock_contention::{LockContention, LockedResource};
+use crate::order::Order;
+use crate::pantry::Pantry;
 use crate::set_conteiners::infinity_conteiner::InfinityConteiner;
 use crate::set_conteiners::no_rechargable_conteiner::NoRechargableConteiner;
 use crate::set_conteiners::rechargable_conteiner::RechargableConteiner;
-use crate::sync::{Arc, Mutex, MutexGuard};
+use crate::sync::{Arc, Condvar, Mutex, MutexGuard};
 use crate::traits::ApplyContainer;
 use crate::utils::Consts;
 
@@ -13,6 +20,9 @@ use crate::utils::Consts;
 /// Ademas como contenedor, en el arc mutex se guarda un objeto contenedor que implementa
 /// el trait ApplyContainer, logrando un polimorfismo para los diferentes tipos de contenedores
 /// que existan en la cafeteria.
+///
+/// Ademas de los contenedores por defecto (`Conteiners::default()`), se puede registrar un contenedor propio para cada
+/// ingrediente con `with_container`.
 pub struct Conteiners {
     /// Contenedor de agua que implementa el trait ApplyContainer
     pub agua: Arc<Mutex<Box<dyn ApplyContainer + Send>>>,
@@ -25,12 +35,20 @@ pub struct Conteiners {
 
     /// Contenedor de leche espumada que implementa el trait ApplyContainer
     pub leche_espuma: Arc<Mutex<Box<dyn ApplyContainer + Send>>>,
+
+    /// Tiempos de espera de los dispensers para tomar los locks de los contenedores y del `ContainersStates`
+    pub contention: LockContention,
+
+    /// Limites de pedidos en proceso con cada ingrediente
+    pub admission: AdmissionControl,
 }
 
 impl Conteiners {
     /// Se retorna un MutexGuard de un contenedor que implementa el trait ApplyContainer,
     /// logrando un polimorfismo segun el tipo de contenedor solicitado por parametro.
     ///
+    /// El tiempo que el dispenser actual espera para tomar el lock se registra en `contention`.
+    ///
     /// # Arguments
     ///  * `IngredientType` - Tipo de ingrediente al que se le quiere obtener su contenedor respectivo.
     /// # Returns
@@ -42,25 +60,184 @@ impl Conteiners {
         &'a self,
         tipo: IngredientType,
     ) -> Result<MutexGuard<'a, Box<dyn ApplyContainer + Send + 'static>>, ErrorCafeteria> {
-        match tipo {
-            IngredientType::Agua => Ok(self
-                .agua
-                .lock()
-                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?),
-            IngredientType::Cacao => Ok(self
-                .cacao
-                .lock()
-                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?),
-            IngredientType::CafeMolido => Ok(self
-                .cafe_molido
+        let container = match tipo {
+            IngredientType::Agua => &self.agua,
+            IngredientType::Cacao => &self.cacao,
+            IngredientType::CafeMolido => &self.cafe_molido,
+            IngredientType::EspumaLeche => &self.leche_espuma,
+            _ => return Err(ErrorCafeteria::new("No existe el tipo de ingrediente")),
+        };
+        self.contention
+            .lock(LockedResource::Container(tipo), container)
+    }
+
+    /// Registra un contenedor propio para el ingrediente recibido (por ejemplo un mock, un contenedor que falla o uno
+    /// remoto), reemplazando al contenedor que tenia.
+    ///
+    /// El contenedor debe mantener actualizado su estado en `ContainersStates` igual que los contenedores de `set_conteiners`
+    /// (ver `ApplyContainer`), si no los dispensers que esperan por el nunca se despiertan.
+    ///
+    /// # Arguments
+    ///  * `tipo` - Tipo de ingrediente del contenedor.
+    ///  * `container` - Contenedor que implementa el trait ApplyContainer.
+    /// # Returns
+    ///  * `Result<Conteiners, ErrorCafeteria>`
+    ///    - Si es Ok, los contenedores con el contenedor registrado.
+    ///    - Si es Err, no hay un contenedor para el tipo de ingrediente recibido (por ejemplo `IngredientType::GranosCafe`).
+    pub fn with_container(
+        mut self,
+        tipo: IngredientType,
+        container: Box<dyn ApplyContainer + Send>,
+    ) -> Result<Self, ErrorCafeteria> {
+        let slot = match tipo {
+            IngredientType::Agua => &mut self.agua,
+            IngredientType::Cacao => &mut self.cacao,
+            IngredientType::CafeMolido => &mut self.cafe_molido,
+            IngredientType::EspumaLeche => &mut self.leche_espuma,
+            _ => return Err(ErrorCafeteria::new("No existe el tipo de ingrediente")),
+        };
+        *slot = Arc::new(Mutex::new(container));
+        Ok(self)
+    }
+}
+
+impl Conteiners {
+    /// Recupera los Mutex que quedaron envenenados porque un dispenser hizo panic mientras los tenia tomados (por ejemplo, por
+    /// un bug en un contenedor propio), para que los demas dispensers puedan seguir usandolos.
+    ///
+    /// Cada contenedor recuperado vuelve a publicar su estado en ContainersStates (`update_and_notify_state`), ya que pudo
+    /// haber quedado como tomado, y se despierta a los dispensers que esperan por el.
+    ///
+    /// # Arguments
+    ///  * `pair_conteiners_states` - Pair de Mutex y Condvar para el estado de los contenedores.
+    /// # Returns
+    ///  * `Result<Vec<IngredientType>, ErrorCafeteria>`
+    ///    - Si es Ok, los ingredientes de los contenedores recuperados.
+    ///    - Si es Err, hubo un error al tomar el mutex de algun contenedor o del ContainersStates.
+    pub fn recover_poisoned(
+        &self,
+        pair_conteiners_states: &Arc<(Mutex<ContainersStates>, Condvar)>,
+    ) -> Result<Vec<IngredientType>, ErrorCafeteria> {
+        let (lock_states, _) = &**pair_conteiners_states;
+        if lock_states.is_poisoned() {
+            lock_states.clear_poison();
+        }
+
+        let mut recovered = Vec::new();
+        for (tipo, container) in [
+            (IngredientType::Agua, &self.agua),
+            (IngredientType::Cacao, &self.cacao),
+            (IngredientType::CafeMolido, &self.cafe_molido),
+            (IngredientType::EspumaLeche, &self.leche_espuma),
+        ] {
+            if !container.is_poisoned() {
+                continue;
+            }
+            container.clear_poison();
+            let mut container = container
                 .lock()
-                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?),
-            IngredientType::EspumaLeche => Ok(self
-                .leche_espuma
+                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+            let states = lock_states
                 .lock()
-                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?),
-            _ => Err(ErrorCafeteria::new("No existe el tipo de ingrediente")),
+                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+            container.update_and_notify_state(states);
+            recovered.push(tipo);
         }
+        Ok(recovered)
+    }
+}
+
+impl Debug for Conteiners {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Conteiners")
+            .field("contention", &self.contention)
+            .field("admission", &self.admission)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Conteiners {
+    /// Idem a default() pero los contenedores recargables reponen su cantidad para recargar desde la
+    /// despensa recibida, que puede estar compartida con otras cafeterias.
+    ///
+    /// # Arguments
+    ///  * `pantry` - Despensa de granos de cafe y leche fria.
+    pub fn with_pantry(pantry: Arc<dyn Pantry>) -> Self {
+        Conteiners {
+            cafe_molido: Arc::new(Mutex::new(Box::new(
+                RechargableConteiner::new(
+                    IngredientType::CafeMolido,
+                    Consts::m_granos_molidos(),
+                    (IngredientType::GranosCafe, Consts::g_granos()),
+                )
+                .with_pantry(pantry.clone()),
+            ))),
+            leche_espuma: Arc::new(Mutex::new(Box::new(
+                RechargableConteiner::new(
+                    IngredientType::EspumaLeche,
+                    Consts::e_espuma_leche(),
+                    (IngredientType::LecheFria, Consts::l_leche_fria()),
+                )
+                .with_pantry(pantry),
+            ))),
+            ..Conteiners::default()
+        }
+    }
+}
+
+impl Conteiners {
+    /// Reemplaza los limites de pedidos en proceso con cada ingrediente
+    pub fn with_admission(mut self, admission: AdmissionControl) -> Self {
+        self.admission = admission;
+        self
+    }
+}
+
+/// Capacidad de cada uno de los 4 contenedores de la cafeteria, para detectar los pedidos que nunca podrian satisfacerse
+/// antes de que algun dispenser tome los locks de los contenedores.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerCapacities {
+    /// Capacidad en gramos del contenedor de cada ingrediente
+    capacities: HashMap<IngredientType, f32>,
+}
+
+impl ContainerCapacities {
+    /// Crea las capacidades con la capacidad recibida para cada ingrediente. Los ingredientes sin capacidad no se limitan.
+    pub fn new(capacities: HashMap<IngredientType, f32>) -> Self {
+        ContainerCapacities { capacities }
+    }
+
+    /// Capacidades de los contenedores segun las constantes de la cafeteria de `utils.rs`, igual que `Conteiners::default()`
+    pub fn from_env() -> Self {
+        ContainerCapacities::new(HashMap::from([
+            (IngredientType::Agua, Consts::a_agua_caliente()),
+            (IngredientType::CafeMolido, Consts::m_granos_molidos()),
+            (IngredientType::EspumaLeche, Consts::e_espuma_leche()),
+            (IngredientType::Cacao, Consts::c_cacao()),
+        ]))
+    }
+
+    /// Retorna el primer ingrediente del pedido que supera la capacidad de su contenedor, o None si el pedido puede satisfacerse.
+    ///
+    /// Es la misma regla de negocio que `belongs_to_range_capacities` de cada contenedor.
+    pub fn exceeded_by(&self, order: &Order) -> Option<IngredientType> {
+        [
+            IngredientType::Agua,
+            IngredientType::CafeMolido,
+            IngredientType::EspumaLeche,
+            IngredientType::Cacao,
+        ]
+        .into_iter()
+        .find(|tipo| match (order.get(tipo), self.capacities.get(tipo)) {
+            (Some(required), Some(capacity)) => required > *capacity,
+            _ => false,
+        })
+    }
+}
+
+impl Default for ContainerCapacities {
+    fn default() -> Self {
+        ContainerCapacities::from_env()
     }
 }
 
@@ -91,6 +268,8 @@ impl Default for Conteiners {
                 Consts::e_espuma_leche(),
                 (IngredientType::LecheFria, Consts::l_leche_fria()),
             )))),
+            contention: LockContention::default(),
+            admission: AdmissionControl::from_env(),
         }
     }
 }
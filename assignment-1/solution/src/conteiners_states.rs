@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
 
+use arc_swap::ArcSwap;
 use log::info;
 use rand::Rng;
 
@@ -8,10 +9,74 @@ use crate::{
     enums::{IngredientType, StateOfConteiner},
     error_dispenser::ErrorCafeteria,
     order::Order,
+    sync::{Arc, Condvar},
     utils::{Consts, X_ALERT_SYSTEM},
 };
 
+/// Cantidad de dispensers despertados al actualizar el estado de los contenedores
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WakeupStats {
+    /// Cantidad de veces que se notifico un cambio de estado de algun contenedor
+    pub notifications: u64,
+
+    /// Dispensers despertados, notificando solo a los que esperan por el contenedor que cambio
+    pub targeted: u64,
+
+    /// Dispensers que se hubiesen despertado notificando a todos los que esperan (como con un unico Condvar y `notify_all`)
+    pub broadcast: u64,
+}
+
+impl WakeupStats {
+    /// Proporcion de despertares evitados respecto de notificar a todos los dispensers que esperan, entre 0.0 y 1.0
+    pub fn reduction(&self) -> f32 {
+        if self.broadcast == 0 {
+            return 0.0;
+        }
+        1.0 - self.targeted as f32 / self.broadcast as f32
+    }
+
+    /// Reporta en el log los despertares de los dispensers
+    pub fn report(&self) {
+        info!(
+            "[ SYSTEM-ALERT ] Dispenser wakeups: {} instead of {} in {} notifications ({:.1}% avoided)",
+            self.targeted,
+            self.broadcast,
+            self.notifications,
+            self.reduction() * 100.0
+        );
+    }
+}
+
+/// Copia inmutable de las cantidades y estados de los contenedores, para leerlos sin tomar el lock de `ContainersStates`.
+///
+/// Cada actualizacion de `ContainersStates` publica una copia nueva (ver `ContainersStates::snapshots`), asi los lectores que
+/// solo reportan (el SYSTEM-ALERT, el CSV de alertas) no compiten con los dispensers por el lock.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContainersSnapshot {
+    /// Estado y cantidad actual de cada contenedor principal (ver `ContainersStates::principal_conteiners`)
+    pub principal_conteiners: HashMap<IngredientType, (StateOfConteiner, f32)>,
+
+    /// Cantidad disponible para recargar de cada contenedor de recarga (ver `ContainersStates::quantity_to_recharge`)
+    pub quantity_to_recharge: HashMap<IngredientType, f32>,
+}
+
+/// Acceso a la ultima copia publicada de los estados de los contenedores. Se obtiene una vez con el lock tomado
+/// (`ContainersStates::snapshots`) y luego se lee sin tomarlo.
+#[derive(Debug, Clone)]
+pub struct StatesSnapshots(Arc<ArcSwap<ContainersSnapshot>>);
+
+impl StatesSnapshots {
+    /// Ultima copia publicada de los estados de los contenedores
+    pub fn load(&self) -> Arc<ContainersSnapshot> {
+        self.0.load_full()
+    }
+}
+
 /// Estructura que contiene los estados de los diferentes contenedores
+///
+/// Los dispensers la consultan y la modifican con el lock tomado, ya que esperan en sus Condvar a que cambie. Los lectores que
+/// solo reportan leen en cambio la copia que se publica en cada modificacion (ver `snapshots`), por eso los contenedores
+/// principales y los de recarga se modifican solo con `set_state` y `set_quantity_to_recharge`.
 pub struct ContainersStates {
     /// Como key se tiene los tipos de ingredientes de los diferentes contenedores, y como
     /// value se tiene una tupla que contiene el estado del contenedor y la cantidad actual del contenedor.
@@ -27,6 +92,25 @@ pub struct ContainersStates {
     /// Se almacena la cantidad disponible para recargar para que el SYSTEM-ALERT tenga manera de reportar
     /// la cantidad disponible para recargar de cada contenedor sin tener que acceder al contenedor en si.
     pub quantity_to_recharge: HashMap<IngredientType, f32>,
+
+    /// Como key se tiene los tipos de ingredientes de los contenedores principales, y como value los Condvar de los
+    /// dispensers que esperan a que cambie el estado de ese contenedor (ver `Dispenser::wait_while_containers_states`).
+    ///
+    /// Cada dispenser espera en su propio Condvar, registrado en todos los contenedores que requiere su pedido, asi al
+    /// cambiar el estado de un contenedor solo se despierta a los dispensers cuyo pedido lo requiere.
+    waiters: HashMap<IngredientType, Vec<Arc<Condvar>>>,
+
+    /// Cantidad de dispensers esperando por algun contenedor
+    waiting: u64,
+
+    /// Despertares de los dispensers al notificar los cambios de estado
+    pub wakeups: WakeupStats,
+
+    /// Es true si se vencio el plazo de apagado, y los dispensers deben abortar los pedidos en lugar de seguir esperando
+    pub aborted: bool,
+
+    /// Ultima copia publicada de los contenedores principales y de recarga
+    snapshot: Arc<ArcSwap<ContainersSnapshot>>,
 }
 
 impl ContainersStates {
@@ -44,6 +128,35 @@ impl ContainersStates {
     ) {
         self.principal_conteiners
             .insert(*tipo, (state, quantity_in_conteiner));
+        self.publish();
+    }
+
+    /// Settea la cantidad disponible para recargar de un contenedor de recarga.
+    ///
+    /// # Arguments
+    /// * `tipo` - Tipo de ingrediente del contenedor de recarga.
+    /// * `quantity` - Cantidad disponible para recargar.
+    pub fn set_quantity_to_recharge(&mut self, tipo: IngredientType, quantity: f32) {
+        self.quantity_to_recharge.insert(tipo, quantity);
+        self.publish();
+    }
+
+    /// Acceso a las copias de los estados que se publican en cada modificacion, para leerlas sin tomar el lock
+    pub fn snapshots(&self) -> StatesSnapshots {
+        StatesSnapshots(self.snapshot.clone())
+    }
+
+    /// Ultima copia publicada de los estados de los contenedores
+    pub fn snapshot(&self) -> Arc<ContainersSnapshot> {
+        self.snapshot.load_full()
+    }
+
+    /// Reemplaza la copia publicada por una con los estados actuales. Los lectores que ya tomaron la anterior la siguen usando
+    fn publish(&self) {
+        self.snapshot.store(Arc::new(ContainersSnapshot {
+            principal_conteiners: self.principal_conteiners.clone(),
+            quantity_to_recharge: self.quantity_to_recharge.clone(),
+        }));
     }
 
     /// Obtiene de forma aleatoria algun tipo de ingrediente de los contenedores principales que se encuentren libre y es que
@@ -94,6 +207,94 @@ impl ContainersStates {
             })
     }
 
+    /// Marca en el pedido los ingredientes que requiere cuyo contenedor principal no tiene recursos
+    /// (ver `Order::missing_ingredients`), al cancelarlo por `container_without_resource_for`.
+    pub fn mark_missing_ingredients_of(&self, order: &mut Order) {
+        let missing = self
+            .principal_conteiners
+            .iter()
+            .filter(|(ingrediente, state)| {
+                state.0.eq(&StateOfConteiner::NoEnoughResource) && order.requiere(ingrediente)
+            })
+            .map(|(ingrediente, _)| *ingrediente)
+            .collect::<Vec<IngredientType>>();
+        missing
+            .into_iter()
+            .for_each(|ingrediente| order.set_no_enough_resource_container(ingrediente));
+    }
+
+    /// Registra el Condvar de un dispenser que espera a que cambie el estado de alguno de los contenedores que requiere el pedido.
+    ///
+    /// # Arguments
+    /// * `order` - Pedido que procesa el dispenser.
+    /// * `waiter` - Condvar en el que espera el dispenser, con el mutex de ContainersStates.
+    pub fn register_waiter(&mut self, order: &Order, waiter: &Arc<Condvar>) {
+        let required = self
+            .principal_conteiners
+            .keys()
+            .filter(|ingrediente| order.requiere(ingrediente))
+            .copied()
+            .collect::<Vec<IngredientType>>();
+
+        required.into_iter().for_each(|ingrediente| {
+            self.waiters
+                .entry(ingrediente)
+                .or_default()
+                .push(waiter.clone())
+        });
+        self.waiting += 1;
+    }
+
+    /// Quita el Condvar de un dispenser que dejo de esperar (ver `register_waiter`).
+    pub fn unregister_waiter(&mut self, waiter: &Arc<Condvar>) {
+        self.waiters
+            .values_mut()
+            .for_each(|waiters| waiters.retain(|registered| !Arc::ptr_eq(registered, waiter)));
+        self.waiting = self.waiting.saturating_sub(1);
+    }
+
+    /// Cantidad de dispensers que esperan por cada contenedor, de mayor a menor. Un dispenser cuenta en todos los contenedores
+    /// que requiere su pedido (ver `register_waiter`).
+    pub fn waiting_by_ingredient(&self) -> Vec<(IngredientType, usize)> {
+        let mut waiting = self
+            .waiters
+            .iter()
+            .filter(|(_, waiters)| !waiters.is_empty())
+            .map(|(ingrediente, waiters)| (*ingrediente, waiters.len()))
+            .collect::<Vec<(IngredientType, usize)>>();
+        waiting.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| format!("{:?}", a.0).cmp(&format!("{:?}", b.0)))
+        });
+        waiting
+    }
+
+    /// Notifica el cambio de estado de un contenedor solo a los dispensers que esperan por el.
+    ///
+    /// Debe llamarse con el lock de ContainersStates tomado, asi ningun dispenser que registro su Condvar pierde la notificacion.
+    pub fn notify_waiters_of(&mut self, tipo: &IngredientType) {
+        let waiters = self
+            .waiters
+            .get(tipo)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        waiters.iter().for_each(|waiter| waiter.notify_one());
+
+        self.wakeups.notifications += 1;
+        self.wakeups.targeted += waiters.len() as u64;
+        self.wakeups.broadcast += self.waiting;
+    }
+
+    /// Marca que se vencio el plazo de apagado y despierta a todos los dispensers que esperan por algun contenedor,
+    /// para que aborten sus pedidos (ver `ShutdownWatchdog`).
+    pub fn abort(&mut self) {
+        self.aborted = true;
+        self.waiters
+            .values()
+            .flatten()
+            .for_each(|waiter| waiter.notify_one());
+    }
+
     /// Alerta por consola mediante uso de logs `info!` cuando los contenedores de agua, granos, leche y cacao
     /// se encuentran por debajo de X% de capacidad.
     pub fn alert_conteiners_status(&mut self) {
@@ -178,9 +379,19 @@ impl Default for ContainersStates {
         conteiners_to_recharge.insert(IngredientType::GranosCafe, Consts::g_granos());
         conteiners_to_recharge.insert(IngredientType::LecheFria, Consts::l_leche_fria());
 
+        let snapshot = ContainersSnapshot {
+            principal_conteiners: initial_conteiners_for_process.clone(),
+            quantity_to_recharge: conteiners_to_recharge.clone(),
+        };
+
         ContainersStates {
             principal_conteiners: initial_conteiners_for_process,
             quantity_to_recharge: conteiners_to_recharge,
+            waiters: HashMap::new(),
+            waiting: 0,
+            wakeups: WakeupStats::default(),
+            aborted: false,
+            snapshot: Arc::new(ArcSwap::from_pointee(snapshot)),
         }
     }
 }
@@ -188,12 +399,13 @@ impl Default for ContainersStates {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::order::OrderIds;
 
     #[test]
     fn test1_find_rng_any_container_free_for_returings_random_container() {
         let containers_states = ContainersStates::default();
 
-        let order = Order::new(10.0, 10.0, 10.0, 10.0);
+        let order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 10.0);
 
         let any_conteiner = containers_states
             .find_rng_any_container_free_for(&order)
@@ -211,6 +423,7 @@ mod tests {
     ) {
         let containers_states = ContainersStates::default();
         let order = Order::new(
+            &OrderIds::default(),
             Consts::m_granos_molidos() + 1.0,
             Consts::e_espuma_leche(),
             1.0,
@@ -232,6 +445,7 @@ mod tests {
     fn test4_order_is_processable() {
         let containers_states = ContainersStates::default();
         let order = Order::new(
+            &OrderIds::default(),
             Consts::m_granos_molidos() + 1.0,
             Consts::e_espuma_leche() + 1.0,
             Consts::c_cacao() + 1.0,
@@ -244,7 +458,7 @@ mod tests {
     #[test]
     fn test5_order_is_not_processable() {
         let containers_states = ContainersStates::default();
-        let order = Order::new(0.0, 0.0, 0.0, 0.0);
+        let order = Order::new(&OrderIds::default(), 0.0, 0.0, 0.0, 0.0);
 
         assert!(!containers_states.order_is_processable(&order));
     }
@@ -253,6 +467,7 @@ mod tests {
     fn test5_order_is_not_processable_when_all_containers_are_taken() {
         let mut containers_states = ContainersStates::default();
         let order = Order::new(
+            &OrderIds::default(),
             Consts::m_granos_molidos() + 1.0,
             Consts::e_espuma_leche() + 1.0,
             Consts::c_cacao() + 1.0,
@@ -270,6 +485,7 @@ mod tests {
     fn test6_order_is_processable_when_one_container_is_free() {
         let mut containers_states = ContainersStates::default();
         let order = Order::new(
+            &OrderIds::default(),
             Consts::m_granos_molidos() + 1.0,
             Consts::e_espuma_leche() + 1.0,
             Consts::c_cacao() + 1.0,
@@ -296,6 +512,7 @@ mod tests {
     ) {
         let mut containers_states = ContainersStates::default();
         let order = Order::new(
+            &OrderIds::default(),
             Consts::m_granos_molidos() + 1.0,
             Consts::e_espuma_leche() + 1.0,
             Consts::c_cacao() + 1.0,
@@ -316,4 +533,127 @@ mod tests {
 
         assert!(containers_states.container_without_resource_for(&order));
     }
+
+    #[test]
+    fn test8_notify_waiters_of_only_wakes_the_waiters_of_the_container() {
+        let mut containers_states = ContainersStates::default();
+        let cacao_waiters = (0..8)
+            .map(|_| Arc::new(Condvar::new()))
+            .collect::<Vec<Arc<Condvar>>>();
+        let agua_waiter = Arc::new(Condvar::new());
+
+        cacao_waiters.iter().for_each(|waiter| {
+            containers_states.register_waiter(
+                &Order::new(&OrderIds::default(), 0.0, 0.0, 1.0, 0.0),
+                waiter,
+            )
+        });
+        containers_states.register_waiter(
+            &Order::new(&OrderIds::default(), 0.0, 0.0, 1.0, 1.0),
+            &agua_waiter,
+        );
+        assert_eq!(
+            containers_states.waiting_by_ingredient(),
+            vec![(IngredientType::Cacao, 9), (IngredientType::Agua, 1)]
+        );
+
+        containers_states.notify_waiters_of(&IngredientType::Agua);
+        assert_eq!(
+            containers_states.wakeups,
+            WakeupStats {
+                notifications: 1,
+                targeted: 1,
+                broadcast: 9,
+            }
+        );
+
+        containers_states.unregister_waiter(&agua_waiter);
+        containers_states.notify_waiters_of(&IngredientType::Agua);
+        containers_states.notify_waiters_of(&IngredientType::Cacao);
+        assert_eq!(
+            containers_states.waiting_by_ingredient(),
+            vec![(IngredientType::Cacao, 8)]
+        );
+        assert_eq!(containers_states.wakeups.targeted, 9);
+        assert_eq!(containers_states.wakeups.broadcast, 25);
+    }
+
+    #[test]
+    fn test9_many_dispensers_waiting_are_woken_only_by_the_container_they_require() {
+        use crate::{
+            dispenser::Dispenser,
+            lock_contention::LockContention,
+            sync::{thread, Mutex},
+        };
+
+        let mut states = ContainersStates::default();
+        states.set_state(0.0, StateOfConteiner::Taken, &IngredientType::Agua);
+        states.set_state(0.0, StateOfConteiner::Taken, &IngredientType::Cacao);
+        let pair_conteiners_states = Arc::new((Mutex::new(states), Condvar::new()));
+
+        let waiters = (0..10)
+            .map(|i| {
+                let pair_conteiners_states = pair_conteiners_states.clone();
+                let mut order = match i {
+                    0 | 1 => Order::new(&OrderIds::default(), 0.0, 0.0, 0.0, 1.0),
+                    _ => Order::new(&OrderIds::default(), 0.0, 0.0, 1.0, 0.0),
+                };
+                thread::spawn(move || {
+                    Dispenser::wait_while_containers_states(
+                        &pair_conteiners_states,
+                        &mut order,
+                        &LockContention::default(),
+                    )
+                    .is_ok()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let wait_until_waiting = |waiting: u64| {
+            while pair_conteiners_states.0.lock().unwrap().waiting != waiting {
+                std::thread::yield_now();
+            }
+        };
+
+        wait_until_waiting(10);
+        {
+            let mut states = pair_conteiners_states.0.lock().unwrap();
+            states.set_state(1.0, StateOfConteiner::Free, &IngredientType::Agua);
+            states.notify_waiters_of(&IngredientType::Agua);
+        }
+        wait_until_waiting(8);
+        {
+            let mut states = pair_conteiners_states.0.lock().unwrap();
+            states.set_state(1.0, StateOfConteiner::Free, &IngredientType::Cacao);
+            states.notify_waiters_of(&IngredientType::Cacao);
+        }
+
+        assert!(waiters.into_iter().all(|waiter| waiter.join().unwrap()));
+        let wakeups = pair_conteiners_states.0.lock().unwrap().wakeups;
+        assert_eq!(wakeups.targeted, 10);
+        assert_eq!(wakeups.broadcast, 18);
+        assert!(wakeups.reduction() > 0.4);
+    }
+
+    #[test]
+    fn test10_each_update_publishes_a_new_snapshot_without_changing_the_previous_one() {
+        let mut states = ContainersStates::default();
+        let snapshots = states.snapshots();
+        let before = snapshots.load();
+
+        states.set_state(7.0, StateOfConteiner::Taken, &IngredientType::Cacao);
+        states.set_quantity_to_recharge(IngredientType::LecheFria, 3.0);
+
+        let after = snapshots.load();
+        assert_eq!(
+            after.principal_conteiners[&IngredientType::Cacao],
+            (StateOfConteiner::Taken, 7.0)
+        );
+        assert_eq!(after.quantity_to_recharge[&IngredientType::LecheFria], 3.0);
+        assert_eq!(
+            before.principal_conteiners[&IngredientType::Cacao],
+            (StateOfConteiner::Free, Consts::c_cacao())
+        );
+        assert_eq!(after, states.snapshot());
+    }
 }
@@ -1,13 +1,20 @@
-use std::collections::VecDeque;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::Ordering,
+};
 
-use log::{debug, info};
+use log::{debug, error, info};
 
 use crate::{
+    admission::AdmissionControl,
     conteiners::Conteiners,
     conteiners_states::ContainersStates,
-    enums::{ErrorType, OrderState},
+    enums::{ErrorType, IngredientType, OrderState},
     error_dispenser::ErrorCafeteria,
+    lock_contention::{LockContention, LockedResource},
     order::Order,
+    respawn::DispenserWatch,
+    scheduling::SchedulingHints,
     sync::thread::{self, Builder, JoinHandle},
     sync::{Arc, Condvar, Mutex, MutexGuard},
     utils::Consts,
@@ -22,13 +29,26 @@ pub struct Dispenser {
     /// Handle del thread dispenser. Se utiliza un Option para poder crear una instancia de Dispenser
     /// sin haber creado el thread.
     pub handle: Option<JoinHandle<Result<(), ErrorCafeteria>>>,
+
+    /// Sugerencias de planificacion (nombre, prioridad y nucleo) que aplica el thread dispenser al comenzar
+    pub scheduling: SchedulingHints,
 }
 
 impl Dispenser {
-    /// Crea una instancia de Dispenser
+    /// Crea una instancia de Dispenser, con las sugerencias de planificacion de las variables de entorno (ver `SchedulingHints::from_env`)
     /// El handle del thread se inicializa en None debido a que el thread no se crea en el constructor
     pub fn new(id: usize) -> Self {
-        Self { id, handle: None }
+        Self {
+            id,
+            handle: None,
+            scheduling: SchedulingHints::from_env(),
+        }
+    }
+
+    /// Reemplaza las sugerencias de planificacion del thread dispenser
+    pub fn with_scheduling(mut self, scheduling: SchedulingHints) -> Self {
+        self.scheduling = scheduling;
+        self
     }
 
     /// Obtiene la identificación del thread dispenser actual
@@ -42,33 +62,75 @@ impl Dispenser {
     /// Spawnea y ejecuta un thread dispenser (sera un consumidor de la cola de pedidos del `pair_vecdeque_orders`, y a su vez sera un
     /// productor de la cola de pedidos finalizados `pair_vecdeque_system_alert`).
     ///
-    /// En un loop va a esperar (con `Dispenser::wait_pedido`) para recibir un Option con el pedido de la cola de pedidos.
-    /// Si el Option es Some, se encarga de procesar el pedido (con `Dispenser::process_order`), y si es None, cierra el thread dispenser.
+    /// En un loop va a esperar (con `Dispenser::wait_admitted_pedido`) para recibir un Option con el pedido de la cola de pedidos.
+    /// Si el Option es Some, se encarga de procesar el pedido (con `Dispenser::process_order`) y luego lo libera del control de
+    /// admision de los contenedores, y si es None, cierra el thread dispenser.
+    ///
+    /// Antes de esperar pedidos, el thread aplica sus sugerencias de planificacion (`SchedulingHints::apply`, con el id como indice).
     ///
     /// # Arguments
     /// * `pair_vecdeque_orders` - Arc<(`Mutex<Option<VecDeque<Order>>>`, Condvar)>: Pair de Mutex y Condvar para la cola de pedidos a procesar.
     /// * `pair_vecdeque_system_alert` - Arc<(`Mutex<VecDeque<Order>>`, Condvar)>: Pair de Mutex y Condvar para la cola pedidos finalizados.
+    /// * `pair_finished_orders` - Arc<(`Mutex<HashMap<usize, OrderState>>`, Condvar)>: Pair de Mutex y Condvar con el estado final de cada pedido finalizado.
     /// * `pair_conteiners_states` - Arc<(`Mutex<ContainersStates>`, Condvar)>: Pair de Mutex y Condvar para los estados de los contenedores.
     /// * `arc_containers` - `Arc<Conteiners>`: Arc de los contenedores.
     pub fn run(
         &mut self,
         pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
         pair_vecdeque_system_alert: Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+        pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+        pair_conteiners_states: Arc<(Mutex<ContainersStates>, Condvar)>,
+        containers: Arc<Conteiners>,
+    ) {
+        self.run_watched(
+            pair_vecdeque_orders,
+            pair_vecdeque_system_alert,
+            pair_finished_orders,
+            pair_conteiners_states,
+            containers,
+            None,
+        )
+    }
+
+    /// Idem a `Dispenser::run`, pero si recibe un `DispenserWatch` registra en el el pedido que esta procesando y avisa al
+    /// monitor cuando termina el thread, incluso si termina por un panic (ver `create_and_run_monitored_dispensers`).
+    pub(crate) fn run_watched(
+        &mut self,
+        pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+        pair_vecdeque_system_alert: Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+        pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
         pair_conteiners_states: Arc<(Mutex<ContainersStates>, Condvar)>,
         containers: Arc<Conteiners>,
+        watch: Option<DispenserWatch>,
     ) {
         let id: usize = self.id;
+        let scheduling = self.scheduling.clone();
         let handle: Option<JoinHandle<Result<(), ErrorCafeteria>>> = Builder::new()
-            .name(format!("[ DISPENSER#{} ]", id))
+            .name(scheduling.thread_name(&format!("[ DISPENSER#{} ]", id)))
             .spawn(move || {
+                if let Err(error) = scheduling.apply(id) {
+                    error!("{}: {}", Self::id_dispenser(), error.mensaje);
+                }
                 loop {
-                    if let Some(order) = Self::wait_pedido(&pair_vecdeque_orders)? {
+                    if let Some((order, counted)) =
+                        Self::wait_admitted_pedido(&pair_vecdeque_orders, &containers.admission)?
+                    {
+                        if let Some(watch) = &watch {
+                            watch.track(&order, &counted)?;
+                        }
                         Self::process_order(
                             order,
                             &pair_conteiners_states,
                             &pair_vecdeque_system_alert,
+                            &pair_finished_orders,
                             &containers,
                         )?;
+                        containers
+                            .admission
+                            .release(&counted, &pair_vecdeque_orders)?;
+                        if let Some(watch) = &watch {
+                            watch.clear()?;
+                        }
                     } else {
                         debug!(
                             "{}: None received. Closing thread dispenser.",
@@ -92,17 +154,30 @@ impl Dispenser {
     ///
     /// Se hace un wait() de la condvar esperando que haya espacio en la cola de pedidos finalizados.
     ///
+    /// Antes se registra el estado final del pedido en `pair_finished_orders` y se notifica al thread principal, que puede estar esperando
+    /// que el pedido finalice para insertar los pedidos que dependen de el.
+    ///
     /// # Arguments
     /// * `order` - Order: Pedido finalizado.
     /// * `pair_vecdeque_system_alert` - Arc<(`Mutex<VecDeque<Order>>`, Condvar)>: Pair de Mutex y Condvar para la cola pedidos finalizados.
+    /// * `pair_finished_orders` - Arc<(`Mutex<HashMap<usize, OrderState>>`, Condvar)>: Pair de Mutex y Condvar con el estado final de cada pedido finalizado.
     /// # Returns
     /// * `Result<(), ErrorCafeteria>`:
     ///    * Si es Ok, se ha insertado el pedido en la cola de pedidos finalizados.
-    ///    * Si es Err, se ha producido un error en el wait() de la condvar o al tomar el mutex de la cola de pedidos finalizados.
+    ///    * Si es Err, se ha producido un error en el wait() de la condvar o al tomar alguno de los mutex.
     pub fn notify_order_finished(
         order: Order,
         pair_vecdeque_system_alert: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+        pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
     ) -> Result<(), ErrorCafeteria> {
+        {
+            let (lock, cvar) = &**pair_finished_orders;
+            lock.lock()
+                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+                .insert(order.id.load(Ordering::SeqCst) as usize, order.status);
+            cvar.notify_all();
+        }
+
         let (lock, cvar) = &*(*pair_vecdeque_system_alert);
 
         match lock.lock() {
@@ -128,6 +203,8 @@ impl Dispenser {
     /// Es decir, siendo consumidor esperara hasta ser despertado por un notify_all() lanzado por el productor para cuando haya
     /// insertado un pedido en la cola o cuando se cierre la cafeteria (en ese caso el productor ha enviando un None).
     ///
+    /// Idem a `Dispenser::wait_admitted_pedido` sin limites de admision.
+    ///
     /// # Arguments
     /// * `pair_vecdeque_orders` - Arc<(`Mutex<Option<VecDeque<Order>>>`, Condvar)>: Pair de Mutex y Condvar para la cola de pedidos a procesar.
     ///
@@ -136,41 +213,57 @@ impl Dispenser {
     ///     * Si se ha recibido un pedido, se devuelve Ok(Some(Order)).
     ///     * Si se ha recibido un None, se devuelve Ok(None) indicando que se ha cerrado la cafeteria.
     ///     * Si ha habido un error, se devuelve Err(ErrorCafeteria). Si es Err, es por que hubo un error en el wait() o al tomar el
-    ///       lock del Mutex o porque se encontro una cola vacia cuando no deberia ser posible debido al wait() (como minimo deberia haber un elemento)
+    ///       lock del Mutex.
     pub fn wait_pedido(
         pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
     ) -> Result<Option<Order>, ErrorCafeteria> {
+        Ok(
+            Self::wait_admitted_pedido(pair_vecdeque_orders, &AdmissionControl::default())?
+                .map(|(order, _)| order),
+        )
+    }
+
+    /// Función que espera mediante el wait() de la condvar hasta que haya en la cola de pedidos un pedido que el control de admision
+    /// permita procesar (ver `AdmissionControl::admit_from`), tomando el mas antiguo de ellos.
+    ///
+    /// Ademas del productor, despierta al dispenser el notify_all() de `AdmissionControl::release` cuando otro dispenser finaliza un pedido.
+    ///
+    /// # Arguments
+    /// * `pair_vecdeque_orders` - Arc<(`Mutex<Option<VecDeque<Order>>>`, Condvar)>: Pair de Mutex y Condvar para la cola de pedidos a procesar.
+    /// * `admission` - &AdmissionControl: Limites de pedidos en proceso por ingrediente.
+    ///
+    /// # Returns
+    /// * `Result<Option<(Order, Vec<IngredientType>)>, ErrorCafeteria>`:
+    ///     * Si se ha admitido un pedido, se devuelve Ok(Some) con el pedido y los ingredientes a liberar cuando finalice.
+    ///     * Si se ha recibido un None, se devuelve Ok(None) indicando que se ha cerrado la cafeteria.
+    ///     * Si es Err, es por que hubo un error en el wait() o al tomar el lock de algun Mutex.
+    pub fn wait_admitted_pedido(
+        pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+        admission: &AdmissionControl,
+    ) -> Result<Option<(Order, Vec<IngredientType>)>, ErrorCafeteria> {
         let (lock, cvar) = &**(pair_vecdeque_orders);
 
         let mut guard_orders_queue = lock
             .lock()
             .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
 
-        while guard_orders_queue
-            .as_ref()
-            .map(|g| g.is_empty())
-            .unwrap_or(false)
-        {
+        loop {
+            match guard_orders_queue.as_mut() {
+                Some(orders) => {
+                    if let Some(admitted) = admission.admit_from(orders)? {
+                        cvar.notify_all();
+                        return Ok(Some(admitted));
+                    }
+                }
+                None => {
+                    cvar.notify_all(); // None.. it is because the cafeteria is closed. Notify all threads/dispe
+                    return Ok(None);
+                }
+            }
             guard_orders_queue = cvar
                 .wait(guard_orders_queue)
                 .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
         }
-
-        let mut optional_orders = guard_orders_queue.take();
-        if let Some(orders) = optional_orders.as_mut() {
-            if let Some(item) = orders.pop_back() {
-                cvar.notify_all();
-                *guard_orders_queue = optional_orders;
-                Ok(Some(item))
-            } else {
-                Err(ErrorCafeteria::new(
-                    "Empty VecDeque when it should have at least one element.",
-                ))
-            }
-        } else {
-            cvar.notify_all(); // None.. it is because the cafeteria is closed. Notify all threads/dispe
-            Ok(None)
-        }
     }
 
     /// Función que espera mediante el wait() de la condvar hasta que haya AL MENOS un contenedor con los recursos necesarios para procesar
@@ -180,39 +273,62 @@ impl Dispenser {
     /// En caso de que no haya un contenedor con los recursos necesarios para el pedido, se devuelve un Err(ErrorCafeteria) con un error indicando
     /// la insuficiencia de recursos en los contenedores.
     ///
+    /// El dispenser espera en su propio Condvar, registrado en ContainersStates para los contenedores que requiere el pedido
+    /// (`ContainersStates::register_waiter`), asi solo lo despiertan los cambios de estado de esos contenedores.
+    /// Si se vencio el plazo de apagado (`ContainersStates::abort`), se aborta el pedido devolviendo un Err(ErrorCafeteria) de tipo `OrderAborted`.
+    ///
     /// Aqui es donde se observa la situacion "no deterministica" del sistema explicado en el README.md.
     ///
     /// # Arguments
     /// * `pair_conteiners_states` - Arc<(`Mutex<ContainersStates>`, Condvar)>: Pair de Mutex y Condvar para la estructura ContainersStates.
     /// * `order` - &mut Order: Referencia mutable al pedido que se quiere procesar.
+    /// * `contention` - &LockContention: Registro donde se mide el tiempo esperado para tomar el lock de la estructura ContainersStates.
     ///
     /// # Returns
     /// * `Result<MutexGuard<ContainersStates>, ErrorCafeteria>`:
     ///    * Si es Ok, hay un contenedor con los recursos necesarios para el pedido. Se devuelve el MutexGuard de la estructura ContainersStates.
     ///    * Si es Err, es por que hubo un error en el wait() o al tomar el lock del Mutex o porque se encontra que el
-    ///      contenedor no tiene los recursos necesarios para satisfacer el pedido.
+    ///      contenedor no tiene los recursos necesarios para satisfacer el pedido, o porque se aborto el pedido.
     pub fn wait_while_containers_states<'a>(
         pair_conteiners_states: &'a Arc<(Mutex<ContainersStates>, Condvar)>,
         order: &mut Order,
+        contention: &LockContention,
     ) -> Result<MutexGuard<'a, ContainersStates>, ErrorCafeteria> {
-        let (lock, cvar) = &*(*pair_conteiners_states);
+        let (lock, _) = &*(*pair_conteiners_states);
+        let waiter = Arc::new(Condvar::new());
+        let mut registered = false;
 
-        let mut conteiners_states = lock
-            .lock()
-            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+        let mut conteiners_states = contention.lock(LockedResource::ContainersStates, lock)?;
 
         loop {
-            if conteiners_states.container_without_resource_for(order)
+            if conteiners_states.aborted
+                || conteiners_states.container_without_resource_for(order)
                 || conteiners_states.order_is_processable(order)
             {
                 break;
             }
-            conteiners_states = cvar
+            if !registered {
+                conteiners_states.register_waiter(order, &waiter);
+                registered = true;
+            }
+            conteiners_states = waiter
                 .wait(conteiners_states)
                 .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
         }
+        if registered {
+            conteiners_states.unregister_waiter(&waiter);
+        }
+
+        if conteiners_states.aborted {
+            order.status = OrderState::Aborted;
+            return Err(ErrorCafeteria::new_of_type(
+                "ABORTED ORDER. The shutdown deadline was exceeded.",
+                ErrorType::OrderAborted,
+            ));
+        }
 
         if conteiners_states.container_without_resource_for(order) {
+            conteiners_states.mark_missing_ingredients_of(order);
             order.status = OrderState::NoEnoughResourceContainer;
             return Err(ErrorCafeteria::new_of_type(
                 "CANCELLED ORDER. There are no containers with the necessary resources to process the order.",
@@ -224,10 +340,13 @@ impl Dispenser {
 
     /// Función que procesa un pedido.
     ///
+    /// Un pedido rechazado al leer el archivo (`OrderState::ExceedsCapacity`) se inserta directamente en la cola de pedidos
+    /// finalizados, sin tomar ningun contenedor.
+    ///
     /// El dispenser inicialmente espera (`Dispenser::wait_while_containers_states`) a que haya al menos un contenedor con los recursos necesarios para
     /// procesar el pedido.
     ///
-    /// En caso de que haya un contenedor sin los recursos necesarios para el pedido: se cancela el pedido y, el dispenser
+    /// En caso de que haya un contenedor sin los recursos necesarios para el pedido, o de que se haya abortado el pedido: se cancela el pedido y, el dispenser
     /// (actuando como productor) lo inserta en la cola de pedidos finalizados (`Dispenser::notify_order_finished`).
     ///
     /// En caso contrario, habra **a lo sumo un** contenedor con los recursos necesarios para el pedido. Se seleccionara entre ellos (de forma random)
@@ -242,6 +361,7 @@ impl Dispenser {
     /// * `order` - Order: Pedido a procesar.
     /// * `pair_conteiners_states` - Arc<(`Mutex<ContainersStates>`, Condvar)>: Pair de Mutex y Condvar para el estado de los contenedores.
     /// * `pair_vecdeque_system_alert` - Arc<(`Mutex<VecDeque<Order>>`, Condvar)>: Pair de Mutex y Condvar para la cola de pedidos finalizados.
+    /// * `pair_finished_orders` - Arc<(`Mutex<HashMap<usize, OrderState>>`, Condvar)>: Pair de Mutex y Condvar con el estado final de cada pedido finalizado.
     /// * `containers` - `Arc<Conteiners>`: Contenedores de la cafeteria.
     ///
     /// # Returns
@@ -253,6 +373,7 @@ impl Dispenser {
         mut order: Order,
         pair_conteiners_states: &Arc<(Mutex<ContainersStates>, Condvar)>,
         pair_vecdeque_system_alert: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+        pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
         containers: &Arc<Conteiners>,
     ) -> Result<(), ErrorCafeteria> {
         info!(
@@ -261,21 +382,51 @@ impl Dispenser {
             order.id,
             order.ingredientes
         );
+        if let Some(recipe) = &order.recipe {
+            info!(
+                "{} | [Order#{:?}] Recipe {}. Steps: {:?}",
+                Self::id_dispenser(),
+                order.id,
+                recipe.name,
+                recipe.steps
+            );
+        }
+        if order.status == OrderState::ExceedsCapacity {
+            info!(
+                "{} | [Order#{:?}]: REJECTED ORDER. It exceeds the capacity of a container.",
+                Self::id_dispenser(),
+                order.id
+            );
+            return Self::notify_order_finished(
+                order,
+                pair_vecdeque_system_alert,
+                pair_finished_orders,
+            );
+        }
         loop {
-            let (lock_states, cvar) = &**pair_conteiners_states;
+            let (lock_states, _) = &**pair_conteiners_states;
 
-            let conteiners_states =
-                Self::wait_while_containers_states(pair_conteiners_states, &mut order);
+            let conteiners_states = Self::wait_while_containers_states(
+                pair_conteiners_states,
+                &mut order,
+                &containers.contention,
+            );
 
             if let Err(err) = conteiners_states {
-                if err.type_error.eq(&ErrorType::ContainerWithoutResource) {
+                if err.type_error.eq(&ErrorType::ContainerWithoutResource)
+                    || err.type_error.eq(&ErrorType::OrderAborted)
+                {
                     info!(
                         "{} | [Order#{:?}]: {}",
                         Self::id_dispenser(),
                         order.id,
                         err.mensaje
                     );
-                    Self::notify_order_finished(order, pair_vecdeque_system_alert)?;
+                    Self::notify_order_finished(
+                        order,
+                        pair_vecdeque_system_alert,
+                        pair_finished_orders,
+                    )?;
                     break;
                 } else {
                     return Err(err);
@@ -295,11 +446,11 @@ impl Dispenser {
             // luego de aplicar precioso lock de nuevo!! "set_taken_state" consume el onwership, actuará el RAII
             // ademas, en el tiempo aplicacion de ingrediente, el "contendores_estados" DEBE estar libre
             // para que otros dispensers puedan tomarlo y consultar.
-            conteiners_states = lock_states
-                .lock()
-                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+            conteiners_states = containers
+                .contention
+                .lock(LockedResource::ContainersStates, lock_states)?;
 
-            container_available.update_and_notify_state(conteiners_states, cvar);
+            container_available.update_and_notify_state(conteiners_states);
 
             match order.get_updated_status() {
                 OrderState::InProgress => {
@@ -316,7 +467,11 @@ impl Dispenser {
                         order.id,
                         order.status
                     );
-                    Self::notify_order_finished(order, pair_vecdeque_system_alert)?;
+                    Self::notify_order_finished(
+                        order,
+                        pair_vecdeque_system_alert,
+                        pair_finished_orders,
+                    )?;
                     break;
                 }
             }
@@ -371,6 +526,7 @@ pub fn send_signal_poweroff_to_dispensers(
 /// # Arguments
 /// * `pair_vecdeque_orders` - Arc<(`Mutex<Option<VecDeque<Order>>>`, Condvar)>: Pair de Mutex y Condvar para la cola de pedidos a procesar.
 /// * `pair_vecdeque_system_alert` - Arc<(`Mutex<VecDeque<Order>>`, Condvar)>: Pair de Mutex y Condvar para la cola pedidos finalizados.
+/// * `pair_finished_orders` - Arc<(`Mutex<HashMap<usize, OrderState>>`, Condvar)>: Pair de Mutex y Condvar con el estado final de cada pedido finalizado.
 /// * `pair_conteiners_states` - Arc<(`Mutex<ContainersStates>`, Condvar)>: Pair de Mutex y Condvar para los estados de los contenedores.
 /// * `arc_containers` - `Arc<Conteiners>`: Arc de los contenedores.
 /// # Returns
@@ -378,6 +534,7 @@ pub fn send_signal_poweroff_to_dispensers(
 pub fn create_and_run_dispensers(
     pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
     pair_vecdeque_system_alert: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+    pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
     pair_conteiners_states: &Arc<(Mutex<ContainersStates>, Condvar)>,
     arc_containers: Arc<Conteiners>,
 ) -> Vec<Dispenser> {
@@ -389,6 +546,7 @@ pub fn create_and_run_dispensers(
         d.run(
             pair_vecdeque_orders.clone(),
             pair_vecdeque_system_alert.clone(),
+            pair_finished_orders.clone(),
             pair_conteiners_states.clone(),
             arc_containers.clone(),
         )
@@ -12,7 +12,7 @@ pub enum IngredientType {
 }
 
 /// Estados posibles del ingrediente de un pedido
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum IngredientStateOfOrder {
     /// El ingrediente fue aplicado con la cantidad indicada
     Applied(f32),
@@ -59,6 +59,37 @@ pub enum OrderState {
 
     /// El pedido no puede completarse por falta de recursos en los contenedores
     NoEnoughResourceContainer,
+
+    /// El pedido se aborto por no haber finalizado antes del plazo de apagado de la cafeteria
+    Aborted,
+
+    /// El pedido requiere de algun ingrediente mas que la capacidad de su contenedor, asi que nunca puede completarse.
+    /// Se detecta al leer el archivo de pedidos, antes de que algun dispenser lo tome.
+    ExceedsCapacity,
+}
+
+/// Que hacer con los pedidos del archivo que nunca pueden completarse porque algun ingrediente supera la capacidad de su contenedor
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ImpossibleOrders {
+    /// Se marcan como `OrderState::ExceedsCapacity` y los dispensers los finalizan sin tomar ningun contenedor
+    #[default]
+    Mark,
+
+    /// Se descartan al leer el archivo, y los pedidos que dependian de ellos ya no los esperan
+    Skip,
+}
+
+///////////////////////////// SCHEDULER /////////////////////////
+
+/// Forma en que se reparten los pedidos entre los threads que los procesan
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Scheduler {
+    /// `N_DISPENSERS` dispensers, donde cada uno procesa un pedido completo tomando los contenedores que esten libres
+    #[default]
+    Dispensers,
+
+    /// Un worker por ingrediente con su propia cola, y cada pedido pasa por los workers de los ingredientes que requiere
+    Pipeline,
 }
 
 /////////////////////////////// ERRORS //////////////////////////////////
@@ -70,4 +101,6 @@ pub enum ErrorType {
     ContainerWithoutResource,
     NoAvailableOrderFile,
     IncorrectOrderFile,
+    IncorrectRecipeFile,
+    OrderAborted,
 }
@@ -1,8 +1,21 @@
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Lines, Read, Stdin},
+    path::Path,
+    sync::Arc,
+};
 
-use log::debug;
+use log::{debug, info};
 
-use crate::{enums::ErrorType, error_dispenser::ErrorCafeteria, order::Order};
+use crate::{
+    conteiners::ContainerCapacities,
+    enums::{ErrorType, ImpossibleOrders, IngredientType, OrderState},
+    error_dispenser::ErrorCafeteria,
+    order::Order,
+    recipes::{Recipe, RecipeBook},
+    traits::OrderSource,
+    utils::Consts,
+};
 
 /// Funcion encargada de leer el archivo de ordenes y devolver un vector de ordenes con los mismos.
 ///
@@ -24,54 +37,311 @@ use crate::{enums::ErrorType, error_dispenser::ErrorCafeteria, order::Order};
 /// ```txt
 /// A1 M0.5
 /// ```
+/// Un pedido puede indicar con `D<id_de_pedido>` que recien debe comenzar cuando finalice otro pedido, donde el id de
+/// un pedido es su numero de linea empezando desde 0. Solo se puede depender de pedidos de lineas anteriores, asi
+/// las dependencias nunca forman ciclos. Por ejemplo, un pedido de cafe y luego la recarga de la misma jarra:
+/// ```txt
+/// A100 M20
+/// A50 M10 D0
+/// ```
+/// Un pedido tambien puede indicar con `R<nombre>` una bebida del archivo de recetas (ver `RecipeBook`), que define los
+/// gramos de cada ingrediente. Los ingredientes indicados en la linea reemplazan a los de la receta, por ejemplo un latte
+/// con el doble de cafe:
+/// ```txt
+/// Rlatte M40
+/// ```
+///
+/// Los pedidos que requieren de algun ingrediente mas que la capacidad de su contenedor (ver `Consts`) nunca pueden
+/// completarse, asi que se marcan como `OrderState::ExceedsCapacity` o se descartan segun `I_PEDIDOS_IMPOSIBLES`
+/// (ver `read_orders_with`).
 ///
 /// # Arguments
 ///  * `file` - Path del archivo de ordenes a leer.
 /// # Returns
 ///  * Si es Ok, `Vec<Order>` - Vector de ordenes leidas del archivo.
-///  * Si es Err, `ErrorCafeteria` debido a que no se pudo abrir el archivo o un error en la lectura del mismo.
+///  * Si es Err, `ErrorCafeteria` debido a que no se pudo abrir el archivo, un error en la lectura del mismo, una
+///    dependencia a un pedido que no esta en una linea anterior, una receta que no existe o un archivo de recetas con
+///    formato incorrecto.
 ///
 pub fn read_orders<P>(file: P) -> Result<Vec<Order>, ErrorCafeteria>
 where
     P: AsRef<Path>,
 {
-    let mut file = File::open(file).map_err(|_| {
+    read_orders_with(
+        file,
+        &ContainerCapacities::from_env(),
+        Consts::i_pedidos_imposibles(),
+        &RecipeBook::from_env()?,
+    )
+}
+
+/// Idem a `read_orders` pero comparando los pedidos con las capacidades de contenedores y la politica recibidas, y con
+/// las recetas recibidas.
+///
+/// Con `ImpossibleOrders::Skip` los pedidos descartados no se devuelven, y los pedidos que dependian de ellos ya no
+/// los esperan. Los ids de los demas pedidos siguen siendo su numero de linea.
+///
+/// # Arguments
+///  * `file` - Path del archivo de ordenes a leer.
+///  * `capacities` - Capacidad de cada contenedor.
+///  * `impossible_orders` - Que hacer con los pedidos que superan la capacidad de algun contenedor.
+///  * `recipes` - Recetas de las bebidas que se pueden pedir por nombre.
+pub fn read_orders_with<P>(
+    file: P,
+    capacities: &ContainerCapacities,
+    impossible_orders: ImpossibleOrders,
+    recipes: &RecipeBook,
+) -> Result<Vec<Order>, ErrorCafeteria>
+where
+    P: AsRef<Path>,
+{
+    let orders = stream_orders_with(file, capacities.clone(), impossible_orders, recipes.clone())?
+        .collect::<Result<Vec<Order>, ErrorCafeteria>>()?;
+
+    debug!("Orders read from file: {:?}", orders);
+
+    Ok(orders)
+}
+
+/// Idem a `read_orders` pero leyendo el archivo de a una linea a medida que se piden los pedidos, asi un archivo de millones
+/// de pedidos no se carga completo en memoria (ver `insert_orders_with_dependencies`).
+///
+/// # Returns
+///  * Si es Ok, `OrdersReader` - Los pedidos del archivo, desde su primera linea.
+///  * Si es Err, `ErrorCafeteria` debido a que no se pudo abrir el archivo o el archivo de recetas tiene un formato
+///    incorrecto. Los errores de cada linea los retorna `OrdersReader` al llegar a ella.
+pub fn stream_orders<P>(file: P) -> Result<OrdersReader<File>, ErrorCafeteria>
+where
+    P: AsRef<Path>,
+{
+    stream_orders_with(
+        file,
+        ContainerCapacities::from_env(),
+        Consts::i_pedidos_imposibles(),
+        RecipeBook::from_env()?,
+    )
+}
+
+/// Idem a `stream_orders` pero leyendo los pedidos de la entrada estandar, hasta que se cierra (por ejemplo con Ctrl-D o cuando
+/// termina el proceso que escribe en el pipe).
+///
+/// # Returns
+///  * Si es Ok, `OrdersReader` - Los pedidos de la entrada estandar, desde su primera linea.
+///  * Si es Err, `ErrorCafeteria` debido a que el archivo de recetas tiene un formato incorrecto.
+pub fn stream_stdin_orders() -> Result<OrdersReader<Stdin>, ErrorCafeteria> {
+    Ok(OrdersReader {
+        name: "stdin".to_string(),
+        ..OrdersReader::new(
+            io::stdin(),
+            ContainerCapacities::from_env(),
+            Consts::i_pedidos_imposibles(),
+            RecipeBook::from_env()?,
+        )
+    })
+}
+
+/// Idem a `stream_orders` pero con las capacidades de contenedores, la politica y las recetas recibidas (ver `read_orders_with`).
+pub fn stream_orders_with<P>(
+    file: P,
+    capacities: ContainerCapacities,
+    impossible_orders: ImpossibleOrders,
+    recipes: RecipeBook,
+) -> Result<OrdersReader<File>, ErrorCafeteria>
+where
+    P: AsRef<Path>,
+{
+    let name = format!("file {}", file.as_ref().display());
+    let file = File::open(file).map_err(|_| {
         ErrorCafeteria::new_of_type("Error opening orders file", ErrorType::NoAvailableOrderFile)
     })?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).map_err(|_| {
-        ErrorCafeteria::new_of_type("Error reading orders file", ErrorType::IncorrectOrderFile)
-    })?;
+    Ok(OrdersReader {
+        name,
+        ..OrdersReader::new(file, capacities, impossible_orders, recipes)
+    })
+}
 
-    let mut orders = Vec::new();
+/// Pedidos de un archivo de ordenes que se leen de a una linea (ver `stream_orders`).
+///
+/// Cada linea se parsea como en `read_orders`, con su numero de linea como id. Los pedidos descartados por
+/// `ImpossibleOrders::Skip` no se devuelven. Luego de un error el iterador termina.
+#[derive(Debug)]
+pub struct OrdersReader<R> {
+    /// Lineas del archivo que faltan leer
+    lines: Lines<BufReader<R>>,
+
+    /// Capacidad de cada contenedor, para detectar los pedidos que nunca pueden completarse
+    capacities: ContainerCapacities,
+
+    /// Que hacer con los pedidos que superan la capacidad de algun contenedor
+    impossible_orders: ImpossibleOrders,
+
+    /// Recetas de las bebidas que se pueden pedir por nombre
+    recipes: RecipeBook,
+
+    /// Numero de la proxima linea
+    next_id: usize,
+
+    /// Numeros de linea de los pedidos descartados por `ImpossibleOrders::Skip`
+    skipped: Vec<usize>,
+
+    /// true luego de retornar un error
+    failed: bool,
+
+    /// Error con el que termino la lectura, cuando se la usa como `OrderSource`
+    error: Option<ErrorCafeteria>,
+
+    /// Nombre de la lectura para los logs y las estadisticas (ver `OrderSource::name`)
+    name: String,
+}
+
+impl<R: Read> OrdersReader<R> {
+    /// Crea la lectura de los pedidos de `reader`, por ejemplo un archivo o un `&[u8]` en los tests
+    pub fn new(
+        reader: R,
+        capacities: ContainerCapacities,
+        impossible_orders: ImpossibleOrders,
+        recipes: RecipeBook,
+    ) -> Self {
+        OrdersReader {
+            lines: BufReader::new(reader).lines(),
+            capacities,
+            impossible_orders,
+            recipes,
+            next_id: 0,
+            skipped: Vec::new(),
+            failed: false,
+            error: None,
+            name: "orders".to_string(),
+        }
+    }
+}
 
-    for (id, line) in contents.lines().enumerate() {
-        let (mut agua, mut granos_molidos, mut cacao, mut espuma_de_leche) =
-            (None, None, None, None);
+impl<R: Read> Iterator for OrdersReader<R> {
+    type Item = Result<Order, ErrorCafeteria>;
 
-        for word in line.split_whitespace() {
-            match word.chars().next() {
-                Some('A') => agua = parse_word(word)?,
-                Some('M') => granos_molidos = parse_word(word)?,
-                Some('C') => cacao = parse_word(word)?,
-                Some('E') => espuma_de_leche = parse_word(word)?,
-                _ => (),
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(_) => {
+                    self.failed = true;
+                    return Some(Err(ErrorCafeteria::new_of_type(
+                        "Error reading orders file",
+                        ErrorType::IncorrectOrderFile,
+                    )));
+                }
+            };
+            let id = self.next_id;
+            self.next_id += 1;
+            match parse_order(
+                id,
+                &line,
+                &self.capacities,
+                self.impossible_orders,
+                &self.recipes,
+                &mut self.skipped,
+            ) {
+                Ok(Some(order)) => return Some(Ok(order)),
+                Ok(None) => continue,
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(error));
+                }
             }
         }
-        let order = Order::new_with_id(
-            id,
-            granos_molidos.unwrap_or(0.0),
-            espuma_de_leche.unwrap_or(0.0),
-            cacao.unwrap_or(0.0),
-            agua.unwrap_or(0.0),
-        );
+        None
+    }
+}
 
-        orders.push(order);
+/// Los pedidos se retornan hasta la ultima linea o hasta la primera linea con error, que se retorna en `finish`
+impl<R: Read> OrderSource for OrdersReader<R> {
+    fn next_order(&mut self) -> Option<Order> {
+        match self.next()? {
+            Ok(order) => Some(order),
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
     }
 
-    debug!("Orders read from file: {:?}", orders);
+    fn finish(&mut self) -> Result<(), ErrorCafeteria> {
+        self.error.take().map_or(Ok(()), Err)
+    }
 
-    Ok(orders)
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/// Funcion encargada de parsear el pedido de la linea `id` del archivo de ordenes (ver `read_orders`).
+///
+/// # Arguments
+///  * `id` - Numero de linea del pedido, que es su id.
+///  * `line` - Linea a parsear. Por ejemplo "A1 M0.5 D0"
+///  * `capacities` - Capacidad de cada contenedor.
+///  * `impossible_orders` - Que hacer con los pedidos que superan la capacidad de algun contenedor.
+///  * `recipes` - Recetas de las bebidas que se pueden pedir por nombre.
+///  * `skipped` - Ids de los pedidos descartados de las lineas anteriores. Si se descarta este pedido, se agrega su id.
+/// # Returns
+///  * Si es Ok, `Option<Order>` - El pedido de la linea, o None si se descarto.
+///  * Si es Err, `ErrorCafeteria` debido a que no se pudo parsear la linea correctamente.
+pub(crate) fn parse_order(
+    id: usize,
+    line: &str,
+    capacities: &ContainerCapacities,
+    impossible_orders: ImpossibleOrders,
+    recipes: &RecipeBook,
+    skipped: &mut Vec<usize>,
+) -> Result<Option<Order>, ErrorCafeteria> {
+    let (mut agua, mut granos_molidos, mut cacao, mut espuma_de_leche) = (None, None, None, None);
+    let mut dependencies = Vec::new();
+    let mut recipe = None;
+
+    for word in line.split_whitespace() {
+        match word.chars().next() {
+            Some('A') => agua = parse_word(word)?,
+            Some('M') => granos_molidos = parse_word(word)?,
+            Some('C') => cacao = parse_word(word)?,
+            Some('E') => espuma_de_leche = parse_word(word)?,
+            Some('D') => dependencies.push(parse_dependency(word, id)?),
+            Some('R') => recipe = Some(parse_recipe(word, recipes)?),
+            _ => (),
+        }
+    }
+    // los ingredientes indicados en la linea tienen prioridad sobre los de la receta
+    let from_recipe = |tipo: IngredientType| recipe.as_ref().and_then(|x| x.get(&tipo));
+    let agua = agua.or_else(|| from_recipe(IngredientType::Agua));
+    let granos_molidos = granos_molidos.or_else(|| from_recipe(IngredientType::CafeMolido));
+    let cacao = cacao.or_else(|| from_recipe(IngredientType::Cacao));
+    let espuma_de_leche = espuma_de_leche.or_else(|| from_recipe(IngredientType::EspumaLeche));
+    // solo se depende de lineas anteriores, asi que ya se sabe si el pedido del que depende fue descartado
+    dependencies.retain(|dependency| !skipped.contains(dependency));
+    let mut order = Order::new_with_id(
+        id,
+        granos_molidos.unwrap_or(0.0),
+        espuma_de_leche.unwrap_or(0.0),
+        cacao.unwrap_or(0.0),
+        agua.unwrap_or(0.0),
+    )
+    .with_dependencies(dependencies)
+    .with_recipe(recipe);
+
+    if let Some(tipo) = capacities.exceeded_by(&order) {
+        info!(
+            "[Order#{}]: {:?} exceeds the capacity of its container. The order can never be completed ({:?}).",
+            id, tipo, impossible_orders
+        );
+        match impossible_orders {
+            ImpossibleOrders::Mark => order.status = OrderState::ExceedsCapacity,
+            ImpossibleOrders::Skip => {
+                skipped.push(id);
+                return Ok(None);
+            }
+        }
+    }
+
+    Ok(Some(order))
 }
 
 /// Funcion encargada de parsear una palabra para convertirla en un float.
@@ -87,14 +357,57 @@ fn parse_word(word: &str) -> Result<Option<f32>, ErrorCafeteria> {
     })?))
 }
 
+/// Funcion encargada de parsear la receta de un pedido.
+///
+/// # Arguments
+///  * `word` - Palabra a parsear. Por ejemplo "Rlatte"
+///  * `recipes` - Recetas de las bebidas que se pueden pedir por nombre.
+/// # Returns
+///  * Si es Ok, `Arc<Recipe>` - La receta con ese nombre.
+///  * Si es Err, `ErrorCafeteria` debido a que no existe una receta con ese nombre.
+fn parse_recipe(word: &str, recipes: &RecipeBook) -> Result<Arc<Recipe>, ErrorCafeteria> {
+    recipes.get(&word[1..]).ok_or_else(|| {
+        ErrorCafeteria::new_of_type(
+            &format!("Error parsing orders file: unknown recipe {}", &word[1..]),
+            ErrorType::IncorrectOrderFile,
+        )
+    })
+}
+
+/// Funcion encargada de parsear una dependencia del pedido de la linea `id`.
+///
+/// # Arguments
+///  * `word` - Palabra a parsear. Por ejemplo "D3"
+///  * `id` - Id del pedido que tiene la dependencia.
+/// # Returns
+///  * Si es Ok, `usize` - Id del pedido del que depende.
+///  * Si es Err, `ErrorCafeteria` debido a que no se pudo parsear la palabra o el pedido no esta en una linea anterior.
+fn parse_dependency(word: &str, id: usize) -> Result<usize, ErrorCafeteria> {
+    word[1..]
+        .parse::<usize>()
+        .ok()
+        .filter(|dependency| *dependency < id)
+        .ok_or_else(|| {
+            ErrorCafeteria::new_of_type(
+                "Error parsing orders file: an order can only depend on a previous order",
+                ErrorType::IncorrectOrderFile,
+            )
+        })
+}
+
 #[cfg(test)]
 mod tests_file_orders {
-    use crate::enums::IngredientType;
+    use crate::conteiners::ContainerCapacities;
+    use crate::enums::{ErrorType, ImpossibleOrders, IngredientType, OrderState};
     use crate::file_orders;
+    use crate::recipes::RecipeBook;
+    use crate::traits::OrderSource;
 
+    use std::collections::HashMap;
     use std::fs::File;
-    use std::io::Write;
+    use std::io::{Read, Write};
     use std::path::PathBuf;
+    use std::sync::atomic::Ordering;
 
     fn create_tests_files(id: u32) {
         let orders_content1 = "A100 M20 C30 E10\nA1 M20 C30 E10\nA330 M20 C30 E10\n";
@@ -156,4 +469,218 @@ mod tests_file_orders {
         assert_eq!(orders[1].get(&IngredientType::EspumaLeche), Some(10.0));
         delete_tests_files(2);
     }
+
+    #[test]
+    fn test3_orders_with_dependencies_on_previous_orders() {
+        let mut orders_file = File::create("orders_test_dependencies_3.txt").unwrap();
+        orders_file
+            .write_all("A100 M20\nA50 D0\nE10 D0 D1\n".as_bytes())
+            .unwrap();
+
+        let orders =
+            file_orders::read_orders(PathBuf::from("orders_test_dependencies_3.txt")).unwrap();
+
+        assert_eq!(orders.len(), 3);
+        assert!(orders[0].dependencies.is_empty());
+        assert_eq!(orders[1].dependencies, vec![0]);
+        assert_eq!(orders[2].dependencies, vec![0, 1]);
+        assert_eq!(orders[2].get(&IngredientType::EspumaLeche), Some(10.0));
+        std::fs::remove_file("orders_test_dependencies_3.txt").unwrap();
+    }
+
+    #[test]
+    fn test4_order_depending_on_itself_or_a_later_order_is_an_incorrect_file() {
+        let mut orders_file = File::create("orders_test_dependencies_4.txt").unwrap();
+        orders_file
+            .write_all("A100 D1\nA50 D1\n".as_bytes())
+            .unwrap();
+
+        let error =
+            file_orders::read_orders(PathBuf::from("orders_test_dependencies_4.txt")).unwrap_err();
+
+        assert_eq!(error.type_error, ErrorType::IncorrectOrderFile);
+        std::fs::remove_file("orders_test_dependencies_4.txt").unwrap();
+    }
+
+    fn capacities_for_tests() -> ContainerCapacities {
+        ContainerCapacities::new(HashMap::from([
+            (IngredientType::Agua, 100.0),
+            (IngredientType::CafeMolido, 20.0),
+        ]))
+    }
+
+    #[test]
+    fn test5_orders_exceeding_the_capacity_of_a_container_are_marked() {
+        let mut orders_file = File::create("orders_test_impossible_5.txt").unwrap();
+        orders_file
+            .write_all("A100 M20\nA101 M1\nM20.5 C1000\nA1 D1\n".as_bytes())
+            .unwrap();
+
+        let orders = file_orders::read_orders_with(
+            PathBuf::from("orders_test_impossible_5.txt"),
+            &capacities_for_tests(),
+            ImpossibleOrders::Mark,
+            &RecipeBook::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            orders.iter().map(|order| order.status).collect::<Vec<_>>(),
+            vec![
+                OrderState::InProgress,
+                OrderState::ExceedsCapacity,
+                OrderState::ExceedsCapacity,
+                OrderState::InProgress
+            ]
+        );
+        assert_eq!(orders[3].dependencies, vec![1]);
+        std::fs::remove_file("orders_test_impossible_5.txt").unwrap();
+    }
+
+    #[test]
+    fn test6_skipped_orders_are_not_returned_and_their_dependents_no_longer_wait_for_them() {
+        let mut orders_file = File::create("orders_test_impossible_6.txt").unwrap();
+        orders_file
+            .write_all("A100 M20\nA101 M1\nA1 D0 D1\n".as_bytes())
+            .unwrap();
+
+        let orders = file_orders::read_orders_with(
+            PathBuf::from("orders_test_impossible_6.txt"),
+            &capacities_for_tests(),
+            ImpossibleOrders::Skip,
+            &RecipeBook::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            orders
+                .iter()
+                .map(|order| order.id.load(Ordering::SeqCst))
+                .collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        assert_eq!(orders[1].dependencies, vec![0]);
+        assert!(orders
+            .iter()
+            .all(|order| order.status == OrderState::InProgress));
+        std::fs::remove_file("orders_test_impossible_6.txt").unwrap();
+    }
+
+    #[test]
+    fn test7_orders_by_recipe_take_its_ingredients_unless_the_line_overrides_them() {
+        let mut orders_file = File::create("orders_test_recipes_7.txt").unwrap();
+        orders_file
+            .write_all("Rlatte\nRlatte M40 D0\nA10\n".as_bytes())
+            .unwrap();
+        let recipes =
+            RecipeBook::parse("[latte]\nagua = 100\ncafe_molido = 20\nespuma_leche = 50").unwrap();
+
+        let orders = file_orders::read_orders_with(
+            PathBuf::from("orders_test_recipes_7.txt"),
+            &ContainerCapacities::new(HashMap::new()),
+            ImpossibleOrders::Mark,
+            &recipes,
+        )
+        .unwrap();
+
+        assert_eq!(orders[0].get(&IngredientType::Agua), Some(100.0));
+        assert_eq!(orders[0].get(&IngredientType::CafeMolido), Some(20.0));
+        assert_eq!(orders[0].get(&IngredientType::EspumaLeche), Some(50.0));
+        assert_eq!(orders[1].get(&IngredientType::CafeMolido), Some(40.0));
+        assert_eq!(orders[1].dependencies, vec![0]);
+        assert_eq!(
+            orders
+                .iter()
+                .map(|order| order.recipe.as_ref().map(|recipe| recipe.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![Some("latte"), Some("latte"), None]
+        );
+        std::fs::remove_file("orders_test_recipes_7.txt").unwrap();
+    }
+
+    #[test]
+    fn test8_order_with_an_unknown_recipe_is_an_incorrect_file() {
+        let mut orders_file = File::create("orders_test_recipes_8.txt").unwrap();
+        orders_file.write_all("Rcortado\n".as_bytes()).unwrap();
+
+        let error = file_orders::read_orders_with(
+            PathBuf::from("orders_test_recipes_8.txt"),
+            &capacities_for_tests(),
+            ImpossibleOrders::Mark,
+            &RecipeBook::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.type_error, ErrorType::IncorrectOrderFile);
+        std::fs::remove_file("orders_test_recipes_8.txt").unwrap();
+    }
+
+    /// Archivo de ordenes que no termina nunca, con el mismo pedido en cada linea
+    struct EndlessOrders;
+
+    impl Read for EndlessOrders {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let line = b"A10 M5\n";
+            let len = line.len().min(buf.len());
+            buf[..len].copy_from_slice(&line[..len]);
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test9_orders_are_streamed_without_reading_the_whole_file() {
+        let orders = file_orders::OrdersReader::new(
+            EndlessOrders,
+            capacities_for_tests(),
+            ImpossibleOrders::Mark,
+            RecipeBook::default(),
+        )
+        .take(1000)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+        assert_eq!(orders.len(), 1000);
+        assert_eq!(orders[999].id.load(Ordering::SeqCst), 999);
+        assert_eq!(orders[999].get(&IngredientType::CafeMolido), Some(5.0));
+    }
+
+    #[test]
+    fn test10_streamed_orders_stop_at_the_first_incorrect_line() {
+        let mut orders = file_orders::OrdersReader::new(
+            "A10\nA20 D5\nA30\n".as_bytes(),
+            capacities_for_tests(),
+            ImpossibleOrders::Mark,
+            RecipeBook::default(),
+        );
+
+        assert_eq!(
+            orders.next().unwrap().unwrap().get(&IngredientType::Agua),
+            Some(10.0)
+        );
+        assert_eq!(
+            orders.next().unwrap().unwrap_err().type_error,
+            ErrorType::IncorrectOrderFile
+        );
+        assert!(orders.next().is_none());
+    }
+    #[test]
+    fn test11_as_an_order_source_the_error_of_an_incorrect_line_is_returned_when_finishing() {
+        let mut orders = file_orders::OrdersReader::new(
+            "A10\nA20 D0\nA30 D9\nA40\n".as_bytes(),
+            capacities_for_tests(),
+            ImpossibleOrders::Mark,
+            RecipeBook::default(),
+        );
+
+        let ids = std::iter::from_fn(|| orders.next_order())
+            .map(|order| order.id.load(Ordering::SeqCst))
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(
+            orders.finish().unwrap_err().type_error,
+            ErrorType::IncorrectOrderFile
+        );
+        assert!(orders.finish().is_ok());
+    }
 }
@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use rand::Rng;
+
+use crate::{
+    enums::IngredientType,
+    order::Order,
+    sync::{reloj, sleep},
+    traits::OrderSource,
+    utils::Consts,
+};
+
+/// Ingredientes que puede requerir un pedido, en el orden de los parametros de `Order::new`
+const INGREDIENTS_OF_ORDER: [IngredientType; 4] = [
+    IngredientType::CafeMolido,
+    IngredientType::EspumaLeche,
+    IngredientType::Cacao,
+    IngredientType::Agua,
+];
+
+/// Distribucion de un ingrediente en los pedidos generados
+#[derive(Debug, Clone, Copy)]
+pub struct IngredientDistribution {
+    /// Probabilidad de que un pedido requiera el ingrediente, entre 0.0 y 1.0
+    pub probability: f32,
+
+    /// Cantidad minima en gramos del ingrediente cuando el pedido lo requiere (excluida)
+    pub min_quantity: f32,
+
+    /// Cantidad maxima en gramos del ingrediente cuando el pedido lo requiere
+    pub max_quantity: f32,
+}
+
+/// Configuracion del generador de pedidos
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    /// Tasa de llegada de los pedidos, en pedidos por segundo
+    pub arrival_rate: f32,
+
+    /// Tiempo durante el cual llegan pedidos
+    pub duration: Duration,
+
+    /// Distribucion de cada ingrediente en los pedidos. Los ingredientes que no estan no se piden nunca
+    pub ingredients: HashMap<IngredientType, IngredientDistribution>,
+}
+
+impl GeneratorConfig {
+    /// Crea la configuracion a partir de las variables de entorno `T_TASA_PEDIDOS`, `D_DURACION_GENERADOR`,
+    /// `P_PROBABILIDAD_INGREDIENTE` y `Q_MAX_GRAMOS_PEDIDO`. Todos los ingredientes usan la misma distribucion.
+    pub fn from_env() -> Self {
+        let distribution = IngredientDistribution {
+            probability: Consts::p_probabilidad_ingrediente(),
+            min_quantity: 0.0,
+            max_quantity: Consts::q_max_gramos_pedido(),
+        };
+        GeneratorConfig {
+            arrival_rate: Consts::t_tasa_pedidos(),
+            duration: Duration::from_secs(Consts::d_duracion_generador()),
+            ingredients: INGREDIENTS_OF_ORDER
+                .into_iter()
+                .map(|tipo| (tipo, distribution))
+                .collect(),
+        }
+    }
+}
+
+/// Generador de pedidos: en lugar de cargar todos los pedidos de un archivo, los pedidos llegan durante la
+/// ejecucion segun un proceso de Poisson, para simular la carga sostenida de una cafeteria.
+///
+/// Los instantes de llegada se sortean al crear el generador (asi se conoce de antemano la cantidad de pedidos que
+/// debe esperar el SYSTEM-ALERT), y los ingredientes de cada pedido se sortean recien cuando llega.
+#[derive(Debug)]
+pub struct Generator {
+    /// Configuracion del generador
+    config: GeneratorConfig,
+
+    /// Instantes de llegada de los pedidos, medidos desde que comienza a ejecutarse el generador
+    arrivals: Vec<Duration>,
+
+    /// Posicion en `arrivals` del proximo pedido que llega
+    next: usize,
+
+    /// Instante en que se pidio el primer pedido, desde el que se miden las llegadas. None si todavia no se pidio ninguno
+    started: Option<Instant>,
+}
+
+impl Generator {
+    /// Crea el generador sorteando los instantes de llegada de los pedidos.
+    pub fn new(config: GeneratorConfig) -> Self {
+        let arrivals = sample_arrivals(
+            config.arrival_rate,
+            config.duration,
+            &mut rand::thread_rng(),
+        );
+        Generator {
+            config,
+            arrivals,
+            next: 0,
+            started: None,
+        }
+    }
+}
+
+/// Cada pedido se retorna recien en su instante de llegada, medido desde que se pide el primero. Si la cola de pedidos esta
+/// llena el pedido espera a que los dispensers se liberen, como los clientes que hacen fila, y los pedidos que llegaron mientras
+/// tanto se retornan apenas haya lugar.
+impl OrderSource for Generator {
+    fn next_order(&mut self) -> Option<Order> {
+        let id = self.next;
+        let arrival = *self.arrivals.get(id)?;
+        self.next += 1;
+
+        let start = *self.started.get_or_insert_with(|| reloj().ahora());
+        let elapsed = reloj().ahora().saturating_duration_since(start);
+        if let Some(wait) = arrival.checked_sub(elapsed) {
+            sleep(wait);
+        }
+
+        let order = sample_order(id, &self.config.ingredients, &mut rand::thread_rng());
+        debug!(
+            "[ GENERATOR ] | [Order#{}] NEW ORDER ARRIVED at {:?}.\n                 Requeriments: {:?}",
+            id, arrival, order.ingredientes
+        );
+        Some(order)
+    }
+
+    fn name(&self) -> String {
+        "generator".to_string()
+    }
+
+    /// Los instantes de llegada ya se sortearon, asi que se conoce la cantidad de pedidos de antemano
+    fn total_orders(&self) -> Option<usize> {
+        Some(self.arrivals.len())
+    }
+}
+
+/// Sortea los instantes de llegada de un proceso de Poisson con la tasa indicada: los tiempos entre llegadas son
+/// exponenciales de media `1 / arrival_rate`. Con una tasa no positiva no llega ningun pedido.
+fn sample_arrivals<R: Rng>(arrival_rate: f32, duration: Duration, rng: &mut R) -> Vec<Duration> {
+    let mut arrivals = Vec::new();
+    if arrival_rate <= 0.0 {
+        return arrivals;
+    }
+
+    let mut next = 0.0_f64;
+    loop {
+        // 1 - U esta en (0, 1], asi el logaritmo siempre es finito
+        next += -(1.0 - rng.gen::<f64>()).ln() / arrival_rate as f64;
+        if next >= duration.as_secs_f64() {
+            return arrivals;
+        }
+        arrivals.push(Duration::from_secs_f64(next));
+    }
+}
+
+/// Sortea los ingredientes de un pedido segun su distribucion. Si no sale ningun ingrediente se sortea uno de los
+/// configurados, para que el pedido requiera al menos uno.
+fn sample_order<R: Rng>(
+    id: usize,
+    ingredients: &HashMap<IngredientType, IngredientDistribution>,
+    rng: &mut R,
+) -> Order {
+    // U esta en [0, 1), asi la cantidad queda en (min_quantity, max_quantity]
+    let sample_quantity = |distribution: &IngredientDistribution, rng: &mut R| {
+        distribution.min_quantity
+            + (distribution.max_quantity - distribution.min_quantity) * (1.0 - rng.gen::<f32>())
+    };
+
+    let mut quantities: HashMap<IngredientType, f32> = HashMap::new();
+    for (tipo, distribution) in ingredients {
+        if rng.gen::<f32>() < distribution.probability {
+            quantities.insert(*tipo, sample_quantity(distribution, rng));
+        }
+    }
+
+    if quantities.is_empty() && !ingredients.is_empty() {
+        let configured: Vec<(&IngredientType, &IngredientDistribution)> =
+            ingredients.iter().collect();
+        let (tipo, distribution) = configured[rng.gen_range(0, configured.len())];
+        quantities.insert(*tipo, sample_quantity(distribution, rng));
+    }
+
+    let [cm, lc, c, ac] =
+        INGREDIENTS_OF_ORDER.map(|tipo| quantities.get(&tipo).copied().unwrap_or(0.0));
+    Order::new_with_id(id, cm, lc, c, ac)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, sync::atomic::Ordering};
+
+    use super::*;
+    use crate::{
+        dispenser::Dispenser,
+        enums::OrderState,
+        sources::insert_source_orders,
+        sync::{thread, Arc, Condvar, Mutex},
+    };
+
+    fn only(
+        tipo: IngredientType,
+        distribution: IngredientDistribution,
+    ) -> HashMap<IngredientType, IngredientDistribution> {
+        HashMap::from([(tipo, distribution)])
+    }
+
+    #[test]
+    fn test1_arrivals_follow_the_configured_rate_within_the_duration() {
+        let arrivals = sample_arrivals(50.0, Duration::from_secs(200), &mut rand::thread_rng());
+
+        // se esperan 10000 llegadas con desvio 100
+        assert!((9500..=10500).contains(&arrivals.len()));
+        assert!(arrivals.windows(2).all(|w| w[0] <= w[1]));
+        assert!(arrivals.iter().all(|a| *a < Duration::from_secs(200)));
+        assert!(sample_arrivals(0.0, Duration::from_secs(200), &mut rand::thread_rng()).is_empty());
+    }
+
+    #[test]
+    fn test2_orders_respect_the_ingredient_distribution() {
+        let mut ingredients = only(
+            IngredientType::Agua,
+            IngredientDistribution {
+                probability: 1.0,
+                min_quantity: 2.0,
+                max_quantity: 4.0,
+            },
+        );
+        ingredients.insert(
+            IngredientType::Cacao,
+            IngredientDistribution {
+                probability: 0.0,
+                min_quantity: 0.0,
+                max_quantity: 10.0,
+            },
+        );
+
+        for id in 0..1000 {
+            let order = sample_order(id, &ingredients, &mut rand::thread_rng());
+            let agua = order.get(&IngredientType::Agua).unwrap();
+            assert!(2.0 < agua && agua <= 4.0);
+            assert!(!order.requiere(&IngredientType::Cacao));
+            assert!(!order.requiere(&IngredientType::CafeMolido));
+        }
+    }
+
+    #[test]
+    fn test3_orders_always_require_at_least_one_ingredient() {
+        let ingredients = only(
+            IngredientType::EspumaLeche,
+            IngredientDistribution {
+                probability: 0.0,
+                min_quantity: 0.0,
+                max_quantity: 5.0,
+            },
+        );
+
+        for id in 0..100 {
+            let order = sample_order(id, &ingredients, &mut rand::thread_rng());
+            assert!(order.requiere(&IngredientType::EspumaLeche));
+        }
+    }
+
+    #[test]
+    fn test4_every_order_is_inserted_after_its_arrival_time() {
+        let mut generator = Generator::new(GeneratorConfig {
+            arrival_rate: 1.0,
+            duration: Duration::from_secs(20),
+            ingredients: only(
+                IngredientType::Agua,
+                IngredientDistribution {
+                    probability: 1.0,
+                    min_quantity: 0.0,
+                    max_quantity: 1.0,
+                },
+            ),
+        });
+        let total_orders = generator.total_orders().unwrap();
+        let last_arrival = generator.arrivals.last().copied().unwrap_or_default();
+
+        let pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)> =
+            Arc::new((Mutex::new(Some(VecDeque::new())), Condvar::new()));
+        let pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)> =
+            Arc::new((Mutex::new(HashMap::new()), Condvar::new()));
+        let consumer = {
+            let pair_vecdeque_orders = pair_vecdeque_orders.clone();
+            thread::spawn(move || {
+                (0..total_orders)
+                    .map(|_| {
+                        Dispenser::wait_pedido(&pair_vecdeque_orders)
+                            .unwrap()
+                            .unwrap()
+                    })
+                    .map(|order| order.id.load(Ordering::SeqCst) as usize)
+                    .collect::<Vec<usize>>()
+            })
+        };
+
+        let start = reloj().ahora();
+        insert_source_orders(&mut generator, &pair_vecdeque_orders, &pair_finished_orders).unwrap();
+
+        assert!(reloj().ahora().saturating_duration_since(start) >= last_arrival);
+        assert_eq!(
+            consumer.join().unwrap(),
+            (0..total_orders).collect::<Vec<usize>>()
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::utils::Consts;
+
+/// Cantidad maxima de decimales con la que se redondean los gramos. Con mas decimales el redondeo ya no es exacto en f32
+const MAX_DECIMALS: u32 = 6;
+
+/// Politica de redondeo de las cantidades en gramos de los contenedores.
+///
+/// Las restas acumuladas en f32 dejan a los contenedores con cantidades como 0.0000001 o -0.0, que confunden a los chequeos
+/// de estado (`quantity.gt(&0.0)`). Los contenedores redondean cada cantidad que actualizan a esta cantidad de decimales,
+/// asi un contenedor que se vacio queda exactamente en 0.0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GramRounding {
+    /// Cantidad de decimales que se conservan
+    decimals: u32,
+}
+
+impl GramRounding {
+    /// Crea la politica de redondeo con la cantidad de decimales indicada, como maximo `MAX_DECIMALS`
+    pub fn new(decimals: u32) -> Self {
+        GramRounding {
+            decimals: decimals.min(MAX_DECIMALS),
+        }
+    }
+
+    /// Crea la politica de redondeo a partir de la variable de entorno `R_DECIMALES_GRAMOS`
+    pub fn from_env() -> Self {
+        GramRounding::new(Consts::r_decimales_gramos())
+    }
+
+    /// Cantidad de decimales que se conservan
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    /// Redondea la cantidad de gramos a la cantidad de decimales de la politica. El -0.0 queda como 0.0.
+    pub fn round(&self, grams: f32) -> f32 {
+        // se redondea en f64 para no perder precision al escalar las cantidades grandes
+        let factor = 10_f64.powi(self.decimals as i32);
+        let rounded = ((grams as f64 * factor).round() / factor) as f32;
+        if rounded == 0.0 {
+            0.0
+        } else {
+            rounded
+        }
+    }
+}
+
+impl Default for GramRounding {
+    fn default() -> Self {
+        GramRounding::from_env()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_accumulated_residues_are_rounded_to_zero() {
+        let rounding = GramRounding::new(3);
+        let mut quantity = 1.0_f32;
+        (0..10).for_each(|_| quantity -= 0.1);
+
+        assert_ne!(quantity, 0.0);
+        assert_eq!(rounding.round(quantity), 0.0);
+        assert_eq!(rounding.round(0.0000001), 0.0);
+        assert_eq!(rounding.round(0.1 + 0.2), 0.3);
+    }
+
+    #[test]
+    fn test2_negative_zero_and_tiny_negatives_become_positive_zero() {
+        let rounding = GramRounding::new(3);
+
+        assert!(rounding.round(-0.0).is_sign_positive());
+        assert!(rounding.round(-0.0000001).is_sign_positive());
+        assert!(!rounding.round(-0.0004).gt(&0.0));
+        assert_eq!(rounding.round(-0.0006), -0.001);
+    }
+
+    #[test]
+    fn test3_decimals_at_the_boundaries() {
+        assert_eq!(GramRounding::new(0).round(2.5), 3.0);
+        assert_eq!(GramRounding::new(0).round(2.4999), 2.0);
+        assert_eq!(GramRounding::new(2).round(9999.995), 10000.0);
+        assert_eq!(GramRounding::new(100).decimals(), MAX_DECIMALS);
+    }
+}
@@ -1,3 +1,6 @@
+pub mod admission;
+pub mod alert_csv;
+pub mod alert_sink;
 pub mod cafeteria;
 pub mod conteiners;
 pub mod conteiners_states;
@@ -5,30 +8,62 @@ pub mod dispenser;
 pub mod enums;
 pub mod error_dispenser;
 pub mod file_orders;
+pub mod generator;
+pub mod grams;
+pub mod lock_contention;
 pub mod order;
+pub mod order_sender;
+pub mod pantry;
 pub mod periodic_alert;
+pub mod pipeline;
+pub mod queue_monitor;
+pub mod recipes;
+pub mod rejections;
+pub mod replay;
+pub mod respawn;
+pub mod scheduling;
+pub mod service_time;
 pub mod set_conteiners;
+pub mod shutdown;
+pub mod sources;
+pub mod tail_orders;
+pub mod tcp_orders;
 pub mod traits;
 pub mod utils;
+pub mod warm_up;
 
 pub mod sync {
     use std::time::Duration;
 
-    pub(crate) use std::sync::{atomic::AtomicI64, Arc, Condvar, Mutex, MutexGuard};
+    #[cfg(test)]
+    use {std::sync::OnceLock, tiempo::RelojVirtual};
 
-    pub(crate) use std::thread;
+    #[cfg(not(test))]
+    use tiempo::RelojReal;
 
-    pub(crate) fn sleep(_d: Duration) {
-        #[cfg(test)]
-        let sleep_fn = std::thread::yield_now;
+    use tiempo::Reloj;
 
+    pub(crate) use std::sync::{atomic::AtomicI64, mpsc, Arc, Condvar, Mutex, MutexGuard};
+
+    pub(crate) use std::thread;
+
+    /// Reloj de la cafeteria. En los tests es un reloj virtual que avanza solo, asi los contenedores,
+    /// dispensers y alertas no esperan los tiempos reales de aplicacion y recarga pero el tiempo
+    /// transcurrido se sigue pudiendo verificar
+    pub(crate) fn reloj() -> &'static dyn Reloj {
         #[cfg(test)]
-        sleep_fn();
+        {
+            static RELOJ: OnceLock<RelojVirtual> = OnceLock::new();
+            RELOJ.get_or_init(RelojVirtual::automatico)
+        }
 
         #[cfg(not(test))]
-        let sleep_fn = std::thread::sleep;
+        {
+            &RelojReal
+        }
+    }
 
-        #[cfg(not(test))]
-        sleep_fn(_d);
+    pub(crate) fn sleep(duracion: Duration) {
+        reloj().dormir(duracion);
     }
 }
@@ -0,0 +1,242 @@
+use std::{cmp::Reverse, collections::HashMap, time::Duration};
+
+use log::info;
+
+use crate::{
+    dispenser::Dispenser,
+    enums::IngredientType,
+    error_dispenser::ErrorCafeteria,
+    sync::{reloj, Mutex, MutexGuard},
+};
+
+/// Lock compartido entre los dispensers cuyo tiempo de espera se mide
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LockedResource {
+    /// Mutex del contenedor de un ingrediente (`Conteiners::lock_for`)
+    Container(IngredientType),
+
+    /// Mutex de la estructura `ContainersStates`
+    ContainersStates,
+}
+
+/// Lock tomado por un dispenser, identificado por el nombre de su thread
+pub type LockOfDispenser = (LockedResource, String);
+
+/// Tiempos de espera acumulados para tomar un lock
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WaitStats {
+    /// Cantidad de veces que se tomo el lock
+    pub acquisitions: u32,
+
+    /// Tiempo total esperado para tomar el lock
+    pub total_wait: Duration,
+
+    /// Mayor tiempo esperado en una sola toma del lock
+    pub max_wait: Duration,
+}
+
+impl WaitStats {
+    /// Registra una toma del lock luego de esperar `wait`
+    fn record(&mut self, wait: Duration) {
+        self.acquisitions += 1;
+        self.total_wait += wait;
+        self.max_wait = self.max_wait.max(wait);
+    }
+
+    /// Suma las estadisticas de otro dispenser para el mismo lock
+    fn merge(&mut self, other: &WaitStats) {
+        self.acquisitions += other.acquisitions;
+        self.total_wait += other.total_wait;
+        self.max_wait = self.max_wait.max(other.max_wait);
+    }
+
+    /// Tiempo promedio esperado por cada toma del lock
+    pub fn mean_wait(&self) -> Duration {
+        if self.acquisitions == 0 {
+            return Duration::ZERO;
+        }
+        self.total_wait / self.acquisitions
+    }
+}
+
+/// Registro del tiempo que esperan los dispensers para tomar los locks de los contenedores y del `ContainersStates`,
+/// por lock y por dispenser, para identificar cual es el verdadero cuello de botella de la cafeteria.
+///
+/// El tiempo se mide con el reloj de la cafeteria, asi en los tests con reloj virtual se mide el tiempo simulado.
+#[derive(Debug, Default)]
+pub struct LockContention {
+    /// Estadisticas de espera segun el lock y el nombre del thread dispenser que lo tomo
+    waits: Mutex<HashMap<LockOfDispenser, WaitStats>>,
+}
+
+impl LockContention {
+    /// Toma el lock del `mutex` registrando el tiempo esperado por el dispenser actual.
+    ///
+    /// # Arguments
+    ///  * `resource` - Lock que se toma, para agrupar las estadisticas.
+    ///  * `mutex` - Mutex a tomar.
+    /// # Returns
+    ///  * `Result<MutexGuard<'a, T>, ErrorCafeteria>`
+    ///    - Si es Ok, se retorna el MutexGuard del `mutex`.
+    ///    - Si es Err, hubo un fallo al tomar el lock del `mutex` o el de las estadisticas.
+    pub fn lock<'a, T: ?Sized>(
+        &self,
+        resource: LockedResource,
+        mutex: &'a Mutex<T>,
+    ) -> Result<MutexGuard<'a, T>, ErrorCafeteria> {
+        let start = reloj().ahora();
+        let guard = mutex
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+        self.record(
+            resource,
+            Dispenser::id_dispenser(),
+            reloj().ahora().saturating_duration_since(start),
+        )?;
+        Ok(guard)
+    }
+
+    /// Registra que `dispenser` espero `wait` para tomar el lock de `resource`
+    fn record(
+        &self,
+        resource: LockedResource,
+        dispenser: String,
+        wait: Duration,
+    ) -> Result<(), ErrorCafeteria> {
+        self.waits
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .entry((resource, dispenser))
+            .or_default()
+            .record(wait);
+        Ok(())
+    }
+
+    /// Estadisticas de espera de cada lock sumando todos los dispensers, ordenadas de mayor a menor tiempo total esperado
+    pub fn by_resource(&self) -> Result<Vec<(LockedResource, WaitStats)>, ErrorCafeteria> {
+        let mut by_resource: HashMap<LockedResource, WaitStats> = HashMap::new();
+        self.waits
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .iter()
+            .for_each(|((resource, _), stats)| {
+                by_resource.entry(*resource).or_default().merge(stats)
+            });
+
+        let mut by_resource: Vec<(LockedResource, WaitStats)> = by_resource.into_iter().collect();
+        by_resource.sort_by_key(|(_, stats)| Reverse(stats.total_wait));
+        Ok(by_resource)
+    }
+
+    /// Estadisticas de espera de cada lock por dispenser, ordenadas de mayor a menor tiempo total esperado
+    pub fn by_dispenser(&self) -> Result<Vec<(LockOfDispenser, WaitStats)>, ErrorCafeteria> {
+        let mut by_dispenser: Vec<(LockOfDispenser, WaitStats)> = self
+            .waits
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .iter()
+            .map(|(key, stats)| (key.clone(), *stats))
+            .collect();
+        by_dispenser.sort_by_key(|(_, stats)| Reverse(stats.total_wait));
+        Ok(by_dispenser)
+    }
+
+    /// Reporta en el log las estadisticas de espera de cada lock, y el detalle por dispenser
+    pub fn report(&self) -> Result<(), ErrorCafeteria> {
+        info!("[ SYSTEM-ALERT ] Lock contention (total, mean, max wait):");
+        for (resource, stats) in self.by_resource()? {
+            info!(
+                "                    {:?} -> {:?}, {:?}, {:?} in {} acquisitions",
+                resource,
+                stats.total_wait,
+                stats.mean_wait(),
+                stats.max_wait,
+                stats.acquisitions
+            );
+        }
+        for ((resource, dispenser), stats) in self.by_dispenser()? {
+            info!(
+                "                    {} {:?} -> {:?}, {:?}, {:?} in {} acquisitions",
+                dispenser,
+                resource,
+                stats.total_wait,
+                stats.mean_wait(),
+                stats.max_wait,
+                stats.acquisitions
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{sleep, thread, Arc};
+
+    #[test]
+    fn test1_waits_are_aggregated_by_resource_and_by_dispenser() {
+        let contention = LockContention::default();
+        let agua = LockedResource::Container(IngredientType::Agua);
+        contention
+            .record(agua, "D0".to_string(), Duration::from_secs(1))
+            .unwrap();
+        contention
+            .record(agua, "D1".to_string(), Duration::from_secs(4))
+            .unwrap();
+        contention
+            .record(agua, "D1".to_string(), Duration::from_secs(1))
+            .unwrap();
+        contention
+            .record(
+                LockedResource::ContainersStates,
+                "D0".to_string(),
+                Duration::from_secs(2),
+            )
+            .unwrap();
+
+        let by_resource = contention.by_resource().unwrap();
+        assert_eq!(by_resource[0].0, agua);
+        assert_eq!(
+            by_resource[0].1,
+            WaitStats {
+                acquisitions: 3,
+                total_wait: Duration::from_secs(6),
+                max_wait: Duration::from_secs(4),
+            }
+        );
+        assert_eq!(by_resource[0].1.mean_wait(), Duration::from_secs(2));
+        assert_eq!(by_resource[1].0, LockedResource::ContainersStates);
+
+        let by_dispenser = contention.by_dispenser().unwrap();
+        assert_eq!(by_dispenser[0].0, (agua, "D1".to_string()));
+        assert_eq!(by_dispenser[0].1.total_wait, Duration::from_secs(5));
+        assert_eq!(by_dispenser.len(), 3);
+    }
+
+    #[test]
+    fn test2_lock_measures_the_time_waited_while_another_thread_holds_the_mutex() {
+        let contention = Arc::new(LockContention::default());
+        let mutex = Arc::new(Mutex::new(0));
+        let cacao = LockedResource::Container(IngredientType::Cacao);
+
+        let guard = contention.lock(cacao, &mutex).unwrap();
+        let waiter = {
+            let (contention, mutex) = (contention.clone(), mutex.clone());
+            thread::Builder::new()
+                .name("[ DISPENSER#1 ]".to_string())
+                .spawn(move || *contention.lock(cacao, &mutex).unwrap() += 1)
+                .unwrap()
+        };
+        // el reloj virtual avanza al instante, se espera en tiempo real a que el otro thread quede bloqueado en el lock
+        std::thread::sleep(Duration::from_millis(200));
+        sleep(Duration::from_secs(3));
+        drop(guard);
+        waiter.join().unwrap();
+
+        let by_dispenser = contention.by_dispenser().unwrap();
+        assert_eq!(by_dispenser[0].0, (cacao, "[ DISPENSER#1 ]".to_string()));
+        assert!(by_dispenser[0].1.total_wait >= Duration::from_secs(3));
+        assert_eq!(contention.by_resource().unwrap()[0].1.acquisitions, 2);
+    }
+}
@@ -1,17 +1,70 @@
 use dotenv::dotenv;
 use std::{env, path::Path};
-use tp1::{cafeteria, error_dispenser::ErrorCafeteria, utils::init_logger};
+use tp1::{
+    cafeteria,
+    error_dispenser::ErrorCafeteria,
+    file_orders,
+    generator::{Generator, GeneratorConfig},
+    sources::Sources,
+    tail_orders::OrdersTail,
+    tcp_orders::TcpOrders,
+    traits::OrderSource,
+    utils::init_logger,
+};
 
 fn main() -> Result<(), ErrorCafeteria> {
     init_logger();
     dotenv().ok();
 
-    let args: Vec<String> = env::args().collect();
-    let mut file_name = &String::from("orders.txt"); // default file name
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    if args.len() > 1 {
-        file_name = &args[1];
+    // todos los pedidos del archivo en un unico thread, con decisiones de planificacion reproducibles
+    if args.first().map(String::as_str) == Some("--replay") {
+        let file_name = args.get(1).map(String::as_str).unwrap_or("orders.txt");
+        return cafeteria::start_replay(Path::new(file_name)).map(|_| ());
     }
 
-    cafeteria::start(Path::new(file_name))
+    let mut sources = parse_sources(&args)?;
+
+    // con una unica fuente se ejecuta sola, con varias se combinan en la misma cola de pedidos
+    if sources.len() > 1 {
+        let sources = sources
+            .into_iter()
+            .fold(Sources::new(), Sources::with_source);
+        return cafeteria::start_with_source(sources);
+    }
+    match sources.pop() {
+        Some(source) => cafeteria::start_with_source(source),
+        None => cafeteria::start(Path::new("orders.txt")), // default file name
+    }
+}
+
+/// Fuentes de pedidos de los argumentos:
+///  * `--generador`: los pedidos llegan durante la ejecucion segun la configuracion del generador.
+///  * `--tail <archivo>`: el archivo se sigue leyendo mientras otro proceso lo escribe, hasta la marca de fin.
+///  * `--tcp <direccion>`: los pedidos llegan por TCP, hasta la marca de fin.
+///  * `--stdin`: los pedidos se leen de la entrada estandar, hasta que se cierra.
+///  * Cualquier otro argumento es un archivo de ordenes.
+fn parse_sources(args: &[String]) -> Result<Vec<Box<dyn OrderSource + Send>>, ErrorCafeteria> {
+    let mut sources = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let source: Box<dyn OrderSource + Send> = match arg.as_str() {
+            "--generador" => Box::new(Generator::new(GeneratorConfig::from_env())),
+            "--tail" => {
+                let file_name = args.next().map(String::as_str).unwrap_or("orders.txt");
+                Box::new(OrdersTail::open(Path::new(file_name))?)
+            }
+            "--tcp" => {
+                let address = args.next().map(String::as_str).unwrap_or("127.0.0.1:7878");
+                Box::new(TcpOrders::bind(address)?)
+            }
+            "--stdin" => Box::new(file_orders::stream_stdin_orders()?),
+            file_name => Box::new(file_orders::stream_orders(Path::new(file_name))?),
+        };
+        sources.push(source);
+    }
+
+    Ok(sources)
 }
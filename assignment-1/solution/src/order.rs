@@ -4,7 +4,7 @@ use std::{
     sync::{Arc, Condvar, Mutex},
 };
 
-use crate::{error_dispenser::ErrorCafeteria, sync::AtomicI64, utils::Consts};
+use crate::{error_dispenser::ErrorCafeteria, recipes::Recipe, sync::AtomicI64, utils::Consts};
 
 use crate::enums::{IngredientStateOfOrder, IngredientType, OrderState};
 
@@ -19,10 +19,45 @@ pub struct Order {
 
     /// Estado del pedido
     pub status: OrderState,
+
+    /// Ids de los pedidos que deben finalizar antes de que este pedido pueda comenzar.
+    pub dependencies: Vec<usize>,
+
+    /// Receta con la que se pidio el pedido, si se pidio por nombre de bebida.
+    pub recipe: Option<Arc<Recipe>>,
+}
+
+impl Clone for Order {
+    /// Copia el pedido conservando su id, sin incrementar el contador de pedidos
+    fn clone(&self) -> Self {
+        Order {
+            id: AtomicI64::new(self.id.load(std::sync::atomic::Ordering::SeqCst)),
+            ingredientes: self.ingredientes.clone(),
+            status: self.status,
+            dependencies: self.dependencies.clone(),
+            recipe: self.recipe.clone(),
+        }
+    }
 }
 
-/// Para manejar pedidos con IDs de forma interna, sin tener que pasarle un ID al crearlo.
-static CONTADOR_PEDIDOS: AtomicI64 = AtomicI64::new(0_i64);
+/// Contador de los ids de los pedidos que se crean con `Order::new`, para no tener que pasarle un id a cada pedido.
+///
+/// Cada Cafetería tiene su propio contador (ver `Cafeteria::order_ids`), asi dos cafeterias en el mismo proceso (o los tests
+/// que corren en paralelo) numeran sus pedidos desde 0 sin intercalarse. Los clones comparten el contador.
+#[derive(Debug, Clone, Default)]
+pub struct OrderIds(Arc<AtomicI64>);
+
+impl OrderIds {
+    /// Crea un contador que comienza en 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retorna el siguiente id del contador
+    pub fn next_id(&self) -> i64 {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    }
+}
 
 impl Order {
     /// Crea un nuevo pedido con los ingredientes necesarios para completar un pedido.
@@ -30,13 +65,14 @@ impl Order {
     /// Si se pasa un ingrediente con valor 0.0 o menos, este no se considera en el pedido.
     ///
     /// # Arguments
+    /// * `ids` - Contador del que se toma el id del pedido.
     /// * `cm` - Cantidad de cafe molido necesaria para el pedido.
     /// * `lc` - Cantidad de espuma de leche  necesaria para el pedido.
     /// * `c` - Cantidad de cacao necesaria para el pedido.
     /// * `ac` - Cantidad de agua caliente necesaria para el pedido.
-    pub fn new(cm: f32, lc: f32, c: f32, ac: f32) -> Self {
+    pub fn new(ids: &OrderIds, cm: f32, lc: f32, c: f32, ac: f32) -> Self {
         Order {
-            id: AtomicI64::new(CONTADOR_PEDIDOS.fetch_add(1, std::sync::atomic::Ordering::SeqCst)),
+            id: AtomicI64::new(ids.next_id()),
             ingredientes: HashMap::from([
                 (IngredientType::CafeMolido, cm),
                 (IngredientType::EspumaLeche, lc),
@@ -48,10 +84,12 @@ impl Order {
             .map(|(k, v)| (k, IngredientStateOfOrder::NotApplied(v)))
             .collect(),
             status: OrderState::InProgress,
+            dependencies: Vec::new(),
+            recipe: None,
         }
     }
 
-    /// Idem a new() pero con un id especifico.
+    /// Idem a new() pero con un id especifico, por ejemplo el que se obtiene del archivo de pedidos.
     pub fn new_with_id(id: usize, cm: f32, lc: f32, c: f32, ac: f32) -> Self {
         Order {
             id: AtomicI64::new(id as i64),
@@ -66,9 +104,30 @@ impl Order {
             .map(|(k, v)| (k, IngredientStateOfOrder::NotApplied(v)))
             .collect(),
             status: OrderState::InProgress,
+            dependencies: Vec::new(),
+            recipe: None,
         }
     }
 
+    /// Retorna el pedido indicando los ids de los pedidos que deben finalizar antes de que este comience.
+    pub fn with_dependencies(mut self, dependencies: Vec<usize>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Retorna el pedido indicando la receta con la que se pidio.
+    pub fn with_recipe(mut self, recipe: Option<Arc<Recipe>>) -> Self {
+        self.recipe = recipe;
+        self
+    }
+
+    /// Retorna true si todos los pedidos de los que depende este pedido ya finalizaron.
+    pub fn dependencies_finished(&self, finished_orders: &HashMap<usize, OrderState>) -> bool {
+        self.dependencies
+            .iter()
+            .all(|id| finished_orders.contains_key(id))
+    }
+
     /// Dado un tipo de ingrediente y una quantity_available, retorna true si el pedido requiere ese ingrediente
     /// y la quantity_available es suficiente para ese ingrediente.
     pub fn can_satisfy(&self, tipo: &IngredientType, quantity_available: f32) -> bool {
@@ -121,6 +180,15 @@ impl Order {
             .insert(tipo, IngredientStateOfOrder::NoEnoughResourceContainer);
     }
 
+    /// Retorna los ingredientes que no pudieron aplicarse por falta de recursos en los contenedores
+    pub fn missing_ingredients(&self) -> Vec<IngredientType> {
+        self.ingredientes
+            .iter()
+            .filter(|(_, state)| matches!(state, IngredientStateOfOrder::NoEnoughResourceContainer))
+            .map(|(tipo, _)| *tipo)
+            .collect()
+    }
+
     /// Retorna true si el pedido requiere el ingrediente
     pub fn requiere(&self, tipo: &IngredientType) -> bool {
         self.ingredientes.contains_key(tipo) && self.get(tipo).unwrap_or(0.0) > 0.0
@@ -160,6 +228,62 @@ impl Order {
     }
 }
 
+/// Numeracion de los pedidos de una de las fuentes que se combinan en la cola de pedidos (ver `OrderSource`).
+///
+/// El pedido `id` de la fuente `index`, de un total de `sources` fuentes, pasa a tener el id `id * sources + index`: asi los ids
+/// de distintas fuentes nunca se repiten en los pedidos finalizados, y de cada id se obtiene su fuente. Por defecto hay una
+/// unica fuente y los ids no cambian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceIds {
+    /// Posicion de la fuente, desde 0
+    pub index: usize,
+
+    /// Cantidad total de fuentes
+    pub sources: usize,
+}
+
+impl SourceIds {
+    /// Crea la numeracion de la fuente `index` de un total de `sources` fuentes (al menos 1)
+    pub fn new(index: usize, sources: usize) -> Self {
+        let sources = sources.max(1);
+        SourceIds {
+            index: index % sources,
+            sources,
+        }
+    }
+
+    /// Id que tiene en la cola de pedidos el pedido `id` de la fuente
+    pub fn global(&self, id: usize) -> usize {
+        id * self.sources + self.index
+    }
+
+    /// Posicion de la fuente de la que proviene un pedido con el id `global`
+    pub fn source_of(&self, global: usize) -> usize {
+        global % self.sources
+    }
+
+    /// Renumera el id del pedido y los de los pedidos de los que depende, que son de la misma fuente
+    pub fn renumber(&self, order: Order) -> Order {
+        let id = order.id.load(std::sync::atomic::Ordering::SeqCst) as usize;
+        let dependencies = order
+            .dependencies
+            .iter()
+            .map(|dependency| self.global(*dependency))
+            .collect();
+        Order {
+            id: AtomicI64::new(self.global(id) as i64),
+            dependencies,
+            ..order
+        }
+    }
+}
+
+impl Default for SourceIds {
+    fn default() -> Self {
+        SourceIds::new(0, 1)
+    }
+}
+
 /// Thread principal productor, encargado de insertar los pedidos en la cola de pedidos del par de VecDeque y Condvar recibidos.
 ///
 /// Esta funcion se encargar de iterar por cada pedido; hace un wait() sobre la  condvar para no seguir
@@ -167,6 +291,8 @@ impl Order {
 ///
 /// Luego, inserta el pedido en la cola de pedidos, y hace un notify_all() sobre la condvar para que los consumidores puedan tomar el pedido.
 ///
+/// Los pedidos se insertan en el orden recibido sin tener en cuenta sus dependencias, ver `insert_orders_with_dependencies`.
+///
 /// # Arguments
 ///  * `orders_to_process` - Vector de pedidos a insertar en la cola de pedidos de la Condvar para que los dispensers consumidores los tomen y procesen.
 ///  * `pair_vecdeque_orders` - Par de VecDeque y Condvar que representa la cola de pedidos.
@@ -176,36 +302,267 @@ impl Order {
 ///     * Si es Err, es por que hubo un error en el wait() o al tomar el lock del Mutex o porque se encontro en el mutex de la cola de pedidos un None
 ///       cuando no deberia pasar (pues ningun dispenser tendria que haber insertado un None a dicha cola).
 pub fn insert_orders(
-    orders_to_process: Vec<Order>,
+    orders_to_process: impl IntoIterator<Item = Order>,
     pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
 ) -> Result<(), ErrorCafeteria> {
     for order in orders_to_process {
-        let (lock, cvar) = &**pair_vecdeque_orders;
+        insert_order(order, pair_vecdeque_orders)?;
+    }
+
+    Ok(())
+}
+
+/// Thread principal productor, igual a `insert_orders` pero respetando las dependencias entre pedidos: un pedido recien
+/// se inserta en la cola cuando todos los pedidos de los que depende finalizaron (completados o no).
+///
+/// Los pedidos se toman de a uno de `orders_to_process` (por ejemplo de `stream_orders`, sin cargar todo el archivo): los que
+/// ya estan liberados se insertan en orden, y los demas quedan pendientes hasta que finalicen sus dependencias. Al terminar de
+/// tomar pedidos, mientras ninguno de los pendientes este liberado se hace un wait() sobre la condvar de los pedidos finalizados
+/// hasta que algun dispenser finalice un pedido.
+///
+/// # Arguments
+///  * `orders_to_process` - Pedidos a insertar. Las dependencias deben formar un DAG, como garantiza `read_orders`.
+///  * `pair_vecdeque_orders` - Par de VecDeque y Condvar que representa la cola de pedidos.
+///  * `pair_finished_orders` - Par de HashMap y Condvar con el estado final de cada pedido finalizado por los dispensers, segun su id.
+/// # Returns
+/// * `Result<(), ErrorCafeteria>` - Resultado de la operacion.
+///     * Si es Ok, se insertaron todos los pedidos en la cola de pedidos.
+///     * Si es Err, es por que hubo un error en algun wait() o al tomar el lock de algun Mutex, o porque se encontro en el mutex de la cola
+///       de pedidos un None.
+pub fn insert_orders_with_dependencies(
+    orders_to_process: impl IntoIterator<Item = Order>,
+    pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+    pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+) -> Result<(), ErrorCafeteria> {
+    let mut pending_orders = Vec::new();
+
+    for order in orders_to_process {
+        pending_orders.push(order);
+        let (ready_orders, waiting_orders): (Vec<Order>, Vec<Order>) = {
+            let _guard = pair_finished_orders
+                .0
+                .lock()
+                .map_err(|e| ErrorCafeteria::new(&format!("Error lock: {:?}", e)))?;
+            pending_orders
+                .into_iter()
+                .partition(|order| order.dependencies_finished(&_guard))
+        };
+        for order in ready_orders {
+            insert_order(order, pair_vecdeque_orders)?;
+        }
+        pending_orders = waiting_orders;
+    }
+
+    while !pending_orders.is_empty() {
+        let (lock, cvar) = &**pair_finished_orders;
         let mut _guard = lock
             .lock()
             .map_err(|e| ErrorCafeteria::new(&format!("Error lock: {:?}", e)))?;
 
-        while _guard
-            .as_ref()
-            .map(|g| g.len() >= Consts::n_dispensers())
-            .unwrap_or(false)
+        while !pending_orders
+            .iter()
+            .any(|order| order.dependencies_finished(&_guard))
         {
             _guard = cvar
                 .wait(_guard)
                 .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
         }
 
-        let mut optional_orders = _guard.take();
-        if let Some(veq) = optional_orders.as_mut() {
-            veq.push_front(order);
-            _guard.replace(optional_orders.unwrap_or_default());
-            cvar.notify_all();
-        } else {
-            return Err(ErrorCafeteria::new(
-                "VeqDeque has a None when it shouldn't occur",
-            ));
+        let (ready_orders, waiting_orders): (Vec<Order>, Vec<Order>) = pending_orders
+            .into_iter()
+            .partition(|order| order.dependencies_finished(&_guard));
+        drop(_guard);
+
+        // se suelta el lock de los finalizados para que los dispensers puedan seguir registrando pedidos
+        // mientras se espera lugar en la cola de pedidos
+        for order in ready_orders {
+            insert_order(order, pair_vecdeque_orders)?;
         }
+        pending_orders = waiting_orders;
     }
 
     Ok(())
 }
+
+/// Inserta un pedido en la cola de pedidos, esperando con un wait() sobre la condvar a que la cola tenga lugar.
+fn insert_order(
+    order: Order,
+    pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+) -> Result<(), ErrorCafeteria> {
+    let (lock, cvar) = &**pair_vecdeque_orders;
+    let mut _guard = lock
+        .lock()
+        .map_err(|e| ErrorCafeteria::new(&format!("Error lock: {:?}", e)))?;
+
+    while _guard
+        .as_ref()
+        .map(|g| g.len() >= Consts::n_dispensers())
+        .unwrap_or(false)
+    {
+        _guard = cvar
+            .wait(_guard)
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+    }
+
+    let mut optional_orders = _guard.take();
+    if let Some(veq) = optional_orders.as_mut() {
+        veq.push_front(order);
+        _guard.replace(optional_orders.unwrap_or_default());
+        cvar.notify_all();
+        Ok(())
+    } else {
+        Err(ErrorCafeteria::new(
+            "VeqDeque has a None when it shouldn't occur",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests_order {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::{
+        dispenser::Dispenser,
+        sync::{mpsc, thread},
+    };
+
+    #[test]
+    fn test1_order_is_inserted_only_after_its_dependency_finishes() {
+        let pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)> =
+            Arc::new((Mutex::new(Some(VecDeque::new())), Condvar::new()));
+        let pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)> =
+            Arc::new((Mutex::new(HashMap::new()), Condvar::new()));
+        let orders = vec![
+            Order::new_with_id(0, 10.0, 0.0, 0.0, 0.0),
+            Order::new_with_id(1, 10.0, 0.0, 0.0, 0.0).with_dependencies(vec![0]),
+        ];
+
+        let producer = {
+            let pair_vecdeque_orders = pair_vecdeque_orders.clone();
+            let pair_finished_orders = pair_finished_orders.clone();
+            thread::spawn(move || {
+                insert_orders_with_dependencies(
+                    orders,
+                    &pair_vecdeque_orders,
+                    &pair_finished_orders,
+                )
+            })
+        };
+
+        let first = Dispenser::wait_pedido(&pair_vecdeque_orders)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.id.load(Ordering::SeqCst), 0);
+
+        // mientras el pedido 0 no finaliza, el pedido 1 no puede estar en la cola
+        assert!(pair_vecdeque_orders
+            .0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .is_empty());
+
+        pair_finished_orders
+            .0
+            .lock()
+            .unwrap()
+            .insert(0, OrderState::NoEnoughResourceContainer);
+        pair_finished_orders.1.notify_all();
+
+        let second = Dispenser::wait_pedido(&pair_vecdeque_orders)
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.id.load(Ordering::SeqCst), 1);
+        assert!(producer.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn test3_orders_are_inserted_while_they_are_still_being_read() {
+        let pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)> =
+            Arc::new((Mutex::new(Some(VecDeque::new())), Condvar::new()));
+        let pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)> =
+            Arc::new((Mutex::new(HashMap::new()), Condvar::new()));
+        let (sender, receiver) = mpsc::channel();
+
+        let producer = {
+            let pair_vecdeque_orders = pair_vecdeque_orders.clone();
+            let pair_finished_orders = pair_finished_orders.clone();
+            thread::spawn(move || {
+                insert_orders_with_dependencies(
+                    receiver,
+                    &pair_vecdeque_orders,
+                    &pair_finished_orders,
+                )
+            })
+        };
+
+        // el pedido 1 queda pendiente de su dependencia, pero no demora al pedido 2
+        sender
+            .send(Order::new_with_id(0, 10.0, 0.0, 0.0, 0.0))
+            .unwrap();
+        let first = Dispenser::wait_pedido(&pair_vecdeque_orders)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.id.load(Ordering::SeqCst), 0);
+
+        sender
+            .send(Order::new_with_id(1, 10.0, 0.0, 0.0, 0.0).with_dependencies(vec![0]))
+            .unwrap();
+        sender
+            .send(Order::new_with_id(2, 10.0, 0.0, 0.0, 0.0))
+            .unwrap();
+        let second = Dispenser::wait_pedido(&pair_vecdeque_orders)
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.id.load(Ordering::SeqCst), 2);
+        drop(sender);
+
+        pair_finished_orders
+            .0
+            .lock()
+            .unwrap()
+            .insert(0, OrderState::Completed);
+        pair_finished_orders.1.notify_all();
+
+        let third = Dispenser::wait_pedido(&pair_vecdeque_orders)
+            .unwrap()
+            .unwrap();
+        assert_eq!(third.id.load(Ordering::SeqCst), 1);
+        assert!(producer.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn test2_source_ids_renumber_orders_and_dependencies_without_repeating_between_sources() {
+        let first = SourceIds::new(0, 3);
+        let third = SourceIds::new(2, 3);
+        let order = Order::new_with_id(4, 0.0, 0.0, 0.0, 1.0).with_dependencies(vec![1, 3]);
+
+        let renumbered = third.renumber(order);
+
+        assert_eq!(renumbered.id.load(Ordering::SeqCst), 14);
+        assert_eq!(renumbered.dependencies, vec![5, 11]);
+        assert_eq!(third.source_of(14), 2);
+        assert_ne!(first.global(4), third.global(4));
+        assert_eq!(SourceIds::default().global(4), 4);
+    }
+
+    #[test]
+    fn test4_orders_of_different_counters_are_numbered_independently() {
+        let first = OrderIds::new();
+        let second = OrderIds::new();
+        let shared = first.clone();
+
+        let ids: Vec<i64> = [&first, &second, &shared, &second]
+            .iter()
+            .map(|ids| {
+                Order::new(ids, 0.0, 0.0, 0.0, 1.0)
+                    .id
+                    .load(Ordering::SeqCst)
+            })
+            .collect();
+
+        assert_eq!(ids, vec![0, 0, 1, 1]);
+    }
+}
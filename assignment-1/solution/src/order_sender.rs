@@ -0,0 +1,142 @@
+use log::debug;
+
+use crate::{
+    error_dispenser::ErrorCafeteria,
+    order::Order,
+    sync::mpsc::{sync_channel, Receiver, SyncSender},
+    traits::OrderSource,
+    utils::Consts,
+};
+
+/// Crea un canal para enviar pedidos a la Cafetería mientras se ejecuta (ver `Cafeteria::start_with_source`).
+///
+/// El canal tiene lugar para `N_DISPENSERS` pedidos, igual que la cola de pedidos, asi quien envia pedidos espera cuando
+/// los dispensers no dan abasto.
+///
+/// # Returns
+/// * `(OrderSender, OrderReceiver)`: El handle para enviar pedidos, que puede clonarse, y el extremo que recibe la Cafetería.
+pub fn order_channel() -> (OrderSender, OrderReceiver) {
+    let (sender, receiver) = sync_channel(Consts::n_dispensers());
+    (OrderSender { sender }, OrderReceiver { receiver })
+}
+
+/// Handle para enviar pedidos a la Cafetería mientras se ejecuta, desde cualquier thread (por ejemplo un front-end TCP o REST).
+///
+/// La Cafetería deja de aceptar pedidos y se apaga cuando se descartan todos los `OrderSender` del canal.
+#[derive(Debug, Clone)]
+pub struct OrderSender {
+    /// Extremo del canal por el que se envian los pedidos
+    sender: SyncSender<Order>,
+}
+
+impl OrderSender {
+    /// Envia un pedido a la Cafetería. Si el canal esta lleno, espera a que la Cafetería tome pedidos del mismo.
+    ///
+    /// # Returns
+    /// * `Result<(), ErrorCafeteria>`:
+    ///    * Si es Ok, el pedido se envio a la Cafetería.
+    ///    * Si es Err, la Cafetería ya no acepta pedidos (se descarto el `OrderReceiver`).
+    pub fn send(&self, order: Order) -> Result<(), ErrorCafeteria> {
+        self.sender
+            .send(order)
+            .map_err(|_| ErrorCafeteria::new("The cafeteria is no longer accepting orders."))
+    }
+}
+
+/// Extremo del canal de pedidos que recibe la Cafetería
+#[derive(Debug)]
+pub struct OrderReceiver {
+    /// Extremo del canal por el que se reciben los pedidos
+    receiver: Receiver<Order>,
+}
+
+/// Los pedidos se retornan en el orden en que llegan, hasta que se descartan todos los `OrderSender`
+impl OrderSource for OrderReceiver {
+    fn next_order(&mut self) -> Option<Order> {
+        let order = self.receiver.recv().ok()?;
+        debug!(
+            "[ ORDER-SENDER ] | [Order#{:?}] NEW ORDER SENT.\n                 Requeriments: {:?}",
+            order.id, order.ingredientes
+        );
+        Some(order)
+    }
+
+    fn name(&self) -> String {
+        "channel".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, VecDeque},
+        sync::atomic::Ordering,
+    };
+
+    use super::*;
+    use crate::{
+        dispenser::Dispenser,
+        enums::OrderState,
+        sources::insert_source_orders,
+        sync::{thread, Arc, Condvar, Mutex},
+    };
+
+    #[test]
+    fn test1_orders_sent_from_many_threads_are_inserted_until_every_sender_is_dropped() {
+        let (sender, mut receiver) = order_channel();
+        let pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)> =
+            Arc::new((Mutex::new(Some(VecDeque::new())), Condvar::new()));
+        let pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)> =
+            Arc::new((Mutex::new(HashMap::new()), Condvar::new()));
+
+        let senders = (0..4)
+            .map(|i| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    (0..10).for_each(|j| {
+                        sender
+                            .send(Order::new_with_id(i * 10 + j, 0.0, 0.0, 0.0, 1.0))
+                            .unwrap()
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(sender);
+
+        let consumer = {
+            let pair_vecdeque_orders = pair_vecdeque_orders.clone();
+            thread::spawn(move || {
+                let mut ids = (0..40)
+                    .map(|_| {
+                        Dispenser::wait_pedido(&pair_vecdeque_orders)
+                            .unwrap()
+                            .unwrap()
+                    })
+                    .map(|order| order.id.load(Ordering::SeqCst) as usize)
+                    .collect::<Vec<usize>>();
+                ids.sort();
+                ids
+            })
+        };
+
+        assert_eq!(
+            insert_source_orders(&mut receiver, &pair_vecdeque_orders, &pair_finished_orders)
+                .unwrap(),
+            40
+        );
+        senders
+            .into_iter()
+            .for_each(|sender| sender.join().unwrap());
+        assert_eq!(consumer.join().unwrap(), (0..40).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test2_send_fails_when_the_cafeteria_no_longer_accepts_orders() {
+        let (sender, receiver) = order_channel();
+        drop(receiver);
+
+        assert!(sender
+            .send(Order::new_with_id(0, 0.0, 0.0, 0.0, 1.0))
+            .is_err());
+    }
+}
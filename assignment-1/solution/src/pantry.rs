@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use log::debug;
+
+use crate::enums::IngredientType;
+use crate::sync::Mutex;
+
+/// Despensa de ingredientes crudos (granos de cafe, leche fria) compartida entre varias cafeterias.
+///
+/// Los contenedores recargables, al quedarse sin cantidad para reponer, le piden a la despensa
+/// permiso para retirar mas. La despensa decide si lo otorga, de forma que entre todas las
+/// cafeterias que la comparten nunca se retire mas stock del que existe.
+pub trait Pantry: Send + Sync {
+    /// Solicita retirar la cantidad indicada del ingrediente. Retorna true solo si la despensa otorgo
+    /// el permiso y la cantidad ya fue descontada de su stock; en caso contrario no se descuenta nada.
+    ///
+    /// # Arguments
+    /// * `tipo` - Tipo de ingrediente a retirar
+    /// * `quantity` - Cantidad en gramos a retirar
+    fn grant(&self, tipo: IngredientType, quantity: f32) -> bool;
+}
+
+/// Despensa en memoria para las cafeterias de un mismo proceso. El stock de cada ingrediente se
+/// protege con un mutex, asi el chequeo y el descuento de un retiro son atomicos.
+pub struct SharedPantry {
+    /// Stock en gramos de cada ingrediente de la despensa.
+    stock: Mutex<HashMap<IngredientType, f32>>,
+}
+
+impl SharedPantry {
+    /// Crea una despensa con el stock inicial indicado. Los ingredientes que no figuran no tienen stock.
+    pub fn new(stock: HashMap<IngredientType, f32>) -> Self {
+        SharedPantry {
+            stock: Mutex::new(stock),
+        }
+    }
+
+    /// Retorna la cantidad del ingrediente que queda en la despensa.
+    pub fn available(&self, tipo: IngredientType) -> f32 {
+        match self.stock.lock() {
+            Ok(stock) => stock.get(&tipo).copied().unwrap_or(0.0),
+            Err(_) => 0.0,
+        }
+    }
+}
+
+impl Pantry for SharedPantry {
+    fn grant(&self, tipo: IngredientType, quantity: f32) -> bool {
+        let mut stock = match self.stock.lock() {
+            Ok(stock) => stock,
+            Err(_) => return false,
+        };
+        match stock.get_mut(&tipo) {
+            Some(available) if *available >= quantity => {
+                *available -= quantity;
+                debug!(
+                    "[PANTRY] GRANTED {} grams of {:?}. Remaining: {}",
+                    quantity, tipo, available
+                );
+                true
+            }
+            _ => {
+                debug!("[PANTRY] DENIED {} grams of {:?}", quantity, tipo);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+    use crate::enums::OrderState;
+    use crate::order::{Order, OrderIds};
+    use crate::set_conteiners::rechargable_conteiner::RechargableConteiner;
+    use crate::traits::ApplyContainer;
+
+    #[test]
+    fn test1_grant_is_denied_when_stock_is_not_enough() {
+        let pantry = SharedPantry::new(HashMap::from([(IngredientType::GranosCafe, 100.0)]));
+
+        assert!(pantry.grant(IngredientType::GranosCafe, 60.0));
+        assert!(!pantry.grant(IngredientType::GranosCafe, 60.0));
+        assert!(!pantry.grant(IngredientType::LecheFria, 1.0));
+        assert_eq!(pantry.available(IngredientType::GranosCafe), 40.0);
+    }
+
+    #[test]
+    fn test2_concurrent_cafeterias_can_not_over_draw_the_shared_stock() {
+        let pantry = Arc::new(SharedPantry::new(HashMap::from([(
+            IngredientType::GranosCafe,
+            500.0,
+        )])));
+
+        let cafeterias: Vec<_> = (0..4)
+            .map(|_| {
+                let pantry = pantry.clone();
+                thread::spawn(move || {
+                    let mut container = RechargableConteiner::new(
+                        IngredientType::CafeMolido,
+                        100.0,
+                        (IngredientType::GranosCafe, 0.0),
+                    )
+                    .with_pantry(pantry);
+                    let mut delivered = 0.0;
+                    for _ in 0..5 {
+                        let mut order = Order::new(&OrderIds::default(), 100.0, 0.0, 0.0, 0.0);
+                        container.apply_ingredient(&mut order);
+                        if order.get_updated_status() == OrderState::Completed {
+                            delivered += 100.0;
+                        }
+                    }
+                    delivered
+                })
+            })
+            .collect();
+
+        let delivered: f32 = cafeterias.into_iter().map(|c| c.join().unwrap()).sum();
+
+        // cada cafeteria arranca con 100 gramos en su contenedor, el resto sale de la despensa
+        assert_eq!(delivered, 4.0 * 100.0 + 500.0);
+        assert_eq!(pantry.available(IngredientType::GranosCafe), 0.0);
+    }
+}
@@ -1,19 +1,82 @@
-use std::{collections::VecDeque, time::Duration};
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 
 use crate::sync::sleep;
 use crate::sync::thread::{self, Builder, JoinHandle};
-use crate::sync::{Arc, Condvar, Mutex};
+use crate::sync::{mpsc::Sender, Arc, Condvar, Mutex};
 
-use log::{debug, info};
+use log::{debug, error};
 
 use crate::{
+    alert_csv::AlertCsv,
+    alert_sink::{AlertSample, AlertSink},
     conteiners_states::ContainersStates,
-    enums::{self, OrderState},
+    enums::OrderState,
     error_dispenser::ErrorCafeteria,
     order::Order,
+    rejections::RejectionHistogram,
+    scheduling::SchedulingHints,
     utils::{Consts, TIME_PERIODIC_ALERT},
 };
 
+/// Cantidad total de pedidos que el sistema va a procesar, para que el SYSTEM-ALERT sepa cuando dejar de esperar por pedidos.
+///
+/// Puede no conocerse al crear el SYSTEM-ALERT (por ejemplo si los pedidos llegan por un `OrderSender`), en ese caso se
+/// settea con `set` cuando ya no hay pedidos para insertar.
+#[derive(Debug, Clone)]
+pub struct TotalOrders(Arc<AtomicUsize>);
+
+impl TotalOrders {
+    /// Valor que representa que todavia no se conoce la cantidad total de pedidos
+    const UNKNOWN: usize = usize::MAX;
+
+    /// Crea la cantidad total de pedidos sin conocerla todavia
+    pub fn unknown() -> Self {
+        TotalOrders(Arc::new(AtomicUsize::new(Self::UNKNOWN)))
+    }
+
+    /// Cantidad total de pedidos, si ya se conoce
+    pub fn get(&self) -> Option<usize> {
+        Some(self.0.load(Ordering::SeqCst)).filter(|total| *total != Self::UNKNOWN)
+    }
+
+    /// Retorna true si ya se conoce la cantidad total de pedidos y se recibieron todos
+    pub fn reached(&self, received: usize) -> bool {
+        self.get().is_some_and(|total| received >= total)
+    }
+
+    /// Settea la cantidad total de pedidos y despierta al SYSTEM-ALERT, que puede estar esperando un pedido que ya no va a llegar.
+    ///
+    /// Se settea con el lock de la cola de pedidos finalizados tomado, asi el SYSTEM-ALERT no pierde la notificacion.
+    ///
+    /// # Arguments
+    ///   * `total` - Cantidad total de pedidos.
+    ///   * `pair_vecdeque_system_alert` - Arc<(`Mutex<VecDeque<Order>>`, Condvar)>: Pair de Mutex y Condvar para la cola pedidos finalizados.
+    pub fn set(
+        &self,
+        total: usize,
+        pair_vecdeque_system_alert: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+    ) -> Result<(), ErrorCafeteria> {
+        let (lock, cvar) = &**pair_vecdeque_system_alert;
+        let _guard = lock
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+        self.0.store(total, Ordering::SeqCst);
+        cvar.notify_all();
+        Ok(())
+    }
+}
+
+impl From<usize> for TotalOrders {
+    fn from(total: usize) -> Self {
+        TotalOrders(Arc::new(AtomicUsize::new(total)))
+    }
+}
+
 /// Estructura encargada de ejecutar el Thread que se encarga de reportar periódicamente el estado del sistema
 pub struct PeriodicAlert {
     /// Handle del thread dispenser. Se utiliza un Option para poder crear una instancia de Dispenser
@@ -22,13 +85,36 @@ pub struct PeriodicAlert {
     /// El JoinHandle contendra un Result que indica si el thread termino correctamente o no.
     /// En caso terminar correctamente, contendra un VecDeque con las ordenes que fueron procesadas en el sistema.
     pub handle: Option<JoinHandle<Result<VecDeque<Order>, ErrorCafeteria>>>,
+
+    /// Sugerencias de planificacion (nombre, prioridad y nucleo) que aplica el thread SYSTEM-ALERT al comenzar
+    pub scheduling: SchedulingHints,
+
+    /// Si es Some, archivo CSV en el que se agrega cada muestra periodica del estado del sistema (ver `AlertCsv`)
+    pub csv_path: Option<PathBuf>,
 }
 
 impl PeriodicAlert {
-    /// Crea una instancia de PeriodicAlert
+    /// Crea una instancia de PeriodicAlert, con las sugerencias de planificacion de las variables de entorno (ver `SchedulingHints::from_env`)
+    /// y el archivo CSV de `O_ALERTAS_CSV`.
     /// El handle del thread se inicializa en None debido a que el thread no se crea en el constructor
     pub fn new() -> Self {
-        Self { handle: None }
+        Self {
+            handle: None,
+            scheduling: SchedulingHints::from_env(),
+            csv_path: Consts::o_alertas_csv().map(PathBuf::from),
+        }
+    }
+
+    /// Agrega cada muestra periodica del estado del sistema al final del archivo CSV `path`
+    pub fn with_csv<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.csv_path = Some(path.into());
+        self
+    }
+
+    /// Reemplaza las sugerencias de planificacion del thread SYSTEM-ALERT
+    pub fn with_scheduling(mut self, scheduling: SchedulingHints) -> Self {
+        self.scheduling = scheduling;
+        self
     }
 
     ///  Spawnea y ejecuta el thread SYSTEM-ALERT que se encargara (en un thread aparte) de reportar el estado del sistema cada cierto tiempo,
@@ -41,28 +127,50 @@ impl PeriodicAlert {
     ///   los estados de los contenedores.
     ///   * `total_orders_to_process` - Cantidad total de pedidos que el sistema va a procesar. Esto sirve como
     ///   indicativo para que el sistema de alertas sepa cuando dejar de seguir esperando por pedidos.
+    ///   * `completed_orders` - Si es Some, se envia por el canal una copia de cada pedido finalizado apenas se recibe.
+    ///   * `sink` - Destino de las muestras periodicas del estado del sistema (ver `AlertSink`).
+    ///
+    /// El thread aplica sus sugerencias de planificacion con `Consts::n_dispensers()` como indice, asi le toca el nucleo siguiente
+    /// al del ultimo dispenser.
     pub fn run(
         &mut self,
         pair_vecdeque_system_alert: Arc<(Mutex<VecDeque<Order>>, Condvar)>,
         pair_conteiners_states: Arc<(Mutex<ContainersStates>, Condvar)>,
-        total_orders_to_process: usize,
+        total_orders_to_process: TotalOrders,
+        completed_orders: Option<Sender<Order>>,
+        sink: Box<dyn AlertSink>,
     ) {
+        let scheduling = self.scheduling.clone();
+        let csv_path = self.csv_path.clone();
         let handle: Option<JoinHandle<Result<VecDeque<Order>, ErrorCafeteria>>> = Builder::new()
-            .name("[ SYSTEM ALERT ]".to_string())
+            .name(scheduling.thread_name("[ SYSTEM ALERT ]"))
             .spawn(move || {
+                if let Err(error) = scheduling.apply(Consts::n_dispensers()) {
+                    error!("[ SYSTEM ALERT ]: {}", error.mensaje);
+                }
                 let orders_finished: Arc<Mutex<Option<VecDeque<Order>>>> =
                     Arc::new(Mutex::new(Some(VecDeque::<Order>::new())));
                 let orders_finished_clone = orders_finished.clone();
+                let csv = csv_path.and_then(|path| match AlertCsv::open(&path) {
+                    Ok(csv) => Some(csv),
+                    Err(error) => {
+                        error!("[ SYSTEM ALERT ]: {:?}: {}", path, error.mensaje);
+                        None
+                    }
+                });
                 let spawn_result = run_periodic_alerts(
                     pair_conteiners_states,
                     orders_finished_clone,
-                    total_orders_to_process,
+                    total_orders_to_process.clone(),
+                    csv,
+                    sink,
                 );
 
                 process_finished_orders(
                     pair_vecdeque_system_alert,
                     orders_finished.clone(),
                     total_orders_to_process,
+                    completed_orders,
                 )?;
 
                 if spawn_result.join().is_err() {
@@ -83,48 +191,51 @@ impl PeriodicAlert {
         self.handle = handle;
     }
 
-    /// Función que espera mediante el wait() de la condvar hasta que haya un pedido procesado en la cola de pedidos.
+    /// Función que espera mediante el wait() de la condvar hasta que haya un pedido procesado en la cola de pedidos, o hasta
+    /// que se hayan recibido todos los pedidos que el sistema va a procesar.
     ///
     /// Es decir, siendo consumidor esperara hasta ser despertado por un notify_all() lanzado por algun dispenser productor
-    /// para cuando haya finalizado de procesar un pedido y termine insertandolo el la cola de pedidos.
+    /// para cuando haya finalizado de procesar un pedido y termine insertandolo el la cola de pedidos, o por `TotalOrders::set`.
     ///
     /// # Arguments
     ///   * `pair_vecdeque_system_alert` - Arc<(`Mutex<VecDeque<Order>>`, Condvar)>: Pair de Mutex y Condvar para la cola pedidos finalizados.
+    ///   * `received` - Cantidad de pedidos procesados que ya se recibieron.
+    ///   * `total_orders_to_process` - Cantidad total de pedidos que el sistema va a procesar.
     ///
     /// # Returns
     /// * `Result<Option<Order>, ErrorCafeteria>`:
-    ///   * Si se ha recibido un pedido procesado, se devuelve Ok(Order).
-    ///   * Si es Err, es por que hubo un error en el wait() o al tomar el
-    ///       lock del Mutex o porque se encontro una cola vacia cuando no deberia ser posible debido al wait() (como minimo deberia haber un elemento)
+    ///   * Si se ha recibido un pedido procesado, se devuelve Ok(Some(Order)).
+    ///   * Si ya se recibieron todos los pedidos, se devuelve Ok(None).
+    ///   * Si es Err, es por que hubo un error en el wait() o al tomar el lock del Mutex.
     fn wait_order(
         pair_vecdeque_system_alert: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
-    ) -> Result<Order, ErrorCafeteria> {
+        received: usize,
+        total_orders_to_process: &TotalOrders,
+    ) -> Result<Option<Order>, ErrorCafeteria> {
         let (lock, cvar) = &**(pair_vecdeque_system_alert);
 
         let mut orders_queue = lock
             .lock()
             .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
 
-        while orders_queue.is_empty() {
+        while orders_queue.is_empty() && !total_orders_to_process.reached(received) {
             orders_queue = cvar
                 .wait(orders_queue)
                 .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
         }
 
-        if let Some(item) = orders_queue.pop_back() {
+        let order = orders_queue.pop_back();
+        if order.is_some() {
             cvar.notify_all();
-            Ok(item)
-        } else {
-            Err(ErrorCafeteria::new(
-                "Empty VecDeque when it should have at least one element.",
-            ))
         }
+        Ok(order)
     }
 }
 
 /// Función que se encarga de recibir a los pedidos procesados de la cola de pedidos (se actua como consumidor)
 ///
-/// Cuando recibe un pedido, lo inserta en la cola interna (`orders_finished`) de pedidos finalizados del SYSTEM-ALERT.
+/// Cuando recibe un pedido, lo inserta en la cola interna (`orders_finished`) de pedidos finalizados del SYSTEM-ALERT, y si hay
+/// un canal de pedidos finalizados (`completed_orders`) envia una copia del pedido. Si ya nadie recibe del canal, se sigue sin enviar.
 /// Se deja de esperar nuevos pedidos cuando se recibe la cantidad total de pedidos que el sistema va a procesar.
 ///
 /// # Arguments
@@ -132,34 +243,39 @@ impl PeriodicAlert {
 ///   * `orders_finished` - Arc<Mutex<Option<VecDeque<Order>>>>: Cola interna de pedidos finalizados del SYSTEM-ALERT.
 ///   * `total_orders_to_process` - Cantidad total de pedidos que el sistema va a procesar. Esto sirve como
 ///   indicativo para que el sistema de alertas sepa cuando dejar de seguir esperando por pedidos.
+///   * `completed_orders` - Option<Sender<Order>>: Canal por el que se envian los pedidos finalizados.
 ///
 /// # Returns
 /// * `Result<(), ErrorCafeteria>`:
 ///   * Si es Ok(()), se ha recibido la cantidad total de pedidos que el sistema va a procesar.
-///   * Si es Err, es por que hubo un error en el wait() o al tomar el lock del Mutex o
-///      porque se encontro una cola vacia cuando no deberia ser posible debido al wait() (como minimo deberia haber un elemento)
+///   * Si es Err, es por que hubo un error en el wait() o al tomar el lock del Mutex
 ///      o debido a que se encontro el mutex de la cola de `orders_finished` con None.
 fn process_finished_orders(
     pair_vecdeque_system_alert: Arc<(Mutex<VecDeque<Order>>, Condvar)>,
     orders_finished: Arc<Mutex<Option<VecDeque<Order>>>>,
-    total_orders_to_process: usize,
+    total_orders_to_process: TotalOrders,
+    mut completed_orders: Option<Sender<Order>>,
 ) -> Result<(), ErrorCafeteria> {
-    loop {
-        let order = PeriodicAlert::wait_order(&pair_vecdeque_system_alert)?;
+    let mut received = 0;
+    while let Some(order) = PeriodicAlert::wait_order(
+        &pair_vecdeque_system_alert,
+        received,
+        &total_orders_to_process,
+    )? {
+        received += 1;
         debug!(
                 "[ SYSTEM ALERT ] | [Order#{:?}] NEW ORDER PROCESSED TO REGISTRY.\n                 Requeriments: {:?}",
                 order.id,
                 order.ingredientes
         );
+        if let Some(sender) = &completed_orders {
+            if sender.send(order.clone()).is_err() {
+                completed_orders = None;
+            }
+        }
         match orders_finished.lock() {
             Ok(mut orders_received) => match orders_received.as_mut() {
-                Some(orders) => {
-                    orders.push_front(order);
-
-                    if orders.len().eq(&total_orders_to_process) {
-                        break;
-                    }
-                }
+                Some(orders) => orders.push_front(order),
                 None => {
                     return Err(ErrorCafeteria::new("VecDeque is None."));
                 }
@@ -181,6 +297,11 @@ fn process_finished_orders(
 ///         los estados de los contenedores.
 ///  * `total_orders_to_process` - Cantidad total de pedidos que el sistema va a procesar. Esto sirve como
 ///         indicativo para que el thraed de reporte de estadisticas sepa cuando dejar de seguir loopeando mostrnado estadisticas.
+///  * `csv` - Si es Some, se agrega cada muestra al CSV. Si falla la escritura se loguea el error y se deja de escribir el CSV.
+///  * `sink` - Destino de cada muestra, que la recibe luego de soltar el lock de los pedidos finalizados.
+///
+/// Los estados de los contenedores se leen de la copia que publica `ContainersStates` en cada modificacion (ver
+/// `ContainersStates::snapshots`), asi el reporte no compite con los dispensers por su lock.
 ///
 /// # Returns
 ///  * Retorna un JoinHandle para poder esperar realizar join a este thread.
@@ -192,65 +313,53 @@ fn process_finished_orders(
 fn run_periodic_alerts(
     pair_conteiners_states: Arc<(Mutex<ContainersStates>, Condvar)>,
     orders_finished: Arc<Mutex<Option<VecDeque<Order>>>>,
-    total_orders_to_process: usize,
+    total_orders_to_process: TotalOrders,
+    mut csv: Option<AlertCsv>,
+    mut sink: Box<dyn AlertSink>,
 ) -> JoinHandle<Result<(), ErrorCafeteria>> {
     let spawn_result: JoinHandle<Result<(), ErrorCafeteria>> = thread::spawn(move || {
-        let a_agua_caliente: f32 = Consts::a_agua_caliente();
-        let m_granos_molidos: f32 = Consts::m_granos_molidos();
-        let g_granos: f32 = Consts::g_granos();
-        let e_espuma_leche: f32 = Consts::e_espuma_leche();
-        let l_leche_fria: f32 = Consts::l_leche_fria();
-        let c_cacao: f32 = Consts::c_cacao();
+        // se toma el lock de los estados una unica vez, luego cada muestra lee la ultima copia publicada sin tomarlo
+        let snapshots = pair_conteiners_states
+            .0
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .snapshots();
 
         loop {
             sleep(Duration::from_secs(TIME_PERIODIC_ALERT));
 
-            match pair_conteiners_states.0.lock() {
-                Ok(_guard) => {
-                    print_info_level_conteiners(
-                        _guard,
-                        a_agua_caliente,
-                        m_granos_molidos,
-                        e_espuma_leche,
-                        l_leche_fria,
-                        g_granos,
-                        c_cacao,
-                    );
-
-                    match orders_finished.lock() {
-                        Ok(_guard) => match _guard.as_ref() {
-                            Some(orders) => {
-                                let quantity_total = orders.len();
-                                info!(
-                                    "[ SYSTEM ALERT ]: Cantidad pedidos totales procesados. {:?}",
-                                    quantity_total
-                                );
-
-                                info!(
-                                    "[ SYSTEM ALERT ]: Cantidad pedidos completados. {:?}/{:?}",
-                                    orders
-                                        .iter()
-                                        .filter(|x| x.status == OrderState::Completed)
-                                        .count(),
-                                    quantity_total
-                                );
-
-                                if quantity_total.eq(&total_orders_to_process) {
-                                    break;
-                                }
-                            }
-                            None => {
-                                return Err(ErrorCafeteria::new("VecDeque is None."));
-                            }
-                        },
-                        Err(err) => {
-                            return Err(ErrorCafeteria::new(&err.to_string()));
-                        }
+            let states = snapshots.load();
+            let sample = match orders_finished.lock() {
+                Ok(_guard) => match _guard.as_ref() {
+                    Some(orders) => {
+                        let quantity_total = orders.len();
+                        let quantity_completed = orders
+                            .iter()
+                            .filter(|x| x.status == OrderState::Completed)
+                            .count();
+                        let rejections = RejectionHistogram::from_orders(orders.iter());
+                        AlertSample::new(&states, quantity_total, quantity_completed, rejections)
                     }
-                }
+                    None => {
+                        return Err(ErrorCafeteria::new("VecDeque is None."));
+                    }
+                },
                 Err(err) => {
                     return Err(ErrorCafeteria::new(&err.to_string()));
                 }
+            };
+
+            if let Some(Err(error)) = csv
+                .as_mut()
+                .map(|csv| csv.append(&states, sample.processed, sample.completed))
+            {
+                error!("[ SYSTEM ALERT ]: {}", error.mensaje);
+                csv = None;
+            }
+
+            sink.report(&sample);
+            if total_orders_to_process.reached(sample.processed) {
+                break;
             }
         }
         Ok(())
@@ -258,52 +367,6 @@ fn run_periodic_alerts(
     spawn_result
 }
 
-///  Funcion que se encarga de imprimir el estado de los contenedores
-///
-/// # Arguments
-///   * `_guard` - std::sync::MutexGuard<ContainersStates>: Guard del Mutex de los estados de los contenedores.
-///   * `a_agua_caliente` - f32: Capacidad total de agua caliente.
-///   * `m_granos_molidos` - f32: Capacidad total de granos molidos.
-///   * `e_espuma_leche` - f32: Capacidad total de espuma de leche.
-///   * `l_leche_fria` - f32: Capacidad total de leche fria.
-///   * `g_granos` - f32: Capacidad total de granos.
-///   * `c_cacao` - f32: Capacidad total de cacao.
-fn print_info_level_conteiners(
-    _guard: std::sync::MutexGuard<ContainersStates>,
-    a_agua_caliente: f32,
-    m_granos_molidos: f32,
-    e_espuma_leche: f32,
-    l_leche_fria: f32,
-    g_granos: f32,
-    c_cacao: f32,
-) {
-    info!("[ SYSTEM ALERT ]: Level of conteiners:");
-    _guard.principal_conteiners.iter().for_each(|(key, value)| {
-        let porcentaje = match key {
-            enums::IngredientType::Agua => value.1 / a_agua_caliente * 100.0,
-            enums::IngredientType::CafeMolido => value.1 / m_granos_molidos * 100.0,
-            enums::IngredientType::EspumaLeche => value.1 / e_espuma_leche * 100.0,
-            enums::IngredientType::Cacao => value.1 / c_cacao * 100.0,
-            _ => 0.0,
-        };
-        info!(
-            "                    {:?} -> {:?}% para usar",
-            key, porcentaje
-        );
-    });
-    _guard.quantity_to_recharge.iter().for_each(|(key, value)| {
-        let porcentaje = match key {
-            enums::IngredientType::LecheFria => value / l_leche_fria * 100.0,
-            enums::IngredientType::GranosCafe => value / g_granos * 100.0,
-            _ => 0.0,
-        };
-        info!(
-            "                    {:?} -> {:?}% para recargar",
-            key, porcentaje
-        );
-    });
-}
-
 impl Default for PeriodicAlert {
     fn default() -> Self {
         Self::new()
@@ -321,19 +384,25 @@ impl Default for PeriodicAlert {
 ///   los estados de los contenedores.
 ///   * `total_orders_to_process` - Cantidad total de pedidos que el sistema va a procesar. Esto sirve como
 ///   indicativo para que el sistema de alertas sepa cuando dejar de seguir esperando por pedidos.
+///   * `completed_orders` - Si es Some, se envia por el canal una copia de cada pedido finalizado apenas se recibe.
+///   * `sink` - Destino de las muestras periodicas del estado del sistema. `LogAlertSink` las reporta en el log.
 ///
 /// # Returns
 ///   * `PeriodicAlert`: el sistema de alertas para poder hacer join al hilo.
 pub fn create_and_run_system_alert(
     pair_vecdeque_system_alert: Arc<(Mutex<VecDeque<Order>>, Condvar)>,
     pair_conteiners_states: Arc<(Mutex<ContainersStates>, Condvar)>,
-    total_orders_to_process: usize,
+    total_orders_to_process: impl Into<TotalOrders>,
+    completed_orders: Option<Sender<Order>>,
+    sink: Box<dyn AlertSink>,
 ) -> PeriodicAlert {
     let mut system_alert = PeriodicAlert::new();
     system_alert.run(
         pair_vecdeque_system_alert,
         pair_conteiners_states,
-        total_orders_to_process,
+        total_orders_to_process.into(),
+        completed_orders,
+        sink,
     );
     system_alert
 }
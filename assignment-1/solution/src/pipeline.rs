@@ -0,0 +1,316 @@
+use std::collections::{HashMap, VecDeque};
+
+use log::{debug, info};
+
+use crate::{
+    conteiners::Conteiners,
+    conteiners_states::ContainersStates,
+    dispenser::{send_signal_poweroff_to_dispensers, Dispenser},
+    enums::{IngredientType, OrderState},
+    error_dispenser::ErrorCafeteria,
+    lock_contention::LockedResource,
+    order::{insert_orders, Order},
+    scheduling::SchedulingHints,
+    sync::thread::{Builder, JoinHandle},
+    sync::{Arc, Condvar, Mutex},
+};
+
+/// Orden en que los pedidos pasan por los workers de cada ingrediente
+pub const PIPELINE_STAGES: [IngredientType; 4] = [
+    IngredientType::Agua,
+    IngredientType::CafeMolido,
+    IngredientType::EspumaLeche,
+    IngredientType::Cacao,
+];
+
+/// Cola de pedidos de cada worker, en el orden de `PIPELINE_STAGES`
+type StageQueues = Vec<Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>>;
+
+/// # Ejecucion y Creacion del pipeline
+///
+/// Alternativa a `create_and_run_dispensers` con los mismos parametros: en lugar de que cada dispenser procese un pedido completo,
+/// hay un worker por ingrediente (ver `PIPELINE_STAGES`), cada uno con su propia cola, y un coordinador que toma los pedidos de la
+/// cola de pedidos y los envia al primer worker que necesitan. Cada worker aplica su ingrediente y envia el pedido al siguiente
+/// worker que necesite, o a la cola de pedidos finalizados si ya no necesita ninguno.
+///
+/// El apagado se propaga por el pipeline: cuando el coordinador recibe el None de la cola de pedidos se lo envia al primer worker
+/// (una vez que su cola esta vacia), y cada worker se lo envia al siguiente cuando ya no le quedan pedidos, asi ningun pedido queda
+/// en una cola cerrada.
+///
+/// # Arguments
+/// * `pair_vecdeque_orders` - Arc<(`Mutex<Option<VecDeque<Order>>>`, Condvar)>: Pair de Mutex y Condvar para la cola de pedidos a procesar.
+/// * `pair_vecdeque_system_alert` - Arc<(`Mutex<VecDeque<Order>>`, Condvar)>: Pair de Mutex y Condvar para la cola pedidos finalizados.
+/// * `pair_finished_orders` - Arc<(`Mutex<HashMap<usize, OrderState>>`, Condvar)>: Pair de Mutex y Condvar con el estado final de cada pedido finalizado.
+/// * `pair_conteiners_states` - Arc<(`Mutex<ContainersStates>`, Condvar)>: Pair de Mutex y Condvar para los estados de los contenedores.
+/// * `arc_containers` - `Arc<Conteiners>`: Arc de los contenedores.
+/// # Returns
+/// * `Vec<Dispenser>`: Los workers de cada ingrediente (con el indice de su etapa como id) y el coordinador, para hacerles join.
+pub fn create_and_run_pipeline(
+    pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+    pair_vecdeque_system_alert: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+    pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+    pair_conteiners_states: &Arc<(Mutex<ContainersStates>, Condvar)>,
+    arc_containers: Arc<Conteiners>,
+) -> Vec<Dispenser> {
+    let stage_queues: StageQueues = PIPELINE_STAGES
+        .iter()
+        .map(|_| Arc::new((Mutex::new(Some(VecDeque::new())), Condvar::new())))
+        .collect();
+    let router = Router {
+        stage_queues,
+        pair_vecdeque_system_alert: pair_vecdeque_system_alert.clone(),
+        pair_finished_orders: pair_finished_orders.clone(),
+    };
+
+    let mut workers: Vec<Dispenser> = PIPELINE_STAGES
+        .iter()
+        .enumerate()
+        .map(|(stage, tipo)| Dispenser {
+            id: stage,
+            scheduling: SchedulingHints::default(),
+            handle: run_worker(
+                stage,
+                *tipo,
+                router.clone(),
+                pair_conteiners_states.clone(),
+                arc_containers.clone(),
+            ),
+        })
+        .collect();
+
+    workers.push(Dispenser {
+        id: PIPELINE_STAGES.len(),
+        scheduling: SchedulingHints::default(),
+        handle: run_coordinator(pair_vecdeque_orders.clone(), router),
+    });
+    workers
+}
+
+/// Colas a las que se puede enviar un pedido luego de una etapa del pipeline
+#[derive(Clone)]
+struct Router {
+    /// Cola de cada worker
+    stage_queues: StageQueues,
+
+    /// Cola de pedidos finalizados
+    pair_vecdeque_system_alert: Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+
+    /// Estado final de cada pedido finalizado
+    pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+}
+
+impl Router {
+    /// Envia el pedido al primer worker desde la etapa `from` cuyo ingrediente todavia requiere. Si no requiere ninguno
+    /// (o se cancelo por falta de recursos, o se rechazo al leer el archivo), lo envia a la cola de pedidos finalizados.
+    fn route(&self, mut order: Order, from: usize) -> Result<(), ErrorCafeteria> {
+        // un pedido cancelado en `apply_stage` o rechazado al leer el archivo no pasa por las etapas que le faltan
+        let finished_status = order.status;
+        let cancelled = matches!(
+            finished_status,
+            OrderState::NoEnoughResourceContainer | OrderState::ExceedsCapacity
+        );
+        let next_stage = match order.get_updated_status() {
+            OrderState::InProgress if !cancelled => {
+                (from..PIPELINE_STAGES.len()).find(|stage| order.requiere(&PIPELINE_STAGES[*stage]))
+            }
+            _ => None,
+        };
+        if cancelled {
+            order.status = finished_status;
+        }
+
+        match next_stage {
+            Some(stage) => insert_orders(vec![order], &self.stage_queues[stage]),
+            None => {
+                info!(
+                    "{} | [Order#{:?}]: {:?}",
+                    Dispenser::id_dispenser(),
+                    order.id,
+                    order.status
+                );
+                Dispenser::notify_order_finished(
+                    order,
+                    &self.pair_vecdeque_system_alert,
+                    &self.pair_finished_orders,
+                )
+            }
+        }
+    }
+
+    /// Apaga el worker de la etapa indicada cuando ya no tenga pedidos en su cola. Las etapas fuera del pipeline se ignoran.
+    fn power_off(&self, stage: usize) -> Result<(), ErrorCafeteria> {
+        match self.stage_queues.get(stage) {
+            Some(queue) => send_signal_poweroff_to_dispensers(queue.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Spawnea el coordinador, que toma los pedidos de la cola de pedidos y los envia al primer worker que necesitan
+fn run_coordinator(
+    pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+    router: Router,
+) -> Option<JoinHandle<Result<(), ErrorCafeteria>>> {
+    Builder::new()
+        .name("[ COORDINATOR ]".to_string())
+        .spawn(move || {
+            while let Some(order) = Dispenser::wait_pedido(&pair_vecdeque_orders)? {
+                info!(
+                    "{} | [Order#{:?}] NEW ORDER RECEIVED.\n                 Requeriments: {:?}",
+                    Dispenser::id_dispenser(),
+                    order.id,
+                    order.ingredientes
+                );
+                router.route(order, 0)?;
+            }
+            debug!(
+                "{}: None received. Powering off the pipeline.",
+                Dispenser::id_dispenser()
+            );
+            router.power_off(0)
+        })
+        .ok() // Failed to spawn thread, None indicates this thread is not running.
+}
+
+/// Spawnea el worker de la etapa `stage`, que aplica el ingrediente `tipo` a cada pedido de su cola y lo envia a la siguiente etapa
+fn run_worker(
+    stage: usize,
+    tipo: IngredientType,
+    router: Router,
+    pair_conteiners_states: Arc<(Mutex<ContainersStates>, Condvar)>,
+    containers: Arc<Conteiners>,
+) -> Option<JoinHandle<Result<(), ErrorCafeteria>>> {
+    Builder::new()
+        .name(format!("[ WORKER {:?} ]", tipo))
+        .spawn(move || {
+            while let Some(mut order) = Dispenser::wait_pedido(&router.stage_queues[stage])? {
+                apply_stage(tipo, &mut order, &pair_conteiners_states, &containers)?;
+                router.route(order, stage + 1)?;
+            }
+            debug!(
+                "{}: None received. Closing worker.",
+                Dispenser::id_dispenser()
+            );
+            router.power_off(stage + 1)
+        })
+        .ok() // Failed to spawn thread, None indicates this thread is not running.
+}
+
+/// Aplica el ingrediente `tipo` al pedido, actualizando el estado del contenedor en ContainersStates igual que `Dispenser::process_order`.
+///
+/// Como cada contenedor tiene un unico worker no hace falta esperar a que se libere: si algun contenedor que requiere el pedido ya
+/// no tiene recursos, el pedido se cancela sin aplicar el ingrediente.
+///
+/// # Returns
+/// * `Result<(), ErrorCafeteria>`:
+///    * Si es Ok, se aplico el ingrediente o se cancelo el pedido (con `order.status` en `NoEnoughResourceContainer`).
+///    * Si es Err, hubo un error al tomar el mutex del ContainersStates o del contenedor.
+fn apply_stage(
+    tipo: IngredientType,
+    order: &mut Order,
+    pair_conteiners_states: &Arc<(Mutex<ContainersStates>, Condvar)>,
+    containers: &Arc<Conteiners>,
+) -> Result<(), ErrorCafeteria> {
+    let (lock_states, _) = &**pair_conteiners_states;
+
+    let conteiners_states = containers
+        .contention
+        .lock(LockedResource::ContainersStates, lock_states)?;
+    if conteiners_states.container_without_resource_for(order) {
+        conteiners_states.mark_missing_ingredients_of(order);
+        order.status = OrderState::NoEnoughResourceContainer;
+        info!(
+            "{} | [Order#{:?}]: CANCELLED ORDER. There are no containers with the necessary resources to process the order.",
+            Dispenser::id_dispenser(),
+            order.id
+        );
+        return Ok(());
+    }
+
+    let mut container = containers.lock_for(tipo)?;
+    container.set_taken_state(conteiners_states);
+    container.apply_ingredient(order);
+
+    let conteiners_states = containers
+        .contention
+        .lock(LockedResource::ContainersStates, lock_states)?;
+    container.update_and_notify_state(conteiners_states);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cafeteria::{init_elements_with_orders, join_dispensers};
+
+    fn run_pipeline(orders: Vec<Order>) -> HashMap<usize, OrderState> {
+        let (
+            orders,
+            pair_vecdeque_orders,
+            pair_vecdeque_system_alert,
+            pair_conteiners_states,
+            arc_containers,
+            pair_finished_orders,
+        ) = init_elements_with_orders(orders);
+        let total_orders = orders.len();
+
+        let workers = create_and_run_pipeline(
+            &pair_vecdeque_orders,
+            &pair_vecdeque_system_alert,
+            &pair_finished_orders,
+            &pair_conteiners_states,
+            arc_containers,
+        );
+        assert_eq!(workers.len(), PIPELINE_STAGES.len() + 1);
+
+        insert_orders(orders, &pair_vecdeque_orders).unwrap();
+        send_signal_poweroff_to_dispensers(pair_vecdeque_orders).unwrap();
+        join_dispensers(workers);
+
+        assert_eq!(
+            pair_vecdeque_system_alert.0.lock().unwrap().len(),
+            total_orders
+        );
+        let finished = pair_finished_orders.0.lock().unwrap().clone();
+        finished
+    }
+
+    #[test]
+    fn test1_every_order_goes_through_the_workers_it_needs() {
+        let finished = run_pipeline(vec![
+            Order::new_with_id(0, 1.0, 1.0, 1.0, 1.0),
+            Order::new_with_id(1, 0.0, 0.0, 1.0, 0.0),
+            Order::new_with_id(2, 1.0, 0.0, 0.0, 1.0),
+            Order::new_with_id(3, 0.0, 1.0, 0.0, 0.0),
+        ]);
+
+        assert_eq!(
+            finished,
+            (0..4).map(|id| (id, OrderState::Completed)).collect()
+        );
+    }
+
+    #[test]
+    fn test2_order_exceeding_the_capacity_of_a_container_is_cancelled() {
+        let finished = run_pipeline(vec![
+            Order::new_with_id(0, 1.0, 0.0, 1_000_000.0, 1.0),
+            Order::new_with_id(1, 1.0, 0.0, 0.0, 1.0),
+        ]);
+
+        assert_eq!(
+            finished.get(&0),
+            Some(&OrderState::NoEnoughResourceContainer)
+        );
+        assert_eq!(finished.get(&1), Some(&OrderState::Completed));
+    }
+
+    #[test]
+    fn test3_order_rejected_when_reading_the_file_skips_every_worker() {
+        let mut rejected = Order::new_with_id(0, 1.0, 0.0, 0.0, 1.0);
+        rejected.status = OrderState::ExceedsCapacity;
+        let finished = run_pipeline(vec![rejected, Order::new_with_id(1, 1.0, 0.0, 0.0, 1.0)]);
+
+        assert_eq!(finished.get(&0), Some(&OrderState::ExceedsCapacity));
+        assert_eq!(finished.get(&1), Some(&OrderState::Completed));
+    }
+}
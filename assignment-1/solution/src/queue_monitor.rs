@@ -0,0 +1,289 @@
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+use crate::{
+    conteiners_states::ContainersStates,
+    error_dispenser::ErrorCafeteria,
+    order::Order,
+    sync::thread::{Builder, JoinHandle},
+    sync::{reloj, sleep, Arc, Condvar, Mutex},
+    utils::Consts,
+};
+
+/// Cada cuanto se mide la cantidad de pedidos de las colas
+const QUEUE_SAMPLE_PERIOD: Duration = Duration::from_millis(100);
+
+/// Cantidad de pedidos de una cola, medida periodicamente
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QueueDepth {
+    /// Cantidad de mediciones
+    pub samples: u64,
+
+    /// Suma de los pedidos de todas las mediciones
+    pub total: u64,
+
+    /// Mayor cantidad de pedidos medida
+    pub max: usize,
+}
+
+impl QueueDepth {
+    /// Registra una medicion de `depth` pedidos en la cola
+    fn record(&mut self, depth: usize) {
+        self.samples += 1;
+        self.total += depth as u64;
+        self.max = self.max.max(depth);
+    }
+
+    /// Cantidad promedio de pedidos en la cola. Como las mediciones son periodicas, es el promedio en el tiempo
+    pub fn mean(&self) -> f32 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.total as f32 / self.samples as f32
+    }
+}
+
+/// Cantidad de pedidos de la cola de pedidos a procesar y de la cola de pedidos finalizados, y las veces que la cola de pedidos
+/// estuvo llena mas tiempo que el umbral de saturacion.
+///
+/// La cola de pedidos esta llena cuando tiene un pedido por dispenser (ver `insert_orders`): si sigue asi mas tiempo que el
+/// umbral, los dispensers no dan abasto y el productor esta bloqueado esperando lugar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueStats {
+    /// Cola de pedidos a procesar
+    pub orders: QueueDepth,
+
+    /// Cola de pedidos finalizados que consume el SYSTEM-ALERT
+    pub finished: QueueDepth,
+
+    /// Cantidad de veces que la cola de pedidos estuvo llena mas tiempo que el umbral
+    pub saturations: u32,
+
+    /// Cantidad maxima de pedidos de la cola de pedidos
+    bound: usize,
+
+    /// Tiempo que la cola de pedidos puede estar llena sin alertar. Si es None no se alerta
+    threshold: Option<Duration>,
+
+    /// Instante desde el que la cola de pedidos esta llena, si lo esta
+    full_since: Option<Instant>,
+
+    /// Es true si ya se alerto la saturacion desde que se lleno la cola
+    alerted: bool,
+}
+
+impl QueueStats {
+    /// Crea las estadisticas para una cola de pedidos de a lo sumo `bound` pedidos
+    pub fn new(bound: usize, threshold: Option<Duration>) -> Self {
+        QueueStats {
+            orders: QueueDepth::default(),
+            finished: QueueDepth::default(),
+            saturations: 0,
+            bound,
+            threshold,
+            full_since: None,
+            alerted: false,
+        }
+    }
+
+    /// Registra una medicion de las dos colas tomada en el instante `now`.
+    ///
+    /// # Returns
+    /// * `Option<Duration>` - Si es Some, la cola de pedidos acaba de superar el umbral de saturacion y contiene el tiempo que
+    ///   lleva llena. Se retorna una sola vez hasta que la cola deje de estar llena.
+    pub fn sample(
+        &mut self,
+        orders_depth: usize,
+        finished_depth: usize,
+        now: Instant,
+    ) -> Option<Duration> {
+        self.orders.record(orders_depth);
+        self.finished.record(finished_depth);
+
+        if orders_depth < self.bound {
+            self.full_since = None;
+            self.alerted = false;
+            return None;
+        }
+        let full_for = now.saturating_duration_since(*self.full_since.get_or_insert(now));
+        match self.threshold {
+            Some(threshold) if !self.alerted && full_for > threshold => {
+                self.alerted = true;
+                self.saturations += 1;
+                Some(full_for)
+            }
+            _ => None,
+        }
+    }
+
+    /// Reporta en el log la cantidad maxima y promedio de pedidos de las colas, y las saturaciones de la cola de pedidos
+    pub fn report(&self) {
+        info!(
+            "[ SYSTEM-ALERT ] Orders queue depth: max {}/{}, mean {:.2}",
+            self.orders.max,
+            self.bound,
+            self.orders.mean()
+        );
+        info!(
+            "[ SYSTEM-ALERT ] Finished orders queue depth: max {}, mean {:.2}",
+            self.finished.max,
+            self.finished.mean()
+        );
+        if let Some(threshold) = self.threshold {
+            info!(
+                "[ SYSTEM-ALERT ] Orders queue full for more than {:?}: {} times",
+                threshold, self.saturations
+            );
+        }
+    }
+}
+
+/// Mide periodicamente la cantidad de pedidos de la cola de pedidos y de la cola de pedidos finalizados mientras la cafeteria
+/// procesa pedidos (ver `QueueStats`).
+///
+/// Cuando la cola de pedidos supera el umbral de saturacion se alerta junto con los contenedores por los que esperan los
+/// dispensers (`ContainersStates::waiting_by_ingredient`), que son el cuello de botella.
+#[derive(Debug)]
+pub struct QueueMonitor {
+    /// Es true cuando la cafeteria termino de procesar los pedidos
+    done: Arc<AtomicBool>,
+
+    /// Handle del thread que mide las colas. Contiene las estadisticas medidas.
+    handle: JoinHandle<Result<QueueStats, ErrorCafeteria>>,
+}
+
+impl QueueMonitor {
+    /// Spawnea el thread que mide las colas. La cola de pedidos esta llena con `Consts::n_dispensers()` pedidos.
+    ///
+    /// # Arguments
+    ///  * `threshold` - Tiempo que la cola de pedidos puede estar llena sin alertar. Si es None no se alerta.
+    ///  * `pair_vecdeque_orders` - Par de VecDeque y Condvar que representa la cola de pedidos.
+    ///  * `pair_vecdeque_system_alert` - Pair de Mutex y Condvar para la cola de pedidos finalizados.
+    ///  * `pair_conteiners_states` - Pair de Mutex y Condvar para el estado de los contenedores.
+    /// # Returns
+    /// * `Result<QueueMonitor, ErrorCafeteria>`:
+    ///    * Si es Ok, el monitor que se debe finalizar con `finish` cuando los dispensers terminen.
+    ///    * Si es Err, no se pudo crear el thread.
+    pub fn start(
+        threshold: Option<Duration>,
+        pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+        pair_vecdeque_system_alert: Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+        pair_conteiners_states: Arc<(Mutex<ContainersStates>, Condvar)>,
+    ) -> Result<Self, ErrorCafeteria> {
+        let done = Arc::new(AtomicBool::new(false));
+        let watching = done.clone();
+
+        let handle = Builder::new()
+            .name("[ QUEUE MONITOR ]".to_string())
+            .spawn(move || {
+                let mut stats = QueueStats::new(Consts::n_dispensers(), threshold);
+                while !watching.load(Ordering::SeqCst) {
+                    sample_queues(
+                        &mut stats,
+                        &pair_vecdeque_orders,
+                        &pair_vecdeque_system_alert,
+                        &pair_conteiners_states,
+                    )?;
+                    sleep(QUEUE_SAMPLE_PERIOD);
+                }
+                Ok(stats)
+            })
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+
+        Ok(QueueMonitor { done, handle })
+    }
+
+    /// Avisa al monitor que la cafeteria termino de procesar los pedidos y espera a que termine.
+    ///
+    /// # Returns
+    /// * `Result<QueueStats, ErrorCafeteria>`:
+    ///    * Si es Ok, las estadisticas medidas de las colas.
+    ///    * Si es Err, hubo un error al tomar el lock de alguna cola o en el join del thread.
+    pub fn finish(self) -> Result<QueueStats, ErrorCafeteria> {
+        self.done.store(true, Ordering::SeqCst);
+        self.handle
+            .join()
+            .map_err(|_| ErrorCafeteria::new("[ QUEUE MONITOR ]: FAILED TO JOIN THREAD"))?
+    }
+}
+
+/// Mide la cantidad de pedidos de las colas, y si la cola de pedidos acaba de superar el umbral de saturacion alerta por cuales
+/// contenedores esperan los dispensers.
+fn sample_queues(
+    stats: &mut QueueStats,
+    pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+    pair_vecdeque_system_alert: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+    pair_conteiners_states: &Arc<(Mutex<ContainersStates>, Condvar)>,
+) -> Result<(), ErrorCafeteria> {
+    let orders_depth = pair_vecdeque_orders
+        .0
+        .lock()
+        .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+        .as_ref()
+        .map(VecDeque::len)
+        .unwrap_or(0);
+    let finished_depth = pair_vecdeque_system_alert
+        .0
+        .lock()
+        .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+        .len();
+
+    if let Some(full_for) = stats.sample(orders_depth, finished_depth, reloj().ahora()) {
+        let waiting = pair_conteiners_states
+            .0
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .waiting_by_ingredient();
+        info!(
+            "[ SYSTEM-ALERT ] Dispensers saturated: orders queue full ({}/{}) for {:?}. Dispensers waiting for: {:?}",
+            orders_depth, stats.bound, full_for, waiting
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_depths_are_tracked_with_their_max_and_mean() {
+        let start = Instant::now();
+        let mut stats = QueueStats::new(4, None);
+
+        for (orders_depth, finished_depth) in [(0, 1), (2, 0), (4, 3), (2, 0)] {
+            assert_eq!(stats.sample(orders_depth, finished_depth, start), None);
+        }
+
+        assert_eq!(stats.orders.max, 4);
+        assert_eq!(stats.orders.mean(), 2.0);
+        assert_eq!(stats.finished.max, 3);
+        assert_eq!(stats.finished.mean(), 1.0);
+        assert_eq!(stats.saturations, 0);
+    }
+
+    #[test]
+    fn test2_saturation_is_alerted_once_each_time_the_queue_stays_full_longer_than_the_threshold() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut stats = QueueStats::new(2, Some(Duration::from_secs(5)));
+
+        assert_eq!(stats.sample(2, 0, at(0)), None);
+        assert_eq!(stats.sample(2, 0, at(5)), None);
+        assert_eq!(stats.sample(2, 0, at(6)), Some(Duration::from_secs(6)));
+        assert_eq!(stats.sample(2, 0, at(20)), None);
+
+        // al liberarse un lugar la cola deja de estar llena, y se vuelve a medir desde que se llena otra vez
+        assert_eq!(stats.sample(1, 0, at(21)), None);
+        assert_eq!(stats.sample(2, 0, at(22)), None);
+        assert_eq!(stats.sample(2, 0, at(26)), None);
+        assert_eq!(stats.sample(2, 0, at(28)), Some(Duration::from_secs(6)));
+
+        assert_eq!(stats.saturations, 2);
+    }
+}
@@ -0,0 +1,344 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::ErrorKind,
+    path::Path,
+    sync::Arc,
+};
+
+use log::info;
+
+use crate::{
+    enums::{ErrorType, IngredientStateOfOrder, IngredientType, OrderState},
+    error_dispenser::ErrorCafeteria,
+    order::Order,
+    utils::Consts,
+};
+
+/// Bebida con nombre definida en el archivo de recetas: los gramos de cada ingrediente y, opcionalmente, los pasos para
+/// prepararla.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe {
+    /// Nombre con el que se pide la bebida en el archivo de ordenes
+    pub name: String,
+
+    /// Gramos de cada ingrediente de la bebida
+    pub ingredients: HashMap<IngredientType, f32>,
+
+    /// Pasos de preparacion, que el dispenser informa al recibir el pedido
+    pub steps: Vec<String>,
+}
+
+impl Recipe {
+    /// Retorna los gramos del ingrediente en la receta, o None si la receta no lo usa
+    pub fn get(&self, tipo: &IngredientType) -> Option<f32> {
+        self.ingredients.get(tipo).copied()
+    }
+}
+
+/// Recetas disponibles para los pedidos, segun su nombre.
+///
+/// Se cargan de un archivo `recetas.toml` con una seccion por bebida, donde cada ingrediente se indica en gramos y los
+/// pasos son opcionales:
+/// ```toml
+/// # comentario
+/// [latte]
+/// agua = 100
+/// cafe_molido = 20
+/// espuma_leche = 50
+/// pasos = ["moler el cafe", "espumar la leche"]
+/// ```
+/// Los ingredientes posibles son `agua`, `cafe_molido`, `cacao` y `espuma_leche`.
+#[derive(Debug, Default, Clone)]
+pub struct RecipeBook {
+    recipes: HashMap<String, Arc<Recipe>>,
+}
+
+impl RecipeBook {
+    /// Carga las recetas del archivo de la variable de entorno B_RECETAS (ver `Consts::b_recetas`). Si el archivo no
+    /// existe no hay recetas, y los pedidos solo pueden indicar gramos.
+    pub fn from_env() -> Result<Self, ErrorCafeteria> {
+        Self::from_file(Consts::b_recetas())
+    }
+
+    /// Carga las recetas del archivo recibido. Si el archivo no existe no hay recetas.
+    ///
+    /// # Returns
+    ///  * Si es Ok, `RecipeBook` - Las recetas del archivo.
+    ///  * Si es Err, `ErrorCafeteria` debido a que no se pudo leer el archivo o tiene un formato incorrecto.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ErrorCafeteria> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(RecipeBook::default()),
+            Err(_) => Err(ErrorCafeteria::new_of_type(
+                "Error reading recipes file",
+                ErrorType::IncorrectRecipeFile,
+            )),
+        }
+    }
+
+    /// Parsea el contenido de un archivo de recetas (ver `RecipeBook`).
+    pub fn parse(contents: &str) -> Result<Self, ErrorCafeteria> {
+        let mut recipes: Vec<Recipe> = Vec::new();
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let incorrect = |detail: &str| {
+                ErrorCafeteria::new_of_type(
+                    &format!("Error parsing recipes file [Line#{}]: {}", number, detail),
+                    ErrorType::IncorrectRecipeFile,
+                )
+            };
+
+            if let Some(name) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+                let name = name.trim();
+                if name.is_empty() || name.contains(char::is_whitespace) {
+                    return Err(incorrect("a recipe name can not be empty or have spaces"));
+                }
+                if recipes.iter().any(|recipe| recipe.name == name) {
+                    return Err(incorrect("duplicated recipe"));
+                }
+                recipes.push(Recipe {
+                    name: name.to_string(),
+                    ingredients: HashMap::new(),
+                    steps: Vec::new(),
+                });
+                continue;
+            }
+
+            let recipe = recipes
+                .last_mut()
+                .ok_or_else(|| incorrect("a key must be inside a recipe section"))?;
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| incorrect("expected `key = value`"))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            if key == "pasos" {
+                recipe.steps = parse_steps(value).ok_or_else(|| incorrect("incorrect steps"))?;
+                continue;
+            }
+            let tipo = match key {
+                "agua" => IngredientType::Agua,
+                "cafe_molido" => IngredientType::CafeMolido,
+                "cacao" => IngredientType::Cacao,
+                "espuma_leche" => IngredientType::EspumaLeche,
+                _ => return Err(incorrect("unknown ingredient")),
+            };
+            let grams = value
+                .parse::<f32>()
+                .ok()
+                .filter(|grams| *grams >= 0.0)
+                .ok_or_else(|| incorrect("incorrect grams"))?;
+            recipe.ingredients.insert(tipo, grams);
+        }
+
+        if let Some(recipe) = recipes
+            .iter()
+            .find(|recipe| recipe.ingredients.values().all(|grams| *grams <= 0.0))
+        {
+            return Err(ErrorCafeteria::new_of_type(
+                &format!(
+                    "Error parsing recipes file: recipe {} has no ingredients",
+                    recipe.name
+                ),
+                ErrorType::IncorrectRecipeFile,
+            ));
+        }
+
+        Ok(RecipeBook {
+            recipes: recipes
+                .into_iter()
+                .map(|recipe| (recipe.name.clone(), Arc::new(recipe)))
+                .collect(),
+        })
+    }
+
+    /// Retorna la receta con el nombre recibido, o None si no existe
+    pub fn get(&self, name: &str) -> Option<Arc<Recipe>> {
+        self.recipes.get(name).cloned()
+    }
+
+    /// Cantidad de recetas cargadas
+    pub fn len(&self) -> usize {
+        self.recipes.len()
+    }
+
+    /// Retorna true si no hay recetas cargadas
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+}
+
+/// Parsea la lista de pasos de una receta, por ejemplo `["moler el cafe", "servir"]`. Retorna None si no es una lista de
+/// textos entre comillas.
+fn parse_steps(value: &str) -> Option<Vec<String>> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let mut steps = Vec::new();
+    let mut rest = inner;
+    while !rest.is_empty() {
+        let (step, after) = rest.strip_prefix('"')?.split_once('"')?;
+        steps.push(step.to_string());
+        rest = after.trim_start();
+        if let Some(after_comma) = rest.strip_prefix(',') {
+            rest = after_comma.trim_start();
+        } else if !rest.is_empty() {
+            return None;
+        }
+    }
+    Some(steps)
+}
+
+/// Estadisticas de los pedidos de una receta
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RecipeCount {
+    /// Cantidad de pedidos de la receta
+    pub orders: usize,
+
+    /// Cantidad de pedidos de la receta completados
+    pub completed: usize,
+
+    /// Gramos aplicados en los pedidos de la receta, incluyendo los de los pedidos que luego se cancelaron
+    pub grams: f32,
+}
+
+/// Estadisticas de los pedidos finalizados segun la receta con la que se pidieron. Los pedidos sin receta no se cuentan.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RecipeStats {
+    /// Como key el nombre de la receta, y como value sus estadisticas
+    pub by_recipe: BTreeMap<String, RecipeCount>,
+}
+
+impl RecipeStats {
+    /// Arma las estadisticas con los pedidos finalizados recibidos
+    pub fn from_orders<'a, I>(orders: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Order>,
+    {
+        let mut stats = RecipeStats::default();
+        orders.into_iter().for_each(|order| stats.record(order));
+        stats
+    }
+
+    /// Cuenta el pedido en su receta, si tiene una
+    pub fn record(&mut self, order: &Order) {
+        let recipe = match &order.recipe {
+            Some(recipe) => recipe,
+            None => return,
+        };
+        let count = self.by_recipe.entry(recipe.name.clone()).or_default();
+        count.orders += 1;
+        if order.status == OrderState::Completed {
+            count.completed += 1;
+        }
+        count.grams += order
+            .ingredientes
+            .values()
+            .map(|state| match state {
+                IngredientStateOfOrder::Applied(grams) => *grams,
+                _ => 0.0,
+            })
+            .sum::<f32>();
+    }
+
+    /// Reporta en el log los pedidos, completados y gramos aplicados de cada receta. Si no hubo pedidos con receta no
+    /// reporta nada
+    pub fn report(&self) {
+        self.by_recipe.iter().for_each(|(name, count)| {
+            info!(
+                "[ SYSTEM-ALERT ] Recipe {}: {} orders, {} completed, {} grams applied",
+                name, count.orders, count.completed, count.grams
+            );
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECETAS: &str = "
+# bebidas de la casa
+[latte]
+agua = 100
+cafe_molido = 20
+espuma_leche = 50.5
+pasos = [\"moler el cafe\", \"espumar la leche\"]
+
+[chocolatada]
+cacao = 30
+";
+
+    #[test]
+    fn test1_recipes_are_parsed_with_their_ingredients_and_optional_steps() {
+        let book = RecipeBook::parse(RECETAS).unwrap();
+
+        assert_eq!(book.len(), 2);
+        let latte = book.get("latte").unwrap();
+        assert_eq!(latte.get(&IngredientType::Agua), Some(100.0));
+        assert_eq!(latte.get(&IngredientType::CafeMolido), Some(20.0));
+        assert_eq!(latte.get(&IngredientType::EspumaLeche), Some(50.5));
+        assert_eq!(latte.get(&IngredientType::Cacao), None);
+        assert_eq!(latte.steps, vec!["moler el cafe", "espumar la leche"]);
+        assert!(book.get("chocolatada").unwrap().steps.is_empty());
+        assert!(book.get("cortado").is_none());
+    }
+
+    #[test]
+    fn test2_incorrect_recipes_files_are_rejected() {
+        for contents in [
+            "agua = 10",
+            "[latte]\nleche = 10",
+            "[latte]\nagua = -1",
+            "[latte]\nagua 10",
+            "[latte]\nagua = 10\n[latte]\nagua = 5",
+            "[latte]\nagua = 10\npasos = [\"moler\" \"servir\"]",
+            "[vacia]\n[latte]\nagua = 10",
+        ] {
+            let error = RecipeBook::parse(contents).unwrap_err();
+            assert_eq!(
+                error.type_error,
+                ErrorType::IncorrectRecipeFile,
+                "{}",
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn test3_a_missing_recipes_file_means_no_recipes() {
+        let book = RecipeBook::from_file("recetas_test_inexistente.toml").unwrap();
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn test4_stats_aggregate_orders_by_recipe() {
+        let book = RecipeBook::parse(RECETAS).unwrap();
+        let mut completed =
+            Order::new_with_id(0, 20.0, 0.0, 0.0, 100.0).with_recipe(book.get("latte"));
+        completed.apply(IngredientType::CafeMolido);
+        completed.apply(IngredientType::Agua);
+        completed.get_updated_status();
+        let mut cancelled =
+            Order::new_with_id(1, 20.0, 0.0, 0.0, 100.0).with_recipe(book.get("latte"));
+        cancelled.apply(IngredientType::Agua);
+        cancelled.set_no_enough_resource_container(IngredientType::CafeMolido);
+        cancelled.get_updated_status();
+        let without_recipe = Order::new_with_id(2, 0.0, 0.0, 5.0, 0.0);
+
+        let stats = RecipeStats::from_orders(&[completed, cancelled, without_recipe]);
+
+        assert_eq!(stats.by_recipe.len(), 1);
+        assert_eq!(
+            stats.by_recipe["latte"],
+            RecipeCount {
+                orders: 2,
+                completed: 1,
+                grams: 220.0
+            }
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use log::info;
+
+use crate::{
+    enums::{IngredientType, OrderState},
+    order::Order,
+};
+
+/// Cantidad de pedidos cancelados por falta de recursos (`OrderState::NoEnoughResourceContainer`) segun el ingrediente que
+/// los cancelo, para saber que contenedor conviene agrandar.
+///
+/// Un pedido cancelado por mas de un ingrediente se cuenta en cada uno de ellos.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RejectionHistogram {
+    /// Cantidad de pedidos cancelados por falta de recursos
+    pub rejected: usize,
+
+    /// Como key el ingrediente sin recursos, y como value la cantidad de pedidos que cancelo
+    pub by_ingredient: HashMap<IngredientType, usize>,
+}
+
+impl RejectionHistogram {
+    /// Arma el histograma con los pedidos finalizados recibidos. Solo se cuentan los cancelados por falta de recursos
+    pub fn from_orders<'a, I>(orders: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Order>,
+    {
+        let mut histogram = RejectionHistogram::default();
+        orders.into_iter().for_each(|order| histogram.record(order));
+        histogram
+    }
+
+    /// Cuenta el pedido si fue cancelado por falta de recursos, en cada ingrediente que lo cancelo (ver `Order::missing_ingredients`)
+    pub fn record(&mut self, order: &Order) {
+        if order.status != OrderState::NoEnoughResourceContainer {
+            return;
+        }
+        self.rejected += 1;
+        order.missing_ingredients().into_iter().for_each(|tipo| {
+            *self.by_ingredient.entry(tipo).or_default() += 1;
+        });
+    }
+
+    /// Ingredientes que cancelaron algun pedido junto a la cantidad de pedidos que cancelaron, de mayor a menor
+    pub fn sorted(&self) -> Vec<(IngredientType, usize)> {
+        let mut sorted: Vec<(IngredientType, usize)> =
+            self.by_ingredient.iter().map(|(k, v)| (*k, *v)).collect();
+        sorted.sort_by_key(|(tipo, count)| (Reverse(*count), format!("{:?}", tipo)));
+        sorted
+    }
+
+    /// Reporta en el log los pedidos cancelados por falta de recursos segun el ingrediente que los cancelo
+    pub fn report(&self) {
+        info!(
+            "[ SYSTEM-ALERT ] Orders cancelled for lack of resources: {} by ingredient {:?}",
+            self.rejected,
+            self.sorted()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{conteiners_states::ContainersStates, enums::StateOfConteiner};
+
+    #[test]
+    fn test1_cancelled_orders_are_counted_in_each_missing_ingredient() {
+        let mut without_cacao = Order::new_with_id(0, 0.0, 0.0, 5.0, 10.0);
+        without_cacao.set_no_enough_resource_container(IngredientType::Cacao);
+        without_cacao.get_updated_status();
+        let mut without_cacao_and_agua = Order::new_with_id(1, 0.0, 0.0, 5.0, 10.0);
+        without_cacao_and_agua.set_no_enough_resource_container(IngredientType::Cacao);
+        without_cacao_and_agua.set_no_enough_resource_container(IngredientType::Agua);
+        without_cacao_and_agua.get_updated_status();
+        let mut completed = Order::new_with_id(2, 0.0, 0.0, 5.0, 0.0);
+        completed.apply(IngredientType::Cacao);
+        completed.get_updated_status();
+
+        let histogram =
+            RejectionHistogram::from_orders(&[without_cacao, without_cacao_and_agua, completed]);
+
+        assert_eq!(histogram.rejected, 2);
+        assert_eq!(
+            histogram.sorted(),
+            vec![(IngredientType::Cacao, 2), (IngredientType::Agua, 1)]
+        );
+    }
+
+    #[test]
+    fn test2_order_cancelled_waiting_for_a_container_without_resources_records_that_ingredient() {
+        let mut states = ContainersStates::default();
+        states.set_state(
+            0.0,
+            StateOfConteiner::NoEnoughResource,
+            &IngredientType::Cacao,
+        );
+        states.set_state(
+            0.0,
+            StateOfConteiner::NoEnoughResource,
+            &IngredientType::EspumaLeche,
+        );
+        let mut order = Order::new_with_id(0, 1.0, 0.0, 5.0, 10.0);
+
+        assert!(states.container_without_resource_for(&order));
+        states.mark_missing_ingredients_of(&mut order);
+        order.status = OrderState::NoEnoughResourceContainer;
+
+        assert_eq!(order.missing_ingredients(), vec![IngredientType::Cacao]);
+        assert_eq!(
+            RejectionHistogram::from_orders([&order]).sorted(),
+            vec![(IngredientType::Cacao, 1)]
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, fs,
+    path::Path,
+    str::FromStr,
+};
+
+use log::info;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    conteiners::Conteiners,
+    conteiners_states::ContainersStates,
+    enums::{IngredientType, OrderState},
+    error_dispenser::ErrorCafeteria,
+    order::Order,
+    sync::{Arc, Mutex},
+    utils::Consts,
+};
+
+/// Ingredientes de los contenedores principales, en el orden en que se ofrecen a las decisiones sorteadas
+const PRINCIPAL_INGREDIENTS: [IngredientType; 4] = [
+    IngredientType::Agua,
+    IngredientType::CafeMolido,
+    IngredientType::EspumaLeche,
+    IngredientType::Cacao,
+];
+
+/// Decision de planificacion de un paso de la ejecucion: que pedido avanza y con que contenedor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    /// Id del pedido que avanza
+    pub order: usize,
+
+    /// Ingrediente que se aplica al pedido. Si es None, el pedido se cancela porque algun contenedor que requiere se quedo
+    /// sin recursos
+    pub ingredient: Option<IngredientType>,
+}
+
+impl fmt::Display for Decision {
+    /// Una decision por linea: el id del pedido y el ingrediente (o `-` si se cancela), por ejemplo `3 Cacao`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ingredient {
+            Some(ingredient) => write!(f, "{} {:?}", self.order, ingredient),
+            None => write!(f, "{} -", self.order),
+        }
+    }
+}
+
+impl FromStr for Decision {
+    type Err = ErrorCafeteria;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let invalid = || ErrorCafeteria::new(&format!("Invalid replay decision: {:?}", line));
+        let mut words = line.split_whitespace();
+        let order = words
+            .next()
+            .and_then(|id| id.parse::<usize>().ok())
+            .ok_or_else(invalid)?;
+        let ingredient = match words.next().ok_or_else(invalid)? {
+            "-" => None,
+            name => Some(
+                PRINCIPAL_INGREDIENTS
+                    .into_iter()
+                    .find(|tipo| format!("{:?}", tipo) == name)
+                    .ok_or_else(invalid)?,
+            ),
+        };
+        Ok(Decision { order, ingredient })
+    }
+}
+
+/// Lee las decisiones grabadas en un archivo, una por linea (ver `Decision`). Las lineas vacias se ignoran.
+pub fn read_decisions<P: AsRef<Path>>(path: P) -> Result<Vec<Decision>, ErrorCafeteria> {
+    fs::read_to_string(path)
+        .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(Decision::from_str)
+        .collect()
+}
+
+/// Origen de las decisiones de planificacion de la ejecucion en un unico thread
+#[derive(Debug)]
+enum Decisions {
+    /// Se sortean con un generador inicializado con la semilla, asi la misma semilla repite las mismas decisiones
+    Seeded(Box<StdRng>),
+
+    /// Se repiten las decisiones grabadas, en orden
+    Recorded(VecDeque<Decision>),
+}
+
+/// Ejecucion determinista de todos los pedidos en un unico thread, para depurar los fallos no deterministas de los tests de
+/// combinaciones.
+///
+/// Se simulan los `N_DISPENSERS` dispensers: hasta `N_DISPENSERS` pedidos estan en proceso a la vez, tomados en orden de la
+/// cola de pedidos apenas finalizan los pedidos de los que dependen. En cada paso una decision (ver `Decision`) elige que
+/// pedido en proceso avanza y que ingrediente se le aplica, con los mismos contenedores y estados que usan los dispensers, asi
+/// la misma semilla o las mismas decisiones grabadas producen exactamente el mismo resultado en cada ejecucion.
+///
+/// Los limites de pedidos en proceso por ingrediente (`AdmissionControl`) no se aplican, ya que un unico thread nunca procesa
+/// dos ingredientes a la vez.
+#[derive(Debug)]
+pub struct Replay {
+    /// Origen de las decisiones
+    decisions: Decisions,
+
+    /// Contenedores de los ingredientes. Si es None se usa `Conteiners::default()`
+    containers: Option<Conteiners>,
+}
+
+impl Replay {
+    /// Crea la ejecucion con decisiones sorteadas a partir de la semilla
+    pub fn seeded(seed: u64) -> Self {
+        Replay {
+            decisions: Decisions::Seeded(Box::new(StdRng::seed_from_u64(seed))),
+            containers: None,
+        }
+    }
+
+    /// Crea la ejecucion que repite las decisiones grabadas
+    pub fn recorded(decisions: Vec<Decision>) -> Self {
+        Replay {
+            decisions: Decisions::Recorded(decisions.into()),
+            containers: None,
+        }
+    }
+
+    /// Crea la ejecucion que repite las decisiones del archivo de `J_REPRODUCIR_DECISIONES`, o si no esta definido la que
+    /// sortea las decisiones con la semilla `J_SEMILLA`.
+    pub fn from_env() -> Result<Self, ErrorCafeteria> {
+        match Consts::j_reproducir_decisiones() {
+            Some(path) => Ok(Replay::recorded(read_decisions(path)?)),
+            None => Ok(Replay::seeded(Consts::j_semilla())),
+        }
+    }
+
+    /// Reemplaza los contenedores de los ingredientes
+    pub fn with_containers(mut self, containers: Conteiners) -> Self {
+        self.containers = Some(containers);
+        self
+    }
+
+    /// Ejecuta todos los pedidos en el thread actual.
+    ///
+    /// # Arguments
+    ///  * `orders` - Pedidos a procesar, en el orden de la cola de pedidos. Las dependencias deben formar un DAG, como
+    ///    garantiza `read_orders`.
+    /// # Returns
+    /// * `Result<ReplayOutcome, ErrorCafeteria>`:
+    ///    * Si es Ok, las decisiones tomadas y el resultado de la ejecucion.
+    ///    * Si es Err, alguna decision grabada no corresponde a la ejecucion (por ejemplo, se grabo con otros pedidos o
+    ///      capacidades), se terminaron las decisiones grabadas antes de finalizar todos los pedidos, o hubo un error al
+    ///      tomar el lock de algun contenedor.
+    pub fn run(self, orders: Vec<Order>) -> Result<ReplayOutcome, ErrorCafeteria> {
+        let Replay {
+            mut decisions,
+            containers,
+        } = self;
+        let containers = Arc::new(containers.unwrap_or_default());
+        let states = Mutex::new(ContainersStates::default());
+
+        let mut queue: VecDeque<Order> = orders.into();
+        let mut in_flight: Vec<Order> = Vec::new();
+        let mut finished_states: HashMap<usize, OrderState> = HashMap::new();
+        let mut taken = Vec::new();
+        let mut finished = Vec::new();
+
+        loop {
+            // como los dispensers, se toman pedidos en orden de la cola cuyas dependencias ya finalizaron
+            while in_flight.len() < Consts::n_dispensers() {
+                match queue
+                    .iter()
+                    .position(|order| order.dependencies_finished(&finished_states))
+                {
+                    Some(position) => in_flight.extend(queue.remove(position)),
+                    None => break,
+                }
+            }
+            if in_flight.is_empty() {
+                break;
+            }
+
+            let candidates = in_flight
+                .iter()
+                .map(|order| options_for(order, &states).map(|options| (id_of(order), options)))
+                .collect::<Result<Vec<_>, ErrorCafeteria>>()?;
+            let decision = decisions.next(&candidates)?;
+            taken.push(decision);
+
+            let index = in_flight
+                .iter()
+                .position(|order| id_of(order) == decision.order)
+                .ok_or_else(|| diverged(&decision))?;
+            let mut order = in_flight.remove(index);
+            if order.status != OrderState::ExceedsCapacity {
+                apply_decision(&mut order, decision, &containers, &states)?;
+            }
+
+            match order.status {
+                OrderState::InProgress => in_flight.insert(index, order),
+                status => {
+                    info!("[ REPLAY ] | [Order#{}]: {:?}", decision.order, status);
+                    finished_states.insert(decision.order, status);
+                    finished.push((decision.order, status));
+                }
+            }
+        }
+
+        let states = states
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+        let mut quantities: Vec<(IngredientType, f32)> = states
+            .principal_conteiners
+            .iter()
+            .map(|(tipo, (_, quantity))| (*tipo, *quantity))
+            .chain(states.quantity_to_recharge.iter().map(|(k, v)| (*k, *v)))
+            .collect();
+        quantities.sort_by_key(|(tipo, _)| format!("{:?}", tipo));
+
+        Ok(ReplayOutcome {
+            decisions: taken,
+            finished,
+            quantities,
+        })
+    }
+}
+
+impl Decisions {
+    /// Elige la proxima decision entre las opciones de cada pedido en proceso (ver `options_for`)
+    fn next(
+        &mut self,
+        candidates: &[(usize, Vec<Option<IngredientType>>)],
+    ) -> Result<Decision, ErrorCafeteria> {
+        match self {
+            Decisions::Seeded(rng) => {
+                let (order, options) = &candidates[rng.gen_range(0, candidates.len())];
+                Ok(Decision {
+                    order: *order,
+                    ingredient: options[rng.gen_range(0, options.len())],
+                })
+            }
+            Decisions::Recorded(recorded) => {
+                let decision = recorded.pop_front().ok_or_else(|| {
+                    ErrorCafeteria::new("The recorded decisions ended before every order finished")
+                })?;
+                let valid = candidates.iter().any(|(order, options)| {
+                    *order == decision.order && options.contains(&decision.ingredient)
+                });
+                if valid {
+                    Ok(decision)
+                } else {
+                    Err(diverged(&decision))
+                }
+            }
+        }
+    }
+}
+
+/// Opciones para avanzar un pedido en proceso: None si se debe finalizar sin aplicar ingredientes (se cancela por un
+/// contenedor sin recursos, o se rechazo al leer el archivo), o si no, cada ingrediente que todavia requiere
+fn options_for(
+    order: &Order,
+    states: &Mutex<ContainersStates>,
+) -> Result<Vec<Option<IngredientType>>, ErrorCafeteria> {
+    let states = states
+        .lock()
+        .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+    if order.status == OrderState::ExceedsCapacity || states.container_without_resource_for(order) {
+        return Ok(vec![None]);
+    }
+    // en un unico thread nunca hay contenedores tomados entre pasos, asi que todos los que requiere estan libres
+    Ok(PRINCIPAL_INGREDIENTS
+        .into_iter()
+        .filter(|tipo| order.requiere(tipo))
+        .map(Some)
+        .collect())
+}
+
+/// Avanza el pedido segun la decision, igual que `Dispenser::process_order`: lo cancela o le aplica el ingrediente con su contenedor
+fn apply_decision(
+    order: &mut Order,
+    decision: Decision,
+    containers: &Conteiners,
+    states: &Mutex<ContainersStates>,
+) -> Result<(), ErrorCafeteria> {
+    let lock_states = || {
+        states
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))
+    };
+
+    match decision.ingredient {
+        None => {
+            lock_states()?.mark_missing_ingredients_of(order);
+            order.status = OrderState::NoEnoughResourceContainer;
+        }
+        Some(tipo) => {
+            let mut container = containers.lock_for(tipo)?;
+            container.set_taken_state(lock_states()?);
+            container.apply_ingredient(order);
+            container.update_and_notify_state(lock_states()?);
+            order.get_updated_status();
+        }
+    }
+    Ok(())
+}
+
+/// Id del pedido
+fn id_of(order: &Order) -> usize {
+    order.id.load(std::sync::atomic::Ordering::SeqCst) as usize
+}
+
+/// Error de una decision grabada que no corresponde a la ejecucion
+fn diverged(decision: &Decision) -> ErrorCafeteria {
+    ErrorCafeteria::new(&format!(
+        "The replay diverged: decision \"{}\" is not possible at this point",
+        decision
+    ))
+}
+
+/// Resultado de una ejecucion en un unico thread
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayOutcome {
+    /// Decisiones tomadas, en orden. Grabadas en un archivo (ver `save_decisions`) permiten repetir la ejecucion
+    pub decisions: Vec<Decision>,
+
+    /// Id y estado final de cada pedido, en el orden en que finalizaron
+    pub finished: Vec<(usize, OrderState)>,
+
+    /// Cantidad final de cada contenedor, y la cantidad para recargar de los contenedores de recarga, por ingrediente
+    pub quantities: Vec<(IngredientType, f32)>,
+}
+
+impl ReplayOutcome {
+    /// Graba las decisiones tomadas en un archivo, una por linea, para repetir la ejecucion con `read_decisions`
+    pub fn save_decisions<P: AsRef<Path>>(&self, path: P) -> Result<(), ErrorCafeteria> {
+        let lines: String = self
+            .decisions
+            .iter()
+            .map(|decision| format!("{}\n", decision))
+            .collect();
+        fs::write(path, lines).map_err(|x| ErrorCafeteria::new(&x.to_string()))
+    }
+
+    /// Reporta en el log el resultado de la ejecucion
+    pub fn report(&self) {
+        info!(
+            "[ REPLAY ] {} decisions. Orders finished (id, status): {:?}",
+            self.decisions.len(),
+            self.finished
+        );
+        info!("[ REPLAY ] Final quantities: {:?}", self.quantities);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orders() -> Vec<Order> {
+        (0..6)
+            .map(|id| Order::new_with_id(id, 10.0, 10.0, 400.0, 20.0))
+            .collect()
+    }
+
+    #[test]
+    fn test1_same_seed_gives_the_same_outcome_and_recorded_decisions_reproduce_it() {
+        let outcome = Replay::seeded(7).run(orders()).unwrap();
+
+        assert_eq!(Replay::seeded(7).run(orders()).unwrap(), outcome);
+        assert_eq!(outcome.finished.len(), 6);
+        assert!(outcome
+            .finished
+            .iter()
+            .any(|(_, status)| *status == OrderState::NoEnoughResourceContainer));
+
+        let recorded = Replay::recorded(outcome.decisions.clone())
+            .run(orders())
+            .unwrap();
+        assert_eq!(recorded, outcome);
+    }
+
+    #[test]
+    fn test2_recorded_decision_that_is_not_possible_is_an_error() {
+        let decisions = "9 Cacao\n"
+            .lines()
+            .map(Decision::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(Replay::recorded(decisions).run(orders()).is_err());
+
+        let decision = Decision {
+            order: 3,
+            ingredient: None,
+        };
+        assert_eq!(decision.to_string().parse::<Decision>().unwrap(), decision);
+    }
+}
@@ -0,0 +1,364 @@
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    sync::atomic::Ordering,
+};
+
+use log::{debug, error, info};
+
+use crate::{
+    conteiners::Conteiners,
+    conteiners_states::ContainersStates,
+    dispenser::Dispenser,
+    enums::{IngredientType, OrderState},
+    error_dispenser::ErrorCafeteria,
+    order::Order,
+    scheduling::SchedulingHints,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        thread::{Builder, JoinHandle},
+        Arc, Condvar, Mutex,
+    },
+    utils::Consts,
+};
+
+/// Pedido que esta procesando un dispenser, con los ingredientes que se contaron al admitirlo
+type InFlight = Arc<Mutex<Option<(Order, Vec<IngredientType>)>>>;
+
+/// Lo que un dispenser vigilado comparte con su monitor: el pedido que esta procesando y el aviso de que termino su thread.
+///
+/// El aviso se envia al descartarse, asi tambien llega cuando el thread termina por un panic.
+#[derive(Debug)]
+pub struct DispenserWatch {
+    /// Id del dispenser vigilado
+    id: usize,
+
+    /// Pedido que esta procesando el dispenser, compartido con el monitor
+    in_flight: InFlight,
+
+    /// Canal por el que se avisa al monitor el id del dispenser que termino
+    exits: Sender<usize>,
+}
+
+impl DispenserWatch {
+    /// Registra el pedido que el dispenser comienza a procesar
+    pub(crate) fn track(
+        &self,
+        order: &Order,
+        counted: &[IngredientType],
+    ) -> Result<(), ErrorCafeteria> {
+        self.in_flight
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .replace((order.clone(), counted.to_vec()));
+        Ok(())
+    }
+
+    /// Registra que el dispenser termino de procesar el pedido
+    pub(crate) fn clear(&self) -> Result<(), ErrorCafeteria> {
+        self.in_flight
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .take();
+        Ok(())
+    }
+}
+
+impl Drop for DispenserWatch {
+    fn drop(&mut self) {
+        // si el monitor ya termino no hay a quien avisar
+        let _ = self.exits.send(self.id);
+    }
+}
+
+/// # Ejecucion y Creacion de los Dispensers vigilados
+///
+/// Alternativa a `create_and_run_dispensers` con los mismos parametros: ademas de los `Consts::n_dispensers()` dispensers se
+/// spawnea un monitor que les hace join apenas terminan. Si un dispenser termina por un panic (un mutex envenenado, un bug en un
+/// contenedor propio, etc.) el monitor loguea la causa, recupera los contenedores envenenados (`Conteiners::recover_poisoned`),
+/// aborta el pedido que estaba procesando (`OrderState::Aborted`) y spawnea otro dispenser conectado a las mismas colas, asi los
+/// pedidos siguen fluyendo.
+///
+/// Si un dispenser termina con error, tambien se aborta el pedido que estaba procesando, pero no se lo reemplaza.
+///
+/// # Arguments
+/// * `pair_vecdeque_orders` - Arc<(`Mutex<Option<VecDeque<Order>>>`, Condvar)>: Pair de Mutex y Condvar para la cola de pedidos a procesar.
+/// * `pair_vecdeque_system_alert` - Arc<(`Mutex<VecDeque<Order>>`, Condvar)>: Pair de Mutex y Condvar para la cola pedidos finalizados.
+/// * `pair_finished_orders` - Arc<(`Mutex<HashMap<usize, OrderState>>`, Condvar)>: Pair de Mutex y Condvar con el estado final de cada pedido finalizado.
+/// * `pair_conteiners_states` - Arc<(`Mutex<ContainersStates>`, Condvar)>: Pair de Mutex y Condvar para los estados de los contenedores.
+/// * `arc_containers` - `Arc<Conteiners>`: Arc de los contenedores.
+/// # Returns
+/// * `Vec<Dispenser>`: El monitor (con `Consts::n_dispensers()` como id), que termina cuando terminaron todos los dispensers.
+pub fn create_and_run_monitored_dispensers(
+    pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+    pair_vecdeque_system_alert: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+    pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+    pair_conteiners_states: &Arc<(Mutex<ContainersStates>, Condvar)>,
+    arc_containers: Arc<Conteiners>,
+) -> Vec<Dispenser> {
+    let (exits_sender, exits) = channel();
+    let monitor = Monitor {
+        pair_vecdeque_orders: pair_vecdeque_orders.clone(),
+        pair_vecdeque_system_alert: pair_vecdeque_system_alert.clone(),
+        pair_finished_orders: pair_finished_orders.clone(),
+        pair_conteiners_states: pair_conteiners_states.clone(),
+        containers: arc_containers,
+        exits: exits_sender,
+    };
+
+    let n_dispensers = Consts::n_dispensers();
+    let watched = (0..n_dispensers)
+        .map(|id| (id, monitor.spawn(id)))
+        .collect::<HashMap<usize, (Dispenser, InFlight)>>();
+
+    let handle = Builder::new()
+        .name("[ DISPENSER MONITOR ]".to_string())
+        .spawn(move || monitor.run(watched, exits))
+        .ok(); // Failed to spawn thread, None indicates this thread is not running.
+
+    vec![Dispenser {
+        id: n_dispensers,
+        handle,
+        scheduling: SchedulingHints::default(),
+    }]
+}
+
+/// Monitor de los dispensers, con las colas a las que se conecta cada dispenser que spawnea
+struct Monitor {
+    /// Cola de pedidos a procesar
+    pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+
+    /// Cola de pedidos finalizados
+    pair_vecdeque_system_alert: Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+
+    /// Estado final de cada pedido finalizado
+    pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+
+    /// Estados de los contenedores
+    pair_conteiners_states: Arc<(Mutex<ContainersStates>, Condvar)>,
+
+    /// Contenedores de la cafeteria
+    containers: Arc<Conteiners>,
+
+    /// Canal por el que los dispensers avisan que terminaron
+    exits: Sender<usize>,
+}
+
+impl Monitor {
+    /// Spawnea un dispenser vigilado con el id recibido
+    fn spawn(&self, id: usize) -> (Dispenser, InFlight) {
+        let in_flight: InFlight = Arc::new(Mutex::new(None));
+        let mut dispenser = Dispenser::new(id);
+        dispenser.run_watched(
+            self.pair_vecdeque_orders.clone(),
+            self.pair_vecdeque_system_alert.clone(),
+            self.pair_finished_orders.clone(),
+            self.pair_conteiners_states.clone(),
+            self.containers.clone(),
+            Some(DispenserWatch {
+                id,
+                in_flight: in_flight.clone(),
+                exits: self.exits.clone(),
+            }),
+        );
+        (dispenser, in_flight)
+    }
+
+    /// Hace join de cada dispenser apenas avisa que termino, reemplazando a los que terminaron por un panic, hasta que
+    /// terminaron todos.
+    fn run(
+        self,
+        mut watched: HashMap<usize, (Dispenser, InFlight)>,
+        exits: Receiver<usize>,
+    ) -> Result<(), ErrorCafeteria> {
+        let mut next_id = watched.len();
+        let mut respawned = 0;
+
+        while !watched.is_empty() {
+            let id = exits
+                .recv()
+                .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+            let (dispenser, in_flight) = match watched.remove(&id) {
+                Some(watched_dispenser) => watched_dispenser,
+                None => continue,
+            };
+
+            match dispenser.handle.map(JoinHandle::join) {
+                Some(Ok(Ok(()))) => debug!("[ DISPENSER#{} ] power off", id),
+                Some(Ok(Err(error))) => {
+                    error!("[ DISPENSER#{} ] Finished with error: {:?}", id, error);
+                    self.abort_in_flight(&in_flight)?;
+                }
+                Some(Err(cause)) => {
+                    error!(
+                        "[ DISPENSER#{} ] Panicked: {}. Spawning DISPENSER#{} to replace it.",
+                        id,
+                        panic_cause(cause.as_ref()),
+                        next_id
+                    );
+                    let recovered = self
+                        .containers
+                        .recover_poisoned(&self.pair_conteiners_states)?;
+                    if !recovered.is_empty() {
+                        error!(
+                            "[ DISPENSER MONITOR ] Recovered poisoned containers: {:?}",
+                            recovered
+                        );
+                    }
+                    self.abort_in_flight(&in_flight)?;
+                    watched.insert(next_id, self.spawn(next_id));
+                    next_id += 1;
+                    respawned += 1;
+                }
+                None => error!("[ MAIN ] Error executing DISPENSER-{}", id),
+            }
+        }
+
+        if respawned > 0 {
+            info!(
+                "[ DISPENSER MONITOR ] {} dispensers were respawned after a panic",
+                respawned
+            );
+        }
+        Ok(())
+    }
+
+    /// Aborta el pedido que estaba procesando un dispenser que termino, si todavia no habia finalizado, y lo libera del control
+    /// de admision de los contenedores.
+    fn abort_in_flight(&self, in_flight: &InFlight) -> Result<(), ErrorCafeteria> {
+        let in_flight = in_flight
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .take();
+        let (mut order, counted) = match in_flight {
+            Some(in_flight) => in_flight,
+            None => return Ok(()),
+        };
+
+        let id = order.id.load(Ordering::SeqCst) as usize;
+        let finished = self
+            .pair_finished_orders
+            .0
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+            .contains_key(&id);
+        if !finished {
+            order.status = OrderState::Aborted;
+            info!(
+                "[ DISPENSER MONITOR ] | [Order#{}]: ABORTED ORDER. Its dispenser finished while processing it.",
+                id
+            );
+            Dispenser::notify_order_finished(
+                order,
+                &self.pair_vecdeque_system_alert,
+                &self.pair_finished_orders,
+            )?;
+        }
+
+        self.containers
+            .admission
+            .release(&counted, &self.pair_vecdeque_orders)
+    }
+}
+
+/// Mensaje del panic de un thread, si fue un `&str` o un `String` (como en `panic!`)
+fn panic_cause(cause: &(dyn Any + Send)) -> String {
+    cause
+        .downcast_ref::<&str>()
+        .map(|cause| cause.to_string())
+        .or_else(|| cause.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown cause".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cafeteria::Cafeteria,
+        enums::StateOfConteiner,
+        order_sender::order_channel,
+        sync::{mpsc, thread, MutexGuard},
+        traits::ApplyContainer,
+    };
+
+    /// Contenedor de agua sin limite que hace panic al aplicar un pedido de 13 gramos de agua
+    struct PanickingAgua;
+
+    impl ApplyContainer for PanickingAgua {
+        fn apply_ingredient(&mut self, order: &mut Order) {
+            if order.get(&IngredientType::Agua) == Some(13.0) {
+                panic!("13 grams of water");
+            }
+            order.apply(IngredientType::Agua);
+        }
+
+        fn update_and_notify_state(&mut self, mut states: MutexGuard<ContainersStates>) {
+            states.set_state(1.0, StateOfConteiner::Free, &IngredientType::Agua);
+            states.notify_waiters_of(&IngredientType::Agua);
+        }
+
+        fn set_taken_state(&mut self, mut states: MutexGuard<ContainersStates>) {
+            states.set_state(1.0, StateOfConteiner::Taken, &IngredientType::Agua);
+        }
+
+        fn get_statistic(&self, _: IngredientType) -> Option<f32> {
+            None
+        }
+    }
+
+    #[test]
+    fn test1_panicked_dispensers_are_replaced_and_their_orders_aborted() {
+        // mas pedidos que hacen panic que dispensers, asi sin reemplazos no quedaria ningun dispenser
+        let panicking_orders = Consts::n_dispensers() + 1;
+        let containers = Conteiners::default()
+            .with_container(IngredientType::Agua, Box::new(PanickingAgua))
+            .unwrap();
+
+        let (order_sender, order_receiver) = order_channel();
+        let (sender, receiver) = mpsc::channel();
+        let cafeteria = Cafeteria::default()
+            .with_containers(containers)
+            .with_completed_orders(sender);
+        let ids = cafeteria.order_ids();
+        let running = thread::spawn(move || cafeteria.start_with_source(order_receiver));
+
+        for _ in 0..panicking_orders {
+            order_sender
+                .send(Order::new(&ids, 1.0, 0.0, 0.0, 13.0))
+                .unwrap();
+        }
+        for _ in 0..4 {
+            order_sender
+                .send(Order::new(&ids, 1.0, 0.0, 0.0, 1.0))
+                .unwrap();
+        }
+        drop(order_sender);
+
+        running.join().unwrap().unwrap();
+        let statuses = receiver
+            .iter()
+            .map(|order| order.status)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses
+                .iter()
+                .filter(|status| **status == OrderState::Aborted)
+                .count(),
+            panicking_orders
+        );
+        assert_eq!(
+            statuses
+                .iter()
+                .filter(|status| **status == OrderState::Completed)
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn test2_panic_cause_is_read_from_str_and_string_payloads() {
+        assert_eq!(panic_cause(&"boom"), "boom");
+        assert_eq!(panic_cause(&"boom".to_string()), "boom");
+        assert_eq!(panic_cause(&13), "unknown cause");
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{error_dispenser::ErrorCafeteria, utils::Consts};
+
+/// Sugerencias de planificacion para los threads de la cafeteria (dispensers y SYSTEM-ALERT), para controlar donde corren en
+/// los experimentos de rendimiento (por ejemplo en maquinas NUMA del laboratorio).
+///
+/// Cada thread aplica las sugerencias sobre si mismo al comenzar (`SchedulingHints::apply`). Si alguna no se puede aplicar
+/// (por ejemplo, bajar el nice sin permisos), el thread sigue corriendo sin ella.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchedulingHints {
+    /// Prefijo del nombre de los threads, para distinguir los threads de varias cafeterias en un mismo proceso
+    prefix: String,
+
+    /// Prioridad (nice de Linux, de -20 a 19) de los threads. Si es None no se cambia
+    priority: Option<i32>,
+
+    /// Nucleos en los que se fijan los threads, repartidos en orden segun el indice de cada thread. Si esta vacio no se fijan
+    cores: Vec<usize>,
+}
+
+impl SchedulingHints {
+    /// Crea las sugerencias a partir de las variables de entorno `H_PREFIJO_THREADS`, `H_PRIORIDAD_THREADS` y `H_NUCLEOS_THREADS`
+    pub fn from_env() -> Self {
+        SchedulingHints {
+            prefix: Consts::h_prefijo_threads(),
+            priority: Consts::h_prioridad_threads(),
+            cores: Consts::h_nucleos_threads(),
+        }
+    }
+
+    /// Reemplaza el prefijo del nombre de los threads
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Reemplaza la prioridad de los threads (nice de Linux, de -20 a 19)
+    pub fn with_priority(mut self, nice: i32) -> Self {
+        self.priority = Some(nice.clamp(-20, 19));
+        self
+    }
+
+    /// Reemplaza los nucleos en los que se fijan los threads
+    pub fn with_cores(mut self, cores: Vec<usize>) -> Self {
+        self.cores = cores;
+        self
+    }
+
+    /// Nombre del thread con el prefijo configurado
+    pub fn thread_name(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    /// Nucleo en el que se fija el thread con el indice recibido, repartiendo los threads en orden entre los nucleos configurados
+    pub fn core_for(&self, index: usize) -> Option<usize> {
+        (!self.cores.is_empty()).then(|| self.cores[index % self.cores.len()])
+    }
+
+    /// Aplica las sugerencias sobre el thread actual, que tiene el indice recibido (por ejemplo el id del dispenser).
+    ///
+    /// # Returns
+    /// * `Result<(), ErrorCafeteria>`:
+    ///    * Si es Ok, se aplicaron todas las sugerencias configuradas.
+    ///    * Si es Err, no se pudo fijar el thread al nucleo o cambiar su prioridad.
+    pub fn apply(&self, index: usize) -> Result<(), ErrorCafeteria> {
+        if let Some(core) = self.core_for(index) {
+            if !core_affinity::set_for_current(core_affinity::CoreId { id: core }) {
+                return Err(ErrorCafeteria::new(&format!(
+                    "Could not pin the thread to core {}",
+                    core
+                )));
+            }
+        }
+        match self.priority {
+            Some(nice) => set_current_thread_priority(nice),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Cambia el nice del thread actual. En Linux `setpriority` con el id del thread cambia solo la prioridad de ese thread.
+#[cfg(target_os = "linux")]
+fn set_current_thread_priority(nice: i32) -> Result<(), ErrorCafeteria> {
+    // SAFETY: gettid y setpriority no reciben punteros, solo el id del thread actual y el nice
+    let result = unsafe {
+        let tid = libc::gettid();
+        libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice)
+    };
+    if result == -1 {
+        return Err(ErrorCafeteria::new(&format!(
+            "Could not set the thread priority to {}: {}",
+            nice,
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(())
+}
+
+/// Cambia el nice del thread actual. Fuera de Linux no se soporta
+#[cfg(not(target_os = "linux"))]
+fn set_current_thread_priority(nice: i32) -> Result<(), ErrorCafeteria> {
+    Err(ErrorCafeteria::new(&format!(
+        "Could not set the thread priority to {}: only supported on Linux",
+        nice
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::thread;
+
+    #[test]
+    fn test1_threads_are_spread_over_the_configured_cores_in_order() {
+        let hints = SchedulingHints::default().with_cores(vec![0, 2, 4]);
+
+        assert_eq!(
+            (0..5)
+                .map(|index| hints.core_for(index))
+                .collect::<Vec<_>>(),
+            vec![Some(0), Some(2), Some(4), Some(0), Some(2)]
+        );
+        assert_eq!(SchedulingHints::default().core_for(3), None);
+    }
+
+    #[test]
+    fn test2_thread_names_have_the_configured_prefix_and_priority_is_clamped() {
+        let hints = SchedulingHints::default()
+            .with_prefix("cafeteria-1 ")
+            .with_priority(-100);
+
+        assert_eq!(
+            hints.thread_name("[ DISPENSER#0 ]"),
+            "cafeteria-1 [ DISPENSER#0 ]"
+        );
+        assert_eq!(hints.priority, Some(-20));
+    }
+
+    #[test]
+    fn test3_a_thread_can_be_pinned_to_an_available_core_and_lower_its_priority() {
+        let core = core_affinity::get_core_ids()
+            .and_then(|cores| cores.first().map(|core| core.id))
+            .unwrap_or(0);
+        let hints = SchedulingHints::default()
+            .with_cores(vec![core])
+            .with_priority(19);
+
+        // se aplica en otro thread para no cambiar el nucleo ni la prioridad del thread del test
+        let applied = thread::spawn(move || hints.apply(0)).join().unwrap();
+
+        assert!(applied.is_ok(), "{:?}", applied);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{f32::consts::PI, time::Duration};
+
+use rand::Rng;
+
+use crate::{
+    enums::IngredientType,
+    utils::{Consts, SEGS_POR_GRAMO},
+};
+
+/// Distribucion de los segundos por gramo que tarda un contenedor en aplicar su ingrediente a un pedido.
+///
+/// Con un tiempo fijo todos los pedidos con la misma cantidad tardan lo mismo, lo que oculta en los experimentos de
+/// planificacion la variabilidad que tiene una cafeteria real. Cada aplicacion sortea sus segundos por gramo, y un sorteo
+/// negativo de la distribucion normal se toma como 0.
+///
+/// Se configura por ingrediente con las variables de entorno `Z_APLICACION_<INGREDIENTE>` (ver `Consts::z_aplicacion`):
+/// ```txt
+/// fijo:<segundos>
+/// uniforme:<minimo>:<maximo>
+/// normal:<media>:<desvio>
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServiceTime {
+    /// Siempre los mismos segundos por gramo
+    Fixed(f32),
+
+    /// Segundos por gramo sorteados uniformemente entre `min` y `max`
+    Uniform { min: f32, max: f32 },
+
+    /// Segundos por gramo sorteados con una distribucion normal
+    Normal { mean: f32, std_dev: f32 },
+}
+
+impl ServiceTime {
+    /// Parsea la distribucion, por ejemplo "uniforme:0.5:1.5". Retorna None si el formato es incorrecto, si algun
+    /// parametro es negativo o si el minimo de la uniforme supera al maximo.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split(':');
+        let kind = parts.next()?.trim().to_lowercase();
+        let params = parts
+            .map(|x| x.trim().parse::<f32>().ok().filter(|x| *x >= 0.0))
+            .collect::<Option<Vec<f32>>>()?;
+        match (kind.as_str(), params.as_slice()) {
+            ("fijo", [seconds]) => Some(ServiceTime::Fixed(*seconds)),
+            ("uniforme", [min, max]) if min <= max => Some(ServiceTime::Uniform {
+                min: *min,
+                max: *max,
+            }),
+            ("normal", [mean, std_dev]) => Some(ServiceTime::Normal {
+                mean: *mean,
+                std_dev: *std_dev,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Distribucion del ingrediente segun su variable de entorno (ver `Consts::z_aplicacion`)
+    pub fn from_env(tipo: IngredientType) -> Self {
+        Consts::z_aplicacion(tipo)
+    }
+
+    /// Sortea los segundos por gramo de una aplicacion
+    pub fn seconds_per_gram<R: Rng>(&self, rng: &mut R) -> f32 {
+        match *self {
+            ServiceTime::Fixed(seconds) => seconds,
+            ServiceTime::Uniform { min, max } if min < max => rng.gen_range(min, max),
+            ServiceTime::Uniform { min, .. } => min,
+            ServiceTime::Normal { mean, std_dev } => {
+                // Box-Muller: u1 en (0, 1] para que el logaritmo sea finito
+                let u1 = 1.0 - rng.gen::<f32>();
+                let u2 = rng.gen::<f32>();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+                (mean + std_dev * z).max(0.0)
+            }
+        }
+    }
+
+    /// Sortea cuanto tarda la aplicacion de la cantidad de gramos recibida
+    pub fn duration<R: Rng>(&self, grams: f32, rng: &mut R) -> Duration {
+        Duration::from_secs_f32((grams * self.seconds_per_gram(rng)).max(0.0))
+    }
+}
+
+impl Default for ServiceTime {
+    fn default() -> Self {
+        ServiceTime::Fixed(SEGS_POR_GRAMO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test1_distributions_are_parsed_with_their_parameters() {
+        assert_eq!(ServiceTime::parse("fijo:2"), Some(ServiceTime::Fixed(2.0)));
+        assert_eq!(
+            ServiceTime::parse(" Uniforme:0.5:1.5 "),
+            Some(ServiceTime::Uniform { min: 0.5, max: 1.5 })
+        );
+        assert_eq!(
+            ServiceTime::parse("normal:1:0.2"),
+            Some(ServiceTime::Normal {
+                mean: 1.0,
+                std_dev: 0.2
+            })
+        );
+        for incorrect in [
+            "",
+            "fijo",
+            "fijo:-1",
+            "uniforme:2:1",
+            "normal:1",
+            "normal:1:x",
+            "poisson:1",
+        ] {
+            assert_eq!(ServiceTime::parse(incorrect), None, "{}", incorrect);
+        }
+    }
+
+    #[test]
+    fn test2_uniform_samples_stay_within_its_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let uniform = ServiceTime::Uniform { min: 0.5, max: 1.5 };
+        let samples: Vec<f32> = (0..1000)
+            .map(|_| uniform.seconds_per_gram(&mut rng))
+            .collect();
+
+        assert!(samples.iter().all(|x| (0.5..1.5).contains(x)));
+        assert!(samples.iter().any(|x| *x < 0.75) && samples.iter().any(|x| *x > 1.25));
+        assert_eq!(
+            ServiceTime::Uniform { min: 1.0, max: 1.0 }.duration(10.0, &mut rng),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn test3_normal_samples_have_its_mean_and_are_never_negative() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let normal = ServiceTime::Normal {
+            mean: 1.0,
+            std_dev: 0.2,
+        };
+        let samples: Vec<f32> = (0..10000)
+            .map(|_| normal.seconds_per_gram(&mut rng))
+            .collect();
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        let variance =
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / samples.len() as f32;
+
+        assert!((mean - 1.0).abs() < 0.02, "{}", mean);
+        assert!((variance.sqrt() - 0.2).abs() < 0.02, "{}", variance.sqrt());
+
+        let wide = ServiceTime::Normal {
+            mean: 0.1,
+            std_dev: 1.0,
+        };
+        assert!((0..1000).all(|_| wide.seconds_per_gram(&mut rng) >= 0.0));
+    }
+
+    #[test]
+    fn test4_fixed_time_is_the_default() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(
+            ServiceTime::default().duration(3.0, &mut rng),
+            Duration::from_secs_f32(3.0 * SEGS_POR_GRAMO)
+        );
+    }
+}
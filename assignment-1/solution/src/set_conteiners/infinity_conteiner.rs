@@ -1,4 +1,4 @@
-use crate::sync::{Condvar, MutexGuard};
+use crate::sync::MutexGuard;
 
 use log::debug;
 
@@ -6,7 +6,9 @@ use crate::{
     conteiners_states::ContainersStates,
     dispenser::Dispenser,
     enums::{IngredientType, StateOfConteiner},
+    grams::GramRounding,
     order::Order,
+    service_time::ServiceTime,
     traits::{ApplyContainer, ProcessApply, ProcessRecharge},
 };
 
@@ -20,6 +22,12 @@ pub struct InfinityConteiner {
 
     /// Cantidad actual de ingrediente en el contenedor.
     pub quantity: f32,
+
+    /// Politica de redondeo de la cantidad actual.
+    pub rounding: GramRounding,
+
+    /// Distribucion de los segundos por gramo que tarda en aplicar el ingrediente.
+    pub service_time: ServiceTime,
 }
 
 impl InfinityConteiner {
@@ -31,9 +39,23 @@ impl InfinityConteiner {
             tipo,
             capacity,
             quantity: capacity,
+            rounding: GramRounding::from_env(),
+            service_time: ServiceTime::from_env(tipo),
         }
     }
 
+    /// Reemplaza la politica de redondeo de la cantidad actual del contenedor.
+    pub fn with_rounding(mut self, rounding: GramRounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Reemplaza la distribucion del tiempo de aplicacion del ingrediente.
+    pub fn with_service_time(mut self, service_time: ServiceTime) -> Self {
+        self.service_time = service_time;
+        self
+    }
+
     /// Retorna true en caso de que el contenedor tenga la cantidad de ingredientes necesarios
     /// para satisfacer el tipo de ingrediente del contenedor actual de la orden.
     fn have_sufficient_quantity(&self, order: &Order) -> bool {
@@ -87,19 +109,14 @@ impl ApplyContainer for InfinityConteiner {
 
     /// Settea en el ContainersStates el estado de este contenedor como "Libre" y la cantidad actual en el contenedor.
     ///
-    /// Se notifica este cambio a los demas dispensers que esten esperando por el condvar.
+    /// Se notifica este cambio a los dispensers que esten esperando por este contenedor.
     ///
     /// # Arguments
     /// * `states` - MutexGuard de ContainersStates
-    /// * `cvar` - Condvar del mutex de ContainersStates
-    fn update_and_notify_state(
-        &mut self,
-        mut estados: MutexGuard<ContainersStates>,
-        cvar: &Condvar,
-    ) {
+    fn update_and_notify_state(&mut self, mut estados: MutexGuard<ContainersStates>) {
         estados.set_state(self.quantity, StateOfConteiner::Free, &self.tipo);
         estados.alert_conteiners_status();
-        cvar.notify_all();
+        estados.notify_waiters_of(&self.tipo);
     }
 
     /// Segun el tipo de ingrediente del contenedor, se aplica la cantidad de ingrediente necesario en el pedido
@@ -128,7 +145,7 @@ impl ApplyContainer for InfinityConteiner {
             let applied = order.apply(self.tipo);
             self.process_apply(applied);
 
-            self.quantity -= applied;
+            self.quantity = self.rounding.round(self.quantity - applied);
             debug!(
                 "{} | [Order#{:?}] FINISH APPLIED {} grams of {:?}.\n                 Remaining: {:?}",
                 Dispenser::id_dispenser(),
@@ -155,11 +172,7 @@ impl ApplyContainer for InfinityConteiner {
     }
 }
 
-use {
-    crate::sync::sleep,
-    crate::utils::{SEGS_FOR_RELOAD, SEGS_POR_GRAMO},
-    std::time::Duration,
-};
+use {crate::sync::sleep, crate::utils::SEGS_FOR_RELOAD, std::time::Duration};
 
 impl ProcessApply for InfinityConteiner {
     /// Representa el proceso de "aplicacion de ingrediente" del contenedor a la orden
@@ -167,7 +180,10 @@ impl ProcessApply for InfinityConteiner {
     /// # Arguments
     ///   * `quantitiy_to_apply` - Cantidad del ingrediente a aplicar
     fn process_apply(&mut self, quantitiy_to_apply: f32) {
-        sleep(Duration::from_secs_f32(quantitiy_to_apply * SEGS_POR_GRAMO));
+        sleep(
+            self.service_time
+                .duration(quantitiy_to_apply, &mut rand::thread_rng()),
+        );
     }
 }
 
@@ -181,14 +197,14 @@ impl ProcessRecharge for InfinityConteiner {
 #[cfg(test)]
 mod tests {
 
-    use crate::enums::OrderState;
+    use crate::{enums::OrderState, order::OrderIds, utils::SEGS_POR_GRAMO};
 
     use super::*;
 
     #[test]
     fn test1_applying_ingredient_from_container_to_order() {
         let mut real = InfinityConteiner::new(IngredientType::Agua, 100.0);
-        let mut order = Order::new(10.0, 10.0, 10.0, 10.0);
+        let mut order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 10.0);
         assert_eq!(order.get_updated_status(), OrderState::InProgress);
 
         real.apply_ingredient(&mut order);
@@ -200,14 +216,14 @@ mod tests {
         let mut real = InfinityConteiner::new(IngredientType::Agua, 100.0);
 
         for _ in 0..10 {
-            let mut order = Order::new(10.0, 10.0, 10.0, 10.0);
+            let mut order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 10.0);
             real.apply_ingredient(&mut order);
             assert_eq!(order.get_updated_status(), OrderState::InProgress);
         }
 
         assert_eq!(real.quantity, 0.0);
 
-        let mut order = Order::new(10.0, 10.0, 10.0, 10.0);
+        let mut order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 10.0);
 
         real.apply_ingredient(&mut order);
 
@@ -220,7 +236,7 @@ mod tests {
         // aca testeo la regla negocio mencionada!!!
 
         let mut real = InfinityConteiner::new(IngredientType::Agua, 100.0);
-        let mut order: Order = Order::new(10.0, 10.0, 10.0, 101.0);
+        let mut order: Order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 101.0);
         assert_eq!(order.get_updated_status(), OrderState::InProgress);
 
         real.apply_ingredient(&mut order);
@@ -231,4 +247,52 @@ mod tests {
             OrderState::NoEnoughResourceContainer
         );
     }
+
+    #[test]
+    fn test4_applying_and_reloading_advance_the_virtual_clock() {
+        let mut real = InfinityConteiner::new(IngredientType::Agua, 10.0);
+        let start = crate::sync::reloj().ahora();
+
+        let mut order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 10.0);
+        real.apply_ingredient(&mut order);
+        assert!(
+            crate::sync::reloj().ahora() - start >= Duration::from_secs_f32(10.0 * SEGS_POR_GRAMO)
+        );
+
+        let mut order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 10.0);
+        real.apply_ingredient(&mut order);
+        assert!(
+            crate::sync::reloj().ahora() - start
+                >= Duration::from_secs_f32(20.0 * SEGS_POR_GRAMO + SEGS_FOR_RELOAD)
+        );
+    }
+
+    #[test]
+    fn test5_container_emptied_in_fractions_is_reloaded_for_the_next_order() {
+        let mut real =
+            InfinityConteiner::new(IngredientType::Agua, 1.0).with_rounding(GramRounding::new(3));
+
+        for _ in 0..10 {
+            let mut order = Order::new(&OrderIds::default(), 0.0, 0.0, 0.0, 0.1);
+            real.apply_ingredient(&mut order);
+        }
+        assert_eq!(real.quantity, 0.0);
+
+        let mut order = Order::new(&OrderIds::default(), 0.0, 0.0, 0.0, 0.1);
+        real.apply_ingredient(&mut order);
+        assert_eq!(real.quantity, 0.9);
+    }
+
+    #[test]
+    fn test6_applying_takes_the_time_of_the_service_time_distribution() {
+        let mut real = InfinityConteiner::new(IngredientType::Agua, 100.0)
+            .with_service_time(ServiceTime::Uniform { min: 3.0, max: 4.0 });
+        let start = crate::sync::reloj().ahora();
+
+        let mut order = Order::new(&OrderIds::default(), 0.0, 0.0, 0.0, 10.0);
+        real.apply_ingredient(&mut order);
+
+        assert!(crate::sync::reloj().ahora() - start >= Duration::from_secs(30));
+        assert_eq!(real.quantity, 90.0);
+    }
 }
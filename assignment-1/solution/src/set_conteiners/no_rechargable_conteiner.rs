@@ -1,4 +1,4 @@
-use crate::sync::{Condvar, MutexGuard};
+use crate::sync::MutexGuard;
 
 use log::debug;
 
@@ -6,7 +6,9 @@ use crate::{
     conteiners_states::ContainersStates,
     dispenser::Dispenser,
     enums::{IngredientType, StateOfConteiner},
+    grams::GramRounding,
     order::Order,
+    service_time::ServiceTime,
     traits::{ApplyContainer, ProcessApply},
 };
 
@@ -23,6 +25,12 @@ pub struct NoRechargableConteiner {
 
     /// Estado del contenedor.
     pub state: StateOfConteiner,
+
+    /// Politica de redondeo de la cantidad actual.
+    pub rounding: GramRounding,
+
+    /// Distribucion de los segundos por gramo que tarda en aplicar el ingrediente.
+    pub service_time: ServiceTime,
 }
 
 impl NoRechargableConteiner {
@@ -33,9 +41,23 @@ impl NoRechargableConteiner {
             capacity,
             quantity: capacity,
             state: StateOfConteiner::Free,
+            rounding: GramRounding::from_env(),
+            service_time: ServiceTime::from_env(tipo),
         }
     }
 
+    /// Reemplaza la politica de redondeo de la cantidad actual del contenedor.
+    pub fn with_rounding(mut self, rounding: GramRounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Reemplaza la distribucion del tiempo de aplicacion del ingrediente.
+    pub fn with_service_time(mut self, service_time: ServiceTime) -> Self {
+        self.service_time = service_time;
+        self
+    }
+
     /// Retorna true en caso de que el contenedor tenga la cantidad de ingredientes necesarios
     /// para satisfacer la demanda de la orden del tipo de ingrediente del contenedor actual.
     fn have_sufficient_quantity(&self, order: &Order) -> bool {
@@ -59,19 +81,14 @@ impl ApplyContainer for NoRechargableConteiner {
     /// Settea en el ContainersStates el estado de este contenedor segun el estado actual del mismo, ademas settea
     /// la cantidad actual en el contenedor.
     ///
-    /// Se notifica este cambio a los demas dispensers que esten esperando por el condvar.
+    /// Se notifica este cambio a los dispensers que esten esperando por este contenedor.
     ///
     /// # Arguments
     /// * `states` - MutexGuard de ContainersStates
-    /// * `cvar` - Condvar del mutex de ContainersStates
-    fn update_and_notify_state(
-        &mut self,
-        mut estados: MutexGuard<ContainersStates>,
-        cvar: &Condvar,
-    ) {
+    fn update_and_notify_state(&mut self, mut estados: MutexGuard<ContainersStates>) {
         estados.set_state(self.quantity, self.state, &self.tipo);
         estados.alert_conteiners_status();
-        cvar.notify_all();
+        estados.notify_waiters_of(&self.tipo);
     }
 
     /// Segun el tipo de ingrediente del contenedor, se aplica la cantidad de ingrediente necesario en el pedido.
@@ -102,7 +119,7 @@ impl ApplyContainer for NoRechargableConteiner {
         } else {
             let applied = order.apply(self.tipo);
             self.process_apply(applied);
-            self.quantity -= applied;
+            self.quantity = self.rounding.round(self.quantity - applied);
             debug!(
                 "{} | [Order#{:?}] FINISH APPLIED {} grams of {:?}.\n                 Remaining: {:?}",
                 Dispenser::id_dispenser(),
@@ -135,25 +152,28 @@ impl ApplyContainer for NoRechargableConteiner {
     }
 }
 
-use {crate::sync::sleep, crate::utils::SEGS_POR_GRAMO, std::time::Duration};
+use crate::sync::sleep;
 
 impl ProcessApply for NoRechargableConteiner {
     fn process_apply(&mut self, quantitiy_to_apply: f32) {
-        sleep(Duration::from_secs_f32(quantitiy_to_apply * SEGS_POR_GRAMO));
+        sleep(
+            self.service_time
+                .duration(quantitiy_to_apply, &mut rand::thread_rng()),
+        );
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::enums::OrderState;
+    use crate::{enums::OrderState, order::OrderIds};
 
     use super::*;
 
     #[test]
     fn test1_applying_ingredient_from_container_to_order() {
         let mut real = NoRechargableConteiner::new(IngredientType::Cacao, 100.0);
-        let mut order = Order::new(10.0, 10.0, 10.0, 10.0);
+        let mut order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 10.0);
 
         real.apply_ingredient(&mut order);
         assert_eq!(real.quantity, 90.0);
@@ -165,14 +185,14 @@ mod tests {
         let mut real = NoRechargableConteiner::new(IngredientType::Cacao, 100.0);
 
         for _ in 0..10 {
-            let mut order = Order::new(10.0, 10.0, 10.0, 10.0);
+            let mut order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 10.0);
             real.apply_ingredient(&mut order);
             assert_eq!(order.get_updated_status(), OrderState::InProgress);
         }
 
         assert_eq!(real.quantity, 0.0);
 
-        let mut order = Order::new(10.0, 10.0, 10.0, 10.0);
+        let mut order = Order::new(&OrderIds::default(), 10.0, 10.0, 10.0, 10.0);
         real.apply_ingredient(&mut order);
 
         assert_eq!(real.quantity, 0.0);
@@ -188,7 +208,7 @@ mod tests {
         // aca testeo la regla negocio mencionada!!!
 
         let mut real = NoRechargableConteiner::new(IngredientType::Cacao, 100.0);
-        let mut order: Order = Order::new(10.0, 10.0, 101.0, 10.0);
+        let mut order: Order = Order::new(&OrderIds::default(), 10.0, 10.0, 101.0, 10.0);
 
         real.apply_ingredient(&mut order);
 
@@ -198,4 +218,20 @@ mod tests {
             OrderState::NoEnoughResourceContainer
         );
     }
+
+    #[test]
+    fn test4_container_emptied_in_fractions_is_left_at_exactly_zero() {
+        let mut real = NoRechargableConteiner::new(IngredientType::Cacao, 1.0)
+            .with_rounding(GramRounding::new(3));
+
+        for _ in 0..10 {
+            let mut order = Order::new(&OrderIds::default(), 0.0, 0.0, 0.1, 0.0);
+            real.apply_ingredient(&mut order);
+            assert_eq!(order.get_updated_status(), OrderState::Completed);
+        }
+
+        assert_eq!(real.quantity, 0.0);
+        assert!(real.quantity.is_sign_positive());
+        assert_eq!(real.state, StateOfConteiner::NoEnoughResource);
+    }
 }
@@ -1,4 +1,4 @@
-use crate::sync::{Condvar, MutexGuard};
+use crate::sync::{Arc, MutexGuard};
 
 use log::debug;
 
@@ -6,7 +6,10 @@ use crate::{
     conteiners_states::ContainersStates,
     dispenser::Dispenser,
     enums::{IngredientType, StateOfConteiner},
+    grams::GramRounding,
     order::Order,
+    pantry::Pantry,
+    service_time::ServiceTime,
     traits::{ApplyContainer, ProcessApply, ProcessRecharge},
 };
 
@@ -26,6 +29,15 @@ pub struct RechargableConteiner {
 
     /// Estado del contenedor.
     pub state: StateOfConteiner,
+
+    /// Despensa compartida de la que se repone la cantidad para recargar cuando se agota.
+    pub pantry: Option<Arc<dyn Pantry>>,
+
+    /// Politica de redondeo de la cantidad actual y de la cantidad para reponer.
+    pub rounding: GramRounding,
+
+    /// Distribucion de los segundos por gramo que tarda en aplicar el ingrediente.
+    pub service_time: ServiceTime,
 }
 
 impl RechargableConteiner {
@@ -44,9 +56,37 @@ impl RechargableConteiner {
             quantity: capacity,
             quantity_to_recharge,
             state: StateOfConteiner::Free,
+            pantry: None,
+            rounding: GramRounding::from_env(),
+            service_time: ServiceTime::from_env(tipo),
         }
     }
 
+    /// Reemplaza la politica de redondeo de la cantidad actual y de la cantidad para reponer del contenedor.
+    pub fn with_rounding(mut self, rounding: GramRounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Reemplaza la distribucion del tiempo de aplicacion del ingrediente.
+    pub fn with_service_time(mut self, service_time: ServiceTime) -> Self {
+        self.service_time = service_time;
+        self
+    }
+
+    /// Asocia el contenedor a una despensa compartida con otras cafeterias. Cuando la cantidad para
+    /// reponer no alcanza para un pedido, se le pide a la despensa lo que falta para llenar el contenedor.
+    pub fn with_pantry(mut self, pantry: Arc<dyn Pantry>) -> Self {
+        self.pantry = Some(pantry);
+        self
+    }
+
+    /// Retorna true si el contenedor todavia puede aplicar ingrediente en algun pedido futuro: le queda
+    /// cantidad, cantidad para reponer o una despensa a la que pedirle mas.
+    fn has_resources(&self) -> bool {
+        self.quantity.gt(&0.0) || self.quantity_to_recharge.1.gt(&0.0) || self.pantry.is_some()
+    }
+
     /// Retorna true en caso de que el contenedor tenga la cantidad de ingredientes necesarios
     /// para satisfacer la demanda de la orden del tipo de ingrediente del contenedor actual.
     fn have_sufficient_quantity(&self, order: &Order) -> bool {
@@ -87,8 +127,10 @@ impl RechargableConteiner {
     /// Se recarga al contenedor con la cantidad faltante segun la capacidad del mismo.
     fn reload_container(&mut self) {
         let need_to_reload = self.capacity - self.quantity;
-        self.quantity += need_to_reload;
-        self.quantity_to_recharge.1 -= need_to_reload;
+        self.quantity = self.capacity;
+        self.quantity_to_recharge.1 = self
+            .rounding
+            .round(self.quantity_to_recharge.1 - need_to_reload);
 
         debug!(
             "{} | [RELOAD] START TO RELOAD THE CONTAINER OF {:?}.",
@@ -104,6 +146,39 @@ impl RechargableConteiner {
             self.tipo
         );
     }
+
+    /// Repone la cantidad para recargar desde la despensa compartida, en caso de tener una.
+    ///
+    /// Se pide la cantidad que falta para poder llenar el contenedor. Si la despensa no otorga el permiso
+    /// (porque otras cafeterias ya retiraron su stock) la cantidad para recargar queda como estaba.
+    fn restock(&mut self) {
+        let pantry = match &self.pantry {
+            Some(pantry) => pantry,
+            None => return,
+        };
+        let missing = self.capacity - self.quantity - self.quantity_to_recharge.1.max(0.0);
+        if missing <= 0.0 {
+            return;
+        }
+        if pantry.grant(self.quantity_to_recharge.0, missing) {
+            self.quantity_to_recharge.1 = self
+                .rounding
+                .round(self.quantity_to_recharge.1.max(0.0) + missing);
+            debug!(
+                "{} | [RESTOCK] {} grams of {:?} GRANTED BY THE PANTRY.",
+                Dispenser::id_dispenser(),
+                missing,
+                self.quantity_to_recharge.0
+            );
+        } else {
+            debug!(
+                "{} | [RESTOCK] {} grams of {:?} DENIED BY THE PANTRY.",
+                Dispenser::id_dispenser(),
+                missing,
+                self.quantity_to_recharge.0
+            );
+        }
+    }
 }
 
 impl ApplyContainer for RechargableConteiner {
@@ -119,22 +194,15 @@ impl ApplyContainer for RechargableConteiner {
     /// la cantidad actual en el contenedor.
     /// Tambien se settea en ContainersStates la cantidad de ingrediente que puede reponer al contenedor.
     ///
-    /// Se notifica este cambio a los demas dispensers que esten esperando por el condvar.
+    /// Se notifica este cambio a los dispensers que esten esperando por este contenedor.
     ///
     /// # Arguments
     /// * `states` - MutexGuard de ContainersStates
-    /// * `cvar` - Condvar del mutex de ContainersStates
-    fn update_and_notify_state(
-        &mut self,
-        mut estados: MutexGuard<ContainersStates>,
-        cvar: &Condvar,
-    ) {
+    fn update_and_notify_state(&mut self, mut estados: MutexGuard<ContainersStates>) {
         estados.set_state(self.quantity, self.state, &self.tipo);
-        estados
-            .quantity_to_recharge
-            .insert(self.quantity_to_recharge.0, self.quantity_to_recharge.1);
+        estados.set_quantity_to_recharge(self.quantity_to_recharge.0, self.quantity_to_recharge.1);
         estados.alert_conteiners_status();
-        cvar.notify_all();
+        estados.notify_waiters_of(&self.tipo);
     }
 
     /// Segun el tipo de ingrediente del contenedor, se aplica la cantidad de ingrediente necesario en el pedido.
@@ -144,6 +212,7 @@ impl ApplyContainer for RechargableConteiner {
     /// - Si una orden no tiene la cantidad suficiente de ingredientes para satisfacer la demanda del pedido, entonces se recarga el contenedor en
     ///  caso de que la cantidad para reponer satisfaga la demanda del pedido. En caso contrario, se settea la orden como "NoEnoughResourceContainer"
     ///  y queda el contenedor como Libre en caso que el mismo disponga de cantidad suficiente de ingredientes para satisfacer la demanda de futuros pedidos.
+    /// - Si la cantidad para reponer no alcanza y el contenedor tiene una despensa, antes se le pide lo que falta para llenarlo.
     ///
     /// # Arguments
     /// * `order` - Orden a la que se le aplica el ingrediente
@@ -160,11 +229,14 @@ impl ApplyContainer for RechargableConteiner {
             self.state = StateOfConteiner::Free;
             return;
         } else if !self.have_sufficient_quantity(order) {
+            if !self.can_reload_for_order(order) {
+                self.restock();
+            }
             if self.can_reload_for_order(order) {
                 self.reload_container();
             } else {
                 order.set_no_enough_resource_container(self.tipo);
-                self.state = if self.quantity.gt(&0.0) || self.quantity_to_recharge.1.gt(&0.0) {
+                self.state = if self.has_resources() {
                     StateOfConteiner::Free
                 } else {
                     StateOfConteiner::NoEnoughResource
@@ -175,8 +247,8 @@ impl ApplyContainer for RechargableConteiner {
 
         let applied = order.apply(self.tipo);
         self.process_apply(applied);
-        self.quantity -= applied;
-        self.state = if self.quantity.gt(&0.0) || self.quantity_to_recharge.1.gt(&0.0) {
+        self.quantity = self.rounding.round(self.quantity - applied);
+        self.state = if self.has_resources() {
             StateOfConteiner::Free
         } else {
             StateOfConteiner::NoEnoughResource
@@ -206,15 +278,14 @@ impl ApplyContainer for RechargableConteiner {
     }
 }
 
-use {
-    crate::sync::sleep,
-    crate::utils::{SEGS_FOR_RELOAD, SEGS_POR_GRAMO},
-    std::time::Duration,
-};
+use {crate::sync::sleep, crate::utils::SEGS_FOR_RELOAD, std::time::Duration};
 
 impl ProcessApply for RechargableConteiner {
     fn process_apply(&mut self, quantitiy_to_apply: f32) {
-        sleep(Duration::from_secs_f32(quantitiy_to_apply * SEGS_POR_GRAMO));
+        sleep(
+            self.service_time
+                .duration(quantitiy_to_apply, &mut rand::thread_rng()),
+        );
     }
 }
 
@@ -227,7 +298,7 @@ impl ProcessRecharge for RechargableConteiner {
 #[cfg(test)]
 mod tests {
 
-    use crate::enums::OrderState;
+    use crate::{enums::OrderState, order::OrderIds};
 
     use super::*;
 
@@ -238,7 +309,7 @@ mod tests {
             100.0,
             (IngredientType::GranosCafe, 300.0),
         );
-        let mut order = Order::new(50.0, 10.0, 10.0, 10.0);
+        let mut order = Order::new(&OrderIds::default(), 50.0, 10.0, 10.0, 10.0);
         assert_eq!(order.get_updated_status(), OrderState::InProgress);
 
         real.apply_ingredient(&mut order);
@@ -255,12 +326,12 @@ mod tests {
         );
         assert_eq!(real.quantity_to_recharge.1, 300.0);
 
-        let mut order = Order::new(100.0, 10.0, 10.0, 10.0);
+        let mut order = Order::new(&OrderIds::default(), 100.0, 10.0, 10.0, 10.0);
         real.apply_ingredient(&mut order);
         assert_eq!(real.quantity, 0.0);
         assert_eq!(order.get_updated_status(), OrderState::InProgress);
 
-        let mut order = Order::new(50.0, 10.0, 10.0, 10.0);
+        let mut order = Order::new(&OrderIds::default(), 50.0, 10.0, 10.0, 10.0);
 
         real.apply_ingredient(&mut order);
 
@@ -278,7 +349,7 @@ mod tests {
             100.0,
             (IngredientType::GranosCafe, 300.0),
         );
-        let mut order: Order = Order::new(110.0, 10.0, 10.0, 101.0);
+        let mut order: Order = Order::new(&OrderIds::default(), 110.0, 10.0, 10.0, 101.0);
         assert_eq!(order.get_updated_status(), OrderState::InProgress);
 
         real.apply_ingredient(&mut order);
@@ -297,14 +368,14 @@ mod tests {
             100.0,
             (IngredientType::GranosCafe, 300.0),
         );
-        let mut order: Order = Order::new(50.0, 10.0, 10.0, 101.0);
+        let mut order: Order = Order::new(&OrderIds::default(), 50.0, 10.0, 10.0, 101.0);
 
         real.apply_ingredient(&mut order);
         assert_eq!(real.quantity, 50.0);
         assert_eq!(real.quantity_to_recharge.1, 300.0);
         assert_eq!(order.get_updated_status(), OrderState::InProgress);
 
-        let mut order: Order = Order::new(100.0, 10.0, 10.0, 101.0);
+        let mut order: Order = Order::new(&OrderIds::default(), 100.0, 10.0, 10.0, 101.0);
 
         real.apply_ingredient(&mut order);
         assert_eq!(real.quantity, 0.0);
@@ -320,7 +391,7 @@ mod tests {
             100.0,
             (IngredientType::GranosCafe, 100.0),
         );
-        let mut order: Order = Order::new(100.0, 10.0, 10.0, 101.0);
+        let mut order: Order = Order::new(&OrderIds::default(), 100.0, 10.0, 10.0, 101.0);
 
         conteiner_coffe.apply_ingredient(&mut order);
         assert_eq!(conteiner_coffe.quantity, 0.0);
@@ -328,7 +399,7 @@ mod tests {
         assert_eq!(conteiner_coffe.state, StateOfConteiner::Free);
         assert_eq!(order.get_updated_status(), OrderState::InProgress);
 
-        let mut order: Order = Order::new(100.0, 10.0, 10.0, 101.0);
+        let mut order: Order = Order::new(&OrderIds::default(), 100.0, 10.0, 10.0, 101.0);
 
         conteiner_coffe.apply_ingredient(&mut order);
         assert_eq!(conteiner_coffe.quantity, 0.0);
@@ -336,7 +407,7 @@ mod tests {
         assert_eq!(conteiner_coffe.state, StateOfConteiner::NoEnoughResource);
         assert_eq!(order.get_updated_status(), OrderState::InProgress);
 
-        let mut order: Order = Order::new(100.0, 10.0, 10.0, 101.0);
+        let mut order: Order = Order::new(&OrderIds::default(), 100.0, 10.0, 10.0, 101.0);
         assert_eq!(order.get_updated_status(), OrderState::InProgress);
 
         conteiner_coffe.apply_ingredient(&mut order);
@@ -348,4 +419,58 @@ mod tests {
             OrderState::NoEnoughResourceContainer
         );
     }
+
+    #[test]
+    fn test6_container_restocks_from_pantry_when_quantity_to_recharge_is_not_enough() {
+        let pantry = std::sync::Arc::new(crate::pantry::SharedPantry::new(
+            std::collections::HashMap::from([(IngredientType::GranosCafe, 150.0)]),
+        ));
+        let mut conteiner_coffe = RechargableConteiner::new(
+            IngredientType::CafeMolido,
+            100.0,
+            (IngredientType::GranosCafe, 20.0),
+        )
+        .with_pantry(pantry.clone());
+
+        let mut order: Order = Order::new(&OrderIds::default(), 100.0, 10.0, 10.0, 101.0);
+        conteiner_coffe.apply_ingredient(&mut order);
+        assert_eq!(conteiner_coffe.quantity, 0.0);
+
+        let mut order: Order = Order::new(&OrderIds::default(), 100.0, 10.0, 10.0, 101.0);
+        conteiner_coffe.apply_ingredient(&mut order);
+        assert_eq!(conteiner_coffe.quantity, 0.0);
+        assert_eq!(conteiner_coffe.quantity_to_recharge.1, 0.0);
+        assert_eq!(pantry.available(IngredientType::GranosCafe), 70.0);
+        assert_eq!(order.get_updated_status(), OrderState::InProgress);
+
+        let mut order: Order = Order::new(&OrderIds::default(), 100.0, 10.0, 10.0, 101.0);
+        conteiner_coffe.apply_ingredient(&mut order);
+        assert_eq!(conteiner_coffe.quantity, 0.0);
+        assert_eq!(pantry.available(IngredientType::GranosCafe), 70.0);
+        assert_eq!(conteiner_coffe.state, StateOfConteiner::Free);
+        assert_eq!(
+            order.get_updated_status(),
+            OrderState::NoEnoughResourceContainer
+        );
+    }
+
+    #[test]
+    fn test7_container_and_quantity_to_recharge_emptied_in_fractions_are_left_at_exactly_zero() {
+        let mut conteiner_coffe = RechargableConteiner::new(
+            IngredientType::CafeMolido,
+            1.0,
+            (IngredientType::GranosCafe, 1.0),
+        )
+        .with_rounding(GramRounding::new(3));
+
+        for _ in 0..20 {
+            let mut order: Order = Order::new(&OrderIds::default(), 0.1, 0.0, 0.0, 0.0);
+            conteiner_coffe.apply_ingredient(&mut order);
+            assert_eq!(order.get_updated_status(), OrderState::Completed);
+        }
+
+        assert_eq!(conteiner_coffe.quantity, 0.0);
+        assert_eq!(conteiner_coffe.quantity_to_recharge.1, 0.0);
+        assert_eq!(conteiner_coffe.state, StateOfConteiner::NoEnoughResource);
+    }
 }
@@ -0,0 +1,235 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use log::info;
+
+use crate::{
+    conteiners_states::ContainersStates,
+    dispenser::Dispenser,
+    enums::OrderState,
+    error_dispenser::ErrorCafeteria,
+    order::Order,
+    sync::thread::{Builder, JoinHandle},
+    sync::{reloj, sleep, Arc, Condvar, Mutex},
+};
+
+/// Cada cuanto se revisa si la cafeteria ya se apago, para no demorar el apagado hasta que se venza el plazo
+const SHUTDOWN_POLL: Duration = Duration::from_millis(100);
+
+/// Vigila que la cafeteria se apague dentro del plazo luego de la señal de apagado de los dispensers.
+///
+/// Si los dispensers no terminaron antes del plazo (por ejemplo, esperando por un contenedor que no se libera), se abortan
+/// los pedidos que quedan en la cola de pedidos y se despierta a los dispensers que esperan por algun contenedor para que
+/// aborten los suyos (`OrderState::Aborted`), asi la cafeteria siempre termina.
+#[derive(Debug)]
+pub struct ShutdownWatchdog {
+    /// Es true cuando la cafeteria termino de apagarse
+    done: Arc<AtomicBool>,
+
+    /// Handle del thread que vigila el plazo. Contiene true si se vencio el plazo y se abortaron los pedidos.
+    handle: JoinHandle<Result<bool, ErrorCafeteria>>,
+}
+
+impl ShutdownWatchdog {
+    /// Spawnea el thread que vigila el plazo de apagado. Debe llamarse al enviar la señal de apagado a los dispensers.
+    ///
+    /// # Arguments
+    ///  * `deadline` - Plazo para que los dispensers terminen los pedidos.
+    ///  * `pair_vecdeque_orders` - Par de VecDeque y Condvar que representa la cola de pedidos.
+    ///  * `pair_conteiners_states` - Pair de Mutex y Condvar para el estado de los contenedores.
+    ///  * `pair_vecdeque_system_alert` - Pair de Mutex y Condvar para la cola de pedidos finalizados.
+    ///  * `pair_finished_orders` - Pair de Mutex y Condvar con el estado final de cada pedido finalizado.
+    /// # Returns
+    /// * `Result<ShutdownWatchdog, ErrorCafeteria>`:
+    ///    * Si es Ok, el watchdog que se debe finalizar con `finish` cuando termine el apagado.
+    ///    * Si es Err, no se pudo crear el thread.
+    pub fn start(
+        deadline: Duration,
+        pair_vecdeque_orders: Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+        pair_conteiners_states: Arc<(Mutex<ContainersStates>, Condvar)>,
+        pair_vecdeque_system_alert: Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+        pair_finished_orders: Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+    ) -> Result<Self, ErrorCafeteria> {
+        let done = Arc::new(AtomicBool::new(false));
+        let watching = done.clone();
+
+        let handle = Builder::new()
+            .name("[ SHUTDOWN WATCHDOG ]".to_string())
+            .spawn(move || {
+                let start = reloj().ahora();
+                while !watching.load(Ordering::SeqCst) {
+                    let elapsed = reloj().ahora().saturating_duration_since(start);
+                    match deadline.checked_sub(elapsed).filter(|left| !left.is_zero()) {
+                        Some(left) => sleep(left.min(SHUTDOWN_POLL)),
+                        None => {
+                            abort_remaining_orders(
+                                deadline,
+                                &pair_vecdeque_orders,
+                                &pair_conteiners_states,
+                                &pair_vecdeque_system_alert,
+                                &pair_finished_orders,
+                            )?;
+                            return Ok(true);
+                        }
+                    }
+                }
+                Ok(false)
+            })
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+
+        Ok(ShutdownWatchdog { done, handle })
+    }
+
+    /// Avisa al watchdog que la cafeteria termino de apagarse y espera a que termine.
+    ///
+    /// # Returns
+    /// * `Result<bool, ErrorCafeteria>`:
+    ///    * Si es Ok, true si se vencio el plazo y se abortaron los pedidos restantes.
+    ///    * Si es Err, hubo un error al abortar los pedidos o en el join del thread.
+    pub fn finish(self) -> Result<bool, ErrorCafeteria> {
+        self.done.store(true, Ordering::SeqCst);
+        self.handle
+            .join()
+            .map_err(|_| ErrorCafeteria::new("[ SHUTDOWN WATCHDOG ]: FAILED TO JOIN THREAD"))?
+    }
+}
+
+/// Aborta los pedidos pendientes al vencerse el plazo de apagado: despierta a los dispensers que esperan por algun contenedor
+/// para que aborten sus pedidos, y saca de la cola de pedidos los que ningun dispenser tomo, insertandolos como abortados en la
+/// cola de pedidos finalizados.
+fn abort_remaining_orders(
+    deadline: Duration,
+    pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+    pair_conteiners_states: &Arc<(Mutex<ContainersStates>, Condvar)>,
+    pair_vecdeque_system_alert: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+    pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+) -> Result<(), ErrorCafeteria> {
+    info!(
+        "[ SYSTEM-ALERT ] Dispensers did not finish within the shutdown deadline of {:?}. Aborting remaining orders",
+        deadline
+    );
+
+    pair_conteiners_states
+        .0
+        .lock()
+        .map_err(|x| ErrorCafeteria::new(&x.to_string()))?
+        .abort();
+
+    let remaining_orders: Vec<Order> = {
+        let (lock, cvar) = &**pair_vecdeque_orders;
+        let mut orders_queue = lock
+            .lock()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+        let remaining_orders = orders_queue
+            .as_mut()
+            .map(|orders| orders.drain(..).rev().collect())
+            .unwrap_or_default();
+        // la señal de apagado espera a que se vacie la cola
+        cvar.notify_all();
+        remaining_orders
+    };
+
+    for mut order in remaining_orders {
+        order.status = OrderState::Aborted;
+        info!(
+            "[ SYSTEM-ALERT ] | [Order#{:?}]: ABORTED ORDER. The shutdown deadline was exceeded.",
+            order.id
+        );
+        Dispenser::notify_order_finished(order, pair_vecdeque_system_alert, pair_finished_orders)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cafeteria::{init_elements_with_orders, join_dispensers},
+        dispenser::{create_and_run_dispensers, send_signal_poweroff_to_dispensers},
+        enums::{IngredientType, StateOfConteiner},
+        order::insert_orders,
+        utils::Consts,
+    };
+
+    #[test]
+    fn test1_orders_blocked_on_a_container_are_aborted_after_the_deadline() {
+        let total_orders = Consts::n_dispensers() + 1;
+        let (
+            orders,
+            pair_vecdeque_orders,
+            pair_vecdeque_system_alert,
+            pair_conteiners_states,
+            arc_containers,
+            pair_finished_orders,
+        ) = init_elements_with_orders(
+            (0..total_orders)
+                .map(|id| Order::new_with_id(id, 0.0, 0.0, 1.0, 0.0))
+                .collect(),
+        );
+        // el contenedor de cacao queda tomado y nunca se libera
+        pair_conteiners_states.0.lock().unwrap().set_state(
+            0.0,
+            StateOfConteiner::Taken,
+            &IngredientType::Cacao,
+        );
+
+        let dispensers = create_and_run_dispensers(
+            &pair_vecdeque_orders,
+            &pair_vecdeque_system_alert,
+            &pair_finished_orders,
+            &pair_conteiners_states,
+            arc_containers,
+        );
+        insert_orders(orders, &pair_vecdeque_orders).unwrap();
+
+        let watchdog = ShutdownWatchdog::start(
+            Duration::from_secs(10),
+            pair_vecdeque_orders.clone(),
+            pair_conteiners_states.clone(),
+            pair_vecdeque_system_alert.clone(),
+            pair_finished_orders.clone(),
+        )
+        .unwrap();
+        send_signal_poweroff_to_dispensers(pair_vecdeque_orders).unwrap();
+        join_dispensers(dispensers);
+
+        assert!(watchdog.finish().unwrap());
+        let finished = pair_finished_orders.0.lock().unwrap();
+        assert_eq!(finished.len(), total_orders);
+        assert!(finished
+            .values()
+            .all(|status| *status == OrderState::Aborted));
+        assert_eq!(
+            pair_vecdeque_system_alert.0.lock().unwrap().len(),
+            total_orders
+        );
+    }
+
+    #[test]
+    fn test2_finish_before_the_deadline_does_not_abort() {
+        let (
+            _,
+            pair_vecdeque_orders,
+            pair_vecdeque_system_alert,
+            pair_conteiners_states,
+            _,
+            pair_finished_orders,
+        ) = init_elements_with_orders(Vec::new());
+
+        // el reloj virtual de los tests avanza al instante, asi que el plazo no debe poder vencerse
+        let watchdog = ShutdownWatchdog::start(
+            Duration::MAX,
+            pair_vecdeque_orders,
+            pair_conteiners_states.clone(),
+            pair_vecdeque_system_alert,
+            pair_finished_orders,
+        )
+        .unwrap();
+
+        assert!(!watchdog.finish().unwrap());
+        assert!(!pair_conteiners_states.0.lock().unwrap().aborted);
+    }
+}
@@ -0,0 +1,509 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use log::{debug, error, info};
+
+use crate::{
+    enums::OrderState,
+    error_dispenser::ErrorCafeteria,
+    order::{insert_orders, Order, SourceIds},
+    sync::{
+        mpsc::{sync_channel, Receiver},
+        reloj, thread, Arc, Condvar, Mutex,
+    },
+    traits::OrderSource,
+    utils::Consts,
+};
+
+/// Pedidos que la fuente ya retorno y todavia no revisa el thread que los inserta (ver `insert_source_orders`)
+#[derive(Debug, Default)]
+struct Intake {
+    /// Pedidos retornados por la fuente, en orden
+    orders: Vec<Order>,
+
+    /// true cuando la fuente ya no tiene mas pedidos
+    ended: bool,
+
+    /// true cuando el thread que inserta los pedidos termino (por ejemplo por un error), asi la fuente deja de leer
+    closed: bool,
+}
+
+/// Thread principal productor: inserta los pedidos de la fuente en la cola de pedidos, cada uno apenas finalizan los pedidos de
+/// los que depende, hasta que la fuente no tiene mas pedidos.
+///
+/// La fuente se lee en su propio thread, asi mientras espera el proximo pedido (una linea que todavia no se escribio, un cliente
+/// TCP que no envia nada, el proximo pedido del generador) igual se insertan los pedidos que se liberan al finalizar los pedidos
+/// de los que dependen. Los pedidos que todavia no se revisaron son como mucho `N_DISPENSERS`, igual que la cola de pedidos, asi
+/// la fuente espera cuando los dispensers no dan abasto.
+///
+/// # Arguments
+///  * `source` - Fuente de los pedidos. Las dependencias deben formar un DAG, como garantiza `read_orders`.
+///  * `pair_vecdeque_orders` - Par de VecDeque y Condvar que representa la cola de pedidos.
+///  * `pair_finished_orders` - Par de HashMap y Condvar con el estado final de cada pedido finalizado por los dispensers, segun su id.
+/// # Returns
+/// * `Result<usize, ErrorCafeteria>` - Resultado de la operacion.
+///     * Si es Ok, la cantidad de pedidos que se insertaron en la cola de pedidos.
+///     * Si es Err, es por que hubo un error en algun wait() o al tomar el lock de algun Mutex, o porque se encontro en el mutex de
+///       la cola de pedidos un None.
+pub fn insert_source_orders<S: OrderSource + Send + ?Sized>(
+    source: &mut S,
+    pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+    pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+) -> Result<usize, ErrorCafeteria> {
+    let pair_intake = (Mutex::new(Intake::default()), Condvar::new());
+
+    thread::scope(|scope| {
+        let reader = thread::Builder::new()
+            .name(format!("[ SOURCE {} ]", source.name()))
+            .spawn_scoped(scope, || {
+                let result = read_source(source, &pair_intake, pair_finished_orders);
+                // aunque falle la lectura, el thread que inserta tiene que saber que no llegan mas pedidos
+                let _finished = pair_finished_orders.0.lock();
+                if let Ok(mut intake) = pair_intake.0.lock() {
+                    intake.ended = true;
+                }
+                pair_finished_orders.1.notify_all();
+                result
+            })
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+
+        let inserted = insert_intake(&pair_intake, pair_vecdeque_orders, pair_finished_orders);
+        if let Ok(mut intake) = pair_intake.0.lock() {
+            intake.closed = true;
+        }
+        pair_intake.1.notify_all();
+
+        reader
+            .join()
+            .map_err(|_| ErrorCafeteria::new("Source thread panicked"))??;
+        inserted
+    })
+}
+
+/// Lee los pedidos de la fuente y los deja en `Intake`, esperando a que haya lugar, hasta que la fuente no tiene mas pedidos
+/// o el thread que los inserta termina
+fn read_source<S: OrderSource + ?Sized>(
+    source: &mut S,
+    pair_intake: &(Mutex<Intake>, Condvar),
+    pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+) -> Result<(), ErrorCafeteria> {
+    let (lock, cvar) = pair_intake;
+
+    while let Some(order) = source.next_order() {
+        {
+            let mut intake = lock
+                .lock()
+                .map_err(|e| ErrorCafeteria::new(&format!("Error lock: {:?}", e)))?;
+            while intake.orders.len() >= Consts::n_dispensers() && !intake.closed {
+                intake = cvar
+                    .wait(intake)
+                    .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+            }
+            if intake.closed {
+                return Ok(());
+            }
+        }
+
+        // el pedido se deja con el lock de los finalizados tomado, asi el thread que inserta no deja de ver el notify_all()
+        // entre que revisa los pedidos nuevos y hace el wait()
+        let _finished = pair_finished_orders
+            .0
+            .lock()
+            .map_err(|e| ErrorCafeteria::new(&format!("Error lock: {:?}", e)))?;
+        lock.lock()
+            .map_err(|e| ErrorCafeteria::new(&format!("Error lock: {:?}", e)))?
+            .orders
+            .push(order);
+        pair_finished_orders.1.notify_all();
+    }
+
+    Ok(())
+}
+
+/// Inserta los pedidos que deja la fuente en `Intake` apenas finalizan los pedidos de los que dependen. Mientras ninguno este
+/// liberado, hace un wait() sobre la condvar de los pedidos finalizados hasta que algun dispenser finalice un pedido o la
+/// fuente deje uno nuevo.
+///
+/// # Returns
+/// * `Result<usize, ErrorCafeteria>` - Cantidad de pedidos insertados, o Err si hubo un error al insertar algun pedido.
+fn insert_intake(
+    pair_intake: &(Mutex<Intake>, Condvar),
+    pair_vecdeque_orders: &Arc<(Mutex<Option<VecDeque<Order>>>, Condvar)>,
+    pair_finished_orders: &Arc<(Mutex<HashMap<usize, OrderState>>, Condvar)>,
+) -> Result<usize, ErrorCafeteria> {
+    let mut pending_orders: Vec<Order> = Vec::new();
+    let mut inserted = 0;
+
+    loop {
+        let (ready_orders, ended) = {
+            let (lock, cvar) = &**pair_finished_orders;
+            let mut _guard = lock
+                .lock()
+                .map_err(|e| ErrorCafeteria::new(&format!("Error lock: {:?}", e)))?;
+            loop {
+                let ended = {
+                    let mut intake = pair_intake
+                        .0
+                        .lock()
+                        .map_err(|e| ErrorCafeteria::new(&format!("Error lock: {:?}", e)))?;
+                    pending_orders.append(&mut intake.orders);
+                    intake.ended
+                };
+                pair_intake.1.notify_all();
+
+                let (ready_orders, waiting_orders): (Vec<Order>, Vec<Order>) =
+                    std::mem::take(&mut pending_orders)
+                        .into_iter()
+                        .partition(|order| order.dependencies_finished(&_guard));
+                pending_orders = waiting_orders;
+                if !ready_orders.is_empty() || (ended && pending_orders.is_empty()) {
+                    break (ready_orders, ended);
+                }
+                _guard = cvar
+                    .wait(_guard)
+                    .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+            }
+        };
+
+        // se suelta el lock de los finalizados para que los dispensers puedan seguir registrando pedidos
+        // mientras se espera lugar en la cola de pedidos
+        inserted += ready_orders.len();
+        insert_orders(ready_orders, pair_vecdeque_orders)?;
+        if ended && pending_orders.is_empty() {
+            return Ok(inserted);
+        }
+    }
+}
+
+/// Fuente de pedidos que combina varias fuentes en la misma cola de pedidos (por ejemplo un archivo, un socket TCP y el
+/// generador). Se arma encadenando los `with_source`.
+///
+/// Cada fuente se lee en su propio thread, y los pedidos de la fuente `i` se numeran con `SourceIds::new(i, total)`, asi los ids
+/// no se repiten entre fuentes. La combinacion no tiene mas pedidos recien cuando ya no queda ninguna fuente con pedidos. Una
+/// fuente que termina con error se reporta en el log y las demas siguen.
+#[derive(Default)]
+pub struct Sources {
+    /// Fuentes que todavia no comenzaron a leerse
+    sources: Vec<Box<dyn OrderSource + Send>>,
+
+    /// Extremo del canal por el que llegan los pedidos de los threads de las fuentes. None hasta pedir el primer pedido
+    receiver: Option<Receiver<Order>>,
+
+    /// Nombre, numeracion y thread de cada fuente que se esta leyendo
+    handles: Vec<(String, SourceIds, Option<SourceHandle>)>,
+
+    /// Estadisticas de cada fuente, luego de `finish`
+    stats: Vec<SourceStats>,
+}
+
+/// Thread que lee una fuente, que retorna la cantidad de pedidos que leyo, el resultado de `OrderSource::finish` y el tiempo
+/// que estuvo leyendo
+type SourceHandle = thread::JoinHandle<(usize, Result<(), ErrorCafeteria>, Duration)>;
+
+impl std::fmt::Debug for Sources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sources")
+            .field(
+                "sources",
+                &self
+                    .sources
+                    .iter()
+                    .map(|source| source.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl Sources {
+    /// Crea la combinacion sin fuentes
+    pub fn new() -> Self {
+        Sources::default()
+    }
+
+    /// Agrega una fuente de pedidos
+    pub fn with_source<S: OrderSource + Send + 'static>(mut self, source: S) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// Estadisticas de cada fuente, en el orden en que se agregaron. Vacio hasta que la combinacion termina (ver `finish`)
+    pub fn stats(&self) -> &[SourceStats] {
+        &self.stats
+    }
+
+    /// Ejecuta cada fuente en su propio thread, que envia los pedidos renumerados por el canal
+    fn spawn(&mut self) -> Receiver<Order> {
+        let (sender, receiver) = sync_channel(Consts::n_dispensers());
+        let total_sources = self.sources.len();
+
+        self.handles = std::mem::take(&mut self.sources)
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut source)| {
+                let ids = SourceIds::new(index, total_sources);
+                let name = source.name();
+                let sender = sender.clone();
+                let handle = thread::Builder::new()
+                    .name(format!("[ SOURCE#{} ]", index))
+                    .spawn(move || {
+                        let start = reloj().ahora();
+                        let mut read = 0;
+                        while let Some(order) = source.next_order() {
+                            if sender.send(ids.renumber(order)).is_err() {
+                                break;
+                            }
+                            read += 1;
+                        }
+                        let result = source.finish();
+                        (
+                            read,
+                            result,
+                            reloj().ahora().saturating_duration_since(start),
+                        )
+                    })
+                    .map_err(|error| {
+                        error!("[ SYSTEM-ALERT ] Source #{} ({}): {}", index, name, error)
+                    })
+                    .ok();
+                (name, ids, handle)
+            })
+            .collect();
+
+        receiver
+    }
+}
+
+impl OrderSource for Sources {
+    fn next_order(&mut self) -> Option<Order> {
+        if self.receiver.is_none() {
+            self.receiver = Some(self.spawn());
+        }
+        let order = self.receiver.as_ref()?.recv().ok()?;
+        debug!(
+            "[ SOURCES ] | [Order#{:?}] NEW ORDER RECEIVED.\n                 Requeriments: {:?}",
+            order.id, order.ingredientes
+        );
+        Some(order)
+    }
+
+    /// Espera a que terminen los threads de las fuentes y reporta sus estadisticas. Los errores de las fuentes solo se reportan,
+    /// asi un error en una fuente no descarta los pedidos de las demas
+    fn finish(&mut self) -> Result<(), ErrorCafeteria> {
+        self.receiver = None;
+        self.stats = std::mem::take(&mut self.handles)
+            .into_iter()
+            .map(|(name, ids, handle)| {
+                let (inserted, result, elapsed) = match handle.map(|handle| handle.join()) {
+                    Some(Ok(joined)) => joined,
+                    Some(Err(_)) => (
+                        0,
+                        Err(ErrorCafeteria::new("Source thread panicked")),
+                        Duration::ZERO,
+                    ),
+                    None => (
+                        0,
+                        Err(ErrorCafeteria::new("Source thread not spawned")),
+                        Duration::ZERO,
+                    ),
+                };
+                let stats = SourceStats {
+                    name,
+                    ids,
+                    inserted,
+                    elapsed,
+                    error: result.err().map(|error| error.mensaje),
+                };
+                stats.report();
+                stats
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        "sources".to_string()
+    }
+
+    /// La suma de los totales de las fuentes, si todas lo conocen de antemano
+    fn total_orders(&self) -> Option<usize> {
+        self.sources
+            .iter()
+            .map(|source| source.total_orders())
+            .sum()
+    }
+}
+
+/// Estadisticas de una fuente de pedidos
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceStats {
+    /// Nombre de la fuente
+    pub name: String,
+
+    /// Numeracion de los pedidos de la fuente en la cola de pedidos
+    pub ids: SourceIds,
+
+    /// Cantidad de pedidos que retorno la fuente
+    pub inserted: usize,
+
+    /// Tiempo que la fuente estuvo retornando pedidos
+    pub elapsed: Duration,
+
+    /// Si es Some, la fuente termino con este error
+    pub error: Option<String>,
+}
+
+impl SourceStats {
+    /// Reporta en el log las estadisticas de la fuente
+    pub fn report(&self) {
+        match &self.error {
+            None => info!(
+                "[ SYSTEM-ALERT ] Source #{} ({}): {} orders read in {:?}",
+                self.ids.index, self.name, self.inserted, self.elapsed
+            ),
+            Some(error) => error!(
+                "[ SYSTEM-ALERT ] Source #{} ({}): stopped after {:?} with error: {}",
+                self.ids.index, self.name, self.elapsed, error
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write, net::TcpStream, sync::atomic::Ordering};
+
+    use super::*;
+    use crate::{
+        cafeteria::{init_elements_with_orders, Cafeteria},
+        conteiners::ContainerCapacities,
+        enums::ImpossibleOrders,
+        file_orders::{stream_orders, OrdersReader},
+        order_sender::order_channel,
+        recipes::RecipeBook,
+        tcp_orders::TcpOrders,
+    };
+
+    #[test]
+    fn test1_orders_of_a_file_a_tcp_client_and_a_channel_are_processed_before_the_shutdown() {
+        fs::write("test_sources1.txt", "A5 M2\nA5 E1 D0\n").unwrap();
+        let tcp = TcpOrders::bind("127.0.0.1:0")
+            .unwrap()
+            .with_end_marker("FIN")
+            .with_poll_interval(Duration::from_millis(10));
+        let address = tcp.local_addr().unwrap();
+        let (order_sender, order_receiver) = order_channel();
+        let sources = Sources::new()
+            .with_source(stream_orders("test_sources1.txt").unwrap())
+            .with_source(tcp)
+            .with_source(order_receiver);
+
+        let (sender, receiver) = crate::sync::mpsc::channel();
+        let cafeteria = Cafeteria::default().with_completed_orders(sender);
+        let running = std::thread::spawn(move || cafeteria.start_with_source(sources));
+
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(b"A1\nA2 D0\nFIN\n").unwrap();
+        });
+        order_sender
+            .send(Order::new_with_id(0, 0.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        order_sender
+            .send(Order::new_with_id(1, 1.0, 0.0, 0.0, 0.0))
+            .unwrap();
+        drop(order_sender);
+
+        // el Receiver termina de iterarse cuando la Cafeteria se descarta al finalizar el thread
+        let received = receiver
+            .iter()
+            .map(|order| (order.id.load(Ordering::SeqCst) as usize, order.status))
+            .collect::<Vec<_>>();
+        running.join().unwrap().unwrap();
+        client.join().unwrap();
+        fs::remove_file("test_sources1.txt").unwrap();
+
+        let mut ids = received.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        ids.sort();
+        // el pedido `i` de la fuente `k` tiene el id `i * 3 + k`
+        assert_eq!(ids, vec![0, 1, 2, 3, 4, 5]);
+        assert!(received
+            .iter()
+            .all(|(_, status)| *status == OrderState::Completed));
+        // los pedidos que dependen del primero de su fuente finalizan despues que el
+        let position = |id| received.iter().position(|(order, _)| *order == id).unwrap();
+        assert!(position(0) < position(3));
+        assert!(position(1) < position(4));
+    }
+
+    #[test]
+    fn test2_a_source_that_fails_is_reported_and_the_others_keep_inserting() {
+        let (_, pair_vecdeque_orders, _, _, _, pair_finished_orders) =
+            init_elements_with_orders(Vec::new());
+        let (order_sender, order_receiver) = order_channel();
+        order_sender
+            .send(Order::new_with_id(0, 0.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        drop(order_sender);
+        let failing = OrdersReader::new(
+            "A10\nAx\n".as_bytes(),
+            ContainerCapacities::from_env(),
+            ImpossibleOrders::Mark,
+            RecipeBook::default(),
+        );
+        let mut sources = Sources::new()
+            .with_source(failing)
+            .with_source(order_receiver);
+
+        let inserted =
+            insert_source_orders(&mut sources, &pair_vecdeque_orders, &pair_finished_orders)
+                .unwrap();
+        sources.finish().unwrap();
+
+        assert_eq!(inserted, 2);
+        let stats = sources.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].inserted, 1);
+        assert!(stats[0].error.is_some());
+        assert_eq!((stats[1].ids.index, stats[1].inserted), (1, 1));
+        assert!(stats[1].error.is_none());
+        let queued = pair_vecdeque_orders.0.lock().unwrap();
+        let mut ids = queued
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|order| order.id.load(Ordering::SeqCst))
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn test3_a_dependent_order_is_inserted_while_the_source_waits_for_more_orders() {
+        let (order_sender, order_receiver) = order_channel();
+        let (sender, receiver) = crate::sync::mpsc::channel();
+        let cafeteria = Cafeteria::default().with_completed_orders(sender);
+        let running = std::thread::spawn(move || cafeteria.start_with_source(order_receiver));
+
+        order_sender
+            .send(Order::new_with_id(0, 0.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        order_sender
+            .send(Order::new_with_id(1, 0.0, 0.0, 0.0, 1.0).with_dependencies(vec![0]))
+            .unwrap();
+
+        // el canal sigue abierto, asi que el pedido 1 solo finaliza si se inserta mientras la fuente espera otro pedido
+        let finished = receiver
+            .iter()
+            .map(|order| order.id.load(Ordering::SeqCst))
+            .take(2)
+            .collect::<Vec<_>>();
+        assert_eq!(finished, vec![0, 1]);
+
+        drop(order_sender);
+        running.join().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    time::Duration,
+};
+
+use log::{debug, error};
+
+use crate::{
+    conteiners::ContainerCapacities,
+    enums::{ErrorType, ImpossibleOrders},
+    error_dispenser::ErrorCafeteria,
+    file_orders::parse_order,
+    order::Order,
+    recipes::RecipeBook,
+    sync::sleep,
+    traits::OrderSource,
+    utils::Consts,
+};
+
+/// Lectura de un archivo de ordenes que otro proceso sigue escribiendo: el archivo se mantiene abierto y los pedidos se
+/// insertan a medida que aparecen nuevas lineas, hasta leer la linea con la marca de fin (como `tail -f`).
+///
+/// Las lineas tienen el mismo formato que en `read_orders`, y el id de cada pedido sigue siendo su numero de linea.
+/// Cuando no hay lineas nuevas se vuelve a revisar el archivo cada `poll_interval`.
+#[derive(Debug)]
+pub struct OrdersTail {
+    /// Archivo de ordenes, que se mantiene abierto mientras se lee
+    reader: BufReader<File>,
+
+    /// Cada cuanto se revisa si el archivo tiene lineas nuevas
+    poll_interval: Duration,
+
+    /// Pedidos de las lineas leidas
+    lines: OrderLines,
+
+    /// Linea a medio escribir del archivo
+    line: String,
+
+    /// true luego de leer la marca de fin
+    finished: bool,
+}
+
+impl OrdersTail {
+    /// Abre el archivo de ordenes, con la marca de fin y el intervalo de `V_MARCA_FIN` y `V_INTERVALO_LECTURA`, y las
+    /// capacidades, la politica de pedidos imposibles y las recetas de las variables de entorno (idem a `read_orders`)
+    ///
+    /// # Arguments
+    ///  * `path` - Path del archivo de ordenes a leer.
+    /// # Returns
+    ///  * Si es Ok, `OrdersTail` - Lectura del archivo desde su primera linea.
+    ///  * Si es Err, `ErrorCafeteria` debido a que no se pudo abrir el archivo o a que el archivo de recetas tiene un
+    ///    formato incorrecto.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ErrorCafeteria> {
+        let file = File::open(path).map_err(|_| {
+            ErrorCafeteria::new_of_type(
+                "Error opening orders file",
+                ErrorType::NoAvailableOrderFile,
+            )
+        })?;
+        Ok(OrdersTail {
+            reader: BufReader::new(file),
+            poll_interval: Duration::from_millis(Consts::v_intervalo_lectura()),
+            lines: OrderLines::from_env()?,
+            line: String::new(),
+            finished: false,
+        })
+    }
+
+    /// Reemplaza la linea que indica que no se van a escribir mas pedidos. Una marca vacia se ignora, ya que las lineas vacias
+    /// son pedidos sin ingredientes
+    pub fn with_end_marker(mut self, end_marker: &str) -> Self {
+        self.lines.set_end_marker(end_marker);
+        self
+    }
+
+    /// Reemplaza cada cuanto se revisa si el archivo tiene lineas nuevas
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Reemplaza las capacidades de los contenedores y que hacer con los pedidos que las superan
+    pub fn with_capacities(
+        mut self,
+        capacities: ContainerCapacities,
+        impossible_orders: ImpossibleOrders,
+    ) -> Self {
+        self.lines.capacities = capacities;
+        self.lines.impossible_orders = impossible_orders;
+        self
+    }
+
+    /// Reemplaza las recetas de las bebidas que se pueden pedir por nombre
+    pub fn with_recipes(mut self, recipes: RecipeBook) -> Self {
+        self.lines.recipes = recipes;
+        self
+    }
+}
+
+/// Los pedidos se retornan a medida que se escriben sus lineas, hasta leer la marca de fin.
+///
+/// Una linea recien se parsea cuando termina con un salto de linea, asi no se lee un pedido a medio escribir. La marca de fin
+/// tambien se acepta sin salto de linea al final del archivo.
+///
+/// Si falla la lectura del archivo o alguna linea tiene un formato incorrecto, se loguea el error y se deja de leer como si se
+/// hubiera leido la marca de fin, asi la cafeteria se apaga procesando los pedidos ya leidos.
+impl OrderSource for OrdersTail {
+    fn next_order(&mut self) -> Option<Order> {
+        while !self.finished {
+            let read = match self.reader.read_line(&mut self.line) {
+                Ok(read) => read,
+                Err(error) => {
+                    error!("[ ORDERS-TAIL ] Error reading orders file: {}", error);
+                    self.finished = true;
+                    break;
+                }
+            };
+            if self.lines.is_end_marker(&self.line) {
+                self.finished = true;
+                break;
+            }
+            if read == 0 || !self.line.ends_with('\n') {
+                // sin lineas nuevas (o con una linea a medio escribir que queda en `line`), se espera a que se escriban
+                sleep(self.poll_interval);
+                continue;
+            }
+
+            let line = std::mem::take(&mut self.line);
+            match self.lines.push("[ ORDERS-TAIL ]", &line) {
+                Ok(Some(order)) => return Some(order),
+                Ok(None) => {}
+                Err(error) => {
+                    error!("[ ORDERS-TAIL ] {}", error.mensaje);
+                    self.finished = true;
+                }
+            }
+        }
+
+        None
+    }
+
+    fn name(&self) -> String {
+        "tail".to_string()
+    }
+}
+
+/// Pedidos de las lineas que se van leyendo de una fuente de pedidos que sigue escribiendose (ver `OrdersTail` y `TcpOrders`).
+///
+/// Cada linea completa se parsea como en `read_orders`, con su numero de linea como id.
+#[derive(Debug)]
+pub(crate) struct OrderLines {
+    /// Linea que indica que no se van a escribir mas pedidos
+    end_marker: String,
+
+    /// Capacidad de cada contenedor, para detectar los pedidos que nunca pueden completarse
+    pub(crate) capacities: ContainerCapacities,
+
+    /// Que hacer con los pedidos que superan la capacidad de algun contenedor
+    pub(crate) impossible_orders: ImpossibleOrders,
+
+    /// Recetas de las bebidas que se pueden pedir por nombre
+    pub(crate) recipes: RecipeBook,
+
+    /// Numero de la proxima linea
+    next_id: usize,
+
+    /// Numeros de linea de los pedidos descartados por `ImpossibleOrders::Skip`
+    skipped: Vec<usize>,
+}
+
+impl OrderLines {
+    /// Crea los pedidos de las lineas con la marca de fin de `V_MARCA_FIN`, y las capacidades, la politica de pedidos
+    /// imposibles y las recetas de las variables de entorno. Falla si el archivo de recetas tiene un formato incorrecto
+    pub(crate) fn from_env() -> Result<Self, ErrorCafeteria> {
+        Ok(OrderLines {
+            end_marker: Consts::v_marca_fin(),
+            capacities: ContainerCapacities::from_env(),
+            impossible_orders: Consts::i_pedidos_imposibles(),
+            recipes: RecipeBook::from_env()?,
+            next_id: 0,
+            skipped: Vec::new(),
+        })
+    }
+
+    /// Reemplaza la marca de fin, salvo que sea vacia
+    pub(crate) fn set_end_marker(&mut self, end_marker: &str) {
+        if !end_marker.trim().is_empty() {
+            self.end_marker = end_marker.trim().to_string();
+        }
+    }
+
+    /// Retorna true si la linea es la marca de fin
+    pub(crate) fn is_end_marker(&self, line: &str) -> bool {
+        line.trim() == self.end_marker
+    }
+
+    /// Parsea una linea completa.
+    ///
+    /// # Returns
+    /// * `Result<Option<Order>, ErrorCafeteria>` - El pedido de la linea, None si se descarto por `ImpossibleOrders::Skip`, o
+    ///   Err si la linea tiene un formato incorrecto.
+    pub(crate) fn push(
+        &mut self,
+        source: &str,
+        line: &str,
+    ) -> Result<Option<Order>, ErrorCafeteria> {
+        let id = self.next_id;
+        let order = parse_order(
+            id,
+            line,
+            &self.capacities,
+            self.impossible_orders,
+            &self.recipes,
+            &mut self.skipped,
+        )
+        .map_err(|error| {
+            ErrorCafeteria::new_of_type(
+                &format!("[Line#{}] {}", id, error.mensaje),
+                error.type_error,
+            )
+        })?;
+        self.next_id += 1;
+
+        if let Some(order) = &order {
+            debug!(
+                "{} | [Order#{:?}] NEW ORDER READ.\n                 Requeriments: {:?}",
+                source, order.id, order.ingredientes
+            );
+        }
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, OpenOptions},
+        io::Write,
+        sync::atomic::Ordering,
+    };
+
+    use super::*;
+    use crate::{cafeteria::Cafeteria, enums::OrderState, sync::thread};
+
+    /// Ejecuta la cafeteria leyendo `file` mientras otro thread le agrega cada parte de `parts`, y retorna el id y el estado
+    /// de los pedidos finalizados en el orden en que finalizaron
+    fn run_tailing(file: &str, parts: Vec<&'static str>) -> Vec<(i64, OrderState)> {
+        fs::write(file, "").unwrap();
+        let tail = OrdersTail::open(file)
+            .unwrap()
+            .with_end_marker("FIN")
+            .with_poll_interval(Duration::from_millis(10));
+
+        let writer = {
+            let file = file.to_string();
+            thread::spawn(move || {
+                let mut orders_file = OpenOptions::new().append(true).open(file).unwrap();
+                for part in parts {
+                    sleep(Duration::from_millis(50));
+                    orders_file.write_all(part.as_bytes()).unwrap();
+                }
+            })
+        };
+
+        let (sender, receiver) = crate::sync::mpsc::channel();
+        let cafeteria = Cafeteria::default().with_completed_orders(sender);
+        let running = thread::spawn(move || cafeteria.start_with_source(tail));
+
+        // el Receiver termina de iterarse cuando la Cafeteria se descarta al finalizar el thread
+        let received = receiver
+            .iter()
+            .map(|order| (order.id.load(Ordering::SeqCst), order.status))
+            .collect::<Vec<_>>();
+        running.join().unwrap().unwrap();
+        writer.join().unwrap();
+        fs::remove_file(file).unwrap();
+        received
+    }
+
+    #[test]
+    fn test1_lines_appended_while_running_are_processed_until_the_end_marker() {
+        let received = run_tailing(
+            "test_tail1.txt",
+            vec!["A5 M2\n", "A5 E1\nA1", "0 M1 D0\n", "FIN"],
+        );
+
+        assert_eq!(received.len(), 3);
+        assert!(received
+            .iter()
+            .all(|(_, status)| *status == OrderState::Completed));
+        // el pedido escrito en dos partes se leyo completo y recien se proceso luego del pedido del que depende
+        let position = |id| received.iter().position(|(order, _)| *order == id).unwrap();
+        assert!(position(0) < position(2));
+    }
+
+    #[test]
+    fn test2_an_incorrect_line_stops_the_reading_and_the_orders_read_are_processed() {
+        let received = run_tailing("test_tail2.txt", vec!["A5 M2\n", "Ax\n", "A5 E1\nFIN\n"]);
+
+        assert_eq!(received, vec![(0, OrderState::Completed)]);
+    }
+}
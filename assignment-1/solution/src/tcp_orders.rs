@@ -0,0 +1,191 @@
+use std::{
+    io::{BufRead, BufReader, ErrorKind},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use log::{error, info};
+
+use crate::{
+    conteiners::ContainerCapacities, enums::ImpossibleOrders, error_dispenser::ErrorCafeteria,
+    order::Order, recipes::RecipeBook, sync::sleep, tail_orders::OrderLines, traits::OrderSource,
+    utils::Consts,
+};
+
+/// Pedidos que llegan por TCP: cada cliente que se conecta envia pedidos de a una linea, con el mismo formato que el archivo
+/// de ordenes (ver `read_orders`), hasta que alguno envia la linea con la marca de fin.
+///
+/// Las conexiones se atienden de a una, en el orden en que llegan. Los ids de los pedidos son su numero de llegada, contando
+/// las lineas de todas las conexiones, y las dependencias (`D<id>`) se refieren a esos ids. Una linea con un formato incorrecto
+/// se descarta y no cuenta como pedido.
+#[derive(Debug)]
+pub struct TcpOrders {
+    /// Socket que acepta las conexiones de los clientes
+    listener: TcpListener,
+
+    /// Cada cuanto se revisa si hay conexiones o lineas nuevas
+    poll_interval: Duration,
+
+    /// Pedidos de las lineas recibidas
+    lines: OrderLines,
+
+    /// Conexion de la que se estan recibiendo pedidos, None mientras se espera la proxima
+    connection: Option<BufReader<TcpStream>>,
+
+    /// Linea a medio recibir de la conexion
+    line: Vec<u8>,
+
+    /// true luego de recibir la marca de fin
+    finished: bool,
+}
+
+impl TcpOrders {
+    /// Abre el socket en la direccion recibida, con la marca de fin y el intervalo de `V_MARCA_FIN` y `V_INTERVALO_LECTURA`,
+    /// y las capacidades, la politica de pedidos imposibles y las recetas de las variables de entorno (idem a `read_orders`)
+    ///
+    /// # Arguments
+    ///  * `address` - Direccion en la que se aceptan conexiones, por ejemplo `127.0.0.1:7878`.
+    /// # Returns
+    ///  * Si es Ok, `TcpOrders` - El socket listo para aceptar conexiones.
+    ///  * Si es Err, `ErrorCafeteria` debido a que no se pudo abrir el socket o a que el archivo de recetas tiene un formato
+    ///    incorrecto.
+    pub fn bind<A: ToSocketAddrs>(address: A) -> Result<Self, ErrorCafeteria> {
+        let listener = TcpListener::bind(address)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))?;
+        Ok(TcpOrders {
+            listener,
+            poll_interval: Duration::from_millis(Consts::v_intervalo_lectura()),
+            lines: OrderLines::from_env()?,
+            connection: None,
+            line: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Direccion en la que se aceptan conexiones, por ejemplo para conocer el puerto al abrir el socket en el puerto 0
+    pub fn local_addr(&self) -> Result<SocketAddr, ErrorCafeteria> {
+        self.listener
+            .local_addr()
+            .map_err(|x| ErrorCafeteria::new(&x.to_string()))
+    }
+
+    /// Reemplaza la linea que indica que no se van a enviar mas pedidos. Una marca vacia se ignora, ya que las lineas vacias
+    /// son pedidos sin ingredientes
+    pub fn with_end_marker(mut self, end_marker: &str) -> Self {
+        self.lines.set_end_marker(end_marker);
+        self
+    }
+
+    /// Reemplaza cada cuanto se revisa si hay conexiones o lineas nuevas
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Reemplaza las capacidades de los contenedores y que hacer con los pedidos que las superan
+    pub fn with_capacities(
+        mut self,
+        capacities: ContainerCapacities,
+        impossible_orders: ImpossibleOrders,
+    ) -> Self {
+        self.lines.capacities = capacities;
+        self.lines.impossible_orders = impossible_orders;
+        self
+    }
+
+    /// Reemplaza las recetas de las bebidas que se pueden pedir por nombre
+    pub fn with_recipes(mut self, recipes: RecipeBook) -> Self {
+        self.lines.recipes = recipes;
+        self
+    }
+
+    /// Espera la proxima linea de la conexion actual, o la proxima conexion si no hay ninguna abierta, como mucho durante
+    /// `poll_interval`. Si el cliente cierra la conexion, la ultima linea puede no terminar con un salto de linea.
+    ///
+    /// # Returns
+    /// * `Option<Order>` - El pedido de la linea recibida, o None si no llego ninguna linea completa, la linea tiene un formato
+    ///   incorrecto o es la marca de fin (en cuyo caso queda `finished` en true).
+    fn receive(&mut self) -> Option<Order> {
+        let connection = match self.connection.as_mut() {
+            Some(connection) => connection,
+            None => self.connection.insert(self.accept()?),
+        };
+
+        let closed = match connection.read_until(b'\n', &mut self.line) {
+            Ok(read) => read == 0,
+            // sin lineas nuevas (o con una linea a medio enviar que queda en `line`)
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return None
+            }
+            Err(error) => {
+                error!("[ ORDERS-TCP ] Error reading connection: {}", error);
+                true
+            }
+        };
+        if closed {
+            self.connection = None;
+        }
+        if closed && self.line.is_empty() {
+            return None;
+        }
+
+        let text = String::from_utf8_lossy(&self.line).into_owned();
+        self.line.clear();
+        if self.lines.is_end_marker(&text) {
+            self.finished = true;
+            return None;
+        }
+        self.lines
+            .push("[ ORDERS-TCP ]", &text)
+            .map_err(|error| error!("[ ORDERS-TCP ] {}", error.mensaje))
+            .ok()
+            .flatten()
+    }
+
+    /// Acepta la proxima conexion, esperando `poll_interval` si no hay ninguna.
+    ///
+    /// # Returns
+    /// * `Option<BufReader<TcpStream>>` - La conexion, o None si no llego ninguna o no se pudo configurar.
+    fn accept(&self) -> Option<BufReader<TcpStream>> {
+        let stream = match self.listener.accept() {
+            Ok((stream, address)) => {
+                info!("[ ORDERS-TCP ] New connection from {}", address);
+                stream
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                sleep(self.poll_interval);
+                return None;
+            }
+            Err(error) => {
+                error!("[ ORDERS-TCP ] Error accepting connection: {}", error);
+                return None;
+            }
+        };
+        if let Err(error) = stream.set_nonblocking(false).and_then(|_| {
+            stream.set_read_timeout(Some(self.poll_interval.max(Duration::from_millis(1))))
+        }) {
+            error!("[ ORDERS-TCP ] Error configuring connection: {}", error);
+            return None;
+        }
+        Some(BufReader::new(stream))
+    }
+}
+
+/// Los pedidos se retornan en el orden en que llegan, hasta recibir la marca de fin
+impl OrderSource for TcpOrders {
+    fn next_order(&mut self) -> Option<Order> {
+        while !self.finished {
+            if let Some(order) = self.receive() {
+                return Some(order);
+            }
+        }
+        None
+    }
+
+    fn name(&self) -> String {
+        self.local_addr()
+            .map(|address| format!("tcp {}", address))
+            .unwrap_or("tcp".to_string())
+    }
+}
@@ -1,6 +1,6 @@
-use crate::sync::{Condvar, MutexGuard};
+use crate::sync::MutexGuard;
 
-use crate::{conteiners_states::ContainersStates, order::Order};
+use crate::{conteiners_states::ContainersStates, error_dispenser::ErrorCafeteria, order::Order};
 
 /// Trait que deben implementar los contenedores para que los dispensers puedan aplicar los ingredientes
 /// a las ordenes.
@@ -18,12 +18,11 @@ pub trait ApplyContainer {
     fn apply_ingredient(&mut self, order: &mut Order);
 
     /// Segun el tipo de ingrediente del contenedor, se actualiza el estado del contenedor en ContainersStates
-    /// y notifica a los dispensers que esten esperando por el condvar.
+    /// y notifica a los dispensers que esten esperando por el contenedor (`ContainersStates::notify_waiters_of`).
     ///
     /// # Arguments
     /// * `states` - MutexGuard de ContainersStates
-    /// * `cvar` - Condvar del mutex de ContainersStates
-    fn update_and_notify_state(&mut self, states: MutexGuard<ContainersStates>, cvar: &Condvar);
+    fn update_and_notify_state(&mut self, states: MutexGuard<ContainersStates>);
 
     /// Segun el tipo de ingrediente del contenedor, settea el estado del contendor como tomado en el ContainersStates
     ///
@@ -54,3 +53,55 @@ pub trait ProcessRecharge {
     /// Representa el proceso de "recarga" del contenedor
     fn process_recharge(&mut self);
 }
+
+/// Trait que deben implementar las fuentes de pedidos para que la Cafetería los procese (ver `Cafeteria::start_with_source`).
+///
+/// De esta forma cualquier entrada de pedidos (un archivo, un socket TCP, la entrada estandar, el generador, un canal, etc) se
+/// conecta a la Cafetería de la misma manera, y la Cafetería se encarga de insertar los pedidos en la cola de pedidos esperando a
+/// los pedidos de los que dependen. Varias fuentes se combinan en una con `Sources`.
+pub trait OrderSource {
+    /// Retorna el proximo pedido, esperando a que llegue si la fuente todavia no lo tiene. Los ids de los pedidos de los que
+    /// depende (`D<id>`) son los de pedidos que la fuente retorno antes.
+    ///
+    /// # Returns
+    /// * `Option<Order>` - El proximo pedido, o None si la fuente ya no tiene mas pedidos.
+    fn next_order(&mut self) -> Option<Order>;
+
+    /// Se llama luego de que `next_order` retorna None, para saber si la fuente termino por un error (por ejemplo una linea con
+    /// formato incorrecto en el archivo de ordenes). Por defecto la fuente no tiene errores.
+    ///
+    /// # Returns
+    /// * `Result<(), ErrorCafeteria>` - Err si la fuente dejo de retornar pedidos por un error.
+    fn finish(&mut self) -> Result<(), ErrorCafeteria> {
+        Ok(())
+    }
+
+    /// Nombre de la fuente para los logs y las estadisticas
+    fn name(&self) -> String {
+        "custom".to_string()
+    }
+
+    /// Cantidad total de pedidos que va a retornar la fuente, si se conoce de antemano (como en el generador). Asi el
+    /// SYSTEM-ALERT la conoce desde el comienzo; por defecto recien se conoce cuando la fuente ya no tiene mas pedidos.
+    fn total_orders(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<S: OrderSource + ?Sized> OrderSource for Box<S> {
+    fn next_order(&mut self) -> Option<Order> {
+        (**self).next_order()
+    }
+
+    fn finish(&mut self) -> Result<(), ErrorCafeteria> {
+        (**self).finish()
+    }
+
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn total_orders(&self) -> Option<usize> {
+        (**self).total_orders()
+    }
+}
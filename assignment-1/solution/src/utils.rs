@@ -2,6 +2,11 @@ use std::env;
 
 use log::LevelFilter;
 
+use crate::{
+    enums::{ImpossibleOrders, IngredientType, Scheduler},
+    service_time::ServiceTime,
+};
+
 /// Constantes definidas mediante variables de entorno.
 /// Expresado en gramos, pudiendo ser integer or float.
 ///
@@ -78,6 +83,262 @@ impl Consts {
             .unwrap_or(1000.0)
     }
 
+    /// Tasa de llegada de los pedidos del generador obtenida de la variable de entorno T_TASA_PEDIDOS.
+    /// Expresada en pedidos por segundo, pudiendo ser integer or float.
+    /// Por defecto 1.0
+    pub fn t_tasa_pedidos() -> f32 {
+        env::var("T_TASA_PEDIDOS")
+            .unwrap_or("1.0".to_string())
+            .parse::<f32>()
+            .unwrap_or(1.0)
+    }
+
+    /// Tiempo durante el cual el generador recibe pedidos, obtenido de la variable de entorno D_DURACION_GENERADOR.
+    /// Expresado en segundos.
+    /// Por defecto 60
+    pub fn d_duracion_generador() -> u64 {
+        env::var("D_DURACION_GENERADOR")
+            .unwrap_or("60".to_string())
+            .parse::<u64>()
+            .unwrap_or(60)
+    }
+
+    /// Probabilidad de que un pedido del generador requiera cada ingrediente, obtenida de la variable de entorno
+    /// P_PROBABILIDAD_INGREDIENTE. Entre 0.0 y 1.0.
+    /// Por defecto 0.75
+    pub fn p_probabilidad_ingrediente() -> f32 {
+        env::var("P_PROBABILIDAD_INGREDIENTE")
+            .unwrap_or("0.75".to_string())
+            .parse::<f32>()
+            .unwrap_or(0.75)
+    }
+
+    /// Cantidad maxima de cada ingrediente en los pedidos del generador, obtenida de la variable de entorno Q_MAX_GRAMOS_PEDIDO.
+    /// Expresado en gramos, pudiendo ser integer or float.
+    /// Por defecto 10.0
+    pub fn q_max_gramos_pedido() -> f32 {
+        env::var("Q_MAX_GRAMOS_PEDIDO")
+            .unwrap_or("10.0".to_string())
+            .parse::<f32>()
+            .unwrap_or(10.0)
+    }
+
+    /// Tiempo que tarda en calentarse el agua antes de aceptar pedidos, obtenido de la variable de entorno W_CALENTAR_AGUA.
+    /// Expresado en segundos, pudiendo ser integer or float. Con 0.0 no se calienta el agua.
+    /// Por defecto 2.0
+    pub fn w_calentar_agua() -> f32 {
+        env::var("W_CALENTAR_AGUA")
+            .unwrap_or("2.0".to_string())
+            .parse::<f32>()
+            .unwrap_or(2.0)
+    }
+
+    /// Tiempo que tarda en molerse la primera tanda de cafe antes de aceptar pedidos, obtenido de la variable de entorno
+    /// W_MOLER_CAFE. Expresado en segundos, pudiendo ser integer or float. Con 0.0 no se muele la primera tanda.
+    /// Por defecto 3.0
+    pub fn w_moler_cafe() -> f32 {
+        env::var("W_MOLER_CAFE")
+            .unwrap_or("3.0".to_string())
+            .parse::<f32>()
+            .unwrap_or(3.0)
+    }
+
+    /// Cantidad de decimales con los que los contenedores redondean las cantidades en gramos, obtenida de la variable de entorno
+    /// R_DECIMALES_GRAMOS. Como maximo 6.
+    /// Por defecto 3
+    pub fn r_decimales_gramos() -> u32 {
+        env::var("R_DECIMALES_GRAMOS")
+            .unwrap_or("3".to_string())
+            .parse::<u32>()
+            .unwrap_or(3)
+    }
+
+    /// Plazo para que los dispensers terminen los pedidos luego de la señal de apagado, obtenido de la variable de entorno
+    /// F_PLAZO_APAGADO. Expresado en segundos, pudiendo ser integer or float. Con 0.0 no hay plazo.
+    /// Por defecto 0.0
+    pub fn f_plazo_apagado() -> f32 {
+        env::var("F_PLAZO_APAGADO")
+            .unwrap_or("0.0".to_string())
+            .parse::<f32>()
+            .unwrap_or(0.0)
+    }
+
+    /// Tiempo que la cola de pedidos puede estar llena (con un pedido por dispenser) antes de que el SYSTEM-ALERT alerte que los
+    /// dispensers estan saturados, obtenido de la variable de entorno U_SATURACION_COLA. Expresado en segundos, pudiendo ser
+    /// integer or float. Con 0.0 no se alerta.
+    /// Por defecto 5.0
+    pub fn u_saturacion_cola() -> f32 {
+        env::var("U_SATURACION_COLA")
+            .unwrap_or("5.0".to_string())
+            .parse::<f32>()
+            .unwrap_or(5.0)
+    }
+
+    /// Forma de repartir los pedidos obtenida de la variable de entorno S_SCHEDULER: `pipeline` para un worker por
+    /// ingrediente o `dispensers` para que cada dispenser procese el pedido completo.
+    /// Por defecto dispensers
+    pub fn s_scheduler() -> Scheduler {
+        match env::var("S_SCHEDULER").as_deref() {
+            Ok("pipeline") => Scheduler::Pipeline,
+            _ => Scheduler::Dispensers,
+        }
+    }
+
+    /// Que hacer con los pedidos del archivo que superan la capacidad de algun contenedor, obtenido de la variable de entorno
+    /// I_PEDIDOS_IMPOSIBLES: `descartar` para no procesarlos o `marcar` para finalizarlos como `ExceedsCapacity`.
+    /// Por defecto marcar
+    pub fn i_pedidos_imposibles() -> ImpossibleOrders {
+        match env::var("I_PEDIDOS_IMPOSIBLES").as_deref() {
+            Ok("descartar") => ImpossibleOrders::Skip,
+            _ => ImpossibleOrders::Mark,
+        }
+    }
+
+    /// Prefijo del nombre de los threads de la cafeteria, obtenido de la variable de entorno H_PREFIJO_THREADS.
+    /// Por defecto sin prefijo
+    pub fn h_prefijo_threads() -> String {
+        env::var("H_PREFIJO_THREADS").unwrap_or_default()
+    }
+
+    /// Prioridad (nice de Linux, de -20 a 19) de los threads de la cafeteria, obtenida de la variable de entorno H_PRIORIDAD_THREADS.
+    /// Por defecto no se cambia la prioridad
+    pub fn h_prioridad_threads() -> Option<i32> {
+        env::var("H_PRIORIDAD_THREADS")
+            .ok()
+            .and_then(|nice| nice.parse::<i32>().ok())
+            .map(|nice| nice.clamp(-20, 19))
+    }
+
+    /// Nucleos en los que se fijan los threads de la cafeteria, separados por coma (por ejemplo `0,2,4`), obtenidos de la
+    /// variable de entorno H_NUCLEOS_THREADS.
+    /// Por defecto no se fijan los threads
+    pub fn h_nucleos_threads() -> Vec<usize> {
+        env::var("H_NUCLEOS_THREADS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|core| core.trim().parse::<usize>().ok())
+            .collect()
+    }
+
+    /// Archivo CSV en el que el SYSTEM-ALERT agrega cada muestra periodica, obtenido de la variable de entorno O_ALERTAS_CSV.
+    /// Por defecto no se escribe el CSV
+    pub fn o_alertas_csv() -> Option<String> {
+        env::var("O_ALERTAS_CSV")
+            .ok()
+            .filter(|path| !path.is_empty())
+    }
+
+    /// Archivo de recetas con las bebidas que se pueden pedir por nombre (ver `RecipeBook`), obtenido de la variable de
+    /// entorno B_RECETAS.
+    /// Por defecto recetas.toml, y si no existe no hay recetas
+    pub fn b_recetas() -> String {
+        env::var("B_RECETAS")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .unwrap_or("recetas.toml".to_string())
+    }
+
+    /// Linea que indica el fin de un archivo de ordenes que se lee mientras se escribe (ver `OrdersTail`), obtenida de la
+    /// variable de entorno V_MARCA_FIN.
+    /// Por defecto FIN
+    pub fn v_marca_fin() -> String {
+        env::var("V_MARCA_FIN")
+            .ok()
+            .map(|marker| marker.trim().to_string())
+            .filter(|marker| !marker.is_empty())
+            .unwrap_or("FIN".to_string())
+    }
+
+    /// Cada cuantos milisegundos se revisa si el archivo de ordenes que se lee mientras se escribe tiene lineas nuevas,
+    /// obtenido de la variable de entorno V_INTERVALO_LECTURA.
+    /// Por defecto 500
+    pub fn v_intervalo_lectura() -> u64 {
+        env::var("V_INTERVALO_LECTURA")
+            .unwrap_or("500".to_string())
+            .parse::<u64>()
+            .unwrap_or(500)
+    }
+
+    /// Semilla de las decisiones de la ejecucion en un unico thread (ver `Replay`), obtenida de la variable de entorno J_SEMILLA.
+    /// Por defecto 0
+    pub fn j_semilla() -> u64 {
+        env::var("J_SEMILLA")
+            .unwrap_or("0".to_string())
+            .parse::<u64>()
+            .unwrap_or(0)
+    }
+
+    /// Archivo con las decisiones grabadas que repite la ejecucion en un unico thread, obtenido de la variable de entorno
+    /// J_REPRODUCIR_DECISIONES.
+    /// Por defecto ninguno, y las decisiones se sortean con `J_SEMILLA`
+    pub fn j_reproducir_decisiones() -> Option<String> {
+        env::var("J_REPRODUCIR_DECISIONES")
+            .ok()
+            .filter(|path| !path.is_empty())
+    }
+
+    /// Archivo en el que se graban las decisiones de la ejecucion en un unico thread, obtenido de la variable de entorno
+    /// J_GRABAR_DECISIONES.
+    /// Por defecto no se graban
+    pub fn j_grabar_decisiones() -> Option<String> {
+        env::var("J_GRABAR_DECISIONES")
+            .ok()
+            .filter(|path| !path.is_empty())
+    }
+
+    /// Cantidad maxima de pedidos con agua caliente que pueden procesarse a la vez, obtenida de la variable de entorno K_LIMITE_AGUA.
+    /// Por defecto 0, sin limite
+    pub fn k_limite_agua() -> usize {
+        env::var("K_LIMITE_AGUA")
+            .unwrap_or("0".to_string())
+            .parse::<usize>()
+            .unwrap_or(0)
+    }
+
+    /// Cantidad maxima de pedidos con cafe molido que pueden procesarse a la vez, obtenida de la variable de entorno K_LIMITE_CAFE_MOLIDO.
+    /// Por defecto 0, sin limite
+    pub fn k_limite_cafe_molido() -> usize {
+        env::var("K_LIMITE_CAFE_MOLIDO")
+            .unwrap_or("0".to_string())
+            .parse::<usize>()
+            .unwrap_or(0)
+    }
+
+    /// Cantidad maxima de pedidos con espuma de leche que pueden procesarse a la vez, obtenida de la variable de entorno K_LIMITE_ESPUMA_LECHE.
+    /// Por defecto 0, sin limite
+    pub fn k_limite_espuma_leche() -> usize {
+        env::var("K_LIMITE_ESPUMA_LECHE")
+            .unwrap_or("0".to_string())
+            .parse::<usize>()
+            .unwrap_or(0)
+    }
+
+    /// Cantidad maxima de pedidos con cacao que pueden procesarse a la vez, obtenida de la variable de entorno K_LIMITE_CACAO.
+    /// Por defecto 0, sin limite
+    pub fn k_limite_cacao() -> usize {
+        env::var("K_LIMITE_CACAO")
+            .unwrap_or("0".to_string())
+            .parse::<usize>()
+            .unwrap_or(0)
+    }
+
+    /// Distribucion de los segundos por gramo que tarda en aplicarse el ingrediente, obtenida de la variable de entorno
+    /// Z_APLICACION_AGUA, Z_APLICACION_CAFE_MOLIDO, Z_APLICACION_ESPUMA_LECHE o Z_APLICACION_CACAO (ver `ServiceTime`).
+    /// Por defecto, o si la distribucion es incorrecta, fijo en `SEGS_POR_GRAMO`
+    pub fn z_aplicacion(tipo: IngredientType) -> ServiceTime {
+        let variable = match tipo {
+            IngredientType::Agua => "Z_APLICACION_AGUA",
+            IngredientType::CafeMolido => "Z_APLICACION_CAFE_MOLIDO",
+            IngredientType::EspumaLeche => "Z_APLICACION_ESPUMA_LECHE",
+            IngredientType::Cacao => "Z_APLICACION_CACAO",
+            _ => return ServiceTime::default(),
+        };
+        env::var(variable)
+            .ok()
+            .and_then(|value| ServiceTime::parse(&value))
+            .unwrap_or_default()
+    }
+
     /// Cantidad de threads dispensers a invocar.
     /// Como maximo se puede tener 1024 dispensers (`utils.rs: LIMIT_DISPENSERS`).
     /// Por defecto se invocan 8 dispensers.
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use log::info;
+
+use crate::{
+    conteiners::Conteiners,
+    enums::IngredientType,
+    error_dispenser::ErrorCafeteria,
+    sync::thread::{Builder, JoinHandle},
+    sync::{reloj, sleep, Arc},
+    utils::Consts,
+};
+
+/// Etapa del calentamiento de la maquina, con el tiempo que tarda
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WarmUpStage {
+    /// Calentar el agua del contenedor de agua caliente
+    HeatWater(Duration),
+
+    /// Moler la primera tanda de granos del contenedor de cafe molido
+    GrindCoffee(Duration),
+}
+
+impl WarmUpStage {
+    /// Contenedor que queda tomado durante la etapa
+    pub fn container(&self) -> IngredientType {
+        match self {
+            WarmUpStage::HeatWater(_) => IngredientType::Agua,
+            WarmUpStage::GrindCoffee(_) => IngredientType::CafeMolido,
+        }
+    }
+
+    /// Tiempo que tarda la etapa
+    pub fn duration(&self) -> Duration {
+        match self {
+            WarmUpStage::HeatWater(duration) | WarmUpStage::GrindCoffee(duration) => *duration,
+        }
+    }
+}
+
+/// Etapa terminada, con el tiempo que tardo (incluyendo la espera por el lock de su contenedor)
+pub type FinishedStage = (WarmUpStage, Duration);
+
+/// Calentamiento de la maquina antes de aceptar pedidos. Cada etapa la ejecuta un worker en un thread aparte, con el
+/// lock de su contenedor tomado, y los pedidos recien se insertan cuando terminaron todas.
+///
+/// Por defecto no tiene etapas, asi los tests que no lo necesitan no lo esperan.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WarmUp {
+    /// Etapas a ejecutar
+    pub stages: Vec<WarmUpStage>,
+}
+
+impl WarmUp {
+    /// Crea el calentamiento a partir de las variables de entorno `W_CALENTAR_AGUA` y `W_MOLER_CAFE`.
+    /// Las etapas con tiempo 0 no se ejecutan.
+    pub fn from_env() -> Self {
+        let duration = |secs: f32| Duration::from_secs_f32(secs.max(0.0));
+        WarmUp::default()
+            .with_stage(WarmUpStage::HeatWater(duration(Consts::w_calentar_agua())))
+            .with_stage(WarmUpStage::GrindCoffee(duration(Consts::w_moler_cafe())))
+    }
+
+    /// Agrega una etapa al calentamiento, si tarda algo
+    pub fn with_stage(mut self, stage: WarmUpStage) -> Self {
+        if !stage.duration().is_zero() {
+            self.stages.push(stage);
+        }
+        self
+    }
+
+    /// Ejecuta cada etapa en un worker y espera a que terminen todas, reportando en el log cuanto tardo cada una.
+    ///
+    /// # Arguments
+    ///  * `containers` - `Arc<Conteiners>`: Contenedores de la cafeteria.
+    /// # Returns
+    /// * `Result<Vec<FinishedStage>, ErrorCafeteria>`:
+    ///    * Si es Ok, cada etapa con el tiempo que tardo.
+    ///    * Si es Err, no se pudo crear algun worker, fallo al tomar el lock de un contenedor o el join de un worker.
+    pub fn run(&self, containers: &Arc<Conteiners>) -> Result<Vec<FinishedStage>, ErrorCafeteria> {
+        if self.stages.is_empty() {
+            return Ok(Vec::new());
+        }
+        info!("[ SYSTEM-ALERT ] Warming up: {:?}", self.stages);
+        let start = reloj().ahora();
+
+        let workers = self
+            .stages
+            .iter()
+            .map(|stage| run_stage(*stage, containers.clone()))
+            .collect::<Result<Vec<_>, ErrorCafeteria>>()?;
+
+        let finished = workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .map_err(|_| ErrorCafeteria::new("[ WARM-UP ]: FAILED TO JOIN THREAD"))?
+            })
+            .collect::<Result<Vec<_>, ErrorCafeteria>>()?;
+
+        info!(
+            "[ SYSTEM-ALERT ] Warm-up finished in {:?}. Accepting orders",
+            reloj().ahora().saturating_duration_since(start)
+        );
+        Ok(finished)
+    }
+}
+
+/// Spawnea el worker de una etapa del calentamiento: toma el lock del contenedor de la etapa y lo retiene el tiempo que tarda.
+fn run_stage(
+    stage: WarmUpStage,
+    containers: Arc<Conteiners>,
+) -> Result<JoinHandle<Result<FinishedStage, ErrorCafeteria>>, ErrorCafeteria> {
+    Builder::new()
+        .name(format!("[ WARM-UP {:?} ]", stage.container()))
+        .spawn(move || {
+            let start = reloj().ahora();
+            let _container = containers.lock_for(stage.container())?;
+            sleep(stage.duration());
+
+            let elapsed = reloj().ahora().saturating_duration_since(start);
+            info!("[ SYSTEM-ALERT ] Warm-up {:?} done in {:?}", stage, elapsed);
+            Ok((stage, elapsed))
+        })
+        .map_err(|x| ErrorCafeteria::new(&x.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_stages_without_duration_are_skipped() {
+        let warm_up = WarmUp::default()
+            .with_stage(WarmUpStage::HeatWater(Duration::ZERO))
+            .with_stage(WarmUpStage::GrindCoffee(Duration::from_secs(3)));
+
+        assert_eq!(
+            warm_up.stages,
+            vec![WarmUpStage::GrindCoffee(Duration::from_secs(3))]
+        );
+        assert!(WarmUp::default()
+            .run(&Arc::new(Conteiners::default()))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test2_run_waits_for_every_stage_holding_its_container() {
+        let containers = Arc::new(Conteiners::default());
+        let warm_up = WarmUp::default()
+            .with_stage(WarmUpStage::HeatWater(Duration::from_secs(2)))
+            .with_stage(WarmUpStage::GrindCoffee(Duration::from_secs(5)));
+
+        let start = reloj().ahora();
+        let finished = warm_up.run(&containers).unwrap();
+
+        assert!(reloj().ahora().saturating_duration_since(start) >= Duration::from_secs(5));
+        assert_eq!(finished.len(), 2);
+        assert!(finished
+            .iter()
+            .all(|(stage, elapsed)| *elapsed >= stage.duration()));
+        assert_eq!(
+            containers
+                .contention
+                .by_resource()
+                .unwrap()
+                .iter()
+                .map(|(_, stats)| stats.acquisitions)
+                .sum::<u32>(),
+            2
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Fields};
+
+/// Implementa `compartido::codificable::Codificable` para un struct de mensaje. El tipo del mensaje
+/// se indica con `#[codificable(tipo = ...)]` y los campos se codifican en el orden en que se declaran,
+/// cada uno con su implementacion de `compartido::codificable::Campo`
+#[proc_macro_derive(Codificable, attributes(codificable))]
+pub fn derive_codificable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expandir(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expandir(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let nombre = &input.ident;
+    let tipo = tipo_mensaje(&input)?;
+
+    let campos = match &input.data {
+        Data::Struct(datos) => &datos.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                nombre,
+                "Codificable solo se puede derivar en structs",
+            ))
+        }
+    };
+
+    let (escribir, leer) = match campos {
+        Fields::Named(campos) => {
+            let nombres: Vec<_> = campos.named.iter().map(|x| &x.ident).collect();
+            let etiquetas: Vec<String> = nombres
+                .iter()
+                .map(|x| x.as_ref().map(|x| x.to_string()).unwrap_or_default())
+                .collect();
+            (
+                quote! {
+                    #( ::compartido::codificable::Campo::escribir(&self.#nombres, bytes); )*
+                },
+                quote! {
+                    Ok(#nombre { #( #nombres: lector.leer(#etiquetas)?, )* })
+                },
+            )
+        }
+        Fields::Unit => (
+            quote! { let _ = bytes; },
+            quote! {
+                let _ = lector;
+                Ok(#nombre)
+            },
+        ),
+        Fields::Unnamed(_) => {
+            return Err(Error::new_spanned(
+                nombre,
+                "Codificable necesita campos con nombre para reportar los errores",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl ::compartido::codificable::Codificable for #nombre {
+            const TIPO: u8 = (#tipo) as u8;
+
+            fn escribir_campos(&self, bytes: &mut ::std::vec::Vec<u8>) {
+                #escribir
+            }
+
+            fn leer_campos(
+                lector: &mut ::compartido::codificable::Lector<'_>,
+            ) -> ::std::result::Result<Self, ::compartido::error_mensaje::ErrorMensaje> {
+                #leer
+            }
+        }
+    })
+}
+
+/// Expresion del atributo `#[codificable(tipo = ...)]`, obligatorio
+fn tipo_mensaje(input: &DeriveInput) -> Result<Expr, Error> {
+    let mut tipo = None;
+    for atributo in input
+        .attrs
+        .iter()
+        .filter(|x| x.path().is_ident("codificable"))
+    {
+        atributo.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tipo") {
+                tipo = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("se esperaba `tipo = ...`"))
+            }
+        })?;
+    }
+    tipo.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "falta el tipo del mensaje: #[codificable(tipo = ...)]",
+        )
+    })
+}
@@ -0,0 +1,42 @@
+#![no_main]
+
+use compartido::codec::id_cafetera_from_bytes;
+use compartido::codificable::Codificable;
+use compartido::mensajes_cafetera::{
+    AltaCuenta, Autenticado, BajaCuenta, Conectar, Confirmado, Consultar, Desafio, Desconectar,
+    Desregistrar, Drenar, Error, Estado, Fallido, MensajeCafetera, OkeyToCafetera, Ping, Registrar,
+    Restar, Sondeo, Sumar, Transferir,
+};
+use libfuzzer_sys::fuzz_target;
+
+// Cualquier paquete udp que llegue a un nodo o a una cafetera debe interpretarse o rechazarse con
+// error, nunca terminar el proceso
+fuzz_target!(|data: &[u8]| {
+    let _id_cafetera = id_cafetera_from_bytes(data);
+    let Some(tipo) = data.first() else {
+        return;
+    };
+    let _res = match MensajeCafetera::from_bytes(*tipo) {
+        MensajeCafetera::SUMAR => Sumar::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::RESTAR => Restar::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::PING => Ping::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::OKEY => OkeyToCafetera::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::ERROR => Error::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::DESCONECTAR => Desconectar::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::CONECTAR => Conectar::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::SONDEO => Sondeo::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::CONFIRMADO => Confirmado::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::FALLIDO => Fallido::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::REGISTRAR => Registrar::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::DESREGISTRAR => Desregistrar::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::ESTADO => Estado::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::TRANSFERIR => Transferir::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::ALTA => AltaCuenta::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::BAJA => BajaCuenta::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::CONSULTAR => Consultar::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::DRENAR => Drenar::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::DESAFIO => Desafio::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::AUTENTICADO => Autenticado::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeCafetera::DESCONOCIDO => return,
+    };
+});
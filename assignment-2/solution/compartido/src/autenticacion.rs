@@ -0,0 +1,122 @@
+//! Autenticacion de los comandos de administracion (CONECTAR, DESCONECTAR, ESTADO y DRENAR) con el
+//! secreto compartido del cluster (cluster.secreto_admin). Antes de cada comando la herramienta le pide
+//! un desafio al nodo, y le envia el comando dentro de un mensaje Autenticado con la firma
+//! HMAC-SHA256 del desafio y del comando. El nodo usa cada desafio una sola vez, por lo que una firma
+//! capturada no sirve para otro comando. Mientras el secreto este vacio no se pueden enviar comandos
+//! de administracion y los nodos los rechazan
+
+use std::io;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::codec::MAX_BYTES_MENSAJE;
+use crate::codificable::Codificable;
+use crate::config::{config, ID_CAFETERA_ADMINISTRACION};
+use crate::mensajes_cafetera::{Autenticado, Desafio, MensajeCafetera, MensajeCafeteraBytes};
+
+/// Error de los comandos de administracion mientras no se configure el secreto
+pub const SIN_SECRETO: &str =
+    "cluster.secreto_admin no esta configurado (config.toml o TP2_CLUSTER_SECRETO_ADMIN)";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// HMAC-SHA256 con el secreto del desafio (en big endian) seguido de los bytes del comando
+fn hmac_comando(secreto: &str, desafio: u64, comando: &[u8]) -> HmacSha256 {
+    let mut hmac = HmacSha256::new_from_slice(secreto.as_bytes())
+        .expect("HMAC acepta claves de cualquier largo");
+    hmac.update(&desafio.to_be_bytes());
+    hmac.update(comando);
+    hmac
+}
+
+/// Firma de un comando de administracion: el HMAC-SHA256 con el secreto del desafio (en big endian)
+/// seguido de los bytes del comando
+pub fn firmar(secreto: &str, desafio: u64, comando: &[u8]) -> Vec<u8> {
+    hmac_comando(secreto, desafio, comando)
+        .finalize()
+        .into_bytes()
+        .to_vec()
+}
+
+/// true si la firma corresponde al desafio y al comando. La comparacion es en tiempo constante, para
+/// que el tiempo de respuesta no indique cuantos bytes coinciden. Con el secreto vacio ninguna firma
+/// es valida
+pub fn firma_valida(secreto: &str, desafio: u64, comando: &[u8], firma: &[u8]) -> bool {
+    !secreto.is_empty()
+        && hmac_comando(secreto, desafio, comando)
+            .verify_slice(firma)
+            .is_ok()
+}
+
+/// Envia un comando de administracion al nodo en el destino: pide primero un desafio (esperando la
+/// respuesta como maximo `espera`) y envia el comando firmado con cluster.secreto_admin. Falla sin
+/// enviar nada si el secreto no esta configurado. Deja el socket con `espera` como timeout de lectura
+pub fn enviar_comando_admin(
+    socket: &UdpSocket,
+    comando: &[u8],
+    destino: &str,
+    espera: Duration,
+) -> io::Result<()> {
+    let secreto = &config().cluster.secreto_admin;
+    if secreto.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, SIN_SECRETO));
+    }
+    socket.set_read_timeout(Some(espera))?;
+    socket.send_to(
+        &Desafio::de_cafetera(ID_CAFETERA_ADMINISTRACION).to_bytes(),
+        destino,
+    )?;
+    let mut buffer = [0u8; MAX_BYTES_MENSAJE];
+    let desafio = loop {
+        // se ignoran las respuestas atrasadas de comandos anteriores
+        let (cantidad_leida, _) = socket.recv_from(&mut buffer)?;
+        if let Some(MensajeCafetera::DESAFIO) =
+            buffer.first().map(|x| MensajeCafetera::from_bytes(*x))
+        {
+            if let Ok(desafio) = Desafio::from_bytes(&buffer[..cantidad_leida]) {
+                break desafio.desafio;
+            }
+        }
+    };
+    let autenticado = Autenticado::de_cafetera(ID_CAFETERA_ADMINISTRACION)
+        .con_comando(comando.to_vec(), firmar(secreto, desafio, comando));
+    socket.send_to(&autenticado.to_bytes(), destino)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod autenticacion_test {
+    use super::*;
+
+    #[test]
+    fn la_firma_es_el_hmac_sha256_del_desafio_y_del_comando() {
+        // RFC 4231, caso 2: "what do ya want for nothing?" partido en desafio y comando
+        let desafio = u64::from_be_bytes(*b"what do ");
+        let firma = firmar("Jefe", desafio, b"ya want for nothing?");
+        let hex: String = firma.iter().map(|x| format!("{:02x}", x)).collect();
+        assert_eq!(
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            hex
+        );
+    }
+
+    #[test]
+    fn la_firma_solo_vale_para_su_desafio_comando_y_secreto() {
+        let firma = firmar("secreto", 7, &[5, 0]);
+        assert!(firma_valida("secreto", 7, &[5, 0], &firma));
+        assert!(!firma_valida("secreto", 8, &[5, 0], &firma));
+        assert!(!firma_valida("secreto", 7, &[6, 0], &firma));
+        assert!(!firma_valida("otro", 7, &[5, 0], &firma));
+        assert!(!firma_valida("secreto", 7, &[5, 0], &firma[..31]));
+    }
+
+    #[test]
+    fn sin_secreto_ninguna_firma_es_valida() {
+        let firma = firmar("", 7, &[5, 0]);
+        assert!(!firma_valida("", 7, &[5, 0], &firma));
+        assert!(!firma_valida("", 7, &[5, 0], &[]));
+    }
+}
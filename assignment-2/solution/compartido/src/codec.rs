@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::config;
+use crate::ids::IdCafetera;
+
+/// Formato en el que se codifica el cuerpo de los mensajes Codificable. El primer byte siempre es el
+/// tipo de mensaje, asi los receptores despachan igual con cualquiera de los dos. Se elige con
+/// cluster.codec ("bytes" o "cbor")
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    /// campos de largo fijo en big endian, en el orden en que se declaran en el struct
+    #[default]
+    Bytes,
+    /// campos con nombre en CBOR, se pueden agregar campos opcionales sin romper a los receptores
+    Cbor,
+}
+
+/// Codec con el que se envian y reciben todos los mensajes, el de cluster.codec. Todos los procesos
+/// (sucursales, nodos y desconexion) deben usar la misma configuracion
+pub fn codec() -> Codec {
+    config().cluster.codec
+}
+
+/// Tamaño maximo de un mensaje de cafetera con cualquiera de los codecs, para dimensionar los buffers
+/// de recepcion
+pub const MAX_BYTES_MENSAJE: usize = 128;
+
+/// Unico campo que se lee de un mensaje de cafetera en CBOR para saber quien lo envio
+#[derive(Deserialize)]
+struct Remitente {
+    #[serde(default)]
+    id_cafetera: IdCafetera,
+}
+
+/// Id de la cafetera que envio el mensaje, sin decodificar el resto. None si el mensaje esta incompleto
+pub fn id_cafetera_from_bytes(bytes: &[u8]) -> Option<IdCafetera> {
+    id_cafetera_con_codec(bytes, codec())
+}
+
+/// Id de la cafetera que envio el mensaje codificado con el codec indicado
+pub fn id_cafetera_con_codec(bytes: &[u8], codec: Codec) -> Option<IdCafetera> {
+    match codec {
+        // todos los mensajes de cafetera empiezan con el id de la cafetera
+        Codec::Bytes => bytes.get(1).copied().map(IdCafetera),
+        Codec::Cbor => ciborium::from_reader::<Remitente, _>(bytes.get(1..)?)
+            .ok()
+            .map(|x| x.id_cafetera),
+    }
+}
+
+#[cfg(test)]
+mod codec_test {
+    use serde::Serialize;
+
+    use super::*;
+    use crate::codificable::Codificable;
+    use crate::ids::IdCuenta;
+    use crate::mensajes_cafetera::*;
+
+    /// Codifica y decodifica el mensaje con cada codec, y verifica que vuelva a codificarse igual
+    fn ida_y_vuelta<M: MensajeCafeteraBytes>(mensaje: M) {
+        for codec in [Codec::Bytes, Codec::Cbor] {
+            let bytes = mensaje.codificar(codec);
+            assert_eq!(Some(&M::TIPO), bytes.first());
+            assert!(bytes.len() <= MAX_BYTES_MENSAJE, "{:?} {:?}", codec, bytes);
+            assert_eq!(
+                Some(mensaje.get_id_cafetera()),
+                id_cafetera_con_codec(&bytes, codec)
+            );
+
+            let recibido = M::decodificar(&bytes, codec).unwrap();
+            assert_eq!(mensaje.get_id_cafetera(), recibido.get_id_cafetera());
+            assert_eq!(mensaje.get_id_cuenta(), recibido.get_id_cuenta());
+            assert_eq!(
+                mensaje.get_cantidad_modificar(),
+                recibido.get_cantidad_modificar()
+            );
+            assert_eq!(bytes, recibido.codificar(codec));
+        }
+    }
+
+    #[test]
+    fn todos_los_mensajes_de_cafetera_van_y_vuelven_con_ambos_codecs() {
+        let cafetera = IdCafetera(3);
+        let cuenta = IdCuenta(7);
+        let mut sumar = Sumar::new(cafetera, cuenta, 150);
+        sumar.id_pedido = 1000003;
+        ida_y_vuelta(sumar);
+        let mut restar = Restar::new(cafetera, cuenta, 150);
+        restar.id_pedido = 1000003;
+        ida_y_vuelta(restar);
+        ida_y_vuelta(Ping::de_cafetera(cafetera));
+        ida_y_vuelta(OkeyToCafetera::new(cafetera, cuenta, 0).con_id_pedido(1000003));
+        ida_y_vuelta(
+            Error::new(cafetera, cuenta, 0)
+                .con_motivo(MotivoError::TRANSACCIONDESCONOCIDA)
+                .con_id_pedido(1000003),
+        );
+        ida_y_vuelta(Desconectar::de_cafetera(cafetera).con_secuencia(42));
+        ida_y_vuelta(Conectar::de_cafetera(cafetera).con_secuencia(42));
+        ida_y_vuelta(Sondeo::de_cafetera(cafetera));
+        let mut confirmado = Confirmado::new(cafetera, cuenta, 150);
+        confirmado.id_pedido = 1000003;
+        ida_y_vuelta(confirmado);
+        let mut fallido = Fallido::new(cafetera, cuenta, 150);
+        fallido.id_pedido = 1000003;
+        ida_y_vuelta(fallido);
+        ida_y_vuelta(Registrar::de_cafetera(cafetera));
+        ida_y_vuelta(Desregistrar::de_cafetera(cafetera));
+        ida_y_vuelta(Estado::de_cafetera(cafetera));
+        let mut transferir = Transferir::new(cafetera, cuenta, 150);
+        transferir.id_pedido = 1000003;
+        transferir.id_cuenta_destino = IdCuenta(4);
+        ida_y_vuelta(transferir);
+        ida_y_vuelta(AltaCuenta::new(cafetera, cuenta, 0));
+        ida_y_vuelta(BajaCuenta::new(cafetera, cuenta, 0));
+        ida_y_vuelta(Consultar::new(cafetera, cuenta, 0));
+        ida_y_vuelta(Drenar::de_cafetera(cafetera));
+        ida_y_vuelta(Desafio::de_cafetera(cafetera).con_desafio(u64::MAX));
+        ida_y_vuelta(
+            Autenticado::de_cafetera(cafetera)
+                .con_comando(Estado::de_cafetera(cafetera).to_bytes(), vec![0xab; 32]),
+        );
+    }
+
+    #[test]
+    fn cbor_ida_y_vuelta() {
+        let mut transferir = Transferir::new(IdCafetera(2), IdCuenta(7), 150);
+        transferir.id_pedido = 1000003;
+        transferir.id_cuenta_destino = IdCuenta(4);
+        let mut bytes = vec![];
+        ciborium::into_writer(&transferir, &mut bytes).unwrap();
+        assert!(bytes.len() < MAX_BYTES_MENSAJE);
+
+        let recibido: Transferir = ciborium::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(IdCuenta(7), recibido.id_cuenta);
+        assert_eq!(1000003, recibido.id_pedido);
+        assert_eq!(IdCuenta(4), recibido.id_cuenta_destino);
+    }
+
+    #[test]
+    fn cbor_con_campos_faltantes_toma_los_valores_por_defecto() {
+        #[derive(Serialize)]
+        struct CamposViejos {
+            id_cafetera: u8,
+            id_cuenta: u32,
+            cantidad_modificar: u32,
+        }
+        let mut bytes = vec![];
+        ciborium::into_writer(
+            &CamposViejos {
+                id_cafetera: 1,
+                id_cuenta: 4,
+                cantidad_modificar: 0,
+            },
+            &mut bytes,
+        )
+        .unwrap();
+        let restar: Restar = ciborium::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(IdCuenta(4), restar.id_cuenta);
+        assert_eq!(0, restar.id_pedido);
+    }
+}
@@ -0,0 +1,211 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub use compartido_derive::Codificable;
+
+use crate::codec::{codec, Codec};
+use crate::error_mensaje::ErrorMensaje;
+
+/// Primer tipo de los mensajes entre cafeteras y nodos, ver mensajes_cafetera
+pub const TIPOS_CAFETERA: u8 = 0;
+/// Primer tipo de los mensajes entre los nodos y el coordinador
+pub const TIPOS_COORDINADOR: u8 = 32;
+/// Primer tipo de los mensajes del algoritmo bully
+pub const TIPOS_BULLY: u8 = 64;
+
+/// Mensaje que viaja por la red. El primer byte es su tipo, unico entre todas las familias de mensajes
+/// (ver los rangos TIPOS_*), seguido de los campos en el codec configurado en cluster.codec. Se implementa
+/// con `#[derive(Codificable)]` y `#[codificable(tipo = ...)]`
+pub trait Codificable: Serialize + DeserializeOwned {
+    /// Tipo de mensaje, primer byte de to_bytes
+    const TIPO: u8;
+
+    /// Escribe los campos en el orden en que se declaran, cada uno con su implementacion de Campo
+    fn escribir_campos(&self, bytes: &mut Vec<u8>);
+
+    /// Lee los campos en el mismo orden en que los escribe escribir_campos
+    fn leer_campos(lector: &mut Lector<'_>) -> Result<Self, ErrorMensaje>;
+
+    /// Arma el mensaje con el tipo seguido de los campos
+    fn to_bytes(&self) -> Vec<u8> {
+        self.codificar(codec())
+    }
+
+    /// Interpreta el mensaje recibido, retorna error si es de otro tipo, le faltan bytes o algun campo
+    /// es invalido. Los bytes que sobran al final se ignoran
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ErrorMensaje> {
+        Self::decodificar(bytes, codec())
+    }
+
+    /// Arma el mensaje con el codec indicado en lugar del configurado
+    fn codificar(&self, codec: Codec) -> Vec<u8> {
+        let mut bytes = vec![Self::TIPO];
+        match codec {
+            Codec::Bytes => self.escribir_campos(&mut bytes),
+            Codec::Cbor => ciborium::into_writer(self, &mut bytes)
+                .expect("Siempre se puede escribir en un Vec"),
+        }
+        bytes
+    }
+
+    /// Interpreta el mensaje codificado con el codec indicado en lugar del configurado
+    fn decodificar(bytes: &[u8], codec: Codec) -> Result<Self, ErrorMensaje> {
+        let (tipo, campos) = bytes
+            .split_first()
+            .ok_or(ErrorMensaje::Incompleto("tipo_mensaje"))?;
+        if *tipo != Self::TIPO {
+            return Err(ErrorMensaje::CampoInvalido("tipo_mensaje"));
+        }
+        match codec {
+            Codec::Bytes => Self::leer_campos(&mut Lector::new(campos)),
+            Codec::Cbor => ciborium::from_reader(campos)
+                .map_err(|_| ErrorMensaje::CampoInvalido("campos cbor")),
+        }
+    }
+}
+
+/// Recorre los bytes de un mensaje a medida que se leen sus campos
+pub struct Lector<'a> {
+    restantes: &'a [u8],
+}
+
+impl<'a> Lector<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Lector { restantes: bytes }
+    }
+
+    /// Lee el campo indicado, su nombre se usa para reportar el error
+    pub fn leer<T: Campo>(&mut self, campo: &'static str) -> Result<T, ErrorMensaje> {
+        T::leer(self, campo)
+    }
+
+    /// Toma los proximos N bytes, o retorna Incompleto si el mensaje termina antes
+    pub fn tomar<const N: usize>(&mut self, campo: &'static str) -> Result<[u8; N], ErrorMensaje> {
+        let (leidos, restantes) = self
+            .restantes
+            .split_first_chunk::<N>()
+            .ok_or(ErrorMensaje::Incompleto(campo))?;
+        self.restantes = restantes;
+        Ok(*leidos)
+    }
+}
+
+/// Campo de un mensaje Codificable
+pub trait Campo: Sized {
+    fn escribir(&self, bytes: &mut Vec<u8>);
+    fn leer(lector: &mut Lector<'_>, campo: &'static str) -> Result<Self, ErrorMensaje>;
+}
+
+/// Los enteros van en big endian con su tamaño fijo
+macro_rules! campo_entero {
+    ($($tipo:ty),*) => {
+        $(
+            impl Campo for $tipo {
+                fn escribir(&self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn leer(lector: &mut Lector<'_>, campo: &'static str) -> Result<Self, ErrorMensaje> {
+                    Ok(<$tipo>::from_be_bytes(lector.tomar(campo)?))
+                }
+            }
+        )*
+    };
+}
+
+campo_entero!(u8, u16, u32, u64);
+
+/// Los booleanos van en un byte, 0 o 1
+impl Campo for bool {
+    fn escribir(&self, bytes: &mut Vec<u8>) {
+        bytes.push(*self as u8);
+    }
+
+    fn leer(lector: &mut Lector<'_>, campo: &'static str) -> Result<Self, ErrorMensaje> {
+        match u8::leer(lector, campo)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(ErrorMensaje::CampoInvalido(campo)),
+        }
+    }
+}
+
+/// Las listas van precedidas por su largo como u32
+impl<T: Campo> Campo for Vec<T> {
+    fn escribir(&self, bytes: &mut Vec<u8>) {
+        (self.len() as u32).escribir(bytes);
+        self.iter().for_each(|x| x.escribir(bytes));
+    }
+
+    fn leer(lector: &mut Lector<'_>, campo: &'static str) -> Result<Self, ErrorMensaje> {
+        let largo = u32::leer(lector, campo)?;
+        // no se reserva memoria segun el largo recibido, si es invalido el mensaje termina antes
+        (0..largo).map(|_| T::leer(lector, campo)).collect()
+    }
+}
+
+impl<A: Campo, B: Campo> Campo for (A, B) {
+    fn escribir(&self, bytes: &mut Vec<u8>) {
+        self.0.escribir(bytes);
+        self.1.escribir(bytes);
+    }
+
+    fn leer(lector: &mut Lector<'_>, campo: &'static str) -> Result<Self, ErrorMensaje> {
+        Ok((A::leer(lector, campo)?, B::leer(lector, campo)?))
+    }
+}
+
+#[cfg(test)]
+mod codificable_test {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, Codificable)]
+    #[codificable(tipo = TIPOS_COORDINADOR + 1)]
+    struct Prueba {
+        id_nodo: u8,
+        corte: u64,
+        cuentas: Vec<(u32, u32)>,
+    }
+
+    #[test]
+    fn ida_y_vuelta_con_lista() {
+        let prueba = Prueba {
+            id_nodo: 2,
+            corte: 9,
+            cuentas: vec![(1, 100), (2, 256)],
+        };
+        let bytes = prueba.to_bytes();
+
+        assert_eq!(
+            vec![
+                33, 2, 0, 0, 0, 0, 0, 0, 0, 9, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 100, 0, 0, 0, 2, 0,
+                0, 1, 0
+            ],
+            bytes
+        );
+        assert_eq!(Ok(prueba), Prueba::from_bytes(&bytes));
+    }
+
+    #[test]
+    fn tipo_distinto_o_incompleto_retorna_error() {
+        assert_eq!(
+            Err(ErrorMensaje::CampoInvalido("tipo_mensaje")),
+            Prueba::from_bytes(&[TIPOS_BULLY, 2])
+        );
+        assert_eq!(
+            Err(ErrorMensaje::Incompleto("corte")),
+            Prueba::from_bytes(&[33, 2, 0, 0])
+        );
+        // el largo de la lista dice mas cuentas de las que llegan
+        assert_eq!(
+            Err(ErrorMensaje::Incompleto("cuentas")),
+            Prueba::from_bytes(&[33, 2, 0, 0, 0, 0, 0, 0, 0, 9, 255, 255, 255, 255])
+        );
+        assert_eq!(
+            Err(ErrorMensaje::Incompleto("tipo_mensaje")),
+            Prueba::from_bytes(&[])
+        );
+    }
+}
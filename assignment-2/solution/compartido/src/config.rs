@@ -0,0 +1,664 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::{env, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::Codec;
+use crate::error::ErrorSistema;
+use crate::ids::{IdCafetera, IdCuenta};
+
+/// Variable de entorno con el path al archivo de configuracion. Si no esta, se busca ARCHIVO_CONFIG en
+/// el directorio actual y en el superior (la raiz de la solucion al correr con cargo run)
+pub const VARIABLE_ARCHIVO_CONFIG: &str = "TP2_CONFIG";
+/// Nombre del archivo de configuracion compartido por todos los procesos
+pub const ARCHIVO_CONFIG: &str = "config.toml";
+/// Prefijo de las variables de entorno que pisan un valor del archivo: TP2_<SECCION>_<CAMPO>, por
+/// ejemplo TP2_SERVIDOR_SALDO_INICIAL=500
+pub const PREFIJO_VARIABLES: &str = "TP2";
+/// Primer byte de las consultas de administracion al coordinador en su puerto de control, en lugar
+/// del ID_NODO con el que se conectan los nodos. Ningun nodo del cluster tiene id 0. El segundo byte
+/// es el tipo de consulta
+pub const ID_CONSULTA_ADMIN: u8 = 0;
+/// Consulta del registro de commits, seguida de la secuencia desde la que se consulta (u64 en big
+/// endian). Se responde una linea json por entrada
+pub const CONSULTA_REGISTRO: u8 = 0;
+/// Consulta que inicia una auditoria de consistencia de los saldos de todos los nodos. Se responde el
+/// informe como una linea json
+pub const CONSULTA_AUDITORIA: u8 = 1;
+/// Consulta de las transacciones en curso del coordinador: la fase de cada una, los nodos que ya
+/// respondieron en esa fase y los que faltan. Se responde una linea json por transaccion
+pub const CONSULTA_TRANSACCIONES: u8 = 2;
+/// Consulta del snapshot de los saldos del cluster: se le piden los saldos a todos los nodos como en la
+/// auditoria y se toman los del nodo del coordinador, marcando en cada cuenta los nodos que difieren. Se
+/// responde el snapshot como una linea json
+pub const CONSULTA_SALDOS: u8 = 3;
+/// id_cafetera con el que la herramienta de administracion envia sus pedidos (altas, bajas y
+/// correcciones de saldo). Las transacciones de este id son prioritarias en la cola de cada cuenta
+pub const ID_CAFETERA_ADMINISTRACION: IdCafetera = IdCafetera(u8::MAX);
+
+/// Configuracion de todos los procesos del sistema. Los valores que no estan en el archivo toman su
+/// valor por defecto, asi un archivo parcial (o ninguno) alcanza para levantar el cluster
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub cluster: ConfigCluster,
+    pub servidor: ConfigServidor,
+    pub sucursal: ConfigSucursal,
+    pub desconexion: ConfigDesconexion,
+    pub dashboard: ConfigDashboard,
+    pub logs: ConfigLogs,
+    pub integracion: ConfigIntegracion,
+    pub caos: ConfigCaos,
+}
+
+/// Topologia del cluster. Las direcciones de cada nodo se arman sumando su id al puerto base
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigCluster {
+    pub host: String,
+    pub cant_max_nodos: u8,
+    pub id_coordinador_inicial: u8,
+    /// puerto base de los mensajes del coordinador a los nodos
+    pub puerto_ctrl: u16,
+    /// puerto base en el que los nodos reciben los mensajes de las cafeteras
+    pub puerto_lectura_datos: u16,
+    /// puerto base desde el que los nodos responden a las cafeteras
+    pub puerto_escritura_datos: u16,
+    /// puerto base en el que los nodos reciben los mensajes del algoritmo bully
+    pub puerto_lectura_bully: u16,
+    /// puerto base desde el que los nodos envian los mensajes del algoritmo bully
+    pub puerto_escritura_bully: u16,
+    /// puerto base del servicio grpc, solo con la feature grpc
+    pub puerto_grpc: u16,
+    /// secreto compartido con el que se firman los comandos de administracion (ver `autenticacion`).
+    /// Mientras este vacio los nodos rechazan esos comandos
+    pub secreto_admin: String,
+    /// ID_NODO de los nodos que arrancan como replicas de solo lectura: aplican los commits y
+    /// responden consultas, pero no inician transacciones ni participan de las elecciones
+    pub replicas: Vec<u8>,
+    /// codec de los mensajes que viajan por la red, el mismo en todos los procesos (ver `codec`)
+    pub codec: Codec,
+}
+
+impl Default for ConfigCluster {
+    fn default() -> Self {
+        ConfigCluster {
+            host: "127.0.0.1".to_string(),
+            cant_max_nodos: 3,
+            id_coordinador_inicial: 1,
+            puerto_ctrl: 12340,
+            puerto_lectura_datos: 12350,
+            puerto_escritura_datos: 12360,
+            puerto_lectura_bully: 12420,
+            puerto_escritura_bully: 12430,
+            puerto_grpc: 50050,
+            secreto_admin: String::new(),
+            replicas: Vec::new(),
+            codec: Codec::Bytes,
+        }
+    }
+}
+
+impl ConfigCluster {
+    fn direccion(&self, puerto_base: u16, id: u8) -> String {
+        format!("{}:{}", self.host, puerto_base.saturating_add(id as u16))
+    }
+
+    pub fn addr_ctrl(&self, id: u8) -> String {
+        self.direccion(self.puerto_ctrl, id)
+    }
+
+    pub fn addr_lectura_datos(&self, id: u8) -> String {
+        self.direccion(self.puerto_lectura_datos, id)
+    }
+
+    pub fn addr_escritura_datos(&self, id: u8) -> String {
+        self.direccion(self.puerto_escritura_datos, id)
+    }
+
+    pub fn addr_lectura_bully(&self, id: u8) -> String {
+        self.direccion(self.puerto_lectura_bully, id)
+    }
+
+    pub fn addr_escritura_bully(&self, id: u8) -> String {
+        self.direccion(self.puerto_escritura_bully, id)
+    }
+
+    pub fn addr_grpc(&self, id: u8) -> String {
+        self.direccion(self.puerto_grpc, id)
+    }
+
+    /// true si el id es el de un nodo del cluster, de 1 a cant_max_nodos
+    pub fn es_miembro(&self, id: u8) -> bool {
+        (1..=self.cant_max_nodos).contains(&id)
+    }
+
+    /// true si el nodo arranca como replica de solo lectura
+    pub fn es_replica(&self, id: u8) -> bool {
+        self.replicas.contains(&id)
+    }
+}
+
+/// Separa de los argumentos de linea de comandos las direcciones indicadas con `--nodo host:puerto`,
+/// que reemplazan a la direccion que se arma con cluster.host y puerto_lectura_datos (por ejemplo
+/// para nodos en otros hosts). Retorna las direcciones en el orden en que se indicaron y el resto de
+/// los argumentos
+pub fn separar_direcciones_nodos(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(Vec<String>, Vec<String>), ErrorSistema> {
+    let mut direcciones = Vec::new();
+    let mut resto = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg != "--nodo" {
+            resto.push(arg);
+            continue;
+        }
+        let direccion = args.next().ok_or_else(|| {
+            ErrorSistema::Argumentos("--nodo necesita una direccion host:puerto".to_string())
+        })?;
+        match direccion.rsplit_once(':') {
+            Some((host, puerto)) if !host.is_empty() && puerto.parse::<u16>().is_ok() => {
+                direcciones.push(direccion)
+            }
+            _ => {
+                return Err(ErrorSistema::Argumentos(format!(
+                    "la direccion de --nodo debe ser host:puerto, se recibio {:?}",
+                    direccion
+                )))
+            }
+        }
+    }
+    Ok((direcciones, resto))
+}
+
+/// Parametros de los nodos servidores y del coordinador
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigServidor {
+    pub saldo_inicial: u32,
+    /// cantidad de cuentas con las que arranca cada nodo (ids 1 a cuentas_iniciales) si no se le indica
+    /// un archivo de cuentas, el resto se crean con altas
+    pub cuentas_iniciales: u32,
+    /// saldo maximo que puede tener una cuenta, las operaciones que lo superarian se rechazan
+    pub saldo_maximo: u32,
+    pub timeout_ok_bully_millis: u64,
+    /// reenvios del COORDINATOR a los nodos que no lo confirmaron, cada timeout_ok_bully_millis
+    pub max_reenvios_coordinator_bully: u32,
+    /// si es true, el nodo rechaza los pedidos de cafeteras que no se hayan registrado previamente
+    pub requiere_registro_cafeteras: bool,
+    /// tiempo maximo (en segundos) que una resta puede estar al frente de la cola de su cuenta antes
+    /// de ser abortada por el coordinador, debe superar el tiempo de preparacion del cafe en la sucursal
+    pub tiempo_maximo_servicio_transaccion: u64,
+    /// cada cuanto (en milisegundos) el coordinador revisa si hay transacciones vencidas
+    pub intervalo_planificacion_millis: u64,
+    /// cada cuanto (en milisegundos) el coordinador reenvia el commit a los nodos que no lo confirmaron
+    pub intervalo_reenvio_commit_millis: u64,
+    /// reenvios del commit sin confirmacion luego de los cuales el coordinador da por caido al nodo
+    pub max_reenvios_commit: u32,
+    /// cantidad de transacciones prioritarias (de administracion) que pueden adelantarse a una
+    /// transaccion comun en la cola de su cuenta, para que esta no espere indefinidamente
+    pub max_adelantamientos: u32,
+    /// cada cuanto (en milisegundos) el coordinador loguea los percentiles de latencia
+    pub intervalo_reporte_latencias_millis: u64,
+    /// espera maxima (en milisegundos) del coordinador por los saldos de los nodos en una auditoria,
+    /// los que no respondan a tiempo figuran sin respuesta en el informe
+    pub tiempo_maximo_auditoria_millis: u64,
+    /// espera maxima (en segundos) del servicio grpc por cada respuesta del nodo, debe superar
+    /// tiempo_maximo_servicio_transaccion para que una resta encolada llegue a abortarse
+    pub timeout_grpc_segundos: u64,
+    /// si es true, los nodos publican por udp sus eventos para el dashboard
+    pub publicar_eventos: bool,
+    /// direccion udp en la que el dashboard recibe los eventos de los nodos
+    pub direccion_eventos_dashboard: String,
+    /// cada cuanto (en milisegundos) un nodo que se esta drenando revisa si termino sus transacciones
+    pub intervalo_drenado_millis: u64,
+    /// tiempo maximo (en segundos) que un nodo espera a sus transacciones en curso al drenarse, debe
+    /// superar tiempo_maximo_servicio_transaccion para que el coordinador pueda vencerlas
+    pub tiempo_maximo_drenado: u64,
+    /// cada cuanto (en milisegundos) un nodo quita de memoria las transacciones terminadas hace mas
+    /// de ttl_transacciones_terminadas_segundos
+    pub intervalo_limpieza_transacciones_millis: u64,
+    /// tiempo (en segundos) que un nodo retiene una transaccion terminada, debe superar los reintentos
+    /// de la sucursal para que un pedido repetido encuentre la transaccion y no inicie otra
+    pub ttl_transacciones_terminadas_segundos: u64,
+    /// cantidad maxima de pedidos de resta ya quitados de memoria cuyo resultado recuerda un nodo,
+    /// para responder igual los RESTAR repetidos
+    pub max_pedidos_terminados: usize,
+    /// segundos sin trafico en una conexion entre un nodo y el coordinador luego de los cuales se
+    /// empiezan a enviar sondas de keepalive tcp
+    pub keepalive_inactividad_segundos: u64,
+    /// segundos entre las sondas de keepalive que no tienen respuesta
+    pub keepalive_intervalo_segundos: u64,
+    /// sondas de keepalive sin respuesta luego de las cuales se da por cerrada la conexion
+    pub keepalive_sondas: u32,
+    /// espera maxima (en milisegundos) de cada escritura en una conexion entre un nodo y el
+    /// coordinador, si el otro extremo deja de leer la conexion se da por cerrada
+    pub timeout_escritura_millis: u64,
+    /// espera maxima (en milisegundos) del coordinador por cada byte de la presentacion de una
+    /// conexion entrante (el ID_NODO y el modo, o ID_CONSULTA_ADMIN), luego se descarta la conexion
+    pub timeout_presentacion_millis: u64,
+    /// diferencia maxima (en milisegundos) entre la secuencia de un CONECTAR/DESCONECTAR y el reloj
+    /// del nodo para que se lo acepte, los comandos fuera de la ventana se descartan como reenviados
+    pub ventana_comandos_admin_millis: u64,
+    /// intentos de conexion con el coordinador inicial al arrancar un nodo, si se agotan el nodo
+    /// arranca desconectado e inicia una eleccion
+    pub intentos_conexion_coordinador: u32,
+    /// espera (en milisegundos) luego del primer intento fallido, se duplica con cada intento
+    pub espera_conexion_coordinador_millis: u64,
+    /// directorio en el que cada nodo compilado con la feature sled guarda los saldos de sus cuentas
+    /// (en nodo-<ID_NODO>), el coordinador su registro de commits (en registro-coordinador-<ID_NODO>.jsonl)
+    /// y cada nodo lo que aplico sobre sus cuentas (en registro-nodo-<ID_NODO>.jsonl), vacio para no
+    /// guardarlos
+    pub directorio_datos: String,
+}
+
+impl Default for ConfigServidor {
+    fn default() -> Self {
+        ConfigServidor {
+            saldo_inicial: 10000,
+            cuentas_iniciales: 10,
+            saldo_maximo: 1_000_000,
+            timeout_ok_bully_millis: 10000,
+            max_reenvios_coordinator_bully: 3,
+            requiere_registro_cafeteras: false,
+            tiempo_maximo_servicio_transaccion: 60,
+            intervalo_planificacion_millis: 1000,
+            intervalo_reenvio_commit_millis: 2000,
+            max_reenvios_commit: 5,
+            max_adelantamientos: 3,
+            intervalo_reporte_latencias_millis: 10000,
+            tiempo_maximo_auditoria_millis: 2000,
+            timeout_grpc_segundos: 70,
+            publicar_eventos: true,
+            direccion_eventos_dashboard: "127.0.0.1:9000".to_string(),
+            intervalo_drenado_millis: 500,
+            tiempo_maximo_drenado: 90,
+            intervalo_limpieza_transacciones_millis: 10000,
+            ttl_transacciones_terminadas_segundos: 300,
+            max_pedidos_terminados: 100_000,
+            keepalive_inactividad_segundos: 5,
+            keepalive_intervalo_segundos: 1,
+            keepalive_sondas: 3,
+            timeout_escritura_millis: 5000,
+            timeout_presentacion_millis: 5000,
+            ventana_comandos_admin_millis: 30000,
+            intentos_conexion_coordinador: 5,
+            espera_conexion_coordinador_millis: 250,
+            directorio_datos: String::new(),
+        }
+    }
+}
+
+/// Parametros de las sucursales y sus cafeteras
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigSucursal {
+    /// cantidad de cafeteras que habra por sucursal
+    pub cantidad_cafeteras: usize,
+    /// cantidad de cafes que cada cafetera puede preparar a la vez (preparadores por cafetera)
+    pub preparaciones_concurrentes: usize,
+    /// cantidad maxima de pedidos leidos del archivo que esperan en la cola de cada cafetera; cuando las
+    /// colas de todas las cafeteras estan llenas se deja de leer hasta que alguna tome un pedido
+    pub pedidos_en_espera_por_cafetera: usize,
+    /// espera maxima (en segundos) para recibir una respuesta por el socket udp
+    pub timeout: u64,
+    /// probabilidad de que la preparacion del cafe falle
+    pub probabilidad_error: f64,
+    /// probabilidad de que la sucursal pierda cada datagrama que envia o recibe, para probar los
+    /// reintentos, los sondeos y la idempotencia de los pedidos sin herramientas externas
+    pub probabilidad_perdida: f64,
+    /// tiempo (en segundos) destinado a la preparacion del cafe
+    pub tiempo_de_preparacion: u64,
+    /// espera maxima (en milisegundos) por la respuesta al sondeo de vida del nodo
+    pub timeout_sondeo_millis: u64,
+    /// espera maxima (en segundos) por el estado final de las sumas pendientes al terminar los pedidos
+    pub timeout_estado_final: u64,
+    /// la espera por la respuesta de un nodo es timeout multiplicado por 1 mas sus fallas consecutivas,
+    /// como mucho por este valor
+    pub multiplicador_timeout_maximo: u64,
+    /// fallas consecutivas (respuestas que no llegan a tiempo) luego de las cuales la cafetera deja de
+    /// enviarle pedidos al nodo
+    pub fallas_para_abrir_circuito: u32,
+    /// cada cuanto (en milisegundos) se vuelve a sondear un nodo que se dejo de usar por sus fallas
+    pub intervalo_prueba_circuito_millis: u64,
+    /// ip en la que las cafeteras abren sus sockets udp. Con nodos en otros hosts tiene que ser una ip
+    /// a la que los nodos puedan responder, o 0.0.0.0
+    pub host: String,
+    /// archivo con las cuentas con las que puede operar cada cafetera, una linea json por cafetera
+    /// ({"id_cafetera": 0, "cuentas": [1, 2]}). Si esta vacio cualquier cafetera opera con cualquier cuenta
+    pub archivo_cuentas_cafeteras: String,
+}
+
+impl Default for ConfigSucursal {
+    fn default() -> Self {
+        ConfigSucursal {
+            cantidad_cafeteras: 3,
+            preparaciones_concurrentes: 1,
+            pedidos_en_espera_por_cafetera: 4,
+            timeout: 1000,
+            probabilidad_error: 0.0,
+            probabilidad_perdida: 0.0,
+            tiempo_de_preparacion: 7,
+            timeout_sondeo_millis: 500,
+            timeout_estado_final: 30,
+            multiplicador_timeout_maximo: 4,
+            fallas_para_abrir_circuito: 3,
+            intervalo_prueba_circuito_millis: 5000,
+            host: "127.0.0.1".to_string(),
+            archivo_cuentas_cafeteras: String::new(),
+        }
+    }
+}
+
+/// Parametros del proceso de desconexion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigDesconexion {
+    /// direccion udp desde la que se envian los mensajes a los nodos
+    pub direccion: String,
+    /// espera maxima (en milisegundos) por la respuesta de un nodo a la consulta de estado
+    pub timeout_estado_millis: u64,
+}
+
+impl Default for ConfigDesconexion {
+    fn default() -> Self {
+        ConfigDesconexion {
+            direccion: "127.0.0.1:1222".to_string(),
+            timeout_estado_millis: 1000,
+        }
+    }
+}
+
+/// Parametros del dashboard, que recibe los eventos en servidor.direccion_eventos_dashboard
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigDashboard {
+    /// direccion tcp en la que se sirve la pagina del dashboard
+    pub direccion_http: String,
+    /// direccion tcp en la que los navegadores se conectan por websocket para recibir los eventos
+    pub direccion_websocket: String,
+}
+
+impl Default for ConfigDashboard {
+    fn default() -> Self {
+        ConfigDashboard {
+            direccion_http: "127.0.0.1:8080".to_string(),
+            direccion_websocket: "127.0.0.1:8081".to_string(),
+        }
+    }
+}
+
+/// Parametros de los logs de todos los procesos, ver logs::init_tracing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigLogs {
+    /// niveles a mostrar, con la sintaxis de EnvFilter (por ejemplo "info,server::coordinador=debug")
+    pub nivel: String,
+    /// archivo al que cada proceso agrega sus logs en json, vacio para no guardarlos
+    pub archivo_json: String,
+    /// intervalo (en milisegundos) en el que un error que se repite se loguea una sola vez, ver
+    /// logs::LimitadorLogs
+    pub intervalo_errores_repetidos_millis: u64,
+}
+
+impl Default for ConfigLogs {
+    fn default() -> Self {
+        ConfigLogs {
+            nivel: "info".to_string(),
+            archivo_json: String::new(),
+            intervalo_errores_repetidos_millis: 5000,
+        }
+    }
+}
+
+/// Parametros de la simulacion que usa la cafeteria del TP1 como cafetera de una sucursal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigIntegracion {
+    /// cuenta a la que se le cobran los pedidos
+    pub id_cuenta: IdCuenta,
+    /// puntos que se cobran por cada gramo de ingrediente del pedido
+    pub puntos_por_gramo: f64,
+    /// si es true, los contenedores recargables reponen granos y leche de una despensa que vive en el
+    /// cluster, compartida con las demas cafeterias
+    pub despensa: bool,
+    /// cuenta cuyo saldo es el stock de granos de cafe (en gramos) de la despensa
+    pub id_cuenta_granos: IdCuenta,
+    /// cuenta cuyo saldo es el stock de leche fria (en gramos) de la despensa
+    pub id_cuenta_leche: IdCuenta,
+}
+
+impl Default for ConfigIntegracion {
+    fn default() -> Self {
+        ConfigIntegracion {
+            id_cuenta: IdCuenta(1),
+            puntos_por_gramo: 1.0,
+            despensa: false,
+            id_cuenta_granos: IdCuenta(2),
+            id_cuenta_leche: IdCuenta(3),
+        }
+    }
+}
+
+/// Fallas que se inyectan en el transporte entre nodos para simular topologias distribuidas, por
+/// ejemplo nodos en distintas regiones
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigCaos {
+    /// latencia de cada enlace entre dos nodos, los enlaces que no figuran no se demoran
+    pub latencias: Vec<LatenciaEnlace>,
+}
+
+impl ConfigCaos {
+    /// Latencia configurada para los mensajes que el nodo origen le envia al nodo destino
+    pub fn latencia(&self, origen: u8, destino: u8) -> Option<&LatenciaEnlace> {
+        self.latencias
+            .iter()
+            .find(|x| x.origen == origen && x.destino == destino)
+    }
+}
+
+/// Latencia de los mensajes de un nodo a otro. El enlace es en un solo sentido, para que la vuelta
+/// tenga la misma latencia hay que agregar el enlace inverso
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatenciaEnlace {
+    pub origen: u8,
+    pub destino: u8,
+    /// demora (en milisegundos) que tienen todos los mensajes del enlace
+    pub base_millis: u64,
+    /// demora adicional maxima (en milisegundos), se sortea para cada mensaje entre 0 y este valor
+    #[serde(default)]
+    pub jitter_millis: u64,
+}
+
+/// Configuracion del proceso, cargada la primera vez que se pide. Si el archivo o alguna variable de
+/// entorno es invalida se avisa y se usan los valores por defecto, para no dejar caer al proceso
+pub fn config() -> &'static Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        cargar_config().unwrap_or_else(|err| {
+            println!(
+                "[CONFIG] Se usan los valores por defecto, error al cargar la configuracion: {}",
+                err
+            );
+            Config::default()
+        })
+    })
+}
+
+/// Lee el archivo de configuracion (si existe) y aplica las variables de entorno
+pub fn cargar_config() -> Result<Config, ErrorSistema> {
+    let config = match buscar_archivo() {
+        Some(path) => {
+            let contenido = fs::read_to_string(&path)
+                .map_err(|x| ErrorSistema::Archivo(format!("{}: {}", path.display(), x)))?;
+            parsear_config(&contenido)?
+        }
+        None => Config::default(),
+    };
+    aplicar_variables(config, |variable| env::var(variable).ok())
+}
+
+fn buscar_archivo() -> Option<PathBuf> {
+    if let Ok(path) = env::var(VARIABLE_ARCHIVO_CONFIG) {
+        return Some(PathBuf::from(path));
+    }
+    [
+        Path::new(".").join(ARCHIVO_CONFIG),
+        Path::new("..").join(ARCHIVO_CONFIG),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Parsea el contenido de un archivo de configuracion
+pub fn parsear_config(contenido: &str) -> Result<Config, ErrorSistema> {
+    toml::from_str(contenido).map_err(|x| ErrorSistema::Parseo(x.to_string()))
+}
+
+/// Pisa cada campo de la configuracion con la variable TP2_<SECCION>_<CAMPO> si esta definida. El valor
+/// se interpreta con el tipo del campo, asi una variable invalida da error en lugar de ignorarse. Las
+/// listas se indican con sus valores separados por comas (por ejemplo TP2_CLUSTER_REPLICAS=3,4)
+fn aplicar_variables(
+    config: Config,
+    leer_variable: impl Fn(&str) -> Option<String>,
+) -> Result<Config, ErrorSistema> {
+    let mut valores =
+        toml::Value::try_from(config).map_err(|x| ErrorSistema::Parseo(x.to_string()))?;
+    if let Some(secciones) = valores.as_table_mut() {
+        for (nombre_seccion, seccion) in secciones.iter_mut() {
+            if let Some(campos) = seccion.as_table_mut() {
+                for (nombre_campo, valor) in campos.iter_mut() {
+                    let variable =
+                        format!("{}_{}_{}", PREFIJO_VARIABLES, nombre_seccion, nombre_campo)
+                            .to_uppercase();
+                    if let Some(texto) = leer_variable(&variable) {
+                        *valor = parsear_variable(valor, &texto).ok_or_else(|| {
+                            ErrorSistema::Parseo(format!("{}={}", variable, texto))
+                        })?;
+                    }
+                }
+            }
+        }
+    }
+    valores
+        .try_into()
+        .map_err(|x: toml::de::Error| ErrorSistema::Parseo(x.to_string()))
+}
+
+fn parsear_variable(actual: &toml::Value, texto: &str) -> Option<toml::Value> {
+    match actual {
+        toml::Value::Integer(_) => texto.parse().ok().map(toml::Value::Integer),
+        toml::Value::Float(_) => texto.parse().ok().map(toml::Value::Float),
+        toml::Value::Boolean(_) => texto.parse().ok().map(toml::Value::Boolean),
+        toml::Value::Array(_) => Some(toml::Value::Array(
+            texto
+                .split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(parsear_elemento)
+                .collect(),
+        )),
+        _ => Some(toml::Value::String(texto.to_string())),
+    }
+}
+
+/// Elemento de una lista de una variable de entorno. Como la lista puede estar vacia no se conoce el
+/// tipo de sus elementos: se toma el primero que lo interpreta y el tipo del campo se valida despues
+fn parsear_elemento(texto: &str) -> toml::Value {
+    texto
+        .parse()
+        .map(toml::Value::Integer)
+        .or_else(|_| texto.parse().map(toml::Value::Float))
+        .or_else(|_| texto.parse().map(toml::Value::Boolean))
+        .unwrap_or_else(|_| toml::Value::String(texto.to_string()))
+}
+
+#[cfg(test)]
+mod config_test {
+    use super::*;
+
+    #[test]
+    fn archivo_parcial_completa_con_valores_por_defecto() {
+        let config = parsear_config("[servidor]\nsaldo_inicial = 500\n").unwrap();
+        assert_eq!(500, config.servidor.saldo_inicial);
+        assert_eq!(ConfigCluster::default(), config.cluster);
+        assert_eq!("127.0.0.1:12352", config.cluster.addr_lectura_datos(2));
+        assert!(config.cluster.es_miembro(3));
+        assert!(!config.cluster.es_miembro(0));
+        assert!(!config.cluster.es_miembro(4));
+        assert!(config.caos.latencias.is_empty());
+    }
+
+    #[test]
+    fn latencias_por_enlace_en_un_solo_sentido() {
+        let config = parsear_config(
+            "[caos]\nlatencias = [\n  { origen = 1, destino = 2, base_millis = 80, jitter_millis = 20 },\n  { origen = 2, destino = 1, base_millis = 80 },\n]\n",
+        )
+        .unwrap();
+        let ida = config.caos.latencia(1, 2).unwrap();
+        assert_eq!((80, 20), (ida.base_millis, ida.jitter_millis));
+        assert_eq!(0, config.caos.latencia(2, 1).unwrap().jitter_millis);
+        assert_eq!(None, config.caos.latencia(1, 3));
+        // la seccion sobrevive a las variables de entorno aunque no se puedan pisar
+        let config = aplicar_variables(config, |_| None).unwrap();
+        assert_eq!(2, config.caos.latencias.len());
+    }
+
+    #[test]
+    fn direcciones_de_nodos_por_argumento() {
+        let args = [
+            "1,2",
+            "--nodo",
+            "10.0.0.5:12351",
+            "pedidos.txt",
+            "--nodo",
+            "nodo2:7000",
+        ];
+        let (direcciones, resto) =
+            separar_direcciones_nodos(args.iter().map(|x| x.to_string())).unwrap();
+        assert_eq!(vec!["10.0.0.5:12351", "nodo2:7000"], direcciones);
+        assert_eq!(vec!["1,2", "pedidos.txt"], resto);
+
+        for invalidos in [&["--nodo"][..], &["--nodo", "10.0.0.5"], &["--nodo", ":80"]] {
+            let resultado = separar_direcciones_nodos(invalidos.iter().map(|x| x.to_string()));
+            assert!(matches!(resultado, Err(ErrorSistema::Argumentos(_))));
+        }
+    }
+
+    #[test]
+    fn variables_de_entorno_pisan_el_archivo() {
+        let config = parsear_config("[cluster]\ncant_max_nodos = 5\n").unwrap();
+        let config = aplicar_variables(config, |variable| match variable {
+            "TP2_CLUSTER_CANT_MAX_NODOS" => Some("4".to_string()),
+            "TP2_SUCURSAL_PROBABILIDAD_ERROR" => Some("0.5".to_string()),
+            "TP2_SUCURSAL_PROBABILIDAD_PERDIDA" => Some("0.1".to_string()),
+            "TP2_CLUSTER_HOST" => Some("10.0.0.1".to_string()),
+            "TP2_CLUSTER_REPLICAS" => Some("3, 4".to_string()),
+            "TP2_CLUSTER_CODEC" => Some("cbor".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(4, config.cluster.cant_max_nodos);
+        assert_eq!(0.5, config.sucursal.probabilidad_error);
+        assert_eq!(0.1, config.sucursal.probabilidad_perdida);
+        assert_eq!("10.0.0.1:12431", config.cluster.addr_escritura_bully(1));
+        assert!(config.cluster.es_replica(4) && !config.cluster.es_replica(1));
+        assert_eq!(Codec::Cbor, config.cluster.codec);
+
+        let invalida = aplicar_variables(Config::default(), |variable| {
+            (variable == "TP2_SERVIDOR_PUBLICAR_EVENTOS").then(|| "quizas".to_string())
+        });
+        assert!(matches!(invalida, Err(ErrorSistema::Parseo(_))));
+        let invalida = aplicar_variables(Config::default(), |variable| {
+            (variable == "TP2_CLUSTER_REPLICAS").then(|| "3,tres".to_string())
+        });
+        assert!(matches!(invalida, Err(ErrorSistema::Parseo(_))));
+        let invalida = aplicar_variables(Config::default(), |variable| {
+            (variable == "TP2_CLUSTER_CODEC").then(|| "json".to_string())
+        });
+        assert!(matches!(invalida, Err(ErrorSistema::Parseo(_))));
+    }
+}
@@ -0,0 +1,84 @@
+use std::io;
+use std::num::ParseIntError;
+use std::sync::PoisonError;
+
+use actix::prelude::SendError;
+use actix::MailboxError;
+use thiserror::Error;
+
+use crate::error_mensaje::ErrorMensaje;
+
+/// Errores de los procesos del sistema distribuido (nodos, sucursales y herramientas). Cada proceso
+/// lo reexporta con su propio nombre (ErrorServer, ErrorSucursal)
+#[derive(Debug, Error)]
+pub enum ErrorSistema {
+    /// fallo un socket udp/tcp o la comunicacion con otro proceso
+    #[error("error de conexion: {0}")]
+    Conexion(#[from] io::Error),
+    /// un valor leido (argumento, archivo, configuracion) no tiene el formato esperado
+    #[error("error de parseo: {0}")]
+    Parseo(String),
+    /// otro proceso no respondio a tiempo
+    #[error("timeout: {0}")]
+    Timeout(String),
+    /// se recibio un mensaje que no respeta el protocolo
+    #[error("mensaje invalido: {0}")]
+    Protocolo(#[from] ErrorMensaje),
+    /// un actor o thread del propio proceso no esta en condiciones de atender el pedido
+    #[error("estado invalido: {0}")]
+    Estado(String),
+    /// faltan argumentos o alguno es invalido
+    #[error("argumentos invalidos: {0}")]
+    Argumentos(String),
+    /// no se pudo leer un archivo de entrada
+    #[error("error de archivo: {0}")]
+    Archivo(String),
+}
+
+impl From<ParseIntError> for ErrorSistema {
+    fn from(err: ParseIntError) -> Self {
+        ErrorSistema::Parseo(err.to_string())
+    }
+}
+
+impl<T> From<PoisonError<T>> for ErrorSistema {
+    fn from(_: PoisonError<T>) -> Self {
+        ErrorSistema::Estado("un thread termino con panic mientras tenia el lock".to_string())
+    }
+}
+
+impl From<MailboxError> for ErrorSistema {
+    fn from(err: MailboxError) -> Self {
+        ErrorSistema::Estado(err.to_string())
+    }
+}
+
+impl<T> From<SendError<T>> for ErrorSistema {
+    fn from(err: SendError<T>) -> Self {
+        let detalle = match err {
+            SendError::Full(_) => "la casilla del actor esta llena",
+            SendError::Closed(_) => "el actor ya termino",
+        };
+        ErrorSistema::Estado(detalle.to_string())
+    }
+}
+
+#[cfg(test)]
+mod error_test {
+    use super::*;
+
+    #[test]
+    fn conversiones_a_la_variante_correspondiente() {
+        let conexion: ErrorSistema = io::Error::from(io::ErrorKind::ConnectionRefused).into();
+        assert!(matches!(conexion, ErrorSistema::Conexion(_)));
+
+        let parseo: ErrorSistema = "x".parse::<u8>().unwrap_err().into();
+        assert!(matches!(parseo, ErrorSistema::Parseo(_)));
+
+        let protocolo: ErrorSistema = ErrorMensaje::Incompleto("id_cuenta").into();
+        assert_eq!(
+            "mensaje invalido: mensaje incompleto, falta id_cuenta",
+            protocolo.to_string()
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use std::fmt;
+
+/// Error al interpretar un mensaje recibido por la red. Los receptores descartan el mensaje en lugar
+/// de terminar el actor que lo recibio
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorMensaje {
+    /// el mensaje termina antes del campo indicado
+    Incompleto(&'static str),
+    /// el campo indicado esta presente pero no tiene un valor valido
+    CampoInvalido(&'static str),
+}
+
+impl fmt::Display for ErrorMensaje {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorMensaje::Incompleto(campo) => write!(f, "mensaje incompleto, falta {}", campo),
+            ErrorMensaje::CampoInvalido(campo) => write!(f, "valor invalido en {}", campo),
+        }
+    }
+}
+
+impl std::error::Error for ErrorMensaje {}
@@ -0,0 +1,115 @@
+//! Identificadores que comparten las sucursales, los nodos y el coordinador. Cada uno es un tipo
+//! distinto para que el compilador detecte los argumentos cambiados de lugar (por ejemplo una cuenta
+//! pasada como id de transaccion), aunque en la red y en los logs se vean igual que el entero que
+//! envuelven
+
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codificable::{Campo, Lector};
+use crate::error_mensaje::ErrorMensaje;
+
+/// Define un id que envuelve al entero indicado. Se codifica, se serializa y se muestra (tambien con
+/// Debug) igual que el entero, asi no cambian los mensajes ni los logs
+macro_rules! id {
+    ($(#[$doc:meta])* $nombre:ident($entero:ty)) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $nombre(pub $entero);
+
+        impl fmt::Display for $nombre {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl fmt::Debug for $nombre {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl FromStr for $nombre {
+            type Err = ParseIntError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse().map($nombre)
+            }
+        }
+
+        impl From<$entero> for $nombre {
+            fn from(id: $entero) -> Self {
+                $nombre(id)
+            }
+        }
+
+        impl From<$nombre> for $entero {
+            fn from(id: $nombre) -> Self {
+                id.0
+            }
+        }
+
+        impl Campo for $nombre {
+            fn escribir(&self, bytes: &mut Vec<u8>) {
+                self.0.escribir(bytes);
+            }
+
+            fn leer(lector: &mut Lector<'_>, campo: &'static str) -> Result<Self, ErrorMensaje> {
+                <$entero>::leer(lector, campo).map($nombre)
+            }
+        }
+    };
+}
+
+id!(
+    /// Id de la cuenta (tarjeta) de un usuario
+    IdCuenta(u32)
+);
+
+id!(
+    /// Id de una cafetera dentro de su sucursal. Las herramientas de administracion tambien envian
+    /// mensajes de cafetera, con su propio id
+    IdCafetera(u8)
+);
+
+id!(
+    /// Id de un nodo del cluster, el mismo con el que se lo levanta
+    IdNodo(u8)
+);
+
+id!(
+    /// Id de una transaccion, unico en el nodo que la inicia
+    IdTransaccion(u32)
+);
+
+#[cfg(test)]
+mod ids_test {
+    use super::*;
+    use crate::codificable::Lector;
+
+    #[test]
+    fn se_codifican_y_se_muestran_igual_que_el_entero() {
+        let mut bytes = vec![];
+        IdCuenta(258).escribir(&mut bytes);
+        IdNodo(3).escribir(&mut bytes);
+        assert_eq!(vec![0, 0, 1, 2, 3], bytes);
+
+        let mut lector = Lector::new(&bytes);
+        assert_eq!(IdCuenta(258), lector.leer("id_cuenta").unwrap());
+        assert_eq!(IdNodo(3), lector.leer("id_nodo").unwrap());
+        assert!(lector.leer::<IdNodo>("id_nodo").is_err());
+
+        assert_eq!("258", IdCuenta(258).to_string());
+        assert_eq!("[3]", format!("{:?}", vec![IdNodo(3)]));
+        let mut cbor = vec![];
+        ciborium::into_writer(&IdTransaccion(7), &mut cbor).unwrap();
+        let mut cbor_entero = vec![];
+        ciborium::into_writer(&7u32, &mut cbor_entero).unwrap();
+        assert_eq!(cbor_entero, cbor);
+        assert_eq!(Ok(IdCafetera(12)), "12".parse());
+    }
+}
@@ -1 +1,12 @@
+// el derive de Codificable se refiere al crate como ::compartido, tambien desde adentro
+extern crate self as compartido;
+
+pub mod autenticacion;
+pub mod codec;
+pub mod codificable;
+pub mod config;
+pub mod error;
+pub mod error_mensaje;
+pub mod ids;
+pub mod logs;
 pub mod mensajes_cafetera;
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::{warn, Event, Subscriber};
+use tracing_subscriber::fmt::format::{format, FormatEvent, FormatFields, JsonFields, Writer};
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::config;
+
+/// Formato que identifica al proceso en cada linea: como prefijo en la consola y como campo
+/// "proceso" en las lineas json, asi se pueden mezclar y filtrar los logs de todos los procesos
+struct FormatoProceso<F> {
+    proceso: String,
+    formato: F,
+    json: bool,
+}
+
+impl<S, N, F> FormatEvent<S, N> for FormatoProceso<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        if !self.json {
+            write!(writer, "[{}] ", self.proceso)?;
+            return self.formato.format_event(ctx, writer, event);
+        }
+        let mut linea = String::new();
+        self.formato
+            .format_event(ctx, Writer::new(&mut linea), event)?;
+        match linea.strip_prefix('{') {
+            Some(resto) => write!(writer, "{{\"proceso\":{:?},{}", self.proceso, resto),
+            None => writer.write_str(&linea),
+        }
+    }
+}
+
+fn filtro() -> EnvFilter {
+    EnvFilter::try_new(&config().logs.nivel).unwrap_or_else(|_| EnvFilter::new("info"))
+}
+
+/// Inicializa los logs del proceso con el nombre indicado (por ejemplo "nodo-2"). Se muestran por
+/// consola los eventos del nivel configurado en logs.nivel (con la sintaxis de EnvFilter, por ejemplo
+/// "info,server::coordinador=debug") y, si logs.archivo_json no esta vacio, se agregan en json a ese
+/// archivo. Si ya se habian inicializado no hace nada
+pub fn init_tracing(proceso: &str) {
+    iniciar_logs(proceso, true);
+}
+
+/// Como init_tracing pero sin mostrar los eventos por consola, solo en logs.archivo_json si esta
+/// configurado. Lo usan los procesos que dibujan su propia salida, como la sucursal con --tui
+pub fn init_tracing_sin_consola(proceso: &str) {
+    iniciar_logs(proceso, false);
+}
+
+fn iniciar_logs(proceso: &str, mostrar_consola: bool) {
+    let consola = mostrar_consola.then(|| {
+        tracing_subscriber::fmt::layer()
+            .event_format(FormatoProceso {
+                proceso: proceso.to_string(),
+                formato: format().with_target(false),
+                json: false,
+            })
+            .with_filter(filtro())
+    });
+
+    let path = &config().logs.archivo_json;
+    let mut error_archivo = None;
+    let archivo = if path.is_empty() {
+        None
+    } else {
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(archivo) => Some(
+                tracing_subscriber::fmt::layer()
+                    .fmt_fields(JsonFields::new())
+                    .event_format(FormatoProceso {
+                        proceso: proceso.to_string(),
+                        formato: format().json(),
+                        json: true,
+                    })
+                    .with_writer(Mutex::new(archivo))
+                    .with_filter(filtro()),
+            ),
+            Err(err) => {
+                error_archivo = Some(err);
+                None
+            }
+        }
+    };
+
+    if tracing_subscriber::registry()
+        .with(consola)
+        .with(archivo)
+        .try_init()
+        .is_ok()
+    {
+        if let Some(err) = error_archivo {
+            warn!("No se pudo abrir el archivo de logs {}: {}", path, err);
+        }
+    }
+}
+
+/// Limita los logs de un error que se repite, por ejemplo cada envio a una conexion que se cayo: de
+/// cada clave se loguea como mucho uno por intervalo, informando cuantos se omitieron desde el anterior
+pub struct LimitadorLogs<K> {
+    intervalo: Duration,
+    /// momento del ultimo log de cada clave y cantidad de omitidos desde entonces
+    ultimos: HashMap<K, (Instant, u64)>,
+}
+
+impl<K: Hash + Eq> LimitadorLogs<K> {
+    pub fn new(intervalo: Duration) -> Self {
+        LimitadorLogs {
+            intervalo,
+            ultimos: HashMap::new(),
+        }
+    }
+
+    /// Limitador con el intervalo de logs.intervalo_errores_repetidos_millis
+    pub fn desde_config() -> Self {
+        Self::new(Duration::from_millis(
+            config().logs.intervalo_errores_repetidos_millis,
+        ))
+    }
+
+    /// Si corresponde loguear ahora el error de la clave retorna la cantidad de omitidos desde el
+    /// ultimo log, si no retorna None y lo cuenta como omitido
+    pub fn permitir(&mut self, clave: K, ahora: Instant) -> Option<u64> {
+        match self.ultimos.get_mut(&clave) {
+            Some((ultimo, omitidos))
+                if ahora.saturating_duration_since(*ultimo) < self.intervalo =>
+            {
+                *omitidos += 1;
+                None
+            }
+            Some((ultimo, omitidos)) => {
+                *ultimo = ahora;
+                Some(std::mem::take(omitidos))
+            }
+            None => {
+                self.ultimos.insert(clave, (ahora, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod logs_test {
+    use super::*;
+
+    #[test]
+    fn error_repetido_se_loguea_una_vez_por_intervalo_con_los_omitidos() {
+        let mut limitador = LimitadorLogs::new(Duration::from_secs(1));
+        let inicio = Instant::now();
+
+        assert_eq!(Some(0), limitador.permitir(1, inicio));
+        assert_eq!(None, limitador.permitir(1, inicio));
+        assert_eq!(
+            None,
+            limitador.permitir(1, inicio + Duration::from_millis(999))
+        );
+        // cada clave se limita por separado
+        assert_eq!(Some(0), limitador.permitir(2, inicio));
+
+        assert_eq!(
+            Some(2),
+            limitador.permitir(1, inicio + Duration::from_secs(1))
+        );
+        assert_eq!(
+            Some(0),
+            limitador.permitir(1, inicio + Duration::from_secs(3))
+        );
+    }
+}
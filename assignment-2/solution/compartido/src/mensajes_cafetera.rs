@@ -1,5 +1,13 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use actix::Message;
 
+use serde::{Deserialize, Serialize};
+
+use crate::codificable::{Campo, Codificable, Lector};
+use crate::error_mensaje::ErrorMensaje;
+use crate::ids::{IdCafetera, IdCuenta};
+
 #[derive(Debug, Clone, Copy)]
 pub enum MensajeCafetera {
     SUMAR = 0,
@@ -9,6 +17,19 @@ pub enum MensajeCafetera {
     ERROR,
     DESCONECTAR,
     CONECTAR,
+    SONDEO,
+    CONFIRMADO,
+    FALLIDO,
+    REGISTRAR,
+    DESREGISTRAR,
+    ESTADO,
+    TRANSFERIR,
+    ALTA,
+    BAJA,
+    CONSULTAR,
+    DRENAR,
+    DESAFIO,
+    AUTENTICADO,
     DESCONOCIDO,
 }
 
@@ -23,321 +44,829 @@ impl MensajeCafetera {
             4_u8 => MensajeCafetera::ERROR,
             5_u8 => MensajeCafetera::DESCONECTAR,
             6_u8 => MensajeCafetera::CONECTAR,
+            7_u8 => MensajeCafetera::SONDEO,
+            8_u8 => MensajeCafetera::CONFIRMADO,
+            9_u8 => MensajeCafetera::FALLIDO,
+            10_u8 => MensajeCafetera::REGISTRAR,
+            11_u8 => MensajeCafetera::DESREGISTRAR,
+            12_u8 => MensajeCafetera::ESTADO,
+            13_u8 => MensajeCafetera::TRANSFERIR,
+            14_u8 => MensajeCafetera::ALTA,
+            15_u8 => MensajeCafetera::BAJA,
+            16_u8 => MensajeCafetera::CONSULTAR,
+            17_u8 => MensajeCafetera::DRENAR,
+            18_u8 => MensajeCafetera::DESAFIO,
+            19_u8 => MensajeCafetera::AUTENTICADO,
             _ => MensajeCafetera::DESCONOCIDO,
         }
     }
 }
 
-pub trait MensajeCafeteraBytes {
-    fn new(id_cafetera: u8, id_cuenta: u32, cantidad_modificar: u32) -> Self;
-    fn get_tipo_mensaje(&self) -> u8;
-    fn get_id_cafetera(&self) -> u8;
-    fn get_id_cuenta(&self) -> u32;
-    fn get_cantidad_modificar(&self) -> u32;
-
-    fn to_bytes(&self) -> Vec<u8> {
-        let result = vec![self.get_tipo_mensaje(), self.get_id_cafetera()];
-        let id_cuenta_bytes = self.get_id_cuenta().to_be_bytes().to_vec();
-        let cantidad_bytes = self.get_cantidad_modificar().to_be_bytes().to_vec();
-        [result, id_cuenta_bytes, cantidad_bytes].concat()
+/// Id de pedido del mensaje, el que la sucursal asigna a cada pedido y el nodo devuelve en sus
+/// respuestas. Retorna 0 si el mensaje no corresponde a un pedido, no trae el id o no se puede leer
+pub fn id_pedido_from_bytes(bytes: &[u8]) -> u32 {
+    let tipo_mensaje = match bytes.first() {
+        Some(tipo) => MensajeCafetera::from_bytes(*tipo),
+        None => return 0,
+    };
+    match tipo_mensaje {
+        MensajeCafetera::SUMAR => Sumar::from_bytes(bytes).map(|x| x.id_pedido),
+        MensajeCafetera::RESTAR => Restar::from_bytes(bytes).map(|x| x.id_pedido),
+        MensajeCafetera::TRANSFERIR => Transferir::from_bytes(bytes).map(|x| x.id_pedido),
+        MensajeCafetera::OKEY => OkeyToCafetera::from_bytes(bytes).map(|x| x.id_pedido),
+        MensajeCafetera::ERROR => Error::from_bytes(bytes).map(|x| x.id_pedido),
+        MensajeCafetera::CONFIRMADO => Confirmado::from_bytes(bytes).map(|x| x.id_pedido),
+        MensajeCafetera::FALLIDO => Fallido::from_bytes(bytes).map(|x| x.id_pedido),
+        _ => Ok(0),
     }
+    .unwrap_or_default()
+}
 
-    fn from_bytes(bytes: Vec<u8>) -> Self
+/// Constructor y campos comunes de los mensajes de cafetera. Los que no llevan cuenta o cantidad
+/// retornan 0
+pub trait MensajeCafeteraBytes: Codificable {
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, cantidad_modificar: u32) -> Self;
+    fn get_id_cafetera(&self) -> IdCafetera;
+    fn get_id_cuenta(&self) -> IdCuenta;
+    fn get_cantidad_modificar(&self) -> u32;
+
+    /// Mensaje de la cafetera sin cuenta ni cantidad, como los de control (sondeo, registro) y los de
+    /// administracion
+    fn de_cafetera(id_cafetera: IdCafetera) -> Self
     where
         Self: Sized,
     {
-        let id_cafetera = bytes[1];
-        let id_cuenta = u32::from_be_bytes(
-            bytes[2..6]
-                .try_into()
-                .expect("Siempre se mandan los bytes correctos"),
-        );
-        let cantidad_modificar = u32::from_be_bytes(
-            bytes[6..10]
-                .try_into()
-                .expect("Siempre se mandan los bytes correctos"),
-        );
-        Self::new(id_cafetera, id_cuenta, cantidad_modificar)
+        Self::new(id_cafetera, IdCuenta::default(), 0)
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::SUMAR)]
 /// Representa un nuevo pedido que suma creditos en una tarjeta
 pub struct Sumar {
-    /// Tipo de mensaje (sumar)
-    pub tipo_mensaje: u8,
     /// id correspondiente a la cafetera que inicio el pedido
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
     /// id de la tarjeta del usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// cantidad de creditos a ser sumados
     pub cantidad_modificar: u32,
+    /// id del pedido generado por la sucursal, para seguirlo en los logs de los nodos. El nodo lo
+    /// incluye en sus respuestas, para que la cafetera descarte las de otro pedido
+    #[serde(default)]
+    pub id_pedido: u32,
 }
 
 impl MensajeCafeteraBytes for Sumar {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
     fn get_cantidad_modificar(&self) -> u32 {
         self.cantidad_modificar
     }
-    fn new(id_cafetera: u8, id_cuenta: u32, cantidad_modificar: u32) -> Sumar {
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, cantidad_modificar: u32) -> Sumar {
         Sumar {
-            tipo_mensaje: 0,
             id_cafetera,
             id_cuenta,
             cantidad_modificar,
+            id_pedido: 0,
         }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::RESTAR)]
 /// Representa un nuevo pedido que resta creditos en una tarjeta
 pub struct Restar {
-    /// tipo de mensaje (restar)
-    pub tipo_mensaje: u8,
     /// id correspondiente a la cafetera que inicio el pedido
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
     /// id de la tarjeta del usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// cantidad de creditos a ser restados
     pub cantidad_modificar: u32,
+    /// id del pedido generado por la sucursal, para seguirlo en los logs de los nodos. El nodo lo
+    /// incluye en sus respuestas, para que la cafetera descarte las de otro pedido
+    #[serde(default)]
+    pub id_pedido: u32,
 }
 
 impl MensajeCafeteraBytes for Restar {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
     fn get_cantidad_modificar(&self) -> u32 {
         self.cantidad_modificar
     }
-    fn new(id_cafetera: u8, id_cuenta: u32, cantidad_modificar: u32) -> Restar {
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, cantidad_modificar: u32) -> Restar {
         Restar {
-            tipo_mensaje: 1,
             id_cafetera,
             id_cuenta,
             cantidad_modificar,
+            id_pedido: 0,
         }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::PING)]
 /// Representa un mensaje que se envia de la cafetera al nodo en caso de timeout
 pub struct Ping {
-    /// tipo de mensaje (ping)
-    pub tipo_mensaje: u8,
     /// id de la cafetera que envia el mensaje
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
 }
 
 impl MensajeCafeteraBytes for Ping {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
-    fn get_id_cuenta(&self) -> u32 {
-        0
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
     }
     fn get_cantidad_modificar(&self) -> u32 {
         0
     }
-    fn new(id_cafetera: u8, _id_cuenta: u32, _cantidad_modificar: u32) -> Ping {
-        Ping {
-            tipo_mensaje: 2,
-            id_cafetera,
-        }
+    fn new(id_cafetera: IdCafetera, _id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Ping {
+        Ping { id_cafetera }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::OKEY)]
 /// Representa un mensaje de Ok tanto de la cafetera al nodo, para confirmar la
 /// preparacion de un cafe, como de un nodo a cafetera para confirmar alguna etapa del pedido
 pub struct OkeyToCafetera {
-    /// tipo de mensaje (OkeyToCafetera)
-    pub tipo_mensaje: u8,
     /// id de la cafetera que inciio el pedido
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
     /// id de la cuenta del usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
+    /// id del pedido al que responde, el mismo que envio la cafetera (0 si no corresponde a un pedido)
+    #[serde(default)]
+    pub id_pedido: u32,
 }
 
-impl MensajeCafeteraBytes for OkeyToCafetera {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
+impl OkeyToCafetera {
+    /// Retorna el mensaje como respuesta al pedido indicado
+    pub fn con_id_pedido(mut self, id_pedido: u32) -> OkeyToCafetera {
+        self.id_pedido = id_pedido;
+        self
     }
-    fn get_id_cafetera(&self) -> u8 {
+}
+
+impl MensajeCafeteraBytes for OkeyToCafetera {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
     fn get_cantidad_modificar(&self) -> u32 {
         0
     }
-    fn new(id_cafetera: u8, id_cuenta: u32, _cantidad_modificar: u32) -> OkeyToCafetera {
+    fn new(
+        id_cafetera: IdCafetera,
+        id_cuenta: IdCuenta,
+        _cantidad_modificar: u32,
+    ) -> OkeyToCafetera {
         OkeyToCafetera {
-            tipo_mensaje: 3,
             id_cafetera,
             id_cuenta,
+            id_pedido: 0,
         }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::ERROR)]
 /// Mensaje que representa un error, puede ser en la preparación de un cafe (si se envia de la cafetera al nodo)
 /// o puede representar falta de saldo, falla en la transacción si se envia del nodo a la cafetera
 pub struct Error {
-    /// tipo de mensaje (error)
-    pub tipo_mensaje: u8,
     /// id de la cafetera que inicio el pedido
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
     /// id de la cuenta de usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
+    /// motivo del error, para distinguir los que el nodo no puede asociar a una transaccion en curso
+    #[serde(default)]
+    pub motivo: MotivoError,
+    /// id del pedido al que responde, el mismo que envio la cafetera (0 si no corresponde a un pedido)
+    #[serde(default)]
+    pub id_pedido: u32,
 }
 
-impl MensajeCafeteraBytes for Error {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
+impl Error {
+    /// Retorna el error con el motivo indicado
+    pub fn con_motivo(mut self, motivo: MotivoError) -> Error {
+        self.motivo = motivo;
+        self
     }
-    fn get_id_cafetera(&self) -> u8 {
+
+    /// Retorna el error como respuesta al pedido indicado
+    pub fn con_id_pedido(mut self, id_pedido: u32) -> Error {
+        self.id_pedido = id_pedido;
+        self
+    }
+}
+
+/// Motivo por el que el nodo responde un Error a la cafetera
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MotivoError {
+    /// la transaccion fue rechazada o abortada, o el cafe no se pudo preparar
+    #[default]
+    GENERICO = 0,
+    /// la cafetera confirmo (OKEY) una transaccion que el nodo no tiene en curso, por ejemplo porque
+    /// la inicio en otro nodo o la cuenta se dio de baja
+    TRANSACCIONDESCONOCIDA = 1,
+}
+
+/// Se codifica en un byte, los valores desconocidos se leen como GENERICO
+impl Campo for MotivoError {
+    fn escribir(&self, bytes: &mut Vec<u8>) {
+        (*self as u8).escribir(bytes);
+    }
+
+    fn leer(lector: &mut Lector<'_>, campo: &'static str) -> Result<Self, ErrorMensaje> {
+        Ok(match lector.leer::<u8>(campo)? {
+            1 => MotivoError::TRANSACCIONDESCONOCIDA,
+            _ => MotivoError::GENERICO,
+        })
+    }
+}
+
+impl MensajeCafeteraBytes for Error {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
     fn get_cantidad_modificar(&self) -> u32 {
         0
     }
-    fn new(id_cafetera: u8, id_cuenta: u32, _cantidad_modificar: u32) -> Error {
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Error {
         Error {
-            tipo_mensaje: 4,
             id_cafetera,
             id_cuenta,
+            motivo: MotivoError::GENERICO,
+            id_pedido: 0,
         }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::DESCONECTAR)]
 /// Mensaje que es enviado por el proceso "desconexion" que avisa a un nodo
 /// que se desconecto a la red.
 pub struct Desconectar {
-    /// tipo de mensaje (Desconectar)
-    pub tipo_mensaje: u8,
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// secuencia del comando (ver `secuencia_comando_admin`), el nodo descarta los comandos
+    /// repetidos o viejos para que no se pueda reenviar uno capturado
+    pub secuencia: u64,
 }
 
-impl MensajeCafeteraBytes for Desconectar {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
+impl Desconectar {
+    /// Retorna el mensaje con la secuencia indicada
+    pub fn con_secuencia(mut self, secuencia: u64) -> Desconectar {
+        self.secuencia = secuencia;
+        self
     }
-    fn get_id_cafetera(&self) -> u8 {
+}
+
+impl MensajeCafeteraBytes for Desconectar {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
-    fn get_id_cuenta(&self) -> u32 {
-        0
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
     }
     fn get_cantidad_modificar(&self) -> u32 {
         0
     }
-    fn new(id_cafetera: u8, _id_cuenta: u32, _cantidad_modificar: u32) -> Desconectar {
+    fn new(id_cafetera: IdCafetera, _id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Desconectar {
         Desconectar {
-            tipo_mensaje: 5,
             id_cafetera,
+            secuencia: 0,
         }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::CONECTAR)]
 /// Mensaje que es enviado por el proceso "desconexion" que avisa a un nodo
 /// que se volvio a conectar a la red.
 pub struct Conectar {
-    pub tipo_mensaje: u8,
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// secuencia del comando, como en Desconectar
+    pub secuencia: u64,
+}
+
+impl Conectar {
+    /// Retorna el mensaje con la secuencia indicada
+    pub fn con_secuencia(mut self, secuencia: u64) -> Conectar {
+        self.secuencia = secuencia;
+        self
+    }
+}
+
+/// Secuencia de un CONECTAR/DESCONECTAR enviado en este momento: los microsegundos desde el epoch
+/// unix. Crece de un comando a otro aunque se reinicie el proceso desconexion, y el nodo la compara con
+/// su propio reloj para descartar los comandos viejos
+pub fn secuencia_comando_admin() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_micros() as u64)
 }
 
 impl MensajeCafeteraBytes for Conectar {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        0
+    }
+    fn new(id_cafetera: IdCafetera, _id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Conectar {
+        Conectar {
+            id_cafetera,
+            secuencia: 0,
+        }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::SONDEO)]
+/// Mensaje liviano que envia la cafetera antes de comenzar cada pedido para saber si
+/// su nodo sigue vivo. El nodo responde con el mismo mensaje.
+pub struct Sondeo {
+    /// id de la cafetera que envia el sondeo
+    pub id_cafetera: IdCafetera,
+}
+
+impl MensajeCafeteraBytes for Sondeo {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        0
+    }
+    fn new(id_cafetera: IdCafetera, _id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Sondeo {
+        Sondeo { id_cafetera }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::CONFIRMADO)]
+/// Mensaje final que envia el nodo a la cafetera cuando una suma fue commiteada
+/// en todos los nodos del sistema
+pub struct Confirmado {
+    /// id de la cafetera que inicio el pedido
+    pub id_cafetera: IdCafetera,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+    /// cantidad de creditos implicados
+    pub cantidad_modificar: u32,
+    /// id del pedido generado por la sucursal, para seguirlo en los logs de los nodos
+    #[serde(default)]
+    pub id_pedido: u32,
+}
+
+impl MensajeCafeteraBytes for Confirmado {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        self.cantidad_modificar
+    }
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, cantidad_modificar: u32) -> Confirmado {
+        Confirmado {
+            id_cafetera,
+            id_cuenta,
+            cantidad_modificar,
+            id_pedido: 0,
+        }
     }
-    fn get_id_cafetera(&self) -> u8 {
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::FALLIDO)]
+/// Mensaje final que envia el nodo a la cafetera cuando una suma fue abortada
+/// en todos los nodos del sistema
+pub struct Fallido {
+    /// id de la cafetera que inicio el pedido
+    pub id_cafetera: IdCafetera,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+    /// cantidad de creditos implicados
+    pub cantidad_modificar: u32,
+    /// id del pedido generado por la sucursal, para seguirlo en los logs de los nodos
+    #[serde(default)]
+    pub id_pedido: u32,
+}
+
+impl MensajeCafeteraBytes for Fallido {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        self.cantidad_modificar
+    }
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, cantidad_modificar: u32) -> Fallido {
+        Fallido {
+            id_cafetera,
+            id_cuenta,
+            cantidad_modificar,
+            id_pedido: 0,
+        }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::REGISTRAR)]
+/// Mensaje con el que una cafetera se da de alta en el nodo antes de enviar pedidos.
+/// El nodo responde con el mismo mensaje
+pub struct Registrar {
+    /// id de la cafetera que se registra
+    pub id_cafetera: IdCafetera,
+}
+
+impl MensajeCafeteraBytes for Registrar {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
         0
     }
+    fn new(id_cafetera: IdCafetera, _id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Registrar {
+        Registrar { id_cafetera }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::DESREGISTRAR)]
+/// Mensaje con el que una cafetera se da de baja del nodo al terminar sus pedidos.
+/// El nodo responde con el mismo mensaje
+pub struct Desregistrar {
+    /// id de la cafetera que se da de baja
+    pub id_cafetera: IdCafetera,
+}
+
+impl MensajeCafeteraBytes for Desregistrar {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
+    }
     fn get_cantidad_modificar(&self) -> u32 {
         0
     }
-    fn new(id_cafetera: u8, _id_cuenta: u32, _cantidad_modificar: u32) -> Conectar {
-        Conectar {
-            tipo_mensaje: 6,
+    fn new(
+        id_cafetera: IdCafetera,
+        _id_cuenta: IdCuenta,
+        _cantidad_modificar: u32,
+    ) -> Desregistrar {
+        Desregistrar { id_cafetera }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::ESTADO)]
+/// Mensaje que consulta el estado de un nodo. El nodo responde con un texto que
+/// lista las cafeteras registradas
+pub struct Estado {
+    /// id de la cafetera (o herramienta) que consulta el estado
+    pub id_cafetera: IdCafetera,
+}
+
+impl MensajeCafeteraBytes for Estado {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        0
+    }
+    fn new(id_cafetera: IdCafetera, _id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Estado {
+        Estado { id_cafetera }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::TRANSFERIR)]
+/// Mensaje que envia la cafetera para transferir creditos de una cuenta a otra en una unica
+/// transaccion. Se agrega la cuenta destino a continuacion del id de pedido
+pub struct Transferir {
+    /// id correspondiente a la cafetera que inicio el pedido
+    pub id_cafetera: IdCafetera,
+    /// id de la cuenta de la que se restan los creditos
+    pub id_cuenta: IdCuenta,
+    /// cantidad de creditos a ser transferidos
+    pub cantidad_modificar: u32,
+    /// id del pedido generado por la sucursal, para seguirlo en los logs de los nodos. El nodo lo
+    /// incluye en sus respuestas, para que la cafetera descarte las de otro pedido
+    #[serde(default)]
+    pub id_pedido: u32,
+    /// id de la cuenta a la que se suman los creditos
+    pub id_cuenta_destino: IdCuenta,
+}
+
+impl MensajeCafeteraBytes for Transferir {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        self.cantidad_modificar
+    }
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, cantidad_modificar: u32) -> Transferir {
+        Transferir {
             id_cafetera,
+            id_cuenta,
+            cantidad_modificar,
+            id_pedido: 0,
+            id_cuenta_destino: IdCuenta::default(),
+        }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::ALTA)]
+/// Mensaje de administracion para dar de alta una cuenta en todos los nodos
+pub struct AltaCuenta {
+    /// id de la cafetera (o herramienta de administracion) que envia el mensaje
+    pub id_cafetera: IdCafetera,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+}
+
+impl MensajeCafeteraBytes for AltaCuenta {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        0
+    }
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, _cantidad_modificar: u32) -> AltaCuenta {
+        AltaCuenta {
+            id_cafetera,
+            id_cuenta,
+        }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::BAJA)]
+/// Mensaje de administracion para dar de baja una cuenta en todos los nodos
+pub struct BajaCuenta {
+    /// id de la cafetera (o herramienta de administracion) que envia el mensaje
+    pub id_cafetera: IdCafetera,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+}
+
+impl MensajeCafeteraBytes for BajaCuenta {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        0
+    }
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, _cantidad_modificar: u32) -> BajaCuenta {
+        BajaCuenta {
+            id_cafetera,
+            id_cuenta,
+        }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::CONSULTAR)]
+/// Consulta del saldo de una cuenta, el nodo responde con el mismo mensaje y el saldo en cantidad_modificar
+pub struct Consultar {
+    /// id de la cafetera (o herramienta de administracion) que envia el mensaje
+    pub id_cafetera: IdCafetera,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+    /// saldo de la cuenta, solo en la respuesta del nodo
+    pub cantidad_modificar: u32,
+}
+
+impl MensajeCafeteraBytes for Consultar {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        self.cantidad_modificar
+    }
+    fn new(id_cafetera: IdCafetera, id_cuenta: IdCuenta, cantidad_modificar: u32) -> Consultar {
+        Consultar {
+            id_cafetera,
+            id_cuenta,
+            cantidad_modificar,
+        }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::DRENAR)]
+/// Mensaje que es enviado por el proceso "desconexion" para que un nodo termine sus transacciones
+/// en curso, deje de aceptar pedidos y se retire de la red
+pub struct Drenar {
+    pub id_cafetera: IdCafetera,
+}
+
+impl MensajeCafeteraBytes for Drenar {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        0
+    }
+    fn new(id_cafetera: IdCafetera, _id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Drenar {
+        Drenar { id_cafetera }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::DESAFIO)]
+/// Pedido de un desafio para autenticar un comando de administracion (ver `autenticacion`). La
+/// herramienta lo envia con desafio 0 y el nodo le responde con uno nuevo
+pub struct Desafio {
+    pub id_cafetera: IdCafetera,
+    /// numero al azar que la herramienta firma junto con el comando, vale para un solo comando
+    pub desafio: u64,
+}
+
+impl Desafio {
+    /// Retorna el mensaje con el desafio indicado
+    pub fn con_desafio(mut self, desafio: u64) -> Desafio {
+        self.desafio = desafio;
+        self
+    }
+}
+
+impl MensajeCafeteraBytes for Desafio {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        0
+    }
+    fn new(id_cafetera: IdCafetera, _id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Desafio {
+        Desafio {
+            id_cafetera,
+            desafio: 0,
+        }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeCafetera::AUTENTICADO)]
+/// Comando de administracion firmado con el secreto compartido del cluster. El nodo verifica la firma
+/// con el ultimo desafio que le envio a ese socket y procesa el comando como si hubiera llegado solo
+pub struct Autenticado {
+    pub id_cafetera: IdCafetera,
+    /// HMAC-SHA256 del desafio y del comando
+    pub firma: Vec<u8>,
+    /// bytes del comando (Conectar, Desconectar, Estado o Drenar)
+    pub comando: Vec<u8>,
+}
+
+impl Autenticado {
+    /// Retorna el mensaje con el comando y su firma
+    pub fn con_comando(mut self, comando: Vec<u8>, firma: Vec<u8>) -> Autenticado {
+        self.comando = comando;
+        self.firma = firma;
+        self
+    }
+}
+
+impl MensajeCafeteraBytes for Autenticado {
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        IdCuenta::default()
+    }
+    fn get_cantidad_modificar(&self) -> u32 {
+        0
+    }
+    fn new(id_cafetera: IdCafetera, _id_cuenta: IdCuenta, _cantidad_modificar: u32) -> Autenticado {
+        Autenticado {
+            id_cafetera,
+            firma: vec![],
+            comando: vec![],
         }
     }
 }
 
 #[cfg(test)]
 mod mensajes_cafetera_test {
-    use crate::mensajes_cafetera::{Error, OkeyToCafetera, Ping, Restar};
+    use crate::codificable::Codificable;
+    use crate::mensajes_cafetera::{
+        id_pedido_from_bytes, AltaCuenta, Autenticado, BajaCuenta, Confirmado, Consultar, Desafio,
+        Desregistrar, Drenar, Error, Fallido, MotivoError, OkeyToCafetera, Ping, Registrar, Restar,
+        Sondeo, Transferir,
+    };
+
+    use crate::error_mensaje::ErrorMensaje;
+    use crate::ids::{IdCafetera, IdCuenta};
 
     use super::{MensajeCafeteraBytes, Sumar};
 
     #[test]
     fn sumar_to_bytes() {
-        let test_pkt = Sumar::new(10, 3, 100);
-        let expected = vec![0, 10, 0, 0, 0, 3, 0, 0, 0, 100];
+        let mut test_pkt = Sumar::new(IdCafetera(10), IdCuenta(3), 100);
+        test_pkt.id_pedido = 7;
+        let expected = vec![0, 10, 0, 0, 0, 3, 0, 0, 0, 100, 0, 0, 0, 7];
 
         assert_eq!(expected, test_pkt.to_bytes())
     }
 
     #[test]
     fn sumar_from_bytes() {
-        let expected = Sumar::new(100, 5, 50);
-        let bytes = vec![0, 100, 0, 0, 0, 5, 0, 0, 0, 50];
-        let final_pkt = Sumar::from_bytes(bytes);
+        let expected = Sumar::new(IdCafetera(100), IdCuenta(5), 50);
+        let bytes = vec![0, 100, 0, 0, 0, 5, 0, 0, 0, 50, 0, 0, 1, 0];
+        let final_pkt = Sumar::from_bytes(&bytes).unwrap();
 
-        assert_eq!(expected.tipo_mensaje, final_pkt.tipo_mensaje);
+        assert_eq!(Sumar::TIPO, bytes[0]);
         assert_eq!(expected.id_cafetera, final_pkt.id_cafetera);
         assert_eq!(expected.id_cuenta, final_pkt.id_cuenta);
-        assert_eq!(expected.cantidad_modificar, final_pkt.cantidad_modificar)
+        assert_eq!(expected.cantidad_modificar, final_pkt.cantidad_modificar);
+        assert_eq!(256, final_pkt.id_pedido)
     }
 
     #[test]
     fn restar_to_bytes() {
-        let test_pkt = Restar::new(10, 3, 100);
-        let expected = vec![1, 10, 0, 0, 0, 3, 0, 0, 0, 100];
+        let test_pkt = Restar::new(IdCafetera(10), IdCuenta(3), 100);
+        let expected = vec![1, 10, 0, 0, 0, 3, 0, 0, 0, 100, 0, 0, 0, 0];
 
         assert_eq!(expected, test_pkt.to_bytes())
     }
 
     #[test]
     fn restar_from_bytes() {
-        let expected = Restar::new(100, 5, 50);
-        let bytes = vec![1, 100, 0, 0, 0, 5, 0, 0, 0, 50];
-        let final_pkt = Restar::from_bytes(bytes);
+        let expected = Restar::new(IdCafetera(100), IdCuenta(5), 50);
+        let bytes = vec![1, 100, 0, 0, 0, 5, 0, 0, 0, 50, 0, 0, 0, 0];
+        let final_pkt = Restar::from_bytes(&bytes).unwrap();
 
-        assert_eq!(expected.tipo_mensaje, final_pkt.tipo_mensaje);
+        assert_eq!(Restar::TIPO, bytes[0]);
         assert_eq!(expected.id_cafetera, final_pkt.id_cafetera);
         assert_eq!(expected.id_cuenta, final_pkt.id_cuenta);
         assert_eq!(expected.cantidad_modificar, final_pkt.cantidad_modificar)
@@ -345,55 +874,235 @@ mod mensajes_cafetera_test {
 
     #[test]
     fn ping_to_bytes() {
-        let test_pkt = Ping::new(10, 3, 100);
-        let expected = vec![2, 10, 0, 0, 0, 0, 0, 0, 0, 0];
+        let test_pkt = Ping::new(IdCafetera(10), IdCuenta(3), 100);
+        let expected = vec![2, 10];
 
         assert_eq!(expected, test_pkt.to_bytes())
     }
 
     #[test]
     fn ping_from_bytes() {
-        let expected = Ping::new(100, 5, 50);
-        let bytes = vec![2, 100, 0, 0, 0, 0, 0, 0, 0, 0];
-        let final_pkt = Ping::from_bytes(bytes);
+        let expected = Ping::new(IdCafetera(100), IdCuenta(5), 50);
+        let bytes = vec![2, 100];
+        let final_pkt = Ping::from_bytes(&bytes).unwrap();
 
-        assert_eq!(expected.tipo_mensaje, final_pkt.tipo_mensaje);
+        assert_eq!(Ping::TIPO, bytes[0]);
         assert_eq!(expected.id_cafetera, final_pkt.id_cafetera);
     }
 
     #[test]
     fn okey_to_bytes() {
-        let test_pkt = OkeyToCafetera::new(10, 3, 100);
-        let expected = vec![3, 10, 0, 0, 0, 0, 0, 0, 0, 0];
+        let test_pkt = OkeyToCafetera::new(IdCafetera(10), IdCuenta(3), 100).con_id_pedido(7);
+        let expected = vec![3, 10, 0, 0, 0, 3, 0, 0, 0, 7];
 
         assert_eq!(expected, test_pkt.to_bytes())
     }
 
     #[test]
     fn okey_from_bytes() {
-        let expected = OkeyToCafetera::new(100, 5, 50);
-        let bytes = vec![3, 100, 0, 0, 0, 0, 0, 0, 0, 0];
-        let final_pkt = OkeyToCafetera::from_bytes(bytes);
+        let expected = OkeyToCafetera::new(IdCafetera(100), IdCuenta(5), 50);
+        let bytes = vec![3, 100, 0, 0, 0, 5, 0, 0, 1, 2];
+        let final_pkt = OkeyToCafetera::from_bytes(&bytes).unwrap();
 
-        assert_eq!(expected.tipo_mensaje, final_pkt.tipo_mensaje);
+        assert_eq!(OkeyToCafetera::TIPO, bytes[0]);
         assert_eq!(expected.id_cafetera, final_pkt.id_cafetera);
+        assert_eq!(expected.id_cuenta, final_pkt.id_cuenta);
+        assert_eq!(258, final_pkt.id_pedido);
     }
 
     #[test]
     fn error_to_bytes() {
-        let test_pkt = Error::new(10, 3, 100);
-        let expected = vec![4, 10, 0, 0, 0, 0, 0, 0, 0, 0];
+        let test_pkt = Error::new(IdCafetera(10), IdCuenta(3), 100)
+            .con_motivo(MotivoError::TRANSACCIONDESCONOCIDA);
+        let expected = vec![4, 10, 0, 0, 0, 3, 1, 0, 0, 0, 0];
 
         assert_eq!(expected, test_pkt.to_bytes())
     }
 
     #[test]
     fn error_from_bytes() {
-        let expected = Error::new(100, 5, 50);
-        let bytes = vec![4, 100, 0, 0, 0, 0, 0, 0, 0, 0];
-        let final_pkt = Error::from_bytes(bytes);
+        let expected = Error::new(IdCafetera(100), IdCuenta(5), 50);
+        let bytes = vec![4, 100, 0, 0, 0, 5, 0, 0, 0, 0, 9];
+        let final_pkt = Error::from_bytes(&bytes).unwrap();
 
-        assert_eq!(expected.tipo_mensaje, final_pkt.tipo_mensaje);
+        assert_eq!(Error::TIPO, bytes[0]);
         assert_eq!(expected.id_cafetera, final_pkt.id_cafetera);
+        assert_eq!(expected.id_cuenta, final_pkt.id_cuenta);
+        assert_eq!(MotivoError::GENERICO, final_pkt.motivo);
+        assert_eq!(9, final_pkt.id_pedido);
+    }
+
+    #[test]
+    fn sondeo_to_bytes() {
+        let test_pkt = Sondeo::new(IdCafetera(10), IdCuenta(3), 100);
+        let expected = vec![7, 10];
+
+        assert_eq!(expected, test_pkt.to_bytes())
+    }
+
+    #[test]
+    fn sondeo_from_bytes() {
+        let bytes = vec![7, 100];
+        let final_pkt = Sondeo::from_bytes(&bytes).unwrap();
+
+        assert_eq!(7, Sondeo::TIPO);
+        assert_eq!(IdCafetera(100), final_pkt.id_cafetera);
+    }
+
+    #[test]
+    fn confirmado_to_bytes() {
+        let mut test_pkt = Confirmado::new(IdCafetera(10), IdCuenta(3), 100);
+        test_pkt.id_pedido = 12;
+        let expected = vec![8, 10, 0, 0, 0, 3, 0, 0, 0, 100, 0, 0, 0, 12];
+
+        assert_eq!(expected, test_pkt.to_bytes())
+    }
+
+    #[test]
+    fn fallido_from_bytes() {
+        let expected = Fallido::new(IdCafetera(100), IdCuenta(5), 50);
+        let bytes = vec![9, 100, 0, 0, 0, 5, 0, 0, 0, 50, 0, 0, 0, 0];
+        let final_pkt = Fallido::from_bytes(&bytes).unwrap();
+
+        assert_eq!(Fallido::TIPO, bytes[0]);
+        assert_eq!(expected.id_cafetera, final_pkt.id_cafetera);
+        assert_eq!(expected.id_cuenta, final_pkt.id_cuenta);
+        assert_eq!(expected.cantidad_modificar, final_pkt.cantidad_modificar)
+    }
+
+    #[test]
+    fn registrar_to_bytes() {
+        let test_pkt = Registrar::new(IdCafetera(10), IdCuenta(3), 100);
+        let expected = vec![10, 10];
+
+        assert_eq!(expected, test_pkt.to_bytes())
+    }
+
+    #[test]
+    fn desregistrar_from_bytes() {
+        let bytes = vec![11, 100];
+        let final_pkt = Desregistrar::from_bytes(&bytes).unwrap();
+
+        assert_eq!(11, Desregistrar::TIPO);
+        assert_eq!(IdCafetera(100), final_pkt.id_cafetera);
+    }
+
+    #[test]
+    fn transferir_to_bytes() {
+        let mut test_pkt = Transferir::new(IdCafetera(10), IdCuenta(3), 100);
+        test_pkt.id_pedido = 7;
+        test_pkt.id_cuenta_destino = IdCuenta(4);
+        let expected = vec![13, 10, 0, 0, 0, 3, 0, 0, 0, 100, 0, 0, 0, 7, 0, 0, 0, 4];
+
+        assert_eq!(expected, test_pkt.to_bytes())
+    }
+
+    #[test]
+    fn transferir_from_bytes() {
+        let bytes = vec![13, 100, 0, 0, 0, 5, 0, 0, 0, 50, 0, 0, 0, 9, 0, 0, 1, 0];
+        let final_pkt = Transferir::from_bytes(&bytes).unwrap();
+
+        assert_eq!(13, Transferir::TIPO);
+        assert_eq!(IdCafetera(100), final_pkt.id_cafetera);
+        assert_eq!(IdCuenta(5), final_pkt.id_cuenta);
+        assert_eq!(50, final_pkt.cantidad_modificar);
+        assert_eq!(9, final_pkt.id_pedido);
+        assert_eq!(IdCuenta(256), final_pkt.id_cuenta_destino)
+    }
+
+    #[test]
+    fn alta_cuenta_to_bytes() {
+        let test_pkt = AltaCuenta::new(IdCafetera(0), IdCuenta(11), 0);
+        let expected = vec![14, 0, 0, 0, 0, 11];
+
+        assert_eq!(expected, test_pkt.to_bytes())
+    }
+
+    #[test]
+    fn baja_cuenta_from_bytes() {
+        let bytes = vec![15, 0, 0, 0, 1, 0];
+        let final_pkt = BajaCuenta::from_bytes(&bytes).unwrap();
+
+        assert_eq!(15, BajaCuenta::TIPO);
+        assert_eq!(IdCuenta(256), final_pkt.id_cuenta)
+    }
+
+    #[test]
+    fn consultar_from_bytes() {
+        let bytes = vec![16, 0, 0, 0, 0, 3, 0, 0, 39, 16];
+        let final_pkt = Consultar::from_bytes(&bytes).unwrap();
+
+        assert_eq!(16, Consultar::TIPO);
+        assert_eq!(IdCuenta(3), final_pkt.id_cuenta);
+        assert_eq!(10000, final_pkt.cantidad_modificar)
+    }
+
+    #[test]
+    fn drenar_to_bytes() {
+        let test_pkt = Drenar::new(IdCafetera(0), IdCuenta(0), 0);
+        let expected = vec![17, 0];
+
+        assert_eq!(expected, test_pkt.to_bytes())
+    }
+
+    #[test]
+    fn autenticado_lleva_la_firma_y_el_comando_con_sus_largos() {
+        let test_pkt =
+            Autenticado::new(IdCafetera(255), IdCuenta(0), 0).con_comando(vec![17, 0], vec![9; 3]);
+        let bytes = test_pkt.to_bytes();
+        let expected = vec![19, 255, 0, 0, 0, 3, 9, 9, 9, 0, 0, 0, 2, 17, 0];
+
+        assert_eq!(expected, bytes);
+        let final_pkt = Autenticado::from_bytes(&bytes).unwrap();
+        assert_eq!(vec![17, 0], final_pkt.comando);
+        assert_eq!(
+            vec![18, 0, 0, 0, 0, 0, 0, 0, 0, 7],
+            Desafio::new(IdCafetera(0), IdCuenta(0), 0)
+                .con_desafio(7)
+                .to_bytes()
+        );
+    }
+
+    #[test]
+    fn from_bytes_incompleto_o_de_otro_tipo_retorna_error() {
+        assert_eq!(
+            Err(ErrorMensaje::Incompleto("cantidad_modificar")),
+            Sumar::from_bytes(&[0, 1, 0, 0, 0, 3, 0]).map(|x| x.id_cuenta)
+        );
+        assert_eq!(
+            Err(ErrorMensaje::Incompleto("id_pedido")),
+            Sumar::from_bytes(&[0, 1, 0, 0, 0, 3, 0, 0, 0, 1, 0]).map(|x| x.id_cuenta)
+        );
+        assert_eq!(
+            Err(ErrorMensaje::CampoInvalido("tipo_mensaje")),
+            Restar::from_bytes(&[0, 1, 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 0]).map(|x| x.id_cuenta)
+        );
+        assert!(Transferir::from_bytes(&[13, 1, 0, 0, 0, 3, 0, 0, 0, 1]).is_err());
+        assert!(Ping::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn id_pedido_de_pedidos_y_respuestas() {
+        let mut sumar = Sumar::new(IdCafetera(1), IdCuenta(3), 10);
+        sumar.id_pedido = 2000015;
+        let mut confirmado = Confirmado::new(IdCafetera(1), IdCuenta(3), 10);
+        confirmado.id_pedido = 2000016;
+
+        assert_eq!(2000015, id_pedido_from_bytes(&sumar.to_bytes()));
+        assert_eq!(2000016, id_pedido_from_bytes(&confirmado.to_bytes()));
+        assert_eq!(
+            7,
+            id_pedido_from_bytes(
+                &Error::new(IdCafetera(0), IdCuenta(0), 0)
+                    .con_id_pedido(7)
+                    .to_bytes()
+            )
+        );
+        assert_eq!(
+            0,
+            id_pedido_from_bytes(&Sondeo::new(IdCafetera(1), IdCuenta(0), 0).to_bytes())
+        );
+        assert_eq!(0, id_pedido_from_bytes(&[0, 1, 0]));
+        assert_eq!(0, id_pedido_from_bytes(&[]));
     }
 }
@@ -0,0 +1,115 @@
+use compartido::config::config;
+use compartido::logs::init_tracing;
+use futures_util::{SinkExt, StreamExt};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{error, info, warn};
+
+/// Cantidad de eventos que se guardan para un navegador lento antes de descartar los mas viejos
+const CAPACIDAD_EVENTOS: usize = 256;
+/// Pagina que se sirve a los navegadores, con el puerto del websocket en lugar de PUERTO_WEBSOCKET
+const PAGINA: &str = include_str!("../pagina.html");
+const PUERTO_WEBSOCKET: &str = "{PUERTO_WEBSOCKET}";
+
+/// Recibe los eventos que publican los nodos por udp y los reenvia a los navegadores conectados
+async fn recibir_eventos(sender: broadcast::Sender<String>) -> std::io::Result<()> {
+    let socket = UdpSocket::bind(&config().servidor.direccion_eventos_dashboard).await?;
+    let mut buffer = [0u8; 2048];
+    loop {
+        let (cantidad_leida, _) = socket.recv_from(&mut buffer).await?;
+        let evento = String::from_utf8_lossy(&buffer[..cantidad_leida]).to_string();
+        info!("[DASHBOARD] {}", evento);
+        // si no hay navegadores conectados el evento se descarta
+        let _res = sender.send(evento);
+    }
+}
+
+/// Responde cualquier pedido http con la pagina del dashboard
+async fn servir_pagina(mut stream: TcpStream, pagina: Arc<str>) -> std::io::Result<()> {
+    let mut pedido = [0u8; 1024];
+    let _leido = stream.read(&mut pedido).await?;
+    let respuesta = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        pagina.len(),
+        pagina
+    );
+    stream.write_all(respuesta.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Envia al navegador cada evento recibido hasta que se desconecte
+async fn atender_navegador(stream: TcpStream, mut receiver: broadcast::Receiver<String>) {
+    let ws = match tokio_tungstenite::accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(err) => {
+            error!(
+                "[DASHBOARD] Error al aceptar el websocket | Detalle: {}",
+                err
+            );
+            return;
+        }
+    };
+    let (mut escritura, mut lectura) = ws.split();
+    loop {
+        tokio::select! {
+            evento = receiver.recv() => match evento {
+                Ok(evento) => {
+                    if escritura.send(Message::Text(evento)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(perdidos)) => {
+                    warn!("[DASHBOARD] Un navegador lento perdio {} eventos", perdidos);
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            // el navegador no envia nada, solo se espera a que cierre la conexion
+            mensaje = lectura.next() => match mensaje {
+                Some(Ok(_)) => {}
+                _ => return,
+            },
+        }
+    }
+}
+
+/// Proceso que recibe los eventos de los nodos (elecciones, commits, aborts, desconexiones) y los
+/// muestra en tiempo real en los navegadores conectados a dashboard.direccion_http
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    init_tracing("dashboard");
+    let (sender, _) = broadcast::channel(CAPACIDAD_EVENTOS);
+
+    let sender_eventos = sender.clone();
+    tokio::spawn(async move {
+        if let Err(err) = recibir_eventos(sender_eventos).await {
+            error!("[DASHBOARD] Error al recibir eventos | Detalle: {}", err);
+        }
+    });
+
+    let direcciones = &config().dashboard;
+    let websocket = TcpListener::bind(&direcciones.direccion_websocket).await?;
+    let pagina: Arc<str> = PAGINA
+        .replace(
+            PUERTO_WEBSOCKET,
+            &websocket.local_addr()?.port().to_string(),
+        )
+        .into();
+    let http = TcpListener::bind(&direcciones.direccion_http).await?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = http.accept().await {
+            tokio::spawn(servir_pagina(stream, pagina.clone()));
+        }
+    });
+
+    info!(
+        "[DASHBOARD] Abrir http://{} en el navegador",
+        direcciones.direccion_http
+    );
+    while let Ok((stream, _)) = websocket.accept().await {
+        tokio::spawn(atender_navegador(stream, sender.subscribe()));
+    }
+    Ok(())
+}
@@ -1,14 +1,61 @@
-use std::{io, net::UdpSocket};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use std::{env, io, net::UdpSocket};
 
-use compartido::mensajes_cafetera::{Conectar, Desconectar, MensajeCafeteraBytes};
+use compartido::autenticacion::{enviar_comando_admin, SIN_SECRETO};
+use compartido::codec::MAX_BYTES_MENSAJE;
+use compartido::codificable::Codificable;
+use compartido::config::{
+    config, separar_direcciones_nodos, CONSULTA_AUDITORIA, CONSULTA_REGISTRO, CONSULTA_SALDOS,
+    CONSULTA_TRANSACCIONES, ID_CAFETERA_ADMINISTRACION, ID_CONSULTA_ADMIN,
+};
+use compartido::ids::{IdCafetera, IdCuenta};
+use compartido::logs::init_tracing;
+use compartido::mensajes_cafetera::{
+    secuencia_comando_admin, AltaCuenta, BajaCuenta, Conectar, Consultar, Desconectar, Drenar,
+    Estado, MensajeCafetera, MensajeCafeteraBytes, Transferir,
+};
+use tracing::{info, warn};
 
-/// Proceso que recibe un tipo de mensaje -> conectar (c) o desconectar (d) y un número de nodo
-/// y envía por udp el mensaje correspondiente
+/// Proceso que recibe un tipo de mensaje -> conectar (c), desconectar (d), consultar estado (e),
+/// dar de alta (a) o de baja (b) una cuenta, consultar su saldo (s) o retirar el nodo (r) y un número
+/// de nodo (y de cuenta en el caso del alta, la baja y el saldo) y envía por udp el mensaje correspondiente.
+/// Los comandos c, d, e, r, a, b y t se firman con el secreto compartido del cluster (ver
+/// `autenticacion`), y no se pueden enviar mientras no este configurado.
+/// Con `t` corrige saldos transfiriendo creditos entre dos cuentas y espera el resultado. Los pedidos se
+/// envian firmados y con ID_CAFETERA_ADMINISTRACION, por lo que se adelantan a las restas de las cafeteras.
+/// Con `l` consulta por tcp el registro de commits del coordinador en ese ID_NODO, opcionalmente desde
+/// una secuencia, y lo muestra como lineas json. Con `v` le pide al coordinador una auditoria de los
+/// saldos de todos los nodos y muestra su informe. Con `p` muestra las transacciones en curso del
+/// coordinador, con la fase de cada una y los nodos de los que espera respuesta. Con `g` le pide al
+/// coordinador el snapshot de los saldos del cluster, con los nodos que difieren en cada cuenta.
+/// Con `--nodo host:puerto` los mensajes se envian a esa direccion en lugar de la que se arma con el
+/// ID_NODO y la configuracion del cluster, por ejemplo para un nodo en otro host
 fn main() {
-    let socket = UdpSocket::bind("127.0.0.1:1222").expect("Error al crear el socket");
+    init_tracing("desconexion");
+    let direccion_nodo = match separar_direcciones_nodos(env::args().skip(1)) {
+        Ok((mut direcciones, _)) if direcciones.len() <= 1 => direcciones.pop(),
+        Ok(_) => {
+            warn!("Se puede indicar un solo --nodo");
+            return;
+        }
+        Err(err) => {
+            warn!("{}", err);
+            return;
+        }
+    };
+    if config().cluster.secreto_admin.is_empty() {
+        warn!(
+            "{}, no se pueden enviar los comandos c, d, e, r, a, b y t",
+            SIN_SECRETO
+        );
+    }
+    let socket =
+        UdpSocket::bind(&config().desconexion.direccion).expect("Error al crear el socket");
 
     loop {
-        println!("Ingrese la accion a realizar (d/c) seguido del ID_NODO a realizar la accion: ");
+        println!("Ingrese la accion a realizar (d/c/e/a/b/s/t/r/l/v/p/g) seguido del ID_NODO a realizar la accion (y del ID_CUENTA para a/b/s, ID_CUENTA ID_CUENTA_DESTINO CANTIDAD para t, o la secuencia desde la que consultar para l): ");
         let stdin = io::stdin();
         let mut _valores: Vec<&str> = vec![];
         let mut valor = String::new();
@@ -16,29 +63,316 @@ fn main() {
         stdin.read_line(&mut valor).expect("Error al leer el input");
         _valores = valor.split(' ').collect();
         if _valores.len() < 2 {
-            println!("Necesito el tipo de mensaje (d/c) y el ID_NODO");
+            warn!("Necesito el tipo de mensaje (d/c/e/a/b/s/t/r/l/v/p/g) y el ID_NODO");
             continue;
         }
         let tipo: String = _valores[0].trim().to_string().to_lowercase();
         let nodo: String = _valores[1].trim().to_string().to_lowercase();
 
-        let ip: String = "127.0.0.1:1235".to_string() + &nodo;
+        if tipo == 'l'.to_string() {
+            let id_nodo: u8 = match nodo.parse() {
+                Ok(x) => x,
+                Err(_) => {
+                    warn!("El ID_NODO debe ser un numero");
+                    continue;
+                }
+            };
+            let desde: u64 = match _valores.get(2).map(|x| x.trim().parse()) {
+                Some(Ok(x)) => x,
+                Some(Err(_)) => {
+                    warn!("La secuencia desde la que consultar debe ser un numero");
+                    continue;
+                }
+                None => 0,
+            };
+            let mut consulta = vec![ID_CONSULTA_ADMIN, CONSULTA_REGISTRO];
+            consulta.extend_from_slice(&desde.to_be_bytes());
+            let espera = Duration::from_millis(config().desconexion.timeout_estado_millis);
+            match consultar_coordinador(id_nodo, &consulta, espera) {
+                Ok(registro) => info!(
+                    "Registro de commits del coordinador en ID_NODO = {}:\n{}",
+                    nodo, registro
+                ),
+                Err(err) => warn!(
+                    "El coordinador en ID_NODO = {} no respondio la consulta del registro: {}",
+                    nodo, err
+                ),
+            }
+            continue;
+        }
+        if tipo == 'v'.to_string() {
+            let id_nodo: u8 = match nodo.parse() {
+                Ok(x) => x,
+                Err(_) => {
+                    warn!("El ID_NODO debe ser un numero");
+                    continue;
+                }
+            };
+            // el coordinador responde recien al cerrar la auditoria
+            let espera = Duration::from_millis(
+                config().desconexion.timeout_estado_millis
+                    + config().servidor.tiempo_maximo_auditoria_millis,
+            );
+            match consultar_coordinador(id_nodo, &[ID_CONSULTA_ADMIN, CONSULTA_AUDITORIA], espera) {
+                Ok(informe) => info!(
+                    "Auditoria del coordinador en ID_NODO = {}:\n{}",
+                    nodo, informe
+                ),
+                Err(err) => warn!(
+                    "El coordinador en ID_NODO = {} no respondio la auditoria: {}",
+                    nodo, err
+                ),
+            }
+            continue;
+        }
+        if tipo == 'g'.to_string() {
+            let id_nodo: u8 = match nodo.parse() {
+                Ok(x) => x,
+                Err(_) => {
+                    warn!("El ID_NODO debe ser un numero");
+                    continue;
+                }
+            };
+            // como en la auditoria, el coordinador responde al recibir los saldos de todos los nodos
+            let espera = Duration::from_millis(
+                config().desconexion.timeout_estado_millis
+                    + config().servidor.tiempo_maximo_auditoria_millis,
+            );
+            match consultar_coordinador(id_nodo, &[ID_CONSULTA_ADMIN, CONSULTA_SALDOS], espera) {
+                Ok(snapshot) => info!(
+                    "Saldos del cluster segun el coordinador en ID_NODO = {}:\n{}",
+                    nodo, snapshot
+                ),
+                Err(err) => warn!(
+                    "El coordinador en ID_NODO = {} no respondio la consulta de saldos: {}",
+                    nodo, err
+                ),
+            }
+            continue;
+        }
+        if tipo == 'p'.to_string() {
+            let id_nodo: u8 = match nodo.parse() {
+                Ok(x) => x,
+                Err(_) => {
+                    warn!("El ID_NODO debe ser un numero");
+                    continue;
+                }
+            };
+            let espera = Duration::from_millis(config().desconexion.timeout_estado_millis);
+            match consultar_coordinador(
+                id_nodo,
+                &[ID_CONSULTA_ADMIN, CONSULTA_TRANSACCIONES],
+                espera,
+            ) {
+                Ok(transacciones) if transacciones.is_empty() => info!(
+                    "El coordinador en ID_NODO = {} no tiene transacciones en curso",
+                    nodo
+                ),
+                Ok(transacciones) => info!(
+                    "Transacciones en curso del coordinador en ID_NODO = {}:\n{}",
+                    nodo, transacciones
+                ),
+                Err(err) => warn!(
+                    "El coordinador en ID_NODO = {} no respondio la consulta de transacciones: {}",
+                    nodo, err
+                ),
+            }
+            continue;
+        }
+
+        let ip = match (nodo.parse(), &direccion_nodo) {
+            (Ok(_), Some(direccion)) => direccion.clone(),
+            (Ok(id_nodo), None) => config().cluster.addr_lectura_datos(id_nodo),
+            (Err(_), _) => {
+                warn!("El ID_NODO debe ser un numero");
+                continue;
+            }
+        };
 
         if tipo == 'd'.to_string() {
-            let msg = Desconectar::new(0, 0, 0).to_bytes();
-            socket
-                .send_to(&msg, ip)
-                .expect("Error fatal al enviar el mensaje");
-            println!("Envio DESCONECTAR al ID_NODO = {}", nodo);
+            let msg = Desconectar::de_cafetera(IdCafetera::default())
+                .con_secuencia(secuencia_comando_admin())
+                .to_bytes();
+            if !enviar_comando(&socket, &msg, &ip, &nodo) {
+                continue;
+            }
+            info!("Envio DESCONECTAR al ID_NODO = {}", nodo);
         } else if tipo == 'c'.to_string() {
-            let msg = Conectar::new(0, 0, 0).to_bytes();
+            let msg = Conectar::de_cafetera(IdCafetera::default())
+                .con_secuencia(secuencia_comando_admin())
+                .to_bytes();
+            if !enviar_comando(&socket, &msg, &ip, &nodo) {
+                continue;
+            }
+            info!("Envio CONECTAR al ID_NODO = {}", nodo);
+        } else if tipo == 'r'.to_string() {
+            let msg = Drenar::de_cafetera(IdCafetera::default()).to_bytes();
+            if !enviar_comando(&socket, &msg, &ip, &nodo) {
+                continue;
+            }
+            info!("Envio DRENAR al ID_NODO = {}", nodo);
+        } else if tipo == 'e'.to_string() {
+            let msg = Estado::de_cafetera(IdCafetera::default()).to_bytes();
+            if !enviar_comando(&socket, &msg, &ip, &nodo) {
+                continue;
+            }
+            socket
+                .set_read_timeout(Some(Duration::from_millis(
+                    config().desconexion.timeout_estado_millis,
+                )))
+                .expect("Error al setear el timeout del socket");
+            let mut buffer = [0u8; 4096];
+            match socket.recv_from(&mut buffer) {
+                Ok((cantidad_leida, _)) => info!(
+                    "Estado del ID_NODO = {}:\n{}",
+                    nodo,
+                    String::from_utf8_lossy(&buffer[..cantidad_leida])
+                ),
+                Err(_) => warn!("El ID_NODO = {} no respondio la consulta de estado", nodo),
+            }
+        } else if tipo == 'a'.to_string() || tipo == 'b'.to_string() {
+            let id_cuenta: IdCuenta = match _valores.get(2).and_then(|x| x.trim().parse().ok()) {
+                Some(x) => x,
+                None => {
+                    warn!("Necesito el ID_CUENTA a dar de alta o de baja");
+                    continue;
+                }
+            };
+            let msg = if tipo == 'a'.to_string() {
+                AltaCuenta::new(ID_CAFETERA_ADMINISTRACION, id_cuenta, 0).to_bytes()
+            } else {
+                BajaCuenta::new(ID_CAFETERA_ADMINISTRACION, id_cuenta, 0).to_bytes()
+            };
+            if !enviar_comando(&socket, &msg, &ip, &nodo) {
+                continue;
+            }
+            info!(
+                "Envio {} de ID_CUENTA = {} al ID_NODO = {}",
+                if tipo == 'a'.to_string() {
+                    "ALTA"
+                } else {
+                    "BAJA"
+                },
+                id_cuenta,
+                nodo
+            );
+        } else if tipo == 's'.to_string() {
+            let id_cuenta: IdCuenta = match _valores.get(2).and_then(|x| x.trim().parse().ok()) {
+                Some(x) => x,
+                None => {
+                    warn!("Necesito el ID_CUENTA a consultar");
+                    continue;
+                }
+            };
+            let msg = Consultar::new(IdCafetera::default(), id_cuenta, 0).to_bytes();
             socket
                 .send_to(&msg, ip)
                 .expect("Error fatal al enviar el mensaje");
-            println!("Envio CONECTAR al ID_NODO = {}", nodo);
+            socket
+                .set_read_timeout(Some(Duration::from_millis(
+                    config().desconexion.timeout_estado_millis,
+                )))
+                .expect("Error al setear el timeout del socket");
+            let mut buffer = [0u8; MAX_BYTES_MENSAJE];
+            match socket.recv_from(&mut buffer) {
+                Ok((cantidad_leida, _)) if cantidad_leida > 0 => {
+                    let respuesta = &buffer[..cantidad_leida];
+                    if let MensajeCafetera::CONSULTAR = MensajeCafetera::from_bytes(respuesta[0]) {
+                        match Consultar::from_bytes(respuesta) {
+                            Ok(consulta) => info!(
+                                "Saldo de ID_CUENTA = {} en ID_NODO = {}: {}",
+                                id_cuenta, nodo, consulta.cantidad_modificar
+                            ),
+                            Err(err) => {
+                                warn!("Respuesta invalida del ID_NODO = {}: {}", nodo, err)
+                            }
+                        }
+                    } else {
+                        warn!(
+                            "El ID_NODO = {} no tiene la ID_CUENTA = {}",
+                            nodo, id_cuenta
+                        );
+                    }
+                }
+                _ => warn!("El ID_NODO = {} no respondio la consulta de saldo", nodo),
+            }
+        } else if tipo == 't'.to_string() {
+            let valores: Option<Vec<u32>> = _valores
+                .get(2..5)
+                .and_then(|x| x.iter().map(|x| x.trim().parse().ok()).collect());
+            let (id_cuenta, id_cuenta_destino, cantidad) = match valores.as_deref() {
+                Some([id_cuenta, id_cuenta_destino, cantidad]) => (
+                    IdCuenta(*id_cuenta),
+                    IdCuenta(*id_cuenta_destino),
+                    *cantidad,
+                ),
+                _ => {
+                    warn!("Necesito el ID_CUENTA, el ID_CUENTA_DESTINO y la CANTIDAD a transferir");
+                    continue;
+                }
+            };
+            let mut transferir = Transferir::new(ID_CAFETERA_ADMINISTRACION, id_cuenta, cantidad);
+            transferir.id_cuenta_destino = id_cuenta_destino;
+            if !enviar_comando(&socket, &transferir.to_bytes(), &ip, &nodo) {
+                continue;
+            }
+            // la correccion puede esperar en la cola de las cuentas a que termine la resta en curso
+            socket
+                .set_read_timeout(Some(
+                    Duration::from_secs(config().servidor.tiempo_maximo_servicio_transaccion)
+                        + Duration::from_millis(config().desconexion.timeout_estado_millis),
+                ))
+                .expect("Error al setear el timeout del socket");
+            let mut buffer = [0u8; MAX_BYTES_MENSAJE];
+            match socket.recv_from(&mut buffer) {
+                Ok((cantidad_leida, _)) if cantidad_leida > 0 => {
+                    match MensajeCafetera::from_bytes(buffer[0]) {
+                        MensajeCafetera::OKEY => info!(
+                            "Se transfirieron {} creditos de ID_CUENTA = {} a ID_CUENTA = {}",
+                            cantidad, id_cuenta, id_cuenta_destino
+                        ),
+                        _ => warn!(
+                            "El ID_NODO = {} rechazo la transferencia de ID_CUENTA = {} a ID_CUENTA = {}",
+                            nodo, id_cuenta, id_cuenta_destino
+                        ),
+                    }
+                }
+                _ => warn!("El ID_NODO = {} no respondio la transferencia", nodo),
+            }
         } else {
-            println!("Mensaje desconocido");
+            warn!("Mensaje desconocido");
             return;
         }
     }
 }
+
+/// Envia un comando de administracion al nodo, firmado con cluster.secreto_admin. Retorna false si no
+/// hay secreto o si el nodo no respondio el desafio
+fn enviar_comando(socket: &UdpSocket, comando: &[u8], ip: &str, nodo: &str) -> bool {
+    let espera = Duration::from_millis(config().desconexion.timeout_estado_millis);
+    match enviar_comando_admin(socket, comando, ip, espera) {
+        Ok(()) => true,
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            warn!("No se envia el comando al ID_NODO = {}: {}", nodo, err);
+            false
+        }
+        Err(err) => {
+            warn!(
+                "No se pudo enviar el comando al ID_NODO = {}, no respondio el desafio: {}",
+                nodo, err
+            );
+            false
+        }
+    }
+}
+
+/// Envia una consulta de administracion al coordinador que corre en el ID_NODO y retorna lo que
+/// responde antes de cerrar la conexion
+fn consultar_coordinador(id_nodo: u8, consulta: &[u8], espera: Duration) -> io::Result<String> {
+    let mut stream = TcpStream::connect(config().cluster.addr_ctrl(id_nodo))?;
+    stream.set_read_timeout(Some(espera))?;
+    stream.write_all(consulta)?;
+    let mut registro = String::new();
+    stream.read_to_string(&mut registro)?;
+    Ok(registro)
+}
@@ -0,0 +1,115 @@
+use std::net::UdpSocket;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use compartido::codificable::Codificable;
+use compartido::config::config;
+use compartido::error::ErrorSistema;
+use compartido::ids::{IdCafetera, IdCuenta};
+use compartido::mensajes_cafetera::{
+    Desregistrar, MensajeCafetera, MensajeCafeteraBytes, OkeyToCafetera, Registrar, Restar,
+};
+use tp1::enums::IngredientType;
+use tp1::pantry::Pantry;
+use tracing::{info, warn};
+
+use crate::consultar_nodo;
+
+/// Id con el que la despensa se presenta ante el nodo
+const ID_DESPENSA: IdCafetera = IdCafetera(1);
+
+/// Despensa de granos y leche que vive en el cluster: el saldo de cada cuenta de la despensa es el
+/// stock en gramos del ingrediente. Cada reposicion es una transaccion distribuida, un RESTAR que el
+/// coordinador solo acepta si hay stock y que se confirma con un OKEY, asi varias cafeterias
+/// conectadas a cualquier nodo nunca retiran mas de lo que hay
+pub struct DespensaCluster {
+    /// Socket propio de la despensa, los dispensers lo usan de a uno
+    socket: Mutex<UdpSocket>,
+    /// Id del nodo al que se envian los pedidos de reposicion
+    id_nodo: u8,
+}
+
+impl DespensaCluster {
+    pub fn new(id_nodo: u8) -> Result<Self, ErrorSistema> {
+        let socket = UdpSocket::bind(format!("{}:0", config().cluster.host))?;
+        socket.set_read_timeout(Some(Duration::from_secs(config().sucursal.timeout)))?;
+        let registrar = Registrar::de_cafetera(ID_DESPENSA).to_bytes();
+        if consultar_nodo(&socket, registrar, id_nodo)?.is_none() {
+            warn!(
+                "La despensa no pudo registrarse en el ID_NODO = {}",
+                id_nodo
+            );
+        }
+        Ok(DespensaCluster {
+            socket: Mutex::new(socket),
+            id_nodo,
+        })
+    }
+
+    fn id_cuenta(tipo: IngredientType) -> Option<IdCuenta> {
+        match tipo {
+            IngredientType::GranosCafe => Some(config().integracion.id_cuenta_granos),
+            IngredientType::LecheFria => Some(config().integracion.id_cuenta_leche),
+            _ => None,
+        }
+    }
+}
+
+impl Pantry for DespensaCluster {
+    /// Retira los gramos (redondeados hacia arriba) de la cuenta del ingrediente. Si el nodo no
+    /// confirma el OKEY no se sabe si el retiro se aplico, y se lo toma como rechazado para no
+    /// reponer de mas
+    fn grant(&self, tipo: IngredientType, quantity: f32) -> bool {
+        let id_cuenta = match DespensaCluster::id_cuenta(tipo) {
+            Some(id_cuenta) => id_cuenta,
+            None => return false,
+        };
+        let gramos = quantity.ceil() as u32;
+        let socket = match self.socket.lock() {
+            Ok(socket) => socket,
+            Err(_) => return false,
+        };
+        let restar = Restar::new(ID_DESPENSA, id_cuenta, gramos).to_bytes();
+        match consultar_nodo(&socket, restar, self.id_nodo) {
+            Ok(Some(MensajeCafetera::OKEY)) => {}
+            Ok(_) => {
+                warn!(
+                    "[DESPENSA] Sin stock para reponer {} gramos de {:?}",
+                    gramos, tipo
+                );
+                return false;
+            }
+            Err(err) => {
+                warn!("[DESPENSA] Error al pedir {:?}: {}", tipo, err);
+                return false;
+            }
+        }
+        let okey = OkeyToCafetera::new(ID_DESPENSA, id_cuenta, gramos).to_bytes();
+        match consultar_nodo(&socket, okey, self.id_nodo) {
+            Ok(Some(MensajeCafetera::OKEY)) => {
+                info!("[DESPENSA] Se reponen {} gramos de {:?}", gramos, tipo);
+                true
+            }
+            _ => {
+                warn!(
+                    "[DESPENSA] No se confirmo la reposicion de {} gramos de {:?}",
+                    gramos, tipo
+                );
+                false
+            }
+        }
+    }
+}
+
+/// Da de baja el socket de la despensa en el nodo, sin esperar la respuesta
+impl Drop for DespensaCluster {
+    fn drop(&mut self) {
+        if let Ok(socket) = self.socket.get_mut() {
+            let desregistrar = Desregistrar::de_cafetera(ID_DESPENSA).to_bytes();
+            let _res = socket.send_to(
+                &desregistrar,
+                config().cluster.addr_lectura_datos(self.id_nodo),
+            );
+        }
+    }
+}
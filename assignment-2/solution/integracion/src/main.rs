@@ -0,0 +1,202 @@
+mod despensa;
+
+use std::collections::VecDeque;
+use std::env;
+use std::net::UdpSocket;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use compartido::codec::MAX_BYTES_MENSAJE;
+use compartido::codificable::Codificable;
+use compartido::config::config;
+use compartido::error::ErrorSistema;
+use compartido::ids::IdCafetera;
+use compartido::logs::init_tracing;
+use compartido::mensajes_cafetera::{
+    Desregistrar, Error, MensajeCafetera, MensajeCafeteraBytes, OkeyToCafetera, Registrar, Restar,
+};
+use despensa::DespensaCluster;
+use tp1::cafeteria::{init_elements, join_dispensers};
+use tp1::conteiners::Conteiners;
+use tp1::dispenser::{create_and_run_dispensers, send_signal_poweroff_to_dispensers};
+use tp1::enums::{IngredientStateOfOrder, OrderState};
+use tp1::error_dispenser::ErrorCafeteria;
+use tp1::order::{insert_orders, Order};
+use tracing::{info, warn};
+
+/// Id con el que la cafeteria del TP1 se presenta ante el nodo
+const ID_CAFETERA: IdCafetera = IdCafetera(0);
+
+/// Cantidad de pedidos en cada estado al terminar la simulacion
+#[derive(Debug, Default)]
+struct Resumen {
+    entregados: usize,
+    sin_saldo: usize,
+    sin_ingredientes: usize,
+    sin_respuesta: usize,
+}
+
+fn error_cafeteria(err: ErrorCafeteria) -> ErrorSistema {
+    ErrorSistema::Estado(err.mensaje)
+}
+
+/// Puntos que se cobran por un pedido: la cantidad total de ingredientes por los puntos por gramo
+/// configurados, redondeada hacia arriba
+fn costo_pedido(orden: &Order) -> u32 {
+    let gramos: f32 = orden
+        .ingredientes
+        .values()
+        .map(|ingrediente| match ingrediente {
+            IngredientStateOfOrder::NotApplied(cantidad) => *cantidad,
+            _ => 0.0,
+        })
+        .sum();
+    (gramos as f64 * config().integracion.puntos_por_gramo).ceil() as u32
+}
+
+/// Envia el mensaje al nodo y espera la respuesta. None si el nodo no responde a tiempo
+fn consultar_nodo(
+    socket: &UdpSocket,
+    mensaje: Vec<u8>,
+    id_nodo: u8,
+) -> Result<Option<MensajeCafetera>, ErrorSistema> {
+    socket.send_to(&mensaje, config().cluster.addr_lectura_datos(id_nodo))?;
+    let mut buffer = [0u8; MAX_BYTES_MENSAJE];
+    match socket.recv_from(&mut buffer) {
+        Ok((cantidad_leida, _)) if cantidad_leida > 0 => {
+            Ok(Some(MensajeCafetera::from_bytes(buffer[0])))
+        }
+        Ok(_) => Ok(None),
+        Err(ref err) if err.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+        Err(ref err) if err.kind() == std::io::ErrorKind::TimedOut => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Espera a que algun dispenser de la cafeteria termine el pedido, como lo hace el SYSTEM-ALERT del
+/// TP1, y lo retira de la cola de pedidos terminados
+fn esperar_pedido_terminado(
+    pair_terminados: &Arc<(Mutex<VecDeque<Order>>, Condvar)>,
+) -> Result<Order, ErrorSistema> {
+    let (lock, cvar) = &**pair_terminados;
+    let mut terminados = lock.lock()?;
+    while terminados.is_empty() {
+        terminados = cvar.wait(terminados)?;
+    }
+    let orden = terminados
+        .pop_back()
+        .ok_or_else(|| ErrorSistema::Estado("Cola de pedidos terminados vacia".to_string()))?;
+    cvar.notify_all();
+    Ok(orden)
+}
+
+/// Simulacion de punta a punta: la cafeteria del TP1 es la cafetera fisica de una sucursal del TP2.
+/// Cada pedido del archivo de ordenes primero se cobra en el cluster con un RESTAR y solo cuando el
+/// nodo lo acepta (la cuenta tiene saldo y quedo bloqueada) los dispensers preparan el cafe. Si el
+/// cafe se completa se confirma el cobro con un OKEY, si faltaron ingredientes se envia un ERROR y la
+/// transaccion se aborta sin descontar los puntos. Con integracion.despensa los granos y la leche
+/// para recargar los contenedores tambien se piden al cluster, ver DespensaCluster
+fn main() -> Result<(), ErrorSistema> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        return Err(ErrorSistema::Argumentos(
+            "Es necesario el ID_NODO al que se conecta la cafeteria y el archivo de ordenes del TP1. Ejemplo: ´cargo run -- 1 orders.txt´".to_string(),
+        ));
+    }
+    let id_nodo: u8 = args[1]
+        .parse()
+        .map_err(|_| ErrorSistema::Argumentos("El ID_NODO debe ser un numero".to_string()))?;
+    init_tracing("integracion");
+
+    let (ordenes, pair_pedidos, pair_terminados, pair_estados, mut contenedores, pair_finalizados) =
+        init_elements(Path::new(&args[2]))
+            .map_err(|err| ErrorSistema::Archivo(format!("{}: {}", args[2], err.mensaje)))?;
+    if config().integracion.despensa {
+        info!("Los contenedores recargables reponen de la despensa del cluster");
+        contenedores = Arc::new(Conteiners::with_pantry(Arc::new(DespensaCluster::new(
+            id_nodo,
+        )?)));
+    }
+    let dispensers = create_and_run_dispensers(
+        &pair_pedidos,
+        &pair_terminados,
+        &pair_finalizados,
+        &pair_estados,
+        contenedores,
+    );
+
+    let socket = UdpSocket::bind(format!("{}:0", config().cluster.host))?;
+    socket.set_read_timeout(Some(Duration::from_secs(config().sucursal.timeout)))?;
+    let id_cuenta = config().integracion.id_cuenta;
+
+    let registrar = Registrar::de_cafetera(ID_CAFETERA).to_bytes();
+    if consultar_nodo(&socket, registrar, id_nodo)?.is_none() {
+        warn!("No pude registrarme en el ID_NODO = {}", id_nodo);
+    }
+
+    let mut resumen = Resumen::default();
+    for (numero, orden) in ordenes.into_iter().enumerate() {
+        let costo = costo_pedido(&orden);
+        let mut restar = Restar::new(ID_CAFETERA, id_cuenta, costo);
+        restar.id_pedido = numero as u32 + 1;
+        info!(
+            "Pedido {} cuesta {} puntos, envio RESTAR de la ID_CUENTA = {}",
+            restar.id_pedido, costo, id_cuenta
+        );
+        match consultar_nodo(&socket, restar.to_bytes(), id_nodo)? {
+            Some(MensajeCafetera::OKEY) => {}
+            Some(_) => {
+                warn!("Pedido {} rechazado por el cluster", restar.id_pedido);
+                resumen.sin_saldo += 1;
+                continue;
+            }
+            None => {
+                warn!("El ID_NODO = {} no respondio el RESTAR", id_nodo);
+                resumen.sin_respuesta += 1;
+                continue;
+            }
+        }
+
+        // los pedidos se preparan de a uno y en el orden del archivo, asi que los pedidos de los que
+        // depende (siempre de lineas anteriores) ya terminaron o fueron rechazados
+        insert_orders(vec![orden], &pair_pedidos).map_err(error_cafeteria)?;
+        let orden = esperar_pedido_terminado(&pair_terminados)?;
+        if orden.status != OrderState::Completed {
+            warn!(
+                "Pedido {} sin ingredientes suficientes, se cancela el cobro",
+                restar.id_pedido
+            );
+            let error = Error::new(ID_CAFETERA, id_cuenta, 0)
+                .con_id_pedido(restar.id_pedido)
+                .to_bytes();
+            socket.send_to(&error, config().cluster.addr_lectura_datos(id_nodo))?;
+            resumen.sin_ingredientes += 1;
+            continue;
+        }
+        let okey = OkeyToCafetera::new(ID_CAFETERA, id_cuenta, costo)
+            .con_id_pedido(restar.id_pedido)
+            .to_bytes();
+        if let Some(MensajeCafetera::OKEY) = consultar_nodo(&socket, okey, id_nodo)? {
+            info!("Pedido {} cobrado y entregado", restar.id_pedido);
+            resumen.entregados += 1;
+        } else {
+            warn!(
+                "Pedido {} preparado pero el cobro no se confirmo",
+                restar.id_pedido
+            );
+            resumen.sin_respuesta += 1;
+        }
+    }
+
+    let desregistrar = Desregistrar::de_cafetera(ID_CAFETERA).to_bytes();
+    consultar_nodo(&socket, desregistrar, id_nodo)?;
+    send_signal_poweroff_to_dispensers(pair_pedidos).map_err(error_cafeteria)?;
+    join_dispensers(dispensers);
+
+    info!(
+        "[RESUMEN] entregados = {}, rechazados por el cluster = {}, sin ingredientes = {}, sin respuesta = {}",
+        resumen.entregados, resumen.sin_saldo, resumen.sin_ingredientes, resumen.sin_respuesta
+    );
+    Ok(())
+}
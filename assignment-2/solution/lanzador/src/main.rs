@@ -0,0 +1,242 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::net::UdpSocket;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use compartido::autenticacion::{enviar_comando_admin, SIN_SECRETO};
+use compartido::codificable::Codificable;
+use compartido::config::config;
+use compartido::ids::IdCafetera;
+use compartido::logs::init_tracing;
+use compartido::mensajes_cafetera::{Estado, MensajeCafeteraBytes};
+use rand::Rng;
+use server::utils::id_to_addr_read_data;
+use tracing::{error, info, warn};
+
+/// Espera maxima (en milisegundos) para que un nodo levantado responda la consulta de estado
+const TIMEOUT_ARRANQUE_NODO_MILLIS: u64 = 20000;
+/// Espera (en milisegundos) por cada consulta de estado mientras el nodo arranca
+const INTERVALO_CONSULTA_NODO_MILLIS: u64 = 250;
+/// Cantidad de pedidos que se generan por sucursal si no se indica
+const PEDIDOS_POR_SUCURSAL: usize = 20;
+/// Cantidad maxima de puntos de cada pedido generado
+const CANTIDAD_MAXIMA_PEDIDO: u32 = 50;
+
+/// Proceso lanzado por el lanzador, con un nombre para los logs
+struct Proceso {
+    nombre: String,
+    hijo: Child,
+}
+
+/// Compila el crate indicado para que los procesos arranquen sin esperar a cargo
+fn compilar(solucion: &Path, modulo: &str) -> Result<(), String> {
+    info!("[LANZADOR] Compilando {}", modulo);
+    let estado = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .current_dir(solucion.join(modulo))
+        .status()
+        .map_err(|x| x.to_string())?;
+    if !estado.success() {
+        return Err(format!("Fallo la compilacion de {}", modulo));
+    }
+    Ok(())
+}
+
+/// Consulta el estado del nodo por udp hasta que responde o se supera TIMEOUT_ARRANQUE_NODO_MILLIS
+fn esperar_nodo(id_nodo: u8) -> bool {
+    let socket = match UdpSocket::bind("127.0.0.1:0") {
+        Ok(x) => x,
+        Err(_) => return false,
+    };
+    if socket
+        .set_read_timeout(Some(Duration::from_millis(INTERVALO_CONSULTA_NODO_MILLIS)))
+        .is_err()
+    {
+        return false;
+    }
+    let inicio = Instant::now();
+    let mut buffer = [0u8; 4096];
+    while inicio.elapsed() < Duration::from_millis(TIMEOUT_ARRANQUE_NODO_MILLIS) {
+        let enviado = enviar_comando_admin(
+            &socket,
+            &Estado::de_cafetera(IdCafetera::default()).to_bytes(),
+            &id_to_addr_read_data(id_nodo),
+            Duration::from_millis(INTERVALO_CONSULTA_NODO_MILLIS),
+        );
+        if enviado.is_ok() && socket.recv_from(&mut buffer).is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Levanta el nodo servidor con el id indicado y espera a que responda
+fn levantar_nodo(solucion: &Path, id_nodo: u8, procesos: &mut Vec<Proceso>) -> Result<(), String> {
+    let hijo = Command::new(solucion.join("server/target/debug/server"))
+        .arg(id_nodo.to_string())
+        .current_dir(solucion.join("server"))
+        .spawn()
+        .map_err(|x| x.to_string())?;
+    procesos.push(Proceso {
+        nombre: format!("NODO-{}", id_nodo),
+        hijo,
+    });
+    if !esperar_nodo(id_nodo) {
+        return Err(format!("El ID_NODO = {} no respondio al arrancar", id_nodo));
+    }
+    info!("[LANZADOR] ID_NODO = {} levantado", id_nodo);
+    Ok(())
+}
+
+/// Genera el archivo de pedidos de una sucursal con sumas y restas al azar sobre las cuentas iniciales
+fn generar_pedidos(path: &Path, cantidad: usize) -> Result<(), String> {
+    let mut rng = rand::thread_rng();
+    let mut archivo = fs::File::create(path).map_err(|x| x.to_string())?;
+    for _ in 0..cantidad {
+        let tipo = if rng.gen_bool(0.5) { "SUMA" } else { "RESTA" };
+        writeln!(
+            archivo,
+            "{{\"id_cuenta\": {}, \"tipo\": \"{}\", \"cantidad\": {}}}",
+            rng.gen_range(1..=config().servidor.cuentas_iniciales),
+            tipo,
+            rng.gen_range(1..=CANTIDAD_MAXIMA_PEDIDO)
+        )
+        .map_err(|x| x.to_string())?;
+    }
+    Ok(())
+}
+
+/// Levanta una sucursal con un archivo de pedidos generado. Su nodo principal se reparte entre los
+/// nodos levantados y el resto quedan como nodos de respaldo
+fn levantar_sucursal(
+    solucion: &Path,
+    directorio: &Path,
+    numero: usize,
+    nodos: &[u8],
+    pedidos: usize,
+    procesos: &mut Vec<Proceso>,
+) -> Result<(), String> {
+    let archivo = format!("sucursal{}.txt", numero);
+    generar_pedidos(&directorio.join("Pedidos").join(&archivo), pedidos)?;
+    let lista_nodos: Vec<String> = (0..nodos.len())
+        .map(|i| nodos[(numero + i) % nodos.len()].to_string())
+        .collect();
+    let hijo = Command::new(solucion.join("sucursal/target/debug/sucursal"))
+        .arg(lista_nodos.join(","))
+        .arg(&archivo)
+        .current_dir(directorio)
+        .spawn()
+        .map_err(|x| x.to_string())?;
+    info!(
+        "[LANZADOR] Sucursal {} levantada con nodos {} y {} pedidos",
+        numero,
+        lista_nodos.join(","),
+        pedidos
+    );
+    procesos.push(Proceso {
+        nombre: format!("SUCURSAL-{}", numero),
+        hijo,
+    });
+    Ok(())
+}
+
+/// Termina todos los procesos lanzados, empezando por las sucursales
+fn terminar(procesos: &mut [Proceso]) {
+    for proceso in procesos.iter_mut().rev() {
+        if let Ok(None) = proceso.hijo.try_wait() {
+            info!("[LANZADOR] Termino {}", proceso.nombre);
+            let _res = proceso.hijo.kill();
+        }
+        let _res = proceso.hijo.wait();
+    }
+}
+
+/// Lee un argumento numerico opcional
+fn argumento(args: &[String], posicion: usize, por_defecto: usize) -> Result<usize, String> {
+    match args.get(posicion) {
+        Some(x) => x
+            .parse()
+            .map_err(|_| format!("El argumento {} debe ser un numero", x)),
+        None => Ok(por_defecto),
+    }
+}
+
+/// Levanta los nodos y sucursales pedidos y los deja corriendo hasta recibir Ctrl-C
+fn lanzar(args: &[String], procesos: &mut Vec<Proceso>) -> Result<(), String> {
+    // sin el secreto no se puede consultar el estado de los nodos para saber si arrancaron
+    if config().cluster.secreto_admin.is_empty() {
+        return Err(SIN_SECRETO.to_string());
+    }
+    let cantidad_nodos = argumento(args, 1, config().cluster.cant_max_nodos as usize)?;
+    if !(1..=config().cluster.cant_max_nodos as usize).contains(&cantidad_nodos) {
+        return Err(format!(
+            "La cantidad de nodos debe estar entre 1 y {}",
+            config().cluster.cant_max_nodos
+        ));
+    }
+    let cantidad_sucursales = argumento(args, 2, 0)?;
+    let pedidos = argumento(args, 3, PEDIDOS_POR_SUCURSAL)?;
+
+    let solucion: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .ok_or("No se encontro el directorio de la solucion")?
+        .to_path_buf();
+    compilar(&solucion, "server")?;
+    if cantidad_sucursales > 0 {
+        compilar(&solucion, "sucursal")?;
+    }
+
+    // el coordinador inicial se levanta primero, el resto de los nodos se conectan a el
+    let mut nodos = vec![config().cluster.id_coordinador_inicial];
+    nodos.extend(
+        (1..=config().cluster.cant_max_nodos)
+            .filter(|id| *id != config().cluster.id_coordinador_inicial)
+            .take(cantidad_nodos - 1),
+    );
+    for id_nodo in nodos.iter() {
+        levantar_nodo(&solucion, *id_nodo, procesos)?;
+    }
+    info!(
+        "[LANZADOR] Coordinador ID_NODO = {} y {} nodos levantados",
+        config().cluster.id_coordinador_inicial,
+        nodos.len()
+    );
+
+    let directorio = env::temp_dir().join("lanzador");
+    fs::create_dir_all(directorio.join("Pedidos")).map_err(|x| x.to_string())?;
+    for numero in 0..cantidad_sucursales {
+        levantar_sucursal(&solucion, &directorio, numero, &nodos, pedidos, procesos)?;
+    }
+    Ok(())
+}
+
+/// Proceso de desarrollo que levanta un cluster local: N nodos servidores (por defecto cant_max_nodos)
+/// empezando por el coordinador inicial y, opcionalmente, M sucursales con archivos de pedidos
+/// generados. Se ejecuta como `cargo run -- [N] [M] [PEDIDOS_POR_SUCURSAL]` y con Ctrl-C termina
+/// todos los procesos lanzados
+fn main() {
+    init_tracing("lanzador");
+    let args: Vec<String> = env::args().collect();
+    let (sender, receiver) = mpsc::channel();
+    if let Err(err) = ctrlc::set_handler(move || {
+        let _res = sender.send(());
+    }) {
+        warn!("[LANZADOR] No se pudo capturar Ctrl-C: {}", err);
+        return;
+    }
+
+    let mut procesos = vec![];
+    match lanzar(&args, &mut procesos) {
+        Ok(()) => {
+            info!("[LANZADOR] Cluster levantado, Ctrl-C para terminar");
+            let _res = receiver.recv();
+        }
+        Err(err) => error!("[LANZADOR] Error: {}", err),
+    }
+    terminar(&mut procesos);
+}
@@ -0,0 +1,11 @@
+/// Con la feature grpc se genera el codigo del servicio a partir de proto/cafetera.proto, usando el
+/// protoc que trae protoc-bin-vendored para no depender de uno instalado
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("No se encontro protoc");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::compile_protos("proto/cafetera.proto")
+            .expect("Error al compilar proto/cafetera.proto");
+    }
+}
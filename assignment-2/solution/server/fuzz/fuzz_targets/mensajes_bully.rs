@@ -0,0 +1,24 @@
+#![no_main]
+
+use compartido::codificable::Codificable;
+use libfuzzer_sys::fuzz_target;
+use server::bully_messages::{
+    Coordinator, CoordinatorAck, Election, MensajeBully, OkeyBully, Ping, PingCord,
+};
+
+// Cualquier paquete udp que llegue al listener del algoritmo bully debe interpretarse o rechazarse
+// con error, nunca terminar el actor que lo recibe
+fuzz_target!(|data: &[u8]| {
+    let Some(tipo) = data.first() else {
+        return;
+    };
+    let _res = match MensajeBully::from_bytes(*tipo) {
+        MensajeBully::OKEY => OkeyBully::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeBully::ELECTION => Election::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeBully::COORDINATOR => Coordinator::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeBully::PING => Ping::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeBully::PINGCORD => PingCord::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeBully::COORDINATORACK => CoordinatorAck::from_bytes(data).map(|x| x.to_bytes()),
+        MensajeBully::DESCONOCIDO => return,
+    };
+});
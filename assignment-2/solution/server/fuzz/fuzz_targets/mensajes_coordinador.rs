@@ -0,0 +1,43 @@
+#![no_main]
+
+use compartido::codificable::Codificable;
+use libfuzzer_sys::fuzz_target;
+use server::coordinador::Disconnect;
+use server::mensaje::{
+    Abort, AltaCord, BajaCord, Commit, ConfirmadoToNodo, Execute, FallidoToNodo, Finish, Mensaje,
+    OkeyAbortToCoordinator, OkeyToCoordinator, PedidoSnapshot, PingCord, Prepare, Rechazo, Retiro,
+    Snapshot, Starter, Transferencia, Validar, Valido, Yes,
+};
+
+// Cualquier mensaje que llegue por tcp entre un nodo y el coordinador debe interpretarse o rechazarse
+// con error, nunca terminar el actor que lo recibe
+fuzz_target!(|data: &[u8]| {
+    let Some(tipo) = data.first() else {
+        return;
+    };
+    let _res = match Mensaje::from_bytes(*tipo) {
+        Mensaje::STARTER => Starter::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::PREPARE => Prepare::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::YES => Yes::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::EXECUTE => Execute::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::FINISH => Finish::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::COMMIT => Commit::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::OKEY => OkeyToCoordinator::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::ABORT => Abort::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::PING => PingCord::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::OKEYABORT => OkeyAbortToCoordinator::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::CONFIRMADO => ConfirmadoToNodo::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::FALLIDO => FallidoToNodo::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::TRANSFERENCIA => Transferencia::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::ALTA => AltaCord::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::BAJA => BajaCord::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::VALIDAR => Validar::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::VALIDO => Valido::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::PEDIDOSNAPSHOT => PedidoSnapshot::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::SNAPSHOT => Snapshot::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::RETIRO => Retiro::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::RECHAZO => Rechazo::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::DISCONNECT => Disconnect::from_bytes(data).map(|x| x.to_bytes()),
+        Mensaje::UNKNOWN => return,
+    };
+});
@@ -0,0 +1,256 @@
+//! Almacenamiento de los saldos de las cuentas de un nodo. El nodo trabaja sobre sus cuentas en
+//! memoria y guarda en el almacen cada saldo que cambia (commits, altas, bajas y snapshots), asi al
+//! arrancar puede retomar los saldos que tenia en lugar de las cuentas iniciales
+
+use std::collections::HashMap;
+
+use compartido::config::config;
+use compartido::ids::{IdCuenta, IdNodo};
+
+use crate::error_server::ErrorServer;
+
+/// Backend en el que el nodo guarda el saldo de cada cuenta
+pub trait AlmacenCuentas: Send {
+    /// Todas las cuentas guardadas, con su saldo
+    fn cuentas(&self) -> Result<HashMap<IdCuenta, u32>, ErrorServer>;
+
+    /// Guarda el saldo de la cuenta, creandola si no existia
+    fn guardar(&mut self, id_cuenta: IdCuenta, saldo: u32) -> Result<(), ErrorServer>;
+
+    /// Elimina la cuenta, si existia
+    fn eliminar(&mut self, id_cuenta: IdCuenta) -> Result<(), ErrorServer>;
+
+    /// Escribe lo que quede pendiente antes de que termine el proceso
+    fn cerrar(&mut self) -> Result<(), ErrorServer> {
+        Ok(())
+    }
+
+    /// Reemplaza todas las cuentas guardadas por las indicadas, por ejemplo al aplicar un snapshot
+    fn reemplazar(&mut self, saldos: &HashMap<IdCuenta, u32>) -> Result<(), ErrorServer> {
+        for id_cuenta in self.cuentas()?.keys() {
+            if !saldos.contains_key(id_cuenta) {
+                self.eliminar(*id_cuenta)?;
+            }
+        }
+        for (id_cuenta, saldo) in saldos {
+            self.guardar(*id_cuenta, *saldo)?;
+        }
+        Ok(())
+    }
+}
+
+/// Almacen que no sobrevive al proceso, el de los tests y el de los nodos sin directorio de datos
+#[derive(Default)]
+pub struct AlmacenEnMemoria {
+    saldos: HashMap<IdCuenta, u32>,
+}
+
+impl AlmacenEnMemoria {
+    /// Almacen con las cuentas indicadas (id de cuenta y saldo)
+    pub fn new(saldos: HashMap<IdCuenta, u32>) -> Self {
+        AlmacenEnMemoria { saldos }
+    }
+}
+
+impl AlmacenCuentas for AlmacenEnMemoria {
+    fn cuentas(&self) -> Result<HashMap<IdCuenta, u32>, ErrorServer> {
+        Ok(self.saldos.clone())
+    }
+
+    fn guardar(&mut self, id_cuenta: IdCuenta, saldo: u32) -> Result<(), ErrorServer> {
+        self.saldos.insert(id_cuenta, saldo);
+        Ok(())
+    }
+
+    fn eliminar(&mut self, id_cuenta: IdCuenta) -> Result<(), ErrorServer> {
+        self.saldos.remove(&id_cuenta);
+        Ok(())
+    }
+
+    fn reemplazar(&mut self, saldos: &HashMap<IdCuenta, u32>) -> Result<(), ErrorServer> {
+        self.saldos = saldos.clone();
+        Ok(())
+    }
+}
+
+/// Almacen en una base sled embebida en el directorio de datos del nodo. Cada cuenta es una clave
+/// con el id en big endian y su saldo como valor
+#[cfg(feature = "sled")]
+pub struct AlmacenSled {
+    db: sled::Db,
+}
+
+#[cfg(feature = "sled")]
+impl AlmacenSled {
+    /// Abre (o crea) la base en el directorio indicado
+    pub fn abrir(path: &std::path::Path) -> Result<Self, ErrorServer> {
+        let db = sled::open(path).map_err(|x| error_sled(path.display(), x))?;
+        Ok(AlmacenSled { db })
+    }
+
+    /// true si la base no tiene ninguna cuenta, por ejemplo la primera vez que arranca el nodo
+    pub fn vacio(&self) -> bool {
+        self.db.is_empty()
+    }
+
+    /// Escribe a disco los cambios pendientes, para que el saldo sobreviva a una caida del proceso
+    fn persistir(&self) -> Result<(), ErrorServer> {
+        self.db.flush().map_err(|x| error_sled("flush", x))?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sled")]
+fn error_sled(contexto: impl std::fmt::Display, err: sled::Error) -> ErrorServer {
+    ErrorServer::Archivo(format!("{}: {}", contexto, err))
+}
+
+#[cfg(feature = "sled")]
+impl AlmacenCuentas for AlmacenSled {
+    fn cuentas(&self) -> Result<HashMap<IdCuenta, u32>, ErrorServer> {
+        self.db
+            .iter()
+            .map(|entrada| {
+                let (clave, valor) = entrada.map_err(|x| error_sled("lectura", x))?;
+                let id_cuenta = <[u8; 4]>::try_from(clave.as_ref())
+                    .map_err(|_| ErrorServer::Parseo(format!("clave invalida {:?}", clave)))?;
+                let saldo = <[u8; 4]>::try_from(valor.as_ref())
+                    .map_err(|_| ErrorServer::Parseo(format!("saldo invalido {:?}", valor)))?;
+                Ok((
+                    IdCuenta(u32::from_be_bytes(id_cuenta)),
+                    u32::from_be_bytes(saldo),
+                ))
+            })
+            .collect()
+    }
+
+    fn guardar(&mut self, id_cuenta: IdCuenta, saldo: u32) -> Result<(), ErrorServer> {
+        self.db
+            .insert(id_cuenta.0.to_be_bytes(), &saldo.to_be_bytes())
+            .map_err(|x| error_sled(id_cuenta, x))?;
+        self.persistir()
+    }
+
+    fn eliminar(&mut self, id_cuenta: IdCuenta) -> Result<(), ErrorServer> {
+        self.db
+            .remove(id_cuenta.0.to_be_bytes())
+            .map_err(|x| error_sled(id_cuenta, x))?;
+        self.persistir()
+    }
+
+    fn cerrar(&mut self) -> Result<(), ErrorServer> {
+        self.persistir()
+    }
+
+    /// Aplica todos los cambios en un unico batch, que se escribe a disco una sola vez
+    fn reemplazar(&mut self, saldos: &HashMap<IdCuenta, u32>) -> Result<(), ErrorServer> {
+        let mut batch = sled::Batch::default();
+        for id_cuenta in self.cuentas()?.keys() {
+            if !saldos.contains_key(id_cuenta) {
+                batch.remove(&id_cuenta.0.to_be_bytes());
+            }
+        }
+        for (id_cuenta, saldo) in saldos {
+            batch.insert(&id_cuenta.0.to_be_bytes(), &saldo.to_be_bytes());
+        }
+        self.db
+            .apply_batch(batch)
+            .map_err(|x| error_sled("batch", x))?;
+        self.persistir()
+    }
+}
+
+/// Almacen del nodo al arrancar. Con la feature sled y servidor.directorio_datos es la base del nodo
+/// en ese directorio, que la primera vez se carga con las cuentas iniciales y luego conserva los saldos
+/// entre reinicios. Si no, son las cuentas iniciales en memoria
+pub fn abrir_almacen(
+    id_nodo: IdNodo,
+    cuentas_iniciales: HashMap<IdCuenta, u32>,
+) -> Result<Box<dyn AlmacenCuentas>, ErrorServer> {
+    let directorio = &config().servidor.directorio_datos;
+    if directorio.is_empty() {
+        return Ok(Box::new(AlmacenEnMemoria::new(cuentas_iniciales)));
+    }
+    #[cfg(feature = "sled")]
+    {
+        let path = std::path::Path::new(directorio).join(format!("nodo-{}", id_nodo));
+        let mut almacen = AlmacenSled::abrir(&path)?;
+        if almacen.vacio() {
+            almacen.reemplazar(&cuentas_iniciales)?;
+        } else {
+            tracing::info!(
+                "[NODO-{}] Retomo los saldos guardados en {}",
+                id_nodo,
+                path.display()
+            );
+        }
+        Ok(Box::new(almacen))
+    }
+    #[cfg(not(feature = "sled"))]
+    {
+        tracing::warn!(
+            "[NODO-{}] servidor.directorio_datos requiere compilar con la feature sled, los saldos no se guardan",
+            id_nodo
+        );
+        Ok(Box::new(AlmacenEnMemoria::new(cuentas_iniciales)))
+    }
+}
+
+#[cfg(test)]
+mod almacen_test {
+    use super::*;
+
+    #[test]
+    fn el_almacen_en_memoria_guarda_y_elimina_saldos() {
+        let mut almacen = AlmacenEnMemoria::new(HashMap::from([(IdCuenta(1), 100)]));
+        almacen.guardar(IdCuenta(1), 60).unwrap();
+        almacen.guardar(IdCuenta(2), 10).unwrap();
+        almacen.eliminar(IdCuenta(3)).unwrap();
+        assert_eq!(
+            HashMap::from([(IdCuenta(1), 60), (IdCuenta(2), 10)]),
+            almacen.cuentas().unwrap()
+        );
+
+        almacen.eliminar(IdCuenta(1)).unwrap();
+        assert_eq!(
+            HashMap::from([(IdCuenta(2), 10)]),
+            almacen.cuentas().unwrap()
+        );
+
+        almacen
+            .reemplazar(&HashMap::from([(IdCuenta(5), 1)]))
+            .unwrap();
+        assert_eq!(
+            HashMap::from([(IdCuenta(5), 1)]),
+            almacen.cuentas().unwrap()
+        );
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn los_saldos_de_sled_sobreviven_a_reabrir_la_base() {
+        let path = std::env::temp_dir().join(format!("tp2-almacen-test-{}", std::process::id()));
+        {
+            let mut almacen = AlmacenSled::abrir(&path).unwrap();
+            assert!(almacen.vacio());
+            almacen.guardar(IdCuenta(7), 40).unwrap();
+            almacen.guardar(IdCuenta(8), 5).unwrap();
+            almacen.eliminar(IdCuenta(8)).unwrap();
+        }
+        let mut almacen = AlmacenSled::abrir(&path).unwrap();
+        assert_eq!(
+            HashMap::from([(IdCuenta(7), 40)]),
+            almacen.cuentas().unwrap()
+        );
+
+        almacen
+            .reemplazar(&HashMap::from([(IdCuenta(1), 3), (IdCuenta(2), 4)]))
+            .unwrap();
+        assert_eq!(
+            HashMap::from([(IdCuenta(1), 3), (IdCuenta(2), 4)]),
+            almacen.cuentas().unwrap()
+        );
+        drop(almacen);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+}
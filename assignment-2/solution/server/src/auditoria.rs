@@ -0,0 +1,351 @@
+//! Auditoria de consistencia del cluster. El coordinador le pide sus saldos a todos los nodos con el
+//! mismo corte (el pedido viaja detras de los COMMIT/ABORT ya difundidos, por lo que cada nodo responde
+//! con todos ellos aplicados) y compara las respuestas cuenta por cuenta. Con los mismos saldos arma el
+//! snapshot de los saldos del cluster, que toma los del nodo de referencia y marca en cada cuenta los
+//! nodos que difieren
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Instant;
+
+use compartido::ids::{IdCuenta, IdNodo};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+/// Cuenta cuyo saldo no coincide en todos los nodos que respondieron
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CuentaDivergente {
+    pub id_cuenta: IdCuenta,
+    /// saldo en cada nodo, None si el nodo no tiene la cuenta
+    pub saldos: Vec<(IdNodo, Option<u32>)>,
+}
+
+/// Resultado de una auditoria
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct InformeAuditoria {
+    /// cantidad de COMMIT/ABORT difundidos al pedir los saldos
+    pub corte: u64,
+    /// nodos que reportaron sus saldos
+    pub nodos: Vec<IdNodo>,
+    /// nodos a los que se les pidio y no respondieron a tiempo (o se desconectaron)
+    pub sin_respuesta: Vec<IdNodo>,
+    pub cuentas: usize,
+    pub divergentes: Vec<CuentaDivergente>,
+}
+
+impl InformeAuditoria {
+    pub fn consistente(&self) -> bool {
+        self.divergentes.is_empty()
+    }
+}
+
+/// Saldo de una cuenta en el snapshot del cluster
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaldoCluster {
+    pub id_cuenta: IdCuenta,
+    /// saldo en el nodo de referencia, None si no tiene la cuenta
+    pub saldo: Option<u32>,
+    /// nodos con un saldo distinto al de referencia y el que reportaron, None si no tienen la cuenta
+    pub discrepancias: Vec<(IdNodo, Option<u32>)>,
+}
+
+/// Saldos de todas las cuentas del cluster con el mismo corte
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotSaldos {
+    /// cantidad de COMMIT/ABORT difundidos al pedir los saldos
+    pub corte: u64,
+    /// nodo del que se toman los saldos: el del coordinador si respondio, o si no el de menor id.
+    /// None si no respondio ningun nodo
+    pub referencia: Option<IdNodo>,
+    /// nodos que reportaron sus saldos
+    pub nodos: Vec<IdNodo>,
+    /// nodos a los que se les pidio y no respondieron a tiempo (o se desconectaron)
+    pub sin_respuesta: Vec<IdNodo>,
+    /// cuentas de todos los nodos que respondieron, por id
+    pub cuentas: Vec<SaldoCluster>,
+}
+
+impl SnapshotSaldos {
+    /// true si todos los nodos que respondieron tienen los mismos saldos que el de referencia
+    pub fn consistente(&self) -> bool {
+        self.cuentas.iter().all(|x| x.discrepancias.is_empty())
+    }
+
+    /// Saldo de la cuenta en el nodo de referencia
+    pub fn saldo(&self, id_cuenta: IdCuenta) -> Option<u32> {
+        self.cuentas
+            .iter()
+            .find(|x| x.id_cuenta == id_cuenta)
+            .and_then(|x| x.saldo)
+    }
+}
+
+/// Lo que se le entrega al cerrar la auditoria a quien la pidio
+pub enum RespuestaAuditoria {
+    /// el informe con las cuentas divergentes
+    Informe(oneshot::Sender<InformeAuditoria>),
+    /// el snapshot de los saldos, con el nodo preferido como referencia
+    Saldos {
+        preferido: IdNodo,
+        respuesta: oneshot::Sender<SnapshotSaldos>,
+    },
+}
+
+/// Auditoria que el coordinador todavia no cerro
+pub struct AuditoriaEnCurso {
+    corte: u64,
+    /// momento en que se pidieron los saldos, para cerrarla aunque falten respuestas
+    pub inicio: Instant,
+    /// nodos de los que se espera respuesta
+    esperados: BTreeSet<IdNodo>,
+    /// nodos que se desconectaron antes de responder
+    desconectados: BTreeSet<IdNodo>,
+    reportes: BTreeMap<IdNodo, HashMap<IdCuenta, u32>>,
+    /// por donde se entrega el informe o el snapshot a quien pidio la auditoria
+    respuesta: RespuestaAuditoria,
+}
+
+impl AuditoriaEnCurso {
+    pub fn new(
+        corte: u64,
+        inicio: Instant,
+        esperados: impl IntoIterator<Item = IdNodo>,
+        respuesta: RespuestaAuditoria,
+    ) -> Self {
+        AuditoriaEnCurso {
+            corte,
+            inicio,
+            esperados: esperados.into_iter().collect(),
+            desconectados: BTreeSet::new(),
+            reportes: BTreeMap::new(),
+            respuesta,
+        }
+    }
+
+    /// Guarda los saldos que reporto el nodo, si se esperaban
+    pub fn reportar(&mut self, id_nodo: IdNodo, cuentas: Vec<(IdCuenta, u32)>) {
+        if self.esperados.remove(&id_nodo) {
+            self.reportes.insert(id_nodo, cuentas.into_iter().collect());
+        }
+    }
+
+    /// El nodo se desconecto, no se espera su respuesta
+    pub fn descartar(&mut self, id_nodo: IdNodo) {
+        if self.esperados.remove(&id_nodo) {
+            self.desconectados.insert(id_nodo);
+        }
+    }
+
+    /// true si ya respondieron todos los nodos esperados
+    pub fn completa(&self) -> bool {
+        self.esperados.is_empty()
+    }
+
+    /// Compara los saldos reportados y entrega el informe, o el snapshot si se pidieron los saldos. Los
+    /// nodos que faltan quedan sin respuesta. Retorna el informe para los logs
+    pub fn cerrar(self) -> InformeAuditoria {
+        let sin_respuesta: Vec<IdNodo> =
+            self.esperados.union(&self.desconectados).copied().collect();
+        let mut informe = comparar(self.corte, &self.reportes);
+        informe.sin_respuesta = sin_respuesta.clone();
+        // si quien pidio la auditoria ya no espera, el informe solo queda en los logs
+        match self.respuesta {
+            RespuestaAuditoria::Informe(respuesta) => {
+                let _res = respuesta.send(informe.clone());
+            }
+            RespuestaAuditoria::Saldos {
+                preferido,
+                respuesta,
+            } => {
+                let mut snapshot = consolidar(self.corte, preferido, &self.reportes);
+                snapshot.sin_respuesta = sin_respuesta;
+                let _res = respuesta.send(snapshot);
+            }
+        }
+        informe
+    }
+}
+
+/// Compara los saldos de cada nodo (por id de cuenta) y retorna las cuentas que no coinciden en todos,
+/// incluidas las que faltan en alguno
+fn comparar(corte: u64, reportes: &BTreeMap<IdNodo, HashMap<IdCuenta, u32>>) -> InformeAuditoria {
+    let ids: BTreeSet<IdCuenta> = reportes.values().flat_map(|x| x.keys().copied()).collect();
+    let divergentes = ids
+        .iter()
+        .filter_map(|id_cuenta| {
+            let saldos: Vec<(IdNodo, Option<u32>)> = reportes
+                .iter()
+                .map(|(id_nodo, cuentas)| (*id_nodo, cuentas.get(id_cuenta).copied()))
+                .collect();
+            let distintos: BTreeSet<Option<u32>> = saldos.iter().map(|(_, x)| *x).collect();
+            (distintos.len() > 1).then_some(CuentaDivergente {
+                id_cuenta: *id_cuenta,
+                saldos,
+            })
+        })
+        .collect();
+    InformeAuditoria {
+        corte,
+        nodos: reportes.keys().copied().collect(),
+        sin_respuesta: vec![],
+        cuentas: ids.len(),
+        divergentes,
+    }
+}
+
+/// Toma los saldos del nodo preferido, o si no respondio los del de menor id, y marca en cada cuenta los
+/// nodos que reportaron otro saldo, incluidos los que no la tienen
+fn consolidar(
+    corte: u64,
+    preferido: IdNodo,
+    reportes: &BTreeMap<IdNodo, HashMap<IdCuenta, u32>>,
+) -> SnapshotSaldos {
+    let referencia = if reportes.contains_key(&preferido) {
+        Some(preferido)
+    } else {
+        reportes.keys().next().copied()
+    };
+    let saldos_referencia = referencia.and_then(|x| reportes.get(&x));
+    let ids: BTreeSet<IdCuenta> = reportes.values().flat_map(|x| x.keys().copied()).collect();
+    let cuentas = ids
+        .iter()
+        .map(|id_cuenta| {
+            let saldo = saldos_referencia.and_then(|x| x.get(id_cuenta).copied());
+            let discrepancias = reportes
+                .iter()
+                .map(|(id_nodo, cuentas)| (*id_nodo, cuentas.get(id_cuenta).copied()))
+                .filter(|(_, x)| *x != saldo)
+                .collect();
+            SaldoCluster {
+                id_cuenta: *id_cuenta,
+                saldo,
+                discrepancias,
+            }
+        })
+        .collect();
+    SnapshotSaldos {
+        corte,
+        referencia,
+        nodos: reportes.keys().copied().collect(),
+        sin_respuesta: vec![],
+        cuentas,
+    }
+}
+
+#[cfg(test)]
+mod auditoria_test {
+    use super::*;
+
+    fn cuentas(saldos: &[(u32, u32)]) -> Vec<(IdCuenta, u32)> {
+        saldos
+            .iter()
+            .map(|(id, saldo)| (IdCuenta(*id), *saldo))
+            .collect()
+    }
+
+    #[test]
+    fn se_reportan_las_cuentas_con_saldos_distintos_o_faltantes() {
+        let (respuesta, mut recibido) = oneshot::channel();
+        let nodos = [IdNodo(1), IdNodo(2), IdNodo(3)];
+        let mut auditoria = AuditoriaEnCurso::new(
+            4,
+            Instant::now(),
+            nodos,
+            RespuestaAuditoria::Informe(respuesta),
+        );
+        auditoria.reportar(IdNodo(1), cuentas(&[(7, 100), (8, 50), (9, 10)]));
+        assert!(!auditoria.completa());
+        auditoria.reportar(IdNodo(2), cuentas(&[(7, 100), (8, 40)]));
+        // un nodo que no se esperaba no cuenta
+        auditoria.reportar(IdNodo(5), cuentas(&[(7, 0)]));
+        auditoria.descartar(IdNodo(3));
+        assert!(auditoria.completa());
+
+        let informe = auditoria.cerrar();
+        assert_eq!(informe, recibido.try_recv().unwrap());
+        assert_eq!(
+            (4, vec![IdNodo(1), IdNodo(2)], vec![IdNodo(3)], 3),
+            (
+                informe.corte,
+                informe.nodos,
+                informe.sin_respuesta,
+                informe.cuentas
+            )
+        );
+        assert_eq!(
+            vec![
+                CuentaDivergente {
+                    id_cuenta: IdCuenta(8),
+                    saldos: vec![(IdNodo(1), Some(50)), (IdNodo(2), Some(40))],
+                },
+                CuentaDivergente {
+                    id_cuenta: IdCuenta(9),
+                    saldos: vec![(IdNodo(1), Some(10)), (IdNodo(2), None)],
+                },
+            ],
+            informe.divergentes
+        );
+    }
+
+    #[test]
+    fn el_snapshot_toma_los_saldos_del_preferido_y_marca_los_nodos_que_difieren() {
+        let (respuesta, mut recibido) = oneshot::channel();
+        let respuesta = RespuestaAuditoria::Saldos {
+            preferido: IdNodo(2),
+            respuesta,
+        };
+        let nodos = [IdNodo(1), IdNodo(2), IdNodo(3)];
+        let mut auditoria = AuditoriaEnCurso::new(4, Instant::now(), nodos, respuesta);
+        auditoria.reportar(IdNodo(1), cuentas(&[(7, 90), (8, 50)]));
+        auditoria.reportar(IdNodo(2), cuentas(&[(7, 100), (8, 50)]));
+        auditoria.descartar(IdNodo(3));
+        auditoria.cerrar();
+
+        let snapshot = recibido.try_recv().unwrap();
+        assert_eq!(
+            (Some(IdNodo(2)), vec![IdNodo(3)], Some(100), false),
+            (
+                snapshot.referencia,
+                snapshot.sin_respuesta.clone(),
+                snapshot.saldo(IdCuenta(7)),
+                snapshot.consistente()
+            )
+        );
+        assert_eq!(
+            vec![
+                SaldoCluster {
+                    id_cuenta: IdCuenta(7),
+                    saldo: Some(100),
+                    discrepancias: vec![(IdNodo(1), Some(90))],
+                },
+                SaldoCluster {
+                    id_cuenta: IdCuenta(8),
+                    saldo: Some(50),
+                    discrepancias: vec![],
+                },
+            ],
+            snapshot.cuentas
+        );
+
+        // sin el preferido, la referencia es el nodo de menor id que respondio
+        let mut reportes = BTreeMap::new();
+        reportes.insert(IdNodo(3), HashMap::from([(IdCuenta(7), 100)]));
+        reportes.insert(IdNodo(1), HashMap::from([(IdCuenta(8), 5)]));
+        let snapshot = consolidar(4, IdNodo(2), &reportes);
+        assert_eq!(Some(IdNodo(1)), snapshot.referencia);
+        assert_eq!(
+            vec![
+                SaldoCluster {
+                    id_cuenta: IdCuenta(7),
+                    saldo: None,
+                    discrepancias: vec![(IdNodo(3), Some(100))],
+                },
+                SaldoCluster {
+                    id_cuenta: IdCuenta(8),
+                    saldo: Some(5),
+                    discrepancias: vec![(IdNodo(3), None)],
+                },
+            ],
+            snapshot.cuentas
+        );
+    }
+}
@@ -0,0 +1,579 @@
+use std::fmt::Debug;
+
+use tracing::{info, warn};
+
+/// Mensajes que intercambian los nodos durante una eleccion. En COORDINATOR el nuevo coordinador
+/// es el nodo que lo envia, y cada nodo que lo acepta le responde CoordinatorAck
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MensajeEleccion {
+    Election,
+    Okey,
+    Coordinator,
+    CoordinatorAck,
+}
+
+/// Estado de la eleccion en un nodo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EstadoEleccion {
+    /// No hay una eleccion en curso
+    Inactiva,
+    /// Se envio ELECTION a los nodos de id mayor y se espera algun OKEY
+    EsperandoOkey,
+    /// Un nodo de id mayor respondio OKEY, se espera que se anuncie como coordinador
+    EsperandoCoordinator,
+}
+
+/// Que se espera al programar un timeout. La espera del COORDINATOR tiene que ser mas larga que la
+/// de los OKEY, porque el nodo que respondio OKEY primero espera los OKEY de su propia eleccion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EsperaEleccion {
+    Okey,
+    Coordinator,
+    /// Confirmaciones del COORDINATOR que envio el nuevo coordinador
+    CoordinatorAck,
+}
+
+/// Medio por el que la eleccion se comunica con el resto de los nodos. No necesita ser confiable:
+/// los mensajes perdidos se cubren con los timeouts de la eleccion y los reenvios del COORDINATOR
+pub trait Transporte<Id> {
+    /// Envia el mensaje al nodo destino
+    fn enviar(&mut self, destino: Id, mensaje: MensajeEleccion);
+
+    /// Programa un timeout de espera. Al vencer se debe invocar `Election::timeout` con la misma
+    /// ronda; los timeouts de rondas anteriores se ignoran, por lo que no hace falta cancelarlos
+    fn programar_timeout(&mut self, ronda: u64, espera: EsperaEleccion);
+
+    /// Notifica que se conoce un nuevo coordinador, que puede ser el propio nodo
+    fn nuevo_coordinador(&mut self, id: Id);
+}
+
+/// Algoritmo bully de eleccion de coordinador, independiente del transporte y del tipo de id.
+/// Se invoca `iniciar` cuando se detecta la caida del coordinador, `recibir` con cada mensaje de
+/// otro nodo y `timeout` al vencer cada timeout programado. Gana el nodo de mayor id que este vivo
+/// y participe de las elecciones
+pub struct Election<T, Id> {
+    /// id de este nodo
+    id: Id,
+    /// ids de todos los nodos del cluster
+    nodos: Vec<Id>,
+    /// false si el nodo no puede ser coordinador (una replica): no responde ELECTION ni se postula
+    participa: bool,
+    estado: EstadoEleccion,
+    /// ultimo coordinador conocido
+    coordinador: Option<Id>,
+    /// se incrementa con cada timeout programado y con cada cambio que invalida los pendientes
+    ronda: u64,
+    /// nodos que todavia no confirmaron el COORDINATOR de este nodo
+    sin_confirmar: Vec<Id>,
+    /// reenvios del COORDINATOR realizados desde que este nodo se proclamo
+    reenvios: u32,
+    /// reenvios luego de los cuales se deja de esperar a los nodos que no confirmaron
+    max_reenvios: u32,
+    transporte: T,
+}
+
+impl<T, Id> Election<T, Id>
+where
+    T: Transporte<Id>,
+    Id: Copy + Ord + Debug,
+{
+    pub fn new(
+        id: Id,
+        nodos: Vec<Id>,
+        participa: bool,
+        coordinador: Option<Id>,
+        max_reenvios: u32,
+        transporte: T,
+    ) -> Self {
+        Election {
+            id,
+            nodos,
+            participa,
+            estado: EstadoEleccion::Inactiva,
+            coordinador,
+            ronda: 0,
+            sin_confirmar: vec![],
+            reenvios: 0,
+            max_reenvios,
+            transporte,
+        }
+    }
+
+    pub fn estado(&self) -> EstadoEleccion {
+        self.estado
+    }
+
+    pub fn coordinador(&self) -> Option<Id> {
+        self.coordinador
+    }
+
+    pub fn soy_coordinador(&self) -> bool {
+        self.coordinador == Some(self.id)
+    }
+
+    pub fn transporte(&self) -> &T {
+        &self.transporte
+    }
+
+    pub fn transporte_mut(&mut self) -> &mut T {
+        &mut self.transporte
+    }
+
+    /// Olvida el coordinador conocido y cualquier eleccion en curso, por ejemplo al desconectarse
+    pub fn reiniciar(&mut self) {
+        self.estado = EstadoEleccion::Inactiva;
+        self.coordinador = None;
+        self.ronda += 1;
+        self.sin_confirmar.clear();
+    }
+
+    /// Empieza una eleccion enviando ELECTION a los nodos de id mayor, salvo que ya haya una en curso
+    pub fn iniciar(&mut self) {
+        if self.estado != EstadoEleccion::Inactiva {
+            return;
+        }
+        info!("[BULLY-{:?}] Inicio una eleccion", self.id);
+        self.estado = EstadoEleccion::EsperandoOkey;
+        for destino in self.nodos.iter().filter(|destino| **destino > self.id) {
+            self.transporte.enviar(*destino, MensajeEleccion::Election);
+        }
+        self.esperar(EsperaEleccion::Okey);
+    }
+
+    /// Procesa un mensaje recibido del nodo origen
+    pub fn recibir(&mut self, origen: Id, mensaje: MensajeEleccion) {
+        match mensaje {
+            MensajeEleccion::Election => self.recibir_election(origen),
+            MensajeEleccion::Okey => self.recibir_okey(origen),
+            MensajeEleccion::Coordinator => self.recibir_coordinator(origen),
+            MensajeEleccion::CoordinatorAck => self.sin_confirmar.retain(|id| *id != origen),
+        }
+    }
+
+    /// Un nodo de id menor inicio una eleccion: se le responde OKEY para que espere y este nodo se
+    /// postula. Si el nodo no participa no responde, asi el otro nunca lo espera
+    fn recibir_election(&mut self, origen: Id) {
+        if !self.participa {
+            return;
+        }
+        self.transporte.enviar(origen, MensajeEleccion::Okey);
+        self.iniciar();
+    }
+
+    /// Un nodo de id mayor va a postularse: se espera su COORDINATOR, con un nuevo timeout por si se
+    /// cae antes de anunciarse
+    fn recibir_okey(&mut self, origen: Id) {
+        if self.estado != EstadoEleccion::EsperandoOkey {
+            return;
+        }
+        info!(
+            "[BULLY-{:?}] El nodo {:?} me respondio OKEY, espero al coordinador",
+            self.id, origen
+        );
+        self.estado = EstadoEleccion::EsperandoCoordinator;
+        self.esperar(EsperaEleccion::Coordinator);
+    }
+
+    /// Un nodo se anuncia coordinador. Si tiene menor id que este nodo (por ejemplo porque se perdio
+    /// el OKEY de este nodo) no se lo acepta y se inicia una eleccion para desplazarlo. Si se lo
+    /// acepta se le confirma; un COORDINATOR reenviado porque se perdio la confirmacion solo se
+    /// vuelve a confirmar, sin avisar otra vez del mismo coordinador
+    fn recibir_coordinator(&mut self, origen: Id) {
+        if self.participa && origen < self.id {
+            info!(
+                "[BULLY-{:?}] Rechazo al coordinador {:?} por tener menor id",
+                self.id, origen
+            );
+            self.iniciar();
+            return;
+        }
+        self.transporte
+            .enviar(origen, MensajeEleccion::CoordinatorAck);
+        if self.estado == EstadoEleccion::Inactiva && self.coordinador == Some(origen) {
+            return;
+        }
+        self.estado = EstadoEleccion::Inactiva;
+        self.ronda += 1;
+        self.coordinador = Some(origen);
+        self.sin_confirmar.clear();
+        self.transporte.nuevo_coordinador(origen);
+    }
+
+    /// Vencio el timeout de la ronda indicada. Si nadie respondio OKEY este nodo es el coordinador;
+    /// si respondieron pero el COORDINATOR no llego, el nodo que respondio se cayo y se vuelve a
+    /// empezar la eleccion. Si este nodo es el coordinador, se le reenvia el COORDINATOR a los nodos
+    /// que no lo confirmaron
+    pub fn timeout(&mut self, ronda: u64) {
+        if ronda != self.ronda {
+            return;
+        }
+        match self.estado {
+            EstadoEleccion::Inactiva => self.reenviar_coordinator(),
+            EstadoEleccion::EsperandoOkey => self.proclamarse(),
+            EstadoEleccion::EsperandoCoordinator => {
+                info!(
+                    "[BULLY-{:?}] No llego el COORDINATOR, reinicio la eleccion",
+                    self.id
+                );
+                self.estado = EstadoEleccion::Inactiva;
+                self.iniciar();
+            }
+        }
+    }
+
+    fn proclamarse(&mut self) {
+        info!(
+            "[BULLY-{:?}] Nadie me respondio OKEY, soy el nuevo coordinador",
+            self.id
+        );
+        self.estado = EstadoEleccion::Inactiva;
+        self.ronda += 1;
+        self.coordinador = Some(self.id);
+        self.sin_confirmar = self
+            .nodos
+            .iter()
+            .filter(|destino| **destino != self.id)
+            .copied()
+            .collect();
+        self.reenvios = 0;
+        for destino in self.sin_confirmar.iter() {
+            self.transporte
+                .enviar(*destino, MensajeEleccion::Coordinator);
+        }
+        self.transporte.nuevo_coordinador(self.id);
+        self.esperar(EsperaEleccion::CoordinatorAck);
+    }
+
+    /// Reenvia el COORDINATOR a los nodos que no lo confirmaron, hasta max_reenvios veces. Luego se
+    /// los deja de esperar: estan caidos o se enteran del coordinador al reconectarse
+    fn reenviar_coordinator(&mut self) {
+        if !self.soy_coordinador() || self.sin_confirmar.is_empty() {
+            return;
+        }
+        if self.reenvios >= self.max_reenvios {
+            warn!(
+                "[BULLY-{:?}] Los nodos {:?} no confirmaron el COORDINATOR luego de {} reenvios",
+                self.id, self.sin_confirmar, self.reenvios
+            );
+            self.sin_confirmar.clear();
+            return;
+        }
+        self.reenvios += 1;
+        for destino in self.sin_confirmar.iter() {
+            self.transporte
+                .enviar(*destino, MensajeEleccion::Coordinator);
+        }
+        self.esperar(EsperaEleccion::CoordinatorAck);
+    }
+
+    fn esperar(&mut self, espera: EsperaEleccion) {
+        self.ronda += 1;
+        self.transporte.programar_timeout(self.ronda, espera);
+    }
+}
+
+#[cfg(test)]
+mod bully_test {
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet, VecDeque};
+    use std::rc::Rc;
+
+    use super::*;
+
+    type Envio = (u8, u8, MensajeEleccion);
+
+    /// Duracion de las esperas en la red simulada, la del COORDINATOR es el doble como en el
+    /// BullyListener
+    const ESPERA_OKEY: u64 = 10;
+    const ESPERA_COORDINATOR: u64 = 20;
+    const MAX_REENVIOS: u32 = 2;
+
+    /// Red simulada: los mensajes quedan en una cola hasta que el test los entrega y los timeouts
+    /// vencen segun un tiempo simulado. Entregar un mensaje no consume tiempo
+    #[derive(Default)]
+    struct Red {
+        ahora: u64,
+        en_transito: VecDeque<Envio>,
+        /// (vencimiento, nodo, ronda) en el orden en que se programaron
+        timeouts: Vec<(u64, u8, u64)>,
+    }
+
+    struct TransporteSimulado {
+        id: u8,
+        red: Rc<RefCell<Red>>,
+        coordinadores: Vec<u8>,
+    }
+
+    impl Transporte<u8> for TransporteSimulado {
+        fn enviar(&mut self, destino: u8, mensaje: MensajeEleccion) {
+            self.red
+                .borrow_mut()
+                .en_transito
+                .push_back((self.id, destino, mensaje));
+        }
+
+        fn programar_timeout(&mut self, ronda: u64, espera: EsperaEleccion) {
+            let mut red = self.red.borrow_mut();
+            let vencimiento = red.ahora
+                + match espera {
+                    EsperaEleccion::Okey | EsperaEleccion::CoordinatorAck => ESPERA_OKEY,
+                    EsperaEleccion::Coordinator => ESPERA_COORDINATOR,
+                };
+            red.timeouts.push((vencimiento, self.id, ronda));
+        }
+
+        fn nuevo_coordinador(&mut self, id: u8) {
+            self.coordinadores.push(id);
+        }
+    }
+
+    struct Cluster {
+        red: Rc<RefCell<Red>>,
+        nodos: HashMap<u8, Election<TransporteSimulado, u8>>,
+        caidos: HashSet<u8>,
+    }
+
+    impl Cluster {
+        /// Cluster con los nodos 1..=cantidad, las replicas no participan de las elecciones
+        fn new(cantidad: u8, replicas: &[u8]) -> Self {
+            let red = Rc::new(RefCell::new(Red::default()));
+            let ids: Vec<u8> = (1..=cantidad).collect();
+            let nodos = ids
+                .iter()
+                .map(|id| {
+                    let transporte = TransporteSimulado {
+                        id: *id,
+                        red: red.clone(),
+                        coordinadores: vec![],
+                    };
+                    let eleccion = Election::new(
+                        *id,
+                        ids.clone(),
+                        !replicas.contains(id),
+                        Some(cantidad),
+                        MAX_REENVIOS,
+                        transporte,
+                    );
+                    (*id, eleccion)
+                })
+                .collect();
+            Cluster {
+                red,
+                nodos,
+                caidos: HashSet::new(),
+            }
+        }
+
+        fn caer(&mut self, id: u8) {
+            self.caidos.insert(id);
+        }
+
+        fn iniciar(&mut self, id: u8) {
+            self.nodos.get_mut(&id).unwrap().iniciar();
+        }
+
+        /// Entrega los mensajes en transito a los nodos vivos, salvo los que descarta el filtro
+        fn entregar(&mut self, perder: impl Fn(&Envio) -> bool) {
+            loop {
+                let envio = self.red.borrow_mut().en_transito.pop_front();
+                let (origen, destino, mensaje) = match envio {
+                    Some(envio) if !perder(&envio) => envio,
+                    Some(_) => continue,
+                    None => return,
+                };
+                if !self.caidos.contains(&destino) {
+                    self.nodos
+                        .get_mut(&destino)
+                        .unwrap()
+                        .recibir(origen, mensaje);
+                }
+            }
+        }
+
+        /// Avanza el tiempo hasta el proximo timeout, lo vence y entrega los mensajes que genera,
+        /// salvo los que descarta el filtro. Retorna false si no quedan timeouts
+        fn vencer_proximo_timeout(&mut self, perder: impl Fn(&Envio) -> bool) -> bool {
+            let proximo = {
+                let mut red = self.red.borrow_mut();
+                let posicion = red
+                    .timeouts
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(posicion, (vencimiento, _, _))| (*vencimiento, *posicion))
+                    .map(|(posicion, _)| posicion);
+                posicion.map(|posicion| {
+                    let timeout = red.timeouts.remove(posicion);
+                    red.ahora = timeout.0;
+                    timeout
+                })
+            };
+            let (_, id, ronda) = match proximo {
+                Some(timeout) => timeout,
+                None => return false,
+            };
+            if !self.caidos.contains(&id) {
+                self.nodos.get_mut(&id).unwrap().timeout(ronda);
+            }
+            self.entregar(perder);
+            true
+        }
+
+        /// Entrega los mensajes y vence los timeouts hasta que no queda nada pendiente
+        fn estabilizar(&mut self) {
+            self.entregar(|_| false);
+            for _ in 0..100 {
+                if !self.vencer_proximo_timeout(|_| false) {
+                    return;
+                }
+            }
+            panic!("La eleccion no termino");
+        }
+
+        fn coordinador_de(&self, id: u8) -> Option<u8> {
+            self.nodos[&id].coordinador()
+        }
+
+        fn vivos(&self) -> Vec<u8> {
+            let mut vivos: Vec<u8> = self
+                .nodos
+                .keys()
+                .filter(|id| !self.caidos.contains(id))
+                .copied()
+                .collect();
+            vivos.sort();
+            vivos
+        }
+    }
+
+    #[test]
+    fn sin_respuestas_el_nodo_se_proclama_coordinador() {
+        let mut cluster = Cluster::new(3, &[]);
+        cluster.caer(3);
+        cluster.caer(2);
+        cluster.iniciar(1);
+        cluster.estabilizar();
+
+        assert_eq!(cluster.coordinador_de(1), Some(1));
+        assert!(cluster.nodos[&1].soy_coordinador());
+        assert_eq!(cluster.nodos[&1].transporte().coordinadores, vec![1]);
+    }
+
+    #[test]
+    fn candidatos_simultaneos_eligen_al_mayor_vivo() {
+        let mut cluster = Cluster::new(4, &[]);
+        cluster.caer(4);
+        cluster.iniciar(1);
+        cluster.iniciar(2);
+        cluster.iniciar(3);
+        cluster.estabilizar();
+
+        for id in cluster.vivos() {
+            assert_eq!(cluster.coordinador_de(id), Some(3));
+            assert_eq!(cluster.nodos[&id].estado(), EstadoEleccion::Inactiva);
+        }
+        // el coordinador se anuncia una sola vez
+        assert_eq!(cluster.nodos[&1].transporte().coordinadores, vec![3]);
+    }
+
+    #[test]
+    fn okey_perdido_no_deja_dos_coordinadores() {
+        let mut cluster = Cluster::new(4, &[]);
+        cluster.caer(4);
+        cluster.iniciar(2);
+        // se pierden los OKEY que 3 le responde a 2, entonces 2 se proclama coordinador
+        cluster.entregar(|(origen, _, mensaje)| *origen == 3 && *mensaje == MensajeEleccion::Okey);
+        cluster.estabilizar();
+
+        for id in cluster.vivos() {
+            assert_eq!(cluster.coordinador_de(id), Some(3));
+        }
+        assert!(!cluster.nodos[&2].soy_coordinador());
+    }
+
+    #[test]
+    fn coordinador_caido_durante_la_eleccion_se_reemplaza() {
+        let mut cluster = Cluster::new(4, &[]);
+        cluster.iniciar(1);
+        // 4 responde OKEY a los demas y se cae antes de anunciarse
+        cluster.entregar(|_| false);
+        assert_eq!(
+            cluster.nodos[&3].estado(),
+            EstadoEleccion::EsperandoCoordinator
+        );
+        cluster.caer(4);
+        cluster.estabilizar();
+
+        for id in cluster.vivos() {
+            assert_eq!(cluster.coordinador_de(id), Some(3));
+        }
+    }
+
+    #[test]
+    fn las_replicas_no_se_postulan() {
+        let mut cluster = Cluster::new(4, &[4]);
+        cluster.iniciar(1);
+        cluster.estabilizar();
+
+        for id in cluster.vivos() {
+            assert_eq!(cluster.coordinador_de(id), Some(3));
+        }
+        assert_eq!(cluster.nodos[&4].estado(), EstadoEleccion::Inactiva);
+    }
+
+    #[test]
+    fn los_timeouts_de_rondas_anteriores_se_ignoran() {
+        let mut cluster = Cluster::new(2, &[]);
+        cluster.iniciar(1);
+        let (_, _, ronda_okey) = cluster.red.borrow().timeouts[0];
+        cluster.estabilizar();
+        assert_eq!(cluster.coordinador_de(1), Some(2));
+        assert_eq!(cluster.nodos[&1].transporte().coordinadores, vec![2]);
+
+        cluster.nodos.get_mut(&1).unwrap().timeout(ronda_okey);
+        assert_eq!(cluster.coordinador_de(1), Some(2));
+        assert!(!cluster.nodos[&1].soy_coordinador());
+    }
+
+    #[test]
+    fn coordinator_perdido_se_reenvia_hasta_que_se_confirma() {
+        let mut cluster = Cluster::new(3, &[]);
+        cluster.caer(3);
+        cluster.iniciar(2);
+        // 2 se proclama coordinador y se pierde su anuncio a 1
+        cluster.vencer_proximo_timeout(|(_, destino, mensaje)| {
+            *destino == 1 && *mensaje == MensajeEleccion::Coordinator
+        });
+        assert!(cluster.nodos[&2].soy_coordinador());
+        assert_eq!(cluster.coordinador_de(1), Some(3));
+
+        cluster.estabilizar();
+        assert_eq!(cluster.coordinador_de(1), Some(2));
+        assert_eq!(cluster.nodos[&1].transporte().coordinadores, vec![2]);
+    }
+
+    #[test]
+    fn coordinator_se_reenvia_una_cantidad_acotada_de_veces_y_se_avisa_una_sola_vez() {
+        let mut cluster = Cluster::new(3, &[]);
+        cluster.caer(3);
+        cluster.iniciar(2);
+        let anuncios = RefCell::new(HashMap::new());
+        // se pierde la primera confirmacion de 1, por lo que recibe el COORDINATOR dos veces
+        let acks_perdidos = RefCell::new(0);
+        while cluster.vencer_proximo_timeout(|(origen, destino, mensaje)| {
+            if *mensaje == MensajeEleccion::Coordinator {
+                *anuncios.borrow_mut().entry(*destino).or_insert(0) += 1;
+            }
+            let perder = *origen == 1
+                && *mensaje == MensajeEleccion::CoordinatorAck
+                && *acks_perdidos.borrow() == 0;
+            if perder {
+                *acks_perdidos.borrow_mut() += 1;
+            }
+            perder
+        }) {}
+
+        assert_eq!(Some(&2), anuncios.borrow().get(&1));
+        assert_eq!(Some(&(1 + MAX_REENVIOS)), anuncios.borrow().get(&3));
+        assert_eq!(cluster.nodos[&1].transporte().coordinadores, vec![2]);
+    }
+}
@@ -1,50 +1,123 @@
 use std::net::SocketAddr;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use crate::bully::{self, EsperaEleccion, MensajeEleccion, Transporte};
 use crate::bully_messages::{
-    Coordinator, Election, MensajeBully, MensajeBullyBytes, OkeyBully, Ping, PingCord,
+    Coordinator, CoordinatorAck, Election, MensajeBully, MensajeBullyBytes, OkeyBully, Ping,
+    PingCord,
 };
+use crate::buzones::{atender_medidos, Medido, BUZON_BULLY_LISTENER};
+use crate::caos;
+use crate::eventos::publicar_evento;
 use crate::nodo::ReceiveNewCoordinator;
-use crate::utils::{
-    id_to_addr_read_bully, id_to_addr_write_bully, CANT_MAX_NODOS, ID_CORDINADOR_INICIAL,
-    MAX_UDP_SIZE, TIMEOUT_OK_BULLY_MILLIS,
-};
-use crate::{
-    error_server::{ErrorServer, TipoError},
-    nodo::Nodo,
-};
-
-use actix::clock::sleep;
+use crate::utils::{id_to_addr_read_bully, id_to_addr_write_bully, MAX_UDP_SIZE};
+use crate::{error_server::ErrorServer, nodo::Nodo};
 
 use actix::fut::wrap_future;
 use actix::{
     Actor, ActorFutureExt, Addr, AsyncContext, Context, ContextFutureSpawner, Handler, Message,
-    ResponseActFuture, WrapFuture,
+    WrapFuture,
 };
+use compartido::codificable::Codificable;
+use compartido::config::config;
+use compartido::ids::IdNodo;
+use tiempo::Reloj;
 use tokio::net::UdpSocket;
+use tracing::{error, info, warn};
+
+/// Transporte de la eleccion sobre los sockets udp del bully listener. Los envios y los timeouts se
+/// delegan al actor, que los realiza de forma asincronica
+pub struct TransporteUdp {
+    /// Id del nodo
+    id_nodo: u8,
+    /// Dirección del actor bully listener que realiza los envios
+    addr_bully: Addr<BullyListener>,
+    /// Dirección del actor nodo al que se le notifica el nuevo coordinador
+    addr_actor_nodo: Addr<Nodo>,
+    /// momento en que este nodo se entero de la eleccion en curso, para loguear cuanto tardo en
+    /// conocer al nuevo coordinador
+    inicio_eleccion: Option<Instant>,
+    reloj: Arc<dyn Reloj>,
+}
+
+impl TransporteUdp {
+    /// Registra el inicio de la eleccion, si no habia una en curso
+    fn marcar_inicio_eleccion(&mut self) {
+        self.inicio_eleccion.get_or_insert(self.reloj.ahora());
+    }
+}
 
-/// Enum que representa los posibles estados del actor
-#[derive(Debug, Clone, Copy)]
-pub enum BullyState {
-    NotExecuting = 0,
-    WaitingOkey,
-    WaitingCoordinator,
+impl Transporte<u8> for TransporteUdp {
+    fn enviar(&mut self, destino: u8, mensaje: MensajeEleccion) {
+        let vec = match mensaje {
+            MensajeEleccion::Election => Election::new(self.id_nodo).to_bytes(),
+            MensajeEleccion::Okey => OkeyBully::new(self.id_nodo).to_bytes(),
+            MensajeEleccion::Coordinator => Coordinator::new(self.id_nodo).to_bytes(),
+            MensajeEleccion::CoordinatorAck => CoordinatorAck::new(self.id_nodo).to_bytes(),
+        };
+        match id_to_addr_write_bully(destino).parse() {
+            Ok(socket) => self.addr_bully.do_send(SenderToUdp {
+                vec,
+                socket,
+                id_destino: destino,
+            }),
+            Err(err) => error!(
+                "[BULLY-LISTENER-{:?}] Error al formar la direccion del ID_NODO {:?} | Detalle: {:?}",
+                self.id_nodo, destino, err
+            ),
+        }
+    }
+
+    fn programar_timeout(&mut self, ronda: u64, espera: EsperaEleccion) {
+        self.addr_bully.do_send(ProgramarTimeout { ronda, espera });
+    }
+
+    fn nuevo_coordinador(&mut self, id: u8) {
+        if let Some(inicio) = self.inicio_eleccion.take() {
+            info!(
+                "[BULLY-LISTENER-{:?}] La eleccion convergio en {:?}",
+                self.id_nodo,
+                self.reloj.ahora().duration_since(inicio)
+            );
+        }
+        if id == self.id_nodo {
+            publicar_evento(
+                &format!("BULLY-{}", self.id_nodo),
+                "COORDINADOR",
+                format!("ID_NODO = {} es el nuevo coordinador", self.id_nodo),
+            );
+        }
+        info!(
+            "[BULLY-LISTENER-{:?}] El ID_NODO {:?} es el COORDINATOR, se lo NOTIFICO AL NODO",
+            self.id_nodo, id
+        );
+        if let Err(err) = self.addr_actor_nodo.try_send(ReceiveNewCoordinator {
+            id_nodo_coordinador: IdNodo(id),
+        }) {
+            error!(
+                "[BULLY-LISTENER-{:?}] Error al enviar mensaje COORDINATOR al nodo {:?} | Detalle: {:?}",
+                self.id_nodo, id, err
+            );
+        }
+    }
 }
 
-/// Estructura que almacena lo necesario para el actor bully listener
+/// Estructura que almacena lo necesario para el actor bully listener, un adaptador udp del
+/// algoritmo de eleccion del modulo bully
 pub struct BullyListener {
     /// Dirección del actor nodo asociado a este bully listener
     addr_actor_nodo: Addr<Nodo>,
-    /// Estado del actor
-    estado: BullyState,
+    /// Eleccion de coordinador, con sus estados y timeouts
+    eleccion: bully::Election<TransporteUdp, u8>,
     /// Representa si el nodo esta conectado o no a la red
     conectado: bool,
     /// Socket utilizado para la escritura a otros bully listeners
     socket_nodo_to_write: Option<UdpSocket>,
     /// Id del nodo
     id_nodo: u8,
-    /// Flag que indica si el nodo asociado es coordinador
-    soy_coordinador: bool,
+    /// Reloj con el que se programan los timeouts de la eleccion
+    reloj: Arc<dyn Reloj>,
 }
 
 impl Actor for BullyListener {
@@ -56,24 +129,38 @@ impl BullyListener {
     pub async fn start(
         id_nodo: u8,
         addr_actor_nodo: Addr<Nodo>,
+        replica: bool,
+        reloj: Arc<dyn Reloj>,
     ) -> Result<Addr<BullyListener>, ErrorServer> {
-        let socket_nodo_to_read = UdpSocket::bind(id_to_addr_write_bully(id_nodo))
-            .await
-            .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorConexion))?;
-
-        let socket_nodo_to_write = UdpSocket::bind(id_to_addr_read_bully(id_nodo))
-            .await
-            .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorConexion))?;
-
-        let addr_actor = BullyListener {
-            addr_actor_nodo,
-            estado: BullyState::NotExecuting,
-            conectado: true,
-            socket_nodo_to_write: Some(socket_nodo_to_write),
-            id_nodo,
-            soy_coordinador: id_nodo == ID_CORDINADOR_INICIAL,
-        }
-        .start();
+        let socket_nodo_to_read = UdpSocket::bind(id_to_addr_write_bully(id_nodo)).await?;
+
+        let socket_nodo_to_write = UdpSocket::bind(id_to_addr_read_bully(id_nodo)).await?;
+
+        let addr_actor = BullyListener::create(|ctx| {
+            // una replica nunca puede ser elegida coordinador, no participa de las elecciones
+            let eleccion = bully::Election::new(
+                id_nodo,
+                (1..=config().cluster.cant_max_nodos).collect(),
+                !replica,
+                Some(config().cluster.id_coordinador_inicial),
+                config().servidor.max_reenvios_coordinator_bully,
+                TransporteUdp {
+                    id_nodo,
+                    addr_bully: ctx.address(),
+                    addr_actor_nodo: addr_actor_nodo.clone(),
+                    inicio_eleccion: None,
+                    reloj: reloj.clone(),
+                },
+            );
+            BullyListener {
+                addr_actor_nodo,
+                eleccion,
+                conectado: true,
+                socket_nodo_to_write: Some(socket_nodo_to_write),
+                id_nodo,
+                reloj,
+            }
+        });
         let addr_actor_bully_clone = addr_actor.clone();
 
         tokio::spawn(async move {
@@ -83,10 +170,13 @@ impl BullyListener {
                     .recv_from(&mut buf)
                     .await
                     .expect("failed to receive from socket");
-                addr_actor_bully_clone.do_send(StreamHandlerUdp {
-                    vec: buf[..cantidad_leida].to_vec(),
-                    _socket: socket,
-                });
+                addr_actor_bully_clone.do_send(Medido::new(
+                    &BUZON_BULLY_LISTENER,
+                    StreamHandlerUdp {
+                        vec: buf[..cantidad_leida].to_vec(),
+                        _socket: socket,
+                    },
+                ));
             }
         });
 
@@ -101,6 +191,8 @@ struct StreamHandlerUdp {
     _socket: SocketAddr,
 }
 
+atender_medidos!(BullyListener: StreamHandlerUdp);
+
 /// Mensaje que se recibe cuando llega algo via UDP
 /// El handler construye el mensaje y lo fordwardea según corresponda
 impl Handler<StreamHandlerUdp> for BullyListener {
@@ -110,133 +202,105 @@ impl Handler<StreamHandlerUdp> for BullyListener {
         if !self.conectado {
             return;
         }
-        let tipo_mensaje = MensajeBully::from_bytes(msg.vec[0]);
-        match tipo_mensaje {
+        let tipo_mensaje = match msg.vec.first() {
+            Some(tipo) => MensajeBully::from_bytes(*tipo),
+            None => return,
+        };
+        let resultado = match tipo_mensaje {
             MensajeBully::OKEY => {
-                let mensaje = OkeyBully::from_bytes(msg.vec);
-                ctx.address().do_send(mensaje);
+                OkeyBully::from_bytes(&msg.vec).map(|mensaje| ctx.address().do_send(mensaje))
             }
             MensajeBully::ELECTION => {
-                let mensaje = Election::from_bytes(msg.vec);
-                ctx.address().do_send(mensaje);
+                Election::from_bytes(&msg.vec).map(|mensaje| ctx.address().do_send(mensaje))
             }
             MensajeBully::COORDINATOR => {
-                let mensaje = Coordinator::from_bytes(msg.vec);
-                ctx.address().do_send(mensaje);
+                Coordinator::from_bytes(&msg.vec).map(|mensaje| ctx.address().do_send(mensaje))
+            }
+            MensajeBully::COORDINATORACK => {
+                CoordinatorAck::from_bytes(&msg.vec).map(|mensaje| ctx.address().do_send(mensaje))
             }
             MensajeBully::PING => {
-                let mensaje = Ping::from_bytes(msg.vec);
-                ctx.address().do_send(mensaje);
+                Ping::from_bytes(&msg.vec).map(|mensaje| ctx.address().do_send(mensaje))
             }
             MensajeBully::PINGCORD => {
-                let mensaje = PingCord::from_bytes(msg.vec);
-                ctx.address().do_send(mensaje);
+                PingCord::from_bytes(&msg.vec).map(|mensaje| ctx.address().do_send(mensaje))
+            }
+            _ => {
+                warn!("Mensaje desconocido");
+                Ok(())
             }
-            _ => println!("Mensaje desconocido"),
+        };
+        if let Err(err) = resultado {
+            warn!(
+                "[BULLY-LISTENER-{:?}] Se descarta un mensaje invalido: {}",
+                self.id_nodo, err
+            );
         }
     }
 }
 
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
-struct TimeoutHandler;
+struct ProgramarTimeout {
+    ronda: u64,
+    espera: EsperaEleccion,
+}
 
-/// Mensaje que se recibe cuando el future con un timeout creado al iniciar un election
-/// termina. En la función se chequea si llego un Okey, en caso contrario el bully listener
-/// se autoproclama coordinador y envia coordinator al resto de los nodos
-impl Handler<TimeoutHandler> for BullyListener {
+/// Mensaje que envia la eleccion para programar un timeout. La espera de los OKEY y de las
+/// confirmaciones del COORDINATOR es de timeout_ok_bully_millis y la del COORDINATOR del doble,
+/// porque el nodo que respondio OKEY primero espera los OKEY de su propia eleccion. Al vencer se le
+/// avisa a la eleccion
+impl Handler<ProgramarTimeout> for BullyListener {
     type Result = ();
 
-    fn handle(&mut self, _: TimeoutHandler, ctx: &mut Context<Self>) -> Self::Result {
-        if self.estado as u8 == BullyState::WaitingOkey as u8 {
-            println!(
-                "[BULLY-LISTENER-{:?}] TIMEOUT END. Nadie me respondio OK. Soy el nuevo coordinador",
-                self.id_nodo
-            );
-            self.soy_coordinador = true;
-            for i in 1..(CANT_MAX_NODOS + 1) {
-                let socket: SocketAddr = id_to_addr_write_bully(i)
-                    .parse()
-                    .expect("Error al formar SocketAddr");
-                if let Err(err) = ctx.address().try_send({
-                    SenderToUdp {
-                        vec: Coordinator::new(self.id_nodo).to_bytes(),
-                        socket,
-                    }
-                }) {
-                    println!("[BULLY-LISTENER-{:?}] Error al enviar mensaje de COORDINATOR al nodo {:?} {:?}", self.id_nodo, i, err);
-                }
+    fn handle(&mut self, msg: ProgramarTimeout, ctx: &mut Context<Self>) -> Self::Result {
+        let millis = match msg.espera {
+            EsperaEleccion::Okey | EsperaEleccion::CoordinatorAck => {
+                config().servidor.timeout_ok_bully_millis
             }
-
-            self.estado = BullyState::NotExecuting;
-        } else {
-            println!(
-                "[BULLY-LISTENER-{:?}] ALGUIEN ME RESPONDIO OK",
-                self.id_nodo
-            );
-
-            self.estado = BullyState::WaitingCoordinator;
-        }
+            EsperaEleccion::Coordinator => 2 * config().servidor.timeout_ok_bully_millis,
+        };
+        self.reloj
+            .esperar(Duration::from_millis(millis))
+            .into_actor(self)
+            .map(move |_, me, _| me.eleccion.timeout(msg.ronda))
+            .spawn(ctx);
     }
 }
 
 /// Mensaje que se recibe de otro nodo cuando su id es mayor y me responde un election
-/// El handler setea el estado interno del actor para que sepa que debe esperar a que se
-/// anuncie el coordinador
 impl Handler<OkeyBully> for BullyListener {
     type Result = ();
-    fn handle(&mut self, _msg: OkeyBully, _ctx: &mut Self::Context) -> Self::Result {
-        self.estado = BullyState::WaitingCoordinator;
+    fn handle(&mut self, msg: OkeyBully, _ctx: &mut Self::Context) -> Self::Result {
+        self.eleccion.recibir(msg.id_nodo, MensajeEleccion::Okey);
     }
 }
 
 /// Mensaje que se recibe de otro nodo cuando comienza el algoritmo de bully
-/// El handler envia un Okey al nodo que lo envio
 impl Handler<Election> for BullyListener {
     type Result = ();
-    fn handle(&mut self, msg: Election, ctx: &mut Self::Context) -> Self::Result {
-        let socket: SocketAddr = id_to_addr_write_bully(msg.id_nodo)
-            .parse()
-            .expect("Error al formar SocketAddr");
-        if let Err(err) = ctx.address().try_send({
-            SenderToUdp {
-                vec: OkeyBully::new(self.id_nodo).to_bytes(),
-                socket,
-            }
-        }) {
-            println!(
-                "[BULLY-LISTENER-{:?}] Error al enviar OKEY al nodo {:?} | Detalle: {:?}",
-                self.id_nodo, msg.id_nodo, err
-            );
-        }
-
-        if self.conectado {
-            if let Err(err) = ctx.address().try_send(StartElection {}) {
-                println!(
-                    "[BULLY-LISTENER-{:?}] Error al enviar OKEY al nodo {:?} | Detalle: {:?}",
-                    self.id_nodo, msg.id_nodo, err
-                );
-            }
-        }
+    fn handle(&mut self, msg: Election, _ctx: &mut Self::Context) -> Self::Result {
+        self.eleccion.transporte_mut().marcar_inicio_eleccion();
+        self.eleccion
+            .recibir(msg.id_nodo, MensajeEleccion::Election);
     }
 }
 
 /// Mensaje que se recibe cuando un nodo se anuncia nuevo coordinador
-/// El handler notifica al actor nodo del id del nuevo coordinador
 impl Handler<Coordinator> for BullyListener {
     type Result = ();
     fn handle(&mut self, msg: Coordinator, _ctx: &mut Self::Context) -> Self::Result {
-        println!(
-            "[BULLY-LISTENER-{:?}] Recibiendo ID {:?} COORDINATOR, se lo NOTIFICO AL NODO",
-            msg.id_nodo, self.id_nodo
-        );
+        self.eleccion
+            .recibir(msg.id_nodo, MensajeEleccion::Coordinator);
+    }
+}
 
-        self.estado = BullyState::NotExecuting;
-        if let Err(err) = self.addr_actor_nodo.try_send(ReceiveNewCoordinator {
-            id_nodo_coordinador: msg.id_nodo,
-        }) {
-            println!("[BULLY-LISTENER-{:?}] Error al enviar mensaje COORDINATOR al nodo {:?} | Detalle: {:?}", self.id_nodo, msg.id_nodo, err);
-        }
+/// Mensaje que se recibe de un nodo que acepto a este nodo como coordinador
+impl Handler<CoordinatorAck> for BullyListener {
+    type Result = ();
+    fn handle(&mut self, msg: CoordinatorAck, _ctx: &mut Self::Context) -> Self::Result {
+        self.eleccion
+            .recibir(msg.id_nodo, MensajeEleccion::CoordinatorAck);
     }
 }
 
@@ -245,46 +309,18 @@ impl Handler<Coordinator> for BullyListener {
 pub struct StartElection;
 
 /// Mensaje que se recibe del actor nodo para empezar el algoritmo bully
-/// El handler envia un mensaje election a todos los nodos de id superior
 impl Handler<StartElection> for BullyListener {
-    type Result = ResponseActFuture<Self, ()>;
+    type Result = ();
 
-    fn handle(&mut self, _msg: StartElection, ctx: &mut Self::Context) -> Self::Result {
-        println!(
-            "[BULLY-LISTENER-{:?}] START ELECTION. EMPIEZO TIMEOUT",
-            self.id_nodo
+    fn handle(&mut self, _msg: StartElection, _ctx: &mut Self::Context) -> Self::Result {
+        info!("[BULLY-LISTENER-{:?}] START ELECTION", self.id_nodo);
+        publicar_evento(
+            &format!("BULLY-{}", self.id_nodo),
+            "ELECCION",
+            format!("ID_NODO = {} inicia una eleccion", self.id_nodo),
         );
-
-        self.estado = BullyState::WaitingOkey;
-
-        for i in 1..(CANT_MAX_NODOS + 1) {
-            if i > self.id_nodo {
-                let socket: SocketAddr = id_to_addr_write_bully(i)
-                    .parse()
-                    .expect("Error al formar SocketAddr");
-                if let Err(err) = ctx.address().try_send({
-                    SenderToUdp {
-                        vec: Election::new(self.id_nodo).to_bytes(),
-                        socket,
-                    }
-                }) {
-                    println!("[BULLY-LISTENER-{:?}] Error al enviar ELECTION al ID_NODO {:?} | Detalle: {:?}", self.id_nodo, i, err);
-                }
-            }
-        }
-
-        Box::pin(
-            sleep(Duration::from_millis(TIMEOUT_OK_BULLY_MILLIS))
-                .into_actor(self)
-                .map(move |_result, me, ctx| {
-                    if let Err(err) = ctx.address().try_send(TimeoutHandler {}) {
-                        println!(
-                            "[BULLY-LISTENER-{:?}] Error al crear mi TIMEOUT | Detalle: {:?}",
-                            me.id_nodo, err
-                        );
-                    }
-                }),
-        )
+        self.eleccion.transporte_mut().marcar_inicio_eleccion();
+        self.eleccion.iniciar();
     }
 }
 
@@ -293,6 +329,8 @@ impl Handler<StartElection> for BullyListener {
 pub struct SenderToUdp {
     pub vec: Vec<u8>,
     pub socket: SocketAddr,
+    /// id del nodo al que se envia, para aplicar la latencia del enlace
+    pub id_destino: u8,
 }
 
 /// Cuando se envia algo por UDP, el actor se autoenvía este mensaje con el contenido
@@ -301,6 +339,22 @@ impl Handler<SenderToUdp> for BullyListener {
     type Result = ();
 
     fn handle(&mut self, msg: SenderToUdp, ctx: &mut Context<Self>) -> Self::Result {
+        let demora = caos::demora(self.id_nodo, msg.id_destino);
+        if demora.is_zero() {
+            self.enviar_udp(msg, ctx);
+            return;
+        }
+        // como en udp, los mensajes demorados pueden llegar desordenados
+        self.reloj
+            .esperar(demora)
+            .into_actor(self)
+            .map(move |_, me, ctx| me.enviar_udp(msg, ctx))
+            .spawn(ctx);
+    }
+}
+
+impl BullyListener {
+    fn enviar_udp(&mut self, msg: SenderToUdp, ctx: &mut Context<Self>) {
         if !self.conectado {
             return;
         }
@@ -334,23 +388,20 @@ impl Handler<SetState> for BullyListener {
     fn handle(&mut self, msg: SetState, _ctx: &mut Context<Self>) -> Self::Result {
         self.conectado = msg.estado;
         if self.conectado {
-            for i in 1..(CANT_MAX_NODOS + 1) {
-                let socket: SocketAddr = id_to_addr_write_bully(i).parse().map_err(|_| {
-                    ErrorServer::new("Error al formar SocketAddr", TipoError::ErrorGenerico)
+            for i in 1..(config().cluster.cant_max_nodos + 1) {
+                let socket: SocketAddr = id_to_addr_write_bully(i)
+                    .parse()
+                    .map_err(|_| ErrorServer::Parseo("Error al formar SocketAddr".to_string()))?;
+                _ctx.address().try_send({
+                    SenderToUdp {
+                        vec: Ping::new(self.id_nodo).to_bytes(),
+                        socket,
+                        id_destino: i,
+                    }
                 })?;
-                _ctx.address()
-                    .try_send({
-                        SenderToUdp {
-                            vec: Ping::new(self.id_nodo).to_bytes(),
-                            socket,
-                        }
-                    })
-                    .map_err(|_| {
-                        ErrorServer::new("Error al enviar mensaje", TipoError::ErrorGenerico)
-                    })?;
             }
         } else {
-            self.soy_coordinador = false;
+            self.eleccion.reiniciar();
         }
 
         Ok(())
@@ -363,7 +414,7 @@ impl Handler<Ping> for BullyListener {
     type Result = ();
 
     fn handle(&mut self, msg: Ping, ctx: &mut Self::Context) -> Self::Result {
-        if self.soy_coordinador {
+        if self.eleccion.soy_coordinador() {
             let socket: SocketAddr = id_to_addr_write_bully(msg.id_nodo)
                 .parse()
                 .expect("Error al formar SocketAddr");
@@ -371,9 +422,10 @@ impl Handler<Ping> for BullyListener {
                 SenderToUdp {
                     vec: PingCord::new(self.id_nodo).to_bytes(),
                     socket,
+                    id_destino: msg.id_nodo,
                 }
             }) {
-                println!(
+                error!(
                     "[BULLY-LISTENER-{:?}] Error al enviar PING_CORD al nodo {:?} | Detalle: {:?}",
                     self.id_nodo, msg.id_nodo, err
                 );
@@ -389,9 +441,9 @@ impl Handler<PingCord> for BullyListener {
 
     fn handle(&mut self, msg: PingCord, _ctx: &mut Self::Context) -> Self::Result {
         if let Err(err) = self.addr_actor_nodo.try_send(ReceiveNewCoordinator {
-            id_nodo_coordinador: msg.id_nodo,
+            id_nodo_coordinador: IdNodo(msg.id_nodo),
         }) {
-            println!(
+            error!(
                 "[BULLY-LISTENER-{:?}] Error al enviar PING_CORD al nodo. | Detalle: {:?}",
                 self.id_nodo, err
             );
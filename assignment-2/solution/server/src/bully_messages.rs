@@ -1,161 +1,165 @@
 use actix::Message;
+use compartido::codificable::{Codificable, TIPOS_BULLY};
+use serde::{Deserialize, Serialize};
 
+/// Mensajes del algoritmo bully, sus tipos empiezan en TIPOS_BULLY
 #[derive(Debug, Clone, Copy)]
 pub enum MensajeBully {
-    OKEY = 0,
+    OKEY = TIPOS_BULLY as isize,
     ELECTION,
     COORDINATOR,
     PING,
     PINGCORD,
+    COORDINATORACK,
     DESCONOCIDO,
 }
 
 impl MensajeBully {
     pub fn from_bytes(byte: u8) -> MensajeBully {
-        match byte {
+        match byte.wrapping_sub(TIPOS_BULLY) {
             0_u8 => MensajeBully::OKEY,
             1_u8 => MensajeBully::ELECTION,
             2_u8 => MensajeBully::COORDINATOR,
             3_u8 => MensajeBully::PING,
             4_u8 => MensajeBully::PINGCORD,
+            5_u8 => MensajeBully::COORDINATORACK,
             _ => MensajeBully::DESCONOCIDO,
         }
     }
 }
 
-pub trait MensajeBullyBytes {
+pub trait MensajeBullyBytes: Codificable {
     fn new(id_nodo: u8) -> Self;
     fn get_id_nodo(&self) -> u8;
-    fn get_tipo_mensaje(&self) -> u8;
-
-    fn to_bytes(&self) -> Vec<u8> {
-        vec![self.get_tipo_mensaje(), self.get_id_nodo()]
-    }
-
-    fn from_bytes(bytes: Vec<u8>) -> Self
-    where
-        Self: Sized,
-    {
-        Self::new(bytes[1])
-    }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeBully::OKEY)]
 pub struct OkeyBully {
-    pub tipo_mensaje: u8,
     pub id_nodo: u8,
 }
 
 impl MensajeBullyBytes for OkeyBully {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-
     fn get_id_nodo(&self) -> u8 {
         self.id_nodo
     }
 
     fn new(id_nodo: u8) -> OkeyBully {
-        OkeyBully {
-            tipo_mensaje: MensajeBully::OKEY as u8,
-            id_nodo,
-        }
+        OkeyBully { id_nodo }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeBully::ELECTION)]
 pub struct Election {
-    pub tipo_mensaje: u8,
     pub id_nodo: u8,
 }
 
 impl MensajeBullyBytes for Election {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-
     fn get_id_nodo(&self) -> u8 {
         self.id_nodo
     }
 
     fn new(id_nodo: u8) -> Election {
-        Election {
-            tipo_mensaje: MensajeBully::ELECTION as u8,
-            id_nodo,
-        }
+        Election { id_nodo }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeBully::COORDINATOR)]
 pub struct Coordinator {
-    pub tipo_mensaje: u8,
     pub id_nodo: u8,
 }
 
 impl MensajeBullyBytes for Coordinator {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
+    fn get_id_nodo(&self) -> u8 {
+        self.id_nodo
+    }
+
+    fn new(id_nodo: u8) -> Coordinator {
+        Coordinator { id_nodo }
     }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = MensajeBully::COORDINATORACK)]
+/// Confirmacion de un COORDINATOR, el nuevo coordinador se lo reenvia a los nodos que no la envian
+pub struct CoordinatorAck {
+    pub id_nodo: u8,
+}
 
+impl MensajeBullyBytes for CoordinatorAck {
     fn get_id_nodo(&self) -> u8 {
         self.id_nodo
     }
 
-    fn new(id_nodo: u8) -> Coordinator {
-        Coordinator {
-            tipo_mensaje: MensajeBully::COORDINATOR as u8,
-            id_nodo,
-        }
+    fn new(id_nodo: u8) -> CoordinatorAck {
+        CoordinatorAck { id_nodo }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeBully::PING)]
 pub struct Ping {
-    pub tipo_mensaje: u8,
     pub id_nodo: u8,
 }
 
 impl MensajeBullyBytes for Ping {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-
     fn get_id_nodo(&self) -> u8 {
         self.id_nodo
     }
 
     fn new(id_nodo: u8) -> Ping {
-        Ping {
-            tipo_mensaje: MensajeBully::PING as u8,
-            id_nodo,
-        }
+        Ping { id_nodo }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = MensajeBully::PINGCORD)]
 pub struct PingCord {
-    pub tipo_mensaje: u8,
     pub id_nodo: u8,
 }
 
 impl MensajeBullyBytes for PingCord {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-
     fn get_id_nodo(&self) -> u8 {
         self.id_nodo
     }
 
     fn new(id_nodo: u8) -> PingCord {
-        PingCord {
-            tipo_mensaje: MensajeBully::PINGCORD as u8,
-            id_nodo,
+        PingCord { id_nodo }
+    }
+}
+
+#[cfg(test)]
+mod bully_messages_test {
+    use compartido::codec::Codec;
+
+    use super::*;
+
+    fn ida_y_vuelta<M: MensajeBullyBytes>() {
+        let mensaje = M::new(3);
+        for codec in [Codec::Bytes, Codec::Cbor] {
+            let bytes = mensaje.codificar(codec);
+            assert_eq!(Some(&M::TIPO), bytes.first());
+            let recibido = M::decodificar(&bytes, codec).unwrap();
+            assert_eq!(3, recibido.get_id_nodo());
+            assert_eq!(bytes, recibido.codificar(codec));
         }
     }
+
+    #[test]
+    fn todos_los_mensajes_del_bully_van_y_vuelven_con_ambos_codecs() {
+        ida_y_vuelta::<OkeyBully>();
+        ida_y_vuelta::<Election>();
+        ida_y_vuelta::<Coordinator>();
+        ida_y_vuelta::<CoordinatorAck>();
+        ida_y_vuelta::<Ping>();
+        ida_y_vuelta::<PingCord>();
+    }
 }
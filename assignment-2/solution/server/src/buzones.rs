@@ -0,0 +1,171 @@
+//! Medicion de los buzones de los actores del nodo: cuantos mensajes esperan en cada uno y cuanto
+//! tardan desde el do_send hasta que se los atiende. Los mensajes que llegan de la red a cada actor se
+//! envuelven en un Medido, asi la sobrecarga de un actor se ve en la consulta de estado antes de que
+//! aparezca como timeouts
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use actix::Message;
+
+/// Medidas del buzon de un actor. Son estaticas porque cada proceso corre un solo nodo
+#[derive(Debug)]
+pub struct Buzon {
+    actor: &'static str,
+    /// mensajes enviados que todavia no se atendieron ni se descartaron
+    pendientes: AtomicU64,
+    maximo_pendientes: AtomicU64,
+    atendidos: AtomicU64,
+    /// demora entre el envio y la atencion del ultimo mensaje atendido, en microsegundos
+    demora_ultima_micros: AtomicU64,
+    demora_maxima_micros: AtomicU64,
+}
+
+pub static BUZON_NODO: Buzon = Buzon::new("Nodo");
+pub static BUZON_COORDINADOR: Buzon = Buzon::new("Coordinador");
+pub static BUZON_CAFETERA_LISTENER: Buzon = Buzon::new("CafeteraListener");
+pub static BUZON_BULLY_LISTENER: Buzon = Buzon::new("BullyListener");
+
+/// Buzones que se informan en la consulta de estado
+pub static BUZONES: [&Buzon; 4] = [
+    &BUZON_NODO,
+    &BUZON_COORDINADOR,
+    &BUZON_CAFETERA_LISTENER,
+    &BUZON_BULLY_LISTENER,
+];
+
+impl Buzon {
+    pub const fn new(actor: &'static str) -> Buzon {
+        Buzon {
+            actor,
+            pendientes: AtomicU64::new(0),
+            maximo_pendientes: AtomicU64::new(0),
+            atendidos: AtomicU64::new(0),
+            demora_ultima_micros: AtomicU64::new(0),
+            demora_maxima_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn pendientes(&self) -> u64 {
+        self.pendientes.load(Ordering::Relaxed)
+    }
+
+    pub fn demora_ultima(&self) -> Duration {
+        Duration::from_micros(self.demora_ultima_micros.load(Ordering::Relaxed))
+    }
+
+    pub fn demora_maxima(&self) -> Duration {
+        Duration::from_micros(self.demora_maxima_micros.load(Ordering::Relaxed))
+    }
+
+    fn encolar(&self) {
+        let pendientes = self.pendientes.fetch_add(1, Ordering::Relaxed) + 1;
+        self.maximo_pendientes
+            .fetch_max(pendientes, Ordering::Relaxed);
+    }
+
+    fn atender(&self, demora: Duration) {
+        let micros = demora.as_micros() as u64;
+        self.atendidos.fetch_add(1, Ordering::Relaxed);
+        self.demora_ultima_micros.store(micros, Ordering::Relaxed);
+        self.demora_maxima_micros
+            .fetch_max(micros, Ordering::Relaxed);
+    }
+
+    /// Linea del buzon para la respuesta a la consulta de estado
+    pub fn resumen(&self) -> String {
+        format!(
+            "ACTOR = {} | EN COLA = {} (MAXIMO {}) | ATENDIDOS = {} | DEMORA ULTIMA = {:?} | DEMORA MAXIMA = {:?}",
+            self.actor,
+            self.pendientes(),
+            self.maximo_pendientes.load(Ordering::Relaxed),
+            self.atendidos.load(Ordering::Relaxed),
+            self.demora_ultima(),
+            self.demora_maxima()
+        )
+    }
+}
+
+/// Mensaje para un actor con el momento en que se envio. Cuenta como pendiente en el buzon desde que
+/// se crea hasta que se atiende o se descarta (por ejemplo si el actor ya se detuvo)
+pub struct Medido<M> {
+    mensaje: Option<M>,
+    enviado: Instant,
+    buzon: &'static Buzon,
+}
+
+impl<M> Medido<M> {
+    /// Envuelve el mensaje que se va a enviar al actor del buzon
+    pub fn new(buzon: &'static Buzon, mensaje: M) -> Medido<M> {
+        buzon.encolar();
+        Medido {
+            mensaje: Some(mensaje),
+            enviado: Instant::now(),
+            buzon,
+        }
+    }
+
+    /// Registra la demora del mensaje y lo retorna para atenderlo
+    pub fn atender(mut self) -> M {
+        self.buzon.atender(self.enviado.elapsed());
+        self.mensaje
+            .take()
+            .expect("El mensaje se atiende una sola vez")
+    }
+}
+
+impl<M> Drop for Medido<M> {
+    fn drop(&mut self) {
+        self.buzon.pendientes.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<M: Message> Message for Medido<M> {
+    type Result = M::Result;
+}
+
+/// Implementa Handler<Medido<M>> para el actor con cada mensaje M indicado, que ya atiende: registra
+/// la demora y lo atiende con su propio handler
+macro_rules! atender_medidos {
+    ($actor:ty: $($mensaje:ty),+ $(,)?) => {
+        $(
+            impl actix::Handler<$crate::buzones::Medido<$mensaje>> for $actor {
+                type Result = <$actor as actix::Handler<$mensaje>>::Result;
+
+                fn handle(
+                    &mut self,
+                    msg: $crate::buzones::Medido<$mensaje>,
+                    ctx: &mut Self::Context,
+                ) -> Self::Result {
+                    <$actor as actix::Handler<$mensaje>>::handle(self, msg.atender(), ctx)
+                }
+            }
+        )+
+    };
+}
+
+pub(crate) use atender_medidos;
+
+#[cfg(test)]
+mod buzones_test {
+    use super::*;
+
+    #[test]
+    fn el_buzon_cuenta_los_pendientes_hasta_atenderlos_o_descartarlos() {
+        static BUZON: Buzon = Buzon::new("Prueba");
+        let primero = Medido::new(&BUZON, 1_u32);
+        let segundo = Medido::new(&BUZON, 2_u32);
+        assert_eq!(2, BUZON.pendientes());
+
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(1, primero.atender());
+        assert_eq!(1, BUZON.pendientes());
+        assert!(BUZON.demora_ultima() >= Duration::from_millis(5));
+
+        drop(segundo);
+        assert_eq!(0, BUZON.pendientes());
+        assert!(BUZON
+            .resumen()
+            .starts_with("ACTOR = Prueba | EN COLA = 0 (MAXIMO 2) | ATENDIDOS = 1"));
+    }
+}
@@ -1,21 +1,60 @@
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use crate::buzones::{atender_medidos, Medido, BUZONES, BUZON_CAFETERA_LISTENER, BUZON_NODO};
+use crate::eventos::publicar_evento;
 use crate::nodo::ReceiveFromCafetera;
 use crate::utils::{id_to_addr_read_data, id_to_addr_write_data, MAX_UDP_SIZE};
-use crate::{
-    error_server::{ErrorServer, TipoError},
-    nodo::Nodo,
-};
+use crate::{error_server::ErrorServer, nodo::Nodo};
 use actix::fut::wrap_future;
-use actix::{Actor, ActorFutureExt, ContextFutureSpawner, Message};
+use actix::{Actor, ActorFutureExt, AsyncContext, ContextFutureSpawner, Message};
 
 use actix::{Addr, Context, Handler};
+use compartido::autenticacion::firma_valida;
+use compartido::codec::id_cafetera_from_bytes;
+use compartido::codificable::Codificable;
+use compartido::config::{config, ID_CAFETERA_ADMINISTRACION};
+use compartido::error_mensaje::ErrorMensaje;
+use compartido::ids::{IdCafetera, IdNodo};
+use compartido::mensajes_cafetera::{
+    id_pedido_from_bytes, Autenticado, Desafio, Desregistrar, Error, MensajeCafetera,
+    MensajeCafeteraBytes, Registrar,
+};
+use tiempo::Reloj;
 use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Tiempo durante el que se puede usar un desafio para firmar un comando de administracion
+const VIGENCIA_DESAFIO: Duration = Duration::from_secs(5);
+
+/// Informacion de una cafetera registrada en el nodo
+pub struct CafeteraRegistrada {
+    /// id de la cafetera dentro de su sucursal
+    pub id_cafetera: IdCafetera,
+    /// momento en el que se recibio el ultimo mensaje de la cafetera
+    pub ultimo_mensaje: Instant,
+}
+
 pub struct CafeteraListener {
+    /// id del nodo, para los eventos de los comandos de administracion
+    id_nodo: IdNodo,
     /// direccion del mail box del actor Nodo
     addr_actor_nodo: Addr<Nodo>,
     /// Socket udp donde puede recibir mensajes de la/s cafetera
     socket_nodo_to_write: Option<UdpSocket>,
+    /// Cafeteras registradas, con clave el socket y el id de la cafetera: todas las cafeteras de una
+    /// sucursal envian sus mensajes desde el mismo socket
+    cafeteras: HashMap<(SocketAddr, IdCafetera), CafeteraRegistrada>,
+    /// Reloj con el que se registra el ultimo mensaje de cada cafetera
+    reloj: Arc<dyn Reloj>,
+    /// Ultimo desafio enviado a cada socket y el momento en que se envio, se quita al usarlo
+    desafios: HashMap<SocketAddr, (u64, Instant)>,
+    /// Pedidos que llegan al nodo sin pasar por udp (los del servicio grpc), con clave la direccion
+    /// con la que el nodo los identifica: sus respuestas se entregan por el canal
+    canales_locales: HashMap<SocketAddr, mpsc::UnboundedSender<Vec<u8>>>,
 }
 
 /// Actor encargado de recibir y enviar mensajes a la cafetera por udp.
@@ -28,19 +67,21 @@ impl CafeteraListener {
     /// Crea los sockets, empieza un nuevo actor CafeteraListener y dispara una Task
     /// encargada de escuchar por udp los mensajes provenientes de las cafeteras y enviarselos al actor
     pub async fn start(
-        id_nodo: u8,
+        id_nodo: IdNodo,
         addr_actor_nodo: Addr<Nodo>,
+        reloj: Arc<dyn Reloj>,
     ) -> Result<Addr<CafeteraListener>, ErrorServer> {
-        let socket_nodo_to_read = UdpSocket::bind(id_to_addr_read_data(id_nodo))
-            .await
-            .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorConexion))?;
-        let socket_nodo_to_write = UdpSocket::bind(id_to_addr_write_data(id_nodo))
-            .await
-            .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorConexion))?;
+        let socket_nodo_to_read = UdpSocket::bind(id_to_addr_read_data(id_nodo.0)).await?;
+        let socket_nodo_to_write = UdpSocket::bind(id_to_addr_write_data(id_nodo.0)).await?;
 
         let addr_actor = CafeteraListener {
+            id_nodo,
             addr_actor_nodo,
             socket_nodo_to_write: Some(socket_nodo_to_write),
+            cafeteras: HashMap::new(),
+            reloj,
+            desafios: HashMap::new(),
+            canales_locales: HashMap::new(),
         }
         .start();
         let addr_actor_clone = addr_actor.clone();
@@ -52,15 +93,95 @@ impl CafeteraListener {
                     .recv_from(&mut buf)
                     .await
                     .expect("failed to receive from socket");
-                addr_actor_clone.do_send(StreamHandlerUdp {
-                    vec: buf[..cantidad_leida].to_vec(),
-                    socket,
-                });
+                addr_actor_clone.do_send(Medido::new(
+                    &BUZON_CAFETERA_LISTENER,
+                    StreamHandlerUdp {
+                        vec: buf[..cantidad_leida].to_vec(),
+                        socket,
+                    },
+                ));
             }
         });
 
         Ok(addr_actor)
     }
+
+    /// Arma el texto con las cafeteras registradas que se responde a un mensaje de Estado
+    fn estado_to_string(&self) -> String {
+        let mut estado = format!("CAFETERAS REGISTRADAS: {}\n", self.cafeteras.len());
+        for ((socket, _), cafetera) in self.cafeteras.iter() {
+            estado.push_str(&format!(
+                "ID_CAFETERA = {} | SOCKET = {} | ULTIMO MENSAJE HACE {} ms\n",
+                cafetera.id_cafetera,
+                socket,
+                self.reloj
+                    .ahora()
+                    .saturating_duration_since(cafetera.ultimo_mensaje)
+                    .as_millis()
+            ));
+        }
+        estado.push_str("BUZONES DE LOS ACTORES:\n");
+        for buzon in BUZONES {
+            estado.push_str(&buzon.resumen());
+            estado.push('\n');
+        }
+        estado
+    }
+
+    /// Genera un desafio nuevo para el socket, descartando los vencidos
+    fn nuevo_desafio(&mut self, socket: SocketAddr) -> u64 {
+        let ahora = self.reloj.ahora();
+        self.desafios
+            .retain(|_, (_, enviado)| ahora.saturating_duration_since(*enviado) < VIGENCIA_DESAFIO);
+        let desafio = rand::random::<u64>().max(1);
+        self.desafios.insert(socket, (desafio, ahora));
+        desafio
+    }
+
+    /// Verifica la firma del comando con el desafio enviado al socket, que se usa una sola vez
+    fn verificar(&mut self, autenticado: &Autenticado, socket: SocketAddr) -> bool {
+        match self.desafios.remove(&socket) {
+            Some((desafio, enviado))
+                if self.reloj.ahora().saturating_duration_since(enviado) < VIGENCIA_DESAFIO =>
+            {
+                firma_valida(
+                    &config().cluster.secreto_admin,
+                    desafio,
+                    &autenticado.comando,
+                    &autenticado.firma,
+                )
+            }
+            _ => false,
+        }
+    }
+
+    /// true si se puede aplicar el comando de administracion: solo se aceptan los que llegaron
+    /// firmados, por lo que sin cluster.secreto_admin se rechazan todos. Los aceptados quedan en los
+    /// logs y en los eventos del nodo con el socket del que provienen
+    fn admitir_comando_admin(
+        &self,
+        tipo_mensaje: MensajeCafetera,
+        socket: SocketAddr,
+        autenticado: bool,
+    ) -> bool {
+        if !autenticado {
+            warn!(
+                "[CAFETERA-LISTENER] Rechazo {:?} de {} por no estar firmado",
+                tipo_mensaje, socket
+            );
+            return false;
+        }
+        info!(
+            "[CAFETERA-LISTENER] Comando de administracion {:?} aceptado de {}",
+            tipo_mensaje, socket
+        );
+        publicar_evento(
+            &format!("NODO-{}", self.id_nodo),
+            "ADMINISTRACION",
+            format!("{:?} desde {}", tipo_mensaje, socket),
+        );
+        true
+    }
 }
 
 #[derive(Message, Debug)]
@@ -72,15 +193,167 @@ struct StreamHandlerUdp {
     socket: SocketAddr,
 }
 
-/// Cuando recibe algo de la cafeterea lo forwardea al actor nodo con el socket_addr correspondiente
+/// Informa que se descarto un mensaje que no se pudo interpretar
+fn descartar_mensaje(socket: SocketAddr, err: ErrorMensaje) {
+    warn!(
+        "[CAFETERA-LISTENER] Se descarta un mensaje invalido de {}: {}",
+        socket, err
+    );
+}
+
+atender_medidos!(CafeteraListener: StreamHandlerUdp);
+
+/// Cuando recibe algo de la cafeterea lo forwardea al actor nodo con el socket_addr correspondiente.
+/// Los mensajes de registro y de estado se resuelven aca mismo, sin pasar por el nodo
 impl Handler<StreamHandlerUdp> for CafeteraListener {
     type Result = ();
 
-    fn handle(&mut self, msg: StreamHandlerUdp, _ctx: &mut Context<Self>) -> Self::Result {
-        self.addr_actor_nodo.do_send(ReceiveFromCafetera {
-            msg: msg.vec,
-            socket: msg.socket,
-        });
+    fn handle(&mut self, msg: StreamHandlerUdp, ctx: &mut Context<Self>) -> Self::Result {
+        self.procesar(msg, false, ctx);
+    }
+}
+
+impl CafeteraListener {
+    /// Procesa un mensaje recibido por udp. `autenticado` indica si llego dentro de un Autenticado
+    /// con una firma valida
+    fn procesar(&mut self, msg: StreamHandlerUdp, autenticado: bool, ctx: &mut Context<Self>) {
+        if msg.vec.is_empty() {
+            return;
+        }
+        let tipo_mensaje = MensajeCafetera::from_bytes(msg.vec[0]);
+        match tipo_mensaje {
+            MensajeCafetera::DESAFIO => {
+                let desafio = self.nuevo_desafio(msg.socket);
+                ctx.address().do_send(ReceiverActorNodo {
+                    vec: Desafio::de_cafetera(IdCafetera::default())
+                        .con_desafio(desafio)
+                        .to_bytes(),
+                    socket: msg.socket,
+                });
+                return;
+            }
+            MensajeCafetera::AUTENTICADO => {
+                let comando = match Autenticado::from_bytes(&msg.vec) {
+                    Ok(comando) => comando,
+                    Err(err) => return descartar_mensaje(msg.socket, err),
+                };
+                if !self.verificar(&comando, msg.socket) {
+                    warn!(
+                        "[CAFETERA-LISTENER] Se descarta un comando de {} con una firma invalida o sin desafio vigente",
+                        msg.socket
+                    );
+                    return;
+                }
+                return self.procesar(
+                    StreamHandlerUdp {
+                        vec: comando.comando,
+                        socket: msg.socket,
+                    },
+                    true,
+                    ctx,
+                );
+            }
+            MensajeCafetera::REGISTRAR => {
+                let registrar = match Registrar::from_bytes(&msg.vec) {
+                    Ok(registrar) => registrar,
+                    Err(err) => return descartar_mensaje(msg.socket, err),
+                };
+                info!(
+                    "[CAFETERA-LISTENER] Se registra ID_CAFETERA = {} desde {}",
+                    registrar.id_cafetera, msg.socket
+                );
+                self.cafeteras.insert(
+                    (msg.socket, registrar.id_cafetera),
+                    CafeteraRegistrada {
+                        id_cafetera: registrar.id_cafetera,
+                        ultimo_mensaje: self.reloj.ahora(),
+                    },
+                );
+                ctx.address().do_send(ReceiverActorNodo {
+                    vec: registrar.to_bytes(),
+                    socket: msg.socket,
+                });
+                return;
+            }
+            MensajeCafetera::DESREGISTRAR => {
+                let desregistrar = match Desregistrar::from_bytes(&msg.vec) {
+                    Ok(desregistrar) => desregistrar,
+                    Err(err) => return descartar_mensaje(msg.socket, err),
+                };
+                info!(
+                    "[CAFETERA-LISTENER] Se da de baja ID_CAFETERA = {} desde {}",
+                    desregistrar.id_cafetera, msg.socket
+                );
+                self.cafeteras
+                    .remove(&(msg.socket, desregistrar.id_cafetera));
+                ctx.address().do_send(ReceiverActorNodo {
+                    vec: desregistrar.to_bytes(),
+                    socket: msg.socket,
+                });
+                return;
+            }
+            MensajeCafetera::ESTADO => {
+                if !self.admitir_comando_admin(tipo_mensaje, msg.socket, autenticado) {
+                    return;
+                }
+                ctx.address().do_send(ReceiverActorNodo {
+                    vec: self.estado_to_string().into_bytes(),
+                    socket: msg.socket,
+                });
+                return;
+            }
+            MensajeCafetera::DESCONECTAR | MensajeCafetera::CONECTAR | MensajeCafetera::DRENAR => {
+                if !self.admitir_comando_admin(tipo_mensaje, msg.socket, autenticado) {
+                    return;
+                }
+            }
+            MensajeCafetera::ALTA | MensajeCafetera::BAJA => {
+                if !self.admitir_comando_admin(tipo_mensaje, msg.socket, autenticado) {
+                    return;
+                }
+            }
+            // las consultas de saldo del proceso desconexion tampoco provienen de cafeteras
+            MensajeCafetera::CONSULTAR => {}
+            // ni las correcciones de saldo, que envia firmadas con el id de administracion
+            _ if autenticado
+                || id_cafetera_from_bytes(&msg.vec) == Some(ID_CAFETERA_ADMINISTRACION) =>
+            {
+                if !self.admitir_comando_admin(tipo_mensaje, msg.socket, autenticado) {
+                    return;
+                }
+            }
+            _ => {
+                let id_cafetera = id_cafetera_from_bytes(&msg.vec).unwrap_or_default();
+                if let Some(cafetera) = self.cafeteras.get_mut(&(msg.socket, id_cafetera)) {
+                    cafetera.ultimo_mensaje = self.reloj.ahora();
+                } else if config().servidor.requiere_registro_cafeteras {
+                    warn!(
+                        "[CAFETERA-LISTENER] Rechazo {:?} de {} por no estar registrada",
+                        tipo_mensaje, msg.socket
+                    );
+                    if let MensajeCafetera::SUMAR
+                    | MensajeCafetera::RESTAR
+                    | MensajeCafetera::TRANSFERIR = tipo_mensaje
+                    {
+                        ctx.address().do_send(ReceiverActorNodo {
+                            vec: Error::de_cafetera(id_cafetera)
+                                .con_id_pedido(id_pedido_from_bytes(&msg.vec))
+                                .to_bytes(),
+                            socket: msg.socket,
+                        });
+                    }
+                    return;
+                }
+            }
+        }
+        self.addr_actor_nodo.do_send(Medido::new(
+            &BUZON_NODO,
+            ReceiveFromCafetera {
+                msg: msg.vec,
+                socket: msg.socket,
+                autenticado,
+            },
+        ));
     }
 }
 
@@ -93,11 +366,16 @@ pub struct ReceiverActorNodo {
     pub socket: SocketAddr,
 }
 
-/// Envia el mensaje vec a la cafetera con el socket udp correspondiente
+/// Envia el mensaje vec a la cafetera con el socket udp correspondiente, o por su canal si el pedido
+/// no llego por udp
 impl Handler<ReceiverActorNodo> for CafeteraListener {
     type Result = ();
 
     fn handle(&mut self, msg: ReceiverActorNodo, ctx: &mut Context<Self>) -> Self::Result {
+        if let Some(canal) = self.canales_locales.get(&msg.socket) {
+            let _res = canal.send(msg.vec);
+            return;
+        }
         let write = self
             .socket_nodo_to_write
             .take()
@@ -113,3 +391,36 @@ impl Handler<ReceiverActorNodo> for CafeteraListener {
         .wait(ctx);
     }
 }
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct AbrirCanalLocal {
+    /// direccion con la que el pedido llega al nodo
+    pub socket: SocketAddr,
+    /// por donde se entregan las respuestas del nodo
+    pub respuestas: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+/// Desde ahora las respuestas del nodo a la direccion se entregan por el canal
+impl Handler<AbrirCanalLocal> for CafeteraListener {
+    type Result = ();
+
+    fn handle(&mut self, msg: AbrirCanalLocal, _ctx: &mut Context<Self>) -> Self::Result {
+        self.canales_locales.insert(msg.socket, msg.respuestas);
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct CerrarCanalLocal {
+    pub socket: SocketAddr,
+}
+
+/// Quita el canal de la direccion, al terminar su pedido
+impl Handler<CerrarCanalLocal> for CafeteraListener {
+    type Result = ();
+
+    fn handle(&mut self, msg: CerrarCanalLocal, _ctx: &mut Context<Self>) -> Self::Result {
+        self.canales_locales.remove(&msg.socket);
+    }
+}
@@ -0,0 +1,76 @@
+//! Capa de caos del transporte entre nodos. Demora los mensajes de cada enlace (nodo origen -> nodo
+//! destino) segun las latencias de la seccion [caos] de la configuracion, para simular nodos en
+//! distintas regiones y medir como afecta a las rondas del commit de dos fases y a las elecciones
+
+use std::time::Duration;
+
+use compartido::config::{config, LatenciaEnlace};
+use rand::Rng;
+use tokio::time::Instant;
+
+/// Demora de un mensaje del enlace: la base mas un jitter sorteado entre 0 y jitter_millis
+pub fn sortear_demora(enlace: &LatenciaEnlace, rng: &mut impl Rng) -> Duration {
+    Duration::from_millis(enlace.base_millis + rng.gen_range(0..=enlace.jitter_millis))
+}
+
+/// Demora que se le aplica a un mensaje del nodo origen al nodo destino, cero si el enlace no tiene
+/// latencia configurada
+pub fn demora(origen: u8, destino: u8) -> Duration {
+    config()
+        .caos
+        .latencia(origen, destino)
+        .map_or(Duration::ZERO, |enlace| {
+            sortear_demora(enlace, &mut rand::thread_rng())
+        })
+}
+
+/// Momento a partir del cual se puede escribir un mensaje del nodo origen al nodo destino que se
+/// envia ahora, None si el enlace no tiene latencia. En las conexiones tcp el momento se calcula al
+/// enviar y se espera luego de tomar la conexion, asi los mensajes se demoran sin desordenarse y un
+/// mensaje no suma la demora de los anteriores
+pub fn entrega(origen: u8, destino: u8) -> Option<Instant> {
+    let demora = demora(origen, destino);
+    (!demora.is_zero()).then(|| Instant::now() + demora)
+}
+
+/// Espera hasta el momento de entrega del mensaje, si tiene uno
+pub async fn esperar_entrega(entrega: Option<Instant>) {
+    if let Some(entrega) = entrega {
+        tokio::time::sleep_until(entrega).await;
+    }
+}
+
+#[cfg(test)]
+mod caos_test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn la_demora_queda_entre_la_base_y_la_base_mas_el_jitter() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let enlace = LatenciaEnlace {
+            origen: 1,
+            destino: 2,
+            base_millis: 50,
+            jitter_millis: 10,
+        };
+        let demoras: Vec<Duration> = (0..200)
+            .map(|_| sortear_demora(&enlace, &mut rng))
+            .collect();
+        assert!(demoras
+            .iter()
+            .all(|x| (50..=60).contains(&(x.as_millis() as u64))));
+        assert!(demoras.iter().any(|x| *x != demoras[0]));
+
+        let sin_jitter = LatenciaEnlace {
+            jitter_millis: 0,
+            ..enlace
+        };
+        assert_eq!(
+            Duration::from_millis(50),
+            sortear_demora(&sin_jitter, &mut rng)
+        );
+    }
+}
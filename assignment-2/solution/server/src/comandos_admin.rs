@@ -0,0 +1,79 @@
+//! Proteccion contra la reproduccion de los comandos CONECTAR y DESCONECTAR del proceso desconexion.
+//! Cada comando lleva una secuencia (los microsegundos desde el epoch unix al enviarlo): el nodo solo
+//! acepta secuencias mayores a la del ultimo comando aceptado y cercanas a su propio reloj, por lo que
+//! un datagrama capturado no se puede reenviar ni enseguida ni despues de reiniciar el nodo
+
+use std::time::Duration;
+
+/// Motivo por el que se descarta un comando
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RechazoComando {
+    /// la secuencia no es mayor a la del ultimo comando aceptado
+    Repetido,
+    /// la secuencia esta fuera de la ventana alrededor del reloj del nodo
+    FueraDeVentana,
+}
+
+/// Filtro de los comandos de administracion que recibe un nodo
+#[derive(Debug)]
+pub struct FiltroComandosAdmin {
+    /// secuencia del ultimo comando aceptado
+    ultima: u64,
+    /// diferencia maxima entre la secuencia de un comando y el reloj del nodo
+    ventana: Duration,
+}
+
+impl FiltroComandosAdmin {
+    pub fn new(ventana: Duration) -> Self {
+        FiltroComandosAdmin { ultima: 0, ventana }
+    }
+
+    /// Acepta el comando con la secuencia indicada si es posterior al ultimo aceptado y no difiere de
+    /// `ahora` (en microsegundos desde el epoch unix) en mas de la ventana
+    pub fn aceptar(&mut self, secuencia: u64, ahora: u64) -> Result<(), RechazoComando> {
+        if secuencia <= self.ultima {
+            return Err(RechazoComando::Repetido);
+        }
+        if u128::from(secuencia.abs_diff(ahora)) > self.ventana.as_micros() {
+            return Err(RechazoComando::FueraDeVentana);
+        }
+        self.ultima = secuencia;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod comandos_admin_test {
+    use super::*;
+
+    const SEGUNDO: u64 = 1_000_000;
+
+    #[test]
+    fn se_rechazan_los_comandos_repetidos_viejos_o_adelantados() {
+        let mut filtro = FiltroComandosAdmin::new(Duration::from_secs(30));
+        let ahora = 1_700_000_000 * SEGUNDO;
+
+        assert_eq!(Ok(()), filtro.aceptar(ahora - SEGUNDO, ahora));
+        assert_eq!(
+            Err(RechazoComando::Repetido),
+            filtro.aceptar(ahora - SEGUNDO, ahora)
+        );
+        assert_eq!(
+            Err(RechazoComando::Repetido),
+            filtro.aceptar(ahora - 2 * SEGUNDO, ahora)
+        );
+        assert_eq!(Ok(()), filtro.aceptar(ahora, ahora));
+
+        // un comando capturado hace un minuto no sirve aunque el nodo se haya reiniciado
+        let mut reiniciado = FiltroComandosAdmin::new(Duration::from_secs(30));
+        assert_eq!(
+            Err(RechazoComando::FueraDeVentana),
+            reiniciado.aceptar(ahora - 60 * SEGUNDO, ahora)
+        );
+        assert_eq!(
+            Err(RechazoComando::FueraDeVentana),
+            reiniciado.aceptar(ahora + 60 * SEGUNDO, ahora)
+        );
+        assert_eq!(Ok(()), reiniciado.aceptar(ahora + SEGUNDO, ahora));
+    }
+}
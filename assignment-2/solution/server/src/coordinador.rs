@@ -1,119 +1,754 @@
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::vec;
 
-use actix::{Actor, Context, Handler, Message, StreamHandler};
-use actix::{Addr, AsyncContext};
+use actix::fut::wrap_future;
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, Context, Handler, Message, Recipient,
+    ResponseActFuture, StreamHandler,
+};
 
-use tokio::io::{split, AsyncBufReadExt, AsyncReadExt, BufReader};
-use tokio::net::TcpListener;
-use tokio::sync::Mutex;
-use tokio_stream::wrappers::LinesStream;
+use compartido::codificable::Codificable;
+use compartido::config::{
+    config, CONSULTA_AUDITORIA, CONSULTA_REGISTRO, CONSULTA_SALDOS, CONSULTA_TRANSACCIONES,
+    ID_CONSULTA_ADMIN,
+};
+use compartido::ids::{IdCafetera, IdCuenta, IdNodo, IdTransaccion};
+use compartido::logs::LimitadorLogs;
+use tokio::io::{split, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{oneshot, Mutex};
 
-use crate::error_server::{ErrorServer, TipoError};
+use crate::auditoria::{AuditoriaEnCurso, InformeAuditoria, RespuestaAuditoria, SnapshotSaldos};
+use crate::buzones::atender_medidos;
+use crate::error_server::ErrorServer;
+use crate::eventos::publicar_evento;
+use crate::latencias::LatenciasTransacciones;
 use crate::mensaje::{
-    Abort, Commit, CommitType, Execute, Finish, MensajeBytes, OkeyAbortToCoordinator,
-    OkeyToCoordinator, PingCord, Prepare, Starter, Yes,
+    Abort, AltaCord, Auditoria, BajaCord, Commit, CommitType, ConfirmadoToNodo, Execute,
+    FallidoToNodo, Finish, Mensaje, MensajeBytes, MotivoAbort, OkeyAbortToCoordinator,
+    OkeyToCoordinator, PedidoAuditoria, PedidoSnapshot, PingCord, Prepare, Rechazo, Retiro,
+    Snapshot, Starter, Transferencia, Validar, Valido, Yes,
 };
-use crate::nodo_handler::{NodoHandler, ReceiverFromCoordinador, Shutdown};
-use crate::utils::id_to_ctrladdr;
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
-enum TransactionState {
-    Uninitialized,
-    Wait,
-    Execute,
-    Commit,
-    Abort,
-    Done,
-}
-/// Información de importancia para el coordinador sobre una transacción
-struct TransactionCoordinator {
-    /// Estado en el que se encuentra la transaccion
-    status: TransactionState,
-    /// vector de id_nodos que respondieron al prepare
-    yes_nodos: Vec<u8>,
-    /// vector de id_nodos que respondieron al commit
-    ok_nodos: Vec<u8>,
+use crate::nodo_handler::{CanalNodo, NodoHandler, ReceiverFromCoordinador, Shutdown};
+use crate::registro_commits::{EntradaRegistro, RegistroCommits, ResultadoTransaccion};
+use crate::tpc::{CoordinadorTpc, EstadoTransaccion, MensajeTpc, Operacion, Transporte};
+use crate::utils::{cada_intervalo, configurar_keepalive, enmarcar, id_to_ctrladdr, leer_mensajes};
+use serde::{Deserialize, Serialize};
+use tiempo::Reloj;
+use tracing::{debug, error, info, warn};
+
+/// Como se une un nodo al coordinador, el byte que envia luego de su id al conectarse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModoConexion {
+    /// el nodo se reconecta conservando sus cuentas, recibe en orden los COMMIT/ABORT que se perdio
+    ConEstado = 0,
+    /// el nodo arranco de cero y no conserva cuentas de antes
+    SinEstado = 1,
+    /// el nodo funciono desconectado del coordinador (aceptando solo sumas) y sus saldos pueden estar
+    /// desactualizados: antes de volver a votar recibe los de referencia, aunque el coordinador
+    /// conozca lo que se perdio
+    Reconciliar = 2,
+}
+
+impl ModoConexion {
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => ModoConexion::SinEstado,
+            2 => ModoConexion::Reconciliar,
+            _ => ModoConexion::ConEstado,
+        }
+    }
+}
+
+/// Operacion de cafe que el coordinador lleva adelante con el commit de dos fases
+struct OperacionCafe {
     /// id_nodo que inicio la transaccion
-    from_id_nodo: u8,
+    from_id_nodo: IdNodo,
     /// cuenta a la que corresponde la transaccion
-    id_cuenta: u32,
+    id_cuenta: IdCuenta,
     /// tipo de orden a realizar
     tipo: CommitType,
     /// id de la cafetera del nodo
-    id_cafetera: u8,
+    id_cafetera: IdCafetera,
+    /// id del pedido asignado por la sucursal, para seguirlo entre procesos
+    id_pedido: u32,
+    /// cuenta a la que se suman los creditos, solo en las transferencias
+    id_cuenta_destino: Option<IdCuenta>,
+    /// cantidad de creditos implicados, se conoce al recibir el Finish
+    cantidad: u32,
+    /// motivo con el que se difunde el abort
+    motivo: MotivoAbort,
+    /// operacion de administracion, se adelanta en la cola de sus cuentas
+    prioritaria: bool,
+    /// momento en que se recibio el STARTER (el FINISH en las sumas), para medir la latencia
+    inicio: Instant,
+}
+
+impl OperacionCafe {
+    fn new(
+        from_id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        tipo: CommitType,
+        id_cafetera: IdCafetera,
+        id_pedido: u32,
+        inicio: Instant,
+    ) -> Self {
+        OperacionCafe {
+            from_id_nodo,
+            id_cuenta,
+            tipo,
+            id_cafetera,
+            id_pedido,
+            id_cuenta_destino: None,
+            cantidad: 0,
+            motivo: MotivoAbort::GENERICO,
+            prioritaria: false,
+            inicio,
+        }
+    }
+
+    /// Mensaje del protocolo para esta operacion, codificado para enviarlo a los nodos-handlers
+    fn codificar(&self, id_transaccion: IdTransaccion, mensaje: MensajeTpc) -> Vec<u8> {
+        match mensaje {
+            // en las transferencias el prepare es el mismo mensaje Transferencia, para que los
+            // nodos bloqueen ambas cuentas
+            MensajeTpc::Prepare => match self.id_cuenta_destino {
+                Some(id_cuenta_destino) => Transferencia::new(
+                    self.from_id_nodo,
+                    self.id_cuenta,
+                    id_transaccion,
+                    self.id_cafetera,
+                    id_cuenta_destino,
+                )
+                .con_id_pedido(self.id_pedido)
+                .to_bytes(),
+                None => Prepare::new(
+                    self.from_id_nodo,
+                    self.id_cuenta,
+                    id_transaccion,
+                    self.id_cafetera,
+                )
+                .con_id_pedido(self.id_pedido)
+                .to_bytes(),
+            },
+            MensajeTpc::Execute => Execute::new(
+                self.from_id_nodo,
+                self.id_cuenta,
+                id_transaccion,
+                self.id_cafetera,
+            )
+            .con_id_pedido(self.id_pedido)
+            .to_bytes(),
+            MensajeTpc::Validar => Validar::new(
+                self.from_id_nodo,
+                self.id_cuenta,
+                id_transaccion,
+                self.tipo,
+                self.cantidad,
+                self.id_cafetera,
+            )
+            .con_id_pedido(self.id_pedido)
+            .to_bytes(),
+            MensajeTpc::Commit { secuencia } => Commit::new(
+                self.from_id_nodo,
+                self.id_cuenta,
+                id_transaccion,
+                self.tipo,
+                self.cantidad,
+                self.id_cafetera,
+            )
+            .con_id_pedido(self.id_pedido)
+            .con_secuencia(secuencia)
+            .to_bytes(),
+            MensajeTpc::Abort { secuencia } => Abort::new(
+                self.from_id_nodo,
+                self.id_cuenta,
+                id_transaccion,
+                self.id_cafetera,
+            )
+            .con_id_pedido(self.id_pedido)
+            .con_motivo(self.motivo)
+            .con_secuencia(secuencia)
+            .to_bytes(),
+        }
+    }
+}
+
+impl Operacion for OperacionCafe {
+    /// Una resta usa su cuenta y una transferencia las dos
+    fn recursos(&self) -> Vec<IdCuenta> {
+        match self.id_cuenta_destino {
+            Some(id_cuenta_destino) => vec![self.id_cuenta, id_cuenta_destino],
+            None => vec![self.id_cuenta],
+        }
+    }
+
+    fn prioritaria(&self) -> bool {
+        self.prioritaria
+    }
+}
+
+/// Nodos-handlers por los que el commit de dos fases se comunica con los nodos
+struct RedNodos<'a> {
+    addr_nodos: &'a HashMap<IdNodo, CanalNodo>,
+    /// COMMIT/ABORT que se pierden los nodos desconectados
+    pendientes_nodos: &'a mut HashMap<IdNodo, Vec<Vec<u8>>>,
+    latencias: &'a mut LatenciasTransacciones,
+    reloj: &'a dyn Reloj,
+    errores_envio: &'a mut LimitadorLogs<IdNodo>,
+    registro: &'a mut RegistroCommits,
+}
+
+impl RedNodos<'_> {
+    /// Envia el mensaje al nodo si esta votando. Si se esta sincronizando o esta desconectado se le
+    /// guarda con sus COMMIT/ABORT pendientes, para que lo reciba luego de aplicarlos
+    fn entregar_mensaje(&mut self, id_nodo: IdNodo, vec: Vec<u8>, nombre: &str) {
+        if self.addr_nodos.contains_key(&id_nodo) {
+            self.enviar_mensaje(id_nodo, vec, nombre);
+        } else if let Some(pendientes) = self.pendientes_nodos.get_mut(&id_nodo) {
+            pendientes.push(vec);
+        }
+    }
+
+    /// Con un nodo-handler caido falla cada envio, asi que de cada nodo se loguea un error por intervalo
+    fn enviar_mensaje(&mut self, id_nodo: IdNodo, vec: Vec<u8>, nombre: &str) {
+        if let Some(addr) = self.addr_nodos.get(&id_nodo) {
+            if let Err(err) = addr.mensajes.try_send(ReceiverFromCoordinador { vec }) {
+                if let Some(omitidos) = self.errores_envio.permitir(id_nodo, self.reloj.ahora()) {
+                    error!(
+                        "[COORDINADOR] Error al enviar {} al ID_NODO = {:?} | Detalle: {:?} | Errores omitidos: {}",
+                        nombre, id_nodo, err, omitidos
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Transporte<IdNodo, OperacionCafe> for RedNodos<'_> {
+    fn participantes(&self) -> Vec<IdNodo> {
+        self.addr_nodos.keys().copied().collect()
+    }
+
+    fn enviar(
+        &mut self,
+        destino: IdNodo,
+        id: IdTransaccion,
+        operacion: &OperacionCafe,
+        mensaje: MensajeTpc,
+    ) {
+        self.enviar_mensaje(
+            destino,
+            operacion.codificar(id, mensaje),
+            &format!("{:?}", mensaje),
+        );
+    }
+
+    /// Los COMMIT/ABORT se publican como eventos y se guardan para los nodos desconectados
+    fn difundir(&mut self, id: IdTransaccion, operacion: &OperacionCafe, mensaje: MensajeTpc) {
+        let bytes = operacion.codificar(id, mensaje);
+        let decision = match mensaje {
+            MensajeTpc::Commit { secuencia } => Some((secuencia, ResultadoTransaccion::COMMIT)),
+            MensajeTpc::Abort { secuencia } => Some((secuencia, ResultadoTransaccion::ABORT)),
+            _ => None,
+        };
+        if let Some((secuencia, resultado)) = decision {
+            self.registro.registrar(EntradaRegistro {
+                secuencia,
+                id_transaccion: id,
+                id_cuenta: operacion.id_cuenta,
+                id_cuenta_destino: operacion.id_cuenta_destino,
+                tipo: operacion.tipo,
+                cantidad: operacion.cantidad,
+                resultado,
+            });
+        }
+        let evento = match mensaje {
+            MensajeTpc::Commit { secuencia } => Some((
+                "COMMIT",
+                format!(
+                    "ID_TRANSACCION = {} | {:?} de {} en ID_CUENTA = {} | SECUENCIA = {}",
+                    id, operacion.tipo, operacion.cantidad, operacion.id_cuenta, secuencia
+                ),
+            )),
+            MensajeTpc::Abort { secuencia } => Some((
+                "ABORT",
+                format!(
+                    "ID_TRANSACCION = {} por {:?} | SECUENCIA = {}",
+                    id, operacion.motivo, secuencia
+                ),
+            )),
+            _ => None,
+        };
+        if let Some((tipo, detalle)) = evento {
+            publicar_evento("COORDINADOR", tipo, detalle);
+            for pendientes in self.pendientes_nodos.values_mut() {
+                pendientes.push(bytes.clone());
+            }
+        }
+        for id_nodo in self.participantes() {
+            self.enviar_mensaje(id_nodo, bytes.clone(), &format!("{:?}", mensaje));
+        }
+    }
+
+    /// Registra la latencia de la transaccion. Las sumas no se encolan, solo se avisa al nodo que la
+    /// inicio si se aplico o no (al volver a votar, si se esta sincronizando)
+    fn terminada(
+        &mut self,
+        id: IdTransaccion,
+        operacion: &OperacionCafe,
+        resultado: EstadoTransaccion,
+    ) {
+        self.latencias.registrar(
+            operacion.tipo,
+            self.reloj
+                .ahora()
+                .saturating_duration_since(operacion.inicio),
+        );
+        if operacion.tipo as u8 != CommitType::SUMA as u8 {
+            return;
+        }
+        if resultado == EstadoTransaccion::Confirmada {
+            let confirmado = ConfirmadoToNodo::new(
+                operacion.from_id_nodo,
+                operacion.id_cuenta,
+                id,
+                operacion.id_cafetera,
+            )
+            .con_id_pedido(operacion.id_pedido);
+            self.entregar_mensaje(operacion.from_id_nodo, confirmado.to_bytes(), "CONFIRMADO");
+        } else {
+            let fallido = FallidoToNodo::new(
+                operacion.from_id_nodo,
+                operacion.id_cuenta,
+                id,
+                operacion.id_cafetera,
+            )
+            .con_id_pedido(operacion.id_pedido);
+            self.entregar_mensaje(operacion.from_id_nodo, fallido.to_bytes(), "FALLIDO");
+        }
+    }
+}
+/// Nodo que se esta uniendo y todavia no recibio el snapshot de las cuentas
+struct NodoSincronizando {
+    /// canal hacia el nodo-handler del nodo que se esta uniendo
+    addr: CanalNodo,
+    /// id del nodo al que se le pidio el snapshot
+    id_donante: IdNodo,
+    /// corte con el que se pidio el snapshot, para descartar respuestas de pedidos anteriores
+    corte: u64,
 }
 /// Estructura que guarda la información general del servidor usada por el coordiandor.
 pub struct Coordinador {
-    /// Hash con clave id_nodo y valor el address del actor nodo-handler
-    addr_nodos: HashMap<u8, Addr<NodoHandler>>,
-    /// Hash con clave id_transaccion y como valor la estructura TransactionCoordinator
-    transacciones: HashMap<u32, TransactionCoordinator>,
-    /// Hash de clave id_cuenta y valor vector de id_transacciones
-    /// indica que transacciones estan pendientes por loquearse ante el uso de una misma cuenta
-    cola: HashMap<u32, Vec<u32>>,
+    /// Hash con clave id_nodo y valor el canal hacia el actor nodo-handler
+    addr_nodos: HashMap<IdNodo, CanalNodo>,
+    /// Commit de dos fases de las transacciones, con las colas por cuenta de las que estan pendientes
+    /// por bloquearse ante el uso de una misma cuenta
+    tpc: CoordinadorTpc<OperacionCafe, IdNodo>,
     /// Estado de la conección
     conectado: bool,
+    /// Hash con clave id_nodo de un nodo desconectado y valor los COMMIT/ABORT (y altas/bajas) que se perdio, en orden,
+    /// para enviarselos cuando se vuelva a conectar
+    pendientes_nodos: HashMap<IdNodo, Vec<Vec<u8>>>,
+    /// Hash con clave id_nodo de los nodos que se estan uniendo, no votan hasta recibir el snapshot
+    sincronizando: HashMap<IdNodo, NodoSincronizando>,
+    /// Histogramas de latencia de las transacciones terminadas, por tipo
+    latencias: LatenciasTransacciones,
+    /// Total de latencias registradas en el ultimo reporte, para no repetir reportes sin novedades
+    latencias_reportadas: u64,
+    /// Reloj con el que se vencen las transacciones, se reenvian los commits y se miden las latencias
+    reloj: Arc<dyn Reloj>,
+    /// Limita los logs de los errores al enviarle mensajes a cada nodo-handler
+    errores_envio: LimitadorLogs<IdNodo>,
+    /// id del nodo en el que corre el coordinador, 0 si no se conoce. Sus saldos son los de referencia
+    /// con los que se sincronizan los nodos que se unen
+    id_nodo: IdNodo,
+    /// Transacciones decididas (COMMIT/ABORT) en el orden en que se difundieron
+    registro: RegistroCommits,
+    /// Auditorias de consistencia que esperan los saldos de los nodos, por id de auditoria
+    auditorias: HashMap<u32, AuditoriaEnCurso>,
+    /// Id de la ultima auditoria iniciada
+    ultima_auditoria: u32,
 }
 
 impl Actor for Coordinador {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        cada_intervalo(
+            ctx,
+            self.reloj.clone(),
+            Duration::from_millis(config().servidor.intervalo_planificacion_millis),
+            |this, _ctx| {
+                this.revisar_vencidas();
+                this.revisar_auditorias();
+            },
+        );
+        cada_intervalo(
+            ctx,
+            self.reloj.clone(),
+            Duration::from_millis(config().servidor.intervalo_reenvio_commit_millis),
+            |this, _ctx| this.reenviar_commits(),
+        );
+        cada_intervalo(
+            ctx,
+            self.reloj.clone(),
+            Duration::from_millis(config().servidor.intervalo_reporte_latencias_millis),
+            |this, _ctx| this.reportar_latencias(),
+        );
+    }
 }
 
 impl Coordinador {
     ///Inicializar el socket TCP
-    pub async fn create_listener(id: u8) -> Result<TcpListener, ErrorServer> {
-        TcpListener::bind(id_to_ctrladdr(id))
-            .await
-            .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorConexion))
+    pub async fn create_listener(id: IdNodo) -> Result<TcpListener, ErrorServer> {
+        Ok(TcpListener::bind(id_to_ctrladdr(id)).await?)
     }
-    /// Crea el actor Coordinador y por cada conección entrante al socket tcp se crea un actor nodo-handler
-    pub async fn start_listener(listener: TcpListener) -> Result<(), ErrorServer> {
-        let coordinador_addr = Coordinador {
+    /// Coordinador sin nodos ni transacciones, que mide sus tiempos con el reloj indicado
+    pub fn new(reloj: Arc<dyn Reloj>) -> Self {
+        Coordinador {
             addr_nodos: HashMap::new(),
-            transacciones: HashMap::new(),
-            cola: HashMap::new(),
+            tpc: Self::nuevo_tpc(reloj.clone()),
             conectado: true,
+            pendientes_nodos: HashMap::new(),
+            sincronizando: HashMap::new(),
+            latencias: LatenciasTransacciones::new(),
+            latencias_reportadas: 0,
+            reloj,
+            errores_envio: LimitadorLogs::desde_config(),
+            id_nodo: IdNodo(0),
+            registro: RegistroCommits::default(),
+            auditorias: HashMap::new(),
+            ultima_auditoria: 0,
         }
-        .start();
+    }
 
-        while let Ok((mut stream, addr)) = listener.accept().await {
-            let coordinador_addr_clone = coordinador_addr.clone();
-            let id_nodo: u8 = stream
-                .read_u8()
-                .await
-                .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorGenerico))?;
+    /// Indica el nodo en el que corre el coordinador, el preferido como donante de los snapshots
+    pub fn con_id_nodo(mut self, id_nodo: IdNodo) -> Self {
+        self.id_nodo = id_nodo;
+        self
+    }
+
+    /// Reemplaza el registro de commits en memoria por el indicado
+    pub fn con_registro(mut self, registro: RegistroCommits) -> Self {
+        self.registro = registro;
+        self
+    }
+
+    /// Crea el actor Coordinador y por cada conección entrante al socket tcp se crea un actor nodo-handler.
+    /// Las conecciones que empiezan con ID_CONSULTA_ADMIN en lugar de un ID_NODO reciben la respuesta a
+    /// su consulta y se cierran. Se aceptan conexiones hasta que llega `apagado`
+    pub async fn start_listener(
+        listener: TcpListener,
+        id_nodo_coordinador: IdNodo,
+        reloj: Arc<dyn Reloj>,
+        mut apagado: oneshot::Receiver<()>,
+    ) -> Result<(), ErrorServer> {
+        let coordinador_addr = Coordinador::new(reloj)
+            .con_id_nodo(id_nodo_coordinador)
+            .con_registro(RegistroCommits::desde_config(id_nodo_coordinador))
+            .start();
+
+        loop {
+            let (stream, addr) = tokio::select! {
+                aceptada = listener.accept() => match aceptada {
+                    Ok(aceptada) => aceptada,
+                    Err(_) => break,
+                },
+                // al apagarse el proceso se deja de aceptar conexiones (el listener se cierra al
+                // retornar) y se cierran las de los nodos
+                _ = &mut apagado => {
+                    coordinador_addr.send(Apagar).await?;
+                    return Ok(());
+                }
+            };
+            // la presentacion se lee en otra tarea, un nodo que no la envia no demora la
+            // aceptacion de otras conexiones
+            let coordinador = coordinador_addr.clone();
+            actix::spawn(async move {
+                if let Err(err) =
+                    Coordinador::atender_conexion(stream, addr, coordinador, id_nodo_coordinador)
+                        .await
+                {
+                    warn!(
+                        "[COORDINADOR] Se descarta la conexion de {:?} | Detalle: {:?}",
+                        addr, err
+                    );
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Lee la presentacion de una conexion entrante, con un plazo de timeout_presentacion_millis para
+    /// cada byte, y crea su nodo-handler o responde la consulta de administracion
+    async fn atender_conexion(
+        mut stream: TcpStream,
+        addr: SocketAddr,
+        coordinador_addr: Addr<Coordinador>,
+        id_nodo_coordinador: IdNodo,
+    ) -> Result<(), ErrorServer> {
+        if let Err(err) = configurar_keepalive(&stream) {
+            warn!(
+                "[COORDINADOR] No se pudo activar el keepalive de la conexion con {:?} | Detalle: {:?}",
+                addr, err
+            );
+        }
+        let primer_byte = leer_byte_con_plazo(&mut stream).await?;
+        if primer_byte == ID_CONSULTA_ADMIN {
+            if let Err(err) = responder_consulta_admin(stream, &coordinador_addr).await {
+                warn!(
+                    "[COORDINADOR] Error al responder la consulta de administracion de {:?} | Detalle: {:?}",
+                    addr, err
+                );
+            }
+            return Ok(());
+        }
+        // el nodo indica si arranco de cero, si se reconecta conservando sus cuentas o si las
+        // tiene que reconciliar
+        let id_nodo = IdNodo(primer_byte);
+        let modo = ModoConexion::from_byte(leer_byte_con_plazo(&mut stream).await?);
 
-            println!(
-                "[COORDINADOR] Conexion establecidada con ID_NODO = {:?}",
+        if !config().cluster.es_miembro(id_nodo.0) {
+            warn!(
+                "[COORDINADOR] Rechazo conexion de ID_NODO = {:?}, no es un nodo del cluster",
                 id_nodo
             );
+            let rechazo = Rechazo::new(IdNodo(0), id_nodo, Mensaje::UNKNOWN.to_bytes()).to_bytes();
+            let _res = stream.write_all(&enmarcar(&rechazo)).await;
+            let _res = stream.shutdown().await;
+            return Ok(());
+        }
 
-            coordinador_addr_clone
-                .try_send(SetState { estado: true })
-                .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorGenerico))?;
+        info!(
+            "[COORDINADOR] Conexion establecidada con ID_NODO = {:?}",
+            id_nodo
+        );
 
-            let nodo_addr = NodoHandler::create(|ctx| {
-                let (read, write_half) = split(stream);
+        coordinador_addr.try_send(SetState { estado: true })?;
 
-                NodoHandler::add_stream(LinesStream::new(BufReader::new(read).lines()), ctx);
-                let write = Arc::new(Mutex::new(write_half));
-                NodoHandler {
-                    addr,
-                    write,
-                    addr_coordinador: coordinador_addr_clone,
-                    id_nodo,
-                    conectado: true,
-                }
+        let coordinador_addr_clone = coordinador_addr.clone();
+        let nodo_addr = NodoHandler::create(|ctx| {
+            let (read, write_half) = split(stream);
+
+            NodoHandler::add_stream(leer_mensajes(read), ctx);
+            let write = Arc::new(Mutex::new(write_half));
+            NodoHandler {
+                addr,
+                write,
+                addr_coordinador: coordinador_addr_clone,
+                id_nodo,
+                id_coordinador: id_nodo_coordinador,
+                conectado: true,
+                errores_escritura: LimitadorLogs::desde_config(),
+            }
+        });
+
+        coordinador_addr
+            .send(AddNodo {
+                nodo_addr: nodo_addr.into(),
+                id_nodo,
+                modo,
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Commit de dos fases sin transacciones, con los tiempos de la configuracion
+    fn nuevo_tpc(reloj: Arc<dyn Reloj>) -> CoordinadorTpc<OperacionCafe, IdNodo> {
+        CoordinadorTpc::new(
+            reloj,
+            Duration::from_secs(config().servidor.tiempo_maximo_servicio_transaccion),
+            Duration::from_millis(config().servidor.intervalo_reenvio_commit_millis),
+            config().servidor.max_reenvios_commit,
+            config().servidor.max_adelantamientos,
+        )
+    }
+
+    /// Ejecuta f sobre el commit de dos fases con los nodos conectados como participantes
+    fn con_tpc<R>(
+        &mut self,
+        f: impl FnOnce(&mut CoordinadorTpc<OperacionCafe, IdNodo>, &mut RedNodos) -> R,
+    ) -> R {
+        let mut red = RedNodos {
+            addr_nodos: &self.addr_nodos,
+            pendientes_nodos: &mut self.pendientes_nodos,
+            latencias: &mut self.latencias,
+            reloj: self.reloj.as_ref(),
+            errores_envio: &mut self.errores_envio,
+            registro: &mut self.registro,
+        };
+        f(&mut self.tpc, &mut red)
+    }
+
+    /// Aborta las transacciones que superaron el tiempo maximo al frente de las colas de sus cuentas,
+    /// para que las siguientes no esperen de forma indefinida
+    fn revisar_vencidas(&mut self) {
+        if !self.conectado {
+            return;
+        }
+        let vencidas = self.con_tpc(|tpc, red| tpc.revisar_vencidas(red));
+        for id_transaccion in vencidas.iter() {
+            warn!(
+                "[COORDINADOR] La transaccion con ID_TRANSACCION = {:?} supero el tiempo maximo de servicio, se aborta",
+                id_transaccion
+            );
+        }
+        if !vencidas.is_empty() {
+            let planificador = self.tpc.planificador();
+            let metricas = planificador.metricas();
+            info!(
+                "[COORDINADOR] Planificacion: despachadas = {}, vencidas = {}, espera promedio = {:?}, espera maxima = {:?}, pendientes = {}",
+                metricas.despachadas,
+                metricas.vencidas,
+                metricas.espera_promedio(),
+                metricas.espera_maxima,
+                planificador.pendientes()
+            );
+        }
+    }
+
+    /// Loguea los percentiles de latencia de cada tipo de transaccion si terminaron transacciones
+    /// desde el ultimo reporte
+    fn reportar_latencias(&mut self) {
+        let total = self.latencias.total();
+        if total == self.latencias_reportadas {
+            return;
+        }
+        self.latencias_reportadas = total;
+        info!(
+            "[COORDINADOR] Latencias SUMA: {}",
+            self.latencias.sumas.resumen()
+        );
+        info!(
+            "[COORDINADOR] Latencias RESTA: {}",
+            self.latencias.restas.resumen()
+        );
+        info!(
+            "[COORDINADOR] Latencias TRANSFERENCIA: {}",
+            self.latencias.transferencias.resumen()
+        );
+    }
+
+    /// Guarda un COMMIT/ABORT (o un alta/baja de cuenta) para entregarselo a los nodos desconectados
+    /// cuando vuelvan
+    fn guardar_para_desconectados(&mut self, bytes: &[u8]) {
+        for pendientes in self.pendientes_nodos.values_mut() {
+            pendientes.push(bytes.to_vec());
+        }
+    }
+
+    /// Le pide al donante el snapshot de las cuentas para el nodo que se esta uniendo. Desde este
+    /// momento se guardan para el nodo los COMMIT/ABORT que se difundan, que no van a estar en el snapshot
+    fn pedir_snapshot(&mut self, id_nodo: IdNodo, addr: CanalNodo, id_donante: IdNodo) {
+        info!(
+            "[COORDINADOR] Pido al ID_NODO = {:?} el snapshot para el ID_NODO = {:?} con corte {}",
+            id_donante,
+            id_nodo,
+            self.tpc.secuencia()
+        );
+        // el snapshot ya refleja los COMMIT/ABORT que se perdio, solo se conservan los avisos de sus sumas
+        let avisos = self
+            .pendientes_nodos
+            .remove(&id_nodo)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|vec| {
+                vec.first().is_some_and(|tipo| {
+                    matches!(
+                        Mensaje::from_bytes(*tipo),
+                        Mensaje::CONFIRMADO | Mensaje::FALLIDO
+                    )
+                })
+            })
+            .collect();
+        self.pendientes_nodos.insert(id_nodo, avisos);
+        if let Some(donante) = self.addr_nodos.get(&id_donante) {
+            donante.mensajes.do_send(ReceiverFromCoordinador {
+                vec: PedidoSnapshot::new(id_nodo, self.tpc.secuencia()).to_bytes(),
             });
+        }
+        self.sincronizando.insert(
+            id_nodo,
+            NodoSincronizando {
+                addr,
+                id_donante,
+                corte: self.tpc.secuencia(),
+            },
+        );
+    }
 
-            coordinador_addr
-                .send(AddNodo { nodo_addr, id_nodo })
-                .await
-                .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorGenerico))?;
+    /// Nodo del que se copian las cuentas para el que se une: el del coordinador si esta votando, o
+    /// si no el de menor id
+    fn elegir_donante(&self, id_nodo: IdNodo) -> Option<IdNodo> {
+        if self.id_nodo != id_nodo && self.addr_nodos.contains_key(&self.id_nodo) {
+            return Some(self.id_nodo);
         }
-        Ok(())
+        self.addr_nodos
+            .keys()
+            .filter(|id| **id != id_nodo)
+            .min()
+            .copied()
+    }
+
+    /// Agrega el nodo a los que votan, enviandole antes en orden los COMMIT/ABORT que se perdio
+    fn agregar_nodo(&mut self, id_nodo: IdNodo, addr: CanalNodo) {
+        if let Some(pendientes) = self.pendientes_nodos.remove(&id_nodo) {
+            info!(
+                "[COORDINADOR] Envio {} COMMIT/ABORT pendientes al ID_NODO = {:?}",
+                pendientes.len(),
+                id_nodo
+            );
+            for vec in pendientes {
+                addr.mensajes.do_send(ReceiverFromCoordinador { vec });
+            }
+        }
+        self.addr_nodos.insert(id_nodo, addr);
+    }
+
+    /// Mensajes de los commits en curso que el nodo todavia no confirmo, en el orden en que se difundieron
+    fn commits_sin_confirmar(&self, id_nodo: IdNodo) -> Vec<Vec<u8>> {
+        self.tpc
+            .commits_sin_confirmar(id_nodo)
+            .into_iter()
+            .filter_map(|(id_transaccion, secuencia)| {
+                self.tpc
+                    .operacion(id_transaccion)
+                    .map(|x| x.codificar(id_transaccion, MensajeTpc::Commit { secuencia }))
+            })
+            .collect()
+    }
+
+    /// Reenvia el commit a los nodos que todavia no lo confirmaron. Si un nodo no lo confirma luego de
+    /// max_reenvios_commit reenvios se lo da por caido: se cierra su conexion y las transacciones se
+    /// completan con los nodos restantes
+    fn reenviar_commits(&mut self) {
+        if !self.conectado {
+            return;
+        }
+        let caidos = self.con_tpc(|tpc, red| tpc.reenviar_commits(red));
+        if caidos.is_empty() {
+            return;
+        }
+        for id_nodo in caidos {
+            // los commits sin confirmar se le entregan cuando se vuelva a conectar
+            let sin_confirmar = self.commits_sin_confirmar(id_nodo);
+            if let Some(addr) = self.addr_nodos.remove(&id_nodo) {
+                warn!(
+                    "[COORDINADOR] El ID_NODO = {:?} no confirmo el COMMIT luego de {} reenvios, se lo da por caido",
+                    id_nodo, config().servidor.max_reenvios_commit
+                );
+                addr.shutdown.do_send(Shutdown);
+            }
+            self.pendientes_nodos
+                .entry(id_nodo)
+                .or_default()
+                .extend(sin_confirmar);
+        }
+        self.con_tpc(|tpc, red| tpc.revisar(red));
     }
 }
 
@@ -121,14 +756,108 @@ impl Coordinador {
 #[rtype(result = "()")]
 /// Se agrega en el vector de addr de nodo-handlers uno nuevo
 pub struct AddNodo {
-    nodo_addr: Addr<NodoHandler>,
-    id_nodo: u8,
+    pub nodo_addr: CanalNodo,
+    pub id_nodo: IdNodo,
+    pub modo: ModoConexion,
 }
 
+// los mensajes que reenvian los NodoHandler
+atender_medidos!(Coordinador: Starter, Yes, PingCord, Finish, Transferencia, OkeyToCoordinator,
+    OkeyAbortToCoordinator, Abort, Valido, AltaCord, BajaCord, Retiro, PedidoSnapshot, Snapshot,
+    Auditoria, Disconnect);
+
 impl Handler<AddNodo> for Coordinador {
     type Result = ();
     fn handle(&mut self, msg: AddNodo, _ctx: &mut Self::Context) -> Self::Result {
-        self.addr_nodos.insert(msg.id_nodo, msg.nodo_addr);
+        publicar_evento(
+            "COORDINADOR",
+            "CONEXION",
+            format!("ID_NODO = {} se conecto", msg.id_nodo),
+        );
+        // si el coordinador no sabe que se perdio el nodo (es nuevo, arranco de cero o el coordinador
+        // cambio) o el nodo pide reconciliar sus saldos, se le transfieren las cuentas de otro nodo
+        // antes de que empiece a votar
+        let conocido =
+            msg.modo == ModoConexion::ConEstado && self.pendientes_nodos.contains_key(&msg.id_nodo);
+        match self.elegir_donante(msg.id_nodo) {
+            Some(id_donante) if !conocido => {
+                if msg.modo == ModoConexion::Reconciliar {
+                    info!(
+                        "[COORDINADOR] El ID_NODO = {:?} funciono desconectado, se reconcilian sus saldos con los del ID_NODO = {:?}",
+                        msg.id_nodo, id_donante
+                    );
+                }
+                self.pedir_snapshot(msg.id_nodo, msg.nodo_addr, id_donante)
+            }
+            _ => {
+                if msg.modo == ModoConexion::SinEstado {
+                    // lo que se perdio no sirve sobre cuentas nuevas y no hay de quien copiarlas
+                    self.pendientes_nodos.remove(&msg.id_nodo);
+                }
+                // antes de que vuelva a votar, el nodo recibe en orden lo que se perdio mientras estaba desconectado
+                self.agregar_nodo(msg.id_nodo, msg.nodo_addr);
+            }
+        }
+    }
+}
+
+/// Recibo el snapshot de un donante, se lo reenvio al nodo que se esta uniendo seguido de los
+/// COMMIT/ABORT posteriores al corte y a partir de ahi el nodo vota como el resto
+impl Handler<Snapshot> for Coordinador {
+    type Result = ();
+
+    fn handle(&mut self, msg: Snapshot, _ctx: &mut Context<Self>) -> Self::Result {
+        let nodo = match self.sincronizando.remove(&msg.id_nodo) {
+            Some(nodo) if nodo.corte == msg.corte => nodo,
+            Some(nodo) => {
+                // respuesta de un pedido anterior, se espera la del ultimo donante
+                self.sincronizando.insert(msg.id_nodo, nodo);
+                return;
+            }
+            None => return,
+        };
+        info!(
+            "[COORDINADOR] Envio snapshot de {} cuentas con corte {} al ID_NODO = {:?}",
+            msg.cuentas.len(),
+            msg.corte,
+            msg.id_nodo
+        );
+        nodo.addr.mensajes.do_send(ReceiverFromCoordinador {
+            vec: msg.to_bytes(),
+        });
+        self.agregar_nodo(msg.id_nodo, nodo.addr);
+    }
+}
+
+/// Recibo el pedido de snapshot de un nodo que detecto un hueco en las secuencias de COMMIT/ABORT.
+/// El nodo deja de votar hasta recibir las cuentas de otro nodo, igual que al unirse
+impl Handler<PedidoSnapshot> for Coordinador {
+    type Result = ();
+
+    fn handle(&mut self, msg: PedidoSnapshot, _ctx: &mut Context<Self>) -> Self::Result {
+        if !self.conectado {
+            return;
+        }
+
+        let donante = self.elegir_donante(msg.id_nodo);
+        match (donante, self.addr_nodos.remove(&msg.id_nodo)) {
+            (Some(id_donante), Some(addr)) => {
+                warn!(
+                    "[COORDINADOR] El ID_NODO = {:?} detecto un hueco luego de la secuencia {}, se lo resincroniza",
+                    msg.id_nodo, msg.corte
+                );
+                self.pedir_snapshot(msg.id_nodo, addr, id_donante);
+            }
+            (None, Some(addr)) => {
+                warn!(
+                    "[COORDINADOR] El ID_NODO = {:?} detecto un hueco pero no hay otro nodo del que copiar las cuentas",
+                    msg.id_nodo
+                );
+                self.addr_nodos.insert(msg.id_nodo, addr);
+            }
+            // ya se esta sincronizando
+            _ => {}
+        }
     }
 }
 /// Recibe Starter solo para las operaciones de RESTA!
@@ -140,55 +869,66 @@ impl Handler<Starter> for Coordinador {
             return;
         }
 
-        println!(
-            "[COORDINADOR] Recibí STARTER de ID_NODO = {:?}",
-            msg.id_nodo
+        info!(
+            "[COORDINADOR] Recibí STARTER de ID_NODO = {:?} | ID_PEDIDO = {}",
+            msg.id_nodo, msg.id_pedido
         );
 
-        let id_transaccion = msg.id_transaccion;
-        let id_cuenta = msg.id_cuenta;
-
-        self.transacciones.insert(
-            id_transaccion,
-            TransactionCoordinator {
-                status: TransactionState::Uninitialized,
-                yes_nodos: vec![],
-                ok_nodos: vec![],
-                from_id_nodo: msg.id_nodo,
-                id_cuenta: msg.id_cuenta,
-                tipo: CommitType::RESTA,
-                id_cafetera: msg.id_cafetera,
-            },
+        let mut operacion = OperacionCafe::new(
+            msg.id_nodo,
+            msg.id_cuenta,
+            CommitType::RESTA,
+            msg.id_cafetera,
+            msg.id_pedido,
+            self.reloj.ahora(),
         );
+        operacion.prioritaria = msg.prioritaria;
+        let repetida =
+            self.con_tpc(|tpc, red| tpc.encolar(msg.id_transaccion, msg.id_nodo, operacion, red));
+        if let Some(estado) = repetida {
+            // un STARTER reintentado no vuelve a encolar la cuenta, el nodo recibe el estado actual
+            warn!(
+                "[COORDINADOR] STARTER repetido de ID_TRANSACCION = {:?}, ya estaba {:?} | ID_PEDIDO = {}",
+                msg.id_transaccion, estado, msg.id_pedido
+            );
+        }
+    }
+}
+/// Recibe una Transferencia, se encola en ambas cuentas y se despacha cuando esta al frente de las dos
+impl Handler<Transferencia> for Coordinador {
+    type Result = ();
 
-        if let Some(cuenta) = self.cola.get_mut(&id_cuenta) {
-            cuenta.push(id_transaccion);
-            if cuenta.len() > 1 {
-                return;
-            }
-        } else {
-            self.cola.insert(id_cuenta, vec![id_transaccion]);
+    fn handle(&mut self, msg: Transferencia, _ctx: &mut Context<Self>) -> Self::Result {
+        if !self.conectado {
+            return;
         }
 
-        self.transacciones
-            .get_mut(&id_transaccion)
-            .expect("Se había insertado la transacción anteriormente")
-            .status = TransactionState::Wait;
+        info!(
+            "[COORDINADOR] Recibí TRANSFERENCIA de ID_NODO = {:?} | ID_CUENTA = {} -> ID_CUENTA = {} | ID_PEDIDO = {}",
+            msg.id_nodo, msg.id_cuenta, msg.id_cuenta_destino, msg.id_pedido
+        );
 
-        for (_, addr) in self.addr_nodos.iter() {
-            let prepare = Prepare::from_start(msg.clone());
-            let res = addr
-                .try_send(ReceiverFromCoordinador {
-                    string: prepare.to_string(),
-                })
-                .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorGenerico));
-            if let Err(res) = res {
-                println!("[HANDLER-STARTER] error: {:?}", res);
-            }
+        let mut operacion = OperacionCafe::new(
+            msg.id_nodo,
+            msg.id_cuenta,
+            CommitType::TRANSFERENCIA,
+            msg.id_cafetera,
+            msg.id_pedido,
+            self.reloj.ahora(),
+        );
+        operacion.id_cuenta_destino = Some(msg.id_cuenta_destino);
+        operacion.prioritaria = msg.prioritaria;
+        let repetida =
+            self.con_tpc(|tpc, red| tpc.encolar(msg.id_transaccion, msg.id_nodo, operacion, red));
+        if let Some(estado) = repetida {
+            warn!(
+                "[COORDINADOR] TRANSFERENCIA repetida de ID_TRANSACCION = {:?}, ya estaba {:?} | ID_PEDIDO = {}",
+                msg.id_transaccion, estado, msg.id_pedido
+            );
         }
     }
 }
-/// Recibe un Yes, se agrega en la correspondiente transaccion
+/// Recibe un Yes, cuando votaron todos los nodos se le pide al que inicio la transaccion que la ejecute
 impl Handler<Yes> for Coordinador {
     type Result = ();
 
@@ -196,33 +936,8 @@ impl Handler<Yes> for Coordinador {
         if !self.conectado {
             return;
         }
-        println!("[COORDINADOR] Recibí YES de ID_NODO = {:?}", msg.id_nodo);
-        if let Some(transaccion) = self.transacciones.get_mut(&msg.id_transaccion) {
-            transaccion.yes_nodos.push(msg.id_nodo);
-
-            if transaccion.yes_nodos.len() == self.addr_nodos.len() {
-                transaccion.status = TransactionState::Execute;
-                if let Err(err) = self
-                    .addr_nodos
-                    .get(&transaccion.from_id_nodo)
-                    .expect("Siempre se obtendra la address")
-                    .try_send(ReceiverFromCoordinador {
-                        string: Execute::new(
-                            transaccion.from_id_nodo,
-                            msg.id_cuenta,
-                            msg.id_transaccion,
-                            msg.id_cafetera,
-                        )
-                        .to_string(),
-                    })
-                {
-                    println!(
-                        "[COORDINADOR] Error al enviar EXECUTE al ID_NODO = {:?} | Detalle: {:?}",
-                        transaccion.from_id_nodo, err
-                    );
-                }
-            }
-        }
+        debug!("[COORDINADOR] Recibí YES de ID_NODO = {:?}", msg.id_nodo);
+        self.con_tpc(|tpc, red| tpc.votar(msg.id_transaccion, msg.id_nodo, red));
     }
 }
 /// Recibi un ping, no hago nada ya que el nodo por ser tcp sabe que esta todo ok
@@ -234,10 +949,10 @@ impl Handler<PingCord> for Coordinador {
             return;
         }
 
-        println!("[COORDINADOR] Recibí PING de ID_NODO = {:?}", msg.id_nodo);
+        debug!("[COORDINADOR] Recibí PING de ID_NODO = {:?}", msg.id_nodo);
     }
 }
-/// Recibo un finish, se actualiza la transaccion y se envia commit a los nodos-handlers
+/// Recibo un finish. Las sumas se commitean directamente, el resto se valida en todos los nodos
 impl Handler<Finish> for Coordinador {
     type Result = ();
 
@@ -246,118 +961,75 @@ impl Handler<Finish> for Coordinador {
             return;
         }
 
-        println!("[COORDINADOR] Recibí FINISH de ID_NODO = {:?}", msg.id_nodo);
+        info!(
+            "[COORDINADOR] Recibí FINISH de ID_NODO = {:?} | ID_PEDIDO = {}",
+            msg.id_nodo, msg.id_pedido
+        );
 
         if msg.tipo as u8 == CommitType::SUMA as u8 {
-            self.transacciones.insert(
-                msg.id_transaccion,
-                TransactionCoordinator {
-                    status: TransactionState::Wait,
-                    yes_nodos: vec![],
-                    ok_nodos: vec![],
-                    from_id_nodo: msg.id_nodo,
-                    id_cuenta: msg.id_cuenta,
-                    tipo: CommitType::SUMA,
-                    id_cafetera: msg.id_cafetera,
-                },
-            );
-        }
-        if let Some(x) = self.transacciones.get_mut(&msg.id_transaccion) {
-            x.status = TransactionState::Commit
-        };
-
-        for (_, addr) in self.addr_nodos.iter() {
-            let commit = Commit::new(
+            let mut operacion = OperacionCafe::new(
                 msg.id_nodo,
                 msg.id_cuenta,
-                msg.id_transaccion,
-                msg.tipo,
-                msg.cantidad,
+                CommitType::SUMA,
                 msg.id_cafetera,
+                msg.id_pedido,
+                self.reloj.ahora(),
+            );
+            operacion.cantidad = msg.cantidad;
+            self.con_tpc(|tpc, red| {
+                tpc.commit_directo(msg.id_transaccion, msg.id_nodo, operacion, red)
+            });
+            return;
+        }
+
+        // antes del commit todos los nodos validan que pueden aplicar el cambio de saldo,
+        // si alguno no puede responde Abort y la transaccion se aborta en todos
+        if let Some(operacion) = self.tpc.operacion_mut(msg.id_transaccion) {
+            operacion.cantidad = msg.cantidad;
+        }
+        if !self.con_tpc(|tpc, red| tpc.validar(msg.id_transaccion, red)) {
+            // por ejemplo la resta fue abortada mientras se preparaba el cafe por vencida
+            warn!(
+                "[COORDINADOR] Se ignora FINISH de ID_TRANSACCION = {:?} porque no se estaba ejecutando",
+                msg.id_transaccion
             );
-            if let Err(err) = addr.try_send(ReceiverFromCoordinador {
-                string: commit.set_to_string(),
-            }) {
-                println!(
-                    "[COORDINADOR] Error al enviar COMMIT al ID_NODO = {:?} | Detalle: {:?}",
-                    msg.id_nodo, err
-                );
-            }
         }
     }
 }
-/// Recibo un okey, actualizo el vector de Ok para la transaccion correspondiente,
-/// valida si ya se tienen todos los ok
-impl Handler<OkeyToCoordinator> for Coordinador {
+/// Recibo un Valido, cuando todos los nodos validaron el cambio de saldo se envia el commit
+impl Handler<Valido> for Coordinador {
     type Result = ();
 
-    fn handle(&mut self, msg: OkeyToCoordinator, _ctx: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: Valido, _ctx: &mut Context<Self>) -> Self::Result {
         if !self.conectado {
             return;
         }
 
-        println!("[COORDINADOR] Recibí OK de ID_NODO = {:?}", msg.id_nodo);
-        if let Some(x) = self.transacciones.get_mut(&msg.id_transaccion) {
-            x.ok_nodos.push(msg.id_nodo);
+        debug!(
+            "[COORDINADOR] Recibí VALIDO de ID_NODO = {:?} | ID_PEDIDO = {}",
+            msg.id_nodo, msg.id_pedido
+        );
+        self.con_tpc(|tpc, red| tpc.valido(msg.id_transaccion, msg.id_nodo, red));
+    }
+}
+/// Recibo un okey del commit, cuando confirmaron todos los nodos la transaccion termina
+impl Handler<OkeyToCoordinator> for Coordinador {
+    type Result = ();
+
+    fn handle(&mut self, msg: OkeyToCoordinator, _ctx: &mut Context<Self>) -> Self::Result {
+        if !self.conectado {
+            return;
         }
 
-        if let Some(x) = self.transacciones.get_mut(&msg.id_transaccion) {
-            if x.ok_nodos.len() == self.addr_nodos.len() {
-                x.status = TransactionState::Done;
-                x.ok_nodos = vec![];
-                if x.tipo as u8 == CommitType::SUMA as u8 {
-                    return;
-                }
-                if let Some(pendientes_cuenta) = self.cola.get_mut(&x.id_cuenta) {
-                    pendientes_cuenta.remove(0);
-                    if !pendientes_cuenta.is_empty() {
-                        let id_transaccion_from_queue = pendientes_cuenta[0];
-                        if let Some(transaccion) =
-                            self.transacciones.get_mut(&id_transaccion_from_queue)
-                        {
-                            transaccion.status = TransactionState::Wait;
-                            for (_, addr) in self.addr_nodos.iter() {
-                                let prepare = Prepare::new(
-                                    transaccion.from_id_nodo,
-                                    transaccion.id_cuenta,
-                                    id_transaccion_from_queue,
-                                    msg.id_cafetera,
-                                );
-                                if let Err(err) = addr
-                                    .try_send(ReceiverFromCoordinador {
-                                        string: prepare.to_string(),
-                                    })
-                                    .map_err(|x| {
-                                        ErrorServer::new(&x.to_string(), TipoError::ErrorGenerico)
-                                    })
-                                {
-                                    println!(
-                                        "[COORDINADOR] Error al enviar PREPARE al ID_NODO = {:?} | Detalle: {:?}",
-                                        transaccion.from_id_nodo, err
-                                    );
-                                }
-                            }
-                        } else {
-                            println!(
-                                "[COORDINADOR] No existe la transaccion con ID_TRANSACCION = {:?}",
-                                id_transaccion_from_queue
-                            );
-                        }
-                    }
-                } else {
-                    println!(
-                        "[COORDINADOR] No existe la cola de pendientes para ID_CUENTA = {:?}",
-                        x.id_cuenta
-                    );
-                }
-            }
-        } else {
-            println!("[COORDINADOR] Recibi OK de ID_NODO = {:?} pero no existe la transaccion con ID_TRANSACCION = {:?}", msg.id_nodo, msg.id_transaccion);
+        debug!("[COORDINADOR] Recibí OK de ID_NODO = {:?}", msg.id_nodo);
+        if self.tpc.estado(msg.id_transaccion).is_none() {
+            warn!("[COORDINADOR] Recibi OK de ID_NODO = {:?} pero no existe la transaccion con ID_TRANSACCION = {:?}", msg.id_nodo, msg.id_transaccion);
+            return;
         }
+        self.con_tpc(|tpc, red| tpc.confirmar(msg.id_transaccion, msg.id_nodo, red));
     }
 }
-/// Recibo un okey, actualizo el vector de OkAbort para la transaccion correspondiente,
-/// valida si ya se tienen todos los OkAbort
+/// Recibo un okey del abort, cuando confirmaron todos los nodos la transaccion termina
 impl Handler<OkeyAbortToCoordinator> for Coordinador {
     type Result = ();
 
@@ -366,71 +1038,18 @@ impl Handler<OkeyAbortToCoordinator> for Coordinador {
             return;
         }
 
-        println!(
+        debug!(
             "[COORDINADOR] Recibí OK_ABORT de ID_NODO = {:?}",
             msg.id_nodo
         );
-        if let Some(x) = self.transacciones.get_mut(&msg.id_transaccion) {
-            x.ok_nodos.push(msg.id_nodo);
-        }
-
-        let mut transaccion = match self.transacciones.get_mut(&msg.id_transaccion) {
-            Some(x) => x,
-            None => {
-                println!("[COORDINADOR] Recibi OK_ABORT de ID_NODO = {:?} pero no existe la transaccion con ID_TRANSACCION = {:?}", msg.id_nodo, msg.id_transaccion);
-                return;
-            }
-        };
-
-        if transaccion.ok_nodos.len() == self.addr_nodos.len() {
-            transaccion.status = TransactionState::Abort;
-            transaccion.ok_nodos = vec![];
-            let pendientes_cuenta = match self.cola.get_mut(&transaccion.id_cuenta) {
-                Some(x) => x,
-                None => {
-                    println!(
-                        "[COORDINADOR] No existe la cola de pendientes para ID_CUENTA = {:?}",
-                        transaccion.id_cuenta
-                    );
-                    return;
-                }
-            };
-            pendientes_cuenta.remove(0);
-            if !pendientes_cuenta.is_empty() {
-                let id_transaccion = pendientes_cuenta[0];
-                let transaccion = match self.transacciones.get_mut(&id_transaccion) {
-                    Some(x) => x,
-                    None => {
-                        println!(
-                            "[COORDINADOR] No existe la transaccion con ID_TRANSACCION = {:?}",
-                            id_transaccion
-                        );
-                        return;
-                    }
-                };
-
-                transaccion.status = TransactionState::Wait;
-                for (_, addr) in self.addr_nodos.iter() {
-                    let prepare = Prepare::new(
-                        transaccion.from_id_nodo,
-                        transaccion.id_cuenta,
-                        id_transaccion,
-                        msg.id_cafetera,
-                    );
-                    if let Err(err) = addr.try_send(ReceiverFromCoordinador {
-                        string: prepare.to_string(),
-                    }) {
-                        println!(
-                                "[COORDINADOR] Error al enviar PREPARE al ID_NODO = {:?} | Detalle: {:?}",
-                                transaccion.from_id_nodo, err
-                            );
-                    }
-                }
-            }
+        if self.tpc.estado(msg.id_transaccion).is_none() {
+            warn!("[COORDINADOR] Recibi OK_ABORT de ID_NODO = {:?} pero no existe la transaccion con ID_TRANSACCION = {:?}", msg.id_nodo, msg.id_transaccion);
+            return;
         }
+        self.con_tpc(|tpc, red| tpc.confirmar(msg.id_transaccion, msg.id_nodo, red));
     }
 }
-/// Handler de Abort, se actualiza la transaccion y se notifica a todos los nodos-handlers
+/// Handler de Abort, si la transaccion no estaba decidida se difunde el abort a todos los nodos-handlers
 impl Handler<Abort> for Coordinador {
     type Result = ();
 
@@ -439,18 +1058,78 @@ impl Handler<Abort> for Coordinador {
             return;
         }
 
-        println!("[COORDINADOR] Recibí ABORT de ID_NODO = {:?}", msg.id_nodo);
-        if let Some(x) = self.transacciones.get_mut(&msg.id_transaccion) {
-            x.status = TransactionState::Abort;
+        info!(
+            "[COORDINADOR] Recibí ABORT de ID_NODO = {:?} por {:?} | ID_PEDIDO = {}",
+            msg.id_nodo, msg.motivo, msg.id_pedido
+        );
+        // varios nodos pueden rechazar la misma transaccion, se difunde el motivo del primero
+        if self
+            .tpc
+            .estado(msg.id_transaccion)
+            .is_some_and(|x| !x.decidida())
+        {
+            if let Some(operacion) = self.tpc.operacion_mut(msg.id_transaccion) {
+                operacion.motivo = msg.motivo;
+            }
         }
+        self.con_tpc(|tpc, red| tpc.abortar(msg.id_transaccion, red));
+    }
+}
+/// Recibo un alta de cuenta y se reenvia a todos los nodos-handlers para que la creen
+impl Handler<AltaCord> for Coordinador {
+    type Result = ();
 
-        self.addr_nodos.iter().for_each(|(_, addr)| {
-            if let Err(err) = addr.try_send(ReceiverFromCoordinador {
-                string: msg.to_string(),
+    fn handle(&mut self, msg: AltaCord, _ctx: &mut Context<Self>) -> Self::Result {
+        if !self.conectado {
+            return;
+        }
+
+        info!(
+            "[COORDINADOR] Recibí ALTA de ID_CUENTA = {} desde ID_NODO = {:?}",
+            msg.id_cuenta, msg.id_nodo
+        );
+        self.guardar_para_desconectados(&msg.to_bytes());
+        self.addr_nodos.iter().for_each(|(id_nodo, addr)| {
+            if let Err(err) = addr.mensajes.try_send(ReceiverFromCoordinador {
+                vec: msg.to_bytes(),
             }) {
-                println!(
-                    "[COORDINADOR] Error al enviar ABORT al ID_NODO = {:?} | Detalle: {:?}",
-                    msg.id_nodo, err
+                error!(
+                    "[COORDINADOR] Error al enviar ALTA al ID_NODO = {:?} | Detalle: {:?}",
+                    id_nodo, err
+                );
+            }
+        });
+    }
+}
+/// Recibo una baja de cuenta, solo se reenvia a los nodos-handlers si la cuenta no tiene
+/// transacciones encoladas o en curso
+impl Handler<BajaCord> for Coordinador {
+    type Result = ();
+
+    fn handle(&mut self, msg: BajaCord, _ctx: &mut Context<Self>) -> Self::Result {
+        if !self.conectado {
+            return;
+        }
+
+        if self.tpc.planificador().cuenta_en_uso(msg.id_cuenta) {
+            info!(
+                "[COORDINADOR] Se rechaza BAJA de ID_CUENTA = {} porque tiene transacciones pendientes",
+                msg.id_cuenta
+            );
+            return;
+        }
+        info!(
+            "[COORDINADOR] Recibí BAJA de ID_CUENTA = {} desde ID_NODO = {:?}",
+            msg.id_cuenta, msg.id_nodo
+        );
+        self.guardar_para_desconectados(&msg.to_bytes());
+        self.addr_nodos.iter().for_each(|(id_nodo, addr)| {
+            if let Err(err) = addr.mensajes.try_send(ReceiverFromCoordinador {
+                vec: msg.to_bytes(),
+            }) {
+                error!(
+                    "[COORDINADOR] Error al enviar BAJA al ID_NODO = {:?} | Detalle: {:?}",
+                    id_nodo, err
                 );
             }
         });
@@ -462,87 +1141,162 @@ impl Handler<Abort> for Coordinador {
 /// Handler de disconnect, hay que simular desconección de red
 /// las transacciones pendientes deben abortarse
 pub struct DisconnectNodo {
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
 }
 
 impl Handler<DisconnectNodo> for Coordinador {
     type Result = ();
 
-    fn handle(&mut self, msg: DisconnectNodo, ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: DisconnectNodo, _ctx: &mut Self::Context) -> Self::Result {
         if !self.conectado {
             return;
         }
 
-        self.addr_nodos.remove(&msg.id_nodo);
-        for (id_transaccion, transaccion) in self.transacciones.iter_mut() {
-            if transaccion.from_id_nodo == msg.id_nodo
-                && transaccion.status as u8 != TransactionState::Abort as u8
-                && transaccion.status as u8 != TransactionState::Done as u8
-            {
-                transaccion.status = TransactionState::Abort;
-
-                if let Err(err) = ctx.address().try_send(Abort::new(
-                    transaccion.from_id_nodo,
-                    transaccion.id_cuenta,
-                    *id_transaccion,
-                    transaccion.id_cafetera,
-                )) {
-                    println!(
-                        "[COORDINADOR] Error al enviar ABORT al ID_NODO = {:?} | Detalle: {:?}",
-                        transaccion.from_id_nodo, err
-                    );
+        publicar_evento(
+            "COORDINADOR",
+            "DESCONEXION",
+            format!("ID_NODO = {} se desconecto", msg.id_nodo),
+        );
+        if self.sincronizando.remove(&msg.id_nodo).is_some() {
+            // se vuelve a pedir el snapshot cuando se reconecte
+            self.pendientes_nodos.remove(&msg.id_nodo);
+        }
+        let sin_donante: Vec<IdNodo> = self
+            .sincronizando
+            .iter()
+            .filter(|(_, nodo)| nodo.id_donante == msg.id_nodo)
+            .map(|(id_nodo, _)| *id_nodo)
+            .collect();
+        if self.addr_nodos.contains_key(&msg.id_nodo) {
+            // a partir de ahora se guardan los COMMIT/ABORT que se pierde, empezando por los commits
+            // en curso que todavia no habia confirmado
+            let en_curso = self.commits_sin_confirmar(msg.id_nodo);
+            self.addr_nodos.remove(&msg.id_nodo);
+            self.pendientes_nodos
+                .entry(msg.id_nodo)
+                .or_default()
+                .extend(en_curso);
+        }
+        // el donante se cayo antes de enviar el snapshot, se le pide a otro nodo
+        for id_nodo in sin_donante {
+            if let Some(nodo) = self.sincronizando.remove(&id_nodo) {
+                match self.elegir_donante(id_nodo) {
+                    Some(id_donante) => self.pedir_snapshot(id_nodo, nodo.addr, id_donante),
+                    None => self.agregar_nodo(id_nodo, nodo.addr),
                 }
             }
         }
+        // las auditorias en curso no esperan mas sus saldos
+        for auditoria in self.auditorias.values_mut() {
+            auditoria.descartar(msg.id_nodo);
+        }
+        self.cerrar_auditorias(|auditoria| auditoria.completa());
+        // se abortan las transacciones que inicio el nodo, las ya commiteadas se completan con los
+        // nodos restantes
+        self.con_tpc(|tpc, red| tpc.participante_caido(msg.id_nodo, red));
     }
 }
 
-#[derive(Message, Debug, Clone)]
-#[rtype(result = "()")]
-pub struct Disconnect {
-    pub tipo_mensaje: u8,
+/// Un nodo termino de drenarse y se retira: se lo quita como si se hubiera desconectado, pero sin
+/// guardarle los COMMIT/ABORT, ya que al volver se une de cero y recibe un snapshot
+impl Handler<Retiro> for Coordinador {
+    type Result = ();
+
+    fn handle(&mut self, msg: Retiro, ctx: &mut Self::Context) -> Self::Result {
+        if !self.conectado {
+            return;
+        }
+
+        info!(
+            "[COORDINADOR] El ID_NODO = {:?} se retira de la red",
+            msg.id_nodo
+        );
+        Handler::<DisconnectNodo>::handle(
+            self,
+            DisconnectNodo {
+                id_nodo: msg.id_nodo,
+            },
+            ctx,
+        );
+        self.pendientes_nodos.remove(&msg.id_nodo);
+    }
 }
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::DISCONNECT)]
+/// Mensaje que envia un nodo al coordinador cuando el proceso desconexion le indica que se desconecte
+pub struct Disconnect;
+
 /// Llega un disconnect, hay que limpiar el estado del servidor y apagar
 /// todos los actores nodo-handlers
 impl Handler<Disconnect> for Coordinador {
     type Result = ();
 
     fn handle(&mut self, _: Disconnect, _: &mut Self::Context) -> Self::Result {
-        for (id_nodo, addr) in self.addr_nodos.iter() {
-            if let Err(err) = addr.try_send(Shutdown {}) {
-                println!(
+        for (id_nodo, shutdown) in self.desconectar_nodos() {
+            if let Err(err) = shutdown.try_send(Shutdown {}) {
+                error!(
                     "[COORDINADOR] Error al enviar SHUTDOWN al ID_NODO = {:?} | Detalle: {:?}",
                     id_nodo, err
                 );
             }
         }
-
-        self.conectado = false;
-        self.addr_nodos = HashMap::new();
-        self.transacciones = HashMap::new();
-        self.cola = HashMap::new();
     }
 }
 
-pub trait DisconnectToString {
-    fn to_string(&self) -> String;
-    fn get_tipo_mensaje(&self) -> u8;
-}
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "()")]
+/// Mensaje para apagar al coordinador cuando termina el proceso (SIGINT o SIGTERM)
+pub struct Apagar;
 
-impl DisconnectToString for Disconnect {
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn to_string(&self) -> String {
-        self.tipo_mensaje.to_string()
+/// Se cierran las conexiones con todos los nodos, esperando a que cada una termine de cerrarse, y se
+/// detiene el actor. Los nodos ven el cierre de la conexion en lugar de descubrir la caida al escribir
+impl Handler<Apagar> for Coordinador {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _: Apagar, _: &mut Self::Context) -> Self::Result {
+        info!("[COORDINADOR] Me apago, cierro las conexiones con los nodos");
+        let nodos = self.desconectar_nodos();
+        self.registro.sincronizar();
+        Box::pin(
+            wrap_future::<_, Self>(async move {
+                for (id_nodo, shutdown) in nodos {
+                    if let Err(err) = shutdown.send(Shutdown {}).await {
+                        warn!(
+                            "[COORDINADOR] No se pudo cerrar la conexion con el ID_NODO = {:?} | Detalle: {:?}",
+                            id_nodo, err
+                        );
+                    }
+                }
+            })
+            .map(|_, _, ctx: &mut Context<Self>| ctx.stop()),
+        )
     }
 }
 
-// impl Display for Disconnect {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         write!(f, "{}", self.tipo_mensaje)
-//     }
-// }
+impl Coordinador {
+    /// Limpia el estado de los nodos conectados (y de los que se estan sincronizando) y retorna por
+    /// donde cerrar la conexion con cada uno
+    fn desconectar_nodos(&mut self) -> Vec<(IdNodo, Recipient<Shutdown>)> {
+        let sincronizando = self.sincronizando.iter().map(|(id, nodo)| (id, &nodo.addr));
+        let nodos = self
+            .addr_nodos
+            .iter()
+            .chain(sincronizando)
+            .map(|(id_nodo, addr)| (*id_nodo, addr.shutdown.clone()))
+            .collect();
+
+        self.conectado = false;
+        self.addr_nodos = HashMap::new();
+        self.pendientes_nodos = HashMap::new();
+        self.sincronizando = HashMap::new();
+        self.tpc = Self::nuevo_tpc(self.reloj.clone());
+        // las auditorias en curso se cierran con los saldos que llegaron
+        self.cerrar_auditorias(|_| true);
+        nodos
+    }
+}
 
 #[derive(Message, Debug, Clone)]
 #[rtype(result = "()")]
@@ -557,3 +1311,884 @@ impl Handler<SetState> for Coordinador {
         self.conectado = msg.estado;
     }
 }
+
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "Vec<EntradaRegistro>")]
+pub struct ConsultarRegistro {
+    /// secuencia de la ultima entrada que ya tiene quien consulta, 0 para pedir todo el registro
+    pub desde: u64,
+}
+/// Se recibe una consulta del registro de commits
+impl Handler<ConsultarRegistro> for Coordinador {
+    type Result = Vec<EntradaRegistro>;
+
+    fn handle(&mut self, msg: ConsultarRegistro, _: &mut Self::Context) -> Self::Result {
+        self.registro.desde(msg.desde)
+    }
+}
+
+/// Transaccion que el coordinador todavia no termino, con los nodos que respondieron en su fase actual
+/// y los que faltan, para diagnosticar las que no avanzan
+#[derive(Debug, Clone, Serialize)]
+pub struct TransaccionEnCurso {
+    pub id_transaccion: IdTransaccion,
+    pub id_cuenta: IdCuenta,
+    pub tipo: CommitType,
+    pub id_pedido: u32,
+    pub fase: EstadoTransaccion,
+    /// nodo que inicio la transaccion
+    pub id_nodo_origen: IdNodo,
+    pub respondieron: Vec<IdNodo>,
+    /// nodos cuya respuesta se espera para pasar a la siguiente fase
+    pub faltan: Vec<IdNodo>,
+    /// milisegundos desde que el coordinador recibio la transaccion
+    pub millis_en_curso: u64,
+}
+
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "Vec<TransaccionEnCurso>")]
+pub struct ConsultarTransacciones;
+/// Se consultan las transacciones en curso y en que respuestas esta esperando cada una
+impl Handler<ConsultarTransacciones> for Coordinador {
+    type Result = Vec<TransaccionEnCurso>;
+
+    fn handle(&mut self, _: ConsultarTransacciones, _: &mut Self::Context) -> Self::Result {
+        let ahora = self.reloj.ahora();
+        let mut participantes: Vec<IdNodo> = self.addr_nodos.keys().copied().collect();
+        participantes.sort_unstable();
+        self.tpc
+            .progreso(&participantes)
+            .into_iter()
+            .filter_map(|progreso| {
+                let operacion = self.tpc.operacion(progreso.id)?;
+                Some(TransaccionEnCurso {
+                    id_transaccion: progreso.id,
+                    id_cuenta: operacion.id_cuenta,
+                    tipo: operacion.tipo,
+                    id_pedido: operacion.id_pedido,
+                    fase: progreso.estado,
+                    id_nodo_origen: progreso.origen,
+                    respondieron: progreso.respondieron,
+                    faltan: progreso.faltan,
+                    millis_en_curso: ahora
+                        .saturating_duration_since(operacion.inicio)
+                        .as_millis() as u64,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Auditar {
+    /// por donde se entrega el informe al cerrar la auditoria
+    pub respuesta: oneshot::Sender<InformeAuditoria>,
+}
+/// Se inicia una auditoria de consistencia: se le piden los saldos a todos los nodos que votan. El
+/// pedido se envia por el mismo canal que los COMMIT/ABORT, asi todos responden con el mismo corte
+impl Handler<Auditar> for Coordinador {
+    type Result = ();
+
+    fn handle(&mut self, msg: Auditar, _: &mut Self::Context) -> Self::Result {
+        self.iniciar_auditoria(RespuestaAuditoria::Informe(msg.respuesta));
+    }
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct ConsultarSaldos {
+    /// por donde se entrega el snapshot al cerrar la auditoria
+    pub respuesta: oneshot::Sender<SnapshotSaldos>,
+}
+/// Se piden los saldos de todos los nodos que votan como en una auditoria, pero se responde el snapshot
+/// de los saldos del cluster: los del nodo del coordinador, con los nodos que difieren en cada cuenta
+impl Handler<ConsultarSaldos> for Coordinador {
+    type Result = ();
+
+    fn handle(&mut self, msg: ConsultarSaldos, _: &mut Self::Context) -> Self::Result {
+        self.iniciar_auditoria(RespuestaAuditoria::Saldos {
+            preferido: self.id_nodo,
+            respuesta: msg.respuesta,
+        });
+    }
+}
+
+impl Coordinador {
+    /// Le pide los saldos a todos los nodos que votan y espera sus respuestas para cerrar la auditoria
+    fn iniciar_auditoria(&mut self, respuesta: RespuestaAuditoria) {
+        self.ultima_auditoria += 1;
+        let id_auditoria = self.ultima_auditoria;
+        let corte = self.tpc.secuencia();
+        let nodos: Vec<IdNodo> = self.addr_nodos.keys().copied().collect();
+        info!(
+            "[COORDINADOR] Inicio la auditoria {} con corte {} sobre los ID_NODO = {:?}",
+            id_auditoria, corte, nodos
+        );
+        let auditoria = AuditoriaEnCurso::new(corte, self.reloj.ahora(), nodos, respuesta);
+        if auditoria.completa() {
+            Self::informar_auditoria(id_auditoria, auditoria.cerrar());
+            return;
+        }
+        let pedido = PedidoAuditoria::new(id_auditoria).to_bytes();
+        for canal in self.addr_nodos.values() {
+            canal.mensajes.do_send(ReceiverFromCoordinador {
+                vec: pedido.clone(),
+            });
+        }
+        self.auditorias.insert(id_auditoria, auditoria);
+    }
+}
+
+/// Se reciben los saldos de un nodo para una auditoria
+impl Handler<Auditoria> for Coordinador {
+    type Result = ();
+
+    fn handle(&mut self, msg: Auditoria, _: &mut Self::Context) -> Self::Result {
+        if let Some(auditoria) = self.auditorias.get_mut(&msg.id_auditoria) {
+            auditoria.reportar(msg.id_nodo, msg.cuentas);
+        }
+        self.cerrar_auditorias(|auditoria| auditoria.completa());
+    }
+}
+
+impl Coordinador {
+    /// Cierra las auditorias que superaron servidor.tiempo_maximo_auditoria_millis, con los nodos que
+    /// respondieron hasta el momento
+    fn revisar_auditorias(&mut self) {
+        let maximo = Duration::from_millis(config().servidor.tiempo_maximo_auditoria_millis);
+        let ahora = self.reloj.ahora();
+        self.cerrar_auditorias(|auditoria| {
+            auditoria.completa() || ahora.saturating_duration_since(auditoria.inicio) >= maximo
+        });
+    }
+
+    fn cerrar_auditorias(&mut self, cerrar: impl Fn(&AuditoriaEnCurso) -> bool) {
+        let cerradas: Vec<u32> = self
+            .auditorias
+            .iter()
+            .filter(|(_, auditoria)| cerrar(auditoria))
+            .map(|(id, _)| *id)
+            .collect();
+        for id_auditoria in cerradas {
+            if let Some(auditoria) = self.auditorias.remove(&id_auditoria) {
+                Self::informar_auditoria(id_auditoria, auditoria.cerrar());
+            }
+        }
+    }
+
+    fn informar_auditoria(id_auditoria: u32, informe: InformeAuditoria) {
+        let detalle = format!(
+            "AUDITORIA = {} | CORTE = {} | {} cuentas en los ID_NODO = {:?} | {} divergentes | sin respuesta: {:?}",
+            id_auditoria,
+            informe.corte,
+            informe.cuentas,
+            informe.nodos,
+            informe.divergentes.len(),
+            informe.sin_respuesta
+        );
+        if informe.consistente() {
+            info!("[COORDINADOR] {}", detalle);
+        } else {
+            warn!(
+                "[COORDINADOR] {} | Cuentas divergentes: {:?}",
+                detalle, informe.divergentes
+            );
+        }
+        publicar_evento("COORDINADOR", "AUDITORIA", detalle);
+    }
+}
+
+/// Lee un byte de la presentacion de una conexion entrante. Si no llega en
+/// timeout_presentacion_millis retorna un error, para no retener la tarea con un cliente mudo
+async fn leer_byte_con_plazo(stream: &mut TcpStream) -> Result<u8, ErrorServer> {
+    let plazo = Duration::from_millis(config().servidor.timeout_presentacion_millis);
+    tokio::time::timeout(plazo, stream.read_u8())
+        .await
+        .map_err(|_| ErrorServer::Timeout("la conexion no envio su presentacion".to_string()))?
+        .map_err(ErrorServer::from)
+}
+
+/// Lee el tipo de consulta de administracion y la responde, cerrando la conexion al terminar. La del
+/// registro de commits lleva la secuencia desde la que se consulta (u64 en big endian) y se responde
+/// con una linea json por entrada; la auditoria se responde con el informe en una linea json, la de
+/// saldos con el snapshot de los saldos del cluster en una linea json, y la de transacciones con una
+/// linea json por transaccion en curso
+async fn responder_consulta_admin(
+    mut stream: tokio::net::TcpStream,
+    coordinador: &actix::Addr<Coordinador>,
+) -> Result<(), ErrorServer> {
+    let mut respuesta = String::new();
+    match stream.read_u8().await? {
+        CONSULTA_REGISTRO => {
+            let desde = stream.read_u64().await?;
+            let entradas = coordinador.send(ConsultarRegistro { desde }).await?;
+            for entrada in entradas {
+                respuesta.push_str(&a_json(&entrada)?);
+                respuesta.push('\n');
+            }
+        }
+        CONSULTA_AUDITORIA => {
+            let (envio, informe) = oneshot::channel();
+            coordinador.send(Auditar { respuesta: envio }).await?;
+            let informe = informe.await.map_err(|_| {
+                ErrorServer::Estado("El coordinador se reinicio durante la auditoria".to_string())
+            })?;
+            respuesta.push_str(&a_json(&informe)?);
+            respuesta.push('\n');
+        }
+        CONSULTA_SALDOS => {
+            let (envio, snapshot) = oneshot::channel();
+            coordinador
+                .send(ConsultarSaldos { respuesta: envio })
+                .await?;
+            let snapshot = snapshot.await.map_err(|_| {
+                ErrorServer::Estado(
+                    "El coordinador se reinicio durante la consulta de saldos".to_string(),
+                )
+            })?;
+            respuesta.push_str(&a_json(&snapshot)?);
+            respuesta.push('\n');
+        }
+        CONSULTA_TRANSACCIONES => {
+            for transaccion in coordinador.send(ConsultarTransacciones).await? {
+                respuesta.push_str(&a_json(&transaccion)?);
+                respuesta.push('\n');
+            }
+        }
+        tipo => {
+            return Err(ErrorServer::Parseo(format!(
+                "Consulta de administracion desconocida {}",
+                tipo
+            )))
+        }
+    }
+    stream.write_all(respuesta.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+fn a_json<T: Serialize>(valor: &T) -> Result<String, ErrorServer> {
+    serde_json::to_string(valor).map_err(|x| ErrorServer::Parseo(x.to_string()))
+}
+
+#[cfg(test)]
+mod coordinador_test {
+    use super::*;
+    use crate::prueba::{CoordinadorDePrueba, NodoHandlerFalso};
+    use actix::Addr;
+    use tiempo::RelojVirtual;
+
+    #[derive(Message)]
+    #[rtype(result = "Option<EstadoTransaccion>")]
+    struct ConsultarEstado(IdTransaccion);
+
+    impl Handler<ConsultarEstado> for Coordinador {
+        type Result = Option<EstadoTransaccion>;
+
+        fn handle(&mut self, msg: ConsultarEstado, _: &mut Context<Self>) -> Self::Result {
+            self.tpc.estado(msg.0)
+        }
+    }
+
+    /// Da tiempo al actor para procesar los timers que vencieron y los mensajes que se autoenvio
+    async fn llega_al_estado(
+        coordinador: &Addr<Coordinador>,
+        id_transaccion: IdTransaccion,
+        estado: EstadoTransaccion,
+    ) -> bool {
+        for _ in 0..100 {
+            let actual = coordinador
+                .send(ConsultarEstado(id_transaccion))
+                .await
+                .unwrap();
+            if actual == Some(estado) {
+                return true;
+            }
+            tokio::task::yield_now().await;
+        }
+        false
+    }
+
+    #[actix_rt::test]
+    async fn una_conexion_muda_o_cerrada_no_detiene_la_aceptacion_de_las_demas() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let direccion = listener.local_addr().unwrap();
+        let (_apagar, apagado) = oneshot::channel();
+        let reloj: Arc<dyn Reloj> = Arc::new(RelojVirtual::new());
+        actix::spawn(Coordinador::start_listener(
+            listener,
+            IdNodo(1),
+            reloj,
+            apagado,
+        ));
+
+        let _muda = TcpStream::connect(direccion).await.unwrap();
+        drop(TcpStream::connect(direccion).await.unwrap());
+
+        let mut consulta = TcpStream::connect(direccion).await.unwrap();
+        consulta
+            .write_all(&[ID_CONSULTA_ADMIN, CONSULTA_TRANSACCIONES])
+            .await
+            .unwrap();
+        let mut respuesta = String::new();
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            consulta.read_to_string(&mut respuesta),
+        )
+        .await
+        .expect("la conexion muda no debe demorar la consulta")
+        .unwrap();
+        assert!(respuesta.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn resta_sin_respuesta_se_aborta_al_vencer_el_tiempo_maximo_de_servicio() {
+        let reloj = RelojVirtual::new();
+        let coordinador = Coordinador::new(Arc::new(reloj.clone())).start();
+        coordinador
+            .send(Starter::new(
+                IdNodo(1),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        assert!(
+            llega_al_estado(
+                &coordinador,
+                IdTransaccion(100),
+                EstadoTransaccion::Preparando
+            )
+            .await
+        );
+
+        let maximo = Duration::from_secs(config().servidor.tiempo_maximo_servicio_transaccion);
+        reloj.avanzar(maximo - Duration::from_millis(1));
+        assert!(!llega_al_estado(&coordinador, IdTransaccion(100), EstadoTransaccion::Abort).await);
+
+        reloj.avanzar(Duration::from_millis(
+            config().servidor.intervalo_planificacion_millis + 1,
+        ));
+        assert!(llega_al_estado(&coordinador, IdTransaccion(100), EstadoTransaccion::Abort).await);
+    }
+
+    #[actix_rt::test]
+    async fn starter_repetido_no_encola_la_cuenta_dos_veces() {
+        let reloj = RelojVirtual::new();
+        let coordinador = Coordinador::new(Arc::new(reloj.clone())).start();
+        coordinador
+            .send(Starter::new(
+                IdNodo(1),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        coordinador
+            .send(Starter::new(
+                IdNodo(1),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        coordinador
+            .send(Starter::new(
+                IdNodo(1),
+                IdCuenta(7),
+                IdTransaccion(101),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        assert!(
+            llega_al_estado(
+                &coordinador,
+                IdTransaccion(101),
+                EstadoTransaccion::Encolada
+            )
+            .await
+        );
+
+        // al vencer la primera se despacha la siguiente de la cuenta, que no es una copia de la vencida
+        let maximo = Duration::from_secs(config().servidor.tiempo_maximo_servicio_transaccion);
+        reloj.avanzar(
+            maximo + Duration::from_millis(config().servidor.intervalo_planificacion_millis + 1),
+        );
+        assert!(llega_al_estado(&coordinador, IdTransaccion(100), EstadoTransaccion::Abort).await);
+        assert!(
+            llega_al_estado(
+                &coordinador,
+                IdTransaccion(101),
+                EstadoTransaccion::Preparando
+            )
+            .await
+        );
+    }
+
+    #[actix_rt::test]
+    async fn resta_pasa_por_todas_las_fases_en_todos_los_nodos() {
+        let mut prueba = CoordinadorDePrueba::iniciar();
+        let nodo_1 = prueba.unir(IdNodo(1)).await;
+        let nodo_2 = prueba.unir(IdNodo(2)).await;
+        let coordinador = prueba.coordinador.clone();
+
+        coordinador
+            .send(Starter::new(
+                IdNodo(1),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            IdTransaccion(100),
+            nodo_1.recibir::<Prepare>().await.id_transaccion
+        );
+        assert_eq!(
+            IdTransaccion(100),
+            nodo_2.recibir::<Prepare>().await.id_transaccion
+        );
+
+        coordinador
+            .send(Yes::new(
+                IdNodo(1),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        coordinador
+            .send(Yes::new(
+                IdNodo(2),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        // solo ejecuta el nodo que inicio la resta
+        assert_eq!(
+            IdTransaccion(100),
+            nodo_1.recibir::<Execute>().await.id_transaccion
+        );
+        assert!(nodo_2.sin_mensajes().await);
+
+        let finish = Finish::new(
+            IdNodo(1),
+            IdCuenta(7),
+            IdTransaccion(100),
+            CommitType::RESTA,
+            50,
+            IdCafetera(0),
+        );
+        coordinador.send(finish).await.unwrap();
+        assert_eq!(50, nodo_1.recibir::<Validar>().await.cantidad);
+        assert_eq!(50, nodo_2.recibir::<Validar>().await.cantidad);
+
+        coordinador
+            .send(Valido::new(
+                IdNodo(1),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        coordinador
+            .send(Valido::new(
+                IdNodo(2),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(1, nodo_1.recibir::<Commit>().await.secuencia);
+        assert_eq!(1, nodo_2.recibir::<Commit>().await.secuencia);
+
+        let registro = coordinador
+            .send(ConsultarRegistro { desde: 0 })
+            .await
+            .unwrap();
+        assert_eq!(1, registro.len());
+        assert_eq!(
+            (
+                1,
+                IdTransaccion(100),
+                IdCuenta(7),
+                50,
+                ResultadoTransaccion::COMMIT
+            ),
+            (
+                registro[0].secuencia,
+                registro[0].id_transaccion,
+                registro[0].id_cuenta,
+                registro[0].cantidad,
+                registro[0].resultado
+            )
+        );
+
+        coordinador
+            .send(OkeyToCoordinator::new(
+                IdNodo(1),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        assert!(
+            !llega_al_estado(
+                &coordinador,
+                IdTransaccion(100),
+                EstadoTransaccion::Confirmada
+            )
+            .await
+        );
+        coordinador
+            .send(OkeyToCoordinator::new(
+                IdNodo(2),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        assert!(
+            llega_al_estado(
+                &coordinador,
+                IdTransaccion(100),
+                EstadoTransaccion::Confirmada
+            )
+            .await
+        );
+    }
+
+    #[actix_rt::test]
+    async fn transacciones_en_curso_indican_los_nodos_que_faltan_responder() {
+        let mut prueba = CoordinadorDePrueba::iniciar();
+        let nodo_1 = prueba.unir(IdNodo(1)).await;
+        let nodo_2 = prueba.unir(IdNodo(2)).await;
+        let coordinador = prueba.coordinador.clone();
+
+        let mut starter = Starter::new(IdNodo(1), IdCuenta(7), IdTransaccion(100), IdCafetera(0));
+        starter.id_pedido = 1000015;
+        coordinador.send(starter).await.unwrap();
+        nodo_1.recibir::<Prepare>().await;
+        nodo_2.recibir::<Prepare>().await;
+        coordinador
+            .send(Yes::new(
+                IdNodo(1),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        prueba.reloj.avanzar(Duration::from_millis(1500));
+
+        let en_curso = coordinador.send(ConsultarTransacciones).await.unwrap();
+        assert_eq!(1, en_curso.len());
+        let resta = &en_curso[0];
+        assert_eq!(
+            (IdTransaccion(100), IdCuenta(7), 1000015, IdNodo(1), 1500),
+            (
+                resta.id_transaccion,
+                resta.id_cuenta,
+                resta.id_pedido,
+                resta.id_nodo_origen,
+                resta.millis_en_curso
+            )
+        );
+        assert_eq!(
+            (
+                EstadoTransaccion::Preparando,
+                vec![IdNodo(1)],
+                vec![IdNodo(2)]
+            ),
+            (resta.fase, resta.respondieron.clone(), resta.faltan.clone())
+        );
+
+        coordinador
+            .send(Yes::new(
+                IdNodo(2),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        let en_curso = coordinador.send(ConsultarTransacciones).await.unwrap();
+        assert_eq!(
+            (EstadoTransaccion::Ejecutando, vec![IdNodo(1)]),
+            (en_curso[0].fase, en_curso[0].faltan.clone())
+        );
+    }
+
+    #[actix_rt::test]
+    async fn alta_se_reenvia_a_todos_y_no_se_da_de_baja_una_cuenta_en_uso() {
+        let mut prueba = CoordinadorDePrueba::iniciar();
+        let nodo_1 = prueba.unir(IdNodo(1)).await;
+        let nodo_2 = prueba.unir(IdNodo(2)).await;
+        let coordinador = prueba.coordinador.clone();
+
+        coordinador
+            .send(AltaCord::new(
+                IdNodo(1),
+                IdCuenta(9),
+                IdTransaccion(0),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(IdCuenta(9), nodo_1.recibir::<AltaCord>().await.id_cuenta);
+        assert_eq!(IdCuenta(9), nodo_2.recibir::<AltaCord>().await.id_cuenta);
+
+        coordinador
+            .send(Starter::new(
+                IdNodo(1),
+                IdCuenta(9),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        nodo_1.recibir::<Prepare>().await;
+        nodo_2.recibir::<Prepare>().await;
+        coordinador
+            .send(BajaCord::new(
+                IdNodo(2),
+                IdCuenta(9),
+                IdTransaccion(0),
+                IdCafetera(0),
+            ))
+            .await
+            .unwrap();
+        assert!(nodo_1.sin_mensajes().await);
+        assert!(nodo_2.sin_mensajes().await);
+    }
+
+    #[actix_rt::test]
+    async fn nodo_que_vuelve_recibe_los_commits_que_se_perdio() {
+        let mut prueba = CoordinadorDePrueba::iniciar();
+        let nodo_1 = prueba.unir(IdNodo(1)).await;
+        prueba.unir(IdNodo(2)).await;
+        let coordinador = prueba.coordinador.clone();
+
+        coordinador
+            .send(DisconnectNodo { id_nodo: IdNodo(2) })
+            .await
+            .unwrap();
+        let suma = Finish::new(
+            IdNodo(1),
+            IdCuenta(7),
+            IdTransaccion(100),
+            CommitType::SUMA,
+            30,
+            IdCafetera(0),
+        );
+        coordinador.send(suma).await.unwrap();
+        assert_eq!(30, nodo_1.recibir::<Commit>().await.cantidad);
+
+        let (canal, nodo_2) = NodoHandlerFalso::iniciar();
+        coordinador
+            .send(AddNodo {
+                nodo_addr: canal,
+                id_nodo: IdNodo(2),
+                modo: ModoConexion::ConEstado,
+            })
+            .await
+            .unwrap();
+        let commit = nodo_2.recibir::<Commit>().await;
+        assert_eq!(
+            (IdTransaccion(100), 30),
+            (commit.id_transaccion, commit.cantidad)
+        );
+        assert!(nodo_1.sin_mensajes().await);
+    }
+
+    #[actix_rt::test]
+    async fn nodo_que_funciono_desconectado_reconcilia_sus_saldos_antes_de_votar() {
+        let mut prueba = CoordinadorDePrueba::en_nodo(IdNodo(2));
+        prueba.unir(IdNodo(1)).await;
+        let nodo_2 = prueba.unir(IdNodo(2)).await;
+        let nodo_3 = prueba.unir(IdNodo(3)).await;
+        let coordinador = prueba.coordinador.clone();
+
+        coordinador
+            .send(DisconnectNodo { id_nodo: IdNodo(1) })
+            .await
+            .unwrap();
+        let suma = Finish::new(
+            IdNodo(2),
+            IdCuenta(7),
+            IdTransaccion(100),
+            CommitType::SUMA,
+            30,
+            IdCafetera(0),
+        );
+        coordinador.send(suma).await.unwrap();
+        nodo_2.recibir::<Commit>().await;
+        nodo_3.recibir::<Commit>().await;
+
+        // aunque el coordinador conoce el commit que se perdio, el nodo copia los saldos del nodo
+        // del coordinador
+        let (canal, nodo_1) = NodoHandlerFalso::iniciar();
+        coordinador
+            .send(AddNodo {
+                nodo_addr: canal,
+                id_nodo: IdNodo(1),
+                modo: ModoConexion::Reconciliar,
+            })
+            .await
+            .unwrap();
+        let pedido = nodo_2.recibir::<PedidoSnapshot>().await;
+        assert_eq!((IdNodo(1), 1), (pedido.id_nodo, pedido.corte));
+        assert!(nodo_3.sin_mensajes().await);
+
+        // la suma que acepto mientras estaba desconectado se aplica sobre el snapshot
+        let suma_desconectado = Finish::new(
+            IdNodo(1),
+            IdCuenta(7),
+            IdTransaccion(101),
+            CommitType::SUMA,
+            5,
+            IdCafetera(0),
+        );
+        coordinador.send(suma_desconectado).await.unwrap();
+        nodo_2.recibir::<Commit>().await;
+        nodo_3.recibir::<Commit>().await;
+        for id_nodo in [IdNodo(2), IdNodo(3)] {
+            coordinador
+                .send(OkeyToCoordinator::new(
+                    id_nodo,
+                    IdCuenta(7),
+                    IdTransaccion(101),
+                    IdCafetera(0),
+                ))
+                .await
+                .unwrap();
+        }
+
+        coordinador
+            .send(Snapshot::new(
+                IdNodo(1),
+                pedido.corte,
+                vec![(IdCuenta(7), 130)],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![(IdCuenta(7), 130)],
+            nodo_1.recibir::<Snapshot>().await.cuentas
+        );
+        let commit = nodo_1.recibir::<Commit>().await;
+        assert_eq!(
+            (IdTransaccion(101), 2),
+            (commit.id_transaccion, commit.secuencia)
+        );
+        assert_eq!(
+            IdTransaccion(101),
+            nodo_1.recibir::<ConfirmadoToNodo>().await.id_transaccion
+        );
+    }
+
+    #[actix_rt::test]
+    async fn auditoria_informa_las_cuentas_divergentes_y_los_nodos_sin_respuesta() {
+        let mut prueba = CoordinadorDePrueba::iniciar();
+        let nodo_1 = prueba.unir(IdNodo(1)).await;
+        let nodo_2 = prueba.unir(IdNodo(2)).await;
+        let nodo_3 = prueba.unir(IdNodo(3)).await;
+        let coordinador = prueba.coordinador.clone();
+
+        let (respuesta, informe) = oneshot::channel();
+        coordinador.send(Auditar { respuesta }).await.unwrap();
+        let id_auditoria = nodo_1.recibir::<PedidoAuditoria>().await.id_auditoria;
+        nodo_2.recibir::<PedidoAuditoria>().await;
+        nodo_3.recibir::<PedidoAuditoria>().await;
+
+        coordinador
+            .send(Auditoria::new(
+                IdNodo(1),
+                id_auditoria,
+                vec![(IdCuenta(7), 100), (IdCuenta(8), 5)],
+            ))
+            .await
+            .unwrap();
+        coordinador
+            .send(Auditoria::new(
+                IdNodo(2),
+                id_auditoria,
+                vec![(IdCuenta(7), 90), (IdCuenta(8), 5)],
+            ))
+            .await
+            .unwrap();
+        coordinador
+            .send(DisconnectNodo { id_nodo: IdNodo(3) })
+            .await
+            .unwrap();
+
+        let informe = informe.await.unwrap();
+        assert_eq!(
+            (vec![IdNodo(1), IdNodo(2)], vec![IdNodo(3)]),
+            (informe.nodos, informe.sin_respuesta)
+        );
+        assert_eq!(1, informe.divergentes.len());
+        assert_eq!(
+            vec![(IdNodo(1), Some(100)), (IdNodo(2), Some(90))],
+            informe.divergentes[0].saldos
+        );
+    }
+
+    #[actix_rt::test]
+    async fn saldos_del_cluster_son_los_del_nodo_del_coordinador_con_las_discrepancias() {
+        let mut prueba = CoordinadorDePrueba::en_nodo(IdNodo(2));
+        prueba.unir(IdNodo(1)).await;
+        prueba.unir(IdNodo(2)).await;
+        prueba.unir(IdNodo(3)).await;
+
+        let snapshot = prueba
+            .saldos(&[
+                (IdNodo(1), vec![(IdCuenta(7), 90), (IdCuenta(8), 5)]),
+                (IdNodo(2), vec![(IdCuenta(7), 100), (IdCuenta(8), 5)]),
+                (IdNodo(3), vec![(IdCuenta(7), 100)]),
+            ])
+            .await;
+
+        assert_eq!(
+            (Some(IdNodo(2)), vec![IdNodo(1), IdNodo(2), IdNodo(3)]),
+            (snapshot.referencia, snapshot.nodos.clone())
+        );
+        assert_eq!(
+            (Some(100), Some(5)),
+            (snapshot.saldo(IdCuenta(7)), snapshot.saldo(IdCuenta(8)))
+        );
+        assert_eq!(
+            vec![vec![(IdNodo(1), Some(90))], vec![(IdNodo(3), None)]],
+            snapshot
+                .cuentas
+                .iter()
+                .map(|x| x.discrepancias.clone())
+                .collect::<Vec<_>>()
+        );
+        assert!(!snapshot.consistente());
+    }
+
+    #[actix_rt::test]
+    async fn disconnect_cierra_la_conexion_de_todos_los_nodos() {
+        let mut prueba = CoordinadorDePrueba::iniciar();
+        let nodo_1 = prueba.unir(IdNodo(1)).await;
+        let nodo_2 = prueba.unir(IdNodo(2)).await;
+
+        prueba.coordinador.send(Disconnect).await.unwrap();
+        assert!(nodo_1.cerrado().await);
+        assert!(nodo_2.cerrado().await);
+    }
+}
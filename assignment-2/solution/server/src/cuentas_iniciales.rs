@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::fs;
+
+use compartido::config::config;
+use compartido::ids::IdCuenta;
+use serde::Deserialize;
+
+use crate::error_server::ErrorServer;
+
+#[derive(Deserialize)]
+/// Cuenta a crear al arrancar el nodo, tal como figura en el archivo de cuentas
+struct CuentaInicial {
+    /// id correspondiente a la tarjeta del usuario
+    id_cuenta: IdCuenta,
+    /// saldo con el que arranca la cuenta
+    saldo: u32,
+}
+
+/// Cuentas con las que arranca un nodo si no se le indica un archivo: ids 1 a cuentas_iniciales con
+/// saldo_inicial
+pub fn cuentas_por_defecto() -> HashMap<IdCuenta, u32> {
+    (1..=config().servidor.cuentas_iniciales)
+        .map(|id_cuenta| (IdCuenta(id_cuenta), config().servidor.saldo_inicial))
+        .collect()
+}
+
+/// Lee el archivo json con las cuentas iniciales, de la forma
+/// `[{"id_cuenta": 1, "saldo": 10000}, ...]`, y retorna el saldo de cada cuenta.
+/// Todos los nodos deben arrancar con el mismo archivo
+pub fn cargar_cuentas(path: &str) -> Result<HashMap<IdCuenta, u32>, ErrorServer> {
+    let contenido =
+        fs::read_to_string(path).map_err(|x| ErrorServer::Archivo(format!("{}: {}", path, x)))?;
+    cuentas_from_json(&contenido)
+}
+
+/// Arma las cuentas a partir del contenido del archivo, rechazando ids repetidos y saldos que
+/// superen saldo_maximo
+fn cuentas_from_json(contenido: &str) -> Result<HashMap<IdCuenta, u32>, ErrorServer> {
+    let lista: Vec<CuentaInicial> =
+        serde_json::from_str(contenido).map_err(|x| ErrorServer::Parseo(x.to_string()))?;
+    let mut cuentas = HashMap::new();
+    for cuenta in lista {
+        if cuenta.saldo > config().servidor.saldo_maximo {
+            return Err(ErrorServer::Parseo(format!(
+                "El saldo de la ID_CUENTA = {} supera el saldo maximo",
+                cuenta.id_cuenta
+            )));
+        }
+        if cuentas.insert(cuenta.id_cuenta, cuenta.saldo).is_some() {
+            return Err(ErrorServer::Parseo(format!(
+                "La ID_CUENTA = {} esta repetida",
+                cuenta.id_cuenta
+            )));
+        }
+    }
+    Ok(cuentas)
+}
+
+#[cfg(test)]
+mod cuentas_iniciales_test {
+    use super::*;
+
+    #[test]
+    fn cuentas_desde_json() {
+        let cuentas =
+            cuentas_from_json(r#"[{"id_cuenta": 1, "saldo": 500}, {"id_cuenta": 7, "saldo": 0}]"#)
+                .unwrap();
+        assert_eq!(
+            HashMap::from([(IdCuenta(1), 500), (IdCuenta(7), 0)]),
+            cuentas
+        );
+    }
+
+    #[test]
+    fn cuentas_repetidas_se_rechazan() {
+        let resultado = cuentas_from_json(
+            r#"[{"id_cuenta": 1, "saldo": 500}, {"id_cuenta": 1, "saldo": 100}]"#,
+        );
+        assert!(matches!(resultado, Err(ErrorServer::Parseo(_))));
+    }
+
+    #[test]
+    fn saldo_por_encima_del_maximo_se_rechaza() {
+        let json = format!(
+            r#"[{{"id_cuenta": 1, "saldo": {}}}]"#,
+            config().servidor.saldo_maximo + 1
+        );
+        assert!(cuentas_from_json(&json).is_err());
+    }
+
+    #[test]
+    fn cuentas_por_defecto_con_saldo_inicial() {
+        let cuentas = cuentas_por_defecto();
+        assert_eq!(config().servidor.cuentas_iniciales as usize, cuentas.len());
+        assert!(cuentas
+            .values()
+            .all(|saldo| *saldo == config().servidor.saldo_inicial));
+    }
+}
@@ -0,0 +1,160 @@
+//! Estados de una transaccion iniciada por un nodo (una suma, resta o transferencia de una de sus
+//! cafeteras) y las transiciones validas entre ellos. Los cambios de estado se hacen con `transicion`,
+//! que rechaza y loguea los que no corresponden, por ejemplo un COMMIT de una resta ya abortada
+
+use compartido::ids::IdTransaccion;
+use tracing::warn;
+
+/// Estado de una transaccion en el nodo que la inicio
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionState {
+    /// se confirmo en el coordinador, si es una suma falta el resultado final (CONFIRMADO o FALLIDO)
+    Accepted,
+    /// se creo: la suma espera el cafe y la resta el EXECUTE del coordinador
+    Wait,
+    /// se envio el FINISH y se espera el COMMIT o el ABORT
+    WaitCommit,
+    /// la resta tiene las cuentas bloqueadas y se prepara el cafe
+    Locked,
+    /// la suma termino sin coordinador, el FINISH se envia al reconectarse
+    ToSend,
+    /// la suma se aplico en todos los nodos
+    Commit,
+    Abort,
+}
+
+/// Evento que cambia el estado de una transaccion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventoTransaccion {
+    /// el coordinador envio el EXECUTE de la resta
+    Bloquear,
+    /// se envio el FINISH al coordinador
+    Finalizar,
+    /// el FINISH de la suma queda pendiente hasta tener coordinador
+    Posponer,
+    /// llego el COMMIT del coordinador
+    Commit,
+    /// llego el CONFIRMADO de la suma
+    Confirmar,
+    /// la transaccion se aborta, en el nodo o en el coordinador
+    Abortar,
+}
+
+impl TransactionState {
+    /// true mientras la transaccion espera algo del coordinador o de la cafetera
+    pub fn en_curso(&self) -> bool {
+        matches!(
+            self,
+            TransactionState::Wait
+                | TransactionState::WaitCommit
+                | TransactionState::Locked
+                | TransactionState::ToSend
+        )
+    }
+
+    /// Estado al que pasa la transaccion con el evento, None si la transicion no es valida
+    pub fn siguiente(&self, evento: EventoTransaccion) -> Option<TransactionState> {
+        use EventoTransaccion as E;
+        use TransactionState as S;
+        match (self, evento) {
+            (S::Wait, E::Bloquear) => Some(S::Locked),
+            // la transferencia y la suma envian el FINISH sin bloquear
+            (S::Wait | S::Locked | S::ToSend, E::Finalizar) => Some(S::WaitCommit),
+            (S::Wait, E::Posponer) => Some(S::ToSend),
+            (S::WaitCommit, E::Commit) => Some(S::Accepted),
+            // si el nodo no voto el commit de su suma (por ejemplo al sincronizarse) solo recibe el
+            // CONFIRMADO
+            (S::Accepted | S::WaitCommit, E::Confirmar) => Some(S::Commit),
+            (estado, E::Abortar) if estado.en_curso() => Some(S::Abort),
+            _ => None,
+        }
+    }
+
+    /// Aplica el evento a la transaccion. Si la transicion no es valida el estado no cambia, se loguea
+    /// y retorna false
+    pub fn transicion(&mut self, evento: EventoTransaccion, id_transaccion: IdTransaccion) -> bool {
+        match self.siguiente(evento) {
+            Some(siguiente) => {
+                *self = siguiente;
+                true
+            }
+            None => {
+                warn!(
+                    "[NODO] Se rechaza {:?} en la transaccion ID_TRANSACCION = {} en estado {:?}",
+                    evento, id_transaccion, self
+                );
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod estado_transaccion_test {
+    use super::*;
+
+    use EventoTransaccion as E;
+    use TransactionState as S;
+
+    const ESTADOS: [TransactionState; 7] = [
+        S::Accepted,
+        S::Wait,
+        S::WaitCommit,
+        S::Locked,
+        S::ToSend,
+        S::Commit,
+        S::Abort,
+    ];
+    const EVENTOS: [EventoTransaccion; 6] = [
+        E::Bloquear,
+        E::Finalizar,
+        E::Posponer,
+        E::Commit,
+        E::Confirmar,
+        E::Abortar,
+    ];
+
+    #[test]
+    fn solo_se_permiten_las_transiciones_del_grafo() {
+        let permitidas = [
+            (S::Wait, E::Bloquear, S::Locked),
+            (S::Wait, E::Finalizar, S::WaitCommit),
+            (S::Locked, E::Finalizar, S::WaitCommit),
+            (S::ToSend, E::Finalizar, S::WaitCommit),
+            (S::Wait, E::Posponer, S::ToSend),
+            (S::WaitCommit, E::Commit, S::Accepted),
+            (S::Accepted, E::Confirmar, S::Commit),
+            (S::WaitCommit, E::Confirmar, S::Commit),
+            (S::Wait, E::Abortar, S::Abort),
+            (S::Locked, E::Abortar, S::Abort),
+            (S::ToSend, E::Abortar, S::Abort),
+            (S::WaitCommit, E::Abortar, S::Abort),
+        ];
+        for estado in ESTADOS {
+            for evento in EVENTOS {
+                let esperado = permitidas
+                    .iter()
+                    .find(|(desde, con, _)| *desde == estado && *con == evento)
+                    .map(|(_, _, hasta)| *hasta);
+                assert_eq!(
+                    esperado,
+                    estado.siguiente(evento),
+                    "{:?} con {:?}",
+                    estado,
+                    evento
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn la_transicion_invalida_deja_el_estado_como_estaba() {
+        let mut estado = S::Wait;
+        assert!(estado.transicion(E::Bloquear, IdTransaccion(1)));
+        assert!(estado.transicion(E::Abortar, IdTransaccion(1)));
+        assert!(!estado.transicion(E::Commit, IdTransaccion(1)));
+        assert_eq!(S::Abort, estado);
+        assert!(!estado.transicion(E::Abortar, IdTransaccion(1)));
+        assert!(!estado.en_curso());
+    }
+}
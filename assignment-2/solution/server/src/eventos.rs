@@ -0,0 +1,32 @@
+use std::net::UdpSocket;
+use std::sync::OnceLock;
+
+use compartido::config::config;
+
+/// Socket desde el que el proceso publica sus eventos, se crea con el primer evento
+static SOCKET_EVENTOS: OnceLock<Option<UdpSocket>> = OnceLock::new();
+
+/// Publica un evento del cluster (elecciones, commits, aborts, desconexiones) para el dashboard.
+/// Se envia por udp como una linea json sin esperar respuesta, si el dashboard no esta levantado el
+/// evento se pierde sin afectar al nodo
+pub fn publicar_evento(origen: &str, tipo: &str, detalle: String) {
+    if !config().servidor.publicar_eventos {
+        return;
+    }
+    let socket = SOCKET_EVENTOS.get_or_init(|| {
+        let socket = UdpSocket::bind("127.0.0.1:0").ok()?;
+        socket.set_nonblocking(true).ok()?;
+        Some(socket)
+    });
+    if let Some(socket) = socket {
+        let evento = serde_json::json!({
+            "origen": origen,
+            "tipo": tipo,
+            "detalle": detalle,
+        });
+        let _res = socket.send_to(
+            evento.to_string().as_bytes(),
+            &config().servidor.direccion_eventos_dashboard,
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use actix::Addr;
+use compartido::codificable::Codificable;
+use compartido::config::config;
+use compartido::ids::{IdCafetera, IdCuenta, IdNodo};
+use compartido::mensajes_cafetera::{
+    Consultar, MensajeCafetera, MensajeCafeteraBytes, OkeyToCafetera, Restar, Sumar,
+};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::cafetera_listener::{AbrirCanalLocal, CafeteraListener, CerrarCanalLocal};
+use crate::error_server::ErrorServer;
+use crate::nodo::{Nodo, ReceiveFromCafetera};
+use crate::utils::id_to_addr_grpc;
+use tracing::info;
+/// Codigo generado a partir de proto/cafetera.proto
+pub mod proto {
+    tonic::include_proto!("cafetera");
+}
+
+use proto::cafetera_server::{Cafetera, CafeteraServer};
+use proto::{PedidoConsulta, PedidoPuntos, Resultado, Saldo};
+
+/// id de cafetera con el que llegan al nodo los pedidos recibidos por grpc
+const ID_CAFETERA_GRPC: IdCafetera = IdCafetera(0);
+
+/// Servicio grpc de un nodo. Cada pedido se le envia al actor Nodo como si viniera de una cafetera,
+/// sin pasar por udp, y el cafetera listener le entrega las respuestas del nodo por un canal propio
+pub struct ServicioCafetera {
+    /// id del nodo que atiende los pedidos
+    id_nodo: IdNodo,
+    /// actor al que se envian los pedidos
+    nodo: Addr<Nodo>,
+    /// actor que recibe las respuestas del nodo a las cafeteras
+    listener: Addr<CafeteraListener>,
+    /// ultimo id de pedido asignado
+    ultimo_pedido: AtomicU32,
+}
+
+/// Pedido en curso del servicio, con las respuestas que le envia el nodo
+struct Canal {
+    /// direccion con la que el nodo identifica al pedido. Nunca es el origen de un datagrama, por lo
+    /// que no se confunde con el de una cafetera
+    socket: SocketAddr,
+    id_pedido: u32,
+    respuestas: mpsc::UnboundedReceiver<Vec<u8>>,
+}
+
+impl ServicioCafetera {
+    /// Id del proximo pedido, distinto en cada uno para que el nodo no lo tome como el reintento de
+    /// uno anterior
+    fn nuevo_id_pedido(&self) -> u32 {
+        self.ultimo_pedido
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(1)
+    }
+
+    /// Abre el canal del pedido en el cafetera listener
+    async fn abrir(&self, id_pedido: u32) -> Result<Canal, Status> {
+        let puerto = (id_pedido % u32::from(u16::MAX)) as u16 + 1;
+        let socket = SocketAddr::from((Ipv4Addr::UNSPECIFIED, puerto));
+        let (respuestas, recibidas) = mpsc::unbounded_channel();
+        self.listener
+            .send(AbrirCanalLocal { socket, respuestas })
+            .await
+            .map_err(|x| Status::unavailable(x.to_string()))?;
+        Ok(Canal {
+            socket,
+            id_pedido,
+            respuestas: recibidas,
+        })
+    }
+
+    /// Quita el canal del pedido del cafetera listener
+    fn cerrar(&self, canal: Canal) {
+        self.listener.do_send(CerrarCanalLocal {
+            socket: canal.socket,
+        });
+    }
+
+    /// Envia el mensaje al nodo
+    async fn enviar(&self, canal: &Canal, paquete: Vec<u8>) -> Result<(), Status> {
+        self.nodo
+            .send(ReceiveFromCafetera {
+                msg: paquete,
+                socket: canal.socket,
+                autenticado: false,
+            })
+            .await
+            .map_err(|x| Status::unavailable(x.to_string()))
+    }
+
+    /// Espera la respuesta del nodo hasta recibir alguno de los tipos indicados, descartando el resto.
+    /// Retorna el mensaje recibido o un error si no llega en timeout_grpc_segundos
+    async fn esperar(
+        &self,
+        canal: &mut Canal,
+        tipos: &[MensajeCafetera],
+    ) -> Result<Vec<u8>, Status> {
+        let recibir = async {
+            loop {
+                let respuesta = canal.respuestas.recv().await.ok_or_else(|| {
+                    Status::unavailable(format!("El ID_NODO = {} se detuvo", self.id_nodo))
+                })?;
+                let tipo = match respuesta.first() {
+                    Some(tipo) => MensajeCafetera::from_bytes(*tipo) as u8,
+                    None => continue,
+                };
+                if tipos.iter().any(|x| *x as u8 == tipo) {
+                    return Ok(respuesta);
+                }
+            }
+        };
+        timeout(
+            Duration::from_secs(config().servidor.timeout_grpc_segundos),
+            recibir,
+        )
+        .await
+        .map_err(|_| Status::deadline_exceeded("El nodo no respondio a tiempo"))?
+    }
+
+    /// Envia el pedido de suma o resta y, si el nodo lo acepta, la cantidad final (como la cafetera
+    /// al terminar de preparar el cafe). Retorna si el pedido termino confirmado
+    async fn modificar(
+        &self,
+        pedido: Vec<u8>,
+        id_pedido: u32,
+        id_cuenta: IdCuenta,
+        cantidad: u32,
+        confirmacion: MensajeCafetera,
+    ) -> Result<bool, Status> {
+        let mut canal = self.abrir(id_pedido).await?;
+        let resultado = self
+            .completar(&mut canal, pedido, id_cuenta, cantidad, confirmacion)
+            .await;
+        self.cerrar(canal);
+        resultado
+    }
+
+    /// Intercambia con el nodo los mensajes de la suma o resta por el canal del pedido
+    async fn completar(
+        &self,
+        canal: &mut Canal,
+        pedido: Vec<u8>,
+        id_cuenta: IdCuenta,
+        cantidad: u32,
+        confirmacion: MensajeCafetera,
+    ) -> Result<bool, Status> {
+        self.enviar(canal, pedido).await?;
+        let respuesta = self
+            .esperar(canal, &[MensajeCafetera::OKEY, MensajeCafetera::ERROR])
+            .await?;
+        if respuesta[0] != MensajeCafetera::OKEY as u8 {
+            return Ok(false);
+        }
+        let okey = OkeyToCafetera::new(ID_CAFETERA_GRPC, id_cuenta, cantidad)
+            .con_id_pedido(canal.id_pedido)
+            .to_bytes();
+        self.enviar(canal, okey).await?;
+        let resultado = self
+            .esperar(
+                canal,
+                &[
+                    confirmacion,
+                    MensajeCafetera::FALLIDO,
+                    MensajeCafetera::ERROR,
+                ],
+            )
+            .await?;
+        Ok(resultado[0] == confirmacion as u8)
+    }
+}
+
+#[tonic::async_trait]
+impl Cafetera for ServicioCafetera {
+    async fn sumar(&self, request: Request<PedidoPuntos>) -> Result<Response<Resultado>, Status> {
+        let pedido = request.into_inner();
+        let mut sumar = Sumar::new(
+            ID_CAFETERA_GRPC,
+            IdCuenta(pedido.id_cuenta),
+            pedido.cantidad,
+        );
+        sumar.id_pedido = self.nuevo_id_pedido();
+        let confirmado = self
+            .modificar(
+                sumar.to_bytes(),
+                sumar.id_pedido,
+                IdCuenta(pedido.id_cuenta),
+                pedido.cantidad,
+                // la suma termina cuando el coordinador la confirma en todos los nodos
+                MensajeCafetera::CONFIRMADO,
+            )
+            .await?;
+        Ok(Response::new(Resultado { confirmado }))
+    }
+
+    async fn restar(&self, request: Request<PedidoPuntos>) -> Result<Response<Resultado>, Status> {
+        let pedido = request.into_inner();
+        let mut restar = Restar::new(
+            ID_CAFETERA_GRPC,
+            IdCuenta(pedido.id_cuenta),
+            pedido.cantidad,
+        );
+        restar.id_pedido = self.nuevo_id_pedido();
+        let confirmado = self
+            .modificar(
+                restar.to_bytes(),
+                restar.id_pedido,
+                IdCuenta(pedido.id_cuenta),
+                pedido.cantidad,
+                MensajeCafetera::OKEY,
+            )
+            .await?;
+        Ok(Response::new(Resultado { confirmado }))
+    }
+
+    async fn consultar(&self, request: Request<PedidoConsulta>) -> Result<Response<Saldo>, Status> {
+        let pedido = request.into_inner();
+        let mut canal = self.abrir(self.nuevo_id_pedido()).await?;
+        let consulta = Consultar::new(ID_CAFETERA_GRPC, IdCuenta(pedido.id_cuenta), 0).to_bytes();
+        let respuesta = match self.enviar(&canal, consulta).await {
+            Ok(()) => {
+                self.esperar(
+                    &mut canal,
+                    &[MensajeCafetera::CONSULTAR, MensajeCafetera::ERROR],
+                )
+                .await
+            }
+            Err(err) => Err(err),
+        };
+        self.cerrar(canal);
+        let respuesta = respuesta?;
+        if respuesta[0] != MensajeCafetera::CONSULTAR as u8 {
+            return Err(Status::not_found(format!(
+                "No existe la ID_CUENTA = {}",
+                pedido.id_cuenta
+            )));
+        }
+        let consulta =
+            Consultar::from_bytes(&respuesta).map_err(|x| Status::internal(x.to_string()))?;
+        Ok(Response::new(Saldo {
+            saldo: consulta.cantidad_modificar,
+        }))
+    }
+}
+
+/// Atiende el servicio grpc del nodo hasta que termine el proceso, enviandole los pedidos al nodo
+pub async fn servir(
+    id_nodo: IdNodo,
+    nodo: Addr<Nodo>,
+    listener: Addr<CafeteraListener>,
+) -> Result<(), ErrorServer> {
+    let addr = id_to_addr_grpc(id_nodo.0)
+        .parse()
+        .map_err(|_| ErrorServer::Argumentos("Direccion grpc invalida".to_string()))?;
+    info!("[GRPC] Escuchando pedidos en {}", addr);
+    Server::builder()
+        .add_service(CafeteraServer::new(ServicioCafetera {
+            id_nodo,
+            nodo,
+            listener,
+            ultimo_pedido: AtomicU32::new(0),
+        }))
+        .serve(addr)
+        .await
+        .map_err(|x| ErrorServer::Conexion(std::io::Error::other(x)))
+}
@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use crate::mensaje::CommitType;
+
+/// Limites superiores (en milisegundos) de los intervalos del histograma, en escala 1-2-5. Las
+/// latencias mayores al ultimo limite caen en un intervalo extra sin limite
+const LIMITES_MILLIS: [u64; 16] = [
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 20_000, 50_000, 100_000,
+];
+
+/// Histograma de latencias con intervalos fijos. Los percentiles se aproximan por el limite superior
+/// del intervalo en el que caen, acotado por la latencia maxima observada
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histograma {
+    /// cantidad de latencias en cada intervalo, el ultimo es el de las mayores a todos los limites
+    cantidades: [u64; LIMITES_MILLIS.len() + 1],
+    /// cantidad total de latencias registradas
+    total: u64,
+    /// mayor latencia registrada
+    maxima: Duration,
+}
+
+impl Default for Histograma {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Histograma {
+    /// Crea un histograma vacio
+    pub fn new() -> Histograma {
+        Histograma {
+            cantidades: [0; LIMITES_MILLIS.len() + 1],
+            total: 0,
+            maxima: Duration::ZERO,
+        }
+    }
+
+    /// Registra una latencia en el intervalo que le corresponde
+    pub fn registrar(&mut self, latencia: Duration) {
+        let millis = latencia.as_millis();
+        let intervalo = LIMITES_MILLIS
+            .iter()
+            .position(|limite| millis <= *limite as u128)
+            .unwrap_or(LIMITES_MILLIS.len());
+        self.cantidades[intervalo] += 1;
+        self.total += 1;
+        self.maxima = self.maxima.max(latencia);
+    }
+
+    /// Cantidad de latencias registradas
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Mayor latencia registrada
+    pub fn maxima(&self) -> Duration {
+        self.maxima
+    }
+
+    /// Latencia por debajo de la cual esta el porcentaje indicado (de 0 a 100) de las registradas
+    pub fn percentil(&self, porcentaje: u64) -> Duration {
+        if self.total == 0 {
+            return Duration::ZERO;
+        }
+        // posicion (contando desde 1) de la latencia buscada si estuvieran ordenadas
+        let posicion = (self.total * porcentaje.min(100)).div_ceil(100).max(1);
+        let mut acumuladas = 0;
+        for (intervalo, cantidad) in self.cantidades.iter().enumerate() {
+            acumuladas += cantidad;
+            if acumuladas >= posicion {
+                return match LIMITES_MILLIS.get(intervalo) {
+                    Some(limite) => Duration::from_millis(*limite).min(self.maxima),
+                    None => self.maxima,
+                };
+            }
+        }
+        self.maxima
+    }
+
+    /// Arma la linea de log con la cantidad, p50, p99 y maximo del histograma
+    pub fn resumen(&self) -> String {
+        format!(
+            "cantidad = {}, p50 = {:?}, p99 = {:?}, maxima = {:?}",
+            self.total,
+            self.percentil(50),
+            self.percentil(99),
+            self.maxima
+        )
+    }
+}
+
+/// Histogramas de latencia de las transacciones que lleva el coordinador, uno por tipo
+#[derive(Debug, Default)]
+pub struct LatenciasTransacciones {
+    /// sumas, desde el FINISH hasta el OK (u OK_ABORT) de todos los nodos
+    pub sumas: Histograma,
+    /// restas, desde el STARTER hasta que terminan confirmadas o abortadas. Incluye la espera en la
+    /// cola de la cuenta y la demora de las elecciones o de los nodos lentos
+    pub restas: Histograma,
+    /// transferencias, desde que se reciben hasta que terminan confirmadas o abortadas
+    pub transferencias: Histograma,
+}
+
+impl LatenciasTransacciones {
+    /// Crea los histogramas vacios
+    pub fn new() -> LatenciasTransacciones {
+        LatenciasTransacciones::default()
+    }
+
+    /// Registra la latencia de una transaccion terminada en el histograma de su tipo
+    pub fn registrar(&mut self, tipo: CommitType, latencia: Duration) {
+        match tipo {
+            CommitType::SUMA => self.sumas.registrar(latencia),
+            CommitType::RESTA => self.restas.registrar(latencia),
+            CommitType::TRANSFERENCIA => self.transferencias.registrar(latencia),
+            CommitType::UNKNOWN => {}
+        }
+    }
+
+    /// Cantidad de latencias registradas entre todos los tipos
+    pub fn total(&self) -> u64 {
+        self.sumas.total() + self.restas.total() + self.transferencias.total()
+    }
+}
+
+#[cfg(test)]
+mod latencias_test {
+    use super::*;
+
+    #[test]
+    fn histograma_vacio_da_cero() {
+        let histograma = Histograma::new();
+        assert_eq!(0, histograma.total());
+        assert_eq!(Duration::ZERO, histograma.percentil(50));
+        assert_eq!(Duration::ZERO, histograma.percentil(99));
+    }
+
+    #[test]
+    fn percentiles_por_limite_del_intervalo() {
+        let mut histograma = Histograma::new();
+        for _ in 0..98 {
+            histograma.registrar(Duration::from_millis(3));
+        }
+        histograma.registrar(Duration::from_millis(150));
+        histograma.registrar(Duration::from_millis(700));
+
+        assert_eq!(100, histograma.total());
+        assert_eq!(Duration::from_millis(5), histograma.percentil(50));
+        assert_eq!(Duration::from_millis(200), histograma.percentil(99));
+        assert_eq!(Duration::from_millis(700), histograma.percentil(100));
+    }
+
+    #[test]
+    fn percentil_acotado_por_la_maxima() {
+        let mut histograma = Histograma::new();
+        histograma.registrar(Duration::from_millis(11));
+        assert_eq!(Duration::from_millis(11), histograma.percentil(50));
+    }
+
+    #[test]
+    fn latencias_por_tipo_de_transaccion() {
+        let mut latencias = LatenciasTransacciones::new();
+        latencias.registrar(CommitType::SUMA, Duration::from_millis(4));
+        latencias.registrar(CommitType::RESTA, Duration::from_millis(40));
+        latencias.registrar(CommitType::RESTA, Duration::from_millis(40));
+        latencias.registrar(CommitType::UNKNOWN, Duration::from_millis(40));
+
+        assert_eq!(1, latencias.sumas.total());
+        assert_eq!(2, latencias.restas.total());
+        assert_eq!(0, latencias.transferencias.total());
+        assert_eq!(3, latencias.total());
+    }
+
+    #[test]
+    fn latencias_mayores_al_ultimo_limite() {
+        let mut histograma = Histograma::new();
+        histograma.registrar(Duration::from_secs(300));
+        assert_eq!(Duration::from_secs(300), histograma.percentil(99));
+        assert_eq!(Duration::from_secs(300), histograma.maxima());
+    }
+}
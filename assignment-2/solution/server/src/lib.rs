@@ -1,9 +1,29 @@
+pub mod almacen;
+pub mod auditoria;
+pub mod bully;
 pub mod bully_listener;
 pub mod bully_messages;
+pub mod buzones;
 pub mod cafetera_listener;
+pub mod caos;
+pub mod comandos_admin;
 pub mod coordinador;
+pub mod cuentas_iniciales;
 pub mod error_server;
+pub mod estado_transaccion;
+pub mod eventos;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod latencias;
 pub mod mensaje;
 pub mod nodo;
 pub mod nodo_handler;
+pub mod pedidos_terminados;
+pub mod planificador;
+#[cfg(test)]
+pub mod prueba;
+pub mod registro_commits;
+pub mod registro_nodo;
+pub mod saldo;
+pub mod tpc;
 pub mod utils;
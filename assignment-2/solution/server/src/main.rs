@@ -1,59 +1,170 @@
+use actix::{Addr, System};
+use compartido::autenticacion::SIN_SECRETO;
+use compartido::config::config;
+use compartido::ids::IdNodo;
+use compartido::logs::init_tracing;
+use server::almacen::{abrir_almacen, AlmacenCuentas};
+use server::coordinador::{Apagar, Coordinador};
+use server::cuentas_iniciales::{cargar_cuentas, cuentas_por_defecto};
 use server::error_server::ErrorServer;
-use server::nodo::Nodo;
-use server::utils::ID_CORDINADOR_INICIAL;
-use server::{coordinador::Coordinador, utils::CANT_MAX_NODOS};
+use server::nodo::{AddAvisoDetencion, Nodo};
+use server::utils::ARG_CUENTAS;
+use std::sync::Arc;
 use std::{env, thread};
+use tiempo::{Reloj, RelojReal};
 use tokio::net::TcpListener;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
 
-async fn empezar_cordinador(listener: TcpListener) {
-    let _res = Coordinador::start_listener(listener).await;
+async fn empezar_cordinador(
+    listener: TcpListener,
+    id: IdNodo,
+    reloj: Arc<dyn Reloj>,
+    apagado: oneshot::Receiver<()>,
+) {
+    let _res = Coordinador::start_listener(listener, id, reloj, apagado).await;
 }
 
-async fn empezar_nodo(id: u8) {
-    let _res = Nodo::start(id, ID_CORDINADOR_INICIAL).await;
+async fn empezar_nodo(
+    id: IdNodo,
+    replica: bool,
+    almacen: Box<dyn AlmacenCuentas>,
+    reloj: Arc<dyn Reloj>,
+) -> Option<Addr<Nodo>> {
+    match Nodo::start(
+        id,
+        IdNodo(config().cluster.id_coordinador_inicial),
+        replica,
+        almacen,
+        reloj,
+    )
+    .await
+    {
+        Ok(nodo) => Some(nodo),
+        Err(err) => {
+            error!("[SYSTEM] Error al iniciar el nodo: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Espera a que el proceso reciba SIGINT (Ctrl-C) o SIGTERM y retorna el nombre de la senal
+async fn esperar_senal_de_apagado() -> Result<&'static str, ErrorServer> {
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = sigint.recv() => Ok("SIGINT"),
+        _ = sigterm.recv() => Ok("SIGTERM"),
+    }
+}
+
+/// Espera a que el nodo se detenga por su cuenta, al terminar de drenarse. Sin nodo no retorna
+async fn esperar_detencion(nodo: Option<Addr<Nodo>>) {
+    let nodo = match nodo {
+        Some(nodo) => nodo,
+        None => return std::future::pending().await,
+    };
+    let (aviso, detenido) = oneshot::channel();
+    nodo.do_send(AddAvisoDetencion { aviso });
+    let _res = detenido.await;
 }
 
 #[actix_rt::main]
 async fn main() -> Result<(), ErrorServer> {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        return Err(ErrorServer::new(
-            "Falta el id del nodo",
-            server::error_server::TipoError::ErrorArgs,
-        ));
+        return Err(ErrorServer::Argumentos("Falta el id del nodo".to_string()));
     }
 
-    let id: u8 = args[1].parse().map_err(|_| {
-        ErrorServer::new(
-            "El id del nodo debe ser un numero",
-            server::error_server::TipoError::ErrorArgs,
-        )
-    })?;
+    let id: IdNodo = args[1]
+        .parse()
+        .map_err(|_| ErrorServer::Argumentos("El id del nodo debe ser un numero".to_string()))?;
+    init_tracing(&format!("nodo-{}", id));
+
+    if !config().cluster.es_miembro(id.0) {
+        warn!("[SYSTEM] Rechazo conexion de ID_NODO = {:?}", id);
+        warn!(
+            "[SYSTEM] ID_NODO permitidos de 1 a {:?}",
+            config().cluster.cant_max_nodos
+        );
+        return Ok(());
+    }
+    if config().cluster.secreto_admin.is_empty() {
+        warn!(
+            "[SYSTEM] {}, se rechazan los comandos de administracion",
+            SIN_SECRETO
+        );
+    }
 
-    if !(1..=CANT_MAX_NODOS).contains(&id) {
-        println!("[SYSTEM] Rechazo conexion de ID_NODO = {:?}", id);
-        println!("[SYSTEM] ID_NODO permitidos de 1 a {:?}", CANT_MAX_NODOS);
+    // los nodos de cluster.replicas arrancan como replicas de solo lectura
+    let replica = config().cluster.es_replica(id.0);
+    // con "--cuentas archivo.json" el nodo arranca con las cuentas del archivo
+    let cuentas = match args.iter().position(|x| x == ARG_CUENTAS) {
+        Some(posicion) => {
+            let path = args.get(posicion + 1).ok_or_else(|| {
+                ErrorServer::Argumentos("Falta el archivo de cuentas".to_string())
+            })?;
+            cargar_cuentas(path)?
+        }
+        None => cuentas_por_defecto(),
+    };
+    if replica && id.0 == config().cluster.id_coordinador_inicial {
+        warn!(
+            "[SYSTEM] El ID_NODO = {:?} es el coordinador inicial, no puede ser replica",
+            id
+        );
         return Ok(());
     }
+    let almacen = abrir_almacen(id, cuentas)?;
 
-    let mut _coordinador = None;
-    let tcp_listener = Coordinador::create_listener(id).await?;
+    // todos los timeouts del nodo y del coordinador se miden con el reloj del sistema
+    let reloj: Arc<dyn Reloj> = Arc::new(RelojReal);
 
-    _coordinador = Some(thread::spawn(move || empezar_cordinador(tcp_listener)));
+    let mut _coordinador = None;
+    // una replica nunca puede ser elegida coordinador, no necesita escuchar a los nodos
+    if !replica {
+        let tcp_listener = Coordinador::create_listener(id).await?;
+        let reloj_coordinador = reloj.clone();
+        let (apagar, apagado) = oneshot::channel();
+        _coordinador = Some((
+            thread::spawn(move || empezar_cordinador(tcp_listener, id, reloj_coordinador, apagado)),
+            apagar,
+        ));
+    }
 
-    empezar_nodo(id).await;
+    let nodo = empezar_nodo(id, replica, almacen, reloj).await;
 
-    if let Some(coordinador) = _coordinador {
-        coordinador
+    // el proceso atiende mensajes hasta recibir SIGINT o SIGTERM, hasta que el nodo se retire (o hasta
+    // que se cierre el listener del coordinador)
+    let senal = esperar_senal_de_apagado();
+    tokio::pin!(senal);
+    let detencion = esperar_detencion(nodo.clone());
+    tokio::pin!(detencion);
+    if let Some((coordinador, apagar)) = _coordinador {
+        let coordinador = coordinador
             .join()
-            .map_err(|_| {
-                ErrorServer::new(
-                    "Error al esperar al coordinador",
-                    server::error_server::TipoError::ErrorJoinThreads,
-                )
-            })?
-            .await;
+            .map_err(|_| ErrorServer::Estado("Error al esperar al coordinador".to_string()))?;
+        tokio::pin!(coordinador);
+        tokio::select! {
+            _ = &mut coordinador => return Ok(()),
+            recibida = &mut senal => info!("[SYSTEM] Recibi {}, apago el nodo", recibida?),
+            _ = &mut detencion => info!("[SYSTEM] El nodo se retiro, apago el coordinador"),
+        }
+        // primero el coordinador deja de aceptar conexiones y cierra las de los nodos
+        let _res = apagar.send(());
+        coordinador.await;
+    } else {
+        tokio::select! {
+            recibida = &mut senal => info!("[SYSTEM] Recibi {}, apago el nodo", recibida?),
+            _ = &mut detencion => info!("[SYSTEM] El nodo se retiro"),
+        }
     }
 
+    // el nodo que se retiro ya se apago solo
+    if let Some(nodo) = nodo.filter(|nodo| nodo.connected()) {
+        nodo.send(Apagar).await?;
+    }
+    System::current().stop();
     Ok(())
 }
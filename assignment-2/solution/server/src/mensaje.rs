@@ -1,9 +1,14 @@
 use actix::Message;
+use compartido::codificable::{Campo, Codificable, Lector, TIPOS_COORDINADOR};
+use compartido::error_mensaje::ErrorMensaje;
+use compartido::ids::{IdCafetera, IdCuenta, IdNodo, IdTransaccion};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
-/// Mensajes que serán enviados entre el coordinador y los nodos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Mensajes que serán enviados entre el coordinador y los nodos, sus tipos empiezan en
+/// TIPOS_COORDINADOR para no confundirse con los de cafetera ni los del bully
 pub enum Mensaje {
-    STARTER,
+    STARTER = TIPOS_COORDINADOR as isize,
     PREPARE,
     YES,
     EXECUTE,
@@ -14,522 +19,630 @@ pub enum Mensaje {
     PING,
     OKEYABORT,
     DISCONNECT,
+    CONFIRMADO,
+    FALLIDO,
+    TRANSFERENCIA,
+    ALTA,
+    BAJA,
+    VALIDAR,
+    VALIDO,
+    PEDIDOSNAPSHOT,
+    SNAPSHOT,
+    RETIRO,
+    RECHAZO,
+    PEDIDOAUDITORIA,
+    AUDITORIA,
     UNKNOWN,
 }
 
 impl Mensaje {
+    /// Todos los tipos conocidos, en el orden de sus valores a partir de TIPOS_COORDINADOR. Es la
+    /// unica tabla que usan tanto el envio como la recepcion de mensajes
+    pub const TODOS: [Mensaje; 24] = [
+        Mensaje::STARTER,
+        Mensaje::PREPARE,
+        Mensaje::YES,
+        Mensaje::EXECUTE,
+        Mensaje::FINISH,
+        Mensaje::COMMIT,
+        Mensaje::OKEY,
+        Mensaje::ABORT,
+        Mensaje::PING,
+        Mensaje::OKEYABORT,
+        Mensaje::DISCONNECT,
+        Mensaje::CONFIRMADO,
+        Mensaje::FALLIDO,
+        Mensaje::TRANSFERENCIA,
+        Mensaje::ALTA,
+        Mensaje::BAJA,
+        Mensaje::VALIDAR,
+        Mensaje::VALIDO,
+        Mensaje::PEDIDOSNAPSHOT,
+        Mensaje::SNAPSHOT,
+        Mensaje::RETIRO,
+        Mensaje::RECHAZO,
+        Mensaje::PEDIDOAUDITORIA,
+        Mensaje::AUDITORIA,
+    ];
+
+    /// Interpreta el primer byte de un mensaje, los valores fuera del rango del coordinador (por
+    /// ejemplo los de cafetera o del bully) son UNKNOWN
     pub fn from_bytes(byte: u8) -> Mensaje {
-        match byte {
-            48_u8 => Mensaje::STARTER,
-            49_u8 => Mensaje::PREPARE,
-            50_u8 => Mensaje::YES,
-            51_u8 => Mensaje::EXECUTE,
-            52_u8 => Mensaje::FINISH,
-            53_u8 => Mensaje::COMMIT,
-            54_u8 => Mensaje::OKEY,
-            55_u8 => Mensaje::ABORT,
-            56_u8 => Mensaje::PING,
-            57_u8 => Mensaje::OKEYABORT,
-            58_u8 => Mensaje::DISCONNECT,
-            _ => Mensaje::UNKNOWN,
-        }
+        byte.checked_sub(TIPOS_COORDINADOR)
+            .and_then(|indice| Mensaje::TODOS.get(indice as usize))
+            .copied()
+            .unwrap_or(Mensaje::UNKNOWN)
     }
 
     pub fn to_bytes(&self) -> u8 {
-        match self {
-            Mensaje::STARTER => 0_u8,
-            Mensaje::PREPARE => 1_u8,
-            Mensaje::YES => 2_u8,
-            Mensaje::EXECUTE => 3_u8,
-            Mensaje::FINISH => 4_u8,
-            Mensaje::COMMIT => 5_u8,
-            Mensaje::OKEY => 6_u8,
-            Mensaje::ABORT => 7_u8,
-            Mensaje::PING => 8_u8,
-            Mensaje::OKEYABORT => 9_u8,
-            Mensaje::DISCONNECT => 10_u8,
-            Mensaje::UNKNOWN => 11_u8,
-        }
+        *self as u8
     }
 }
 
-pub trait MensajeBytes {
-    fn new(id_nodo: u8, id_cuenta: u32, id_transaccion: u32, id_cafetera: u8) -> Self;
-    fn get_tipo_mensaje(&self) -> u8;
-    fn get_id_nodo(&self) -> u8;
-    fn get_id_cuenta(&self) -> u32;
-    fn get_id_transaccion(&self) -> u32;
-    fn get_id_cafetera(&self) -> u8;
-
-    fn to_string(&self) -> String {
-        let mut result = String::new();
-        let tipo_mensaje = self.get_tipo_mensaje().to_string();
-        let id_nodo = self.get_id_nodo().to_string();
-        let id_cuenta = self.get_id_cuenta().to_string();
-        let id_transaccion = self.get_id_transaccion().to_string();
-        let id_cafetera = self.get_id_cafetera().to_string();
-
-        result.push_str(&tipo_mensaje);
-        result.push('-');
-        result.push_str(&id_nodo);
-        result.push('-');
-        result.push_str(&id_cuenta);
-        result.push('-');
-        result.push_str(&id_transaccion);
-        result.push('-');
-        result.push_str(&id_cafetera);
+/// Constructor y campos comunes de los mensajes entre los nodos y el coordinador
+pub trait MensajeBytes: Codificable {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> Self;
+    fn get_id_nodo(&self) -> IdNodo;
+    fn get_id_cuenta(&self) -> IdCuenta;
+    fn get_id_transaccion(&self) -> IdTransaccion;
+    fn get_id_cafetera(&self) -> IdCafetera;
+    fn get_id_pedido(&self) -> u32;
+    fn set_id_pedido(&mut self, id_pedido: u32);
 
-        result
+    /// Mensaje que solo lleva el nodo que lo envia, sin cuenta, transaccion ni cafetera
+    fn de_nodo(id_nodo: IdNodo) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new(
+            id_nodo,
+            IdCuenta::default(),
+            IdTransaccion::default(),
+            IdCafetera::default(),
+        )
     }
 
-    fn from_string(string: String) -> Self
+    /// Retorna el mensaje con el id de pedido de la sucursal que lo origino
+    fn con_id_pedido(mut self, id_pedido: u32) -> Self
     where
         Self: Sized,
     {
-        // split for "-" in string
-        let bytes: Vec<String> = string.split('-').map(|x| x.to_string()).collect();
-
-        let id_nodo: u8 = bytes[1].parse().expect("Error parsing id_nodo");
-        let id_cuenta: u32 = bytes[2].parse().expect("Error parsing id_cuenta");
-        let id_transaccion: u32 = bytes[3].parse().expect("Error parsing id_transaccion");
-        let id_cafetera: u8 = bytes[4].parse().expect("Error parsing id_cafetera");
-
-        Self::new(id_nodo, id_cuenta, id_transaccion, id_cafetera)
+        self.set_id_pedido(id_pedido);
+        self
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = Mensaje::STARTER)]
 ///Mensaje que envia un nodo al coordinador al comenzar un pedido del tipo Resta
 pub struct Starter {
-    /// Tipo de mensaje (Starter)
-    pub tipo_mensaje: u8,
     /// id del Nodo que inicia el pedido
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
     /// id de la cuenta de usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// id de la transaccion iniciada
-    pub id_transaccion: u32,
+    pub id_transaccion: IdTransaccion,
     /// id de la cafetera del nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+    /// pedido de administracion, se adelanta en la cola de la cuenta a las restas de las cafeteras
+    pub prioritaria: bool,
+}
+
+impl Starter {
+    /// Retorna el mensaje con la prioridad indicada
+    pub fn con_prioridad(mut self, prioritaria: bool) -> Starter {
+        self.prioritaria = prioritaria;
+        self
+    }
 }
 
 impl MensajeBytes for Starter {
-    fn new(id_nodo: u8, id_cuenta: u32, id_transaccion: u32, id_cafetera: u8) -> Starter {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> Starter {
         Starter {
-            tipo_mensaje: Mensaje::STARTER.to_bytes(),
             id_nodo,
             id_cuenta,
             id_transaccion,
             id_cafetera,
+            id_pedido: 0,
+            prioritaria: false,
         }
     }
 
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_nodo(&self) -> u8 {
+    fn get_id_nodo(&self) -> IdNodo {
         self.id_nodo
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
-    fn get_id_transaccion(&self) -> u32 {
+    fn get_id_transaccion(&self) -> IdTransaccion {
         self.id_transaccion
     }
 
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = Mensaje::PREPARE)]
 /// Mensaje enviado por el coordinador a todos los nodos al momento de recibir un Starter
 pub struct Prepare {
-    /// tipo de mensaje (prepare)
-    pub tipo_mensaje: u8,
     /// id del nodo que inicio el starter
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
     /// id de la cuenta de usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// id de la transaccion iniciada
-    pub id_transaccion: u32,
+    pub id_transaccion: IdTransaccion,
     /// id de la cafetera correspondiente al nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
 }
 
 impl MensajeBytes for Prepare {
-    fn new(id_nodo: u8, id_cuenta: u32, id_transaccion: u32, id_cafetera: u8) -> Prepare {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> Prepare {
         Prepare {
-            tipo_mensaje: Mensaje::PREPARE.to_bytes(),
             id_nodo,
             id_cuenta,
             id_transaccion,
             id_cafetera,
+            id_pedido: 0,
         }
     }
 
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_nodo(&self) -> u8 {
+    fn get_id_nodo(&self) -> IdNodo {
         self.id_nodo
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
-    fn get_id_transaccion(&self) -> u32 {
+    fn get_id_transaccion(&self) -> IdTransaccion {
         self.id_transaccion
     }
 
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
 }
 
 impl Prepare {
     pub fn from_start(starter: Starter) -> Prepare {
         Prepare {
-            tipo_mensaje: Mensaje::PREPARE.to_bytes(),
             id_nodo: starter.id_nodo,
             id_cuenta: starter.id_cuenta,
             id_transaccion: starter.id_transaccion,
             id_cafetera: starter.id_cafetera,
+            id_pedido: starter.id_pedido,
         }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = Mensaje::YES)]
 /// Mensaje enviado por los nodos al coordiandor luego de recibir un prepare
 pub struct Yes {
-    /// tipo de mensaje (yes)
-    pub tipo_mensaje: u8,
     /// id del nodo que comenzo la transaccion
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
     /// id de la cuenta de usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// id de la transaccion
-    pub id_transaccion: u32,
+    pub id_transaccion: IdTransaccion,
     /// id de la cafetera correspondiente al nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
 }
 
 impl MensajeBytes for Yes {
-    fn new(id_nodo: u8, id_cuenta: u32, id_transaccion: u32, id_cafetera: u8) -> Yes {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> Yes {
         Yes {
-            tipo_mensaje: Mensaje::YES.to_bytes(),
             id_nodo,
             id_cuenta,
             id_transaccion,
             id_cafetera,
+            id_pedido: 0,
         }
     }
 
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_nodo(&self) -> u8 {
+    fn get_id_nodo(&self) -> IdNodo {
         self.id_nodo
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
-    fn get_id_transaccion(&self) -> u32 {
+    fn get_id_transaccion(&self) -> IdTransaccion {
         self.id_transaccion
     }
 
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = Mensaje::EXECUTE)]
 /// Mensaje enviado del coordinador al nodo que envió el starter
 pub struct Execute {
-    /// tipode mensaje (execute)
-    pub tipo_mensaje: u8,
     /// id del nodo qeu envio el starter
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
     /// id de la cuenta de usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// id de la transaccion a realizar
-    pub id_transaccion: u32,
+    pub id_transaccion: IdTransaccion,
     /// id de la cafetera del nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
 }
 
 impl MensajeBytes for Execute {
-    fn new(id_nodo: u8, id_cuenta: u32, id_transaccion: u32, id_cafetera: u8) -> Execute {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> Execute {
         Execute {
-            tipo_mensaje: Mensaje::EXECUTE.to_bytes(),
             id_nodo,
             id_cuenta,
             id_transaccion,
             id_cafetera,
+            id_pedido: 0,
         }
     }
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_nodo(&self) -> u8 {
+    fn get_id_nodo(&self) -> IdNodo {
         self.id_nodo
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
-    fn get_id_transaccion(&self) -> u32 {
+    fn get_id_transaccion(&self) -> IdTransaccion {
         self.id_transaccion
     }
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = Mensaje::FINISH)]
 /// Mensaje enviado por el nodo que realizó el pedido una vez finalizado
 pub struct Finish {
-    /// tipo de mensaje (finish)
-    pub tipo_mensaje: u8,
     /// id del nodo que finalizo el pedido
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
     /// id dela cuenta de usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// id de latransaccion finalizada
-    pub id_transaccion: u32,
+    pub id_transaccion: IdTransaccion,
     /// tipo de pedido finalizado
     pub tipo: CommitType,
     /// cantidad de creditos implicados
     pub cantidad: u32,
     /// id de la cafetera del nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
 }
 
 impl Finish {
     pub fn new(
-        id_nodo: u8,
-        id_cuenta: u32,
-        id_transaccion: u32,
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
         tipo: CommitType,
         cantidad: u32,
-        id_cafetera: u8,
+        id_cafetera: IdCafetera,
     ) -> Finish {
         Finish {
-            tipo_mensaje: Mensaje::FINISH.to_bytes(),
             id_nodo,
             id_cuenta,
             id_transaccion,
             tipo,
             cantidad,
             id_cafetera,
+            id_pedido: 0,
         }
     }
 
-    pub fn f_to_string(&self) -> String {
-        let mut result = String::new();
-        let tipo_mensaje = self.get_tipo_mensaje().to_string();
-        let id_nodo = self.get_id_nodo().to_string();
-        let id_cuenta = self.get_id_cuenta().to_string();
-        let id_transaccion = self.get_id_transaccion().to_string();
-        let tipo = (self.tipo as u8).to_string();
-        let cantidad = self.cantidad.to_string();
-        let id_cafetera = self.id_cafetera.to_string();
-        result.push_str(&tipo_mensaje);
-        result.push('-');
-        result.push_str(&id_nodo);
-        result.push('-');
-        result.push_str(&id_cuenta);
-        result.push('-');
-        result.push_str(&id_transaccion);
-        result.push('-');
-        result.push_str(&tipo);
-        result.push('-');
-        result.push_str(&cantidad);
-        result.push('-');
-        result.push_str(&id_cafetera);
-        result
-    }
-
-    pub fn from_string(string: String) -> Finish {
-        // split for "-" in string
-        let bytes: Vec<String> = string.split('-').map(|x| x.to_string()).collect();
-
-        let id_nodo: u8 = bytes[1].parse().expect("Error al parsear id_nodo");
-        let id_cuenta: u32 = bytes[2].parse().expect("Error al parsear id_cuenta");
-        let id_transaccion: u32 = bytes[3].parse().expect("Error al parsear id_transaccion");
-        let tipo = CommitType::from_bytes(bytes[4].parse().expect("Error al parsear tipo"));
-        let cantidad: u32 = bytes[5].parse().expect("Error al parsear cantidad");
-        let id_cafetera: u8 = bytes[6].parse().expect("Error al parsear id_cafetera");
-
-        Finish::new(
-            id_nodo,
-            id_cuenta,
-            id_transaccion,
-            tipo,
-            cantidad,
-            id_cafetera,
-        )
-    }
-
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_nodo(&self) -> u8 {
-        self.id_nodo
-    }
-    fn get_id_cuenta(&self) -> u32 {
-        self.id_cuenta
-    }
-    fn get_id_transaccion(&self) -> u32 {
-        self.id_transaccion
+    /// Retorna el mensaje con el id de pedido de la sucursal que lo origino
+    pub fn con_id_pedido(mut self, id_pedido: u32) -> Finish {
+        self.id_pedido = id_pedido;
+        self
     }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = Mensaje::OKEY)]
 /// Mensaje enviado por los nodos al coordinador luego de recibir un commit
 pub struct OkeyToCoordinator {
-    /// tipo de mensaje (OkeyToCoordinator)
-    pub tipo_mensaje: u8,
     /// id del nodo que realizo el pedido
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
     /// id del usuario implicado
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// id de la transaccion commiteada
-    pub id_transaccion: u32,
+    pub id_transaccion: IdTransaccion,
     /// id dela cafetera del nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
 }
 
 impl MensajeBytes for OkeyToCoordinator {
-    fn new(id_nodo: u8, id_cuenta: u32, id_transaccion: u32, id_cafetera: u8) -> OkeyToCoordinator {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> OkeyToCoordinator {
         OkeyToCoordinator {
-            tipo_mensaje: Mensaje::OKEY.to_bytes(),
             id_nodo,
             id_cuenta,
             id_transaccion,
             id_cafetera,
+            id_pedido: 0,
         }
     }
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_nodo(&self) -> u8 {
+    fn get_id_nodo(&self) -> IdNodo {
         self.id_nodo
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
-    fn get_id_transaccion(&self) -> u32 {
+    fn get_id_transaccion(&self) -> IdTransaccion {
         self.id_transaccion
     }
 
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = Mensaje::OKEYABORT)]
 /// Mensaje enviado por los nodos al coordinador luego de recibir un abort
 pub struct OkeyAbortToCoordinator {
-    /// tipo de mensaje (OkeyAbortToCoordinator)
-    pub tipo_mensaje: u8,
     /// id del nodo que realizaba el pedido
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
     /// id del usuario implicado
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// id de la transaccion abortada
-    pub id_transaccion: u32,
+    pub id_transaccion: IdTransaccion,
     /// id de la cafetera del nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
 }
 
 impl MensajeBytes for OkeyAbortToCoordinator {
     fn new(
-        id_nodo: u8,
-        id_cuenta: u32,
-        id_transaccion: u32,
-        id_cafetera: u8,
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
     ) -> OkeyAbortToCoordinator {
         OkeyAbortToCoordinator {
-            tipo_mensaje: Mensaje::OKEYABORT.to_bytes(),
             id_nodo,
             id_cuenta,
             id_transaccion,
             id_cafetera,
+            id_pedido: 0,
         }
     }
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_nodo(&self) -> u8 {
+    fn get_id_nodo(&self) -> IdNodo {
         self.id_nodo
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
-    fn get_id_transaccion(&self) -> u32 {
+    fn get_id_transaccion(&self) -> IdTransaccion {
         self.id_transaccion
     }
 
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = Mensaje::ABORT)]
 /// Mensaje abort
 pub struct Abort {
-    /// tipo de mensaje (abort)
-    pub tipo_mensaje: u8,
     /// id del nodo que realizaba el pedido
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
     /// id del usuario implicado
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// id de la transaccion
-    pub id_transaccion: u32,
+    pub id_transaccion: IdTransaccion,
     /// id de la cafetera del nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+    /// motivo por el que se aborta la transaccion
+    pub motivo: MotivoAbort,
+    /// numero de secuencia global asignado por el coordinador al difundirlo, 0 si no se difundio
+    pub secuencia: u64,
+}
+
+impl Abort {
+    /// Retorna el abort con el motivo indicado
+    pub fn con_motivo(mut self, motivo: MotivoAbort) -> Abort {
+        self.motivo = motivo;
+        self
+    }
+
+    /// Retorna el abort con el numero de secuencia global indicado
+    pub fn con_secuencia(mut self, secuencia: u64) -> Abort {
+        self.secuencia = secuencia;
+        self
+    }
 }
 
 impl MensajeBytes for Abort {
-    fn new(id_nodo: u8, id_cuenta: u32, id_transaccion: u32, id_cafetera: u8) -> Abort {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> Abort {
         Abort {
-            tipo_mensaje: Mensaje::ABORT.to_bytes(),
             id_nodo,
             id_cuenta,
             id_transaccion,
             id_cafetera,
+            id_pedido: 0,
+            motivo: MotivoAbort::GENERICO,
+            secuencia: 0,
         }
     }
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
-    }
-    fn get_id_nodo(&self) -> u8 {
+
+    fn get_id_nodo(&self) -> IdNodo {
         self.id_nodo
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
-    fn get_id_transaccion(&self) -> u32 {
+    fn get_id_transaccion(&self) -> IdTransaccion {
         self.id_transaccion
     }
 
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::TRANSFERENCIA)]
+/// Mensaje de transferencia entre dos cuentas. El nodo que recibio el pedido lo envia al coordinador
+/// (como el Starter de una resta) y el coordinador lo reenvia a todos los nodos como prepare, para que
+/// bloqueen ambas cuentas
+pub struct Transferencia {
+    /// id del nodo que inicio la transferencia
+    pub id_nodo: IdNodo,
+    /// id de la cuenta de la que se restan los creditos
+    pub id_cuenta: IdCuenta,
+    /// id de la transaccion
+    pub id_transaccion: IdTransaccion,
+    /// id de la cafetera del nodo
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+    /// id de la cuenta a la que se suman los creditos
+    pub id_cuenta_destino: IdCuenta,
+    /// pedido de administracion (una correccion de saldo), se adelanta en la cola de ambas cuentas
+    pub prioritaria: bool,
+}
+
+impl Transferencia {
+    pub fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+        id_cuenta_destino: IdCuenta,
+    ) -> Transferencia {
+        Transferencia {
+            id_nodo,
+            id_cuenta,
+            id_transaccion,
+            id_cafetera,
+            id_pedido: 0,
+            id_cuenta_destino,
+            prioritaria: false,
+        }
+    }
+
+    /// Retorna el mensaje con el id de pedido de la sucursal que lo origino
+    pub fn con_id_pedido(mut self, id_pedido: u32) -> Transferencia {
+        self.id_pedido = id_pedido;
+        self
+    }
+
+    /// Retorna el mensaje con la prioridad indicada
+    pub fn con_prioridad(mut self, prioritaria: bool) -> Transferencia {
+        self.prioritaria = prioritaria;
+        self
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CommitType {
     SUMA = 0,
     RESTA = 1,
-    UNKNOWN = 2,
+    TRANSFERENCIA = 2,
+    UNKNOWN = 3,
 }
 
 impl CommitType {
@@ -537,7 +650,8 @@ impl CommitType {
         match self {
             CommitType::SUMA => 0,
             CommitType::RESTA => 1,
-            CommitType::UNKNOWN => 2,
+            CommitType::TRANSFERENCIA => 2,
+            CommitType::UNKNOWN => 3,
         }
     }
 
@@ -545,150 +659,851 @@ impl CommitType {
         match bytes {
             0 => CommitType::SUMA,
             1 => CommitType::RESTA,
+            2 => CommitType::TRANSFERENCIA,
             _ => CommitType::UNKNOWN,
         }
     }
 }
 
-#[derive(Message, Debug, Clone)]
+/// Se codifica en un byte, los valores desconocidos se leen como UNKNOWN
+impl Campo for CommitType {
+    fn escribir(&self, bytes: &mut Vec<u8>) {
+        self.to_bytes().escribir(bytes);
+    }
+
+    fn leer(lector: &mut Lector<'_>, campo: &'static str) -> Result<Self, ErrorMensaje> {
+        Ok(CommitType::from_bytes(lector.leer(campo)?))
+    }
+}
+
+/// Motivo por el que se aborta una transaccion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MotivoAbort {
+    /// error en la preparacion, desconexion del nodo o transaccion vencida
+    GENERICO = 0,
+    /// la cuenta no tiene saldo suficiente para la resta
+    SALDOINSUFICIENTE = 1,
+    /// la operacion dejaria a la cuenta por encima de saldo_maximo
+    SALDOMAXIMO = 2,
+}
+
+impl MotivoAbort {
+    pub fn to_bytes(&self) -> u8 {
+        match self {
+            MotivoAbort::GENERICO => 0,
+            MotivoAbort::SALDOINSUFICIENTE => 1,
+            MotivoAbort::SALDOMAXIMO => 2,
+        }
+    }
+
+    pub fn from_bytes(bytes: u8) -> MotivoAbort {
+        match bytes {
+            1 => MotivoAbort::SALDOINSUFICIENTE,
+            2 => MotivoAbort::SALDOMAXIMO,
+            _ => MotivoAbort::GENERICO,
+        }
+    }
+}
+
+/// Se codifica en un byte, los valores desconocidos se leen como GENERICO
+impl Campo for MotivoAbort {
+    fn escribir(&self, bytes: &mut Vec<u8>) {
+        self.to_bytes().escribir(bytes);
+    }
+
+    fn leer(lector: &mut Lector<'_>, campo: &'static str) -> Result<Self, ErrorMensaje> {
+        Ok(MotivoAbort::from_bytes(lector.leer(campo)?))
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
+#[codificable(tipo = Mensaje::COMMIT)]
 /// Mensaje commit, enviado de un coordinador a sus nodos
 pub struct Commit {
-    /// tipo de mensaje (commit)
-    pub tipo_mensaje: u8,
     /// id del nodo que realizo la operacion
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
     /// id del usuario
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
     /// id de la transaccion commiteda
-    pub id_transaccion: u32,
+    pub id_transaccion: IdTransaccion,
     /// tipo de operacion realizada
     pub tipo: CommitType,
     /// cantidad de creditos implicados
     pub cantidad: u32,
     /// id de la cafetera del nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+    /// numero de secuencia global asignado por el coordinador al difundirlo, 0 si no se difundio
+    pub secuencia: u64,
 }
 
 impl Commit {
     pub fn new(
-        id_nodo: u8,
-        id_cuenta: u32,
-        id_transaccion: u32,
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
         tipo: CommitType,
         cantidad: u32,
-        id_cafetera: u8,
+        id_cafetera: IdCafetera,
     ) -> Commit {
         Commit {
-            tipo_mensaje: Mensaje::COMMIT.to_bytes(),
             id_nodo,
             id_cuenta,
             id_transaccion,
             tipo,
             cantidad,
             id_cafetera,
+            id_pedido: 0,
+            secuencia: 0,
         }
     }
 
-    pub fn set_to_string(&self) -> String {
-        let mut result = String::new();
-        let tipo_mensaje = self.get_tipo_mensaje().to_string();
-        let id_nodo = self.get_id_nodo().to_string();
-        let id_cuenta = self.get_id_cuenta().to_string();
-        let id_transaccion = self.get_id_transaccion().to_string();
-        let tipo = (self.tipo as u8).to_string();
-        let cantidad = self.cantidad.to_string();
-        let id_cafetera = self.id_cafetera.to_string();
-        result.push_str(&tipo_mensaje);
-        result.push('-');
-        result.push_str(&id_nodo);
-        result.push('-');
-        result.push_str(&id_cuenta);
-        result.push('-');
-        result.push_str(&id_transaccion);
-        result.push('-');
-        result.push_str(&tipo);
-        result.push('-');
-        result.push_str(&cantidad);
-        result.push('-');
-        result.push_str(&id_cafetera);
-        result
-    }
-
-    pub fn from_string(string: String) -> Commit {
-        // split for "-" in string
-        let bytes: Vec<String> = string.split('-').map(|x| x.to_string()).collect();
-
-        let id_nodo: u8 = bytes[1].parse().expect("Error parsing id_nodo");
-        let id_cuenta: u32 = bytes[2].parse().expect("Error parsing id_cuenta");
-        let id_transaccion: u32 = bytes[3].parse().expect("Error parsing id_transaccion");
-        let tipo = CommitType::from_bytes(bytes[4].parse().expect("Error parsing tipo"));
-        let cantidad: u32 = bytes[5].parse().expect("Error parsing cantidad");
-        let id_cafetera: u8 = bytes[6].parse().expect("Error parsing id_cafetera");
-
-        Commit::new(
+    /// Retorna el mensaje con el id de pedido de la sucursal que lo origino
+    pub fn con_id_pedido(mut self, id_pedido: u32) -> Commit {
+        self.id_pedido = id_pedido;
+        self
+    }
+
+    /// Retorna el commit con el numero de secuencia global indicado
+    pub fn con_secuencia(mut self, secuencia: u64) -> Commit {
+        self.secuencia = secuencia;
+        self
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::PING)]
+///Mensaje ping enviado de un nodo a un coordinador para validar coneccion
+pub struct PingCord {
+    /// id del nodo que envia ping
+    pub id_nodo: IdNodo,
+    pub id_cuenta: IdCuenta,
+    pub id_transaccion: IdTransaccion,
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+}
+
+impl MensajeBytes for PingCord {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> PingCord {
+        PingCord {
+            id_nodo,
+            id_cuenta,
+            id_transaccion,
+            id_cafetera,
+            id_pedido: 0,
+        }
+    }
+    fn get_id_nodo(&self) -> IdNodo {
+        self.id_nodo
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_id_transaccion(&self) -> IdTransaccion {
+        self.id_transaccion
+    }
+
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::CONFIRMADO)]
+/// Mensaje enviado por el coordinador al nodo que inicio una suma una vez que todos
+/// los nodos confirmaron el commit
+pub struct ConfirmadoToNodo {
+    /// id del nodo que inicio la transaccion
+    pub id_nodo: IdNodo,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+    /// id de la transaccion confirmada
+    pub id_transaccion: IdTransaccion,
+    /// id de la cafetera del nodo
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+}
+
+impl MensajeBytes for ConfirmadoToNodo {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> ConfirmadoToNodo {
+        ConfirmadoToNodo {
+            id_nodo,
+            id_cuenta,
+            id_transaccion,
+            id_cafetera,
+            id_pedido: 0,
+        }
+    }
+    fn get_id_nodo(&self) -> IdNodo {
+        self.id_nodo
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_id_transaccion(&self) -> IdTransaccion {
+        self.id_transaccion
+    }
+
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::FALLIDO)]
+/// Mensaje enviado por el coordinador al nodo que inicio una suma una vez que todos
+/// los nodos confirmaron el abort
+pub struct FallidoToNodo {
+    /// id del nodo que inicio la transaccion
+    pub id_nodo: IdNodo,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+    /// id de la transaccion abortada
+    pub id_transaccion: IdTransaccion,
+    /// id de la cafetera del nodo
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+}
+
+impl MensajeBytes for FallidoToNodo {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> FallidoToNodo {
+        FallidoToNodo {
             id_nodo,
             id_cuenta,
             id_transaccion,
-            tipo,
-            cantidad,
             id_cafetera,
+            id_pedido: 0,
+        }
+    }
+    fn get_id_nodo(&self) -> IdNodo {
+        self.id_nodo
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_id_transaccion(&self) -> IdTransaccion {
+        self.id_transaccion
+    }
+
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
+}
+
+#[cfg(test)]
+mod mensaje_test {
+    use compartido::codec::Codec;
+    use compartido::codificable::Codificable;
+    use compartido::error_mensaje::ErrorMensaje;
+    use compartido::ids::{IdCafetera, IdCuenta, IdNodo, IdTransaccion};
+    use compartido::mensajes_cafetera::{MensajeCafeteraBytes, Restar};
+
+    use super::{
+        Abort, AltaCord, Auditoria, BajaCord, Commit, CommitType, ConfirmadoToNodo, Execute,
+        FallidoToNodo, Finish, Mensaje, MensajeBytes, MotivoAbort, OkeyAbortToCoordinator,
+        OkeyToCoordinator, PedidoAuditoria, PedidoSnapshot, PingCord, Prepare, Rechazo, Retiro,
+        Snapshot, Starter, Transferencia, Validar, Valido, Yes,
+    };
+    use crate::coordinador::Disconnect;
+
+    /// Codifica y decodifica el mensaje con cada codec, verificando que su primer byte se interprete
+    /// como su tipo y que con CBOR se reciban los mismos campos que con bytes
+    fn ida_y_vuelta<M: Codificable>(mensaje: &M, tipo: Mensaje) -> M {
+        let bytes = mensaje.codificar(Codec::Bytes);
+        let cbor = mensaje.codificar(Codec::Cbor);
+        for codificado in [&bytes, &cbor] {
+            assert_eq!(tipo, Mensaje::from_bytes(codificado[0]));
+        }
+        let recibido_cbor = M::decodificar(&cbor, Codec::Cbor).unwrap();
+        assert_eq!(cbor, recibido_cbor.codificar(Codec::Cbor));
+        assert_eq!(bytes, recibido_cbor.codificar(Codec::Bytes));
+        M::decodificar(&bytes, Codec::Bytes).unwrap()
+    }
+
+    fn campos_comunes_ida_y_vuelta<M: MensajeBytes>(tipo: Mensaje) {
+        let mensaje = M::new(IdNodo(3), IdCuenta(5), IdTransaccion(2301), IdCafetera(2))
+            .con_id_pedido(2000015);
+        let recibido = ida_y_vuelta(&mensaje, tipo);
+
+        assert_eq!(IdNodo(3), recibido.get_id_nodo());
+        assert_eq!(IdCuenta(5), recibido.get_id_cuenta());
+        assert_eq!(IdTransaccion(2301), recibido.get_id_transaccion());
+        assert_eq!(IdCafetera(2), recibido.get_id_cafetera());
+        assert_eq!(2000015, recibido.get_id_pedido());
+    }
+
+    #[test]
+    fn cada_tipo_se_interpreta_como_el_mismo_tipo() {
+        for tipo in Mensaje::TODOS {
+            assert_eq!(tipo, Mensaje::from_bytes(tipo.to_bytes()));
+        }
+        // los tipos de cafetera, del bully y los que no existen no son del coordinador
+        for byte in [0, 31, Mensaje::UNKNOWN.to_bytes(), 64, 255] {
+            assert_eq!(Mensaje::UNKNOWN, Mensaje::from_bytes(byte));
+        }
+    }
+
+    #[test]
+    fn mensajes_con_campos_comunes_ida_y_vuelta() {
+        campos_comunes_ida_y_vuelta::<Starter>(Mensaje::STARTER);
+        campos_comunes_ida_y_vuelta::<Prepare>(Mensaje::PREPARE);
+        campos_comunes_ida_y_vuelta::<Yes>(Mensaje::YES);
+        campos_comunes_ida_y_vuelta::<Execute>(Mensaje::EXECUTE);
+        campos_comunes_ida_y_vuelta::<OkeyToCoordinator>(Mensaje::OKEY);
+        campos_comunes_ida_y_vuelta::<OkeyAbortToCoordinator>(Mensaje::OKEYABORT);
+        campos_comunes_ida_y_vuelta::<Abort>(Mensaje::ABORT);
+        campos_comunes_ida_y_vuelta::<PingCord>(Mensaje::PING);
+        campos_comunes_ida_y_vuelta::<ConfirmadoToNodo>(Mensaje::CONFIRMADO);
+        campos_comunes_ida_y_vuelta::<FallidoToNodo>(Mensaje::FALLIDO);
+        campos_comunes_ida_y_vuelta::<Retiro>(Mensaje::RETIRO);
+        campos_comunes_ida_y_vuelta::<AltaCord>(Mensaje::ALTA);
+        campos_comunes_ida_y_vuelta::<BajaCord>(Mensaje::BAJA);
+        campos_comunes_ida_y_vuelta::<Valido>(Mensaje::VALIDO);
+    }
+
+    #[test]
+    fn mensajes_con_campos_propios_ida_y_vuelta() {
+        let finish = Finish::new(
+            IdNodo(2),
+            IdCuenta(5),
+            IdTransaccion(2301),
+            CommitType::RESTA,
+            150,
+            IdCafetera(3),
+        )
+        .con_id_pedido(7);
+        let recibido = ida_y_vuelta(&finish, Mensaje::FINISH);
+        assert_eq!(
+            (IdCuenta(5), 150, 7),
+            (recibido.id_cuenta, recibido.cantidad, recibido.id_pedido)
+        );
+        assert_eq!(CommitType::RESTA.to_bytes(), recibido.tipo.to_bytes());
+
+        let transferencia = Transferencia::new(
+            IdNodo(2),
+            IdCuenta(5),
+            IdTransaccion(2301),
+            IdCafetera(3),
+            IdCuenta(6),
+        )
+        .con_id_pedido(7)
+        .con_prioridad(true);
+        let recibido = ida_y_vuelta(&transferencia, Mensaje::TRANSFERENCIA);
+        assert_eq!(
+            (IdCuenta(5), IdCuenta(6), 7, true),
+            (
+                recibido.id_cuenta,
+                recibido.id_cuenta_destino,
+                recibido.id_pedido,
+                recibido.prioritaria
+            )
+        );
+
+        let starter = Starter::new(IdNodo(2), IdCuenta(5), IdTransaccion(2301), IdCafetera(3))
+            .con_prioridad(true);
+        assert!(ida_y_vuelta(&starter, Mensaje::STARTER).prioritaria);
+
+        let validar = Validar::new(
+            IdNodo(2),
+            IdCuenta(5),
+            IdTransaccion(2301),
+            CommitType::SUMA,
+            150,
+            IdCafetera(3),
+        )
+        .con_id_pedido(7);
+        let recibido = ida_y_vuelta(&validar, Mensaje::VALIDAR);
+        assert_eq!(
+            (IdCuenta(5), 150, 7),
+            (recibido.id_cuenta, recibido.cantidad, recibido.id_pedido)
+        );
+
+        let pedido = ida_y_vuelta(&PedidoSnapshot::new(IdNodo(2), 4), Mensaje::PEDIDOSNAPSHOT);
+        assert_eq!((IdNodo(2), 4), (pedido.id_nodo, pedido.corte));
+
+        let pedido = ida_y_vuelta(&PedidoAuditoria::new(3), Mensaje::PEDIDOAUDITORIA);
+        assert_eq!(3, pedido.id_auditoria);
+        let auditoria = ida_y_vuelta(
+            &Auditoria::new(IdNodo(2), 3, vec![(IdCuenta(7), 100), (IdCuenta(8), 5)]),
+            Mensaje::AUDITORIA,
+        );
+        assert_eq!(
+            vec![(IdCuenta(7), 100), (IdCuenta(8), 5)],
+            auditoria.cuentas
+        );
+
+        ida_y_vuelta(&Disconnect, Mensaje::DISCONNECT);
+    }
+
+    #[test]
+    fn commit_ida_y_vuelta() {
+        let commit = Commit::new(
+            IdNodo(2),
+            IdCuenta(5),
+            IdTransaccion(2301),
+            CommitType::SUMA,
+            150,
+            IdCafetera(3),
         )
+        .con_id_pedido(2000015)
+        .con_secuencia(9);
+        let recibido = ida_y_vuelta(&commit, Mensaje::COMMIT);
+
+        assert_eq!(IdCuenta(5), recibido.id_cuenta);
+        assert_eq!(150, recibido.cantidad);
+        assert_eq!(2000015, recibido.id_pedido);
+        assert_eq!(9, recibido.secuencia);
     }
 
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
+    #[test]
+    fn abort_y_snapshot_ida_y_vuelta() {
+        let abort = Abort::new(IdNodo(1), IdCuenta(5), IdTransaccion(100), IdCafetera(2))
+            .con_motivo(MotivoAbort::SALDOMAXIMO);
+        let recibido = ida_y_vuelta(&abort, Mensaje::ABORT);
+        assert_eq!(MotivoAbort::SALDOMAXIMO, recibido.motivo);
+
+        let snapshot = Snapshot::new(IdNodo(2), 4, vec![(IdCuenta(1), 100), (IdCuenta(2), 50)]);
+        let recibido = ida_y_vuelta(&snapshot, Mensaje::SNAPSHOT);
+        assert_eq!(
+            vec![(IdCuenta(1), 100), (IdCuenta(2), 50)],
+            recibido.cuentas
+        );
+
+        let rechazo = Rechazo::new(IdNodo(2), IdNodo(3), Mensaje::STARTER.to_bytes());
+        let recibido = ida_y_vuelta(&rechazo, Mensaje::RECHAZO);
+        assert_eq!(IdNodo(3), recibido.id_nodo_mensaje);
+        assert!(matches!(
+            Mensaje::from_bytes(recibido.tipo),
+            Mensaje::STARTER
+        ));
     }
-    fn get_id_nodo(&self) -> u8 {
+
+    #[test]
+    fn mensaje_incompleto_o_de_otro_tipo_retorna_error() {
+        let starter =
+            Starter::new(IdNodo(1), IdCuenta(5), IdTransaccion(100), IdCafetera(0)).to_bytes();
+        assert_eq!(
+            Err(ErrorMensaje::Incompleto("id_transaccion")),
+            Starter::from_bytes(&starter[..7]).map(|x| x.id_cuenta)
+        );
+        // un mensaje de cafetera nunca se confunde con uno del coordinador
+        assert_eq!(
+            Err(ErrorMensaje::CampoInvalido("tipo_mensaje")),
+            Starter::from_bytes(&Restar::new(IdCafetera(1), IdCuenta(5), 100).to_bytes())
+                .map(|x| x.id_cuenta)
+        );
+        assert!(Snapshot::from_bytes(
+            &Snapshot::new(IdNodo(2), 4, vec![(IdCuenta(1), 100)]).to_bytes()[..15]
+        )
+        .is_err());
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::RETIRO)]
+/// Mensaje enviado por un nodo que termino de drenarse, para que el coordinador deje de contarlo
+/// sin guardarle los COMMIT/ABORT que se pierda
+pub struct Retiro {
+    /// id del nodo que se retira
+    pub id_nodo: IdNodo,
+    /// no se usa
+    pub id_cuenta: IdCuenta,
+    /// no se usa
+    pub id_transaccion: IdTransaccion,
+    /// no se usa
+    pub id_cafetera: IdCafetera,
+    /// no se usa
+    pub id_pedido: u32,
+}
+
+impl MensajeBytes for Retiro {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> Retiro {
+        Retiro {
+            id_nodo,
+            id_cuenta,
+            id_transaccion,
+            id_cafetera,
+            id_pedido: 0,
+        }
+    }
+    fn get_id_nodo(&self) -> IdNodo {
         self.id_nodo
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
-    fn get_id_transaccion(&self) -> u32 {
+    fn get_id_transaccion(&self) -> IdTransaccion {
         self.id_transaccion
     }
+
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
 #[rtype(result = "()")]
-///Mensaje ping enviado de un nodo a un coordinador para validar coneccion
-pub struct PingCord {
-    /// tipo de mensaje (PingCord)
-    pub tipo_mensaje: u8,
-    /// id del nodo que envia ping
-    pub id_nodo: u8,
-    pub id_cuenta: u32,
-    pub id_transaccion: u32,
-    pub id_cafetera: u8,
+#[codificable(tipo = Mensaje::RECHAZO)]
+/// Error de protocolo que el coordinador le responde a un nodo cuando rechaza su mensaje, porque el
+/// id_nodo del mensaje no es el que se registro en la conexion o no es un nodo del cluster
+pub struct Rechazo {
+    /// id del nodo con el que se registro la conexion, 0 si se rechazo la conexion
+    pub id_nodo: IdNodo,
+    /// id del nodo que traia el mensaje rechazado
+    pub id_nodo_mensaje: IdNodo,
+    /// tipo del mensaje rechazado, UNKNOWN si se rechazo la conexion
+    pub tipo: u8,
 }
 
-impl MensajeBytes for PingCord {
-    fn new(id_nodo: u8, id_cuenta: u32, id_transaccion: u32, id_cafetera: u8) -> PingCord {
-        PingCord {
-            tipo_mensaje: Mensaje::PING.to_bytes(),
+impl Rechazo {
+    pub fn new(id_nodo: IdNodo, id_nodo_mensaje: IdNodo, tipo: u8) -> Rechazo {
+        Rechazo {
+            id_nodo,
+            id_nodo_mensaje,
+            tipo,
+        }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::ALTA)]
+/// Mensaje de alta de una cuenta. El nodo que lo recibe de la herramienta de administracion lo envia
+/// al coordinador, y este lo reenvia a todos los nodos para que creen la cuenta con saldo_inicial
+pub struct AltaCord {
+    /// id del nodo que recibio el pedido de alta
+    pub id_nodo: IdNodo,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+    /// no se usa, las altas no son transacciones
+    pub id_transaccion: IdTransaccion,
+    /// id de la cafetera (o herramienta de administracion) que pidio la alta
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+}
+
+impl MensajeBytes for AltaCord {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> AltaCord {
+        AltaCord {
             id_nodo,
             id_cuenta,
             id_transaccion,
             id_cafetera,
+            id_pedido: 0,
         }
     }
-    fn get_tipo_mensaje(&self) -> u8 {
-        self.tipo_mensaje
+    fn get_id_nodo(&self) -> IdNodo {
+        self.id_nodo
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_id_transaccion(&self) -> IdTransaccion {
+        self.id_transaccion
+    }
+
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
     }
-    fn get_id_nodo(&self) -> u8 {
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::BAJA)]
+/// Mensaje de baja de una cuenta. El nodo que lo recibe de la herramienta de administracion lo envia
+/// al coordinador, y este lo reenvia a todos los nodos si la cuenta no esta en uso
+pub struct BajaCord {
+    /// id del nodo que recibio el pedido de baja
+    pub id_nodo: IdNodo,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+    /// no se usa, las bajas no son transacciones
+    pub id_transaccion: IdTransaccion,
+    /// id de la cafetera (o herramienta de administracion) que pidio la baja
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+}
+
+impl MensajeBytes for BajaCord {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> BajaCord {
+        BajaCord {
+            id_nodo,
+            id_cuenta,
+            id_transaccion,
+            id_cafetera,
+            id_pedido: 0,
+        }
+    }
+    fn get_id_nodo(&self) -> IdNodo {
+        self.id_nodo
+    }
+    fn get_id_cuenta(&self) -> IdCuenta {
+        self.id_cuenta
+    }
+    fn get_id_transaccion(&self) -> IdTransaccion {
+        self.id_transaccion
+    }
+
+    fn get_id_cafetera(&self) -> IdCafetera {
+        self.id_cafetera
+    }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::VALIDAR)]
+/// Mensaje enviado por el coordinador a todos los nodos al recibir el Finish de una resta o
+/// transferencia, para que cada nodo valide que puede aplicar el cambio de saldo antes del Commit
+pub struct Validar {
+    /// id del nodo que realizo la operacion
+    pub id_nodo: IdNodo,
+    /// id del usuario
+    pub id_cuenta: IdCuenta,
+    /// id de la transaccion a validar
+    pub id_transaccion: IdTransaccion,
+    /// tipo de operacion a validar
+    pub tipo: CommitType,
+    /// cantidad de creditos implicados
+    pub cantidad: u32,
+    /// id de la cafetera del nodo
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+}
+
+impl Validar {
+    pub fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        tipo: CommitType,
+        cantidad: u32,
+        id_cafetera: IdCafetera,
+    ) -> Validar {
+        Validar {
+            id_nodo,
+            id_cuenta,
+            id_transaccion,
+            tipo,
+            cantidad,
+            id_cafetera,
+            id_pedido: 0,
+        }
+    }
+
+    /// Retorna el mensaje con el id de pedido de la sucursal que lo origino
+    pub fn con_id_pedido(mut self, id_pedido: u32) -> Validar {
+        self.id_pedido = id_pedido;
+        self
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::VALIDO)]
+/// Mensaje enviado por los nodos al coordinador cuando pueden aplicar el cambio de saldo de un Validar
+pub struct Valido {
+    /// id del nodo que valido la transaccion
+    pub id_nodo: IdNodo,
+    /// id de la cuenta de usuario
+    pub id_cuenta: IdCuenta,
+    /// id de la transaccion
+    pub id_transaccion: IdTransaccion,
+    /// id de la cafetera correspondiente al nodo
+    pub id_cafetera: IdCafetera,
+    /// id del pedido de la sucursal que origino el mensaje
+    pub id_pedido: u32,
+}
+
+impl MensajeBytes for Valido {
+    fn new(
+        id_nodo: IdNodo,
+        id_cuenta: IdCuenta,
+        id_transaccion: IdTransaccion,
+        id_cafetera: IdCafetera,
+    ) -> Valido {
+        Valido {
+            id_nodo,
+            id_cuenta,
+            id_transaccion,
+            id_cafetera,
+            id_pedido: 0,
+        }
+    }
+
+    fn get_id_nodo(&self) -> IdNodo {
         self.id_nodo
     }
-    fn get_id_cuenta(&self) -> u32 {
+    fn get_id_cuenta(&self) -> IdCuenta {
         self.id_cuenta
     }
-    fn get_id_transaccion(&self) -> u32 {
+    fn get_id_transaccion(&self) -> IdTransaccion {
         self.id_transaccion
     }
 
-    fn get_id_cafetera(&self) -> u8 {
+    fn get_id_cafetera(&self) -> IdCafetera {
         self.id_cafetera
     }
+    fn get_id_pedido(&self) -> u32 {
+        self.id_pedido
+    }
+    fn set_id_pedido(&mut self, id_pedido: u32) {
+        self.id_pedido = id_pedido;
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::PEDIDOSNAPSHOT)]
+/// Mensaje enviado por el coordinador a un nodo al día para que le envie sus cuentas a un nodo que
+/// se esta uniendo
+pub struct PedidoSnapshot {
+    /// id del nodo que se esta uniendo y va a recibir el snapshot
+    pub id_nodo: IdNodo,
+    /// cantidad de COMMIT/ABORT difundidos por el coordinador al momento del pedido
+    pub corte: u64,
+}
+
+impl PedidoSnapshot {
+    pub fn new(id_nodo: IdNodo, corte: u64) -> PedidoSnapshot {
+        PedidoSnapshot { id_nodo, corte }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::SNAPSHOT)]
+/// Saldos de todas las cuentas de un nodo al momento del corte, el nodo se lo envia al coordinador
+/// y este se lo reenvia al nodo que se esta uniendo
+pub struct Snapshot {
+    /// id del nodo que se esta uniendo y va a recibir el snapshot
+    pub id_nodo: IdNodo,
+    /// cantidad de COMMIT/ABORT que ya estan reflejados en los saldos
+    pub corte: u64,
+    /// pares (id_cuenta, saldo) de todas las cuentas
+    pub cuentas: Vec<(IdCuenta, u32)>,
+}
+
+impl Snapshot {
+    pub fn new(id_nodo: IdNodo, corte: u64, cuentas: Vec<(IdCuenta, u32)>) -> Snapshot {
+        Snapshot {
+            id_nodo,
+            corte,
+            cuentas,
+        }
+    }
+}
+
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::PEDIDOAUDITORIA)]
+/// Mensaje enviado por el coordinador a todos los nodos para que le reporten sus saldos en una
+/// auditoria de consistencia
+pub struct PedidoAuditoria {
+    pub id_auditoria: u32,
+}
+
+impl PedidoAuditoria {
+    pub fn new(id_auditoria: u32) -> PedidoAuditoria {
+        PedidoAuditoria { id_auditoria }
+    }
 }
 
-// TODO: Agregar tests
+#[derive(Message, Debug, Clone, Serialize, Deserialize, Codificable)]
+#[rtype(result = "()")]
+#[codificable(tipo = Mensaje::AUDITORIA)]
+/// Saldos de todas las cuentas de un nodo, en respuesta a un PedidoAuditoria
+pub struct Auditoria {
+    /// id del nodo que reporta sus saldos
+    pub id_nodo: IdNodo,
+    pub id_auditoria: u32,
+    /// pares (id_cuenta, saldo) de todas las cuentas
+    pub cuentas: Vec<(IdCuenta, u32)>,
+}
+
+impl Auditoria {
+    pub fn new(id_nodo: IdNodo, id_auditoria: u32, cuentas: Vec<(IdCuenta, u32)>) -> Auditoria {
+        Auditoria {
+            id_nodo,
+            id_auditoria,
+            cuentas,
+        }
+    }
+}
@@ -1,38 +1,54 @@
+use crate::almacen::AlmacenCuentas;
 use crate::bully_listener::{BullyListener, SetState, StartElection};
+use crate::buzones::atender_medidos;
 use crate::cafetera_listener::{CafeteraListener, ReceiverActorNodo};
-use crate::coordinador::{Disconnect, DisconnectToString};
-use crate::error_server::{ErrorServer, TipoError};
+use crate::caos;
+use crate::comandos_admin::FiltroComandosAdmin;
+use crate::coordinador::{Apagar, Disconnect, ModoConexion};
+use crate::error_server::ErrorServer;
+use crate::estado_transaccion::{EventoTransaccion, TransactionState};
+use crate::eventos::publicar_evento;
 use crate::mensaje::{
-    Abort, Commit, CommitType, Execute, Finish, Mensaje, MensajeBytes, OkeyAbortToCoordinator,
-    OkeyToCoordinator, PingCord, Starter, Yes,
+    Abort, AltaCord, Auditoria, BajaCord, Commit, CommitType, ConfirmadoToNodo, Execute,
+    FallidoToNodo, Finish, Mensaje, MensajeBytes, OkeyAbortToCoordinator, OkeyToCoordinator,
+    PedidoAuditoria, PedidoSnapshot, PingCord, Prepare, Rechazo, Retiro, Snapshot, Starter,
+    Transferencia, Validar, Valido, Yes,
 };
-use crate::utils::{id_to_ctrladdr, SALDO_INICIAL};
-use actix::{Actor, ActorFutureExt, AsyncContext, Message};
+use crate::pedidos_terminados::PedidosTerminados;
+use crate::registro_nodo::{EventoNodo, RegistroNodo};
+use crate::saldo::{restar_saldo, sumar_saldo, transferir_saldo};
+use crate::tpc::{ParticipanteTpc, Resolucion};
+use crate::utils::{
+    cada_intervalo, configurar_keepalive, enmarcar, escribir_con_plazo, id_to_ctrladdr,
+    leer_mensajes, reintentar_con_backoff,
+};
+use actix::{
+    Actor, ActorContext, ActorFutureExt, AsyncContext, Message, Recipient, ResponseActFuture,
+    System,
+};
+use compartido::codificable::Codificable;
+use compartido::config::config;
+use compartido::error_mensaje::ErrorMensaje;
+use compartido::ids::{IdCafetera, IdCuenta, IdNodo, IdTransaccion};
+use compartido::logs::LimitadorLogs;
 use compartido::mensajes_cafetera::{
-    Error, MensajeCafetera, MensajeCafeteraBytes, OkeyToCafetera, Ping, Restar, Sumar,
+    id_pedido_from_bytes, secuencia_comando_admin, AltaCuenta, BajaCuenta, Conectar, Confirmado,
+    Consultar, Desconectar, Drenar, Error, Fallido, MensajeCafetera, MensajeCafeteraBytes,
+    MotivoError, OkeyToCafetera, Ping, Restar, Sondeo, Sumar, Transferir,
 };
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::io::WriteHalf;
-use tokio::io::{split, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use std::time::{Duration, Instant};
+use tiempo::Reloj;
+use tokio::io::{split, AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
-use tokio::sync::Mutex;
-use tokio_stream::wrappers::LinesStream;
+use tokio::sync::{oneshot, Mutex};
 
 use actix::fut::wrap_future;
 use actix::{Addr, Context, ContextFutureSpawner, Handler, StreamHandler};
-
-#[derive(PartialEq, Eq)]
-pub enum TransactionState {
-    Accepted,
-    Wait,
-    WaitCommit,
-    Locked,
-    ToSend,
-    Commit,
-    Abort,
-}
+use tracing::{debug, error, info, trace, warn};
 
 /// Estructura que guarda la información necesaria para
 /// completar un pedido correctamente
@@ -44,36 +60,79 @@ pub struct Transaction {
     /// Estado de la transaccion
     pub state: TransactionState,
     /// id de la cafetera del nodo
-    pub id_cafetera: u8,
+    pub id_cafetera: IdCafetera,
     /// id de la cuenta de usuario correspondiente a la transaccion
-    pub id_cuenta: u32,
+    pub id_cuenta: IdCuenta,
+    /// id del pedido asignado por la sucursal, para seguirlo entre procesos
+    pub id_pedido: u32,
+    /// cuenta a la que se suman los creditos, solo en las transferencias
+    pub id_cuenta_destino: Option<IdCuenta>,
+    /// true si la pidio un comando de administracion firmado, que se adelanta en la cola de la
+    /// cuenta
+    pub prioritaria: bool,
+    /// momento en que la limpieza periodica la encontro terminada por primera vez, None mientras
+    /// esta en curso
+    pub terminada: Option<Instant>,
 }
+/// Lado de escritura de la conexion con el coordinador. Normalmente es un socket tcp, en los tests
+/// puede ser un stream en memoria
+type EscrituraCoordinador = Arc<Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+
 /// Estructura que almacena un nodo para realizar los pedidos
 pub struct Nodo {
     /// socket tcp al coordinador (nodo handler en realidad)
-    stream_cordinador: Arc<Mutex<WriteHalf<TcpStream>>>,
+    stream_cordinador: EscrituraCoordinador,
     /// hash map de clave id transaccion y valor id_cuenta
-    cuentas: HashMap<u32, Cuenta>,
+    cuentas: HashMap<IdCuenta, Cuenta>,
+    /// donde se guarda el saldo de cada cuenta cuando cambia, para retomarlo si el nodo se reinicia
+    almacen: Box<dyn AlmacenCuentas>,
     /// hashmap de clave id transaccion y valor estructura Transaction para el caso de Restas
-    transacciones_resta: HashMap<u32, Transaction>,
+    transacciones_resta: HashMap<IdTransaccion, Transaction>,
     /// id del nodo en funcionamiento
-    id_nodo: u8,
+    id_nodo: IdNodo,
     /// Contador de la cantidad de ordenes procesadas por el nodo
     id_orden: u32,
     /// addr del actor cafetera listener
-    addr_actor_cafetera: Option<Addr<CafeteraListener>>,
+    addr_actor_cafetera: Option<Recipient<ReceiverActorNodo>>,
     /// addr del actor bully listener
     addr_actor_bully: Option<Addr<BullyListener>>,
     /// estado de la coneccion
     conectado: bool,
     /// hashmap de clave id transaccion y valor estructura Transaction para el caso de Sumas
-    transacciones_suma: HashMap<u32, Transaction>,
+    transacciones_suma: HashMap<IdTransaccion, Transaction>,
     /// id del coordinador actual
-    id_coordinador: u8,
+    id_coordinador: IdNodo,
+    /// lado participante del commit de dos fases: las cuentas bloqueadas por cada PREPARE, los commits
+    /// ya aplicados para no aplicar dos veces uno reenviado y la secuencia del ultimo COMMIT/ABORT del
+    /// coordinador reflejado en las cuentas
+    participante: ParticipanteTpc<Bloqueo>,
+    /// si es true el nodo es una replica de solo lectura: aplica los commits y responde consultas,
+    /// pero no inicia transacciones ni participa de las elecciones
+    replica: bool,
+    /// momento en que se empezo a drenar el nodo, None si atiende pedidos normalmente
+    drenando: Option<Instant>,
+    /// reloj con el que se miden el drenado y los timeouts de los actores del nodo
+    reloj: Arc<dyn Reloj>,
+    /// limita los logs de los errores de envio que se repiten, por destino (coordinador o cafetera)
+    errores_envio: LimitadorLogs<&'static str>,
+    /// true si el nodo funciono sin coordinador desde la ultima conexion: al volver a conectarse pide
+    /// reconciliar sus saldos antes de votar
+    reconciliar: bool,
+    /// descarta los CONECTAR/DESCONECTAR repetidos o viejos, para que no se puedan reenviar
+    comandos_admin: FiltroComandosAdmin,
+    /// resultado de las restas ya quitadas de transacciones_resta, para responder los RESTAR repetidos
+    pedidos_terminados: PedidosTerminados,
+    /// registro de los commits, snapshots, altas y bajas que aplico el nodo, para el verificador
+    registro: RegistroNodo,
+    /// avisa al proceso cuando el nodo se detiene por su cuenta, al terminar de drenarse
+    aviso_detencion: Option<oneshot::Sender<()>>,
 }
 
-type IdCafetera = u8;
-type IdTransaccion = u32;
+/// Cuentas que bloqueo el PREPARE de una transaccion, ademas de la suya, hasta el COMMIT/ABORT
+struct Bloqueo {
+    /// cuenta a la que se suman los creditos, solo en las transferencias
+    id_cuenta_destino: Option<IdCuenta>,
+}
 
 #[derive(Debug)]
 /// Estructura que almacena el estado de una cuenta de usuario
@@ -82,80 +141,662 @@ pub struct Cuenta {
     blocked: bool,
     /// saldo restante de la cuenta
     saldo: u32,
-    /// hash map que a partir de un id_cafetera retorna el id_transaccion
-    transacciones: HashMap<IdCafetera, IdTransaccion>,
+    /// hash map que a partir del socket de la cafetera y del id de pedido retorna el id_transaccion.
+    /// Se usa el socket y no el id_cafetera porque un nodo puede recibir pedidos de cafeteras
+    /// de otras sucursales que hicieron failover, y sus ids pueden repetirse. El id de pedido
+    /// distingue los pedidos en curso de todas las cafeteras de una sucursal, que comparten el socket
+    transacciones: HashMap<(SocketAddr, u32), IdTransaccion>,
+}
+
+impl Cuenta {
+    /// Crea una cuenta desbloqueada con el saldo indicado
+    fn new(saldo: u32) -> Cuenta {
+        Cuenta {
+            blocked: false,
+            saldo,
+            transacciones: HashMap::new(),
+        }
+    }
 }
 /// Actor nodo, realiza las acciones que van entre el actor Cafetera-Listener y el Nodo-Handler
 /// hace de "servidor local"
 impl Actor for Nodo {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        cada_intervalo(
+            ctx,
+            self.reloj.clone(),
+            Duration::from_millis(config().servidor.intervalo_limpieza_transacciones_millis),
+            Self::limpiar_transacciones,
+        );
+    }
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        if let Some(aviso) = self.aviso_detencion.take() {
+            let _res = aviso.send(());
+        }
+    }
+}
+
+/// Bits bajos del id de transaccion que ocupa el numero de orden del nodo, los 8 altos son del
+/// ID_NODO que la inicia
+const BITS_ORDEN: u32 = 24;
+const MASCARA_ORDEN: u32 = (1 << BITS_ORDEN) - 1;
+
+/// Id de la transaccion con el numero de orden indicado del nodo. Los de nodos distintos nunca
+/// coinciden, y los de un mismo nodo se repiten recien luego de 2^24 transacciones
+fn id_transaccion(id_nodo: IdNodo, orden: u32) -> IdTransaccion {
+    IdTransaccion((u32::from(id_nodo.0) << BITS_ORDEN) | (orden & MASCARA_ORDEN))
+}
+
+/// true si la suma ya no espera nada del coordinador ni de la cafetera
+fn suma_terminada(state: TransactionState) -> bool {
+    matches!(state, TransactionState::Commit | TransactionState::Abort)
+}
+
+/// true si la resta (o transferencia) ya no espera nada del coordinador ni de la cafetera
+fn resta_terminada(state: TransactionState) -> bool {
+    matches!(
+        state,
+        TransactionState::Accepted | TransactionState::Commit | TransactionState::Abort
+    )
+}
+
+/// Quita de las transacciones las terminadas hace mas de `ttl` y las retorna. Las que recien se
+/// encuentran terminadas se marcan con `ahora`, asi el ttl se cuenta desde la primera limpieza que
+/// las ve terminadas
+fn quitar_vencidas(
+    transacciones: &mut HashMap<IdTransaccion, Transaction>,
+    terminada: fn(TransactionState) -> bool,
+    ahora: Instant,
+    ttl: Duration,
+) -> Vec<(IdTransaccion, Transaction)> {
+    let vencidas: Vec<IdTransaccion> = transacciones
+        .iter_mut()
+        .filter(|(_, x)| terminada(x.state))
+        .filter_map(|(id, x)| {
+            let desde = *x.terminada.get_or_insert(ahora);
+            (ahora.saturating_duration_since(desde) >= ttl).then_some(*id)
+        })
+        .collect();
+    vencidas
+        .into_iter()
+        .filter_map(|id| transacciones.remove(&id).map(|x| (id, x)))
+        .collect()
 }
 
 impl Nodo {
-    /// Incrementa el numero de orden y lo retorna
-    fn id_nueva_orden(&mut self) -> u32 {
-        self.id_orden += 1;
-        self.id_orden
+    /// Incrementa el numero de orden y retorna el id de la transaccion que lo usa
+    fn id_nueva_transaccion(&mut self) -> IdTransaccion {
+        self.id_orden = (self.id_orden + 1) & MASCARA_ORDEN;
+        id_transaccion(self.id_nodo, self.id_orden)
     }
 
-    pub async fn start(id_nodo: u8, id_coordinador: u8) -> Result<(), ErrorServer> {
-        let mut stream_cordinador = tokio::net::TcpStream::connect(id_to_ctrladdr(id_coordinador))
-            .await
-            .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorConexion))?;
+    /// Guarda en el almacen el saldo actual de la cuenta, o la elimina si el nodo ya no la tiene
+    fn persistir_cuenta(&mut self, id_cuenta: IdCuenta) {
+        let resultado = match self.cuentas.get(&id_cuenta) {
+            Some(cuenta) => self.almacen.guardar(id_cuenta, cuenta.saldo),
+            None => self.almacen.eliminar(id_cuenta),
+        };
+        if let Err(err) = resultado {
+            error!(
+                "[NODO-{}] Error guardando la ID_CUENTA = {} en el almacen | Detalle: {}",
+                self.id_nodo, id_cuenta, err
+            );
+        }
+    }
 
-        stream_cordinador
-            .write(&[id_nodo])
-            .await
-            .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorConexion))?;
+    /// Pares (id_cuenta, saldo) de todas las cuentas ordenados por id, para los snapshots y las
+    /// auditorias
+    fn saldos(&self) -> Vec<(IdCuenta, u32)> {
+        let mut saldos: Vec<(IdCuenta, u32)> = self
+            .cuentas
+            .iter()
+            .map(|(id_cuenta, cuenta)| (*id_cuenta, cuenta.saldo))
+            .collect();
+        saldos.sort();
+        saldos
+    }
+
+    /// Reemplaza las cuentas del almacen por las del nodo, por ejemplo luego de aplicar un snapshot
+    fn persistir_cuentas(&mut self) {
+        let saldos = self
+            .cuentas
+            .iter()
+            .map(|(id_cuenta, cuenta)| (*id_cuenta, cuenta.saldo))
+            .collect();
+        if let Err(err) = self.almacen.reemplazar(&saldos) {
+            error!(
+                "[NODO-{}] Error guardando las cuentas en el almacen | Detalle: {}",
+                self.id_nodo, err
+            );
+        }
+    }
+
+    /// Envia al coordinador el ok de un commit
+    fn confirmar_commit(&self, commit: &Commit, ctx: &mut Context<Self>) {
+        ctx.address().do_send(SendHandlerToCoordinator {
+            vec: OkeyToCoordinator::new(
+                self.id_nodo,
+                commit.id_cuenta,
+                commit.id_transaccion,
+                commit.id_cafetera,
+            )
+            .con_id_pedido(commit.id_pedido)
+            .to_bytes(),
+        });
+    }
+
+    /// Registra el COMMIT aplicado, con los saldos que quedaron en las cuentas involucradas que tiene el
+    /// nodo
+    fn registrar_commit(&mut self, commit: &Commit, id_cuenta_destino: Option<IdCuenta>) {
+        let saldos = std::iter::once(commit.id_cuenta)
+            .chain(id_cuenta_destino)
+            .filter_map(|id| self.cuentas.get(&id).map(|x| (id, x.saldo)))
+            .collect();
+        self.registro.registrar(
+            self.id_coordinador,
+            EventoNodo::Commit {
+                secuencia: commit.secuencia,
+                id_transaccion: commit.id_transaccion,
+                id_cuenta: commit.id_cuenta,
+                id_cuenta_destino,
+                tipo: commit.tipo,
+                cantidad: commit.cantidad,
+                saldos,
+            },
+        );
+    }
+
+    /// Registra la secuencia de un COMMIT/ABORT recibido. Si se salteo alguna, el nodo se perdio
+    /// mensajes y le pide al coordinador que lo resincronice con un snapshot
+    fn registrar_secuencia(&mut self, secuencia: u64, ctx: &mut Context<Self>) {
+        if let Some(ultima) = self.participante.registrar_secuencia(secuencia) {
+            warn!(
+                "[NODO-{}] Hueco en las secuencias: faltan de la {} a la {}, pido resincronizar",
+                self.id_nodo,
+                ultima + 1,
+                secuencia - 1
+            );
+            ctx.address().do_send(SendHandlerToCoordinator {
+                vec: PedidoSnapshot::new(self.id_nodo, ultima).to_bytes(),
+            });
+        }
+    }
+
+    /// Rechaza el pedido de una cafetera sobre una cuenta que no existe en el nodo
+    fn rechazar_cuenta_inexistente(&self, id_cuenta: IdCuenta, id_pedido: u32, socket: SocketAddr) {
+        warn!(
+            "[NODO-{}] Pedido sobre ID_CUENTA = {} inexistente, se rechaza",
+            self.id_nodo, id_cuenta
+        );
+        self.addr_actor_cafetera
+            .as_ref()
+            .expect("Siempre se cuenta con el address del actor cafetera")
+            .do_send(ReceiverActorNodo {
+                vec: Error::de_cafetera(IdCafetera::default())
+                    .con_id_pedido(id_pedido)
+                    .to_bytes(),
+                socket,
+            });
+    }
+
+    /// Responde con un error el OKEY de una cafetera sin transaccion en curso en este nodo, para que no
+    /// espere indefinidamente la confirmacion de un cafe que no se va a cobrar
+    fn rechazar_okey_desconocido(&self, mensaje: &OkeyToCafetera, socket: SocketAddr) {
+        warn!(
+            "[NODO-{}] OKEY de ID_CAFETERA = {} sin transaccion en curso sobre ID_CUENTA = {}, se responde error",
+            self.id_nodo, mensaje.id_cafetera, mensaje.id_cuenta
+        );
+        self.addr_actor_cafetera
+            .as_ref()
+            .expect("Siempre se cuenta con el address del actor cafetera")
+            .do_send(ReceiverActorNodo {
+                vec: Error::new(mensaje.id_cafetera, mensaje.id_cuenta, 0)
+                    .con_motivo(MotivoError::TRANSACCIONDESCONOCIDA)
+                    .con_id_pedido(mensaje.id_pedido)
+                    .to_bytes(),
+                socket,
+            });
+    }
+
+    /// Rechaza un pedido de una cafetera que intenta iniciar una transaccion en una replica o en un
+    /// nodo que se esta drenando
+    fn rechazar_pedido_nuevo(
+        &self,
+        tipo_mensaje: MensajeCafetera,
+        bytes: &[u8],
+        socket: SocketAddr,
+    ) {
+        info!(
+            "[NODO-{}] {}, rechazo {:?}",
+            self.id_nodo,
+            if self.replica {
+                "Soy replica de solo lectura"
+            } else {
+                "Me estoy drenando"
+            },
+            tipo_mensaje
+        );
+        self.addr_actor_cafetera
+            .as_ref()
+            .expect("Siempre se cuenta con el address del actor cafetera")
+            .do_send(ReceiverActorNodo {
+                vec: Error::de_cafetera(IdCafetera::default())
+                    .con_id_pedido(id_pedido_from_bytes(bytes))
+                    .to_bytes(),
+                socket,
+            });
+    }
+
+    /// Resta que ya se inicio para el mismo pedido de la misma cafetera, por ejemplo si la cafetera
+    /// reintenta el RESTAR tras una falla parcial. Los pedidos sin id asignado por la sucursal no se
+    /// pueden distinguir y siempre se toman como nuevos
+    fn resta_existente(&self, socket: SocketAddr, mensaje: &Restar) -> Option<IdTransaccion> {
+        if mensaje.id_pedido == 0 {
+            return None;
+        }
+        self.transacciones_resta
+            .iter()
+            .find(|(_, x)| {
+                x.socket == socket
+                    && x.id_pedido == mensaje.id_pedido
+                    && x.id_cuenta == mensaje.id_cuenta
+                    && x.id_cuenta_destino.is_none()
+            })
+            .map(|(id, _)| *id)
+    }
+
+    /// Responde un RESTAR repetido con el estado de la resta existente en lugar de iniciar otra: si
+    /// todavia no hubo respuesta del coordinador se le reenvia el mismo STARTER (el coordinador no la
+    /// vuelve a encolar), si la cafetera ya puede preparar el cafe o la resta se commiteo se le
+    /// reenvia el ok, si se aborto el error, y si se espera el commit no se responde nada
+    fn responder_resta_repetida(&self, id_transaccion: IdTransaccion, ctx: &mut Context<Self>) {
+        let transaccion = match self.transacciones_resta.get(&id_transaccion) {
+            Some(x) => x,
+            None => return,
+        };
+        info!(
+            "[NODO-{}] RESTAR repetido de ID_TRANSACCION = {} | ID_PEDIDO = {}",
+            self.id_nodo, id_transaccion, transaccion.id_pedido
+        );
+        let respuesta = match transaccion.state {
+            TransactionState::Wait => {
+                let starter = Starter {
+                    id_cuenta: transaccion.id_cuenta,
+                    id_nodo: self.id_nodo,
+                    id_transaccion,
+                    id_cafetera: transaccion.id_cafetera,
+                    id_pedido: transaccion.id_pedido,
+                    prioritaria: transaccion.prioritaria,
+                };
+                ctx.address().do_send(SendHandlerToCoordinator {
+                    vec: starter.to_bytes(),
+                });
+                return;
+            }
+            TransactionState::Locked | TransactionState::Accepted | TransactionState::Commit => {
+                OkeyToCafetera::de_cafetera(IdCafetera::default())
+                    .con_id_pedido(transaccion.id_pedido)
+                    .to_bytes()
+            }
+            TransactionState::Abort => Error::de_cafetera(IdCafetera::default())
+                .con_id_pedido(transaccion.id_pedido)
+                .to_bytes(),
+            TransactionState::ToSend | TransactionState::WaitCommit => return,
+        };
+        self.addr_actor_cafetera
+            .as_ref()
+            .expect("Siempre se cuenta con el address del actor cafetera")
+            .do_send(ReceiverActorNodo {
+                vec: respuesta,
+                socket: transaccion.socket,
+            });
+    }
+
+    /// Cantidad de transacciones iniciadas por el nodo que todavia no terminaron
+    fn transacciones_en_curso(&self) -> usize {
+        let sumas = self
+            .transacciones_suma
+            .values()
+            .filter(|x| !suma_terminada(x.state))
+            .count();
+        let restas = self
+            .transacciones_resta
+            .values()
+            .filter(|x| !resta_terminada(x.state))
+            .count();
+        sumas + restas
+    }
+
+    /// Quita de memoria las transacciones terminadas hace mas de ttl_transacciones_terminadas_segundos,
+    /// junto con su entrada en las transacciones de la cuenta. De las restas se recuerda solo el
+    /// resultado del pedido (ver `PedidosTerminados`), asi un RESTAR repetido no inicia otra resta
+    fn limpiar_transacciones(&mut self, _ctx: &mut Context<Self>) {
+        let ahora = self.reloj.ahora();
+        let ttl = Duration::from_secs(config().servidor.ttl_transacciones_terminadas_segundos);
+        let sumas = quitar_vencidas(&mut self.transacciones_suma, suma_terminada, ahora, ttl);
+        let restas = quitar_vencidas(&mut self.transacciones_resta, resta_terminada, ahora, ttl);
+        let quitadas = sumas.len() + restas.len();
+
+        for (id_transaccion, transaccion) in sumas.iter().chain(restas.iter()) {
+            if let Some(cuenta) = self.cuentas.get_mut(&transaccion.id_cuenta) {
+                if let Entry::Occupied(entrada) = cuenta
+                    .transacciones
+                    .entry((transaccion.socket, transaccion.id_pedido))
+                {
+                    if entrada.get() == id_transaccion {
+                        entrada.remove();
+                    }
+                }
+            }
+        }
+        for (_, transaccion) in restas {
+            if transaccion.id_pedido != 0 && transaccion.id_cuenta_destino.is_none() {
+                self.pedidos_terminados.recordar(
+                    (
+                        transaccion.socket,
+                        transaccion.id_pedido,
+                        transaccion.id_cuenta,
+                    ),
+                    transaccion.state != TransactionState::Abort,
+                );
+            }
+        }
+
+        if quitadas > 0 {
+            info!(
+                "[NODO-{}] Se quitan {} transacciones terminadas | En memoria: SUMAS = {} | RESTAS = {} | PEDIDOS TERMINADOS = {}",
+                self.id_nodo,
+                quitadas,
+                self.transacciones_suma.len(),
+                self.transacciones_resta.len(),
+                self.pedidos_terminados.len()
+            );
+        } else {
+            debug!(
+                "[NODO-{}] En memoria: SUMAS = {} | RESTAS = {} | PEDIDOS TERMINADOS = {}",
+                self.id_nodo,
+                self.transacciones_suma.len(),
+                self.transacciones_resta.len(),
+                self.pedidos_terminados.len()
+            );
+        }
+    }
 
-        let addr_actor_nodo = Nodo::create(|ctx| {
+    /// Responde un RESTAR repetido de un pedido cuya resta ya se quito de memoria con el resultado
+    /// recordado: el ok si se commiteo o el error si se aborto. Retorna false si el pedido no se
+    /// recuerda
+    fn responder_resta_terminada(&self, socket: SocketAddr, mensaje: &Restar) -> bool {
+        if mensaje.id_pedido == 0 {
+            return false;
+        }
+        let commiteada =
+            match self
+                .pedidos_terminados
+                .resultado(&(socket, mensaje.id_pedido, mensaje.id_cuenta))
+            {
+                Some(x) => x,
+                None => return false,
+            };
+        info!(
+            "[NODO-{}] RESTAR repetido de un pedido ya terminado | ID_PEDIDO = {}",
+            self.id_nodo, mensaje.id_pedido
+        );
+        let respuesta = if commiteada {
+            OkeyToCafetera::de_cafetera(IdCafetera::default())
+                .con_id_pedido(mensaje.id_pedido)
+                .to_bytes()
+        } else {
+            Error::de_cafetera(IdCafetera::default())
+                .con_id_pedido(mensaje.id_pedido)
+                .to_bytes()
+        };
+        self.addr_actor_cafetera
+            .as_ref()
+            .expect("Siempre se cuenta con el address del actor cafetera")
+            .do_send(ReceiverActorNodo {
+                vec: respuesta,
+                socket,
+            });
+        true
+    }
+
+    /// Si el nodo se esta drenando y ya no tiene transacciones en curso (o se supero el
+    /// tiempo_maximo_drenado), avisa al coordinador que se retira, se apaga y detiene el System
+    fn revisar_drenado(&mut self, ctx: &mut Context<Self>) {
+        let inicio = match self.drenando {
+            Some(inicio) => inicio,
+            None => return,
+        };
+        let en_curso = self.transacciones_en_curso();
+        if en_curso > 0
+            && self.reloj.ahora().saturating_duration_since(inicio)
+                < Duration::from_secs(config().servidor.tiempo_maximo_drenado)
+        {
+            return;
+        }
+        info!(
+            "[NODO-{}] Drenado terminado con {} transacciones en curso, me retiro",
+            self.id_nodo, en_curso
+        );
+        self.drenando = None;
+        let arc = self.stream_cordinador.clone();
+        let retiro = enmarcar(&Retiro::de_nodo(self.id_nodo).to_bytes());
+        wrap_future::<_, Self>(async move {
+            let mut stream = arc.lock().await;
+            if stream.write_all(&retiro).await.is_ok() {
+                let _res = stream.shutdown().await;
+            }
+        })
+        .map(|_, _, ctx: &mut Context<Self>| {
+            ctx.notify(Apagar);
+            System::current().stop();
+        })
+        .wait(ctx);
+    }
+
+    /// Crea el actor nodo sobre una conexion ya establecida con el coordinador, sin los actores que
+    /// escuchan a las cafeteras y al bully
+    pub fn conectado_a<S>(
+        stream_cordinador: S,
+        id_nodo: IdNodo,
+        id_coordinador: IdNodo,
+        replica: bool,
+        almacen: Box<dyn AlmacenCuentas>,
+        reloj: Arc<dyn Reloj>,
+    ) -> Addr<Nodo>
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        Nodo::create(|ctx| {
             let (read, write_half) = split(stream_cordinador);
 
-            Nodo::add_stream(LinesStream::new(BufReader::new(read).lines()), ctx);
-            let write = Arc::new(Mutex::new(write_half));
-
-            Nodo {
-                stream_cordinador: write,
-                cuentas: HashMap::from([(
-                    1,
-                    Cuenta {
-                        blocked: false,
-                        saldo: SALDO_INICIAL,
-                        transacciones: HashMap::new(),
-                    },
-                )]),
-                transacciones_resta: HashMap::new(),
-                id_orden: 0,
+            Nodo::add_stream(leer_mensajes(read), ctx);
+            let write: EscrituraCoordinador = Arc::new(Mutex::new(Box::new(write_half)));
+
+            Nodo::new(
+                write,
+                true,
                 id_nodo,
-                addr_actor_cafetera: None,
-                conectado: true,
-                transacciones_suma: HashMap::new(),
-                addr_actor_bully: None,
                 id_coordinador,
-            }
+                replica,
+                almacen,
+                reloj,
+            )
+        })
+    }
+
+    /// Crea el actor nodo sin conexion con el coordinador, como queda luego de que este se cae: no
+    /// inicia transacciones hasta que una eleccion le indique a que coordinador conectarse
+    fn sin_coordinador(
+        id_nodo: IdNodo,
+        id_coordinador: IdNodo,
+        replica: bool,
+        almacen: Box<dyn AlmacenCuentas>,
+        reloj: Arc<dyn Reloj>,
+    ) -> Addr<Nodo> {
+        let write: EscrituraCoordinador = Arc::new(Mutex::new(Box::new(tokio::io::sink())));
+        Nodo::new(
+            write,
+            false,
+            id_nodo,
+            id_coordinador,
+            replica,
+            almacen,
+            reloj,
+        )
+        .start()
+    }
+
+    fn new(
+        stream_cordinador: EscrituraCoordinador,
+        conectado: bool,
+        id_nodo: IdNodo,
+        id_coordinador: IdNodo,
+        replica: bool,
+        almacen: Box<dyn AlmacenCuentas>,
+        reloj: Arc<dyn Reloj>,
+    ) -> Nodo {
+        let cuentas = almacen.cuentas().unwrap_or_else(|err| {
+            error!(
+                "[NODO-{}] No se pudieron leer las cuentas del almacen, arranco sin cuentas hasta recibir un snapshot | Detalle: {}",
+                id_nodo, err
+            );
+            HashMap::new()
         });
+        let mut registro = RegistroNodo::desde_config(id_nodo);
+        let mut iniciales: Vec<(IdCuenta, u32)> = cuentas.iter().map(|(k, v)| (*k, *v)).collect();
+        iniciales.sort();
+        registro.registrar(id_coordinador, EventoNodo::Inicio { cuentas: iniciales });
+        Nodo {
+            stream_cordinador,
+            cuentas: cuentas
+                .into_iter()
+                .map(|(id_cuenta, saldo)| (id_cuenta, Cuenta::new(saldo)))
+                .collect(),
+            almacen,
+            transacciones_resta: HashMap::new(),
+            id_orden: 0,
+            id_nodo,
+            addr_actor_cafetera: None,
+            conectado,
+            transacciones_suma: HashMap::new(),
+            addr_actor_bully: None,
+            id_coordinador,
+            participante: ParticipanteTpc::new(),
+            replica,
+            drenando: None,
+            reloj,
+            errores_envio: LimitadorLogs::desde_config(),
+            reconciliar: !conectado,
+            comandos_admin: FiltroComandosAdmin::new(Duration::from_millis(
+                config().servidor.ventana_comandos_admin_millis,
+            )),
+            pedidos_terminados: PedidosTerminados::new(config().servidor.max_pedidos_terminados),
+            registro,
+            aviso_detencion: None,
+        }
+    }
+
+    /// Se conecta al coordinador indicandole que el nodo arranca sin cuentas previas (ModoConexion)
+    async fn conectar_coordinador_inicial(
+        id_nodo: IdNodo,
+        id_coordinador: IdNodo,
+    ) -> Result<TcpStream, ErrorServer> {
+        let mut stream = TcpStream::connect(id_to_ctrladdr(id_coordinador)).await?;
+        configurar_keepalive(&stream)?;
+        stream
+            .write_all(&[id_nodo.0, ModoConexion::SinEstado as u8])
+            .await?;
+        Ok(stream)
+    }
+
+    /// Conecta el nodo al coordinador y arranca sus actores (y el servicio grpc con la feature grpc), con las cuentas del almacen. Si el coordinador todavia no arranco se reintenta la conexion, y si no
+    /// responde en servidor.intentos_conexion_coordinador intentos el nodo arranca desconectado e
+    /// inicia una eleccion, como si el coordinador se hubiera caido. Retorna el actor del nodo
+    pub async fn start(
+        id_nodo: IdNodo,
+        id_coordinador: IdNodo,
+        replica: bool,
+        almacen: Box<dyn AlmacenCuentas>,
+        reloj: Arc<dyn Reloj>,
+    ) -> Result<Addr<Nodo>, ErrorServer> {
+        let servidor = &config().servidor;
+        let conexion = reintentar_con_backoff(
+            reloj.as_ref(),
+            servidor.intentos_conexion_coordinador,
+            Duration::from_millis(servidor.espera_conexion_coordinador_millis),
+            &format!(
+                "[NODO-{}] Conexion con el ID_COORDINADOR = {}",
+                id_nodo, id_coordinador
+            ),
+            || Nodo::conectar_coordinador_inicial(id_nodo, id_coordinador),
+        )
+        .await;
+        let conectado = conexion.is_ok();
+        let addr_actor_nodo = match conexion {
+            Ok(stream_cordinador) => Nodo::conectado_a(
+                stream_cordinador,
+                id_nodo,
+                id_coordinador,
+                replica,
+                almacen,
+                reloj.clone(),
+            ),
+            Err(err) => {
+                warn!(
+                    "[NODO-{}] No me pude conectar al ID_COORDINADOR = {}, arranco desconectado | Detalle: {}",
+                    id_nodo, id_coordinador, err
+                );
+                Nodo::sin_coordinador(id_nodo, id_coordinador, replica, almacen, reloj.clone())
+            }
+        };
 
-        let addr_actor_cafetera = CafeteraListener::start(id_nodo, addr_actor_nodo.clone()).await?;
+        let addr_actor_cafetera =
+            CafeteraListener::start(id_nodo, addr_actor_nodo.clone(), reloj.clone()).await?;
+        #[cfg(feature = "grpc")]
+        {
+            let servicio = crate::grpc::servir(
+                id_nodo,
+                addr_actor_nodo.clone(),
+                addr_actor_cafetera.clone(),
+            );
+            actix::spawn(async move {
+                if let Err(err) = servicio.await {
+                    error!("[NODO-{}] Error en el servicio grpc: {:?}", id_nodo, err);
+                }
+            });
+        }
         addr_actor_nodo.do_send(AddAddrActorCafetera {
-            addr_actor_cafetera,
+            addr_actor_cafetera: addr_actor_cafetera.recipient(),
         });
 
-        let addr_actor_bully = BullyListener::start(id_nodo, addr_actor_nodo.clone()).await?;
-        addr_actor_nodo.do_send(AddAddrActorBully { addr_actor_bully });
+        let addr_actor_bully =
+            BullyListener::start(id_nodo.0, addr_actor_nodo.clone(), replica, reloj).await?;
+        addr_actor_nodo.do_send(AddAddrActorBully {
+            addr_actor_bully: addr_actor_bully.clone(),
+        });
 
-        println!(
-            "[NODO-{}] Conectado con el ID_COORDINADOR = {}",
-            id_nodo, id_coordinador
-        );
+        if conectado {
+            info!(
+                "[NODO-{}] Conectado con el ID_COORDINADOR = {}",
+                id_nodo, id_coordinador
+            );
+        } else if !replica {
+            // una replica no participa de las elecciones, espera el COORDINATOR del que gane
+            addr_actor_bully.do_send(StartElection);
+        }
 
-        Ok(())
+        Ok(addr_actor_nodo)
     }
 }
 
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct AddAddrActorCafetera {
-    pub addr_actor_cafetera: Addr<CafeteraListener>,
+    pub addr_actor_cafetera: Recipient<ReceiverActorNodo>,
 }
 /// Mensaje para agregar el address del actor cafetera
 impl Handler<AddAddrActorCafetera> for Nodo {
@@ -180,68 +821,173 @@ impl Handler<AddAddrActorBully> for Nodo {
     }
 }
 
-/// Mensaje que se recibe escuchando al coordinador, handlea según el tipo de mensaje recibido
-impl StreamHandler<Result<String, std::io::Error>> for Nodo {
-    fn handle(&mut self, read: Result<String, std::io::Error>, ctx: &mut Self::Context) {
-        if let Ok(line) = read {
-            let aux = line.as_bytes().to_vec();
-            let tipo_mensaje = Mensaje::from_bytes(aux[0]);
-
-            match tipo_mensaje {
-                Mensaje::PREPARE => {
-                    let mut yes = Yes::from_string(line); // 🚀
-                    let id = yes.id_cuenta;
-                    yes.id_nodo = self.id_nodo;
-                    let cuenta = self.cuentas.get_mut(&id);
-
-                    if let Some(cuenta) = cuenta {
-                        cuenta.blocked = true;
-                    } else {
-                        self.cuentas.insert(
-                            id,
-                            Cuenta {
-                                blocked: true,
-                                saldo: SALDO_INICIAL,
-                                transacciones: HashMap::new(),
-                            },
-                        );
-                    }
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct AddAvisoDetencion {
+    pub aviso: oneshot::Sender<()>,
+}
+/// Mensaje para que el nodo avise cuando se detenga
+impl Handler<AddAvisoDetencion> for Nodo {
+    type Result = ();
 
-                    if let Err(err) = ctx.address().try_send(SendHandlerToCoordinator {
-                        vec: yes.to_string(),
-                    }) {
-                        println!("ERROR ENVIANDO MENSAJE AL COORDINADOR: {:?}", err);
-                    }
+    fn handle(&mut self, msg: AddAvisoDetencion, _ctx: &mut Context<Self>) -> Self::Result {
+        self.aviso_detencion = Some(msg.aviso);
+    }
+}
+
+/// Al apagarse el proceso (SIGINT o SIGTERM) el nodo escribe lo pendiente del almacen, cierra la
+/// conexion con el coordinador para que vea el cierre en lugar de descubrir la caida al escribir, y se
+/// detiene. La respuesta llega cuando termino de cerrarla
+impl Handler<Apagar> for Nodo {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _: Apagar, _: &mut Context<Self>) -> Self::Result {
+        info!("[NODO-{}] Me apago", self.id_nodo);
+        self.conectado = false;
+        if let Err(err) = self.almacen.cerrar() {
+            error!(
+                "[NODO-{}] Error al cerrar el almacen de cuentas | Detalle: {:?}",
+                self.id_nodo, err
+            );
+        }
+        let stream = self.stream_cordinador.clone();
+        Box::pin(
+            wrap_future::<_, Self>(async move {
+                let _res = stream.lock().await.shutdown().await;
+            })
+            .map(|_, _, ctx: &mut Context<Self>| ctx.stop()),
+        )
+    }
+}
+
+impl Nodo {
+    /// Loguea un error al enviarle un mensaje al destino. Con el coordinador o la cafetera caidos falla
+    /// cada envio, asi que de cada destino se loguea uno por intervalo
+    fn error_de_envio(&mut self, destino: &'static str, detalle: impl std::fmt::Display) {
+        if let Some(omitidos) = self.errores_envio.permitir(destino, self.reloj.ahora()) {
+            error!(
+                "[NODO-{}] Error al enviar mensaje al {} | Detalle: {} | Errores omitidos: {}",
+                self.id_nodo, destino, detalle, omitidos
+            );
+        }
+    }
+
+    /// Procesa un mensaje recibido del coordinador segun su tipo. Retorna error si el mensaje no se
+    /// puede interpretar, en cuyo caso no se aplica ningun cambio
+    fn procesar_mensaje_coordinador(
+        &mut self,
+        bytes: Vec<u8>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), ErrorMensaje> {
+        let tipo_mensaje = Mensaje::from_bytes(
+            *bytes
+                .first()
+                .ok_or(ErrorMensaje::Incompleto("tipo_mensaje"))?,
+        );
+
+        match tipo_mensaje {
+            Mensaje::PREPARE => {
+                let prepare = Prepare::from_bytes(&bytes)?; // 🚀
+                let yes = Yes::new(
+                    self.id_nodo,
+                    prepare.id_cuenta,
+                    prepare.id_transaccion,
+                    prepare.id_cafetera,
+                )
+                .con_id_pedido(prepare.id_pedido);
+                let id = yes.id_cuenta;
+                let cuenta = self.cuentas.get_mut(&id);
+
+                // si la cuenta no existe en este nodo no se puede participar de la transaccion
+                let respuesta = if let Some(cuenta) = cuenta {
+                    cuenta.blocked = true;
+                    self.participante.preparar(
+                        yes.id_transaccion,
+                        Bloqueo {
+                            id_cuenta_destino: None,
+                        },
+                    );
+                    yes.to_bytes()
+                } else {
+                    warn!(
+                        "[NODO-{}] PREPARE sobre ID_CUENTA = {} inexistente, se aborta | ID_PEDIDO = {}",
+                        self.id_nodo, id, yes.id_pedido
+                    );
+                    Abort::new(self.id_nodo, id, yes.id_transaccion, yes.id_cafetera)
+                        .con_id_pedido(yes.id_pedido)
+                        .to_bytes()
+                };
+
+                if let Err(err) = ctx
+                    .address()
+                    .try_send(SendHandlerToCoordinator { vec: respuesta })
+                {
+                    self.error_de_envio("coordinador", err);
                 }
-                Mensaje::EXECUTE => {
-                    let execute = Execute::from_string(line);
-                    let socket = self
-                        .transacciones_resta
-                        .get(&execute.id_transaccion)
-                        .expect("Ya se habia insertado la transacción")
-                        .socket;
-                    println!(
-                        "[NODO-{}] Voy a ejecutar EXECUTE en SOCKET {:?}",
-                        self.id_nodo, socket
+            }
+            Mensaje::TRANSFERENCIA => {
+                // prepare de una transferencia: se bloquean ambas cuentas, siempre en orden
+                // ascendente de id para que todos los nodos las tomen en el mismo orden
+                let transferencia = Transferencia::from_bytes(&bytes)?;
+                let mut cuentas = [transferencia.id_cuenta, transferencia.id_cuenta_destino];
+                cuentas.sort_unstable();
+                let respuesta = if cuentas.iter().all(|id| self.cuentas.contains_key(id)) {
+                    for id in cuentas {
+                        if let Some(cuenta) = self.cuentas.get_mut(&id) {
+                            cuenta.blocked = true;
+                        }
+                    }
+                    self.participante.preparar(
+                        transferencia.id_transaccion,
+                        Bloqueo {
+                            id_cuenta_destino: Some(transferencia.id_cuenta_destino),
+                        },
                     );
-                    if self
-                        .transacciones_resta
-                        .get(&execute.get_id_transaccion())
-                        .expect("Ya se habia insertado la transacción")
-                        .cantidad
-                        > self
-                            .cuentas
-                            .get(&execute.get_id_cuenta())
-                            .expect("La cuenta fue insertad previamente")
-                            .saldo
-                    {
-                        self.addr_actor_cafetera
-                            .as_ref()
-                            .expect("Ya se habia insertado la transacción")
-                            .do_send(ReceiverActorNodo {
-                                vec: Error::new(0, 0, 0).to_bytes(),
-                                socket,
-                            });
+                    Yes::new(
+                        self.id_nodo,
+                        transferencia.id_cuenta,
+                        transferencia.id_transaccion,
+                        transferencia.id_cafetera,
+                    )
+                    .con_id_pedido(transferencia.id_pedido)
+                    .to_bytes()
+                } else {
+                    warn!(
+                        "[NODO-{}] TRANSFERENCIA con una cuenta inexistente, se aborta | ID_PEDIDO = {}",
+                        self.id_nodo, transferencia.id_pedido
+                    );
+                    Abort::new(
+                        self.id_nodo,
+                        transferencia.id_cuenta,
+                        transferencia.id_transaccion,
+                        transferencia.id_cafetera,
+                    )
+                    .con_id_pedido(transferencia.id_pedido)
+                    .to_bytes()
+                };
+                if let Err(err) = ctx
+                    .address()
+                    .try_send(SendHandlerToCoordinator { vec: respuesta })
+                {
+                    self.error_de_envio("coordinador", err);
+                }
+            }
+            Mensaje::EXECUTE => {
+                let execute = Execute::from_bytes(&bytes)?;
+                // un EXECUTE repetido o atrasado puede llegar cuando la transaccion ya se quito (o
+                // nunca se preparo en este nodo): se responde Abort en lugar de ejecutarla
+                let transaccion = self.transacciones_resta.get(&execute.id_transaccion);
+                let saldo = self.cuentas.get(&execute.get_id_cuenta()).map(|x| x.saldo);
+                let (transaccion, saldo) = match (transaccion, saldo) {
+                    (Some(transaccion), Some(saldo)) => (transaccion, saldo),
+                    _ => {
+                        warn!(
+                            "[NODO-{}] EXECUTE de ID_TRANSACCION = {} sin transaccion en curso sobre ID_CUENTA = {}, se aborta | ID_PEDIDO = {}",
+                            self.id_nodo,
+                            execute.id_transaccion,
+                            execute.get_id_cuenta(),
+                            execute.id_pedido
+                        );
                         ctx.address().do_send(SendHandlerToCoordinator {
                             vec: Abort::new(
                                 self.id_nodo,
@@ -249,120 +995,527 @@ impl StreamHandler<Result<String, std::io::Error>> for Nodo {
                                 execute.id_transaccion,
                                 execute.get_id_cafetera(),
                             )
-                            .to_string(),
+                            .con_id_pedido(execute.id_pedido)
+                            .to_bytes(),
                         });
-                        if let Some(transaccion) =
-                            self.transacciones_resta.get_mut(&execute.id_transaccion)
-                        {
-                            transaccion.state = TransactionState::Abort;
+                        return Ok(());
+                    }
+                };
+                let socket = transaccion.socket;
+                info!(
+                    "[NODO-{}] Voy a ejecutar EXECUTE en SOCKET {:?} | ID_PEDIDO = {}",
+                    self.id_nodo, socket, execute.id_pedido
+                );
+                let validacion = match transaccion.id_cuenta_destino {
+                    Some(id_cuenta_destino) => {
+                        let saldo_destino = self
+                            .cuentas
+                            .get(&id_cuenta_destino)
+                            .map(|x| x.saldo)
+                            .unwrap_or_default();
+                        transferir_saldo(saldo, saldo_destino, transaccion.cantidad).map(|_| ())
+                    }
+                    None => restar_saldo(saldo, transaccion.cantidad).map(|_| ()),
+                };
+                if let Err(motivo) = validacion {
+                    info!(
+                        "[NODO-{}] Se aborta ID_TRANSACCION = {} por {:?} | ID_PEDIDO = {}",
+                        self.id_nodo, execute.id_transaccion, motivo, execute.id_pedido
+                    );
+                    self.addr_actor_cafetera
+                        .as_ref()
+                        .expect("Siempre se cuenta con el address del actor cafetera")
+                        .do_send(ReceiverActorNodo {
+                            vec: Error::de_cafetera(IdCafetera::default())
+                                .con_id_pedido(execute.id_pedido)
+                                .to_bytes(),
+                            socket,
+                        });
+                    ctx.address().do_send(SendHandlerToCoordinator {
+                        vec: Abort::new(
+                            self.id_nodo,
+                            execute.get_id_cuenta(),
+                            execute.id_transaccion,
+                            execute.get_id_cafetera(),
+                        )
+                        .con_id_pedido(execute.id_pedido)
+                        .con_motivo(motivo)
+                        .to_bytes(),
+                    });
+                    if let Some(transaccion) =
+                        self.transacciones_resta.get_mut(&execute.id_transaccion)
+                    {
+                        transaccion
+                            .state
+                            .transicion(EventoTransaccion::Abortar, execute.id_transaccion);
+                    }
+                } else {
+                    if let Some(transaccion) =
+                        self.transacciones_resta.get_mut(&execute.id_transaccion)
+                    {
+                        if transaccion.id_cuenta_destino.is_some() {
+                            // la transferencia no requiere preparar un cafe, se finaliza directamente
+                            transaccion
+                                .state
+                                .transicion(EventoTransaccion::Finalizar, execute.id_transaccion);
+                            ctx.address().do_send(SendHandlerToCoordinator {
+                                vec: Finish::new(
+                                    self.id_nodo,
+                                    execute.get_id_cuenta(),
+                                    execute.id_transaccion,
+                                    CommitType::TRANSFERENCIA,
+                                    transaccion.cantidad,
+                                    execute.get_id_cafetera(),
+                                )
+                                .con_id_pedido(execute.id_pedido)
+                                .to_bytes(),
+                            });
+                            return Ok(());
                         }
-                    } else {
-                        if let Some(transaccion) =
-                            self.transacciones_resta.get_mut(&execute.id_transaccion)
-                        {
-                            transaccion.state = TransactionState::Locked;
+                        transaccion
+                            .state
+                            .transicion(EventoTransaccion::Bloquear, execute.id_transaccion);
+                    }
+                    self.addr_actor_cafetera
+                        .as_ref()
+                        .expect("Siempre se cuenta con el address del actor cafetera")
+                        .do_send(ReceiverActorNodo {
+                            vec: OkeyToCafetera::de_cafetera(IdCafetera::default())
+                                .con_id_pedido(execute.id_pedido)
+                                .to_bytes(),
+                            socket,
+                        })
+                }
+            }
+            Mensaje::VALIDAR => {
+                // antes del commit se valida que el cambio de saldo se pueda aplicar en este nodo,
+                // si no se puede se responde Abort y el coordinador aborta la transaccion en todos
+                let validar = Validar::from_bytes(&bytes)?;
+                let validacion = match self.cuentas.get(&validar.id_cuenta) {
+                    // la cuenta no existe en este nodo, no hay saldo que modificar
+                    None => Ok(()),
+                    Some(cuenta) => match self
+                        .participante
+                        .preparada(validar.id_transaccion)
+                        .and_then(|x| x.id_cuenta_destino)
+                        .and_then(|x| self.cuentas.get(&x))
+                    {
+                        Some(destino) => {
+                            transferir_saldo(cuenta.saldo, destino.saldo, validar.cantidad)
+                                .map(|_| ())
                         }
-                        self.addr_actor_cafetera
-                            .as_ref()
-                            .expect("Siempre se cuenta con el address del actor cafetera")
-                            .do_send(ReceiverActorNodo {
-                                vec: OkeyToCafetera::new(0, 0, 0).to_bytes(),
-                                socket,
-                            })
+                        None => restar_saldo(cuenta.saldo, validar.cantidad).map(|_| ()),
+                    },
+                };
+                let respuesta = match validacion {
+                    Ok(()) => Valido::new(
+                        self.id_nodo,
+                        validar.id_cuenta,
+                        validar.id_transaccion,
+                        validar.id_cafetera,
+                    )
+                    .con_id_pedido(validar.id_pedido)
+                    .to_bytes(),
+                    Err(motivo) => {
+                        warn!(
+                            "[NODO-{}] VALIDAR de ID_TRANSACCION = {} rechazado por {:?} | ID_PEDIDO = {}",
+                            self.id_nodo, validar.id_transaccion, motivo, validar.id_pedido
+                        );
+                        Abort::new(
+                            self.id_nodo,
+                            validar.id_cuenta,
+                            validar.id_transaccion,
+                            validar.id_cafetera,
+                        )
+                        .con_id_pedido(validar.id_pedido)
+                        .con_motivo(motivo)
+                        .to_bytes()
                     }
+                };
+                if let Err(err) = ctx
+                    .address()
+                    .try_send(SendHandlerToCoordinator { vec: respuesta })
+                {
+                    self.error_de_envio("coordinador", err);
                 }
-                Mensaje::COMMIT => {
-                    let commit = Commit::from_string(line);
-                    let id = commit.id_cuenta;
-                    self.cuentas.entry(id).or_insert(Cuenta {
-                        blocked: false,
-                        saldo: SALDO_INICIAL,
-                        transacciones: HashMap::new(),
-                    });
-                    let cuenta = self
-                        .cuentas
-                        .get_mut(&id)
-                        .expect("La cuenta ya habia sido insertada");
+            }
+            Mensaje::COMMIT => {
+                let commit = Commit::from_bytes(&bytes)?;
+                let resolucion = self
+                    .participante
+                    .commit(commit.id_transaccion, commit.secuencia);
+                self.registrar_secuencia(commit.secuencia, ctx);
+                let bloqueo = match resolucion {
+                    Resolucion::Aplicar(bloqueo) => bloqueo,
+                    Resolucion::Repetido => {
+                        // el coordinador reenvia el commit si no recibio el ok, o el commit es
+                        // anterior al corte del snapshot aplicado: no se vuelve a aplicar
+                        info!(
+                            "[NODO-{}] COMMIT de ID_TRANSACCION = {} ya aplicado, solo se confirma | ID_PEDIDO = {}",
+                            self.id_nodo, commit.id_transaccion, commit.id_pedido
+                        );
+                        self.confirmar_commit(&commit, ctx);
+                        return Ok(());
+                    }
+                };
+                let id = commit.id_cuenta;
+                let id_cuenta_destino = if commit.tipo as u8 == CommitType::TRANSFERENCIA as u8 {
+                    bloqueo.and_then(|x| x.id_cuenta_destino)
+                } else {
+                    None
+                };
+                if let Some(destino) = id_cuenta_destino.and_then(|x| self.cuentas.get_mut(&x)) {
+                    destino.blocked = false;
+                }
+                let saldo_destino = id_cuenta_destino
+                    .and_then(|x| self.cuentas.get(&x))
+                    .map(|x| x.saldo);
+                let cuenta = match self.cuentas.get_mut(&id) {
+                    Some(cuenta) => cuenta,
+                    None => {
+                        // la cuenta se dio de baja en este nodo: no hay saldo que modificar, pero se
+                        // confirma igual para que el coordinador no quede esperando la respuesta
+                        warn!(
+                            "[NODO-{}] COMMIT sobre ID_CUENTA = {} inexistente | ID_PEDIDO = {}",
+                            self.id_nodo, id, commit.id_pedido
+                        );
+                        self.registrar_commit(&commit, id_cuenta_destino);
+                        self.confirmar_commit(&commit, ctx);
+                        return Ok(());
+                    }
+                };
 
-                    cuenta.blocked = false;
-                    if commit.tipo as u8 == CommitType::SUMA as u8 {
-                        cuenta.saldo += commit.cantidad;
-                        if let Some(transaccion) =
-                            self.transacciones_suma.get_mut(&commit.id_transaccion)
-                        {
-                            transaccion.state = TransactionState::Accepted;
-                        }
-                    } else {
-                        if let Some(transaccion) =
-                            self.transacciones_resta.get_mut(&commit.id_transaccion)
+                cuenta.blocked = false;
+                if commit.tipo as u8 == CommitType::SUMA as u8 {
+                    match sumar_saldo(cuenta.saldo, commit.cantidad) {
+                        Ok(saldo) => cuenta.saldo = saldo,
+                        Err(motivo) => warn!(
+                            "[NODO-{}] COMMIT de ID_TRANSACCION = {} no aplicado por {:?} | ID_PEDIDO = {}",
+                            self.id_nodo, commit.id_transaccion, motivo, commit.id_pedido
+                        ),
+                    }
+                    if let Some(transaccion) =
+                        self.transacciones_suma.get_mut(&commit.id_transaccion)
+                    {
+                        transaccion
+                            .state
+                            .transicion(EventoTransaccion::Commit, commit.id_transaccion);
+                    }
+                } else {
+                    if let Some(transaccion) =
+                        self.transacciones_resta.get_mut(&commit.id_transaccion)
+                    {
+                        // si la transaccion es tuya. Si ya se habia abortado, la cafetera recibio el
+                        // error y no se le envia el okey
+                        if transaccion
+                            .state
+                            .transicion(EventoTransaccion::Commit, commit.id_transaccion)
                         {
-                            transaccion.state = TransactionState::Accepted;
-                            // si la transaccion es tuya
                             if let Err(err) = self
                                 .addr_actor_cafetera
                                 .as_ref()
                                 .expect("Siempre se cuenta con el address del actor cafetera")
                                 .try_send(ReceiverActorNodo {
-                                    vec: OkeyToCafetera::new(0, 0, 0).to_bytes(),
+                                    vec: OkeyToCafetera::de_cafetera(IdCafetera::default())
+                                        .con_id_pedido(transaccion.id_pedido)
+                                        .to_bytes(),
                                     socket: transaccion.socket,
                                 })
                             {
-                                println!("[NODO-{}] ERROR ENVIANDO MENSAJE AL ACTOR CAFETERA | Detalle: {:?}", self.id_nodo, err);
+                                error!("[NODO-{}] ERROR ENVIANDO MENSAJE AL ACTOR CAFETERA | Detalle: {:?}", self.id_nodo, err);
                             }
                         }
-                        cuenta.saldo -= commit.cantidad
+                    }
+                    let resultado = match saldo_destino {
+                        Some(saldo_destino) => {
+                            transferir_saldo(cuenta.saldo, saldo_destino, commit.cantidad)
+                                .map(|(origen, destino)| (origen, Some(destino)))
+                        }
+                        None => restar_saldo(cuenta.saldo, commit.cantidad).map(|x| (x, None)),
                     };
+                    match resultado {
+                        Ok((saldo, saldo_destino)) => {
+                            cuenta.saldo = saldo;
+                            if let Some((destino, saldo_destino)) = id_cuenta_destino
+                                .and_then(|x| self.cuentas.get_mut(&x))
+                                .zip(saldo_destino)
+                            {
+                                destino.saldo = saldo_destino;
+                            }
+                        }
+                        Err(motivo) => warn!(
+                            "[NODO-{}] COMMIT de ID_TRANSACCION = {} no aplicado por {:?} | ID_PEDIDO = {}",
+                            self.id_nodo, commit.id_transaccion, motivo, commit.id_pedido
+                        ),
+                    }
+                };
+                self.persistir_cuenta(id);
+                if let Some(id_cuenta_destino) = id_cuenta_destino {
+                    self.persistir_cuenta(id_cuenta_destino);
+                }
+                self.registrar_commit(&commit, id_cuenta_destino);
 
-                    ctx.address().do_send(SendHandlerToCoordinator {
-                        vec: OkeyToCoordinator::new(
-                            self.id_nodo,
-                            commit.id_cuenta,
-                            commit.id_transaccion,
-                            commit.id_cafetera,
-                        )
-                        .to_string(),
-                    });
+                self.confirmar_commit(&commit, ctx);
 
-                    println!(
-                        "[NODO-{}] LLEGO COMMIT, CUENTAS: {:?}",
-                        self.id_nodo, self.cuentas.iter().map(|(k, v)| (k, v.saldo)).collect::<Vec<_>>()
-                    );
-                }
-                Mensaje::ABORT => {
-                    let abort = Abort::from_string(line);
-                    let id = abort.id_cuenta;
-                    let cuenta = self
-                        .cuentas
-                        .get_mut(&id)
-                        .expect("La cuenta ya fue insertada");
+                debug!(
+                    "[NODO-{}] LLEGO COMMIT | ID_PEDIDO = {}",
+                    self.id_nodo, commit.id_pedido
+                );
+                trace!(
+                    "[NODO-{}] Saldos luego del COMMIT | ID_PEDIDO = {} | CUENTAS: {:?}",
+                    self.id_nodo,
+                    commit.id_pedido,
+                    self.cuentas
+                        .iter()
+                        .map(|(k, v)| (k, v.saldo))
+                        .collect::<Vec<_>>()
+                );
+            }
+            Mensaje::ABORT => {
+                let abort = Abort::from_bytes(&bytes)?;
+                self.registrar_secuencia(abort.secuencia, ctx);
+                let id = abort.id_cuenta;
+                if let Some(cuenta) = self.cuentas.get_mut(&id) {
                     cuenta.blocked = false;
+                }
+                if let Some(destino) = self
+                    .participante
+                    .abort(abort.id_transaccion)
+                    .and_then(|x| x.id_cuenta_destino)
+                    .and_then(|x| self.cuentas.get_mut(&x))
+                {
+                    destino.blocked = false;
+                }
 
-                    ctx.address().do_send(SendHandlerToCoordinator {
-                        vec: OkeyAbortToCoordinator::new(
-                            self.id_nodo,
-                            abort.id_cuenta,
-                            abort.id_transaccion,
-                            abort.get_id_cafetera(),
-                        )
-                        .to_string(),
-                    });
+                // si la resta es de este nodo y seguia en curso (por ejemplo porque el coordinador
+                // la dio por vencida), se avisa a la cafetera que el pedido no se pudo completar
+                if let Some(transaccion) = self.transacciones_resta.get_mut(&abort.id_transaccion) {
+                    if transaccion.state.en_curso()
+                        && transaccion
+                            .state
+                            .transicion(EventoTransaccion::Abortar, abort.id_transaccion)
+                    {
+                        self.addr_actor_cafetera
+                            .as_ref()
+                            .expect("Siempre se cuenta con el address del actor cafetera")
+                            .do_send(ReceiverActorNodo {
+                                vec: Error::de_cafetera(IdCafetera::default())
+                                    .con_id_pedido(transaccion.id_pedido)
+                                    .to_bytes(),
+                                socket: transaccion.socket,
+                            });
+                    }
                 }
 
-                _ => (),
+                ctx.address().do_send(SendHandlerToCoordinator {
+                    vec: OkeyAbortToCoordinator::new(
+                        self.id_nodo,
+                        abort.id_cuenta,
+                        abort.id_transaccion,
+                        abort.get_id_cafetera(),
+                    )
+                    .con_id_pedido(abort.id_pedido)
+                    .to_bytes(),
+                });
+            }
+            Mensaje::CONFIRMADO => {
+                let confirmado = ConfirmadoToNodo::from_bytes(&bytes)?;
+                if let Some(transaccion) = self
+                    .transacciones_suma
+                    .get_mut(&confirmado.id_transaccion)
+                    .and_then(|x| {
+                        x.state
+                            .transicion(EventoTransaccion::Confirmar, confirmado.id_transaccion)
+                            .then_some(x)
+                    })
+                {
+                    let mut mensaje = Confirmado::new(
+                        transaccion.id_cafetera,
+                        transaccion.id_cuenta,
+                        transaccion.cantidad,
+                    );
+                    mensaje.id_pedido = transaccion.id_pedido;
+                    self.addr_actor_cafetera
+                        .as_ref()
+                        .expect("Siempre se cuenta con el address del actor cafetera")
+                        .do_send(ReceiverActorNodo {
+                            vec: mensaje.to_bytes(),
+                            socket: transaccion.socket,
+                        });
+                }
+            }
+            Mensaje::FALLIDO => {
+                let fallido = FallidoToNodo::from_bytes(&bytes)?;
+                if let Some(transaccion) = self
+                    .transacciones_suma
+                    .get_mut(&fallido.id_transaccion)
+                    .and_then(|x| {
+                        x.state
+                            .transicion(EventoTransaccion::Abortar, fallido.id_transaccion)
+                            .then_some(x)
+                    })
+                {
+                    let mut mensaje = Fallido::new(
+                        transaccion.id_cafetera,
+                        transaccion.id_cuenta,
+                        transaccion.cantidad,
+                    );
+                    mensaje.id_pedido = transaccion.id_pedido;
+                    self.addr_actor_cafetera
+                        .as_ref()
+                        .expect("Siempre se cuenta con el address del actor cafetera")
+                        .do_send(ReceiverActorNodo {
+                            vec: mensaje.to_bytes(),
+                            socket: transaccion.socket,
+                        });
+                }
+            }
+            Mensaje::ALTA => {
+                let alta = AltaCord::from_bytes(&bytes)?;
+                if let Entry::Vacant(entrada) = self.cuentas.entry(alta.id_cuenta) {
+                    entrada.insert(Cuenta::new(config().servidor.saldo_inicial));
+                    self.persistir_cuenta(alta.id_cuenta);
+                    self.registro.registrar(
+                        self.id_coordinador,
+                        EventoNodo::Alta {
+                            id_cuenta: alta.id_cuenta,
+                            saldo: config().servidor.saldo_inicial,
+                        },
+                    );
+                    info!(
+                        "[NODO-{}] ALTA de ID_CUENTA = {} con saldo {}",
+                        self.id_nodo,
+                        alta.id_cuenta,
+                        config().servidor.saldo_inicial
+                    );
+                } else {
+                    warn!(
+                        "[NODO-{}] ALTA de ID_CUENTA = {} ya existente, se ignora",
+                        self.id_nodo, alta.id_cuenta
+                    );
+                }
+            }
+            Mensaje::BAJA => {
+                let baja = BajaCord::from_bytes(&bytes)?;
+                match self.cuentas.get(&baja.id_cuenta) {
+                    Some(cuenta) if cuenta.blocked => warn!(
+                        "[NODO-{}] BAJA de ID_CUENTA = {} en uso, se ignora",
+                        self.id_nodo, baja.id_cuenta
+                    ),
+                    Some(_) => {
+                        self.cuentas.remove(&baja.id_cuenta);
+                        self.persistir_cuenta(baja.id_cuenta);
+                        self.registro.registrar(
+                            self.id_coordinador,
+                            EventoNodo::Baja {
+                                id_cuenta: baja.id_cuenta,
+                            },
+                        );
+                        info!(
+                            "[NODO-{}] BAJA de ID_CUENTA = {}",
+                            self.id_nodo, baja.id_cuenta
+                        );
+                    }
+                    None => warn!(
+                        "[NODO-{}] BAJA de ID_CUENTA = {} inexistente, se ignora",
+                        self.id_nodo, baja.id_cuenta
+                    ),
+                }
+            }
+            Mensaje::PEDIDOSNAPSHOT => {
+                let pedido = PedidoSnapshot::from_bytes(&bytes)?;
+                let cuentas = self.saldos();
+                info!(
+                    "[NODO-{}] Envio snapshot de {} cuentas para el ID_NODO = {} con corte {}",
+                    self.id_nodo,
+                    cuentas.len(),
+                    pedido.id_nodo,
+                    pedido.corte
+                );
+                ctx.address().do_send(SendHandlerToCoordinator {
+                    vec: Snapshot::new(pedido.id_nodo, pedido.corte, cuentas).to_bytes(),
+                });
+            }
+            Mensaje::SNAPSHOT => {
+                let snapshot = Snapshot::from_bytes(&bytes)?;
+                let ids: HashSet<IdCuenta> = snapshot.cuentas.iter().map(|(id, _)| *id).collect();
+                // las cuentas en uso por transacciones propias se conservan, el resto se reemplaza
+                self.cuentas
+                    .retain(|id_cuenta, cuenta| cuenta.blocked || ids.contains(id_cuenta));
+                for (id_cuenta, saldo) in snapshot.cuentas {
+                    match self.cuentas.entry(id_cuenta) {
+                        Entry::Occupied(mut entrada) => entrada.get_mut().saldo = saldo,
+                        Entry::Vacant(entrada) => {
+                            entrada.insert(Cuenta::new(saldo));
+                        }
+                    }
+                }
+                self.persistir_cuentas();
+                self.participante.sincronizar(snapshot.corte);
+                self.registro.registrar(
+                    self.id_coordinador,
+                    EventoNodo::Snapshot {
+                        corte: snapshot.corte,
+                        cuentas: self.saldos(),
+                    },
+                );
+                info!(
+                    "[NODO-{}] Snapshot aplicado con corte {} | CUENTAS: {:?}",
+                    self.id_nodo,
+                    snapshot.corte,
+                    self.cuentas
+                        .iter()
+                        .map(|(k, v)| (k, v.saldo))
+                        .collect::<Vec<_>>()
+                );
+            }
+            Mensaje::PEDIDOAUDITORIA => {
+                // el pedido llega detras de los COMMIT/ABORT difundidos antes, que ya estan aplicados
+                let pedido = PedidoAuditoria::from_bytes(&bytes)?;
+                let auditoria = Auditoria::new(self.id_nodo, pedido.id_auditoria, self.saldos());
+                ctx.address().do_send(SendHandlerToCoordinator {
+                    vec: auditoria.to_bytes(),
+                });
+            }
+            Mensaje::RECHAZO => {
+                let rechazo = Rechazo::from_bytes(&bytes)?;
+                error!(
+                    "[NODO-{}] El coordinador rechazo un mensaje de tipo {:?} con ID_NODO = {:?} (conexion registrada como ID_NODO = {:?})",
+                    self.id_nodo,
+                    Mensaje::from_bytes(rechazo.tipo),
+                    rechazo.id_nodo_mensaje,
+                    rechazo.id_nodo
+                );
+            }
+
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// Mensaje que se recibe escuchando al coordinador, handlea según el tipo de mensaje recibido
+impl StreamHandler<Result<Vec<u8>, std::io::Error>> for Nodo {
+    fn handle(&mut self, read: Result<Vec<u8>, std::io::Error>, ctx: &mut Self::Context) {
+        if let Ok(bytes) = read {
+            if let Err(err) = self.procesar_mensaje_coordinador(bytes, ctx) {
+                warn!(
+                    "[NODO-{}] Se descarta un mensaje invalido del coordinador: {}",
+                    self.id_nodo, err
+                );
             }
-        } else {
         }
     }
 
     fn finished(&mut self, _ctx: &mut Self::Context) {
-        if self.conectado && self.id_nodo != self.id_coordinador {
+        if self.conectado && !self.replica && self.id_nodo != self.id_coordinador {
             if let Err(err) = self
                 .addr_actor_bully
                 .as_ref()
                 .expect("Siempre se cuenta con el address del actor bully")
                 .try_send(StartElection {})
             {
-                println!(
+                error!(
                     "[NODO-{}] ERROR ENVIANDO MENSAJE AL ACTOR BULLY | Detalle: {:?}",
                     self.id_nodo, err
                 );
@@ -370,26 +1523,27 @@ impl StreamHandler<Result<String, std::io::Error>> for Nodo {
         }
         self.transacciones_resta
             .iter_mut()
-            .for_each(|(_, transaccion)| {
-                match transaccion.state {
-                    TransactionState::WaitCommit
-                    | TransactionState::Wait
-                    | TransactionState::Locked => {
-                        transaccion.state = TransactionState::Abort;
-                        // Error a la cafetera
-                        self.addr_actor_cafetera
-                            .as_ref()
-                            .expect("Siempre se cuenta con el address del actor cafetera")
-                            .do_send(ReceiverActorNodo {
-                                vec: Error::new(0, 0, 0).to_bytes(),
-                                socket: transaccion.socket,
-                            });
-                    }
-                    _ => {}
+            .for_each(|(id_transaccion, transaccion)| {
+                if transaccion.state.en_curso()
+                    && transaccion
+                        .state
+                        .transicion(EventoTransaccion::Abortar, *id_transaccion)
+                {
+                    // Error a la cafetera
+                    self.addr_actor_cafetera
+                        .as_ref()
+                        .expect("Siempre se cuenta con el address del actor cafetera")
+                        .do_send(ReceiverActorNodo {
+                            vec: Error::de_cafetera(IdCafetera::default())
+                                .con_id_pedido(transaccion.id_pedido)
+                                .to_bytes(),
+                            socket: transaccion.socket,
+                        });
                 }
             });
 
         self.conectado = false;
+        self.reconciliar = true;
     }
 }
 
@@ -397,61 +1551,64 @@ impl StreamHandler<Result<String, std::io::Error>> for Nodo {
 #[rtype(result = "()")]
 pub struct ReceiveNewCoordinator {
     ///id del nuevo coordinador
-    pub id_nodo_coordinador: u8,
+    pub id_nodo_coordinador: IdNodo,
 }
 /// Hubo un cambio de coordinador, cambia el id y se conecta
 impl Handler<ReceiveNewCoordinator> for Nodo {
     type Result = ();
 
     fn handle(&mut self, msg: ReceiveNewCoordinator, ctx: &mut Context<Self>) -> Self::Result {
+        if self.id_coordinador != msg.id_nodo_coordinador {
+            // cada coordinador numera sus COMMIT/ABORT desde cero
+            self.participante.sincronizar(0);
+        }
         self.id_coordinador = msg.id_nodo_coordinador;
-        println!(
+        info!(
             "[NODO-{}] Conectado con el nuevo ID_COORDINADOR = {}",
             self.id_nodo, self.id_coordinador
         );
 
         let id_coordinador = self.id_coordinador;
         let my_id = self.id_nodo;
+        let modo = if self.reconciliar {
+            // las sumas que acepto desconectado se envian recien ahora, y el coordinador las
+            // aplica sobre los saldos de referencia
+            ModoConexion::Reconciliar
+        } else {
+            ModoConexion::ConEstado
+        };
 
         wrap_future::<_, Self>(async move {
-            let stream_cordinador = tokio::net::TcpStream::connect(id_to_ctrladdr(id_coordinador))
-                .await
-                .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorConexion));
-
-            if let Ok(mut stream) = stream_cordinador {
-                stream
-                    .write(&[my_id])
-                    .await
-                    .map_err(|x| ErrorServer::new(&x.to_string(), TipoError::ErrorConexion))?;
-                Ok(stream)
-            } else {
-                Err(ErrorServer::new(
-                    "[ERROR-IMPOSIBLE-OCURRIR] no pude conectarme al nuevo coordinador!!",
-                    TipoError::ErrorConexion,
-                ))
-            }
+            let mut stream = tokio::net::TcpStream::connect(id_to_ctrladdr(id_coordinador)).await?;
+            configurar_keepalive(&stream)?;
+            stream.write_all(&[my_id.0, modo as u8]).await?;
+            Ok::<_, ErrorServer>(stream)
         })
         .map(|stream_cordinador, this, ctx| {
             if let Ok(stream) = stream_cordinador {
                 let (read, write_half) = split(stream);
 
-                ctx.add_stream(LinesStream::new(BufReader::new(read).lines()));
-                this.stream_cordinador = Arc::new(Mutex::new(write_half));
-                println!(
+                ctx.add_stream(leer_mensajes(read));
+                this.stream_cordinador = Arc::new(Mutex::new(Box::new(write_half)));
+                info!(
                     "[NODO-{:?}] Ya me conecté al nuevo cordinador con ID {:?}",
                     this.id_nodo, this.id_coordinador
                 );
 
                 this.conectado = true;
+                this.reconciliar = false;
             } else {
-                println!("[ERROR-IMPOSIBLE-OCURRIR] No me pude conectar al nuevo coordinador!!");
+                error!("[ERROR-IMPOSIBLE-OCURRIR] No me pude conectar al nuevo coordinador!!");
             }
         })
         .wait(ctx);
 
         for (id_transaccion, transaccion) in self.transacciones_suma.iter_mut() {
-            if transaccion.state == TransactionState::ToSend {
-                transaccion.state = TransactionState::WaitCommit;
+            if transaccion.state == TransactionState::ToSend
+                && transaccion
+                    .state
+                    .transicion(EventoTransaccion::Finalizar, *id_transaccion)
+            {
                 let finish = Finish::new(
                     self.id_nodo,
                     transaccion.id_cuenta,
@@ -459,9 +1616,10 @@ impl Handler<ReceiveNewCoordinator> for Nodo {
                     CommitType::SUMA,
                     transaccion.cantidad,
                     transaccion.id_cafetera,
-                );
+                )
+                .con_id_pedido(transaccion.id_pedido);
                 let _res = ctx.address().try_send(SendHandlerToCoordinator {
-                    vec: finish.f_to_string(),
+                    vec: finish.to_bytes(),
                 });
             }
         }
@@ -471,48 +1629,55 @@ impl Handler<ReceiveNewCoordinator> for Nodo {
 #[derive(Message)]
 #[rtype(result = "()")]
 struct SendHandlerToCoordinator {
-    vec: String,
+    vec: Vec<u8>,
 }
 
 /// Mensaje cuando hay que enviarle algo al coordinador
 impl Handler<SendHandlerToCoordinator> for Nodo {
     type Result = ();
 
-    fn handle(
-        &mut self,
-        mut msg: SendHandlerToCoordinator,
-        ctx: &mut Context<Self>,
-    ) -> Self::Result {
+    fn handle(&mut self, msg: SendHandlerToCoordinator, ctx: &mut Context<Self>) -> Self::Result {
         let stream_coor_clone = self.stream_cordinador.clone();
         let addr_actor_bully = self.addr_actor_bully.clone();
         let id_nodo = self.id_nodo;
-        msg.vec.push('\n');
+        let replica = self.replica;
+        let entrega = caos::entrega(self.id_nodo.0, self.id_coordinador.0);
         wrap_future::<_, Self>(async move {
-            stream_coor_clone
-                .lock()
-                .await
-                .write_all(msg.vec.as_bytes())
-                .await
-                .map_err(|x| {
-                    if x.kind() == std::io::ErrorKind::BrokenPipe {
-                        println!(
-                            "[NODO-{}] El coordinador se cayó, mando StartElection",
-                            id_nodo
+            let mut stream = stream_coor_clone.lock().await;
+            caos::esperar_entrega(entrega).await;
+            let resultado = escribir_con_plazo(&mut *stream, &msg.vec).await;
+            if let Err(x) = &resultado {
+                // un coordinador que deja de leer (por ejemplo porque se cayo sin cerrar la
+                // conexion) se trata igual que uno que la cerro
+                let caido = matches!(
+                    x.kind(),
+                    std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::TimedOut
+                );
+                if caido && !replica {
+                    // se repite en cada envio mientras no haya coordinador, el error se loguea
+                    // limitado al volver al actor
+                    debug!(
+                        "[NODO-{}] El coordinador se cayó, mando StartElection",
+                        id_nodo
+                    );
+                    if let Err(err) = addr_actor_bully
+                        .as_ref()
+                        .expect("Siempre se cuenta con el address del actor bully")
+                        .try_send(StartElection {})
+                    {
+                        error!(
+                            "[NODO-{}] Error al enviar mensaje al bully | Detalle: {}",
+                            id_nodo, err
                         );
-                        if let Err(err) = addr_actor_bully
-                            .as_ref()
-                            .expect("Siempre se cuenta con el address del actor bully")
-                            .try_send(StartElection {})
-                        {
-                            println!(
-                                "[NODO-{}] Error al enviar mensaje al bully | Detalle: {}",
-                                id_nodo, err
-                            );
-                        }
                     }
-                    println!("Error al enviar mensaje al coordinador: {}", x);
-                })
-                .expect("Error al enviar mensaje al coordinador");
+                }
+            }
+            resultado
+        })
+        .map(|resultado, this, _| {
+            if let Err(x) = resultado {
+                this.error_de_envio("coordinador", x);
+            }
         })
         .spawn(ctx);
     }
@@ -523,41 +1688,66 @@ impl Handler<SendHandlerToCoordinator> for Nodo {
 pub struct ReceiveFromCafetera {
     pub msg: Vec<u8>,
     pub socket: SocketAddr,
+    /// true si llego dentro de un AUTENTICADO con firma valida. Solo esos comandos se adelantan
+    /// en la cola de la cuenta
+    pub autenticado: bool,
 }
 
-/// Mensaje cuando se recibe algo de cafetera listener, handlea según el tipo del mensaje
-impl Handler<ReceiveFromCafetera> for Nodo {
-    type Result = ();
+impl Nodo {
+    /// true si el CONECTAR/DESCONECTAR con la secuencia indicada se puede aplicar. Los comandos
+    /// repetidos o fuera de la ventana se descartan y se loguean
+    fn aceptar_comando_admin(&mut self, comando: &str, secuencia: u64) -> bool {
+        match self
+            .comandos_admin
+            .aceptar(secuencia, secuencia_comando_admin())
+        {
+            Ok(()) => true,
+            Err(motivo) => {
+                warn!(
+                    "[NODO-{}] Se descarta un {} con SECUENCIA = {} | Motivo: {:?}",
+                    self.id_nodo, comando, secuencia, motivo
+                );
+                false
+            }
+        }
+    }
 
-    fn handle(&mut self, msg: ReceiveFromCafetera, ctx: &mut Context<Self>) -> Self::Result {
-        let tipo_mensaje = MensajeCafetera::from_bytes(msg.msg[0]);
+    /// Procesa un mensaje recibido de una cafetera segun su tipo. Retorna error si el mensaje no se
+    /// puede interpretar, en cuyo caso no se aplica ningun cambio
+    fn procesar_mensaje_cafetera(
+        &mut self,
+        msg: ReceiveFromCafetera,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), ErrorMensaje> {
+        let tipo_mensaje = match msg.msg.first() {
+            Some(tipo) => MensajeCafetera::from_bytes(*tipo),
+            None => return Err(ErrorMensaje::Incompleto("tipo_mensaje")),
+        };
+        if self.replica || self.drenando.is_some() {
+            if let MensajeCafetera::SUMAR
+            | MensajeCafetera::RESTAR
+            | MensajeCafetera::TRANSFERIR
+            | MensajeCafetera::ALTA
+            | MensajeCafetera::BAJA = tipo_mensaje
+            {
+                self.rechazar_pedido_nuevo(tipo_mensaje, &msg.msg, msg.socket);
+                return Ok(());
+            }
+        }
         match tipo_mensaje {
             MensajeCafetera::SUMAR => {
-                let mensaje = Sumar::from_bytes(msg.msg.clone());
-                let new_id_transaccion: u32 = (self.id_nodo.to_string()
-                    + mensaje.id_cafetera.to_string().as_str()
-                    + self.id_nueva_orden().to_string().as_str())
-                .parse()
-                .expect("Error al parsear el id de la transaccion");
-
+                let mensaje = Sumar::from_bytes(&msg.msg)?;
                 let id = mensaje.get_id_cuenta();
-                let cuenta = self.cuentas.get_mut(&id);
-                if let Some(cuenta) = cuenta {
+                if !self.cuentas.contains_key(&id) {
+                    self.rechazar_cuenta_inexistente(id, mensaje.id_pedido, msg.socket);
+                    return Ok(());
+                }
+                let new_id_transaccion = self.id_nueva_transaccion();
+
+                if let Some(cuenta) = self.cuentas.get_mut(&id) {
                     cuenta
                         .transacciones
-                        .insert(mensaje.id_cafetera, new_id_transaccion);
-                } else {
-                    self.cuentas.insert(
-                        id,
-                        Cuenta {
-                            blocked: false,
-                            saldo: SALDO_INICIAL,
-                            transacciones: HashMap::from([(
-                                mensaje.id_cafetera,
-                                new_id_transaccion,
-                            )]),
-                        },
-                    );
+                        .insert((msg.socket, mensaje.id_pedido), new_id_transaccion);
                 }
 
                 self.transacciones_suma.insert(
@@ -568,33 +1758,51 @@ impl Handler<ReceiveFromCafetera> for Nodo {
                         state: TransactionState::Wait,
                         id_cuenta: id,
                         id_cafetera: mensaje.id_cafetera,
+                        id_pedido: mensaje.id_pedido,
+                        id_cuenta_destino: None,
+                        prioritaria: msg.autenticado,
+                        terminada: None,
                     },
                 );
+                info!(
+                    "[NODO-{}] SUMA recibida | ID_TRANSACCION = {} | ID_PEDIDO = {}",
+                    self.id_nodo, new_id_transaccion, mensaje.id_pedido
+                );
 
                 if let Err(err) = self
                     .addr_actor_cafetera
                     .as_ref()
                     .expect("Siempre se cuenta con el address del actor cafetera")
                     .try_send(ReceiverActorNodo {
-                        vec: OkeyToCafetera::new(0, 0, 0).to_bytes(),
+                        vec: OkeyToCafetera::de_cafetera(IdCafetera::default())
+                            .con_id_pedido(mensaje.id_pedido)
+                            .to_bytes(),
                         socket: msg.socket,
                     })
                 {
-                    println!(
-                        "[NODO-{}] Error al enviar mensaje al actor cafetera | Detalle: {}",
-                        self.id_nodo, err
-                    );
+                    self.error_de_envio("actor cafetera", err);
                 }
             }
             MensajeCafetera::RESTAR => {
                 if self.conectado {
-                    let mensaje = Restar::from_bytes(msg.msg.clone());
+                    let mensaje = Restar::from_bytes(&msg.msg)?;
+                    if !self.cuentas.contains_key(&mensaje.id_cuenta) {
+                        self.rechazar_cuenta_inexistente(
+                            mensaje.id_cuenta,
+                            mensaje.id_pedido,
+                            msg.socket,
+                        );
+                        return Ok(());
+                    }
+                    if let Some(id_transaccion) = self.resta_existente(msg.socket, &mensaje) {
+                        self.responder_resta_repetida(id_transaccion, ctx);
+                        return Ok(());
+                    }
+                    if self.responder_resta_terminada(msg.socket, &mensaje) {
+                        return Ok(());
+                    }
 
-                    let new_id_transaccion: u32 = (self.id_nodo.to_string()
-                        + mensaje.id_cafetera.to_string().as_str()
-                        + self.id_nueva_orden().to_string().as_str())
-                    .parse()
-                    .expect("Error al parsear el nuevo id de la transaccion");
+                    let new_id_transaccion = self.id_nueva_transaccion();
 
                     self.transacciones_resta.insert(
                         new_id_transaccion,
@@ -604,86 +1812,176 @@ impl Handler<ReceiveFromCafetera> for Nodo {
                             state: TransactionState::Wait,
                             id_cafetera: mensaje.id_cafetera,
                             id_cuenta: mensaje.id_cuenta,
+                            id_pedido: mensaje.id_pedido,
+                            id_cuenta_destino: None,
+                            prioritaria: msg.autenticado,
+                            terminada: None,
                         },
                     );
+                    info!(
+                        "[NODO-{}] RESTA recibida | ID_TRANSACCION = {} | ID_PEDIDO = {}",
+                        self.id_nodo, new_id_transaccion, mensaje.id_pedido
+                    );
 
                     let starter = Starter {
-                        tipo_mensaje: Mensaje::STARTER.to_bytes(),
                         id_cuenta: mensaje.get_id_cuenta(),
                         id_nodo: self.id_nodo,
                         id_transaccion: new_id_transaccion,
                         id_cafetera: mensaje.get_id_cafetera(),
+                        id_pedido: mensaje.id_pedido,
+                        prioritaria: msg.autenticado,
                     };
 
                     let id = mensaje.get_id_cuenta();
-                    let cuenta = self.cuentas.get_mut(&id);
-                    if let Some(cuenta) = cuenta {
+                    if let Some(cuenta) = self.cuentas.get_mut(&id) {
                         cuenta
                             .transacciones
-                            .insert(mensaje.id_cafetera, new_id_transaccion);
-                    } else {
-                        self.cuentas.insert(
-                            id,
-                            Cuenta {
-                                blocked: false,
-                                saldo: SALDO_INICIAL,
-                                transacciones: HashMap::from([(
-                                    mensaje.id_cafetera,
-                                    new_id_transaccion,
-                                )]),
-                            },
-                        );
+                            .insert((msg.socket, mensaje.id_pedido), new_id_transaccion);
                     }
 
                     let _res = ctx.address().try_send(SendHandlerToCoordinator {
-                        vec: starter.to_string(),
+                        vec: starter.to_bytes(),
                     });
                 } else if let Err(err) = self
                     .addr_actor_cafetera
                     .as_ref()
                     .expect("Siempre se cuenta con el address del actor cafetera")
                     .try_send(ReceiverActorNodo {
-                        vec: Error::new(0, 0, 0).to_bytes(),
+                        vec: Error::de_cafetera(IdCafetera::default())
+                            .con_id_pedido(id_pedido_from_bytes(&msg.msg))
+                            .to_bytes(),
                         socket: msg.socket,
                     })
                 {
-                    println!(
-                        "[NODO-{}] Error al enviar mensaje al actor cafetera | Detalle: {}",
-                        self.id_nodo, err
+                    self.error_de_envio("actor cafetera", err);
+                }
+            }
+            MensajeCafetera::TRANSFERIR => {
+                if self.conectado {
+                    let mensaje = Transferir::from_bytes(&msg.msg)?;
+                    for id in [mensaje.id_cuenta, mensaje.id_cuenta_destino] {
+                        if !self.cuentas.contains_key(&id) {
+                            self.rechazar_cuenta_inexistente(id, mensaje.id_pedido, msg.socket);
+                            return Ok(());
+                        }
+                    }
+
+                    let new_id_transaccion = self.id_nueva_transaccion();
+
+                    // la transferencia se gestiona como una resta de la cuenta origen
+                    self.transacciones_resta.insert(
+                        new_id_transaccion,
+                        Transaction {
+                            socket: msg.socket,
+                            cantidad: mensaje.cantidad_modificar,
+                            state: TransactionState::Wait,
+                            id_cafetera: mensaje.id_cafetera,
+                            id_cuenta: mensaje.id_cuenta,
+                            id_pedido: mensaje.id_pedido,
+                            id_cuenta_destino: Some(mensaje.id_cuenta_destino),
+                            prioritaria: msg.autenticado,
+                            terminada: None,
+                        },
+                    );
+                    info!(
+                        "[NODO-{}] TRANSFERENCIA recibida de ID_CUENTA = {} a ID_CUENTA = {} | ID_TRANSACCION = {} | ID_PEDIDO = {}",
+                        self.id_nodo,
+                        mensaje.id_cuenta,
+                        mensaje.id_cuenta_destino,
+                        new_id_transaccion,
+                        mensaje.id_pedido
                     );
+
+                    let transferencia = Transferencia::new(
+                        self.id_nodo,
+                        mensaje.id_cuenta,
+                        new_id_transaccion,
+                        mensaje.id_cafetera,
+                        mensaje.id_cuenta_destino,
+                    )
+                    .con_id_pedido(mensaje.id_pedido)
+                    .con_prioridad(msg.autenticado);
+                    let _res = ctx.address().try_send(SendHandlerToCoordinator {
+                        vec: transferencia.to_bytes(),
+                    });
+                } else if let Err(err) = self
+                    .addr_actor_cafetera
+                    .as_ref()
+                    .expect("Siempre se cuenta con el address del actor cafetera")
+                    .try_send(ReceiverActorNodo {
+                        vec: Error::de_cafetera(IdCafetera::default())
+                            .con_id_pedido(id_pedido_from_bytes(&msg.msg))
+                            .to_bytes(),
+                        socket: msg.socket,
+                    })
+                {
+                    self.error_de_envio("actor cafetera", err);
                 }
             }
             MensajeCafetera::PING => {
                 //enviar al coordinador otro ping, para ver si seguimos conectados
-                let mensaje = Ping::from_bytes(msg.msg);
+                let mensaje = Ping::from_bytes(&msg.msg)?;
 
                 let ping = PingCord {
-                    tipo_mensaje: Mensaje::PING.to_bytes(),
                     id_cuenta: mensaje.get_id_cuenta(),
                     id_nodo: self.id_nodo,
-                    id_transaccion: 0,
+                    id_transaccion: IdTransaccion::default(),
                     id_cafetera: mensaje.get_id_cafetera(),
+                    id_pedido: 0,
                 };
                 // SI FALLA ENTRAR EN MODO DESCONECTADO
                 let _res = ctx.address().try_send(SendHandlerToCoordinator {
-                    vec: ping.to_string(),
+                    vec: ping.to_bytes(),
                 });
             }
 
             MensajeCafetera::OKEY => {
-                let mensaje = OkeyToCafetera::from_bytes(msg.msg);
+                let mensaje = OkeyToCafetera::from_bytes(&msg.msg)?;
                 let id_cuenta = mensaje.get_id_cuenta();
 
-                let id_transaccion = self
+                let id_transaccion = match self
                     .cuentas
                     .get(&id_cuenta)
-                    .expect("La cuenta se ha borrado")
-                    .transacciones
-                    .get(&mensaje.id_cafetera)
-                    .expect("La cuenta se ha borrado");
+                    .and_then(|x| x.transacciones.get(&(msg.socket, mensaje.id_pedido)))
+                {
+                    Some(x) => x,
+                    None => {
+                        self.rechazar_okey_desconocido(&mensaje, msg.socket);
+                        return Ok(());
+                    }
+                };
                 if let Some(transaccion_suma) = self.transacciones_suma.get_mut(id_transaccion) {
-                    if self.conectado {
-                        transaccion_suma.state = TransactionState::WaitCommit;
+                    let saldo = self
+                        .cuentas
+                        .get(&id_cuenta)
+                        .map(|x| x.saldo)
+                        .unwrap_or_default();
+                    if let Err(motivo) = sumar_saldo(saldo, transaccion_suma.cantidad) {
+                        // la suma dejaria la cuenta por encima del saldo maximo, se aborta localmente
+                        info!(
+                            "[NODO-{}] Se aborta la SUMA con ID_TRANSACCION = {} por {:?} | ID_PEDIDO = {}",
+                            self.id_nodo, id_transaccion, motivo, transaccion_suma.id_pedido
+                        );
+                        transaccion_suma
+                            .state
+                            .transicion(EventoTransaccion::Abortar, *id_transaccion);
+                        let mut fallido = Fallido::new(
+                            transaccion_suma.id_cafetera,
+                            id_cuenta,
+                            transaccion_suma.cantidad,
+                        );
+                        fallido.id_pedido = transaccion_suma.id_pedido;
+                        self.addr_actor_cafetera
+                            .as_ref()
+                            .expect("Siempre se cuenta con el address del actor cafetera")
+                            .do_send(ReceiverActorNodo {
+                                vec: fallido.to_bytes(),
+                                socket: transaccion_suma.socket,
+                            });
+                    } else if self.conectado {
+                        transaccion_suma
+                            .state
+                            .transicion(EventoTransaccion::Finalizar, *id_transaccion);
                         let finish = Finish::new(
                             self.id_nodo,
                             id_cuenta,
@@ -691,18 +1989,31 @@ impl Handler<ReceiveFromCafetera> for Nodo {
                             CommitType::SUMA,
                             transaccion_suma.cantidad,
                             mensaje.id_cafetera,
-                        );
+                        )
+                        .con_id_pedido(transaccion_suma.id_pedido);
                         let _res = ctx.address().try_send(SendHandlerToCoordinator {
-                            vec: finish.f_to_string(),
+                            vec: finish.to_bytes(),
                         });
                     } else {
-                        transaccion_suma.state = TransactionState::ToSend;
+                        transaccion_suma
+                            .state
+                            .transicion(EventoTransaccion::Posponer, *id_transaccion);
                     }
                 } else if let Some(transaccion_resta) =
                     self.transacciones_resta.get_mut(id_transaccion)
                 {
-                    if self.conectado {
-                        transaccion_resta.state = TransactionState::WaitCommit;
+                    if transaccion_resta.state == TransactionState::Abort {
+                        // la resta se aborto mientras se preparaba el cafe: la cafetera ya recibio el
+                        // error al abortarse y lo lee al esperar la confirmacion, asi que no se le
+                        // envia otro que tomaria como respuesta de su proximo pedido
+                        info!(
+                            "[NODO-{}] OKEY de la resta ya abortada ID_TRANSACCION = {}, el cafe se desecha | ID_PEDIDO = {}",
+                            self.id_nodo, id_transaccion, transaccion_resta.id_pedido
+                        );
+                    } else if self.conectado {
+                        transaccion_resta
+                            .state
+                            .transicion(EventoTransaccion::Finalizar, *id_transaccion);
                         let finish = Finish::new(
                             self.id_nodo,
                             id_cuenta,
@@ -710,76 +2021,170 @@ impl Handler<ReceiveFromCafetera> for Nodo {
                             CommitType::RESTA,
                             transaccion_resta.cantidad,
                             mensaje.id_cafetera,
-                        );
+                        )
+                        .con_id_pedido(transaccion_resta.id_pedido);
                         let _res = ctx.address().try_send(SendHandlerToCoordinator {
-                            vec: finish.f_to_string(),
+                            vec: finish.to_bytes(),
                         });
                     } else {
-                        transaccion_resta.state = TransactionState::Abort;
+                        transaccion_resta
+                            .state
+                            .transicion(EventoTransaccion::Abortar, *id_transaccion);
                         self.addr_actor_cafetera
                             .as_ref()
                             .expect("Error al obtener la direccion del actor cafetera")
                             .do_send(ReceiverActorNodo {
-                                vec: Error::new(0, 0, 0).to_bytes(),
+                                vec: Error::de_cafetera(IdCafetera::default())
+                                    .con_id_pedido(transaccion_resta.id_pedido)
+                                    .to_bytes(),
                                 socket: transaccion_resta.socket,
                             });
                     }
                 } else {
-                    // matate!
+                    self.rechazar_okey_desconocido(&mensaje, msg.socket);
                 }
             }
             MensajeCafetera::ERROR => {
-                let mensaje = Error::from_bytes(msg.msg);
+                let mensaje = Error::from_bytes(&msg.msg)?;
                 let id_cuenta = mensaje.get_id_cuenta();
-                let id_transaccion = self
+                let id_transaccion = match self
                     .cuentas
                     .get(&id_cuenta)
-                    .expect("Cuenta previamente creada")
-                    .transacciones
-                    .get(&mensaje.id_cafetera)
-                    .expect("Transaccion previamente creada");
+                    .and_then(|x| x.transacciones.get(&(msg.socket, mensaje.id_pedido)))
+                {
+                    Some(x) => x,
+                    None => {
+                        warn!(
+                            "[NODO-{}] ERROR sin transaccion en curso sobre ID_CUENTA = {}",
+                            self.id_nodo, id_cuenta
+                        );
+                        return Ok(());
+                    }
+                };
 
                 if let Some(transaccion_suma) = self.transacciones_suma.get_mut(id_transaccion) {
-                    transaccion_suma.state = TransactionState::Abort;
+                    transaccion_suma
+                        .state
+                        .transicion(EventoTransaccion::Abortar, *id_transaccion);
                 } else if let Some(transaccion_resta) =
                     self.transacciones_resta.get_mut(id_transaccion)
                 {
                     if self.conectado {
-                        println!(
-                            "[NODO-{}] MANDO UN abort AL COORDINADOR sobre ID_TRANSACCION: {}",
-                            self.id_nodo, id_transaccion
+                        info!(
+                            "[NODO-{}] MANDO UN abort AL COORDINADOR sobre ID_TRANSACCION: {} | ID_PEDIDO = {}",
+                            self.id_nodo, id_transaccion, transaccion_resta.id_pedido
                         );
-                        transaccion_resta.state = TransactionState::Abort;
+                        transaccion_resta
+                            .state
+                            .transicion(EventoTransaccion::Abortar, *id_transaccion);
                         let finish = Abort::new(
                             self.id_nodo,
                             id_cuenta,
                             *id_transaccion,
                             mensaje.id_cafetera,
-                        );
+                        )
+                        .con_id_pedido(transaccion_resta.id_pedido);
                         if let Err(err) = ctx.address().try_send(SendHandlerToCoordinator {
-                            vec: finish.to_string(),
+                            vec: finish.to_bytes(),
                         }) {
-                            println!(
+                            error!(
                                 "[NODO-{}] Error al enviar mensaje al coordinador | Detalle: {:?}",
                                 self.id_nodo, err
                             );
                         }
-                        transaccion_resta.state = TransactionState::Abort;
                     }
                 }
             }
-            MensajeCafetera::DESCONOCIDO => {}
+            MensajeCafetera::SONDEO => {
+                if self.drenando.is_some() {
+                    // sin respuesta la cafetera pasa a su siguiente nodo de respaldo
+                    return Ok(());
+                }
+                // la cafetera solo quiere saber si el nodo sigue vivo, se le devuelve el sondeo
+                let mensaje = Sondeo::from_bytes(&msg.msg)?;
+                if let Err(err) = self
+                    .addr_actor_cafetera
+                    .as_ref()
+                    .expect("Siempre se cuenta con el address del actor cafetera")
+                    .try_send(ReceiverActorNodo {
+                        vec: Sondeo::de_cafetera(mensaje.id_cafetera).to_bytes(),
+                        socket: msg.socket,
+                    })
+                {
+                    error!(
+                        "[NODO-{}] Error al responder SONDEO al actor cafetera | Detalle: {}",
+                        self.id_nodo, err
+                    );
+                }
+            }
+            MensajeCafetera::DRENAR => {
+                let _mensaje = Drenar::from_bytes(&msg.msg)?;
+                if self.id_nodo == self.id_coordinador {
+                    warn!(
+                        "[NODO-{}] Soy el coordinador, no me puedo drenar sin abortar las transacciones del resto",
+                        self.id_nodo
+                    );
+                    return Ok(());
+                }
+                if self.drenando.is_some() {
+                    return Ok(());
+                }
+                info!(
+                    "[NODO-{}] Empiezo a drenarme con {} transacciones en curso",
+                    self.id_nodo,
+                    self.transacciones_en_curso()
+                );
+                self.drenando = Some(self.reloj.ahora());
+                cada_intervalo(
+                    ctx,
+                    self.reloj.clone(),
+                    Duration::from_millis(config().servidor.intervalo_drenado_millis),
+                    Self::revisar_drenado,
+                );
+            }
+            MensajeCafetera::CONSULTAR => {
+                let mensaje = Consultar::from_bytes(&msg.msg)?;
+                let respuesta = match self.cuentas.get(&mensaje.id_cuenta) {
+                    Some(cuenta) => {
+                        Consultar::new(mensaje.id_cafetera, mensaje.id_cuenta, cuenta.saldo)
+                            .to_bytes()
+                    }
+                    None => Error::new(mensaje.id_cafetera, mensaje.id_cuenta, 0).to_bytes(),
+                };
+                self.addr_actor_cafetera
+                    .as_ref()
+                    .expect("Siempre se cuenta con el address del actor cafetera")
+                    .do_send(ReceiverActorNodo {
+                        vec: respuesta,
+                        socket: msg.socket,
+                    });
+            }
+            // los mensajes de registro, estado y autenticacion los resuelve el actor cafetera listener
+            MensajeCafetera::DESCONOCIDO
+            | MensajeCafetera::CONFIRMADO
+            | MensajeCafetera::FALLIDO
+            | MensajeCafetera::REGISTRAR
+            | MensajeCafetera::DESREGISTRAR
+            | MensajeCafetera::ESTADO
+            | MensajeCafetera::DESAFIO
+            | MensajeCafetera::AUTENTICADO => {}
             MensajeCafetera::DESCONECTAR => {
+                let comando = Desconectar::from_bytes(&msg.msg)?;
+                if !self.aceptar_comando_admin("DESCONECTAR", comando.secuencia) {
+                    return Ok(());
+                }
+                publicar_evento(
+                    &format!("NODO-{}", self.id_nodo),
+                    "DESCONEXION",
+                    "Desconectado por el proceso de desconexion".to_string(),
+                );
                 //chequeamos si somos el cordiandor
                 if self.id_nodo == self.id_coordinador {
                     //soy el cordinador, desconecto los nodo-handlers
-                    let mensaje = Disconnect {
-                        tipo_mensaje: Mensaje::DISCONNECT as u8,
-                    };
                     if let Err(err) = ctx.address().try_send(SendHandlerToCoordinator {
-                        vec: mensaje.to_string(),
+                        vec: Disconnect.to_bytes(),
                     }) {
-                        println!(
+                        error!(
                             "[NODO-{}] Error al enviar mensaje al coordinador | Detalle: {:?}",
                             self.id_nodo, err
                         );
@@ -791,13 +2196,14 @@ impl Handler<ReceiveFromCafetera> for Nodo {
                         .expect("Siempre se dispone de un address bully")
                         .try_send(SetState { estado: false })
                     {
-                        println!(
+                        error!(
                             "[NODO-{}] Error al enviar mensaje al bully | Detalle: {:?}",
                             self.id_nodo, err
                         );
                     }
                 } else {
                     self.conectado = false;
+                    self.reconciliar = true;
                     let arc = self.stream_cordinador.clone();
                     wrap_future::<_, Self>(async move {
                         arc.lock()
@@ -810,13 +2216,22 @@ impl Handler<ReceiveFromCafetera> for Nodo {
                 }
             }
             MensajeCafetera::CONECTAR => {
+                let comando = Conectar::from_bytes(&msg.msg)?;
+                if !self.aceptar_comando_admin("CONECTAR", comando.secuencia) {
+                    return Ok(());
+                }
+                publicar_evento(
+                    &format!("NODO-{}", self.id_nodo),
+                    "CONEXION",
+                    "Reconectado por el proceso de desconexion".to_string(),
+                );
                 if let Err(err) = self
                     .addr_actor_bully
                     .as_ref()
                     .expect("Siempre se dispone de un bully")
                     .try_send(SetState { estado: true })
                 {
-                    println!(
+                    error!(
                         "[NODO-{}] Error al enviar mensaje al bully | Detalle: {:?}",
                         self.id_nodo, err
                     );
@@ -824,6 +2239,514 @@ impl Handler<ReceiveFromCafetera> for Nodo {
                 self.conectado = true;
                 let _arc = self.stream_cordinador.clone();
             }
+            MensajeCafetera::ALTA => {
+                // el alta se reenvia al coordinador para que la aplique en todos los nodos
+                let mensaje = AltaCuenta::from_bytes(&msg.msg)?;
+                let alta = AltaCord::new(
+                    self.id_nodo,
+                    mensaje.id_cuenta,
+                    IdTransaccion::default(),
+                    mensaje.id_cafetera,
+                );
+                let _res = ctx.address().try_send(SendHandlerToCoordinator {
+                    vec: alta.to_bytes(),
+                });
+            }
+            MensajeCafetera::BAJA => {
+                let mensaje = BajaCuenta::from_bytes(&msg.msg)?;
+                let baja = BajaCord::new(
+                    self.id_nodo,
+                    mensaje.id_cuenta,
+                    IdTransaccion::default(),
+                    mensaje.id_cafetera,
+                );
+                let _res = ctx.address().try_send(SendHandlerToCoordinator {
+                    vec: baja.to_bytes(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+atender_medidos!(Nodo: ReceiveFromCafetera);
+
+/// Mensaje cuando se recibe algo de cafetera listener, handlea según el tipo del mensaje
+impl Handler<ReceiveFromCafetera> for Nodo {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReceiveFromCafetera, ctx: &mut Context<Self>) -> Self::Result {
+        let socket = msg.socket;
+        if let Err(err) = self.procesar_mensaje_cafetera(msg, ctx) {
+            warn!(
+                "[NODO-{}] Se descarta un mensaje invalido de {}: {}",
+                self.id_nodo, socket, err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod nodo_test {
+    use super::*;
+    use crate::prueba::NodoDePrueba;
+    use compartido::config::ID_CAFETERA_ADMINISTRACION;
+
+    fn cafetera() -> SocketAddr {
+        "127.0.0.1:9000".parse().unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn prepare_bloquea_la_cuenta_existente_y_aborta_la_inexistente() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+
+        prueba
+            .coordinador
+            .enviar(&Prepare::new(
+                IdNodo(2),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await;
+        let yes = prueba.coordinador.recibir::<Yes>().await;
+        assert_eq!(
+            (IdNodo(1), IdTransaccion(100)),
+            (yes.id_nodo, yes.id_transaccion)
+        );
+
+        prueba
+            .coordinador
+            .enviar(&Prepare::new(
+                IdNodo(2),
+                IdCuenta(8),
+                IdTransaccion(101),
+                IdCafetera(0),
+            ))
+            .await;
+        assert_eq!(
+            IdTransaccion(101),
+            prueba.coordinador.recibir::<Abort>().await.id_transaccion
+        );
+    }
+
+    #[actix_rt::test]
+    async fn commit_reenviado_se_confirma_pero_se_aplica_una_sola_vez() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+        let commit = Commit::new(
+            IdNodo(2),
+            IdCuenta(7),
+            IdTransaccion(100),
+            CommitType::SUMA,
+            30,
+            IdCafetera(0),
+        )
+        .con_secuencia(1);
+
+        prueba.coordinador.enviar(&commit).await;
+        assert_eq!(
+            IdTransaccion(100),
+            prueba
+                .coordinador
+                .recibir::<OkeyToCoordinator>()
+                .await
+                .id_transaccion
+        );
+        prueba.coordinador.enviar(&commit).await;
+        assert_eq!(
+            IdTransaccion(100),
+            prueba
+                .coordinador
+                .recibir::<OkeyToCoordinator>()
+                .await
+                .id_transaccion
+        );
+
+        prueba.nodo.do_send(ReceiveFromCafetera {
+            msg: Consultar::new(IdCafetera(0), IdCuenta(7), 0).to_bytes(),
+            socket: cafetera(),
+            autenticado: false,
+        });
+        let consulta = prueba.cafetera.recibir::<Consultar>().await;
+        assert_eq!(130, consulta.cantidad_modificar);
+    }
+
+    #[actix_rt::test]
+    async fn commit_anterior_al_corte_del_snapshot_se_confirma_sin_aplicarse() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+        prueba
+            .coordinador
+            .enviar(&Snapshot::new(IdNodo(1), 5, vec![(IdCuenta(7), 130)]))
+            .await;
+
+        let commit = Commit::new(
+            IdNodo(2),
+            IdCuenta(7),
+            IdTransaccion(100),
+            CommitType::SUMA,
+            30,
+            IdCafetera(0),
+        )
+        .con_secuencia(3);
+        prueba.coordinador.enviar(&commit).await;
+        assert_eq!(
+            IdTransaccion(100),
+            prueba
+                .coordinador
+                .recibir::<OkeyToCoordinator>()
+                .await
+                .id_transaccion
+        );
+
+        prueba.nodo.do_send(ReceiveFromCafetera {
+            msg: Consultar::new(IdCafetera(0), IdCuenta(7), 0).to_bytes(),
+            socket: cafetera(),
+            autenticado: false,
+        });
+        let consulta = prueba.cafetera.recibir::<Consultar>().await;
+        assert_eq!(130, consulta.cantidad_modificar);
+    }
+
+    #[actix_rt::test]
+    async fn resta_de_una_cafetera_se_ejecuta_cuando_el_coordinador_lo_pide() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+
+        let mut restar = Restar::new(IdCafetera(0), IdCuenta(7), 40);
+        restar.id_pedido = 12;
+        prueba.nodo.do_send(ReceiveFromCafetera {
+            msg: restar.to_bytes(),
+            socket: cafetera(),
+            autenticado: false,
+        });
+        let starter = prueba.coordinador.recibir::<Starter>().await;
+        assert_eq!(
+            (IdNodo(1), IdCuenta(7), 12),
+            (starter.id_nodo, starter.id_cuenta, starter.id_pedido)
+        );
+
+        let id_transaccion = starter.id_transaccion;
+        prueba
+            .coordinador
+            .enviar(&Prepare::new(
+                IdNodo(1),
+                IdCuenta(7),
+                id_transaccion,
+                IdCafetera(0),
+            ))
+            .await;
+        prueba.coordinador.recibir::<Yes>().await;
+        assert!(prueba.cafetera.sin_mensajes().await);
+
+        prueba
+            .coordinador
+            .enviar(
+                &Execute::new(IdNodo(1), IdCuenta(7), id_transaccion, IdCafetera(0))
+                    .con_id_pedido(12),
+            )
+            .await;
+        // la cafetera recibe el id de su pedido, para no confundir la respuesta con la de otro
+        let okey = prueba.cafetera.recibir::<OkeyToCafetera>().await;
+        assert_eq!(12, okey.id_pedido);
+    }
+
+    #[actix_rt::test]
+    async fn solo_las_restas_firmadas_se_piden_con_prioridad() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+
+        // el id de administracion no alcanza, cualquiera puede ponerlo en un datagrama
+        for (id_pedido, autenticado) in [(1, false), (2, true)] {
+            let mut restar = Restar::new(ID_CAFETERA_ADMINISTRACION, IdCuenta(7), 40);
+            restar.id_pedido = id_pedido;
+            prueba.nodo.do_send(ReceiveFromCafetera {
+                msg: restar.to_bytes(),
+                socket: cafetera(),
+                autenticado,
+            });
+            let starter = prueba.coordinador.recibir::<Starter>().await;
+            assert_eq!(
+                (id_pedido, autenticado),
+                (starter.id_pedido, starter.prioritaria)
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn sumas_en_curso_desde_el_mismo_socket_se_distinguen_por_el_id_de_pedido() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+
+        // las cafeteras de una sucursal comparten el socket, y pueden tener varias sumas en curso
+        // sobre la misma cuenta
+        for id_pedido in [1, 2] {
+            let mut sumar = Sumar::new(IdCafetera(0), IdCuenta(7), 10 * id_pedido);
+            sumar.id_pedido = id_pedido;
+            prueba.nodo.do_send(ReceiveFromCafetera {
+                msg: sumar.to_bytes(),
+                socket: cafetera(),
+                autenticado: false,
+            });
+            prueba.cafetera.recibir::<OkeyToCafetera>().await;
+        }
+
+        for id_pedido in [1, 2] {
+            prueba.nodo.do_send(ReceiveFromCafetera {
+                msg: OkeyToCafetera::new(IdCafetera(0), IdCuenta(7), 0)
+                    .con_id_pedido(id_pedido)
+                    .to_bytes(),
+                socket: cafetera(),
+                autenticado: false,
+            });
+            let finish = prueba.coordinador.recibir::<Finish>().await;
+            assert_eq!(
+                (id_pedido, 10 * id_pedido),
+                (finish.id_pedido, finish.cantidad)
+            );
         }
     }
+
+    #[actix_rt::test]
+    async fn okey_de_una_resta_abortada_durante_la_preparacion_no_se_confirma_ni_se_responde() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+        prueba.nodo.do_send(ReceiveFromCafetera {
+            msg: Restar::new(IdCafetera(0), IdCuenta(7), 40).to_bytes(),
+            socket: cafetera(),
+            autenticado: false,
+        });
+        let id_transaccion = prueba.coordinador.recibir::<Starter>().await.id_transaccion;
+        prueba
+            .coordinador
+            .enviar(&Prepare::new(
+                IdNodo(1),
+                IdCuenta(7),
+                id_transaccion,
+                IdCafetera(0),
+            ))
+            .await;
+        prueba.coordinador.recibir::<Yes>().await;
+        prueba
+            .coordinador
+            .enviar(&Execute::new(
+                IdNodo(1),
+                IdCuenta(7),
+                id_transaccion,
+                IdCafetera(0),
+            ))
+            .await;
+        prueba.cafetera.recibir::<OkeyToCafetera>().await;
+
+        // el coordinador da por vencida la resta mientras la cafetera prepara el cafe
+        prueba
+            .coordinador
+            .enviar(
+                &Abort::new(IdNodo(1), IdCuenta(7), id_transaccion, IdCafetera(0)).con_secuencia(1),
+            )
+            .await;
+        prueba.coordinador.recibir::<OkeyAbortToCoordinator>().await;
+        let error = prueba.cafetera.recibir::<Error>().await;
+        assert_eq!(MotivoError::GENERICO, error.motivo);
+
+        prueba.nodo.do_send(ReceiveFromCafetera {
+            msg: OkeyToCafetera::new(IdCafetera(0), IdCuenta(7), 40).to_bytes(),
+            socket: cafetera(),
+            autenticado: false,
+        });
+        assert!(prueba.cafetera.sin_mensajes().await);
+        // lo proximo que recibe el coordinador es el voto de otra transaccion, no un FINISH
+        prueba
+            .coordinador
+            .enviar(&Prepare::new(
+                IdNodo(2),
+                IdCuenta(7),
+                IdTransaccion(200),
+                IdCafetera(0),
+            ))
+            .await;
+        assert_eq!(
+            IdTransaccion(200),
+            prueba.coordinador.recibir::<Yes>().await.id_transaccion
+        );
+    }
+
+    #[actix_rt::test]
+    async fn okey_sin_transaccion_en_curso_se_responde_con_error() {
+        let prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+
+        prueba.nodo.do_send(ReceiveFromCafetera {
+            msg: OkeyToCafetera::new(IdCafetera(3), IdCuenta(7), 40).to_bytes(),
+            socket: cafetera(),
+            autenticado: false,
+        });
+        let error = prueba.cafetera.recibir::<Error>().await;
+        assert_eq!(
+            (IdCafetera(3), IdCuenta(7)),
+            (error.id_cafetera, error.id_cuenta)
+        );
+        assert_eq!(MotivoError::TRANSACCIONDESCONOCIDA, error.motivo);
+    }
+
+    #[actix_rt::test]
+    async fn execute_de_una_transaccion_desconocida_se_aborta_sin_detener_el_nodo() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+
+        prueba
+            .coordinador
+            .enviar(&Execute::new(
+                IdNodo(2),
+                IdCuenta(7),
+                IdTransaccion(100),
+                IdCafetera(0),
+            ))
+            .await;
+        assert_eq!(
+            IdTransaccion(100),
+            prueba.coordinador.recibir::<Abort>().await.id_transaccion
+        );
+
+        // el nodo sigue atendiendo los mensajes del coordinador
+        prueba
+            .coordinador
+            .enviar(&Prepare::new(
+                IdNodo(2),
+                IdCuenta(7),
+                IdTransaccion(101),
+                IdCafetera(0),
+            ))
+            .await;
+        assert_eq!(
+            IdTransaccion(101),
+            prueba.coordinador.recibir::<Yes>().await.id_transaccion
+        );
+    }
+
+    #[actix_rt::test]
+    async fn restar_repetido_de_una_resta_ya_quitada_de_memoria_responde_el_resultado_recordado() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+        let mut restar = Restar::new(IdCafetera(0), IdCuenta(7), 40);
+        restar.id_pedido = 5;
+        prueba.nodo.do_send(ReceiveFromCafetera {
+            msg: restar.to_bytes(),
+            socket: cafetera(),
+            autenticado: false,
+        });
+        let id_transaccion = prueba.coordinador.recibir::<Starter>().await.id_transaccion;
+        prueba
+            .coordinador
+            .enviar(
+                &Abort::new(IdNodo(1), IdCuenta(7), id_transaccion, IdCafetera(0)).con_secuencia(1),
+            )
+            .await;
+        prueba.coordinador.recibir::<OkeyAbortToCoordinator>().await;
+        assert_eq!(5, prueba.cafetera.recibir::<Error>().await.id_pedido);
+
+        // la primera limpieza la encuentra terminada y la siguiente luego del ttl la quita
+        let servidor = &config().servidor;
+        prueba.reloj.avanzar(Duration::from_millis(
+            servidor.intervalo_limpieza_transacciones_millis,
+        ));
+        assert!(prueba.cafetera.sin_mensajes().await);
+        prueba.reloj.avanzar(Duration::from_secs(
+            servidor.ttl_transacciones_terminadas_segundos,
+        ));
+        assert!(prueba.cafetera.sin_mensajes().await);
+
+        prueba.nodo.do_send(ReceiveFromCafetera {
+            msg: restar.to_bytes(),
+            socket: cafetera(),
+            autenticado: false,
+        });
+        assert_eq!(5, prueba.cafetera.recibir::<Error>().await.id_pedido);
+        // el RESTAR repetido no inicia otra resta: lo proximo que recibe el coordinador es un voto
+        prueba
+            .coordinador
+            .enviar(&Prepare::new(
+                IdNodo(2),
+                IdCuenta(7),
+                IdTransaccion(200),
+                IdCafetera(0),
+            ))
+            .await;
+        assert_eq!(
+            IdTransaccion(200),
+            prueba.coordinador.recibir::<Yes>().await.id_transaccion
+        );
+    }
+
+    #[actix_rt::test]
+    async fn nodo_drenado_sin_transacciones_se_retira_y_se_apaga() {
+        let mut prueba = NodoDePrueba::new(IdNodo(1), &[(IdCuenta(7), 100)]);
+        let (aviso, detenido) = oneshot::channel();
+        prueba.nodo.do_send(AddAvisoDetencion { aviso });
+
+        prueba.nodo.do_send(ReceiveFromCafetera {
+            msg: Drenar::de_cafetera(IdCafetera::default()).to_bytes(),
+            socket: cafetera(),
+            autenticado: true,
+        });
+        assert!(prueba.cafetera.sin_mensajes().await);
+        prueba.reloj.avanzar(Duration::from_millis(
+            config().servidor.intervalo_drenado_millis,
+        ));
+
+        assert_eq!(
+            IdNodo(1),
+            prueba.coordinador.recibir::<Retiro>().await.id_nodo
+        );
+        tokio::time::timeout(Duration::from_secs(1), detenido)
+            .await
+            .expect("El nodo se detiene luego de retirarse")
+            .expect("El nodo avisa que se detuvo");
+    }
+
+    #[test]
+    fn el_id_de_transaccion_separa_el_nodo_del_numero_de_orden() {
+        assert_eq!(IdTransaccion(0x0100_0001), id_transaccion(IdNodo(1), 1));
+        assert_eq!(
+            IdTransaccion(0xff00_0000),
+            id_transaccion(IdNodo(255), 1 << 24)
+        );
+        assert_ne!(
+            id_transaccion(IdNodo(1), 1_000_000),
+            id_transaccion(IdNodo(2), 1_000_000)
+        );
+    }
+
+    #[test]
+    fn las_transacciones_terminadas_se_quitan_luego_del_ttl() {
+        let transaccion = |state| Transaction {
+            socket: cafetera(),
+            cantidad: 10,
+            state,
+            id_cafetera: IdCafetera(0),
+            id_cuenta: IdCuenta(7),
+            id_pedido: 1,
+            id_cuenta_destino: None,
+            prioritaria: false,
+            terminada: None,
+        };
+        let mut transacciones = HashMap::from([
+            (IdTransaccion(1), transaccion(TransactionState::Commit)),
+            (IdTransaccion(2), transaccion(TransactionState::WaitCommit)),
+            (IdTransaccion(3), transaccion(TransactionState::Accepted)),
+        ]);
+        let ttl = Duration::from_secs(60);
+        let inicio = Instant::now();
+
+        assert!(quitar_vencidas(&mut transacciones, suma_terminada, inicio, ttl).is_empty());
+        transacciones.get_mut(&IdTransaccion(2)).unwrap().state = TransactionState::Abort;
+        let quitadas = quitar_vencidas(&mut transacciones, suma_terminada, inicio + ttl, ttl);
+        assert_eq!(
+            vec![IdTransaccion(1)],
+            quitadas.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+        );
+
+        // la abortada recien se ve terminada en la limpieza anterior, y la suma aceptada sigue en curso
+        let quitadas = quitar_vencidas(&mut transacciones, suma_terminada, inicio + 2 * ttl, ttl);
+        assert_eq!(
+            vec![IdTransaccion(2)],
+            quitadas.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![&IdTransaccion(3)],
+            transacciones.keys().collect::<Vec<_>>()
+        );
+    }
 }
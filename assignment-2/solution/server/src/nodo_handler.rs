@@ -1,90 +1,169 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 
 use actix::fut::wrap_future;
+use actix::ActorFutureExt;
 use actix::{
-    Actor, ActorContext, Addr, Context, ContextFutureSpawner, Handler, Message, StreamHandler,
+    Actor, ActorContext, Addr, AsyncContext, Context, ContextFutureSpawner, Handler, Message,
+    Recipient, ResponseActFuture, StreamHandler,
 };
 
+use crate::buzones::{Medido, BUZON_COORDINADOR};
+use crate::caos;
 use crate::coordinador::{Coordinador, Disconnect, DisconnectNodo};
 use crate::mensaje::{
-    Abort, Finish, Mensaje, MensajeBytes, OkeyAbortToCoordinator, OkeyToCoordinator, PingCord,
-    Starter, Yes,
+    Abort, AltaCord, Auditoria, BajaCord, Finish, Mensaje, OkeyAbortToCoordinator,
+    OkeyToCoordinator, PedidoSnapshot, PingCord, Rechazo, Retiro, Snapshot, Starter, Transferencia,
+    Valido, Yes,
 };
+use crate::utils::escribir_con_plazo;
+use compartido::codificable::Codificable;
+use compartido::config::config;
+use compartido::ids::IdNodo;
+use compartido::logs::LimitadorLogs;
 use tokio::io::{AsyncWriteExt, WriteHalf};
 use tokio::net::TcpStream;
 use tokio::sync::Mutex;
+use tracing::{error, warn};
 
 pub struct NodoHandler {
     pub addr_coordinador: Addr<Coordinador>,
     pub write: Arc<Mutex<WriteHalf<TcpStream>>>,
     pub addr: SocketAddr,
-    pub id_nodo: u8,
+    pub id_nodo: IdNodo,
+    /// id del nodo en el que corre el coordinador, origen de los mensajes de la conexion
+    pub id_coordinador: IdNodo,
     pub conectado: bool,
+    /// limita los logs de los errores de escritura que se repiten con cada mensaje
+    pub errores_escritura: LimitadorLogs<std::io::ErrorKind>,
 }
 
 impl Actor for NodoHandler {
     type Context = Context<Self>;
 }
 
-impl StreamHandler<Result<String, std::io::Error>> for NodoHandler {
-    fn handle(&mut self, read: Result<String, std::io::Error>, _ctx: &mut Self::Context) {
-        if let Ok(line) = read {
-            let aux = line.bytes().collect::<Vec<u8>>();
-            let tipo_mensaje = Mensaje::from_bytes(aux[0]);
-            let _arc = self.write.clone();
-            let addr_coor_clone = self.addr_coordinador.clone();
+/// Canal por el que el coordinador le envia mensajes a un nodo y cierra su conexion. Normalmente es
+/// un NodoHandler, pero en los tests puede ser cualquier actor que reciba ambos mensajes
+#[derive(Debug, Clone)]
+pub struct CanalNodo {
+    pub mensajes: Recipient<ReceiverFromCoordinador>,
+    pub shutdown: Recipient<Shutdown>,
+}
 
-            match tipo_mensaje {
-                Mensaje::STARTER => {
-                    let mensaje = Starter::from_string(line);
+impl From<Addr<NodoHandler>> for CanalNodo {
+    fn from(addr: Addr<NodoHandler>) -> Self {
+        CanalNodo {
+            mensajes: addr.clone().recipient(),
+            shutdown: addr.recipient(),
+        }
+    }
+}
 
-                    addr_coor_clone.do_send(mensaje);
-                }
-                Mensaje::YES => {
-                    let mensaje = Yes::from_string(line);
-                    addr_coor_clone.do_send(mensaje);
-                }
-                Mensaje::PING => {
-                    let mensaje = PingCord::from_string(line);
-                    addr_coor_clone.do_send(mensaje);
-                }
-                Mensaje::FINISH => {
-                    let mensaje = Finish::from_string(line);
-                    addr_coor_clone.do_send(mensaje);
-                }
-                Mensaje::OKEY => {
-                    let mensaje = OkeyToCoordinator::from_string(line);
-                    addr_coor_clone.do_send(mensaje);
-                }
-                Mensaje::OKEYABORT => {
-                    let mensaje = OkeyAbortToCoordinator::from_string(line);
-                    addr_coor_clone.do_send(mensaje);
-                }
-                Mensaje::ABORT => {
-                    let mensaje = Abort::from_string(line);
-                    addr_coor_clone.do_send(mensaje);
-                }
-                // TODO: Mensaje Disconnect, nunca recibo un prepare entonces funciona
-                Mensaje::PREPARE => {
+impl NodoHandler {
+    /// Reenvia el mensaje al coordinador si su id_nodo es el que se registro en la conexion y es un
+    /// nodo del cluster. Si no, lo descarta y le responde al nodo un Rechazo
+    fn reenviar<M>(&self, id_nodo: IdNodo, tipo: Mensaje, mensaje: M, ctx: &mut Context<Self>)
+    where
+        M: Message<Result = ()> + Send + 'static,
+        Coordinador: Handler<Medido<M>>,
+    {
+        if id_nodo == self.id_nodo && config().cluster.es_miembro(id_nodo.0) {
+            self.addr_coordinador
+                .do_send(Medido::new(&BUZON_COORDINADOR, mensaje));
+            return;
+        }
+        warn!(
+            "[NODO-{}] [HANDLER-COORDINADOR] Se rechaza {:?} con ID_NODO = {:?}",
+            self.id_nodo, tipo, id_nodo
+        );
+        ctx.address().do_send(ReceiverFromCoordinador {
+            vec: Rechazo::new(self.id_nodo, id_nodo, tipo.to_bytes()).to_bytes(),
+        });
+    }
+}
+
+impl StreamHandler<Result<Vec<u8>, std::io::Error>> for NodoHandler {
+    fn handle(&mut self, read: Result<Vec<u8>, std::io::Error>, ctx: &mut Self::Context) {
+        if let Ok(bytes) = read {
+            let tipo = match bytes.first() {
+                Some(tipo) => Mensaje::from_bytes(*tipo),
+                None => Mensaje::UNKNOWN,
+            };
+
+            let resultado = match tipo {
+                Mensaje::STARTER => Starter::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::YES => Yes::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::PING => PingCord::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::FINISH => Finish::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::TRANSFERENCIA => Transferencia::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::OKEY => OkeyToCoordinator::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::OKEYABORT => OkeyAbortToCoordinator::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::ABORT => Abort::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::VALIDO => Valido::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::ALTA => AltaCord::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::BAJA => BajaCord::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::RETIRO => Retiro::from_bytes(&bytes).map(|mensaje| {
+                    // el nodo se retira a proposito, al cerrarse la conexion no hay que tratarlo como caido
+                    if mensaje.id_nodo == self.id_nodo {
+                        self.conectado = false;
+                    }
+                    self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)
+                }),
+                Mensaje::PEDIDOSNAPSHOT => PedidoSnapshot::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                // el id_nodo del snapshot es el del nodo que lo recibe, no el del donante que lo envia
+                Mensaje::SNAPSHOT => Snapshot::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(self.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::AUDITORIA => Auditoria::from_bytes(&bytes)
+                    .map(|mensaje| self.reenviar(mensaje.id_nodo, tipo, mensaje, ctx)),
+                Mensaje::DISCONNECT => Disconnect::from_bytes(&bytes).map(|mensaje| {
                     self.conectado = false;
-                    let mensaje = Disconnect {
-                        tipo_mensaje: Mensaje::DISCONNECT as u8,
-                    };
-                    addr_coor_clone.do_send(mensaje);
+                    self.reenviar(self.id_nodo, tipo, mensaje, ctx)
+                }),
+                _ => {
+                    warn!(
+                        "[NODO-{}] [HANDLER-COORDINADOR] MSG NO RECONOCIDO",
+                        self.id_nodo
+                    );
+                    Ok(())
                 }
-                _ => println!("[NODO-{}] [HANDLER-COORDINADOR] MSG NO RECONOCIDO", aux[1]),
             };
-        } else {
+            if let Err(err) = resultado {
+                warn!(
+                    "[NODO-{}] [HANDLER-COORDINADOR] Se descarta un mensaje invalido: {}",
+                    self.id_nodo, err
+                );
+            }
         }
     }
 
     fn finished(&mut self, ctx: &mut Self::Context) {
+        self.desconectar(ctx);
+    }
+}
+
+impl NodoHandler {
+    /// La conexion con el nodo se cerro (o se dio por cerrada): si no se retiro a proposito se le
+    /// avisa al coordinador que el nodo se desconecto, y se detiene el actor
+    fn desconectar(&mut self, ctx: &mut Context<Self>) {
         if self.conectado {
+            self.conectado = false;
             if let Err(err) = self.addr_coordinador.try_send(DisconnectNodo {
                 id_nodo: self.id_nodo,
             }) {
-                println!(
+                error!(
                     "[HANDLER-COORDINADOR] Error al enviar DISCONNECT al coordinador {:?}",
                     err
                 );
@@ -97,27 +176,38 @@ impl StreamHandler<Result<String, std::io::Error>> for NodoHandler {
 #[derive(Message, Debug, Clone)]
 #[rtype(result = "()")]
 pub struct ReceiverFromCoordinador {
-    pub string: String,
+    pub vec: Vec<u8>,
 }
 
 impl Handler<ReceiverFromCoordinador> for NodoHandler {
     type Result = ();
-    fn handle(
-        &mut self,
-        mut msg: ReceiverFromCoordinador,
-        ctx: &mut Context<Self>,
-    ) -> Self::Result {
+    fn handle(&mut self, msg: ReceiverFromCoordinador, ctx: &mut Context<Self>) -> Self::Result {
         let arc = self.write.clone();
-        msg.string.push('\n');
         let id_nodo = self.id_nodo;
+        let entrega = caos::entrega(self.id_coordinador.0, self.id_nodo.0);
         wrap_future::<_, Self>(async move {
-            let res = arc.lock().await.write_all(msg.string.as_bytes()).await;
-
-            if let Err(e) = res {
-                if e.kind() == std::io::ErrorKind::BrokenPipe {
-                    println!("El coordinador se cayó {:}", id_nodo);
+            let mut write = arc.lock().await;
+            caos::esperar_entrega(entrega).await;
+            escribir_con_plazo(&mut *write, &msg.vec).await
+        })
+        .map(move |res, this, ctx| match res {
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                if let Some(omitidos) = this.errores_escritura.permitir(e.kind(), Instant::now()) {
+                    warn!(
+                        "El coordinador se cayó {:} | Errores omitidos: {}",
+                        id_nodo, omitidos
+                    );
                 }
-            };
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                // el nodo dejo de leer, por ejemplo porque se cayo sin cerrar la conexion
+                warn!(
+                    "[HANDLER-COORDINADOR] El ID_NODO = {:?} no recibe mensajes, se lo da por desconectado",
+                    id_nodo
+                );
+                this.desconectar(ctx);
+            }
+            _ => {}
         })
         .spawn(ctx);
     }
@@ -127,17 +217,18 @@ impl Handler<ReceiverFromCoordinador> for NodoHandler {
 #[rtype(result = "()")]
 pub struct Shutdown;
 
+/// Cierra la escritura de la conexion con el nodo. La respuesta llega cuando se termino de cerrar,
+/// asi el coordinador puede esperar a todas las conexiones antes de apagarse
 impl Handler<Shutdown> for NodoHandler {
-    type Result = ();
-    fn handle(&mut self, _msg: Shutdown, ctx: &mut Context<Self>) -> Self::Result {
+    type Result = ResponseActFuture<Self, ()>;
+    fn handle(&mut self, _msg: Shutdown, _ctx: &mut Context<Self>) -> Self::Result {
         let arc = self.write.clone();
-        wrap_future::<_, Self>(async move {
+        Box::pin(wrap_future::<_, Self>(async move {
             arc.lock()
                 .await
                 .shutdown()
                 .await
                 .expect("deberia hacer shutdown")
-        })
-        .spawn(ctx);
+        }))
     }
 }
@@ -0,0 +1,92 @@
+//! Resultado de los pedidos de resta cuyas transacciones el nodo ya quito de memoria (ver
+//! `servidor.ttl_transacciones_terminadas_segundos`). Alcanza con guardar el pedido y si la resta se
+//! commiteo para seguir respondiendo igual los RESTAR repetidos, sin retener la transaccion entera.
+//! Se guardan a lo sumo `capacidad` pedidos, al superarla se olvidan los mas viejos
+
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+
+use compartido::ids::IdCuenta;
+
+/// Pedido de resta: socket de la cafetera, id de pedido asignado por la sucursal e id de cuenta
+pub type ClavePedido = (SocketAddr, u32, IdCuenta);
+
+/// Pedidos de resta terminados, en el orden en que se fueron quitando sus transacciones
+#[derive(Debug)]
+pub struct PedidosTerminados {
+    /// true si la resta del pedido se commiteo, false si se aborto
+    resultados: HashMap<ClavePedido, bool>,
+    /// pedidos del mas viejo al mas nuevo, para olvidar los mas viejos al superar la capacidad
+    orden: VecDeque<ClavePedido>,
+    /// cantidad maxima de pedidos recordados
+    capacidad: usize,
+}
+
+impl PedidosTerminados {
+    pub fn new(capacidad: usize) -> Self {
+        PedidosTerminados {
+            resultados: HashMap::new(),
+            orden: VecDeque::new(),
+            capacidad,
+        }
+    }
+
+    /// Recuerda el resultado del pedido. Si ya se recordaban `capacidad` pedidos se olvida el mas viejo
+    pub fn recordar(&mut self, pedido: ClavePedido, commiteada: bool) {
+        if self.capacidad == 0 {
+            return;
+        }
+        if self.resultados.insert(pedido, commiteada).is_none() {
+            self.orden.push_back(pedido);
+        }
+        while self.orden.len() > self.capacidad {
+            if let Some(viejo) = self.orden.pop_front() {
+                self.resultados.remove(&viejo);
+            }
+        }
+    }
+
+    /// Si el pedido esta recordado, retorna true si su resta se commiteo y false si se aborto
+    pub fn resultado(&self, pedido: &ClavePedido) -> Option<bool> {
+        self.resultados.get(pedido).copied()
+    }
+
+    /// Cantidad de pedidos recordados
+    pub fn len(&self) -> usize {
+        self.resultados.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resultados.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod pedidos_terminados_test {
+    use super::*;
+
+    fn pedido(id_pedido: u32) -> ClavePedido {
+        ("127.0.0.1:9000".parse().unwrap(), id_pedido, IdCuenta(7))
+    }
+
+    #[test]
+    fn se_olvidan_los_pedidos_mas_viejos_al_superar_la_capacidad() {
+        let mut pedidos = PedidosTerminados::new(2);
+        pedidos.recordar(pedido(1), true);
+        pedidos.recordar(pedido(2), false);
+        assert_eq!(Some(true), pedidos.resultado(&pedido(1)));
+        assert_eq!(Some(false), pedidos.resultado(&pedido(2)));
+
+        // recordar de nuevo un pedido no lo cuenta dos veces
+        pedidos.recordar(pedido(2), false);
+        pedidos.recordar(pedido(3), true);
+        assert_eq!(2, pedidos.len());
+        assert_eq!(None, pedidos.resultado(&pedido(1)));
+        assert_eq!(Some(false), pedidos.resultado(&pedido(2)));
+        assert_eq!(Some(true), pedidos.resultado(&pedido(3)));
+
+        let mut sin_capacidad = PedidosTerminados::new(0);
+        sin_capacidad.recordar(pedido(1), true);
+        assert!(sin_capacidad.is_empty());
+    }
+}
@@ -0,0 +1,499 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use compartido::ids::{IdCuenta, IdTransaccion};
+
+/// Transaccion encolada a la espera de poder usar sus cuentas
+struct Pendiente {
+    /// cuentas que usa la transaccion, ordenadas de menor a mayor
+    cuentas: Vec<IdCuenta>,
+    /// momento en el que la transaccion llego al coordinador
+    encolada: Instant,
+    /// momento en el que la transaccion paso al frente de sus colas y se envio el prepare
+    despachada: Option<Instant>,
+    /// transaccion de administracion, se adelanta a las comunes en las colas de sus cuentas
+    prioritaria: bool,
+    /// cantidad de transacciones prioritarias que se le adelantaron
+    adelantada: u32,
+}
+
+/// Metricas de la planificacion de transacciones por cuenta
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MetricasPlanificacion {
+    /// cantidad de transacciones que pasaron al frente de su cola
+    pub despachadas: u32,
+    /// suma de las esperas en cola de las transacciones despachadas
+    pub espera_total: Duration,
+    /// mayor espera en cola observada
+    pub espera_maxima: Duration,
+    /// transacciones abortadas por superar el tiempo maximo al frente de la cola
+    pub vencidas: u32,
+}
+
+impl MetricasPlanificacion {
+    /// Espera promedio en cola de las transacciones despachadas
+    pub fn espera_promedio(&self) -> Duration {
+        if self.despachadas == 0 {
+            return Duration::ZERO;
+        }
+        self.espera_total / self.despachadas
+    }
+}
+
+/// Planificador de las transacciones pendientes por cuenta. Cada cuenta tiene su propia cola FIFO y una
+/// transaccion solo puede ejecutarse cuando esta al frente de las colas de todas sus cuentas (una resta usa
+/// una cuenta y una transferencia dos). Como la transaccion se agrega a todas sus colas en un mismo paso,
+/// el orden entre dos transacciones es el mismo en todas las colas que comparten y no puede haber esperas
+/// circulares entre ellas.
+/// Para que ninguna transaccion espere de forma indefinida, la que esta despachada tiene un tiempo maximo
+/// de servicio: si lo supera (por ejemplo porque un participante lento nunca responde) se da por vencida y
+/// se libera su lugar. Como el orden es FIFO y el tiempo de servicio esta acotado, la espera de cada
+/// transaccion queda acotada por su posicion en las colas.
+/// Las transacciones prioritarias (las de administracion) no se agregan al final sino delante de las
+/// comunes que todavia no se despacharon. Para que las colas sigan ordenadas de la misma forma, las
+/// transacciones pendientes tienen un orden global del que cada cola es una subsecuencia, y la
+/// prioritaria se ubica en ese orden justo detras de la ultima transaccion de sus cuentas a la que no
+/// puede adelantarse. Cada transaccion comun puede ser adelantada a lo sumo `max_adelantamientos`
+/// veces, con lo que su espera sigue acotada
+pub struct Planificador {
+    /// Hash de clave id_cuenta y valor la cola de id_transacciones pendientes de esa cuenta
+    colas: HashMap<IdCuenta, VecDeque<IdTransaccion>>,
+    /// Hash de clave id_transaccion y valor la transaccion pendiente
+    pendientes: HashMap<IdTransaccion, Pendiente>,
+    /// id_transacciones pendientes en el orden global, cada cola respeta este orden
+    orden: Vec<IdTransaccion>,
+    /// tiempo maximo que una transaccion puede permanecer despachada
+    tiempo_maximo_servicio: Duration,
+    /// veces que una transaccion comun puede ser adelantada por una prioritaria
+    max_adelantamientos: u32,
+    /// metricas acumuladas
+    metricas: MetricasPlanificacion,
+}
+
+impl Planificador {
+    pub fn new(tiempo_maximo_servicio: Duration, max_adelantamientos: u32) -> Planificador {
+        Planificador {
+            colas: HashMap::new(),
+            pendientes: HashMap::new(),
+            orden: Vec::new(),
+            tiempo_maximo_servicio,
+            max_adelantamientos,
+            metricas: MetricasPlanificacion::default(),
+        }
+    }
+
+    /// Encola la transaccion en las colas de sus cuentas, al final o, si es prioritaria, delante de
+    /// las transacciones comunes a las que puede adelantarse. Retorna true si quedo al frente de todas,
+    /// es decir si puede despacharse en este momento
+    pub fn encolar(
+        &mut self,
+        cuentas: &[IdCuenta],
+        id_transaccion: IdTransaccion,
+        prioritaria: bool,
+        ahora: Instant,
+    ) -> bool {
+        let mut cuentas = cuentas.to_vec();
+        cuentas.sort_unstable();
+        cuentas.dedup();
+        let posicion = if prioritaria {
+            self.posicion_prioritaria(&cuentas)
+        } else {
+            self.orden.len()
+        };
+        // las transacciones de sus cuentas que quedan detras en el orden global son las adelantadas
+        let mut adelantadas = BTreeSet::new();
+        for id in self.orden[posicion..].iter() {
+            if let Some(pendiente) = self.pendientes.get_mut(id) {
+                if pendiente.cuentas.iter().any(|x| cuentas.contains(x)) {
+                    pendiente.adelantada += 1;
+                    adelantadas.insert(*id);
+                }
+            }
+        }
+        self.orden.insert(posicion, id_transaccion);
+        for id_cuenta in cuentas.iter() {
+            let cola = self.colas.entry(*id_cuenta).or_default();
+            match cola.iter().position(|x| adelantadas.contains(x)) {
+                Some(indice) => cola.insert(indice, id_transaccion),
+                None => cola.push_back(id_transaccion),
+            }
+        }
+        self.pendientes.insert(
+            id_transaccion,
+            Pendiente {
+                cuentas,
+                encolada: ahora,
+                despachada: None,
+                prioritaria,
+                adelantada: 0,
+            },
+        );
+        self.despachar_si_esta_lista(id_transaccion, ahora)
+    }
+
+    /// Posicion del orden global en la que se ubica una transaccion prioritaria: detras de la ultima
+    /// transaccion de sus cuentas que no puede adelantarse, porque ya fue despachada, es prioritaria
+    /// (las prioritarias se atienden en orden de llegada) o ya fue adelantada el maximo de veces
+    fn posicion_prioritaria(&self, cuentas: &[IdCuenta]) -> usize {
+        self.orden
+            .iter()
+            .rposition(|id| {
+                self.pendientes.get(id).is_some_and(|x| {
+                    x.cuentas
+                        .iter()
+                        .any(|id_cuenta| cuentas.contains(id_cuenta))
+                        && (x.despachada.is_some()
+                            || x.prioritaria
+                            || x.adelantada >= self.max_adelantamientos)
+                })
+            })
+            .map_or(0, |x| x + 1)
+    }
+
+    /// Retira la transaccion de sus colas al finalizar (commit o abort) y retorna las transacciones
+    /// que quedaron al frente de todas sus colas y deben despacharse. Si la transaccion ya habia sido
+    /// retirada (por ejemplo por haber vencido) no hace nada
+    pub fn liberar(&mut self, id_transaccion: IdTransaccion, ahora: Instant) -> Vec<IdTransaccion> {
+        let pendiente = match self.pendientes.remove(&id_transaccion) {
+            Some(x) => x,
+            None => return vec![],
+        };
+        self.orden.retain(|x| *x != id_transaccion);
+        let mut candidatas = vec![];
+        for id_cuenta in pendiente.cuentas.iter() {
+            if let Some(cola) = self.colas.get_mut(id_cuenta) {
+                cola.retain(|x| *x != id_transaccion);
+                match cola.front() {
+                    Some(frente) => candidatas.push(*frente),
+                    None => {
+                        self.colas.remove(id_cuenta);
+                    }
+                }
+            }
+        }
+        candidatas.sort_unstable();
+        candidatas.dedup();
+        candidatas
+            .into_iter()
+            .filter(|x| self.despachar_si_esta_lista(*x, ahora))
+            .collect()
+    }
+
+    /// Retira de las colas las transacciones que superaron el tiempo maximo de servicio y que
+    /// `puede_vencer` indica que todavia pueden abortarse: una transaccion cuyo resultado ya se decidio
+    /// (por ejemplo un commit que se sigue reenviando) conserva sus cuentas hasta que se la libere.
+    /// Retorna, por cada una, el id de la transaccion vencida y las transacciones a despachar en su lugar
+    pub fn vencidas(
+        &mut self,
+        ahora: Instant,
+        puede_vencer: impl Fn(IdTransaccion) -> bool,
+    ) -> Vec<(IdTransaccion, Vec<IdTransaccion>)> {
+        let mut vencidas: Vec<IdTransaccion> = self
+            .pendientes
+            .iter()
+            .filter(|(id_transaccion, x)| match x.despachada {
+                Some(despachada) => {
+                    ahora.duration_since(despachada) > self.tiempo_maximo_servicio
+                        && puede_vencer(**id_transaccion)
+                }
+                None => false,
+            })
+            .map(|(id_transaccion, _)| *id_transaccion)
+            .collect();
+        vencidas.sort_unstable();
+
+        let mut resultado = vec![];
+        for id_transaccion in vencidas {
+            self.metricas.vencidas += 1;
+            let siguientes = self.liberar(id_transaccion, ahora);
+            resultado.push((id_transaccion, siguientes));
+        }
+        resultado
+    }
+
+    /// Cantidad de transacciones encoladas (incluyendo las despachadas)
+    pub fn pendientes(&self) -> usize {
+        self.pendientes.len()
+    }
+
+    /// Indica si la cuenta tiene transacciones encoladas o despachadas
+    pub fn cuenta_en_uso(&self, id_cuenta: IdCuenta) -> bool {
+        self.colas.contains_key(&id_cuenta)
+    }
+
+    pub fn metricas(&self) -> &MetricasPlanificacion {
+        &self.metricas
+    }
+
+    /// Si la transaccion esta al frente de todas sus colas y aun no fue despachada, la marca como
+    /// despachada, actualiza las metricas y retorna true
+    fn despachar_si_esta_lista(&mut self, id_transaccion: IdTransaccion, ahora: Instant) -> bool {
+        let pendiente = match self.pendientes.get_mut(&id_transaccion) {
+            Some(x) => x,
+            None => return false,
+        };
+        let lista = pendiente.despachada.is_none()
+            && pendiente.cuentas.iter().all(|id_cuenta| {
+                self.colas
+                    .get(id_cuenta)
+                    .and_then(|cola| cola.front())
+                    .is_some_and(|frente| *frente == id_transaccion)
+            });
+        if !lista {
+            return false;
+        }
+        pendiente.despachada = Some(ahora);
+        let espera = ahora.duration_since(pendiente.encolada);
+        self.metricas.despachadas += 1;
+        self.metricas.espera_total += espera;
+        self.metricas.espera_maxima = self.metricas.espera_maxima.max(espera);
+        true
+    }
+}
+
+#[cfg(test)]
+mod planificador_test {
+    use super::*;
+
+    const SERVICIO: Duration = Duration::from_secs(10);
+    const MAX_ADELANTAMIENTOS: u32 = 2;
+
+    #[test]
+    fn la_primera_transaccion_de_la_cuenta_se_despacha() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let ahora = Instant::now();
+
+        assert!(planificador.encolar(&[IdCuenta(1)], IdTransaccion(100), false, ahora));
+        assert!(!planificador.encolar(&[IdCuenta(1)], IdTransaccion(101), false, ahora));
+        assert!(planificador.encolar(&[IdCuenta(2)], IdTransaccion(200), false, ahora));
+        assert_eq!(3, planificador.pendientes());
+    }
+
+    #[test]
+    fn liberar_despacha_en_orden_fifo() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let ahora = Instant::now();
+        planificador.encolar(&[IdCuenta(1)], IdTransaccion(100), false, ahora);
+        planificador.encolar(&[IdCuenta(1)], IdTransaccion(101), false, ahora);
+        planificador.encolar(&[IdCuenta(1)], IdTransaccion(102), false, ahora);
+
+        assert_eq!(
+            vec![IdTransaccion(101)],
+            planificador.liberar(IdTransaccion(100), ahora)
+        );
+        assert_eq!(
+            vec![IdTransaccion(102)],
+            planificador.liberar(IdTransaccion(101), ahora)
+        );
+        assert!(planificador.liberar(IdTransaccion(102), ahora).is_empty());
+        assert_eq!(0, planificador.pendientes());
+    }
+
+    #[test]
+    fn liberar_una_transaccion_ya_retirada_no_altera_la_cola() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let ahora = Instant::now();
+        planificador.encolar(&[IdCuenta(1)], IdTransaccion(100), false, ahora);
+        planificador.encolar(&[IdCuenta(1)], IdTransaccion(101), false, ahora);
+        planificador.liberar(IdTransaccion(100), ahora);
+
+        assert!(planificador.liberar(IdTransaccion(100), ahora).is_empty());
+        assert!(planificador.liberar(IdTransaccion(300), ahora).is_empty());
+        assert_eq!(1, planificador.pendientes());
+    }
+
+    #[test]
+    fn la_transaccion_trabada_al_frente_vence_y_libera_la_cola() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let inicio = Instant::now();
+        planificador.encolar(&[IdCuenta(1)], IdTransaccion(100), false, inicio);
+        planificador.encolar(&[IdCuenta(1)], IdTransaccion(101), false, inicio);
+
+        assert!(planificador
+            .vencidas(inicio + Duration::from_secs(5), |_| true)
+            .is_empty());
+
+        let vencidas = planificador.vencidas(inicio + Duration::from_secs(11), |_| true);
+        assert_eq!(
+            vec![(IdTransaccion(100), vec![IdTransaccion(101)])],
+            vencidas
+        );
+        assert_eq!(1, planificador.metricas().vencidas);
+
+        // el okey abort atrasado de la transaccion vencida no debe sacar a la siguiente
+        assert!(planificador
+            .liberar(IdTransaccion(100), inicio + Duration::from_secs(12))
+            .is_empty());
+        assert_eq!(1, planificador.pendientes());
+    }
+
+    #[test]
+    fn cuenta_caliente_no_demora_a_las_demas_y_su_espera_queda_acotada() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let inicio = Instant::now();
+        let cantidad = 20;
+        for i in 0..cantidad {
+            assert_eq!(
+                i == 0,
+                planificador.encolar(&[IdCuenta(1)], IdTransaccion(1000 + i), false, inicio)
+            );
+        }
+        // las transacciones de otras cuentas se despachan aunque la cuenta caliente este trabada
+        assert!(planificador.encolar(&[IdCuenta(2)], IdTransaccion(2000), false, inicio));
+        assert!(planificador.liberar(IdTransaccion(2000), inicio).is_empty());
+
+        // ninguna transaccion de la cuenta caliente responde: todas terminan venciendo
+        let mut ahora = inicio;
+        let mut despachadas = vec![];
+        for _ in 0..cantidad {
+            ahora += SERVICIO + Duration::from_secs(1);
+            for (_, siguientes) in planificador.vencidas(ahora, |_| true) {
+                despachadas.extend(siguientes);
+            }
+        }
+        assert_eq!(
+            (1001..1000 + cantidad)
+                .map(IdTransaccion)
+                .collect::<Vec<_>>(),
+            despachadas
+        );
+        assert_eq!(cantidad, planificador.metricas().vencidas);
+        assert_eq!(0, planificador.pendientes());
+
+        let limite = (SERVICIO + Duration::from_secs(1)) * cantidad;
+        assert!(planificador.metricas().espera_maxima <= limite);
+        assert!(planificador.metricas().espera_promedio() <= planificador.metricas().espera_maxima);
+    }
+
+    #[test]
+    fn la_transferencia_espera_a_estar_al_frente_de_ambas_cuentas() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let ahora = Instant::now();
+        assert!(planificador.encolar(&[IdCuenta(1)], IdTransaccion(100), false, ahora));
+        assert!(planificador.encolar(&[IdCuenta(2)], IdTransaccion(200), false, ahora));
+        assert!(!planificador.encolar(
+            &[IdCuenta(2), IdCuenta(1)],
+            IdTransaccion(300),
+            false,
+            ahora
+        ));
+
+        // liberar solo la cuenta 1 no alcanza, la cuenta 2 sigue en uso
+        assert!(planificador.liberar(IdTransaccion(100), ahora).is_empty());
+        assert_eq!(
+            vec![IdTransaccion(300)],
+            planificador.liberar(IdTransaccion(200), ahora)
+        );
+    }
+
+    #[test]
+    fn transferencias_cruzadas_no_se_bloquean_entre_si() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let ahora = Instant::now();
+        assert!(planificador.encolar(
+            &[IdCuenta(1), IdCuenta(2)],
+            IdTransaccion(100),
+            false,
+            ahora
+        ));
+        assert!(!planificador.encolar(
+            &[IdCuenta(2), IdCuenta(1)],
+            IdTransaccion(200),
+            false,
+            ahora
+        ));
+        assert!(!planificador.encolar(&[IdCuenta(1)], IdTransaccion(300), false, ahora));
+
+        assert_eq!(
+            vec![IdTransaccion(200)],
+            planificador.liberar(IdTransaccion(100), ahora)
+        );
+        assert_eq!(
+            vec![IdTransaccion(300)],
+            planificador.liberar(IdTransaccion(200), ahora)
+        );
+    }
+
+    #[test]
+    fn la_cuenta_deja_de_estar_en_uso_al_liberar_sus_transacciones() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let ahora = Instant::now();
+        assert!(!planificador.cuenta_en_uso(IdCuenta(1)));
+        planificador.encolar(
+            &[IdCuenta(1), IdCuenta(2)],
+            IdTransaccion(100),
+            false,
+            ahora,
+        );
+        assert!(planificador.cuenta_en_uso(IdCuenta(1)));
+        assert!(planificador.cuenta_en_uso(IdCuenta(2)));
+
+        planificador.liberar(IdTransaccion(100), ahora);
+        assert!(!planificador.cuenta_en_uso(IdCuenta(1)));
+        assert!(!planificador.cuenta_en_uso(IdCuenta(2)));
+    }
+
+    #[test]
+    fn la_prioritaria_se_adelanta_a_las_comunes_hasta_el_maximo_de_adelantamientos() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let ahora = Instant::now();
+        assert!(planificador.encolar(&[IdCuenta(1)], IdTransaccion(100), false, ahora));
+        planificador.encolar(&[IdCuenta(1)], IdTransaccion(101), false, ahora);
+        planificador.encolar(&[IdCuenta(1)], IdTransaccion(102), false, ahora);
+        // la despachada no se adelanta y las prioritarias se atienden en orden de llegada
+        assert!(!planificador.encolar(&[IdCuenta(1)], IdTransaccion(900), true, ahora));
+        assert!(!planificador.encolar(&[IdCuenta(1)], IdTransaccion(901), true, ahora));
+        // 101 y 102 ya fueron adelantadas dos veces, la siguiente prioritaria va al final
+        assert!(!planificador.encolar(&[IdCuenta(1)], IdTransaccion(902), true, ahora));
+
+        let mut despachadas = vec![];
+        let mut siguiente = IdTransaccion(100);
+        for _ in 0..5 {
+            siguiente = planificador.liberar(siguiente, ahora)[0];
+            despachadas.push(siguiente);
+        }
+        assert_eq!(
+            vec![
+                IdTransaccion(900),
+                IdTransaccion(901),
+                IdTransaccion(101),
+                IdTransaccion(102),
+                IdTransaccion(902)
+            ],
+            despachadas
+        );
+    }
+
+    #[test]
+    fn la_prioritaria_queda_en_el_mismo_orden_en_todas_sus_colas() {
+        let mut planificador = Planificador::new(SERVICIO, MAX_ADELANTAMIENTOS);
+        let ahora = Instant::now();
+        assert!(planificador.encolar(&[IdCuenta(1)], IdTransaccion(100), false, ahora));
+        assert!(!planificador.encolar(
+            &[IdCuenta(1), IdCuenta(3)],
+            IdTransaccion(101),
+            false,
+            ahora
+        ));
+        // la transferencia prioritaria se adelanta a 101 en la cuenta 3 y queda despachada
+        assert!(planificador.encolar(&[IdCuenta(3), IdCuenta(2)], IdTransaccion(102), true, ahora));
+        assert!(!planificador.encolar(&[IdCuenta(2)], IdTransaccion(103), false, ahora));
+        // 200 se adelanta a 101 y a 103, pero no a las despachadas de sus cuentas
+        assert!(!planificador.encolar(
+            &[IdCuenta(1), IdCuenta(2)],
+            IdTransaccion(200),
+            true,
+            ahora
+        ));
+
+        assert!(planificador.liberar(IdTransaccion(100), ahora).is_empty());
+        assert_eq!(
+            vec![IdTransaccion(200)],
+            planificador.liberar(IdTransaccion(102), ahora)
+        );
+        assert_eq!(
+            vec![IdTransaccion(101), IdTransaccion(103)],
+            planificador.liberar(IdTransaccion(200), ahora)
+        );
+    }
+}
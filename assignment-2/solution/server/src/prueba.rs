@@ -0,0 +1,311 @@
+//! Actores falsos para testear los handlers del Coordinador y del Nodo sin abrir sockets. Los
+//! nodos-handlers y la cafetera se reemplazan por actores que registran los mensajes que reciben, y
+//! la conexion tcp del nodo con el coordinador por un stream en memoria
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix::{Actor, Addr, Context, Handler};
+use compartido::codificable::Codificable;
+use compartido::ids::{IdCuenta, IdNodo};
+use tiempo::RelojVirtual;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::sync::oneshot;
+
+use crate::almacen::AlmacenEnMemoria;
+use crate::auditoria::SnapshotSaldos;
+use crate::cafetera_listener::ReceiverActorNodo;
+use crate::coordinador::{AddNodo, ConsultarSaldos, Coordinador, ModoConexion};
+use crate::mensaje::{Auditoria, PedidoAuditoria, PedidoSnapshot, Snapshot};
+use crate::nodo::{AddAddrActorCafetera, Nodo};
+use crate::nodo_handler::{CanalNodo, ReceiverFromCoordinador, Shutdown};
+use crate::utils::enmarcar;
+
+/// Cantidad de veces que se le cede el turno a los actores antes de dar por perdido un mensaje
+const ESPERAS: usize = 100;
+
+/// Mensajes que recibio un actor falso, en el orden en que llegaron
+#[derive(Clone, Default)]
+pub struct Recibidos {
+    mensajes: Arc<Mutex<VecDeque<Vec<u8>>>>,
+    cerrado: Arc<AtomicBool>,
+}
+
+impl Recibidos {
+    fn registrar(&self, bytes: Vec<u8>) {
+        self.mensajes
+            .lock()
+            .expect("Ningun test entra en panico con el lock tomado")
+            .push_back(bytes);
+    }
+
+    fn siguiente(&self) -> Option<Vec<u8>> {
+        self.mensajes
+            .lock()
+            .expect("Ningun test entra en panico con el lock tomado")
+            .pop_front()
+    }
+
+    /// Espera el proximo mensaje, que tiene que ser del tipo M
+    pub async fn recibir<M: Codificable>(&self) -> M {
+        for _ in 0..ESPERAS {
+            if let Some(bytes) = self.siguiente() {
+                return M::from_bytes(&bytes).unwrap_or_else(|err| {
+                    panic!(
+                        "Se esperaba el tipo {} y llego {:?}: {:?}",
+                        M::TIPO,
+                        bytes,
+                        err
+                    )
+                });
+            }
+            tokio::task::yield_now().await;
+        }
+        panic!("No llego ningun mensaje del tipo {}", M::TIPO);
+    }
+
+    /// true si luego de dar tiempo a los actores no llego ningun mensaje mas
+    pub async fn sin_mensajes(&self) -> bool {
+        for _ in 0..ESPERAS {
+            tokio::task::yield_now().await;
+        }
+        self.mensajes
+            .lock()
+            .expect("Ningun test entra en panico con el lock tomado")
+            .is_empty()
+    }
+
+    /// true si se cerro la conexion, por ejemplo porque el coordinador dio por caido al nodo
+    pub async fn cerrado(&self) -> bool {
+        for _ in 0..ESPERAS {
+            if self.cerrado.load(Ordering::SeqCst) {
+                return true;
+            }
+            tokio::task::yield_now().await;
+        }
+        false
+    }
+}
+
+/// Reemplaza al NodoHandler de un nodo: registra lo que el coordinador le envia
+pub struct NodoHandlerFalso {
+    recibidos: Recibidos,
+}
+
+impl Actor for NodoHandlerFalso {
+    type Context = Context<Self>;
+}
+
+impl NodoHandlerFalso {
+    /// Arranca el actor y retorna el canal para registrarlo en el coordinador junto con lo que recibe
+    pub fn iniciar() -> (CanalNodo, Recibidos) {
+        let recibidos = Recibidos::default();
+        let addr = NodoHandlerFalso {
+            recibidos: recibidos.clone(),
+        }
+        .start();
+        let canal = CanalNodo {
+            mensajes: addr.clone().recipient(),
+            shutdown: addr.recipient(),
+        };
+        (canal, recibidos)
+    }
+}
+
+impl Handler<ReceiverFromCoordinador> for NodoHandlerFalso {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReceiverFromCoordinador, _: &mut Context<Self>) -> Self::Result {
+        self.recibidos.registrar(msg.vec);
+    }
+}
+
+impl Handler<Shutdown> for NodoHandlerFalso {
+    type Result = ();
+
+    fn handle(&mut self, _: Shutdown, _: &mut Context<Self>) -> Self::Result {
+        self.recibidos.cerrado.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Reemplaza al CafeteraListener de un nodo: registra las respuestas que el nodo le envia a las cafeteras
+pub struct CafeteraListenerFalso {
+    recibidos: Recibidos,
+}
+
+impl Actor for CafeteraListenerFalso {
+    type Context = Context<Self>;
+}
+
+impl Handler<ReceiverActorNodo> for CafeteraListenerFalso {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReceiverActorNodo, _: &mut Context<Self>) -> Self::Result {
+        self.recibidos.registrar(msg.vec);
+    }
+}
+
+/// Coordinador con nodos falsos, medido con un reloj virtual
+pub struct CoordinadorDePrueba {
+    pub coordinador: Addr<Coordinador>,
+    pub reloj: RelojVirtual,
+    /// nodo en el que corre el coordinador
+    pub id_nodo: IdNodo,
+    /// mensajes que recibe cada nodo conectado
+    pub nodos: HashMap<IdNodo, Recibidos>,
+}
+
+impl CoordinadorDePrueba {
+    /// Arranca el coordinador, todavia sin nodos
+    pub fn iniciar() -> Self {
+        Self::en_nodo(IdNodo(0))
+    }
+
+    /// Arranca el coordinador como si corriera en el ID_NODO indicado, todavia sin nodos
+    pub fn en_nodo(id_nodo: IdNodo) -> Self {
+        let reloj = RelojVirtual::new();
+        CoordinadorDePrueba {
+            coordinador: Coordinador::new(Arc::new(reloj.clone()))
+                .con_id_nodo(id_nodo)
+                .start(),
+            reloj,
+            id_nodo,
+            nodos: HashMap::new(),
+        }
+    }
+
+    /// Conecta un nodo falso. Si ya habia otros, el del coordinador (o si no el de menor id) le envia
+    /// el snapshot (sin cuentas) para que el nuevo empiece a votar
+    pub async fn unir(&mut self, id_nodo: IdNodo) -> Recibidos {
+        let (canal, recibidos) = NodoHandlerFalso::iniciar();
+        self.coordinador
+            .send(AddNodo {
+                nodo_addr: canal,
+                id_nodo,
+                modo: ModoConexion::SinEstado,
+            })
+            .await
+            .expect("El coordinador sigue vivo");
+        let donante = self
+            .nodos
+            .get(&self.id_nodo)
+            .or_else(|| self.nodos.iter().min_by_key(|(id, _)| **id).map(|(_, x)| x));
+        if let Some(donante) = donante {
+            let pedido = donante.recibir::<PedidoSnapshot>().await;
+            self.coordinador
+                .send(Snapshot::new(pedido.id_nodo, pedido.corte, vec![]))
+                .await
+                .expect("El coordinador sigue vivo");
+            recibidos.recibir::<Snapshot>().await;
+        }
+        self.nodos.insert(id_nodo, recibidos.clone());
+        recibidos
+    }
+
+    /// Pide el snapshot de los saldos del cluster y responde por cada nodo conectado con los saldos
+    /// indicados (sin cuentas los que no figuran), para verificar al final de un test los saldos con los
+    /// que quedo el cluster. Los nodos no tienen que tener otros mensajes sin leer
+    pub async fn saldos(&self, saldos: &[(IdNodo, Vec<(IdCuenta, u32)>)]) -> SnapshotSaldos {
+        let (respuesta, snapshot) = oneshot::channel();
+        self.coordinador
+            .send(ConsultarSaldos { respuesta })
+            .await
+            .expect("El coordinador sigue vivo");
+        for (id_nodo, recibidos) in &self.nodos {
+            let pedido = recibidos.recibir::<PedidoAuditoria>().await;
+            let cuentas = saldos
+                .iter()
+                .find(|(id, _)| id == id_nodo)
+                .map(|(_, cuentas)| cuentas.clone())
+                .unwrap_or_default();
+            self.coordinador
+                .send(Auditoria::new(*id_nodo, pedido.id_auditoria, cuentas))
+                .await
+                .expect("El coordinador sigue vivo");
+        }
+        snapshot
+            .await
+            .expect("El coordinador responde al recibir los saldos de todos los nodos")
+    }
+}
+
+/// Lado del coordinador de la conexion en memoria con un nodo
+pub struct CoordinadorFalso {
+    stream: DuplexStream,
+}
+
+impl CoordinadorFalso {
+    /// Le envia un mensaje al nodo como lo haria su NodoHandler
+    pub async fn enviar<M: Codificable>(&mut self, mensaje: &M) {
+        self.stream
+            .write_all(&enmarcar(&mensaje.to_bytes()))
+            .await
+            .expect("El nodo sigue conectado");
+    }
+
+    /// Espera el proximo mensaje del nodo, que tiene que ser del tipo M
+    pub async fn recibir<M: Codificable>(&mut self) -> M {
+        let lectura = async {
+            let largo = self.stream.read_u32().await?;
+            let mut bytes = vec![0; largo as usize];
+            self.stream.read_exact(&mut bytes).await?;
+            Ok::<_, std::io::Error>(bytes)
+        };
+        let bytes = tokio::time::timeout(Duration::from_secs(1), lectura)
+            .await
+            .unwrap_or_else(|_| panic!("No llego ningun mensaje del tipo {}", M::TIPO))
+            .expect("El nodo sigue conectado");
+        M::from_bytes(&bytes).unwrap_or_else(|err| {
+            panic!(
+                "Se esperaba el tipo {} y llego {:?}: {:?}",
+                M::TIPO,
+                bytes,
+                err
+            )
+        })
+    }
+}
+
+/// Nodo conectado a un coordinador falso y a una cafetera falsa, medido con un reloj virtual
+pub struct NodoDePrueba {
+    pub nodo: Addr<Nodo>,
+    pub coordinador: CoordinadorFalso,
+    /// respuestas que el nodo le envia a las cafeteras
+    pub cafetera: Recibidos,
+    pub reloj: RelojVirtual,
+}
+
+impl NodoDePrueba {
+    /// Nodo con las cuentas indicadas (id de cuenta y saldo)
+    pub fn new(id_nodo: IdNodo, cuentas: &[(IdCuenta, u32)]) -> Self {
+        let reloj = RelojVirtual::new();
+        let (extremo_nodo, extremo_coordinador) = duplex(64 * 1024);
+        let nodo = Nodo::conectado_a(
+            extremo_nodo,
+            id_nodo,
+            IdNodo(0),
+            false,
+            Box::new(AlmacenEnMemoria::new(cuentas.iter().copied().collect())),
+            Arc::new(reloj.clone()),
+        );
+        let cafetera = Recibidos::default();
+        let addr_cafetera = CafeteraListenerFalso {
+            recibidos: cafetera.clone(),
+        }
+        .start();
+        nodo.do_send(AddAddrActorCafetera {
+            addr_actor_cafetera: addr_cafetera.recipient(),
+        });
+        NodoDePrueba {
+            nodo,
+            coordinador: CoordinadorFalso {
+                stream: extremo_coordinador,
+            },
+            cafetera,
+            reloj,
+        }
+    }
+}
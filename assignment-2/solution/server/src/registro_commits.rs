@@ -0,0 +1,148 @@
+//! Registro ordenado de las transacciones que decide el coordinador. Cada COMMIT/ABORT que difunde se
+//! agrega con su secuencia, asi un verificador externo puede consultarlo y comparar que todos los nodos
+//! aplicaron la misma secuencia de operaciones
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use compartido::config::config;
+use compartido::ids::{IdCuenta, IdNodo, IdTransaccion};
+use serde::{Deserialize, Serialize};
+
+use crate::error_server::ErrorServer;
+use crate::mensaje::CommitType;
+
+/// Decision del coordinador sobre una transaccion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResultadoTransaccion {
+    COMMIT,
+    ABORT,
+}
+
+/// Transaccion decidida, tal como se difundio a los nodos
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntradaRegistro {
+    pub secuencia: u64,
+    pub id_transaccion: IdTransaccion,
+    pub id_cuenta: IdCuenta,
+    /// cuenta a la que se suman los creditos, solo en las transferencias
+    pub id_cuenta_destino: Option<IdCuenta>,
+    pub tipo: CommitType,
+    pub cantidad: u32,
+    pub resultado: ResultadoTransaccion,
+}
+
+/// Registro de commits del coordinador. Las entradas se guardan en memoria y, si tiene un archivo,
+/// tambien como lineas json al final del mismo
+#[derive(Default)]
+pub struct RegistroCommits {
+    entradas: Vec<EntradaRegistro>,
+    archivo: Option<File>,
+}
+
+impl RegistroCommits {
+    /// Registro que escribe cada entrada en el archivo indicado, que se trunca al crearlo
+    pub fn en_archivo(path: &Path) -> Result<Self, ErrorServer> {
+        let archivo = File::create(path)
+            .map_err(|x| ErrorServer::Archivo(format!("{}: {}", path.display(), x)))?;
+        Ok(RegistroCommits {
+            entradas: Vec::new(),
+            archivo: Some(archivo),
+        })
+    }
+
+    /// Registro del coordinador en el ID_NODO indicado. Con servidor.directorio_datos se guarda en
+    /// registro-coordinador-<ID_NODO>.jsonl dentro de ese directorio, si no solo en memoria
+    pub fn desde_config(id_nodo: IdNodo) -> Self {
+        let directorio = &config().servidor.directorio_datos;
+        if directorio.is_empty() {
+            return Self::default();
+        }
+        let path = Path::new(directorio).join(format!("registro-coordinador-{}.jsonl", id_nodo));
+        match Self::en_archivo(&path) {
+            Ok(registro) => registro,
+            Err(err) => {
+                tracing::warn!(
+                    "[COORDINADOR] No se pudo crear el registro de commits, queda solo en memoria | Detalle: {:?}",
+                    err
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Agrega la entrada al final del registro. Un error al escribir el archivo no detiene al
+    /// coordinador, la entrada queda igual en memoria
+    pub fn registrar(&mut self, entrada: EntradaRegistro) {
+        if let Some(archivo) = self.archivo.as_mut() {
+            let escrita = serde_json::to_string(&entrada)
+                .map_err(|x| x.to_string())
+                .and_then(|linea| writeln!(archivo, "{}", linea).map_err(|x| x.to_string()));
+            if let Err(err) = escrita {
+                tracing::error!(
+                    "[COORDINADOR] Error al escribir el registro de commits | Detalle: {}",
+                    err
+                );
+            }
+        }
+        self.entradas.push(entrada);
+    }
+
+    /// Espera a que las entradas escritas en el archivo lleguen al disco, al apagar el coordinador
+    pub fn sincronizar(&self) {
+        if let Some(Err(err)) = self.archivo.as_ref().map(File::sync_all) {
+            tracing::error!(
+                "[COORDINADOR] Error al sincronizar el registro de commits | Detalle: {}",
+                err
+            );
+        }
+    }
+
+    /// Entradas con secuencia mayor a la indicada, en el orden en que se decidieron
+    pub fn desde(&self, secuencia: u64) -> Vec<EntradaRegistro> {
+        self.entradas
+            .iter()
+            .filter(|x| x.secuencia > secuencia)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod registro_commits_test {
+    use super::*;
+
+    fn entrada(secuencia: u64, resultado: ResultadoTransaccion) -> EntradaRegistro {
+        EntradaRegistro {
+            secuencia,
+            id_transaccion: IdTransaccion(secuencia as u32 + 100),
+            id_cuenta: IdCuenta(7),
+            id_cuenta_destino: None,
+            tipo: CommitType::RESTA,
+            cantidad: 50,
+            resultado,
+        }
+    }
+
+    #[test]
+    fn el_registro_en_archivo_guarda_una_linea_json_por_entrada() {
+        let path =
+            std::env::temp_dir().join(format!("tp2-registro-test-{}.jsonl", std::process::id()));
+        let mut registro = RegistroCommits::en_archivo(&path).unwrap();
+        registro.registrar(entrada(1, ResultadoTransaccion::COMMIT));
+        registro.registrar(entrada(2, ResultadoTransaccion::ABORT));
+
+        let secuencias: Vec<u64> = registro.desde(1).iter().map(|x| x.secuencia).collect();
+        assert_eq!(vec![2], secuencias);
+
+        let contenido = std::fs::read_to_string(&path).unwrap();
+        let leidas: Vec<EntradaRegistro> = contenido
+            .lines()
+            .map(|x| serde_json::from_str(x).unwrap())
+            .collect();
+        assert_eq!(2, leidas.len());
+        assert_eq!(ResultadoTransaccion::ABORT, leidas[1].resultado);
+        let _ = std::fs::remove_file(&path);
+    }
+}
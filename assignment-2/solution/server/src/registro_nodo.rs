@@ -0,0 +1,161 @@
+//! Registro de lo que cada nodo aplica sobre sus cuentas: los saldos con los que arranca, los snapshots,
+//! los COMMIT (con los saldos que quedan) y las altas y bajas. Junto con el registro de commits del
+//! coordinador es la entrada del verificador, que luego de una prueba comprueba que todos los nodos
+//! aplicaron los mismos commits en el mismo orden y que sus saldos se derivan de los iniciales
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use compartido::config::config;
+use compartido::ids::{IdCuenta, IdNodo, IdTransaccion};
+use serde::{Deserialize, Serialize};
+
+use crate::error_server::ErrorServer;
+use crate::mensaje::CommitType;
+
+/// Cambio que el nodo aplico sobre sus cuentas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventoNodo {
+    /// el nodo arranco (o se reinicio) con estas cuentas de su almacen
+    Inicio { cuentas: Vec<(IdCuenta, u32)> },
+    /// el nodo aplico el snapshot del coordinador con ese corte y quedo con estas cuentas
+    Snapshot {
+        corte: u64,
+        cuentas: Vec<(IdCuenta, u32)>,
+    },
+    /// el nodo aplico el COMMIT. Los saldos son los de las cuentas involucradas que tiene el nodo,
+    /// luego de aplicarlo
+    Commit {
+        secuencia: u64,
+        id_transaccion: IdTransaccion,
+        id_cuenta: IdCuenta,
+        /// cuenta a la que se sumaron los creditos, solo en las transferencias
+        id_cuenta_destino: Option<IdCuenta>,
+        tipo: CommitType,
+        cantidad: u32,
+        saldos: Vec<(IdCuenta, u32)>,
+    },
+    /// se creo la cuenta con el saldo indicado
+    Alta { id_cuenta: IdCuenta, saldo: u32 },
+    /// se elimino la cuenta
+    Baja { id_cuenta: IdCuenta },
+}
+
+/// Linea del registro del nodo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntradaNodo {
+    /// coordinador al que estaba conectado el nodo, el que difundio el COMMIT o envio el snapshot
+    pub id_coordinador: IdNodo,
+    pub evento: EventoNodo,
+}
+
+/// Registro del nodo. A diferencia del del coordinador no se guarda en memoria: sin archivo no
+/// registra nada
+#[derive(Default)]
+pub struct RegistroNodo {
+    archivo: Option<File>,
+}
+
+impl RegistroNodo {
+    /// Registro que agrega las entradas al final del archivo indicado, asi un nodo que se reinicia
+    /// continua el mismo registro
+    pub fn en_archivo(path: &Path) -> Result<Self, ErrorServer> {
+        let archivo = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|x| ErrorServer::Archivo(format!("{}: {}", path.display(), x)))?;
+        Ok(RegistroNodo {
+            archivo: Some(archivo),
+        })
+    }
+
+    /// Registro del ID_NODO indicado. Con servidor.directorio_datos se guarda en
+    /// registro-nodo-<ID_NODO>.jsonl dentro de ese directorio, si no no se registra nada
+    pub fn desde_config(id_nodo: IdNodo) -> Self {
+        let directorio = &config().servidor.directorio_datos;
+        if directorio.is_empty() {
+            return Self::default();
+        }
+        let path = Path::new(directorio).join(format!("registro-nodo-{}.jsonl", id_nodo));
+        match Self::en_archivo(&path) {
+            Ok(registro) => registro,
+            Err(err) => {
+                tracing::warn!(
+                    "[NODO-{}] No se pudo abrir el registro del nodo, no se registra nada | Detalle: {:?}",
+                    id_nodo,
+                    err
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Agrega la entrada al final del registro. Un error al escribir no detiene al nodo
+    pub fn registrar(&mut self, id_coordinador: IdNodo, evento: EventoNodo) {
+        if let Some(archivo) = self.archivo.as_mut() {
+            let entrada = EntradaNodo {
+                id_coordinador,
+                evento,
+            };
+            let escrita = serde_json::to_string(&entrada)
+                .map_err(|x| x.to_string())
+                .and_then(|linea| writeln!(archivo, "{}", linea).map_err(|x| x.to_string()));
+            if let Err(err) = escrita {
+                tracing::error!(
+                    "[NODO] Error al escribir el registro del nodo | Detalle: {}",
+                    err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod registro_nodo_test {
+    use super::*;
+
+    #[test]
+    fn el_registro_se_continua_al_reabrir_el_archivo() {
+        let path = std::env::temp_dir().join(format!(
+            "tp2-registro-nodo-test-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let commit = EventoNodo::Commit {
+            secuencia: 1,
+            id_transaccion: IdTransaccion(100),
+            id_cuenta: IdCuenta(7),
+            id_cuenta_destino: Some(IdCuenta(8)),
+            tipo: CommitType::TRANSFERENCIA,
+            cantidad: 5,
+            saldos: vec![(IdCuenta(7), 95), (IdCuenta(8), 5)],
+        };
+        let mut registro = RegistroNodo::en_archivo(&path).unwrap();
+        registro.registrar(
+            IdNodo(1),
+            EventoNodo::Inicio {
+                cuentas: vec![(IdCuenta(7), 100)],
+            },
+        );
+        registro.registrar(IdNodo(1), commit.clone());
+        drop(registro);
+        RegistroNodo::en_archivo(&path).unwrap().registrar(
+            IdNodo(2),
+            EventoNodo::Baja {
+                id_cuenta: IdCuenta(8),
+            },
+        );
+
+        let leidas: Vec<EntradaNodo> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|x| serde_json::from_str(x).unwrap())
+            .collect();
+        assert_eq!(3, leidas.len());
+        assert_eq!(commit, leidas[1].evento);
+        assert_eq!(IdNodo(2), leidas[2].id_coordinador);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::mensaje::MotivoAbort;
+use compartido::config::config;
+
+/// Retorna el saldo que queda al sumar la cantidad, o el motivo de rechazo si el resultado
+/// supera saldo_maximo (o no entra en un u32)
+pub fn sumar_saldo(saldo: u32, cantidad: u32) -> Result<u32, MotivoAbort> {
+    saldo
+        .checked_add(cantidad)
+        .filter(|x| *x <= config().servidor.saldo_maximo)
+        .ok_or(MotivoAbort::SALDOMAXIMO)
+}
+
+/// Retorna el saldo que queda al restar la cantidad, o el motivo de rechazo si no alcanza
+pub fn restar_saldo(saldo: u32, cantidad: u32) -> Result<u32, MotivoAbort> {
+    saldo
+        .checked_sub(cantidad)
+        .ok_or(MotivoAbort::SALDOINSUFICIENTE)
+}
+
+/// Retorna los saldos de origen y destino luego de transferir la cantidad, o el motivo de rechazo
+/// si la cuenta origen no tiene saldo suficiente o la destino superaria saldo_maximo
+pub fn transferir_saldo(
+    saldo_origen: u32,
+    saldo_destino: u32,
+    cantidad: u32,
+) -> Result<(u32, u32), MotivoAbort> {
+    Ok((
+        restar_saldo(saldo_origen, cantidad)?,
+        sumar_saldo(saldo_destino, cantidad)?,
+    ))
+}
+
+#[cfg(test)]
+mod saldo_test {
+    use super::*;
+
+    #[test]
+    fn sumar_hasta_el_saldo_maximo_se_acepta() {
+        assert_eq!(
+            Ok(config().servidor.saldo_maximo),
+            sumar_saldo(config().servidor.saldo_maximo - 10, 10)
+        );
+        assert_eq!(
+            Ok(config().servidor.saldo_maximo),
+            sumar_saldo(config().servidor.saldo_maximo, 0)
+        );
+    }
+
+    #[test]
+    fn sumar_por_encima_del_saldo_maximo_se_rechaza() {
+        assert_eq!(
+            Err(MotivoAbort::SALDOMAXIMO),
+            sumar_saldo(config().servidor.saldo_maximo - 10, 11)
+        );
+        assert_eq!(
+            Err(MotivoAbort::SALDOMAXIMO),
+            sumar_saldo(config().servidor.saldo_maximo, 1)
+        );
+    }
+
+    #[test]
+    fn sumar_con_overflow_de_u32_se_rechaza() {
+        assert_eq!(Err(MotivoAbort::SALDOMAXIMO), sumar_saldo(u32::MAX, 1));
+        assert_eq!(Err(MotivoAbort::SALDOMAXIMO), sumar_saldo(1, u32::MAX));
+    }
+
+    #[test]
+    fn restar_todo_el_saldo_deja_la_cuenta_en_cero() {
+        assert_eq!(Ok(0), restar_saldo(100, 100));
+        assert_eq!(Ok(0), restar_saldo(0, 0));
+    }
+
+    #[test]
+    fn restar_mas_que_el_saldo_se_rechaza() {
+        assert_eq!(Err(MotivoAbort::SALDOINSUFICIENTE), restar_saldo(100, 101));
+        assert_eq!(Err(MotivoAbort::SALDOINSUFICIENTE), restar_saldo(0, 1));
+    }
+
+    #[test]
+    fn transferir_valida_ambas_cuentas() {
+        assert_eq!(Ok((0, 200)), transferir_saldo(100, 100, 100));
+        assert_eq!(
+            Err(MotivoAbort::SALDOINSUFICIENTE),
+            transferir_saldo(100, 100, 101)
+        );
+        assert_eq!(
+            Err(MotivoAbort::SALDOMAXIMO),
+            transferir_saldo(100, config().servidor.saldo_maximo, 1)
+        );
+    }
+}
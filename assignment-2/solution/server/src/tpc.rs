@@ -0,0 +1,1215 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use compartido::ids::{IdCuenta, IdTransaccion};
+use serde::Serialize;
+use tiempo::Reloj;
+use tracing::{info, warn};
+
+use crate::planificador::Planificador;
+
+/// Estado de una transaccion en el coordinador
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum EstadoTransaccion {
+    /// Espera llegar al frente de las colas de sus recursos
+    Encolada,
+    /// Se difundio el PREPARE y se esperan los votos de todos los participantes
+    Preparando,
+    /// Todos votaron que si, el participante que la inicio la esta ejecutando
+    Ejecutando,
+    /// Se difundio VALIDAR y se espera que todos validen el resultado antes del commit
+    Validando,
+    /// Se difundio el COMMIT y se esperan las confirmaciones
+    Commit,
+    /// Se difundio el ABORT y se esperan las confirmaciones
+    Abort,
+    /// Todos los participantes confirmaron el commit
+    Confirmada,
+    /// Todos los participantes confirmaron el abort
+    Abortada,
+}
+
+impl EstadoTransaccion {
+    /// true si ya se decidio el resultado de la transaccion y no puede abortarse
+    pub fn decidida(&self) -> bool {
+        matches!(
+            self,
+            EstadoTransaccion::Commit
+                | EstadoTransaccion::Abort
+                | EstadoTransaccion::Confirmada
+                | EstadoTransaccion::Abortada
+        )
+    }
+}
+
+/// Respuestas de una transaccion que todavia no termino en su fase actual, para diagnosticar las que
+/// no avanzan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgresoTransaccion<P> {
+    pub id: IdTransaccion,
+    pub estado: EstadoTransaccion,
+    /// participante que inicio la transaccion
+    pub origen: P,
+    /// participantes que ya respondieron en la fase actual
+    pub respondieron: Vec<P>,
+    /// participantes cuya respuesta se espera para avanzar de fase. Una encolada no espera a nadie y
+    /// una en ejecucion espera solo al origen
+    pub faltan: Vec<P>,
+}
+
+/// Mensajes que el coordinador envia a los participantes. Los COMMIT/ABORT llevan el numero de
+/// secuencia global con el que los participantes detectan los que se perdieron
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MensajeTpc {
+    Prepare,
+    Execute,
+    Validar,
+    Commit { secuencia: u64 },
+    Abort { secuencia: u64 },
+}
+
+/// Operacion que se coordina con el protocolo
+pub trait Operacion {
+    /// Cuentas que la operacion usa de forma exclusiva. Las operaciones que comparten alguna cuenta
+    /// se ejecutan de a una, en orden de llegada
+    fn recursos(&self) -> Vec<IdCuenta>;
+
+    /// Las operaciones prioritarias se adelantan en los recursos a las comunes que todavia no se
+    /// ejecutan, hasta el maximo de adelantamientos del coordinador
+    fn prioritaria(&self) -> bool {
+        false
+    }
+}
+
+/// Medio por el que el coordinador se comunica con los participantes
+pub trait Transporte<P, Op> {
+    /// Participantes que votan en este momento
+    fn participantes(&self) -> Vec<P>;
+
+    /// Envia el mensaje de la transaccion a un participante
+    fn enviar(&mut self, destino: P, id: IdTransaccion, operacion: &Op, mensaje: MensajeTpc);
+
+    /// Envia el mensaje a todos los participantes
+    fn difundir(&mut self, id: IdTransaccion, operacion: &Op, mensaje: MensajeTpc) {
+        for destino in self.participantes() {
+            self.enviar(destino, id, operacion, mensaje);
+        }
+    }
+
+    /// Notifica que todos los participantes confirmaron el resultado de la transaccion, que es
+    /// Confirmada o Abortada
+    fn terminada(&mut self, id: IdTransaccion, operacion: &Op, resultado: EstadoTransaccion);
+}
+
+/// Informacion del coordinador sobre una transaccion
+struct Transaccion<Op, P> {
+    operacion: Op,
+    /// participante que inicio la transaccion y la ejecuta
+    origen: P,
+    estado: EstadoTransaccion,
+    /// participantes que respondieron en la fase actual
+    respuestas: BTreeSet<P>,
+    /// secuencia con la que se difundio el commit o el abort
+    secuencia: u64,
+    /// momento del ultimo envio del commit
+    ultimo_envio: Option<Instant>,
+    /// reenvios del commit desde la ultima difusion
+    reenvios: u32,
+}
+
+/// Lado coordinador del commit de dos fases, independiente del transporte, de los participantes y
+/// de la operacion. Cada transaccion se encola en los recursos de su operacion y al llegar al frente
+/// recorre PREPARE, EXECUTE al origen, VALIDAR y COMMIT, o ABORT si algun participante la rechaza.
+/// Una fase avanza cuando respondieron todos los participantes que votan en ese momento, por lo que
+/// al quitarse uno hay que llamar a `revisar`
+pub struct CoordinadorTpc<Op, P> {
+    transacciones: HashMap<IdTransaccion, Transaccion<Op, P>>,
+    planificador: Planificador,
+    /// cantidad de COMMIT/ABORT difundidos
+    secuencia: u64,
+    reloj: Arc<dyn Reloj>,
+    /// espera entre reenvios del commit a los participantes que no lo confirmaron
+    intervalo_reenvio: Duration,
+    /// reenvios tras los que un participante que no confirma el commit se da por caido
+    max_reenvios: u32,
+}
+
+impl<Op, P> CoordinadorTpc<Op, P>
+where
+    Op: Operacion,
+    P: Copy + Ord + Debug,
+{
+    pub fn new(
+        reloj: Arc<dyn Reloj>,
+        tiempo_maximo_servicio: Duration,
+        intervalo_reenvio: Duration,
+        max_reenvios: u32,
+        max_adelantamientos: u32,
+    ) -> Self {
+        CoordinadorTpc {
+            transacciones: HashMap::new(),
+            planificador: Planificador::new(tiempo_maximo_servicio, max_adelantamientos),
+            secuencia: 0,
+            reloj,
+            intervalo_reenvio,
+            max_reenvios,
+        }
+    }
+
+    pub fn estado(&self, id: IdTransaccion) -> Option<EstadoTransaccion> {
+        self.transacciones.get(&id).map(|x| x.estado)
+    }
+
+    pub fn operacion(&self, id: IdTransaccion) -> Option<&Op> {
+        self.transacciones.get(&id).map(|x| &x.operacion)
+    }
+
+    pub fn operacion_mut(&mut self, id: IdTransaccion) -> Option<&mut Op> {
+        self.transacciones.get_mut(&id).map(|x| &mut x.operacion)
+    }
+
+    /// Progreso de las transacciones que todavia no terminaron, ordenadas por id, segun los
+    /// participantes que votan en este momento
+    pub fn progreso(&self, participantes: &[P]) -> Vec<ProgresoTransaccion<P>> {
+        let mut progreso: Vec<ProgresoTransaccion<P>> = self
+            .transacciones
+            .iter()
+            .filter(|(_, x)| {
+                !matches!(
+                    x.estado,
+                    EstadoTransaccion::Confirmada | EstadoTransaccion::Abortada
+                )
+            })
+            .map(|(id, x)| {
+                let faltan = match x.estado {
+                    EstadoTransaccion::Encolada => vec![],
+                    EstadoTransaccion::Ejecutando => vec![x.origen],
+                    _ => participantes
+                        .iter()
+                        .filter(|p| !x.respuestas.contains(p))
+                        .copied()
+                        .collect(),
+                };
+                ProgresoTransaccion {
+                    id: *id,
+                    estado: x.estado,
+                    origen: x.origen,
+                    respondieron: x.respuestas.iter().copied().collect(),
+                    faltan,
+                }
+            })
+            .collect();
+        progreso.sort_unstable_by_key(|x| x.id);
+        progreso
+    }
+
+    /// Cantidad de COMMIT/ABORT difundidos, marca el corte de los snapshots
+    pub fn secuencia(&self) -> u64 {
+        self.secuencia
+    }
+
+    pub fn planificador(&self) -> &Planificador {
+        &self.planificador
+    }
+
+    /// Encola la transaccion en los recursos de la operacion y si quedo al frente difunde el PREPARE.
+    /// Si la transaccion ya existia (por ejemplo un STARTER reintentado tras una falla parcial) no se
+    /// vuelve a encolar: se responde al origen segun su estado actual (ver `repetida`) y se retorna
+    /// ese estado
+    pub fn encolar(
+        &mut self,
+        id: IdTransaccion,
+        origen: P,
+        operacion: Op,
+        transporte: &mut impl Transporte<P, Op>,
+    ) -> Option<EstadoTransaccion> {
+        if self.transacciones.contains_key(&id) {
+            return self.repetida(id, transporte);
+        }
+        let recursos = operacion.recursos();
+        let prioritaria = operacion.prioritaria();
+        self.insertar(id, origen, operacion, EstadoTransaccion::Encolada);
+        if self
+            .planificador
+            .encolar(&recursos, id, prioritaria, self.reloj.ahora())
+        {
+            self.despachar(id, transporte);
+        }
+        None
+    }
+
+    /// Reenvia al origen de una transaccion repetida el ultimo mensaje de su estado, solo si repetirlo
+    /// no tiene efectos: el PREPARE (un voto repetido no cuenta dos veces) o el COMMIT/ABORT ya
+    /// difundido. EXECUTE y VALIDAR no se reenvian porque el origen ejecutaria dos veces la
+    /// operacion, y una encolada recibe el PREPARE al llegar al frente. Retorna el estado
+    fn repetida(
+        &mut self,
+        id: IdTransaccion,
+        transporte: &mut impl Transporte<P, Op>,
+    ) -> Option<EstadoTransaccion> {
+        let transaccion = self.transacciones.get(&id)?;
+        let mensaje = match transaccion.estado {
+            EstadoTransaccion::Preparando => Some(MensajeTpc::Prepare),
+            EstadoTransaccion::Commit | EstadoTransaccion::Confirmada => Some(MensajeTpc::Commit {
+                secuencia: transaccion.secuencia,
+            }),
+            EstadoTransaccion::Abort | EstadoTransaccion::Abortada => Some(MensajeTpc::Abort {
+                secuencia: transaccion.secuencia,
+            }),
+            _ => None,
+        };
+        if let Some(mensaje) = mensaje {
+            transporte.enviar(transaccion.origen, id, &transaccion.operacion, mensaje);
+        }
+        Some(transaccion.estado)
+    }
+
+    /// Difunde directamente el commit de una operacion que no necesita votacion ni bloquear recursos
+    pub fn commit_directo(
+        &mut self,
+        id: IdTransaccion,
+        origen: P,
+        operacion: Op,
+        transporte: &mut impl Transporte<P, Op>,
+    ) {
+        self.insertar(id, origen, operacion, EstadoTransaccion::Validando);
+        self.difundir_commit(id, transporte);
+    }
+
+    /// Voto afirmativo de un participante. Cuando votaron todos se le pide al origen que ejecute
+    pub fn votar(
+        &mut self,
+        id: IdTransaccion,
+        participante: P,
+        transporte: &mut impl Transporte<P, Op>,
+    ) {
+        self.responder(id, participante, EstadoTransaccion::Preparando, transporte);
+    }
+
+    /// El origen termino de ejecutar la operacion: se difunde VALIDAR para que todos comprueben que
+    /// pueden aplicar el resultado. Se ignora si la transaccion no se estaba ejecutando
+    pub fn validar(&mut self, id: IdTransaccion, transporte: &mut impl Transporte<P, Op>) -> bool {
+        let transaccion = match self.transacciones.get_mut(&id) {
+            Some(x) if x.estado == EstadoTransaccion::Ejecutando => x,
+            _ => return false,
+        };
+        transaccion.estado = EstadoTransaccion::Validando;
+        transaccion.respuestas.clear();
+        transporte.difundir(id, &transaccion.operacion, MensajeTpc::Validar);
+        true
+    }
+
+    /// Un participante valido el resultado. Cuando validaron todos se difunde el commit
+    pub fn valido(
+        &mut self,
+        id: IdTransaccion,
+        participante: P,
+        transporte: &mut impl Transporte<P, Op>,
+    ) {
+        self.responder(id, participante, EstadoTransaccion::Validando, transporte);
+    }
+
+    /// Confirmacion de un COMMIT o de un ABORT. Cuando confirmaron todos la transaccion termina y
+    /// libera sus recursos. Las confirmaciones repetidas de un commit reenviado no cuentan dos veces
+    pub fn confirmar(
+        &mut self,
+        id: IdTransaccion,
+        participante: P,
+        transporte: &mut impl Transporte<P, Op>,
+    ) {
+        match self.estado(id) {
+            Some(EstadoTransaccion::Commit) => {
+                self.responder(id, participante, EstadoTransaccion::Commit, transporte)
+            }
+            Some(EstadoTransaccion::Abort) => {
+                self.responder(id, participante, EstadoTransaccion::Abort, transporte)
+            }
+            _ => {}
+        }
+    }
+
+    /// Aborta la transaccion difundiendo el ABORT, salvo que ya se haya decidido su resultado. Varios
+    /// participantes pueden rechazar la misma transaccion y se aborta una sola vez. Retorna true si
+    /// se difundio el ABORT
+    pub fn abortar(&mut self, id: IdTransaccion, transporte: &mut impl Transporte<P, Op>) -> bool {
+        let transaccion = match self.transacciones.get_mut(&id) {
+            Some(x) if !x.estado.decidida() => x,
+            _ => return false,
+        };
+        self.secuencia += 1;
+        transaccion.estado = EstadoTransaccion::Abort;
+        transaccion.respuestas.clear();
+        transaccion.secuencia = self.secuencia;
+        transporte.difundir(
+            id,
+            &transaccion.operacion,
+            MensajeTpc::Abort {
+                secuencia: self.secuencia,
+            },
+        );
+        true
+    }
+
+    /// Commits en curso que el participante todavia no confirmo, en el orden en que se difundieron,
+    /// como (id, secuencia)
+    pub fn commits_sin_confirmar(&self, participante: P) -> Vec<(IdTransaccion, u64)> {
+        let mut commits: Vec<(IdTransaccion, u64)> = self
+            .transacciones
+            .iter()
+            .filter(|(_, x)| {
+                x.estado == EstadoTransaccion::Commit && !x.respuestas.contains(&participante)
+            })
+            .map(|(id, x)| (*id, x.secuencia))
+            .collect();
+        commits.sort_by_key(|(_, secuencia)| *secuencia);
+        commits
+    }
+
+    /// El participante dejo de votar: se descartan sus respuestas (si vuelve debe votar de nuevo), las
+    /// transacciones que inicio y que seguian encoladas se retiran de las colas sin difundirlas, las
+    /// demas que inicio y que todavia no estaban decididas se abortan, y se revisan las que solo
+    /// esperaban su respuesta. Las commiteadas no se abortan, se completan con los participantes
+    /// restantes
+    pub fn participante_caido(&mut self, participante: P, transporte: &mut impl Transporte<P, Op>) {
+        for transaccion in self.transacciones.values_mut() {
+            transaccion.respuestas.remove(&participante);
+        }
+        let mut iniciadas: Vec<(IdTransaccion, EstadoTransaccion)> = self
+            .transacciones
+            .iter()
+            .filter(|(_, x)| x.origen == participante && !x.estado.decidida())
+            .map(|(id, x)| (*id, x.estado))
+            .collect();
+        iniciadas.sort_unstable_by_key(|(id, _)| *id);
+        for (id, estado) in iniciadas {
+            if estado == EstadoTransaccion::Encolada {
+                // ningun participante recibio su PREPARE, no hace falta difundir el ABORT
+                self.terminar(id, EstadoTransaccion::Abortada, transporte);
+            } else {
+                self.abortar(id, transporte);
+            }
+        }
+        self.revisar(transporte);
+    }
+
+    /// Avanza las transacciones cuya fase ya respondieron todos los participantes actuales
+    pub fn revisar(&mut self, transporte: &mut impl Transporte<P, Op>) {
+        let mut ids: Vec<IdTransaccion> = self.transacciones.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            self.avanzar(id, transporte);
+        }
+    }
+
+    /// Aborta las transacciones que superaron el tiempo maximo de servicio al frente de sus colas y
+    /// despacha las que quedaron en su lugar. Retorna las vencidas
+    pub fn revisar_vencidas(
+        &mut self,
+        transporte: &mut impl Transporte<P, Op>,
+    ) -> Vec<IdTransaccion> {
+        let transacciones = &self.transacciones;
+        let vencidas = self.planificador.vencidas(self.reloj.ahora(), |id| {
+            !transacciones.get(&id).is_some_and(|x| x.estado.decidida())
+        });
+        let mut ids = vec![];
+        for (id, siguientes) in vencidas {
+            self.abortar(id, transporte);
+            for siguiente in siguientes {
+                self.despachar(siguiente, transporte);
+            }
+            ids.push(id);
+        }
+        ids
+    }
+
+    /// Reenvia el commit a los participantes que no lo confirmaron en el ultimo intervalo. Retorna
+    /// los participantes que siguen sin confirmar luego de max_reenvios reenvios, que deben darse por
+    /// caidos
+    pub fn reenviar_commits(&mut self, transporte: &mut impl Transporte<P, Op>) -> Vec<P> {
+        let ahora = self.reloj.ahora();
+        let participantes = transporte.participantes();
+        let mut caidos = BTreeSet::new();
+        let mut ids: Vec<IdTransaccion> = self.transacciones.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let transaccion = match self.transacciones.get_mut(&id) {
+                Some(x) if x.estado == EstadoTransaccion::Commit => x,
+                _ => continue,
+            };
+            if transaccion
+                .ultimo_envio
+                .is_some_and(|x| ahora.saturating_duration_since(x) < self.intervalo_reenvio)
+            {
+                continue;
+            }
+            let faltantes: Vec<P> = participantes
+                .iter()
+                .filter(|p| !transaccion.respuestas.contains(p))
+                .copied()
+                .collect();
+            if transaccion.reenvios >= self.max_reenvios {
+                caidos.extend(faltantes);
+                continue;
+            }
+            transaccion.reenvios += 1;
+            transaccion.ultimo_envio = Some(ahora);
+            for destino in faltantes {
+                info!(
+                    "[COORDINADOR] Reenvio COMMIT de ID_TRANSACCION = {:?} al participante {:?} ({}/{})",
+                    id, destino, transaccion.reenvios, self.max_reenvios
+                );
+                transporte.enviar(
+                    destino,
+                    id,
+                    &transaccion.operacion,
+                    MensajeTpc::Commit {
+                        secuencia: transaccion.secuencia,
+                    },
+                );
+            }
+        }
+        caidos.into_iter().collect()
+    }
+
+    fn insertar(&mut self, id: IdTransaccion, origen: P, operacion: Op, estado: EstadoTransaccion) {
+        self.transacciones.insert(
+            id,
+            Transaccion {
+                operacion,
+                origen,
+                estado,
+                respuestas: BTreeSet::new(),
+                secuencia: 0,
+                ultimo_envio: None,
+                reenvios: 0,
+            },
+        );
+    }
+
+    /// Difunde el PREPARE de una transaccion que paso al frente de las colas de sus recursos
+    fn despachar(&mut self, id: IdTransaccion, transporte: &mut impl Transporte<P, Op>) {
+        let transaccion = match self.transacciones.get_mut(&id) {
+            Some(x) if x.estado == EstadoTransaccion::Encolada => x,
+            _ => {
+                warn!(
+                    "[COORDINADOR] No existe la transaccion encolada con ID_TRANSACCION = {:?}",
+                    id
+                );
+                return;
+            }
+        };
+        transaccion.estado = EstadoTransaccion::Preparando;
+        transporte.difundir(id, &transaccion.operacion, MensajeTpc::Prepare);
+    }
+
+    /// Registra la respuesta del participante si la transaccion esta en la fase esperada
+    fn responder(
+        &mut self,
+        id: IdTransaccion,
+        participante: P,
+        fase: EstadoTransaccion,
+        transporte: &mut impl Transporte<P, Op>,
+    ) {
+        match self.transacciones.get_mut(&id) {
+            Some(x) if x.estado == fase => {
+                x.respuestas.insert(participante);
+            }
+            _ => return,
+        }
+        self.avanzar(id, transporte);
+    }
+
+    /// Si respondieron todos los participantes actuales pasa a la siguiente fase
+    fn avanzar(&mut self, id: IdTransaccion, transporte: &mut impl Transporte<P, Op>) {
+        let participantes = transporte.participantes();
+        let transaccion = match self.transacciones.get_mut(&id) {
+            Some(x) => x,
+            None => return,
+        };
+        if !participantes
+            .iter()
+            .all(|p| transaccion.respuestas.contains(p))
+        {
+            return;
+        }
+        match transaccion.estado {
+            EstadoTransaccion::Preparando => {
+                transaccion.estado = EstadoTransaccion::Ejecutando;
+                transaccion.respuestas.clear();
+                transporte.enviar(
+                    transaccion.origen,
+                    id,
+                    &transaccion.operacion,
+                    MensajeTpc::Execute,
+                );
+            }
+            EstadoTransaccion::Validando => self.difundir_commit(id, transporte),
+            EstadoTransaccion::Commit => {
+                self.terminar(id, EstadoTransaccion::Confirmada, transporte)
+            }
+            EstadoTransaccion::Abort => self.terminar(id, EstadoTransaccion::Abortada, transporte),
+            _ => {}
+        }
+    }
+
+    fn difundir_commit(&mut self, id: IdTransaccion, transporte: &mut impl Transporte<P, Op>) {
+        let transaccion = match self.transacciones.get_mut(&id) {
+            Some(x) => x,
+            None => return,
+        };
+        self.secuencia += 1;
+        transaccion.estado = EstadoTransaccion::Commit;
+        transaccion.respuestas.clear();
+        transaccion.secuencia = self.secuencia;
+        transaccion.ultimo_envio = Some(self.reloj.ahora());
+        transaccion.reenvios = 0;
+        transporte.difundir(
+            id,
+            &transaccion.operacion,
+            MensajeTpc::Commit {
+                secuencia: self.secuencia,
+            },
+        );
+    }
+
+    /// Termina la transaccion, libera sus recursos y despacha las que quedaron al frente de sus colas
+    fn terminar(
+        &mut self,
+        id: IdTransaccion,
+        resultado: EstadoTransaccion,
+        transporte: &mut impl Transporte<P, Op>,
+    ) {
+        if let Some(transaccion) = self.transacciones.get_mut(&id) {
+            transaccion.estado = resultado;
+            transaccion.respuestas.clear();
+            transporte.terminada(id, &transaccion.operacion, resultado);
+        }
+        for siguiente in self.planificador.liberar(id, self.reloj.ahora()) {
+            self.despachar(siguiente, transporte);
+        }
+    }
+}
+
+/// Resultado de recibir un COMMIT en un participante
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolucion<Op> {
+    /// Primera vez que llega el commit, se aplica. Incluye la operacion preparada, si la hubo
+    Aplicar(Option<Op>),
+    /// Commit reenviado que ya se aplico, o cuya secuencia ya esta reflejada en el estado (por
+    /// ejemplo por un snapshot), solo se vuelve a confirmar
+    Repetido,
+}
+
+/// Lado participante del commit de dos fases: recuerda las operaciones preparadas hasta su
+/// COMMIT/ABORT, no aplica dos veces un commit reenviado y detecta por su secuencia los COMMIT/ABORT
+/// que se perdio
+pub struct ParticipanteTpc<Op> {
+    /// operaciones preparadas por id de transaccion
+    preparadas: HashMap<IdTransaccion, Op>,
+    /// id de las transacciones cuyo commit ya se aplico
+    aplicadas: HashSet<IdTransaccion>,
+    /// secuencia del ultimo COMMIT/ABORT reflejado en el estado del participante
+    ultima_secuencia: u64,
+}
+
+impl<Op> Default for ParticipanteTpc<Op> {
+    fn default() -> Self {
+        ParticipanteTpc {
+            preparadas: HashMap::new(),
+            aplicadas: HashSet::new(),
+            ultima_secuencia: 0,
+        }
+    }
+}
+
+impl<Op> ParticipanteTpc<Op> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Recuerda la operacion que el participante preparo y voto
+    pub fn preparar(&mut self, id: IdTransaccion, operacion: Op) {
+        self.preparadas.insert(id, operacion);
+    }
+
+    pub fn preparada(&self, id: IdTransaccion) -> Option<&Op> {
+        self.preparadas.get(&id)
+    }
+
+    pub fn ultima_secuencia(&self) -> u64 {
+        self.ultima_secuencia
+    }
+
+    /// Registra la secuencia de un COMMIT/ABORT recibido. Si se salteo alguna retorna la ultima
+    /// secuencia aplicada antes del hueco, desde la que hay que resincronizar
+    pub fn registrar_secuencia(&mut self, secuencia: u64) -> Option<u64> {
+        if secuencia == 0 || secuencia <= self.ultima_secuencia {
+            // sin secuencia o reenviado por el coordinador
+            return None;
+        }
+        let anterior = self.ultima_secuencia;
+        self.ultima_secuencia = secuencia;
+        (secuencia > anterior + 1).then_some(anterior)
+    }
+
+    /// El estado del participante pasa a reflejar hasta la secuencia indicada, por ejemplo al
+    /// aplicar un snapshot o al cambiar de coordinador
+    pub fn sincronizar(&mut self, secuencia: u64) {
+        self.ultima_secuencia = secuencia;
+    }
+
+    /// Recibe el COMMIT de la transaccion con la secuencia con la que lo difundio el coordinador. Se
+    /// debe llamar antes de registrar la secuencia, para distinguir un commit nuevo de uno que ya
+    /// esta reflejado en el estado aunque el participante no lo haya aplicado
+    pub fn commit(&mut self, id: IdTransaccion, secuencia: u64) -> Resolucion<Op> {
+        let reflejado = secuencia != 0 && secuencia <= self.ultima_secuencia;
+        if !self.aplicadas.insert(id) || reflejado {
+            self.preparadas.remove(&id);
+            return Resolucion::Repetido;
+        }
+        Resolucion::Aplicar(self.preparadas.remove(&id))
+    }
+
+    /// Recibe el ABORT de la transaccion y retorna la operacion preparada, si la hubo
+    pub fn abort(&mut self, id: IdTransaccion) -> Option<Op> {
+        self.preparadas.remove(&id)
+    }
+}
+
+#[cfg(test)]
+mod tpc_test {
+    use std::collections::BTreeSet;
+
+    use tiempo::RelojVirtual;
+
+    use super::*;
+
+    const INTERVALO_REENVIO: Duration = Duration::from_millis(100);
+    const MAX_REENVIOS: u32 = 2;
+    const MAX_ADELANTAMIENTOS: u32 = 3;
+    const TIEMPO_MAXIMO: Duration = Duration::from_secs(5);
+
+    /// Operacion de prueba que usa los recursos indicados
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Op(Vec<u32>);
+
+    impl Operacion for Op {
+        fn recursos(&self) -> Vec<IdCuenta> {
+            self.0.iter().copied().map(IdCuenta).collect()
+        }
+    }
+
+    /// Red simulada que registra los mensajes enviados y las transacciones terminadas
+    #[derive(Default)]
+    struct Red {
+        participantes: BTreeSet<u8>,
+        enviados: Vec<(u8, IdTransaccion, MensajeTpc)>,
+        terminadas: Vec<(IdTransaccion, EstadoTransaccion)>,
+    }
+
+    impl Red {
+        fn new(participantes: &[u8]) -> Self {
+            Red {
+                participantes: participantes.iter().copied().collect(),
+                ..Default::default()
+            }
+        }
+
+        /// Mensajes enviados desde la ultima llamada
+        fn tomar(&mut self) -> Vec<(u8, IdTransaccion, MensajeTpc)> {
+            std::mem::take(&mut self.enviados)
+        }
+    }
+
+    impl Transporte<u8, Op> for Red {
+        fn participantes(&self) -> Vec<u8> {
+            self.participantes.iter().copied().collect()
+        }
+
+        fn enviar(&mut self, destino: u8, id: IdTransaccion, _operacion: &Op, mensaje: MensajeTpc) {
+            self.enviados.push((destino, id, mensaje));
+        }
+
+        fn terminada(&mut self, id: IdTransaccion, _operacion: &Op, resultado: EstadoTransaccion) {
+            self.terminadas.push((id, resultado));
+        }
+    }
+
+    fn coordinador(reloj: &RelojVirtual) -> CoordinadorTpc<Op, u8> {
+        CoordinadorTpc::new(
+            Arc::new(reloj.clone()),
+            TIEMPO_MAXIMO,
+            INTERVALO_REENVIO,
+            MAX_REENVIOS,
+            MAX_ADELANTAMIENTOS,
+        )
+    }
+
+    fn a_todos(
+        participantes: &[u8],
+        id: IdTransaccion,
+        mensaje: MensajeTpc,
+    ) -> Vec<(u8, IdTransaccion, MensajeTpc)> {
+        participantes.iter().map(|p| (*p, id, mensaje)).collect()
+    }
+
+    /// Lleva la transaccion hasta la difusion del commit con las respuestas de todos
+    fn hasta_el_commit(tpc: &mut CoordinadorTpc<Op, u8>, red: &mut Red, id: IdTransaccion) {
+        for p in red.participantes() {
+            tpc.votar(id, p, red);
+        }
+        tpc.validar(id, red);
+        for p in red.participantes() {
+            tpc.valido(id, p, red);
+        }
+    }
+
+    #[test]
+    fn con_todas_las_respuestas_la_transaccion_se_confirma() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1, 2, 3]);
+
+        tpc.encolar(IdTransaccion(10), 2, Op(vec![7]), &mut red);
+        assert_eq!(
+            red.tomar(),
+            a_todos(&[1, 2, 3], IdTransaccion(10), MensajeTpc::Prepare)
+        );
+
+        tpc.votar(IdTransaccion(10), 1, &mut red);
+        tpc.votar(IdTransaccion(10), 3, &mut red);
+        // un voto repetido no completa la votacion
+        tpc.votar(IdTransaccion(10), 3, &mut red);
+        assert!(red.tomar().is_empty());
+        tpc.votar(IdTransaccion(10), 2, &mut red);
+        assert_eq!(
+            red.tomar(),
+            vec![(2, IdTransaccion(10), MensajeTpc::Execute)]
+        );
+
+        assert!(tpc.validar(IdTransaccion(10), &mut red));
+        assert_eq!(
+            red.tomar(),
+            a_todos(&[1, 2, 3], IdTransaccion(10), MensajeTpc::Validar)
+        );
+        for p in [1, 2, 3] {
+            tpc.valido(IdTransaccion(10), p, &mut red);
+        }
+        let commit = MensajeTpc::Commit { secuencia: 1 };
+        assert_eq!(red.tomar(), a_todos(&[1, 2, 3], IdTransaccion(10), commit));
+
+        for p in [1, 2, 3] {
+            tpc.confirmar(IdTransaccion(10), p, &mut red);
+        }
+        assert_eq!(
+            tpc.estado(IdTransaccion(10)),
+            Some(EstadoTransaccion::Confirmada)
+        );
+        assert_eq!(
+            red.terminadas,
+            vec![(IdTransaccion(10), EstadoTransaccion::Confirmada)]
+        );
+        assert_eq!(tpc.planificador().pendientes(), 0);
+    }
+
+    #[test]
+    fn las_transacciones_con_recursos_en_comun_se_ejecutan_de_a_una() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1]);
+
+        tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red);
+        tpc.encolar(IdTransaccion(11), 1, Op(vec![7, 8]), &mut red);
+        tpc.encolar(IdTransaccion(12), 1, Op(vec![9]), &mut red);
+        assert_eq!(
+            tpc.estado(IdTransaccion(11)),
+            Some(EstadoTransaccion::Encolada)
+        );
+        assert_eq!(
+            red.tomar(),
+            vec![
+                (1, IdTransaccion(10), MensajeTpc::Prepare),
+                (1, IdTransaccion(12), MensajeTpc::Prepare)
+            ]
+        );
+
+        hasta_el_commit(&mut tpc, &mut red, IdTransaccion(10));
+        red.tomar();
+        tpc.confirmar(IdTransaccion(10), 1, &mut red);
+        assert_eq!(
+            red.tomar(),
+            vec![(1, IdTransaccion(11), MensajeTpc::Prepare)]
+        );
+        assert_eq!(
+            tpc.estado(IdTransaccion(11)),
+            Some(EstadoTransaccion::Preparando)
+        );
+    }
+
+    #[test]
+    fn una_transaccion_repetida_no_se_encola_dos_veces_y_responde_su_estado() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1, 2]);
+
+        assert_eq!(
+            tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red),
+            None
+        );
+        red.tomar();
+        assert_eq!(
+            tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red),
+            Some(EstadoTransaccion::Preparando)
+        );
+        assert_eq!(
+            red.tomar(),
+            vec![(1, IdTransaccion(10), MensajeTpc::Prepare)]
+        );
+        assert_eq!(tpc.planificador().pendientes(), 1);
+
+        // en ejecucion no se reenvia nada, el origen ejecutaria dos veces
+        tpc.votar(IdTransaccion(10), 1, &mut red);
+        tpc.votar(IdTransaccion(10), 2, &mut red);
+        red.tomar();
+        assert_eq!(
+            tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red),
+            Some(EstadoTransaccion::Ejecutando)
+        );
+        assert!(red.tomar().is_empty());
+
+        assert!(tpc.abortar(IdTransaccion(10), &mut red));
+        tpc.confirmar(IdTransaccion(10), 1, &mut red);
+        tpc.confirmar(IdTransaccion(10), 2, &mut red);
+        red.tomar();
+        assert_eq!(
+            tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red),
+            Some(EstadoTransaccion::Abortada)
+        );
+        let abort = MensajeTpc::Abort { secuencia: 1 };
+        assert_eq!(red.tomar(), vec![(1, IdTransaccion(10), abort)]);
+        assert_eq!(tpc.planificador().pendientes(), 0);
+    }
+
+    #[test]
+    fn el_abort_se_difunde_una_sola_vez_y_libera_los_recursos() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1, 2]);
+
+        tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red);
+        tpc.encolar(IdTransaccion(11), 1, Op(vec![7]), &mut red);
+        red.tomar();
+        assert!(tpc.abortar(IdTransaccion(10), &mut red));
+        assert!(!tpc.abortar(IdTransaccion(10), &mut red));
+        let abort = MensajeTpc::Abort { secuencia: 1 };
+        assert_eq!(red.tomar(), a_todos(&[1, 2], IdTransaccion(10), abort));
+
+        // las respuestas de otra fase no cuentan como confirmaciones del abort
+        tpc.votar(IdTransaccion(10), 1, &mut red);
+        tpc.valido(IdTransaccion(10), 2, &mut red);
+        assert_eq!(
+            tpc.estado(IdTransaccion(10)),
+            Some(EstadoTransaccion::Abort)
+        );
+
+        tpc.confirmar(IdTransaccion(10), 1, &mut red);
+        tpc.confirmar(IdTransaccion(10), 2, &mut red);
+        assert_eq!(
+            tpc.estado(IdTransaccion(10)),
+            Some(EstadoTransaccion::Abortada)
+        );
+        assert_eq!(
+            red.terminadas,
+            vec![(IdTransaccion(10), EstadoTransaccion::Abortada)]
+        );
+        assert_eq!(
+            red.tomar(),
+            a_todos(&[1, 2], IdTransaccion(11), MensajeTpc::Prepare)
+        );
+        assert_eq!(tpc.secuencia(), 1);
+    }
+
+    #[test]
+    fn una_transaccion_commiteada_no_se_aborta() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1, 2]);
+
+        tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red);
+        hasta_el_commit(&mut tpc, &mut red, IdTransaccion(10));
+        red.tomar();
+        assert!(!tpc.abortar(IdTransaccion(10), &mut red));
+        assert!(red.tomar().is_empty());
+        assert_eq!(
+            tpc.estado(IdTransaccion(10)),
+            Some(EstadoTransaccion::Commit)
+        );
+
+        tpc.commit_directo(IdTransaccion(20), 2, Op(vec![7]), &mut red);
+        assert!(!tpc.abortar(IdTransaccion(20), &mut red));
+        let commit = MensajeTpc::Commit { secuencia: 2 };
+        assert_eq!(red.tomar(), a_todos(&[1, 2], IdTransaccion(20), commit));
+    }
+
+    #[test]
+    fn la_caida_de_un_participante_aborta_lo_que_inicio_y_completa_lo_que_esperaba() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1, 2, 3]);
+
+        // 10 la inicio el 3 y esta en votacion, 11 solo espera la confirmacion del 3
+        tpc.encolar(IdTransaccion(10), 3, Op(vec![7]), &mut red);
+        tpc.encolar(IdTransaccion(11), 1, Op(vec![8]), &mut red);
+        hasta_el_commit(&mut tpc, &mut red, IdTransaccion(11));
+        tpc.confirmar(IdTransaccion(11), 1, &mut red);
+        tpc.confirmar(IdTransaccion(11), 2, &mut red);
+        tpc.votar(IdTransaccion(10), 1, &mut red);
+        red.tomar();
+        assert_eq!(tpc.commits_sin_confirmar(3), vec![(IdTransaccion(11), 1)]);
+
+        red.participantes.remove(&3);
+        tpc.participante_caido(3, &mut red);
+        let abort = MensajeTpc::Abort { secuencia: 2 };
+        assert_eq!(red.tomar(), a_todos(&[1, 2], IdTransaccion(10), abort));
+        assert_eq!(
+            tpc.estado(IdTransaccion(10)),
+            Some(EstadoTransaccion::Abort)
+        );
+        assert_eq!(
+            tpc.estado(IdTransaccion(11)),
+            Some(EstadoTransaccion::Confirmada)
+        );
+    }
+
+    #[test]
+    fn la_caida_de_un_participante_descarta_sus_votos_y_lo_que_tenia_encolado() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1, 2, 3]);
+
+        // 10 esta en votacion, 11 (del 3) y 12 esperan detras en la cola del recurso 7
+        tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red);
+        tpc.encolar(IdTransaccion(11), 3, Op(vec![7]), &mut red);
+        tpc.encolar(IdTransaccion(12), 2, Op(vec![7]), &mut red);
+        tpc.votar(IdTransaccion(10), 3, &mut red);
+        red.tomar();
+
+        red.participantes.remove(&3);
+        tpc.participante_caido(3, &mut red);
+        // la encolada se retira sin difundir nada
+        assert!(red.tomar().is_empty());
+        assert_eq!(
+            tpc.estado(IdTransaccion(11)),
+            Some(EstadoTransaccion::Abortada)
+        );
+        assert_eq!(
+            red.terminadas,
+            vec![(IdTransaccion(11), EstadoTransaccion::Abortada)]
+        );
+
+        // al volver, su voto anterior ya no cuenta
+        red.participantes.insert(3);
+        tpc.votar(IdTransaccion(10), 1, &mut red);
+        tpc.votar(IdTransaccion(10), 2, &mut red);
+        assert!(red.tomar().is_empty());
+        tpc.votar(IdTransaccion(10), 3, &mut red);
+        assert_eq!(
+            red.tomar(),
+            vec![(1, IdTransaccion(10), MensajeTpc::Execute)]
+        );
+
+        // al terminar 10 se despacha 12 sin esperar a la que se retiro
+        assert!(tpc.abortar(IdTransaccion(10), &mut red));
+        red.tomar();
+        for p in [1, 2, 3] {
+            tpc.confirmar(IdTransaccion(10), p, &mut red);
+        }
+        assert_eq!(
+            red.tomar(),
+            a_todos(&[1, 2, 3], IdTransaccion(12), MensajeTpc::Prepare)
+        );
+    }
+
+    #[test]
+    fn el_commit_se_reenvia_y_quien_no_lo_confirma_se_da_por_caido() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1, 2]);
+
+        tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red);
+        hasta_el_commit(&mut tpc, &mut red, IdTransaccion(10));
+        tpc.confirmar(IdTransaccion(10), 1, &mut red);
+        red.tomar();
+
+        // recien difundido no se reenvia
+        assert!(tpc.reenviar_commits(&mut red).is_empty());
+        assert!(red.tomar().is_empty());
+
+        let commit = MensajeTpc::Commit { secuencia: 1 };
+        for _ in 0..MAX_REENVIOS {
+            reloj.avanzar(INTERVALO_REENVIO);
+            assert!(tpc.reenviar_commits(&mut red).is_empty());
+            assert_eq!(red.tomar(), vec![(2, IdTransaccion(10), commit)]);
+        }
+        reloj.avanzar(INTERVALO_REENVIO);
+        assert_eq!(tpc.reenviar_commits(&mut red), vec![2]);
+        assert!(red.tomar().is_empty());
+    }
+
+    #[test]
+    fn la_transaccion_vencida_se_aborta_y_despacha_la_siguiente() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1]);
+
+        tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red);
+        tpc.encolar(IdTransaccion(11), 1, Op(vec![7]), &mut red);
+        red.tomar();
+        reloj.avanzar(TIEMPO_MAXIMO);
+        assert!(tpc.revisar_vencidas(&mut red).is_empty());
+
+        reloj.avanzar(Duration::from_millis(1));
+        assert_eq!(tpc.revisar_vencidas(&mut red), vec![IdTransaccion(10)]);
+        let abort = MensajeTpc::Abort { secuencia: 1 };
+        assert_eq!(
+            red.tomar(),
+            vec![
+                (1, IdTransaccion(10), abort),
+                (1, IdTransaccion(11), MensajeTpc::Prepare)
+            ]
+        );
+    }
+
+    #[test]
+    fn la_transaccion_decidida_no_vence_mientras_se_reenvia_el_commit() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1, 2]);
+
+        tpc.encolar(IdTransaccion(10), 1, Op(vec![7]), &mut red);
+        tpc.encolar(IdTransaccion(11), 1, Op(vec![7]), &mut red);
+        hasta_el_commit(&mut tpc, &mut red, IdTransaccion(10));
+        tpc.confirmar(IdTransaccion(10), 1, &mut red);
+        red.tomar();
+
+        // el participante 2 no confirma el commit: la transaccion sigue con sus cuentas tomadas
+        reloj.avanzar(TIEMPO_MAXIMO + Duration::from_millis(1));
+        assert!(tpc.revisar_vencidas(&mut red).is_empty());
+        assert!(red.tomar().is_empty());
+
+        // al confirmarlo se libera la cuenta y recien entonces se despacha la siguiente
+        tpc.confirmar(IdTransaccion(10), 2, &mut red);
+        assert_eq!(
+            red.tomar(),
+            a_todos(&[1, 2], IdTransaccion(11), MensajeTpc::Prepare)
+        );
+    }
+
+    #[test]
+    fn el_progreso_indica_que_participantes_faltan_en_cada_fase() {
+        let reloj = RelojVirtual::new();
+        let mut tpc = coordinador(&reloj);
+        let mut red = Red::new(&[1, 2, 3]);
+        let participantes = red.participantes();
+
+        tpc.encolar(IdTransaccion(10), 2, Op(vec![7]), &mut red);
+        tpc.encolar(IdTransaccion(11), 1, Op(vec![7]), &mut red);
+        tpc.votar(IdTransaccion(10), 1, &mut red);
+        tpc.votar(IdTransaccion(10), 3, &mut red);
+        let progreso = tpc.progreso(&participantes);
+        assert_eq!(
+            progreso,
+            vec![
+                ProgresoTransaccion {
+                    id: IdTransaccion(10),
+                    estado: EstadoTransaccion::Preparando,
+                    origen: 2,
+                    respondieron: vec![1, 3],
+                    faltan: vec![2],
+                },
+                ProgresoTransaccion {
+                    id: IdTransaccion(11),
+                    estado: EstadoTransaccion::Encolada,
+                    origen: 1,
+                    respondieron: vec![],
+                    faltan: vec![],
+                },
+            ]
+        );
+
+        // en ejecucion solo se espera el FINISH del origen
+        tpc.votar(IdTransaccion(10), 2, &mut red);
+        assert_eq!(tpc.progreso(&participantes)[0].faltan, vec![2]);
+        tpc.validar(IdTransaccion(10), &mut red);
+        for p in [1, 2, 3] {
+            tpc.valido(IdTransaccion(10), p, &mut red);
+        }
+        tpc.confirmar(IdTransaccion(10), 3, &mut red);
+        let progreso = tpc.progreso(&participantes);
+        assert_eq!(
+            (progreso[0].estado, progreso[0].faltan.clone()),
+            (EstadoTransaccion::Commit, vec![1, 2])
+        );
+
+        // las terminadas no figuran
+        tpc.confirmar(IdTransaccion(10), 1, &mut red);
+        tpc.confirmar(IdTransaccion(10), 2, &mut red);
+        let progreso = tpc.progreso(&participantes);
+        assert_eq!(
+            progreso
+                .iter()
+                .map(|x| (x.id, x.estado))
+                .collect::<Vec<_>>(),
+            vec![(IdTransaccion(11), EstadoTransaccion::Preparando)]
+        );
+    }
+
+    #[test]
+    fn el_participante_aplica_cada_commit_una_vez_y_detecta_huecos() {
+        let mut participante = ParticipanteTpc::new();
+        participante.preparar(IdTransaccion(10), Op(vec![7]));
+        assert_eq!(
+            participante.commit(IdTransaccion(10), 1),
+            Resolucion::Aplicar(Some(Op(vec![7])))
+        );
+        assert_eq!(participante.registrar_secuencia(1), None);
+        // commit reenviado por el coordinador
+        assert_eq!(
+            participante.commit(IdTransaccion(10), 1),
+            Resolucion::Repetido
+        );
+        assert_eq!(participante.registrar_secuencia(1), None);
+
+        participante.preparar(IdTransaccion(11), Op(vec![8]));
+        assert_eq!(participante.registrar_secuencia(4), Some(1));
+        assert_eq!(participante.abort(IdTransaccion(11)), Some(Op(vec![8])));
+        assert_eq!(participante.ultima_secuencia(), 4);
+
+        participante.sincronizar(0);
+        assert_eq!(participante.registrar_secuencia(1), None);
+    }
+
+    #[test]
+    fn el_participante_no_aplica_un_commit_que_ya_refleja_el_snapshot() {
+        let mut participante = ParticipanteTpc::new();
+        participante.preparar(IdTransaccion(20), Op(vec![7]));
+        participante.sincronizar(5);
+        // difundido antes del corte: los saldos del snapshot ya lo incluyen
+        assert_eq!(
+            participante.commit(IdTransaccion(20), 3),
+            Resolucion::Repetido
+        );
+        assert_eq!(participante.preparada(IdTransaccion(20)), None);
+        assert_eq!(
+            participante.commit(IdTransaccion(21), 6),
+            Resolucion::Aplicar(None)
+        );
+        // sin secuencia no hay forma de saber si el snapshot lo incluye, se aplica
+        assert_eq!(
+            participante.commit(IdTransaccion(22), 0),
+            Resolucion::Aplicar(None)
+        );
+    }
+}
@@ -1,27 +1,198 @@
-pub const ID_CORDINADOR_INICIAL: u8 = 1;
-pub const SALDO_INICIAL: u32 = 10000;
-pub const MAX_UDP_SIZE: usize = 14;
-pub const CANT_MAX_NODOS: u8 = 3;
-pub const TIMEOUT_OK_BULLY_MILLIS: u64 = 10000;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix::fut::wrap_future;
+use actix::{Actor, ActorFutureExt, AsyncContext, Context};
+use compartido::config::config;
+use compartido::ids::IdNodo;
+use socket2::{SockRef, TcpKeepalive};
+use tiempo::Reloj;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::codec::{FramedRead, LengthDelimitedCodec};
+use tracing::warn;
+
+// El resto de los parametros del servidor (saldos, timeouts, puertos) se leen de la configuracion
+// compartida, ver compartido::config
+
+/// tamaño de los buffers udp de los nodos, alcanza para los mensajes de cafetera con cualquier codec
+pub const MAX_UDP_SIZE: usize = compartido::codec::MAX_BYTES_MENSAJE;
+/// Argumento seguido del path al archivo json con las cuentas con las que arranca el nodo
+pub const ARG_CUENTAS: &str = "--cuentas";
 
 pub fn id_to_addr_read_data(id: u8) -> String {
-    "127.0.0.1:1235".to_owned() + &id.to_string()
+    config().cluster.addr_lectura_datos(id)
 }
 
 pub fn id_to_addr_write_data(id: u8) -> String {
-    "127.0.0.1:1236".to_owned() + &id.to_string()
+    config().cluster.addr_escritura_datos(id)
 }
 
-pub fn id_to_ctrladdr(id: u8) -> String {
-    "127.0.0.1:1234".to_owned() + &id.to_string()
+/// Direccion del servicio grpc del nodo, solo con la feature grpc
+pub fn id_to_addr_grpc(id: u8) -> String {
+    config().cluster.addr_grpc(id)
+}
+
+pub fn id_to_ctrladdr(id: IdNodo) -> String {
+    config().cluster.addr_ctrl(id.0)
 }
 
 pub fn id_to_addr_write_bully(id: u8) -> String {
-    "127.0.0.1:1243".to_owned() + &id.to_string()
+    config().cluster.addr_escritura_bully(id)
 }
 
 pub fn id_to_addr_read_bully(id: u8) -> String {
-    "127.0.0.1:1242".to_owned() + &id.to_string()
+    config().cluster.addr_lectura_bully(id)
+}
+
+/// Mensajes que llegan por la conexion tcp entre un nodo y el coordinador, cada uno precedido por
+/// su largo como u32 en big endian
+pub fn leer_mensajes<R: AsyncRead>(read: R) -> impl Stream<Item = Result<Vec<u8>, std::io::Error>> {
+    FramedRead::new(read, LengthDelimitedCodec::new()).map(|trama| trama.map(|x| x.to_vec()))
+}
+
+/// Antepone al mensaje su largo, para enviarlo por la conexion tcp entre un nodo y el coordinador
+pub fn enmarcar(mensaje: &[u8]) -> Vec<u8> {
+    [&(mensaje.len() as u32).to_be_bytes()[..], mensaje].concat()
+}
+
+/// Activa el keepalive tcp en una conexion entre un nodo y el coordinador. Si el otro extremo se cae
+/// sin cerrarla (por ejemplo por un corte de luz), luego de keepalive_sondas sondas sin respuesta la
+/// lectura termina con error y la conexion se trata como cerrada
+pub fn configurar_keepalive(stream: &TcpStream) -> std::io::Result<()> {
+    let servidor = &config().servidor;
+    let keepalive = TcpKeepalive::new()
+        .with_time(Duration::from_secs(servidor.keepalive_inactividad_segundos))
+        .with_interval(Duration::from_secs(servidor.keepalive_intervalo_segundos));
+    #[cfg(not(windows))]
+    let keepalive = keepalive.with_retries(servidor.keepalive_sondas);
+    SockRef::from(stream).set_tcp_keepalive(&keepalive)
+}
+
+/// Enmarca y escribe el mensaje en la conexion con un plazo de timeout_escritura_millis. Si el otro
+/// extremo dejo de leer y se llenaron los buffers, retorna un error TimedOut en lugar de esperar
+/// indefinidamente
+pub async fn escribir_con_plazo<W: AsyncWrite + Unpin>(
+    write: &mut W,
+    mensaje: &[u8],
+) -> std::io::Result<()> {
+    let plazo = Duration::from_millis(config().servidor.timeout_escritura_millis);
+    tokio::time::timeout(plazo, write.write_all(&enmarcar(mensaje)))
+        .await
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))?
+}
+
+/// Ejecuta la operacion hasta que tenga exito o se agoten los intentos, esperando con el reloj entre
+/// uno y otro: `espera_inicial` luego del primero y el doble cada vez. Retorna el error del ultimo
+pub async fn reintentar_con_backoff<T, E, F, Fut>(
+    reloj: &dyn Reloj,
+    intentos: u32,
+    espera_inicial: Duration,
+    descripcion: &str,
+    mut operacion: F,
+) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut espera = espera_inicial;
+    let mut intento = 1;
+    loop {
+        match operacion().await {
+            Err(err) if intento < intentos => {
+                warn!(
+                    "{} | Intento {}/{} fallido, se reintenta en {:?} | Detalle: {}",
+                    descripcion, intento, intentos, espera, err
+                );
+                reloj.esperar(espera).await;
+                espera *= 2;
+                intento += 1;
+            }
+            resultado => return resultado,
+        }
+    }
 }
 
-// echo -e '\x00''\x10' | nc -u 127.0.0.1 12341
+/// Como `ctx.run_interval`, pero el intervalo se mide con el reloj del actor, asi en los tests las
+/// tareas periodicas se disparan al avanzar un RelojVirtual
+pub fn cada_intervalo<A>(
+    ctx: &mut Context<A>,
+    reloj: Arc<dyn Reloj>,
+    intervalo: Duration,
+    tarea: fn(&mut A, &mut Context<A>),
+) where
+    A: Actor<Context = Context<A>>,
+{
+    ctx.spawn(
+        wrap_future::<_, A>(reloj.esperar(intervalo)).map(move |_, actor, ctx| {
+            tarea(actor, ctx);
+            cada_intervalo(ctx, reloj, intervalo, tarea);
+        }),
+    );
+}
+
+#[cfg(test)]
+mod utils_test {
+    use super::*;
+    use std::cell::Cell;
+    use tiempo::RelojVirtual;
+    use tokio::net::TcpListener;
+
+    #[actix_rt::test]
+    async fn la_conexion_con_el_coordinador_queda_con_keepalive() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        assert!(!SockRef::from(&stream).keepalive().unwrap());
+
+        configurar_keepalive(&stream).unwrap();
+        let socket = SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(
+            Duration::from_secs(config().servidor.keepalive_inactividad_segundos),
+            socket.keepalive_time().unwrap()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn se_reintenta_duplicando_la_espera_hasta_agotar_los_intentos() {
+        let reloj = RelojVirtual::automatico();
+        let llamadas = Cell::new(0);
+        let resultado: Result<(), String> =
+            reintentar_con_backoff(&reloj, 4, Duration::from_millis(100), "prueba", || {
+                llamadas.set(llamadas.get() + 1);
+                async { Err("sin coordinador".to_string()) }
+            })
+            .await;
+
+        assert_eq!(Err("sin coordinador".to_string()), resultado);
+        assert_eq!(4, llamadas.get());
+        assert_eq!(Duration::from_millis(100 + 200 + 400), reloj.transcurrido());
+    }
+
+    #[actix_rt::test]
+    async fn se_deja_de_reintentar_con_el_primer_exito() {
+        let reloj = RelojVirtual::automatico();
+        let llamadas = Cell::new(0);
+        let resultado =
+            reintentar_con_backoff(&reloj, 4, Duration::from_millis(100), "prueba", || {
+                llamadas.set(llamadas.get() + 1);
+                let intento = llamadas.get();
+                async move {
+                    match intento {
+                        3 => Ok(intento),
+                        _ => Err("sin coordinador"),
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(Ok(3), resultado);
+        assert_eq!(Duration::from_millis(100 + 200), reloj.transcurrido());
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use compartido::config::config;
+
+/// Circuit breaker de los nodos a los que una cafetera envia sus pedidos. Cada respuesta que no
+/// llega a tiempo (sondeo o pedido) cuenta como una falla del nodo, y la espera del siguiente intento
+/// crece con las fallas consecutivas hasta multiplicador_maximo. Luego de fallas_para_abrir fallas
+/// consecutivas el circuito del nodo se abre: la cafetera no le envia mas pedidos hasta que pasa
+/// intervalo_prueba, cuando se lo vuelve a sondear. Si responde el circuito se cierra
+pub struct Circuito {
+    fallas_para_abrir: u32,
+    intervalo_prueba: Duration,
+    multiplicador_maximo: u64,
+    nodos: HashMap<String, EstadoNodo>,
+}
+
+#[derive(Default)]
+struct EstadoNodo {
+    fallas_consecutivas: u32,
+    /// momento en que se abrio el circuito (o en que fallo la ultima prueba), None si esta cerrado
+    abierto_desde: Option<Instant>,
+}
+
+impl Circuito {
+    pub fn new(
+        fallas_para_abrir: u32,
+        intervalo_prueba: Duration,
+        multiplicador_maximo: u64,
+    ) -> Self {
+        Circuito {
+            fallas_para_abrir,
+            intervalo_prueba,
+            multiplicador_maximo,
+            nodos: HashMap::new(),
+        }
+    }
+
+    /// Circuito con los parametros de la seccion sucursal de la configuracion
+    pub fn desde_config() -> Self {
+        let sucursal = &config().sucursal;
+        Self::new(
+            sucursal.fallas_para_abrir_circuito,
+            Duration::from_millis(sucursal.intervalo_prueba_circuito_millis),
+            sucursal.multiplicador_timeout_maximo,
+        )
+    }
+
+    /// true si se le puede enviar trafico al nodo: el circuito esta cerrado, o esta abierto pero ya
+    /// paso el intervalo de prueba
+    pub fn permite(&self, nodo: &str, ahora: Instant) -> bool {
+        match self.nodos.get(nodo).and_then(|x| x.abierto_desde) {
+            Some(desde) => ahora.saturating_duration_since(desde) >= self.intervalo_prueba,
+            None => true,
+        }
+    }
+
+    pub fn abierto(&self, nodo: &str) -> bool {
+        self.nodos
+            .get(nodo)
+            .is_some_and(|x| x.abierto_desde.is_some())
+    }
+
+    /// Multiplicador del timeout del proximo intento con el nodo, 1 mas sus fallas consecutivas
+    pub fn multiplicador_timeout(&self, nodo: &str) -> u64 {
+        let fallas = self.nodos.get(nodo).map_or(0, |x| x.fallas_consecutivas);
+        (1 + u64::from(fallas)).min(self.multiplicador_maximo)
+    }
+
+    /// El nodo respondio: se cierra su circuito
+    pub fn registrar_exito(&mut self, nodo: &str) {
+        self.nodos.remove(nodo);
+    }
+
+    /// El nodo no respondio a tiempo. Retorna true si con esta falla su circuito queda abierto
+    pub fn registrar_falla(&mut self, nodo: &str, ahora: Instant) -> bool {
+        let estado = self.nodos.entry(nodo.to_string()).or_default();
+        estado.fallas_consecutivas += 1;
+        if estado.fallas_consecutivas >= self.fallas_para_abrir {
+            estado.abierto_desde = Some(ahora);
+        }
+        estado.abierto_desde.is_some()
+    }
+}
+
+#[cfg(test)]
+mod circuito_test {
+    use super::*;
+
+    #[test]
+    fn se_abre_con_fallas_consecutivas_y_se_cierra_si_responde_la_prueba() {
+        let mut circuito = Circuito::new(3, Duration::from_secs(5), 2);
+        let inicio = Instant::now();
+
+        assert!(!circuito.registrar_falla("1", inicio));
+        assert_eq!(2, circuito.multiplicador_timeout("1"));
+        assert!(!circuito.registrar_falla("1", inicio));
+        // el timeout no crece por encima del maximo
+        assert_eq!(2, circuito.multiplicador_timeout("1"));
+        assert!(circuito.registrar_falla("1", inicio));
+        assert!(!circuito.permite("1", inicio + Duration::from_secs(4)));
+        assert!(circuito.permite("2", inicio));
+
+        // falla la prueba: el circuito sigue abierto otro intervalo
+        let prueba = inicio + Duration::from_secs(5);
+        assert!(circuito.permite("1", prueba));
+        assert!(circuito.registrar_falla("1", prueba));
+        assert!(!circuito.permite("1", prueba + Duration::from_secs(1)));
+
+        circuito.registrar_exito("1");
+        assert!(!circuito.abierto("1"));
+        assert_eq!(1, circuito.multiplicador_timeout("1"));
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use compartido::ids::{IdCafetera, IdCuenta};
+use serde::Deserialize;
+
+use crate::error_sucursal::ErrorSucursal;
+
+/// Linea del archivo de cuentas por cafetera, por ejemplo {"id_cafetera": 0, "cuentas": [1, 2]}
+#[derive(Deserialize)]
+struct LineaCuentas {
+    id_cafetera: IdCafetera,
+    cuentas: Vec<IdCuenta>,
+}
+
+/// Cuentas con las que puede operar cada cafetera, como si el lector de tarjetas de cada maquina solo
+/// aceptara las tarjetas dadas de alta en ella. Una cuenta puede estar en varias cafeteras
+#[derive(Debug, Default)]
+pub struct CuentasCafeteras {
+    cafeteras: BTreeMap<IdCafetera, BTreeSet<IdCuenta>>,
+}
+
+impl CuentasCafeteras {
+    /// Lee el archivo indicado en sucursal.archivo_cuentas_cafeteras
+    pub fn desde_archivo(path: &str) -> Result<Self, ErrorSucursal> {
+        let contenido = std::fs::read_to_string(path)
+            .map_err(|x| ErrorSucursal::Archivo(format!("{}: {}", path, x)))?;
+        Self::parsear(&contenido)
+    }
+
+    /// Arma las cuentas por cafetera a partir de una linea json por cafetera. Las lineas vacias se
+    /// ignoran y si una cafetera aparece mas de una vez se juntan sus cuentas. Si hay lineas invalidas
+    /// el error las lista a todas
+    pub fn parsear(contenido: &str) -> Result<Self, ErrorSucursal> {
+        let mut cafeteras: BTreeMap<IdCafetera, BTreeSet<IdCuenta>> = BTreeMap::new();
+        let mut errores = vec![];
+        for (numero_linea, linea) in contenido.lines().enumerate() {
+            if linea.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<LineaCuentas>(linea) {
+                Ok(x) => cafeteras
+                    .entry(x.id_cafetera)
+                    .or_default()
+                    .extend(x.cuentas),
+                Err(x) => errores.push(format!("linea {}: {}", numero_linea + 1, x)),
+            }
+        }
+        if !errores.is_empty() {
+            return Err(ErrorSucursal::Parseo(format!(
+                "Archivo de cuentas por cafetera invalido | {}",
+                errores.join(" | ")
+            )));
+        }
+        Ok(CuentasCafeteras { cafeteras })
+    }
+
+    /// true si la cafetera puede operar con la cuenta
+    pub fn permite(&self, id_cafetera: IdCafetera, id_cuenta: IdCuenta) -> bool {
+        self.cafeteras
+            .get(&id_cafetera)
+            .is_some_and(|x| x.contains(&id_cuenta))
+    }
+
+    /// Elige la cafetera a la que se le asigna un pedido de la cuenta: de las que pueden operar con
+    /// ella, la que tiene menos pedidos asignados (la de menor id si empatan). `asignados` tiene la
+    /// cantidad de pedidos de cada cafetera de la sucursal, las cafeteras del archivo que no estan en
+    /// la sucursal no se tienen en cuenta. None si ninguna cafetera de la sucursal puede operar con la cuenta
+    pub fn elegir(&self, id_cuenta: IdCuenta, asignados: &[usize]) -> Option<IdCafetera> {
+        self.cafeteras
+            .iter()
+            .filter(|(id_cafetera, cuentas)| {
+                (id_cafetera.0 as usize) < asignados.len() && cuentas.contains(&id_cuenta)
+            })
+            .min_by_key(|(id_cafetera, _)| asignados[id_cafetera.0 as usize])
+            .map(|(id_cafetera, _)| *id_cafetera)
+    }
+}
+
+#[cfg(test)]
+mod cuentas_cafeteras_test {
+    use super::*;
+
+    #[test]
+    fn los_pedidos_van_a_la_cafetera_habilitada_con_menos_pedidos() {
+        let cuentas = CuentasCafeteras::parsear(
+            "{\"id_cafetera\": 0, \"cuentas\": [1, 2]}\n\n{\"id_cafetera\": 1, \"cuentas\": [2]}\n{\"id_cafetera\": 5, \"cuentas\": [3]}\n{\"id_cafetera\": 1, \"cuentas\": [4]}",
+        )
+        .unwrap();
+        assert!(cuentas.permite(IdCafetera(1), IdCuenta(4)));
+        assert!(!cuentas.permite(IdCafetera(1), IdCuenta(1)));
+
+        assert_eq!(Some(IdCafetera(0)), cuentas.elegir(IdCuenta(1), &[3, 0]));
+        assert_eq!(Some(IdCafetera(0)), cuentas.elegir(IdCuenta(2), &[0, 0]));
+        assert_eq!(Some(IdCafetera(1)), cuentas.elegir(IdCuenta(2), &[1, 0]));
+        // la cafetera 5 no existe en una sucursal de dos cafeteras
+        assert_eq!(None, cuentas.elegir(IdCuenta(3), &[0, 0]));
+        assert_eq!(None, cuentas.elegir(IdCuenta(9), &[0, 0]));
+    }
+
+    #[test]
+    fn el_error_lista_todas_las_lineas_invalidas() {
+        let error = CuentasCafeteras::parsear(
+            "{\"id_cafetera\": 0, \"cuentas\": [1]}\n{\"id_cafetera\": 300, \"cuentas\": []}\nnada",
+        )
+        .unwrap_err();
+        let ErrorSucursal::Parseo(detalle) = error else {
+            panic!("se esperaba un error de parseo: {:?}", error);
+        };
+        assert!(detalle.contains("linea 2"));
+        assert!(detalle.contains("linea 3"));
+        assert!(!detalle.contains("linea 1:"));
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use compartido::codec::{id_cafetera_from_bytes, MAX_BYTES_MENSAJE};
+use compartido::ids::IdCafetera;
+use compartido::mensajes_cafetera::{id_pedido_from_bytes, MensajeCafetera};
+use rand::Rng;
+use tracing::{debug, error, warn};
+
+/// Maximo que se bloquea cada lectura del socket antes de revisar si se cerro el demultiplexor
+const INTERVALO_LECTURA: Duration = Duration::from_millis(100);
+
+/// Socket udp unico de la sucursal, compartido por los preparadores de todas sus cafeteras. Un thread
+/// lee los mensajes de los nodos y los reparte entre los canales de los preparadores:
+/// * las respuestas a un pedido (OKEY, ERROR, CONFIRMADO y FALLIDO) van al canal que envio el pedido
+///   con ese id de pedido, hasta la respuesta final
+/// * las respuestas a los mensajes de control, que no llevan id de pedido (sondeo, registro y baja),
+///   van a los canales de la cafetera que enviaron una consulta de ese tipo, en el orden en que
+///   consultaron
+///
+/// Los mensajes que no corresponden a ningun canal abierto se descartan. Para probar los reintentos sin
+/// herramientas externas, el demultiplexor puede simular la perdida de datagramas: con la probabilidad
+/// indicada cada mensaje no se envia (aunque se informa como enviado) o se descarta al leerlo
+pub struct Demultiplexor {
+    compartido: Arc<Compartido>,
+    lector: Option<JoinHandle<()>>,
+}
+
+/// Estado que comparten el thread lector y los canales
+struct Compartido {
+    socket: UdpSocket,
+    rutas: Mutex<Rutas>,
+    /// true cuando se cierra el demultiplexor, el thread lector termina
+    cerrado: AtomicBool,
+    /// id del proximo canal que se abre
+    proximo_canal: AtomicUsize,
+    /// probabilidad de que se pierda cada datagrama enviado o recibido
+    probabilidad_perdida: f64,
+}
+
+/// Canales abiertos y a cual de ellos va cada respuesta
+#[derive(Default)]
+struct Rutas {
+    /// por id de canal, el extremo por el que se le envian sus mensajes
+    canales: HashMap<usize, Sender<Vec<u8>>>,
+    /// por id de pedido, el canal que lo envio
+    pedidos: HashMap<u32, RutaPedido>,
+    /// por id de cafetera y tipo de mensaje, los canales que esperan la respuesta a una consulta
+    consultas: HashMap<(IdCafetera, u8), VecDeque<usize>>,
+}
+
+/// Canal que envio un pedido y que espera sus respuestas
+struct RutaPedido {
+    id_canal: usize,
+    /// false si el ultimo mensaje del canal fue el SUMAR o RESTAR, cuyo OKEY solo habilita a
+    /// preparar el cafe. Los OKEY de la transferencia y de la confirmacion de la cafetera son finales
+    okey_final: bool,
+}
+
+impl RutaPedido {
+    /// true si luego del mensaje el nodo no envia mas respuestas al pedido, salvo que el canal le
+    /// vuelva a escribir
+    fn es_final(&self, mensaje: &[u8]) -> bool {
+        match MensajeCafetera::from_bytes(mensaje[0]) {
+            MensajeCafetera::OKEY => self.okey_final,
+            MensajeCafetera::ERROR | MensajeCafetera::CONFIRMADO | MensajeCafetera::FALLIDO => true,
+            _ => false,
+        }
+    }
+}
+
+impl Rutas {
+    /// Canal al que va el mensaje, None si no lo espera ningun canal. Con la respuesta final de un
+    /// pedido se quita su ruta
+    fn destino(&mut self, mensaje: &[u8]) -> Option<usize> {
+        match id_pedido_from_bytes(mensaje) {
+            0 => {
+                let id_cafetera = id_cafetera_from_bytes(mensaje)?;
+                self.consultas
+                    .get_mut(&(id_cafetera, mensaje[0]))?
+                    .pop_front()
+            }
+            id_pedido => {
+                let ruta = self.pedidos.get(&id_pedido)?;
+                let id_canal = ruta.id_canal;
+                if ruta.es_final(mensaje) {
+                    self.pedidos.remove(&id_pedido);
+                }
+                Some(id_canal)
+            }
+        }
+    }
+
+    /// Quita el canal y todas sus rutas
+    fn cerrar(&mut self, id_canal: usize) {
+        self.canales.remove(&id_canal);
+        self.pedidos.retain(|_, ruta| ruta.id_canal != id_canal);
+        self.consultas
+            .values_mut()
+            .for_each(|canales| canales.retain(|canal| *canal != id_canal));
+    }
+}
+
+impl Compartido {
+    fn rutas(&self) -> MutexGuard<'_, Rutas> {
+        // las rutas quedan consistentes aunque un preparador entre en panico con el lock tomado
+        self.rutas.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Lee los mensajes del socket y los reparte hasta que se cierra el demultiplexor
+    fn leer(&self) {
+        let mut buffer = [0u8; MAX_BYTES_MENSAJE];
+        while !self.cerrado.load(Ordering::SeqCst) {
+            let cantidad_leida = match self.socket.recv_from(&mut buffer) {
+                Ok((0, _)) => continue,
+                Ok((cantidad_leida, _)) => cantidad_leida,
+                Err(ref err)
+                    if err.kind() == io::ErrorKind::WouldBlock
+                        || err.kind() == io::ErrorKind::TimedOut =>
+                {
+                    continue
+                }
+                Err(err) => {
+                    error!("Error reading from socket: {}", err);
+                    continue;
+                }
+            };
+            if self.se_pierde("recibido", &buffer[..cantidad_leida]) {
+                continue;
+            }
+            self.repartir(&buffer[..cantidad_leida]);
+        }
+    }
+
+    /// true si se simula la perdida del datagrama, segun probabilidad_perdida
+    fn se_pierde(&self, sentido: &str, mensaje: &[u8]) -> bool {
+        let perdido = self.probabilidad_perdida > 0.0
+            && rand::thread_rng().gen::<f64>() < self.probabilidad_perdida;
+        if perdido {
+            debug!(
+                "Se simula la perdida de un mensaje {} de tipo {} | ID_CAFETERA = {:?} | ID_PEDIDO = {}",
+                sentido,
+                mensaje[0],
+                id_cafetera_from_bytes(mensaje),
+                id_pedido_from_bytes(mensaje)
+            );
+        }
+        perdido
+    }
+
+    /// Envia el mensaje por el socket, salvo que se simule su perdida
+    fn enviar<A: ToSocketAddrs>(&self, mensaje: &[u8], destino: A) -> io::Result<usize> {
+        if self.se_pierde("enviado", mensaje) {
+            return Ok(mensaje.len());
+        }
+        self.socket.send_to(mensaje, destino)
+    }
+
+    /// Envia el mensaje al canal que lo espera, o lo descarta si no hay ninguno
+    fn repartir(&self, mensaje: &[u8]) {
+        let mut rutas = self.rutas();
+        let canal = rutas
+            .destino(mensaje)
+            .and_then(|id_canal| rutas.canales.get(&id_canal));
+        match canal {
+            Some(canal) => {
+                let _ = canal.send(mensaje.to_vec());
+            }
+            None => warn!(
+                "Se descarta un mensaje de tipo {} que no espera ninguna cafetera | ID_CAFETERA = {:?} | ID_PEDIDO = {}",
+                mensaje[0],
+                id_cafetera_from_bytes(mensaje),
+                id_pedido_from_bytes(mensaje)
+            ),
+        }
+    }
+}
+
+impl Demultiplexor {
+    /// Abre el socket de la sucursal en la direccion indicada (con puerto 0 lo elige el sistema
+    /// operativo) y empieza a leer sus mensajes
+    pub fn new<A: ToSocketAddrs>(direccion: A) -> io::Result<Self> {
+        Self::con_perdida(direccion, 0.0)
+    }
+
+    /// Igual que new, pero cada datagrama que se envia o se recibe se pierde con la probabilidad
+    /// indicada
+    pub fn con_perdida<A: ToSocketAddrs>(
+        direccion: A,
+        probabilidad_perdida: f64,
+    ) -> io::Result<Self> {
+        let socket = UdpSocket::bind(direccion)?;
+        socket.set_read_timeout(Some(INTERVALO_LECTURA))?;
+        let compartido = Arc::new(Compartido {
+            socket,
+            rutas: Mutex::new(Rutas::default()),
+            cerrado: AtomicBool::new(false),
+            proximo_canal: AtomicUsize::new(0),
+            probabilidad_perdida,
+        });
+        let lector = compartido.clone();
+        let lector = thread::Builder::new()
+            .name("demultiplexor".to_string())
+            .spawn(move || lector.leer())?;
+        Ok(Demultiplexor {
+            compartido,
+            lector: Some(lector),
+        })
+    }
+
+    /// Direccion del socket de la sucursal, desde la que se envian todos los mensajes
+    pub fn direccion_local(&self) -> io::Result<SocketAddr> {
+        self.compartido.socket.local_addr()
+    }
+
+    /// Abre un canal para un preparador de la cafetera, por el que recibe las respuestas a sus pedidos
+    /// y consultas
+    pub fn canal(&self, id_cafetera: IdCafetera) -> Canal {
+        let id = self.compartido.proximo_canal.fetch_add(1, Ordering::SeqCst);
+        let (emisor, receptor) = channel();
+        self.compartido.rutas().canales.insert(id, emisor);
+        Canal {
+            id,
+            id_cafetera,
+            receptor,
+            compartido: self.compartido.clone(),
+        }
+    }
+}
+
+/// Al cerrarse se detiene el thread lector. Los canales que sigan abiertos ya no reciben mensajes
+impl Drop for Demultiplexor {
+    fn drop(&mut self) {
+        self.compartido.cerrado.store(true, Ordering::SeqCst);
+        if let Some(lector) = self.lector.take() {
+            let _ = lector.join();
+        }
+    }
+}
+
+/// Extremo del socket de la sucursal de un preparador de una cafetera. Al cerrarse (drop) las
+/// respuestas de sus pedidos que lleguen despues se descartan
+pub struct Canal {
+    id: usize,
+    id_cafetera: IdCafetera,
+    receptor: Receiver<Vec<u8>>,
+    compartido: Arc<Compartido>,
+}
+
+impl Canal {
+    /// Envia el mensaje al nodo. Si es un pedido (o la respuesta de la cafetera a uno), las respuestas
+    /// con su id de pedido llegan a este canal hasta la final
+    pub fn enviar<A: ToSocketAddrs>(&self, mensaje: &[u8], destino: A) -> io::Result<usize> {
+        match id_pedido_from_bytes(mensaje) {
+            0 => {}
+            id_pedido => {
+                let okey_final = !matches!(
+                    MensajeCafetera::from_bytes(mensaje[0]),
+                    MensajeCafetera::SUMAR | MensajeCafetera::RESTAR
+                );
+                let ruta = RutaPedido {
+                    id_canal: self.id,
+                    okey_final,
+                };
+                self.compartido.rutas().pedidos.insert(id_pedido, ruta);
+            }
+        }
+        self.compartido.enviar(mensaje, destino)
+    }
+
+    /// Envia un mensaje de control al nodo: la proxima respuesta del mismo tipo para la cafetera que
+    /// no este esperando otro canal llega a este canal
+    pub fn consultar<A: ToSocketAddrs>(&self, mensaje: &[u8], destino: A) -> io::Result<usize> {
+        let tipo = match mensaje.first() {
+            Some(tipo) => *tipo,
+            None => return Ok(0),
+        };
+        self.compartido
+            .rutas()
+            .consultas
+            .entry((self.id_cafetera, tipo))
+            .or_default()
+            .push_back(self.id);
+        let enviado = self.compartido.enviar(mensaje, destino);
+        if enviado.is_err() {
+            self.cancelar_consulta(tipo);
+        }
+        enviado
+    }
+
+    /// Deja de esperar la respuesta a una consulta del tipo indicado, por ejemplo porque se vencio su
+    /// espera, asi una respuesta atrasada no le quita la suya a otro preparador de la cafetera
+    pub fn cancelar_consulta(&self, tipo: u8) {
+        if let Some(canales) = self
+            .compartido
+            .rutas()
+            .consultas
+            .get_mut(&(self.id_cafetera, tipo))
+        {
+            if let Some(posicion) = canales.iter().position(|canal| *canal == self.id) {
+                canales.remove(posicion);
+            }
+        }
+    }
+
+    /// Espera el proximo mensaje del canal. Si no llega ninguno en `espera` retorna un error
+    /// WouldBlock, igual que el timeout de lectura de un socket
+    pub fn recibir(&self, espera: Duration) -> io::Result<Vec<u8>> {
+        self.receptor.recv_timeout(espera).map_err(|err| match err {
+            RecvTimeoutError::Timeout => io::ErrorKind::WouldBlock.into(),
+            RecvTimeoutError::Disconnected => io::ErrorKind::NotConnected.into(),
+        })
+    }
+}
+
+impl Drop for Canal {
+    fn drop(&mut self) {
+        self.compartido.rutas().cerrar(self.id);
+    }
+}
+
+#[cfg(test)]
+mod demux_test {
+    use compartido::codificable::Codificable;
+    use compartido::ids::IdCuenta;
+    use compartido::mensajes_cafetera::{
+        Confirmado, MensajeCafeteraBytes, OkeyToCafetera, Sondeo, Sumar,
+    };
+
+    use super::*;
+
+    const ESPERA: Duration = Duration::from_secs(2);
+
+    /// Nodo de prueba: un socket que responde a la sucursal
+    fn nodo() -> (UdpSocket, SocketAddr) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(ESPERA)).unwrap();
+        let direccion = socket.local_addr().unwrap();
+        (socket, direccion)
+    }
+
+    fn recibir_en_nodo(nodo: &UdpSocket) -> (Vec<u8>, SocketAddr) {
+        let mut buffer = [0u8; MAX_BYTES_MENSAJE];
+        let (cantidad_leida, origen) = nodo.recv_from(&mut buffer).unwrap();
+        (buffer[..cantidad_leida].to_vec(), origen)
+    }
+
+    fn sumar(id_cafetera: IdCafetera, id_pedido: u32) -> Vec<u8> {
+        let mut sumar = Sumar::new(id_cafetera, IdCuenta(7), 10);
+        sumar.id_pedido = id_pedido;
+        sumar.to_bytes()
+    }
+
+    #[test]
+    fn las_respuestas_llegan_al_canal_que_envio_el_pedido() {
+        let demux = Demultiplexor::new("127.0.0.1:0").unwrap();
+        let (nodo, direccion_nodo) = nodo();
+        // dos cafeteras con dos digitos de id, que comparten el socket de la sucursal
+        let cafetera_10 = demux.canal(IdCafetera(10));
+        let cafetera_11 = demux.canal(IdCafetera(11));
+
+        cafetera_10
+            .enviar(&sumar(IdCafetera(10), 1000001), direccion_nodo)
+            .unwrap();
+        cafetera_11
+            .enviar(&sumar(IdCafetera(11), 1000002), direccion_nodo)
+            .unwrap();
+        let (_, origen) = recibir_en_nodo(&nodo);
+        assert_eq!(demux.direccion_local().unwrap().port(), origen.port());
+        assert_eq!(origen, recibir_en_nodo(&nodo).1);
+
+        // el nodo responde en otro orden y sin id de cafetera, solo con el id de pedido
+        let okey = OkeyToCafetera::de_cafetera(IdCafetera(0)).con_id_pedido(1000002);
+        nodo.send_to(&okey.to_bytes(), origen).unwrap();
+        let mut confirmado = Confirmado::new(IdCafetera(0), IdCuenta(7), 10);
+        confirmado.id_pedido = 1000001;
+        nodo.send_to(&confirmado.to_bytes(), origen).unwrap();
+
+        let recibido = cafetera_11.recibir(ESPERA).unwrap();
+        assert_eq!(1000002, id_pedido_from_bytes(&recibido));
+        let recibido = cafetera_10.recibir(ESPERA).unwrap();
+        assert_eq!(1000001, id_pedido_from_bytes(&recibido));
+        assert_eq!(
+            io::ErrorKind::WouldBlock,
+            cafetera_10
+                .recibir(Duration::from_millis(50))
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn la_ruta_del_pedido_se_quita_con_la_respuesta_final() {
+        let demux = Demultiplexor::new("127.0.0.1:0").unwrap();
+        let (nodo, direccion_nodo) = nodo();
+        let cafetera = demux.canal(IdCafetera(2));
+        let okey = OkeyToCafetera::new(IdCafetera(2), IdCuenta(7), 10).con_id_pedido(1000001);
+        let mut confirmado = Confirmado::new(IdCafetera(0), IdCuenta(7), 10);
+        confirmado.id_pedido = 1000001;
+
+        // el OKEY del SUMAR solo habilita a preparar el cafe, la ruta sigue
+        cafetera
+            .enviar(&sumar(IdCafetera(2), 1000001), direccion_nodo)
+            .unwrap();
+        let (_, origen) = recibir_en_nodo(&nodo);
+        nodo.send_to(&okey.to_bytes(), origen).unwrap();
+        cafetera.recibir(ESPERA).unwrap();
+        assert!(demux.compartido.rutas().pedidos.contains_key(&1000001));
+
+        cafetera.enviar(&okey.to_bytes(), direccion_nodo).unwrap();
+        recibir_en_nodo(&nodo);
+        nodo.send_to(&confirmado.to_bytes(), origen).unwrap();
+        cafetera.recibir(ESPERA).unwrap();
+        assert!(demux.compartido.rutas().pedidos.is_empty());
+
+        // un CONFIRMADO repetido ya no tiene a donde ir
+        nodo.send_to(&confirmado.to_bytes(), origen).unwrap();
+        assert!(cafetera.recibir(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn las_respuestas_de_control_llegan_en_el_orden_de_las_consultas() {
+        let demux = Demultiplexor::new("127.0.0.1:0").unwrap();
+        let (nodo, direccion_nodo) = nodo();
+        let primero = demux.canal(IdCafetera(3));
+        let segundo = demux.canal(IdCafetera(3));
+        let vencido = demux.canal(IdCafetera(3));
+        let sondeo = Sondeo::de_cafetera(IdCafetera(3)).to_bytes();
+
+        vencido.consultar(&sondeo, direccion_nodo).unwrap();
+        vencido.cancelar_consulta(sondeo[0]);
+        primero.consultar(&sondeo, direccion_nodo).unwrap();
+        segundo.consultar(&sondeo, direccion_nodo).unwrap();
+        for _ in 0..3 {
+            let (mensaje, origen) = recibir_en_nodo(&nodo);
+            nodo.send_to(&mensaje, origen).unwrap();
+        }
+
+        assert_eq!(sondeo, primero.recibir(ESPERA).unwrap());
+        assert_eq!(sondeo, segundo.recibir(ESPERA).unwrap());
+        // la tercera respuesta no la espera nadie y se descarta
+        assert!(vencido.recibir(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn con_perdida_total_no_sale_ni_llega_ningun_mensaje() {
+        let demux = Demultiplexor::con_perdida("127.0.0.1:0", 1.0).unwrap();
+        let (nodo, direccion_nodo) = nodo();
+        nodo.set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        let cafetera = demux.canal(IdCafetera(4));
+
+        // el envio se informa como hecho aunque el datagrama no sale
+        let pedido = sumar(IdCafetera(4), 1000001);
+        assert_eq!(pedido.len(), cafetera.enviar(&pedido, direccion_nodo).unwrap());
+        let mut buffer = [0u8; MAX_BYTES_MENSAJE];
+        assert!(nodo.recv_from(&mut buffer).is_err());
+
+        // la respuesta llega al socket de la sucursal pero se descarta
+        let okey = OkeyToCafetera::de_cafetera(IdCafetera(0)).con_id_pedido(1000001);
+        nodo.send_to(&okey.to_bytes(), demux.direccion_local().unwrap())
+            .unwrap();
+        assert_eq!(
+            io::ErrorKind::WouldBlock,
+            cafetera
+                .recibir(Duration::from_millis(300))
+                .unwrap_err()
+                .kind()
+        );
+    }
+}
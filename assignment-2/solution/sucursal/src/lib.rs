@@ -1,2 +1,7 @@
+pub mod circuito;
+pub mod cuentas_cafeteras;
+pub mod demux;
 pub mod error_sucursal;
-pub mod utils;
+pub mod reparto;
+pub mod resumen;
+pub mod tablero;
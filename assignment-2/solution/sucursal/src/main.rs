@@ -1,74 +1,257 @@
 extern crate serde;
 extern crate serde_json;
+use compartido::codificable::Codificable;
+use compartido::config::{config, separar_direcciones_nodos};
+use compartido::ids::{IdCafetera, IdCuenta};
+use compartido::logs::{init_tracing, init_tracing_sin_consola};
 use compartido::mensajes_cafetera::{
-    Error, MensajeCafetera, MensajeCafeteraBytes, OkeyToCafetera, Ping, Restar, Sumar,
+    id_pedido_from_bytes, Confirmado, Desregistrar, Error, Fallido, MensajeCafetera,
+    MensajeCafeteraBytes, OkeyToCafetera, Ping, Registrar, Restar, Sondeo, Sumar, Transferir,
 };
 use rand::Rng;
 use serde::Deserialize;
-use sucursal::utils::{CANTIDAD_CAFETERAS, TIMEOUT, TIEMPO_DE_PREPARACION, PROBABILIDAD_ERROR};
+use std::collections::{BTreeSet, HashMap};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::net::UdpSocket;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 use std::{env, thread};
-use sucursal::error_sucursal::{ErrorSucursal, TipoError};
+use sucursal::circuito::Circuito;
+use sucursal::cuentas_cafeteras::CuentasCafeteras;
+use sucursal::demux::{Canal, Demultiplexor};
+use sucursal::error_sucursal::ErrorSucursal;
+use sucursal::reparto::Reparto;
+use sucursal::resumen::ResumenCafetera;
+use sucursal::tablero::{EstadoPedido, ResultadoPedido, Tablero};
+use tiempo::{Reloj, RelojReal};
+use tracing::{error, info, warn};
 
+/// Pedido leido del archivo junto con su id de pedido, que se envia a los nodos para
+/// poder seguir el pedido en los logs de la sucursal, del nodo y del coordinador. El nodo lo
+/// devuelve en sus respuestas, asi la cafetera no toma la respuesta de un pedido por la de otro
+type PedidoEnCola = (u32, String);
 
 #[derive(Deserialize)]
 /// Estructura que define un unico pedido para una cafetera
 struct Pedido {
     /// id correspondiente a la tarjeta del usuario
-    id_cuenta: u32,
+    id_cuenta: IdCuenta,
     /// tipo de pedido a realizar (suma o resta de puntos)
     tipo: String,
     /// cantidad de puntos a ser sumados o restados
     cantidad: u32,
+    /// cuenta a la que se suman los puntos, solo para los pedidos de tipo TRANSFERENCIA
+    id_cuenta_destino: Option<IdCuenta>,
+    /// id de pedido asignado por la sucursal, no se lee del archivo
+    #[serde(skip)]
+    id_pedido: u32,
+}
+
+/// Maximo que se bloquea cada lectura del canal antes de volver a consultar el reloj
+const INTERVALO_LECTURA: Duration = Duration::from_millis(100);
+
+/// Direcciones de los nodos indicadas con --nodo al arrancar la sucursal, por id de nodo
+static DIRECCIONES_NODOS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Tablero con el progreso de cada cafetera, solo si la sucursal se inicio con --tui
+static TABLERO: OnceLock<Tablero> = OnceLock::new();
+
+/// Cada cuanto se redibuja el tablero del modo --tui
+const INTERVALO_TABLERO: Duration = Duration::from_millis(250);
+
+/// Registra en el tablero la etapa en la que esta el pedido, si la sucursal se inicio con --tui
+fn mostrar_estado(id_cafetera: IdCafetera, id_pedido: u32, estado: EstadoPedido) {
+    if let Some(tablero) = TABLERO.get() {
+        tablero.actualizar(id_cafetera, id_pedido, estado);
+    }
+}
+
+/// Registra en el tablero como termino el pedido, si la sucursal se inicio con --tui
+fn mostrar_resultado(id_cafetera: IdCafetera, id_pedido: u32, resultado: ResultadoPedido) {
+    if let Some(tablero) = TABLERO.get() {
+        tablero.terminar(id_cafetera, id_pedido, resultado);
+    }
+}
+
+/// Direccion udp en la que el nodo recibe los mensajes de las cafeteras: la indicada con --nodo o la
+/// que se arma con la configuracion del cluster. Los ids de nodo se validan al arrancar la sucursal
+fn direccion_nodo(id_nodo: &str) -> String {
+    match DIRECCIONES_NODOS.get().and_then(|x| x.get(id_nodo)) {
+        Some(direccion) => direccion.clone(),
+        None => config()
+            .cluster
+            .addr_lectura_datos(id_nodo.parse().unwrap_or_default()),
+    }
+}
+
+/// Lee el siguiente mensaje del nodo. Los estados finales de sumas anteriores (Confirmado/Fallido)
+/// pueden llegar en cualquier momento, por lo que se registran en el resumen y se sigue esperando.
+/// Un OKEY o ERROR de un pedido distinto de id_pedido es una respuesta atrasada de un pedido anterior
+/// (por ejemplo uno que se dio por vencido) y tambien se descarta. Con id_pedido 0, o si la respuesta
+/// no trae id de pedido, se acepta cualquier respuesta. Cada lectura del canal espera como maximo
+/// `espera`
+fn recibir_mensaje(
+    canal: &Canal,
+    id_pedido: u32,
+    resumen: &mut ResumenCafetera,
+    espera: Duration,
+) -> std::io::Result<MensajeCafetera> {
+    loop {
+        let mensaje = canal.recibir(espera)?;
+        let mensaje = mensaje.as_slice();
+        match MensajeCafetera::from_bytes(mensaje[0]) {
+            MensajeCafetera::CONFIRMADO => {
+                let confirmado = match Confirmado::from_bytes(mensaje) {
+                    Ok(confirmado) => confirmado,
+                    Err(err) => {
+                        warn!("Se descarta un CONFIRMADO invalido: {}", err);
+                        continue;
+                    }
+                };
+                info!(
+                    "Soy cafetera {}, la suma de {} a la cuenta {} fue confirmada | ID_PEDIDO = {}",
+                    resumen.id_cafetera,
+                    confirmado.cantidad_modificar,
+                    confirmado.id_cuenta,
+                    confirmado.id_pedido
+                );
+                resumen.registrar_estado_final(true);
+            }
+            MensajeCafetera::FALLIDO => {
+                let fallido = match Fallido::from_bytes(mensaje) {
+                    Ok(fallido) => fallido,
+                    Err(err) => {
+                        warn!("Se descarta un FALLIDO invalido: {}", err);
+                        continue;
+                    }
+                };
+                info!(
+                    "Soy cafetera {}, la suma de {} a la cuenta {} fallo | ID_PEDIDO = {}",
+                    resumen.id_cafetera,
+                    fallido.cantidad_modificar,
+                    fallido.id_cuenta,
+                    fallido.id_pedido
+                );
+                resumen.registrar_estado_final(false);
+            }
+            tipo_mensaje => match id_pedido_from_bytes(mensaje) {
+                id_respuesta
+                    if id_pedido != 0 && id_respuesta != 0 && id_respuesta != id_pedido =>
+                {
+                    warn!(
+                        "Soy cafetera {}, se descarta un {:?} atrasado del pedido {} mientras espero el pedido {}",
+                        resumen.id_cafetera, tipo_mensaje, id_respuesta, id_pedido
+                    );
+                }
+                _ => return Ok(tipo_mensaje),
+            },
+        }
+    }
+}
+
+/// Lee el siguiente mensaje del nodo esperando como maximo `espera` segun el reloj de la cafetera. El
+/// canal se lee en intervalos de a lo sumo INTERVALO_LECTURA, asi con un reloj virtual la espera
+/// vence en cuanto se avanza el tiempo. Al vencer retorna un error WouldBlock, igual que el timeout
+/// de lectura de un socket
+fn recibir_mensaje_con_timeout(
+    canal: &Canal,
+    id_pedido: u32,
+    resumen: &mut ResumenCafetera,
+    reloj: &dyn Reloj,
+    espera: Duration,
+) -> std::io::Result<MensajeCafetera> {
+    let vencimiento = reloj.ahora() + espera;
+    loop {
+        let restante = vencimiento.saturating_duration_since(reloj.ahora());
+        if restante.is_zero() {
+            return Err(std::io::ErrorKind::WouldBlock.into());
+        }
+        match recibir_mensaje(canal, id_pedido, resumen, restante.min(INTERVALO_LECTURA)) {
+            Err(ref err)
+                if err.kind() == std::io::ErrorKind::WouldBlock
+                    || err.kind() == std::io::ErrorKind::TimedOut => {}
+            resultado => return resultado,
+        }
+    }
+}
+
+/// Espera por la respuesta del nodo: sucursal.timeout multiplicado segun sus fallas consecutivas, con el
+/// tope de sucursal.multiplicador_timeout_maximo
+fn espera_respuesta(circuito: &Circuito, id_nodo: &str) -> Duration {
+    Duration::from_secs(config().sucursal.timeout * circuito.multiplicador_timeout(id_nodo))
+}
+
+/// Se vencio la espera por una respuesta del nodo: se la registra como falla en el circuito. Si el
+/// circuito sigue cerrado se le envia un ping al nodo y se retorna true para volver a esperar; si se
+/// abrio se retorna false y se deja de esperar
+fn reintentar_tras_timeout(
+    canal: &Canal,
+    id_cafetera: IdCafetera,
+    pedido: &Pedido,
+    id_nodo: &str,
+    circuito: &mut Circuito,
+    reloj: &dyn Reloj,
+    esperando: &str,
+) -> Result<bool, ErrorSucursal> {
+    if circuito.registrar_falla(id_nodo, reloj.ahora()) {
+        warn!(
+            "Soy cafetera {}, el nodo id {:?} no responde, dejo de esperar {}",
+            id_cafetera, id_nodo, esperando
+        );
+        return Ok(false);
+    }
+    // Envio ping, por que se pasó el timeout
+    warn!(
+        "Soy cafetera {}, hubo un timeout al esperar {}, envió ping",
+        id_cafetera, esperando
+    );
+    let ping = Ping::new(id_cafetera, pedido.id_cuenta, 0).to_bytes();
+    canal.enviar(&ping, direccion_nodo(id_nodo))?;
+    mostrar_estado(id_cafetera, pedido.id_pedido, EstadoPedido::Reintentando);
+
+    info!("Ya envié el ping, vuelvo a esperar por un Ok o Err");
+    Ok(true)
 }
 
 /// Funcion que se invoca una vez finalizada la preparación de un cafe espera por la confirmación de la
 /// sucursal para ser entregado, en caso de recibir error lo desecha
 fn esperar_confirmacion(
-    socket: UdpSocket,
-    id_cuenta: u32,
-    _cantidad: u32,
-    id_cafetera: u8,
+    canal: &Canal,
+    pedido: &Pedido,
+    id_cafetera: IdCafetera,
     id_nodo: String,
-    multiplicador_timeout: u64,
+    circuito: &mut Circuito,
+    resumen: &mut ResumenCafetera,
+    reloj: &dyn Reloj,
 ) -> Result<bool, ErrorSucursal> {
-    socket
-        .set_read_timeout(Some(Duration::from_secs(TIMEOUT * multiplicador_timeout)))
-        .map_err(|x| ErrorSucursal::new(&x.to_string(), TipoError::ErrorGenerico))?;
-
-    let mut buffer = [0u8; 14];
-    match socket.recv_from(&mut buffer) {
-        Ok((_, _addr)) => {
-            let aux = buffer.to_vec();
-            let tipo_mensaje = MensajeCafetera::from_bytes(aux[0]);
+    let espera = espera_respuesta(circuito, &id_nodo);
 
+    match recibir_mensaje_con_timeout(canal, pedido.id_pedido, resumen, reloj, espera) {
+        Ok(tipo_mensaje) => {
+            circuito.registrar_exito(&id_nodo);
             if let MensajeCafetera::OKEY = tipo_mensaje {
-                println!("El cafe fue entregado correctamente");
+                info!("El cafe fue entregado correctamente");
+                mostrar_resultado(id_cafetera, pedido.id_pedido, ResultadoPedido::Completado);
             } else {
-                println!("El cafe fue desechado correctamente");
+                warn!("El cafe fue desechado correctamente");
+                mostrar_resultado(id_cafetera, pedido.id_pedido, ResultadoPedido::Fallido);
             }
         }
         Err(ref err) if err.kind() == std::io::ErrorKind::WouldBlock => {
-            // Envio ping, por que se pasó el timeout
-            println!(
-                "Soy cafetera {}, hubo un timeout al esperar confirmacion, envió ping",
-                id_cafetera
+            return reintentar_tras_timeout(
+                canal,
+                id_cafetera,
+                pedido,
+                &id_nodo,
+                circuito,
+                reloj,
+                "la confirmacion",
             );
-
-            let ping = Ping::new(id_cafetera, id_cuenta, 0).to_bytes();
-            socket
-                .send_to(&ping, "127.0.0.1:1235".to_owned() + &id_nodo)
-                .map_err(|x| ErrorSucursal::new(&x.to_string(), TipoError::ErrorGenerico))?;
-
-            println!("Ya envié el ping, vuelvo a esperar por un Ok o Err de confirmacion");
-            return Ok(true);
         }
         Err(err) => {
             // Error al leer del socket, paso al siguiente pedido
-            println!("Error reading from socket: {}", err);
+            error!("Error reading from socket: {}", err);
+            mostrar_resultado(id_cafetera, pedido.id_pedido, ResultadoPedido::Fallido);
         }
     }
     Ok(false)
@@ -76,150 +259,567 @@ fn esperar_confirmacion(
 
 /// Funcion que se invoca por una cafetera luego de que se envie el pedido actual se esperan las correspondientes
 /// respuestas o se puede producir un timeout que invoca un ping. La preparación del cafe puede fallar con una
-/// probabilidad dada por PROBABILIDAD_ERROR y el tiempo de preparacion del mismo es de TIEMPO_DE_PREPARACION
+/// probabilidad dada por probabilidad_error y el tiempo de preparacion del mismo es de tiempo_de_preparacion
 fn escuchar_respuesta(
-    socket: UdpSocket,
-    id_cuenta: u32,
-    cantidad: u32,
-    id_cafetera: u8,
+    canal: &Canal,
+    pedido: &Pedido,
+    id_cafetera: IdCafetera,
     id_nodo: String,
-    multiplicador_timeout: u64,
-    tipo: String,
+    circuito: &mut Circuito,
+    resumen: &mut ResumenCafetera,
+    reloj: &dyn Reloj,
 ) -> Result<bool, ErrorSucursal> {
-    socket
-        .set_read_timeout(Some(Duration::from_secs(TIMEOUT * multiplicador_timeout)))
-        .map_err(|x| ErrorSucursal::new(&x.to_string(), TipoError::ErrorGenerico))?;
-
-    let mut buffer = [0u8; 14];
+    let id_cuenta = pedido.id_cuenta;
+    let cantidad = pedido.cantidad;
+    let tipo = pedido.tipo.as_str();
+    let espera = espera_respuesta(circuito, &id_nodo);
 
-    match socket.recv_from(&mut buffer) {
-        Ok((_, _addr)) => {
+    match recibir_mensaje_con_timeout(canal, pedido.id_pedido, resumen, reloj, espera) {
+        Ok(tipo_mensaje) => {
+            circuito.registrar_exito(&id_nodo);
             // Se recibió una respuesta, debería ser un OK
-            let aux = buffer.to_vec();
-            let tipo_mensaje = MensajeCafetera::from_bytes(aux[0]);
-            println!(
-                "Recibí un {:?} de la cafetera.",
-                tipo_mensaje
-            );
+            info!("Recibí un {:?} de la cafetera.", tipo_mensaje);
             if let MensajeCafetera::OKEY = tipo_mensaje {
                 //Recibo un ok, tengo que preparar el cafe y puede fallar...
                 let numero_random: f64 = rand::thread_rng().gen();
+                mostrar_estado(id_cafetera, pedido.id_pedido, EstadoPedido::Preparando);
 
                 //Simulo que preparo el cafe
-                thread::sleep(Duration::from_secs(TIEMPO_DE_PREPARACION));
+                reloj.dormir(Duration::from_secs(config().sucursal.tiempo_de_preparacion));
 
                 let mut _paquete: Vec<u8> = vec![];
                 //verificar si hubo un error
-                if numero_random < PROBABILIDAD_ERROR {
-                    println!("Error producido en la cafetera {}", id_cafetera);
-                    let mensaje_error = Error::new(id_cafetera, id_cuenta, 0);
+                if numero_random < config().sucursal.probabilidad_error {
+                    warn!("Error producido en la cafetera {}", id_cafetera);
+                    let mensaje_error =
+                        Error::new(id_cafetera, id_cuenta, 0).con_id_pedido(pedido.id_pedido);
                     _paquete = mensaje_error.to_bytes();
-                    socket
-                        .send_to(&_paquete, "127.0.0.1:1235".to_owned() + &id_nodo)
-                        .map_err(|x| {
-                            ErrorSucursal::new(&x.to_string(), TipoError::ErrorGenerico)
-                        })?;
+                    canal.enviar(&_paquete, direccion_nodo(&id_nodo))?;
+                    mostrar_resultado(id_cafetera, pedido.id_pedido, ResultadoPedido::Fallido);
                 } else {
-                    println!(
+                    info!(
                         "El café se termino de preparar en la cafetera {}",
                         id_cafetera
                     );
-                    let mensaje_ok = OkeyToCafetera::new(id_cafetera, id_cuenta, cantidad);
+                    let mensaje_ok = OkeyToCafetera::new(id_cafetera, id_cuenta, cantidad)
+                        .con_id_pedido(pedido.id_pedido);
                     _paquete = mensaje_ok.to_bytes();
-                    socket
-                        .send_to(&_paquete, "127.0.0.1:1235".to_owned() + &id_nodo)
-                        .map_err(|x| {
-                            ErrorSucursal::new(&x.to_string(), TipoError::ErrorGenerico)
-                        })?;
+                    canal.enviar(&_paquete, direccion_nodo(&id_nodo))?;
+                    if tipo == "SUMA" {
+                        // El estado final de la suma llega luego, de forma asincronica
+                        resumen.sumas_pendientes += 1;
+                        mostrar_resultado(
+                            id_cafetera,
+                            pedido.id_pedido,
+                            ResultadoPedido::Completado,
+                        );
+                    }
                     if tipo == "RESTA" {
                         // Esperamos un Ok, si recibimos Err el cafe no es entregado
                         let mut repetir_confirmacion: bool = true;
-                        let mut multiplicador_timeout_confirmacion: u64 = 1;
+                        mostrar_estado(
+                            id_cafetera,
+                            pedido.id_pedido,
+                            EstadoPedido::EsperandoConfirmacion,
+                        );
 
                         while repetir_confirmacion {
                             repetir_confirmacion = esperar_confirmacion(
-                                socket.try_clone().expect("Error al clonar el socket"),
-                                id_cuenta,
-                                cantidad,
+                                canal,
+                                pedido,
                                 id_cafetera,
                                 id_nodo.clone(),
-                                multiplicador_timeout_confirmacion,
+                                circuito,
+                                resumen,
+                                reloj,
                             )?;
-                            multiplicador_timeout_confirmacion += 1;
                         }
                     }
                 }
             } else {
-                println!("No se pudo ejecutar el pedido");
+                warn!("No se pudo ejecutar el pedido");
+                mostrar_resultado(id_cafetera, pedido.id_pedido, ResultadoPedido::Fallido);
             }
         }
         Err(ref err) if err.kind() == std::io::ErrorKind::WouldBlock => {
-            // Envio ping, por que se pasó el timeout
-            println!("Soy cafetera {}, hubo un timeout, envió ping", id_cafetera);
+            return reintentar_tras_timeout(
+                canal,
+                id_cafetera,
+                pedido,
+                &id_nodo,
+                circuito,
+                reloj,
+                "la respuesta",
+            );
+        }
+        Err(err) => {
+            // Error al leer del socket, paso al siguiente pedido
+            error!("Error reading from socket: {}", err);
+            mostrar_resultado(id_cafetera, pedido.id_pedido, ResultadoPedido::Fallido);
+        }
+    }
+    Ok(false)
+}
 
-            let ping = Ping::new(id_cafetera, id_cuenta, 0).to_bytes();
-            socket
-                .send_to(&ping, "127.0.0.1:1235".to_owned() + &id_nodo)
-                .map_err(|x| ErrorSucursal::new(&x.to_string(), TipoError::ErrorGenerico))?;
+/// Funcion que se invoca luego de enviar una transferencia, que no requiere preparar un cafe. Se espera el Ok
+/// (la transferencia se commiteo en todos los nodos) o el Error (no habia saldo suficiente o se aborto)
+fn esperar_transferencia(
+    canal: &Canal,
+    pedido: &Pedido,
+    id_cafetera: IdCafetera,
+    id_nodo: &str,
+    circuito: &mut Circuito,
+    resumen: &mut ResumenCafetera,
+    reloj: &dyn Reloj,
+) -> Result<bool, ErrorSucursal> {
+    let espera = espera_respuesta(circuito, id_nodo);
 
-            println!("Ya envié el ping, vuelvo a esperar por un Ok o Err");
-            return Ok(true);
+    let respuesta = recibir_mensaje_con_timeout(canal, pedido.id_pedido, resumen, reloj, espera);
+    if respuesta.is_ok() {
+        circuito.registrar_exito(id_nodo);
+    }
+    match respuesta {
+        Ok(MensajeCafetera::OKEY) => {
+            info!(
+                "Soy cafetera {}, se transfirieron {} creditos de la cuenta {} a la cuenta {:?}",
+                id_cafetera, pedido.cantidad, pedido.id_cuenta, pedido.id_cuenta_destino
+            );
+            mostrar_resultado(id_cafetera, pedido.id_pedido, ResultadoPedido::Completado);
+        }
+        Ok(_) => {
+            warn!(
+                "Soy cafetera {}, la transferencia de la cuenta {} fue rechazada",
+                id_cafetera, pedido.id_cuenta
+            );
+            mostrar_resultado(id_cafetera, pedido.id_pedido, ResultadoPedido::Fallido);
+        }
+        Err(ref err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+            return reintentar_tras_timeout(
+                canal,
+                id_cafetera,
+                pedido,
+                id_nodo,
+                circuito,
+                reloj,
+                "la transferencia",
+            );
         }
         Err(err) => {
-            // Error al leer del socket, paso al siguiente pedido
-            println!("Error reading from socket: {}", err);
+            error!("Error reading from socket: {}", err);
+            mostrar_resultado(id_cafetera, pedido.id_pedido, ResultadoPedido::Fallido);
         }
     }
     Ok(false)
 }
 
-/// Funcion que se encarga de procesar los pedidos de cada cafetera
-/// invoca un socket por udp con direccion unica, que se genera con el id de nodo y cafetera
-fn process_sublist(
-    pedidos: Vec<String>,
-    id_nodo: String,
-    id_cafetera: u8,
-) -> Result<(), ErrorSucursal> {
-    let socket = UdpSocket::bind("127.0.0.1:57".to_owned() + &id_nodo + &id_cafetera.to_string())
-    .map_err(|x| ErrorSucursal::new(&x.to_string(), TipoError::ErrorConexion))?;
-    
-    println!("Soy la cafetera {} y voy a procesar {} pedidos", id_cafetera, pedidos.len());
-    
-    // Cada pedido de la cafetera es procesado
-    for p in pedidos {
-        let pedido: Pedido = serde_json::from_str(&p)
-            .map_err(|x| ErrorSucursal::new(&x.to_string(), TipoError::ErrorGenerico))?;
+/// Funcion que envia al nodo un mensaje de control (sondeo, registro o baja) y espera durante
+/// timeout_sondeo_millis (segun el reloj de la cafetera) a que el nodo responda con un mensaje del mismo tipo.
+/// Retorna false si el nodo no respondio a tiempo o no esta levantado. La respuesta no lleva id de pedido,
+/// por lo que el demultiplexor se la entrega a los preparadores de la cafetera en el orden en que consultaron
+fn consultar_nodo(
+    canal: &Canal,
+    paquete: Vec<u8>,
+    id_nodo: &str,
+    resumen: &mut ResumenCafetera,
+    reloj: &dyn Reloj,
+) -> Result<bool, ErrorSucursal> {
+    let vencimiento =
+        reloj.ahora() + Duration::from_millis(config().sucursal.timeout_sondeo_millis);
+
+    if canal.consultar(&paquete, direccion_nodo(id_nodo)).is_err() {
+        return Ok(false);
+    }
+
+    let tipo_esperado = paquete[0];
+    loop {
+        let restante = vencimiento.saturating_duration_since(reloj.ahora());
+        match recibir_mensaje_con_timeout(canal, 0, resumen, reloj, restante) {
+            // Se descartan respuestas atrasadas de pedidos anteriores hasta recibir la esperada
+            Ok(tipo_mensaje) if tipo_mensaje as u8 == tipo_esperado => return Ok(true),
+            Ok(_) => {}
+            Err(_) => {
+                canal.cancelar_consulta(tipo_esperado);
+                return Ok(false);
+            }
+        }
+    }
+}
+
+/// Funcion que envia un sondeo de vida al nodo antes de comenzar un pedido y espera su respuesta
+fn sondear_nodo(
+    canal: &Canal,
+    id_cafetera: IdCafetera,
+    id_nodo: &str,
+    resumen: &mut ResumenCafetera,
+    reloj: &dyn Reloj,
+) -> Result<bool, ErrorSucursal> {
+    let sondeo = Sondeo::de_cafetera(id_cafetera).to_bytes();
+    consultar_nodo(canal, sondeo, id_nodo, resumen, reloj)
+}
+
+/// Funcion que busca un nodo que responda al sondeo, empezando por el nodo principal y siguiendo
+/// con los nodos de respaldo en el orden en que fueron configurados. Los nodos con el circuito abierto
+/// se saltean sin sondearlos hasta que toca probarlos, asi la cafetera vuelve a su nodo principal
+/// cuando se recupera. Retorna su posicion en la lista
+fn elegir_nodo(
+    canal: &Canal,
+    id_cafetera: IdCafetera,
+    nodos: &[String],
+    circuito: &mut Circuito,
+    resumen: &mut ResumenCafetera,
+    reloj: &dyn Reloj,
+) -> Result<Option<usize>, ErrorSucursal> {
+    for (candidato, id_nodo) in nodos.iter().enumerate() {
+        if !circuito.permite(id_nodo, reloj.ahora()) {
+            continue;
+        }
+        if sondear_nodo(canal, id_cafetera, id_nodo, resumen, reloj)? {
+            circuito.registrar_exito(id_nodo);
+            return Ok(Some(candidato));
+        }
+        circuito.registrar_falla(id_nodo, reloj.ahora());
+    }
+    Ok(None)
+}
+
+/// Funcion que ejecuta uno de los preparadores de una cafetera: toma pedidos de la cola compartida
+/// de la cafetera hasta que no se le asignen mas. Cada preparador abre un canal del socket de la sucursal, por el que
+/// recibe las respuestas de sus pedidos segun su id de pedido, asi el nodo trata a cada preparacion
+/// concurrente como un flujo de confirmacion independiente.
+/// Antes de cada pedido se sondea al nodo actual; si no responde, el preparador pasa a enviar su trafico
+/// al siguiente nodo de respaldo que responda. Si ningun nodo responde, el pedido actual y los que se le
+/// sigan asignando a la cafetera se registran como diferidos sin esperar los timeouts de cada uno. Los nodos en los que
+/// registra a la cafetera se agregan a `registrados`, para darla de baja cuando terminan todos sus
+/// preparadores
+fn ejecutar_preparador(
+    reparto: Arc<Reparto<PedidoEnCola>>,
+    nodos: Vec<String>,
+    id_cafetera: IdCafetera,
+    canal: Canal,
+    registrados: Arc<Mutex<BTreeSet<usize>>>,
+    reloj: Arc<dyn Reloj>,
+) -> Result<ResumenCafetera, ErrorSucursal> {
+    let mut resumen = ResumenCafetera::new(id_cafetera);
+
+    let mut nodo_actual = 0;
+    let mut nodo_registrado = None;
+    let mut circuito = Circuito::desde_config();
+    // Cada pedido de la cola de la cafetera es procesado
+    while let Some((id_pedido, p)) = reparto.siguiente(id_cafetera.0 as usize)? {
+        match elegir_nodo(
+            &canal,
+            id_cafetera,
+            &nodos,
+            &mut circuito,
+            &mut resumen,
+            reloj.as_ref(),
+        )? {
+            Some(nodo) => {
+                if nodo != nodo_actual {
+                    warn!(
+                        "Soy Cafetera {}, dejo de enviar mis pedidos al nodo id {:?}. Paso a enviarlos al nodo id {:?}",
+                        id_cafetera, nodos[nodo_actual], nodos[nodo]
+                    );
+                    resumen.cambios_de_nodo += 1;
+                    nodo_actual = nodo;
+                }
+                // La cafetera se registra en cada nodo al que le envia pedidos
+                if nodo_registrado != Some(nodo_actual) {
+                    let registrar = Registrar::de_cafetera(id_cafetera).to_bytes();
+                    if consultar_nodo(
+                        &canal,
+                        registrar,
+                        &nodos[nodo_actual],
+                        &mut resumen,
+                        reloj.as_ref(),
+                    )? {
+                        nodo_registrado = Some(nodo_actual);
+                        registrados.lock()?.insert(nodo_actual);
+                    } else {
+                        warn!(
+                            "Soy Cafetera {}, no pude registrarme en el nodo id {:?}",
+                            id_cafetera, nodos[nodo_actual]
+                        );
+                    }
+                }
+            }
+            None => {
+                info!(
+                    "Soy Cafetera {}, ningun nodo de {:?} responde o todos tienen el circuito abierto. Difiero los pedidos restantes",
+                    id_cafetera, nodos
+                );
+                mostrar_resultado(id_cafetera, id_pedido, ResultadoPedido::Diferido);
+                resumen.pedidos_diferidos.push(p);
+                while let Some((id_restante, restante)) =
+                    reparto.siguiente(id_cafetera.0 as usize)?
+                {
+                    mostrar_resultado(id_cafetera, id_restante, ResultadoPedido::Diferido);
+                    resumen.pedidos_diferidos.push(restante);
+                }
+                return Ok(resumen);
+            }
+        }
+        let id_nodo = nodos[nodo_actual].clone();
+
+        let mut pedido: Pedido =
+            serde_json::from_str(&p).map_err(|x| ErrorSucursal::Parseo(x.to_string()))?;
+        pedido.id_pedido = id_pedido;
         let mut _msg: Vec<u8> = vec![];
         // a partir del tipo envio el mensaje correspondiente por udp
         if pedido.tipo == "SUMA" {
-            _msg = Sumar::new(id_cafetera, pedido.id_cuenta, pedido.cantidad).to_bytes();
+            let mut sumar = Sumar::new(id_cafetera, pedido.id_cuenta, pedido.cantidad);
+            sumar.id_pedido = id_pedido;
+            _msg = sumar.to_bytes();
         } else if pedido.tipo == "RESTA" {
-            _msg = Restar::new(id_cafetera, pedido.id_cuenta, pedido.cantidad).to_bytes();
+            let mut restar = Restar::new(id_cafetera, pedido.id_cuenta, pedido.cantidad);
+            restar.id_pedido = id_pedido;
+            _msg = restar.to_bytes();
+        } else if let ("TRANSFERENCIA", Some(id_cuenta_destino)) =
+            (pedido.tipo.as_str(), pedido.id_cuenta_destino)
+        {
+            let mut transferir = Transferir::new(id_cafetera, pedido.id_cuenta, pedido.cantidad);
+            transferir.id_pedido = id_pedido;
+            transferir.id_cuenta_destino = id_cuenta_destino;
+            _msg = transferir.to_bytes();
         } else {
             continue;
         }
-        println!(
-            "Soy Cafetera {}, envió {:?} al nodo id {:?}",
-            id_cafetera, pedido.tipo, id_nodo
+        info!(
+            "Soy Cafetera {}, envió {:?} al nodo id {:?} | ID_PEDIDO = {}",
+            id_cafetera, pedido.tipo, id_nodo, id_pedido
         );
 
         let mut repetir_pedido: bool = true;
-        let mut multiplicador_timeout: u64 = 1;
-        socket
-            .send_to(&_msg, "127.0.0.1:1235".to_owned() + &id_nodo)
-            .map_err(|x| ErrorSucursal::new(&x.to_string(), TipoError::ErrorGenerico))?;
+        canal.enviar(&_msg, direccion_nodo(&id_nodo))?;
 
+        // la transferencia no prepara un cafe, solo espera el resultado de la transaccion
+        let es_transferencia = pedido.tipo == "TRANSFERENCIA";
+        let estado = if es_transferencia {
+            EstadoPedido::EsperandoConfirmacion
+        } else {
+            EstadoPedido::EsperandoNodo
+        };
+        mostrar_estado(id_cafetera, id_pedido, estado);
         while repetir_pedido {
-            repetir_pedido = escuchar_respuesta(
-                socket.try_clone().expect("Error al clonar el socket"),
-                pedido.id_cuenta,
-                pedido.cantidad,
+            repetir_pedido = if es_transferencia {
+                esperar_transferencia(
+                    &canal,
+                    &pedido,
+                    id_cafetera,
+                    &id_nodo,
+                    &mut circuito,
+                    &mut resumen,
+                    reloj.as_ref(),
+                )?
+            } else {
+                escuchar_respuesta(
+                    &canal,
+                    &pedido,
+                    id_cafetera,
+                    id_nodo.clone(),
+                    &mut circuito,
+                    &mut resumen,
+                    reloj.as_ref(),
+                )?
+            };
+        }
+        // el nodo dejo de responder durante el pedido: queda diferido, sin resultado conocido
+        if circuito.abierto(&id_nodo) {
+            warn!(
+                "Soy Cafetera {}, el nodo id {:?} no respondio el pedido, lo difiero | ID_PEDIDO = {}",
+                id_cafetera, id_nodo, id_pedido
+            );
+            resumen.pedidos_diferidos.push(p);
+            mostrar_resultado(id_cafetera, id_pedido, ResultadoPedido::Diferido);
+        }
+    }
+
+    // Se espera el estado final de las sumas que quedaron pendientes
+    let espera = Duration::from_secs(config().sucursal.timeout_estado_final);
+    while resumen.sumas_pendientes > 0 {
+        if recibir_mensaje_con_timeout(&canal, 0, &mut resumen, reloj.as_ref(), espera).is_err() {
+            break;
+        }
+    }
+    Ok(resumen)
+}
+
+/// Funcion que se encarga de procesar los pedidos de cada cafetera. Los pedidos que se le asignan a la
+/// cafetera en el reparto son tomados por preparaciones_concurrentes preparadores, cada uno en su propio thread y con su canal
+/// del socket de la sucursal, de forma que la cafetera puede preparar varios cafes a la vez. Cuando
+/// terminan todos, la cafetera se da de baja en los nodos en los que se registro. Retorna el resumen
+/// combinado de la cafetera
+fn procesar_cafetera(
+    reparto: Arc<Reparto<PedidoEnCola>>,
+    nodos: Vec<String>,
+    id_cafetera: IdCafetera,
+    demux: Arc<Demultiplexor>,
+    reloj: Arc<dyn Reloj>,
+) -> Result<ResumenCafetera, ErrorSucursal> {
+    info!(
+        "Soy la cafetera {} y voy a procesar los pedidos que se me asignen con {} preparadores",
+        id_cafetera,
+        config().sucursal.preparaciones_concurrentes
+    );
+    let registrados = Arc::new(Mutex::new(BTreeSet::new()));
+
+    let mut preparadores = vec![];
+    for _ in 0..config().sucursal.preparaciones_concurrentes {
+        let reparto_cpy = reparto.clone();
+        let nodos_cpy = nodos.clone();
+        let canal = demux.canal(id_cafetera);
+        let registrados_cpy = registrados.clone();
+        let reloj_cpy = reloj.clone();
+        preparadores.push(thread::spawn(move || {
+            ejecutar_preparador(
+                reparto_cpy,
+                nodos_cpy,
                 id_cafetera,
-                id_nodo.clone(),
-                multiplicador_timeout,
-                pedido.tipo.clone(),
-            )?;
-            multiplicador_timeout += 1;
+                canal,
+                registrados_cpy,
+                reloj_cpy,
+            )
+        }));
+    }
+
+    let mut resumen = ResumenCafetera::new(id_cafetera);
+    for preparador in preparadores {
+        let resumen_preparador = preparador
+            .join()
+            .map_err(|_x| ErrorSucursal::Estado("Error en join threads".to_string()))?;
+        resumen.combinar(resumen_preparador?);
+    }
+
+    let canal = demux.canal(id_cafetera);
+    let registrados = registrados.lock()?.clone();
+    for nodo in registrados {
+        let desregistrar = Desregistrar::de_cafetera(id_cafetera).to_bytes();
+        consultar_nodo(
+            &canal,
+            desregistrar,
+            &nodos[nodo],
+            &mut resumen,
+            reloj.as_ref(),
+        )?;
+    }
+    Ok(resumen)
+}
+
+/// Pedido de una linea del archivo. Es invalido si no se puede leer o si su tipo no es SUMA, RESTA o
+/// TRANSFERENCIA (esta con id_cuenta_destino)
+fn leer_pedido(linea: &str) -> Result<Pedido, String> {
+    let pedido: Pedido = serde_json::from_str(linea).map_err(|x| x.to_string())?;
+    match (pedido.tipo.as_str(), pedido.id_cuenta_destino) {
+        ("SUMA", _) | ("RESTA", _) | ("TRANSFERENCIA", Some(_)) => Ok(pedido),
+        (tipo, _) => Err(format!(
+            "pedido de tipo {:?} invalido o sin id_cuenta_destino",
+            tipo
+        )),
+    }
+}
+
+/// Lineas del archivo de pedidos, que se leen de a una a medida que se recorren
+fn lineas_pedidos(
+    path: &str,
+) -> Result<impl Iterator<Item = Result<String, ErrorSucursal>>, ErrorSucursal> {
+    let file = File::open(path).map_err(|x| ErrorSucursal::Archivo(format!("{}: {}", path, x)))?;
+    let path = path.to_string();
+    Ok(BufReader::new(file)
+        .lines()
+        .map(move |x| x.map_err(|x| ErrorSucursal::Archivo(format!("{}: {}", path, x)))))
+}
+
+/// Valida todos los pedidos del archivo antes de enviar ninguno, recorriendolo sin cargarlo en memoria.
+/// Ademas de los invalidos para `leer_pedido`, si hay archivo de cuentas por cafetera es invalido el
+/// pedido con cuya cuenta no puede operar ninguna cafetera de la sucursal. Si hay pedidos invalidos el
+/// error los lista a todos con su numero de linea.
+/// Retorna, para cada cafetera de la sucursal, si se le pueden asignar pedidos: sin archivo de cuentas
+/// se usan a lo sumo tantas cafeteras como pedidos, y con el archivo las que pueden operar con la
+/// cuenta de algun pedido
+fn validar_pedidos(
+    path: &str,
+    cuentas_cafeteras: Option<&CuentasCafeteras>,
+) -> Result<Vec<bool>, ErrorSucursal> {
+    let cafeteras = config().sucursal.cantidad_cafeteras;
+    let mut habilitadas = vec![false; cafeteras];
+    let mut pedidos = 0;
+    let mut errores = vec![];
+    for (indice, linea) in lineas_pedidos(path)?.enumerate() {
+        let pedido = match leer_pedido(&linea?) {
+            Ok(x) => x,
+            Err(x) => {
+                errores.push(format!("linea {}: {}", indice + 1, x));
+                continue;
+            }
+        };
+        if let Some(cuentas) = cuentas_cafeteras {
+            let permitidas: Vec<usize> = (0..cafeteras)
+                .filter(|id_cafetera| {
+                    cuentas.permite(IdCafetera(*id_cafetera as u8), pedido.id_cuenta)
+                })
+                .collect();
+            if permitidas.is_empty() {
+                errores.push(format!(
+                    "linea {}: ninguna cafetera puede operar con la cuenta {}",
+                    indice + 1,
+                    pedido.id_cuenta
+                ));
+                continue;
+            }
+            permitidas.into_iter().for_each(|x| habilitadas[x] = true);
+        }
+        pedidos += 1;
+    }
+    if !errores.is_empty() {
+        return Err(ErrorSucursal::Parseo(format!(
+            "Hay {} pedidos invalidos, no se envia ninguno | {}",
+            errores.len(),
+            errores.join(" | ")
+        )));
+    }
+    if cuentas_cafeteras.is_none() {
+        habilitadas
+            .iter_mut()
+            .take(pedidos.max(1))
+            .for_each(|x| *x = true);
+    }
+    Ok(habilitadas)
+}
+
+/// Reparte los pedidos del archivo entre las cafeteras habilitadas a medida que lo lee, sin cargarlo
+/// entero en memoria: cada pedido va a la cafetera con menos pedidos en espera y, si todas tienen su
+/// cola llena, se deja de leer hasta que alguna tome un pedido (ver `Reparto`). Con archivo de cuentas
+/// por cafetera, cada pedido solo puede ir a las cafeteras que pueden operar con su cuenta.
+/// El id de pedido se arma con el nodo principal y el numero de linea del archivo (ej: nodo 2, linea
+/// 15 => 2000015), asi es unico entre sucursales y facil de ubicar en los logs
+fn repartir_pedidos(
+    path: &str,
+    id_nodo_principal: u32,
+    habilitadas: &[bool],
+    cuentas_cafeteras: Option<&CuentasCafeteras>,
+    reparto: &Reparto<PedidoEnCola>,
+) -> Result<(), ErrorSucursal> {
+    for (numero_linea, linea) in lineas_pedidos(path)?.enumerate() {
+        let linea = linea?;
+        let id_pedido = id_nodo_principal * 1_000_000 + numero_linea as u32 + 1;
+        let id_cuenta = leer_pedido(&linea)
+            .map_err(|x| ErrorSucursal::Parseo(format!("linea {}: {}", numero_linea + 1, x)))?
+            .id_cuenta;
+        let habilitada = |id_cafetera: usize| {
+            habilitadas[id_cafetera]
+                && cuentas_cafeteras
+                    .is_none_or(|x| x.permite(IdCafetera(id_cafetera as u8), id_cuenta))
+        };
+        match reparto.encolar((id_pedido, linea), habilitada)? {
+            Some(id_cafetera) => {
+                if let Some(tablero) = TABLERO.get() {
+                    tablero.asignar_pedido(IdCafetera(id_cafetera as u8));
+                }
+            }
+            None => warn!(
+                "Ninguna cafetera puede tomar el pedido, se descarta | ID_PEDIDO = {}",
+                id_pedido
+            ),
         }
     }
     Ok(())
@@ -227,62 +827,159 @@ fn process_sublist(
 
 /// Procesamiento de la lista de pedidos por parte de las cafeteras, recibimos por parámetros nuestro numero de ID_NODO
 /// al que se conectara la sucursal, y tambien el nombre del archivo de pedidos que procesara la sucursal.
-/// Los pedidos serán divididos entre las cafeteras, según la cantidad dada por la constante CANTIDAD_CAFETERAS y
-/// cada procesamiento de cafetera se ejecutara en su propio thread
+/// Los pedidos se leen del archivo a medida que las cafeteras los toman y se reparten entre ellas, según la
+/// cantidad dada por el parametro cantidad_cafeteras, y cada procesamiento de cafetera se ejecutara en su
+/// propio thread.
+/// Con `--nodo host:puerto` se indica la direccion de un nodo en lugar de armarla con la configuracion
+/// del cluster: el primer --nodo es la del primer ID_NODO de la lista, el segundo la del segundo, etc.
+/// Con `--tui` en lugar de los logs se muestra un tablero con el progreso de cada cafetera
 fn main() -> Result<(), ErrorSucursal> {
-    let args: Vec<String> = env::args().collect();
+    let (direcciones, mut args) = separar_direcciones_nodos(env::args())?;
+    let tui = args.iter().any(|x| x == "--tui");
+    args.retain(|x| x != "--tui");
     if args.len() < 3 {
-        return Err(ErrorSucursal::new(
-            "Es necesario recibir el numero de ID_NODO al que se conectara la sucursal, y tambien el nombre del archivo de pedidos. Ejemplo: procesando los pedidos (indicados en pedidos.txt) en el servidor con ID_NODO = 1: ´cargo run -- 1 pedidos.txt´",
-            TipoError::ErrorArgs,
+        return Err(ErrorSucursal::Argumentos(
+            "Es necesario recibir el numero de ID_NODO al que se conectara la sucursal, y tambien el nombre del archivo de pedidos. Ejemplo: procesando los pedidos (indicados en pedidos.txt) en el servidor con ID_NODO = 1: ´cargo run -- 1 pedidos.txt´. Opcionalmente se pueden indicar nodos de respaldo separados por coma: ´cargo run -- 1,2,3 pedidos.txt´, y la direccion de cada nodo en el mismo orden: ´cargo run -- 1,2 pedidos.txt --nodo 10.0.0.5:12351 --nodo 10.0.0.6:12352´".to_string(),
+        ));
+    }
+    // El primer id es el nodo principal, el resto son nodos de respaldo (ej: 1,2,3)
+    let nodos: Vec<String> = args[1]
+        .split(',')
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect();
+    if nodos.is_empty() {
+        return Err(ErrorSucursal::Argumentos(
+            "Es necesario indicar al menos un ID_NODO".to_string(),
+        ));
+    }
+    if nodos.iter().any(|id_nodo| id_nodo.parse::<u8>().is_err()) {
+        return Err(ErrorSucursal::Argumentos(
+            "Los ID_NODO deben ser numeros entre 0 y 255".to_string(),
         ));
     }
-    let id_nodo: String = args[1].to_string();
+    if direcciones.len() > nodos.len() {
+        return Err(ErrorSucursal::Argumentos(format!(
+            "Se indicaron {} direcciones con --nodo para {} ID_NODO",
+            direcciones.len(),
+            nodos.len()
+        )));
+    }
+    DIRECCIONES_NODOS.get_or_init(|| nodos.iter().cloned().zip(direcciones).collect());
     let pedidos_file: String = args[2].to_string();
+    let proceso = format!("sucursal-{}", pedidos_file.trim_end_matches(".txt"));
+    if tui {
+        init_tracing_sin_consola(&proceso);
+        TABLERO.get_or_init(|| {
+            Tablero::new(&format!(
+                "Sucursal {} | nodos {}",
+                pedidos_file,
+                nodos.join(",")
+            ))
+        });
+    } else {
+        init_tracing(&proceso);
+    }
 
     // let path_pedidos = "Pedidos/pedidos_sucursal".to_string() + &id_nodo + ".txt";
     let path_pedidos = "Pedidos/".to_string() + &pedidos_file;
-    let file = File::open(path_pedidos)
-        .map_err(|x| ErrorSucursal::new(&x.to_string(), TipoError::ErrorArchivo))?;
-
-    let reader = BufReader::new(file);
-
-    let mut lines = Vec::new();
 
-    //Pasar todos los pedidos a una lista
-    for line in reader.lines().flatten() {
-        lines.push(line);
-    }
+    let id_nodo_principal: u32 = nodos[0]
+        .parse()
+        .map_err(|_x| ErrorSucursal::Argumentos("El ID_NODO debe ser numerico".to_string()))?;
 
-    let mut cafeteras = CANTIDAD_CAFETERAS;
-    if CANTIDAD_CAFETERAS > lines.len() {
-        cafeteras = lines.len();
-    }
+    let cuentas_cafeteras = match config().sucursal.archivo_cuentas_cafeteras.as_str() {
+        "" => None,
+        path => Some(CuentasCafeteras::desde_archivo(path)?),
+    };
+    let habilitadas = validar_pedidos(&path_pedidos, cuentas_cafeteras.as_ref())?;
+    let reparto = Arc::new(Reparto::new(
+        habilitadas.len(),
+        config().sucursal.pedidos_en_espera_por_cafetera,
+    ));
 
-    let cantidad_por_cafetera = lines.len() / cafeteras;
+    // Todas las cafeteras envian y reciben sus mensajes por el mismo socket, en un puerto libre del host
+    let demux = Arc::new(Demultiplexor::con_perdida(
+        config().sucursal.host.clone() + ":0",
+        config().sucursal.probabilidad_perdida,
+    )?);
+    info!("Sucursal escuchando en {:?}", demux.direccion_local()?);
 
     let mut thread_handles = vec![];
+    // Reloj con el que las cafeteras simulan el tiempo de preparacion de cada cafe y miden los timeouts
+    let reloj: Arc<dyn Reloj> = Arc::new(RelojReal);
 
-    for i in 0..cafeteras {
-        let inicio = i * cantidad_por_cafetera;
-        let rango_final = if i == cafeteras - 1 {
-            lines.len()
-        } else {
-            (i + 1) * cantidad_por_cafetera
-        };
-
-        let sublista = lines[inicio..rango_final].to_vec();
-
-        let id_nodo_cpy = id_nodo.clone();
+    for (i, _) in habilitadas.iter().enumerate().filter(|(_, x)| **x) {
+        if let Some(tablero) = TABLERO.get() {
+            tablero.agregar_cafetera(IdCafetera(i as u8), 0);
+        }
+        let reparto_cpy = reparto.clone();
+        let nodos_cpy = nodos.clone();
+        let demux_cpy = demux.clone();
+        let reloj_cpy = reloj.clone();
         thread_handles.push(thread::spawn(move || {
-            process_sublist(sublista, id_nodo_cpy, i as u8)
+            let resumen = procesar_cafetera(
+                reparto_cpy.clone(),
+                nodos_cpy,
+                IdCafetera(i as u8),
+                demux_cpy,
+                reloj_cpy,
+            );
+            // si la cafetera termina antes de tiempo (por un error) no se le asignan mas pedidos
+            reparto_cpy.retirar(i)?;
+            resumen
         }));
     }
 
+    let refresco = TABLERO.get().map(|tablero| {
+        thread::spawn(move || {
+            while !tablero.finalizado() {
+                tablero.mostrar();
+                thread::sleep(INTERVALO_TABLERO);
+            }
+        })
+    });
+
+    let lectura = repartir_pedidos(
+        &path_pedidos,
+        id_nodo_principal,
+        &habilitadas,
+        cuentas_cafeteras.as_ref(),
+        &reparto,
+    );
+    // las cafeteras terminan al vaciar sus colas, aunque no se haya podido leer el archivo entero
+    reparto.cerrar()?;
+    if let Err(err) = &lectura {
+        error!(
+            "No se pudieron repartir los pedidos restantes del archivo | Detalle: {:?}",
+            err
+        );
+    }
+
+    let mut resumenes = vec![];
     for handle in thread_handles {
-        let _ = handle
+        let resumen = handle
             .join()
-            .map_err(|_x| ErrorSucursal::new("Error en join threads", TipoError::ErrorArchivo))?;
+            .map_err(|_x| ErrorSucursal::Estado("Error en join threads".to_string()))?;
+        if let Ok(resumen) = resumen {
+            resumenes.push(resumen);
+        }
     }
-    Ok(())
+
+    match (TABLERO.get(), refresco) {
+        (Some(tablero), Some(refresco)) => {
+            // se dibuja el estado final y debajo los resumenes, que en este modo no van a la consola
+            tablero.finalizar();
+            let _ = refresco.join();
+            tablero.mostrar();
+            println!();
+            for resumen in resumenes {
+                for linea in resumen.lineas() {
+                    println!("{}", linea);
+                }
+            }
+        }
+        _ => resumenes.iter().for_each(ResumenCafetera::imprimir),
+    }
+    lectura
 }
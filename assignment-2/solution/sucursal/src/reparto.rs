@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+use crate::error_sucursal::ErrorSucursal;
+
+#[derive(Debug)]
+struct Colas<T> {
+    /// pedidos en espera de cada cafetera
+    colas: Vec<VecDeque<T>>,
+    /// true cuando ya no se van a encolar mas pedidos
+    cerrado: bool,
+    /// cafeteras que dejaron de tomar pedidos, por ejemplo por un error
+    retiradas: Vec<bool>,
+    /// cafetera desde la que se busca en el proximo pedido, para repartir por turnos entre las que
+    /// tienen la misma cantidad de pedidos en espera
+    turno: usize,
+}
+
+/// Colas de pedidos de las cafeteras de la sucursal, que se llenan a medida que se lee el archivo de
+/// pedidos en lugar de repartirlo entero al arrancar. Cada pedido va a la cafetera habilitada con menos
+/// pedidos en espera, asi una cafetera cuyos pedidos tardan mas recibe menos. Cada cola tiene a lo
+/// sumo `capacidad` pedidos: si las de todas las cafeteras habilitadas estan llenas, quien encola
+/// espera a que alguna se libere, por lo que nunca hay mas de `capacidad` pedidos por cafetera en memoria
+#[derive(Debug)]
+pub struct Reparto<T> {
+    colas: Mutex<Colas<T>>,
+    cambio: Condvar,
+    capacidad: usize,
+}
+
+impl<T> Reparto<T> {
+    pub fn new(cafeteras: usize, capacidad: usize) -> Self {
+        Reparto {
+            colas: Mutex::new(Colas {
+                colas: (0..cafeteras).map(|_| VecDeque::new()).collect(),
+                cerrado: false,
+                retiradas: vec![false; cafeteras],
+                turno: 0,
+            }),
+            cambio: Condvar::new(),
+            capacidad: capacidad.max(1),
+        }
+    }
+
+    fn colas(&self) -> Result<MutexGuard<'_, Colas<T>>, ErrorSucursal> {
+        Ok(self.colas.lock()?)
+    }
+
+    /// Encola el pedido en la cafetera habilitada con menos pedidos en espera, esperando a que haya
+    /// lugar si todas estan llenas. Retorna la cafetera elegida, o None si ninguna esta habilitada (o
+    /// se retiraron todas las habilitadas)
+    pub fn encolar(
+        &self,
+        pedido: T,
+        habilitada: impl Fn(usize) -> bool,
+    ) -> Result<Option<usize>, ErrorSucursal> {
+        let mut colas = self.colas()?;
+        let cafeteras = colas.colas.len();
+        loop {
+            if !(0..cafeteras).any(|i| habilitada(i) && !colas.retiradas[i]) {
+                return Ok(None);
+            }
+            let turno = colas.turno;
+            let elegida = (0..cafeteras)
+                .map(|i| (turno + i) % cafeteras)
+                .filter(|i| {
+                    habilitada(*i) && !colas.retiradas[*i] && colas.colas[*i].len() < self.capacidad
+                })
+                .min_by_key(|i| colas.colas[*i].len());
+            if let Some(elegida) = elegida {
+                colas.colas[elegida].push_back(pedido);
+                colas.turno = (elegida + 1) % cafeteras;
+                self.cambio.notify_all();
+                return Ok(Some(elegida));
+            }
+            colas = self.cambio.wait(colas)?;
+        }
+    }
+
+    /// Retira el proximo pedido de la cafetera, esperando a que llegue uno. None cuando su cola esta
+    /// vacia y ya no se van a encolar mas pedidos
+    pub fn siguiente(&self, cafetera: usize) -> Result<Option<T>, ErrorSucursal> {
+        let mut colas = self.colas()?;
+        loop {
+            if let Some(pedido) = colas.colas.get_mut(cafetera).and_then(VecDeque::pop_front) {
+                self.cambio.notify_all();
+                return Ok(Some(pedido));
+            }
+            if colas.cerrado || colas.retiradas.get(cafetera).copied().unwrap_or(true) {
+                return Ok(None);
+            }
+            colas = self.cambio.wait(colas)?;
+        }
+    }
+
+    /// Indica que ya no se van a encolar mas pedidos, las cafeteras terminan al vaciar sus colas
+    pub fn cerrar(&self) -> Result<(), ErrorSucursal> {
+        self.colas()?.cerrado = true;
+        self.cambio.notify_all();
+        Ok(())
+    }
+
+    /// La cafetera deja de tomar pedidos: no se le encolan mas y se descartan los que tenia en
+    /// espera, asi quien encola no espera indefinidamente a que se libere su cola
+    pub fn retirar(&self, cafetera: usize) -> Result<(), ErrorSucursal> {
+        let mut colas = self.colas()?;
+        if let Some(retirada) = colas.retiradas.get_mut(cafetera) {
+            *retirada = true;
+        }
+        if let Some(cola) = colas.colas.get_mut(cafetera) {
+            cola.clear();
+        }
+        self.cambio.notify_all();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod reparto_test {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn cada_pedido_va_a_la_cafetera_habilitada_con_menos_pedidos_en_espera() {
+        let reparto = Reparto::new(3, 10);
+        let todas = |_| true;
+        // con las colas igual de cargadas se reparte por turnos
+        assert_eq!(Some(0), reparto.encolar(1, todas).unwrap());
+        assert_eq!(Some(1), reparto.encolar(2, todas).unwrap());
+        assert_eq!(Some(2), reparto.encolar(3, todas).unwrap());
+        assert_eq!(Some(2), reparto.siguiente(1).unwrap());
+        assert_eq!(Some(1), reparto.encolar(4, todas).unwrap());
+
+        // una cafetera no habilitada no recibe pedidos aunque tenga la cola vacia
+        assert_eq!(Some(3), reparto.siguiente(2).unwrap());
+        assert_eq!(Some(0), reparto.encolar(5, |i| i == 0).unwrap());
+        assert_eq!(None, reparto.encolar(6, |_| false).unwrap());
+
+        reparto.cerrar().unwrap();
+        assert_eq!(Some(1), reparto.siguiente(0).unwrap());
+        assert_eq!(Some(5), reparto.siguiente(0).unwrap());
+        assert_eq!(None, reparto.siguiente(0).unwrap());
+        assert_eq!(Some(4), reparto.siguiente(1).unwrap());
+        assert_eq!(None, reparto.siguiente(2).unwrap());
+    }
+
+    #[test]
+    fn con_las_colas_llenas_se_espera_a_que_una_cafetera_tome_un_pedido() {
+        let reparto = Arc::new(Reparto::new(2, 1));
+        let lector = {
+            let reparto = reparto.clone();
+            thread::spawn(move || {
+                let elegidas: Vec<Option<usize>> = (0..4)
+                    .map(|pedido| reparto.encolar(pedido, |_| true).unwrap())
+                    .collect();
+                reparto.cerrar().unwrap();
+                elegidas
+            })
+        };
+
+        // la cafetera 1 no toma pedidos hasta que la 0 termine: el resto le toca a la 0
+        let mut tomados_0 = vec![];
+        while let Some(pedido) = reparto.siguiente(0).unwrap() {
+            tomados_0.push(pedido);
+            if tomados_0.len() == 3 {
+                break;
+            }
+        }
+        assert_eq!(vec![0, 2, 3], tomados_0);
+        assert_eq!(Some(1), reparto.siguiente(1).unwrap());
+        assert_eq!(None, reparto.siguiente(1).unwrap());
+        assert_eq!(
+            vec![Some(0), Some(1), Some(0), Some(0)],
+            lector.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn una_cafetera_retirada_no_bloquea_a_quien_encola() {
+        let reparto = Reparto::new(2, 1);
+        assert_eq!(Some(0), reparto.encolar(1, |i| i == 0).unwrap());
+        reparto.retirar(0).unwrap();
+        assert_eq!(None, reparto.siguiente(0).unwrap());
+        assert_eq!(None, reparto.encolar(2, |i| i == 0).unwrap());
+        assert_eq!(Some(1), reparto.encolar(3, |_| true).unwrap());
+    }
+}
@@ -0,0 +1,72 @@
+use compartido::ids::IdCafetera;
+use tracing::info;
+/// Resumen de lo ocurrido con los pedidos de una cafetera, se imprime al finalizar la sucursal
+#[derive(Debug, Default)]
+pub struct ResumenCafetera {
+    /// id de la cafetera
+    pub id_cafetera: IdCafetera,
+    /// sumas que el nodo confirmo como commiteadas en todo el sistema
+    pub sumas_confirmadas: u32,
+    /// sumas que el nodo informo como abortadas en todo el sistema
+    pub sumas_fallidas: u32,
+    /// sumas enviadas de las que todavia no se recibio el estado final
+    pub sumas_pendientes: u32,
+    /// cantidad de veces que la cafetera cambio de nodo por falta de respuesta
+    pub cambios_de_nodo: u32,
+    /// pedidos que no se llegaron a enviar por falta de respuesta de los nodos
+    pub pedidos_diferidos: Vec<String>,
+}
+
+impl ResumenCafetera {
+    pub fn new(id_cafetera: IdCafetera) -> Self {
+        ResumenCafetera {
+            id_cafetera,
+            ..Default::default()
+        }
+    }
+
+    /// Registra el estado final de una suma informado por el nodo
+    pub fn registrar_estado_final(&mut self, confirmada: bool) {
+        if confirmada {
+            self.sumas_confirmadas += 1;
+        } else {
+            self.sumas_fallidas += 1;
+        }
+        self.sumas_pendientes = self.sumas_pendientes.saturating_sub(1);
+    }
+
+    /// Suma al resumen lo ocurrido en otro preparador de la misma cafetera
+    pub fn combinar(&mut self, otro: ResumenCafetera) {
+        self.sumas_confirmadas += otro.sumas_confirmadas;
+        self.sumas_fallidas += otro.sumas_fallidas;
+        self.sumas_pendientes += otro.sumas_pendientes;
+        self.cambios_de_nodo += otro.cambios_de_nodo;
+        self.pedidos_diferidos.extend(otro.pedidos_diferidos);
+    }
+
+    /// Lineas del resumen: los totales de la cafetera y luego una por pedido diferido
+    pub fn lineas(&self) -> Vec<String> {
+        let mut lineas = vec![format!(
+            "[RESUMEN] Cafetera {}: sumas confirmadas = {}, sumas fallidas = {}, sumas sin estado final = {}, cambios de nodo = {}, pedidos diferidos = {}",
+            self.id_cafetera,
+            self.sumas_confirmadas,
+            self.sumas_fallidas,
+            self.sumas_pendientes,
+            self.cambios_de_nodo,
+            self.pedidos_diferidos.len()
+        )];
+        lineas.extend(
+            self.pedidos_diferidos
+                .iter()
+                .map(|pedido| format!("  diferido: {}", pedido)),
+        );
+        lineas
+    }
+
+    /// Imprime por consola el resumen de la cafetera
+    pub fn imprimir(&self) {
+        for linea in self.lineas() {
+            info!("{}", linea);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::sync::{Mutex, MutexGuard};
+
+use compartido::ids::IdCafetera;
+
+/// Etapa en la que esta un pedido que una cafetera todavia no termino
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EstadoPedido {
+    /// se envio el pedido y se espera la respuesta del nodo
+    EsperandoNodo,
+    /// el nodo acepto el pedido y se esta preparando el cafe
+    Preparando,
+    /// se espera el resultado de la resta o de la transferencia
+    EsperandoConfirmacion,
+    /// vencio la espera y se le envio un ping al nodo
+    Reintentando,
+}
+
+impl EstadoPedido {
+    fn nombre(&self) -> &'static str {
+        match self {
+            EstadoPedido::EsperandoNodo => "esperando al nodo",
+            EstadoPedido::Preparando => "preparando",
+            EstadoPedido::EsperandoConfirmacion => "esperando confirmacion",
+            EstadoPedido::Reintentando => "reintentando",
+        }
+    }
+}
+
+/// Como termino un pedido para la cafetera
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultadoPedido {
+    Completado,
+    /// el nodo rechazo el pedido o fallo la preparacion del cafe
+    Fallido,
+    /// no se supo el resultado porque ningun nodo respondio
+    Diferido,
+}
+
+#[derive(Debug, Default)]
+struct FilaCafetera {
+    pedidos: usize,
+    en_curso: BTreeMap<u32, EstadoPedido>,
+    completados: usize,
+    fallidos: usize,
+    diferidos: usize,
+}
+
+#[derive(Debug, Default)]
+struct Estado {
+    cafeteras: BTreeMap<IdCafetera, FilaCafetera>,
+    finalizado: bool,
+}
+
+/// Progreso de las cafeteras de la sucursal para el modo --tui: una fila por cafetera con los pedidos
+/// en curso (y en que etapa estan) y cuantos termino de cada forma. Lo actualizan los preparadores
+/// y se redibuja periodicamente en la consola en lugar de mostrar los logs
+#[derive(Debug)]
+pub struct Tablero {
+    titulo: String,
+    estado: Mutex<Estado>,
+}
+
+impl Tablero {
+    pub fn new(titulo: &str) -> Self {
+        Tablero {
+            titulo: titulo.to_string(),
+            estado: Mutex::new(Estado::default()),
+        }
+    }
+
+    /// Si un preparador entro en panico con el lock tomado el tablero sigue con lo que tenia
+    fn estado(&self) -> MutexGuard<'_, Estado> {
+        self.estado
+            .lock()
+            .unwrap_or_else(|envenenado| envenenado.into_inner())
+    }
+
+    fn modificar(&self, id_cafetera: IdCafetera, f: impl FnOnce(&mut FilaCafetera)) {
+        f(self.estado().cafeteras.entry(id_cafetera).or_default());
+    }
+
+    /// Agrega la fila de la cafetera con la cantidad de pedidos que tiene asignados
+    pub fn agregar_cafetera(&self, id_cafetera: IdCafetera, pedidos: usize) {
+        self.modificar(id_cafetera, |fila| fila.pedidos = pedidos);
+    }
+
+    /// Suma un pedido a los asignados a la cafetera, a medida que se reparte el archivo de pedidos
+    pub fn asignar_pedido(&self, id_cafetera: IdCafetera) {
+        self.modificar(id_cafetera, |fila| fila.pedidos += 1);
+    }
+
+    /// Registra la etapa en la que esta un pedido de la cafetera
+    pub fn actualizar(&self, id_cafetera: IdCafetera, id_pedido: u32, estado: EstadoPedido) {
+        self.modificar(id_cafetera, |fila| {
+            fila.en_curso.insert(id_pedido, estado);
+        });
+    }
+
+    /// El pedido deja de estar en curso y se cuenta segun su resultado
+    pub fn terminar(&self, id_cafetera: IdCafetera, id_pedido: u32, resultado: ResultadoPedido) {
+        self.modificar(id_cafetera, |fila| {
+            fila.en_curso.remove(&id_pedido);
+            match resultado {
+                ResultadoPedido::Completado => fila.completados += 1,
+                ResultadoPedido::Fallido => fila.fallidos += 1,
+                ResultadoPedido::Diferido => fila.diferidos += 1,
+            }
+        });
+    }
+
+    /// Todas las cafeteras terminaron, se deja de redibujar
+    pub fn finalizar(&self) {
+        self.estado().finalizado = true;
+    }
+
+    pub fn finalizado(&self) -> bool {
+        self.estado().finalizado
+    }
+
+    /// Texto del tablero: el titulo, el total de pedidos terminados y una fila por cafetera
+    pub fn dibujar(&self) -> String {
+        let estado = self.estado();
+        let pedidos: usize = estado.cafeteras.values().map(|x| x.pedidos).sum();
+        let terminados: usize = estado
+            .cafeteras
+            .values()
+            .map(|x| x.completados + x.fallidos + x.diferidos)
+            .sum();
+        let mut texto = format!(
+            "{} | {}/{} pedidos terminados\n\n{:<9}{:>9}{:>12}{:>10}{:>11}  EN CURSO\n",
+            self.titulo,
+            terminados,
+            pedidos,
+            "CAFETERA",
+            "PEDIDOS",
+            "COMPLETADOS",
+            "FALLIDOS",
+            "DIFERIDOS"
+        );
+        for (id_cafetera, fila) in estado.cafeteras.iter() {
+            let terminados = fila.completados + fila.fallidos + fila.diferidos;
+            let en_curso = if fila.en_curso.is_empty() && terminados == fila.pedidos {
+                "terminada".to_string()
+            } else {
+                fila.en_curso
+                    .iter()
+                    .map(|(id_pedido, estado)| format!("{} {}", id_pedido, estado.nombre()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            let _ = writeln!(
+                texto,
+                "{:<9}{:>9}{:>12}{:>10}{:>11}  {}",
+                id_cafetera,
+                format!("{}/{}", terminados, fila.pedidos),
+                fila.completados,
+                fila.fallidos,
+                fila.diferidos,
+                en_curso
+            );
+        }
+        texto
+    }
+
+    /// Borra la consola y dibuja el tablero
+    pub fn mostrar(&self) {
+        let mut salida = std::io::stdout().lock();
+        let _ = write!(salida, "\x1b[2J\x1b[H{}", self.dibujar());
+        let _ = salida.flush();
+    }
+}
+
+#[cfg(test)]
+mod tablero_test {
+    use super::*;
+
+    #[test]
+    fn cada_cafetera_muestra_sus_pedidos_en_curso_y_terminados() {
+        let tablero = Tablero::new("sucursal prueba.txt");
+        tablero.agregar_cafetera(IdCafetera(0), 1);
+        tablero.asignar_pedido(IdCafetera(0));
+        tablero.asignar_pedido(IdCafetera(1));
+        tablero.actualizar(IdCafetera(0), 1000001, EstadoPedido::EsperandoNodo);
+        tablero.actualizar(IdCafetera(0), 1000001, EstadoPedido::Preparando);
+        tablero.actualizar(IdCafetera(1), 1000003, EstadoPedido::Reintentando);
+        tablero.terminar(IdCafetera(1), 1000003, ResultadoPedido::Diferido);
+
+        let texto = tablero.dibujar();
+        let lineas: Vec<&str> = texto.lines().collect();
+        assert_eq!("sucursal prueba.txt | 1/3 pedidos terminados", lineas[0]);
+        assert!(lineas[3].starts_with("0"));
+        assert!(lineas[3].contains("0/2"));
+        assert!(lineas[3].ends_with("1000001 preparando"));
+        assert!(lineas[4].contains("1/1"));
+        assert!(lineas[4].ends_with("terminada"));
+    }
+}
@@ -1,8 +0,0 @@
-/// Cantidad de cafeteras que habrá por nodo
-pub const CANTIDAD_CAFETERAS: usize = 3;
-/// Espera maxima para recibir una respuesta por el socket udp
-pub const TIMEOUT: u64 = 1000;
-/// Probabilidad que la preparacion del cafe falle
-pub const PROBABILIDAD_ERROR: f64 = 0.0;
-/// Tiempo destinado a la preparacion del cafe
-pub const TIEMPO_DE_PREPARACION: u64 = 7;
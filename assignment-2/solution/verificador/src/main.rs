@@ -0,0 +1,117 @@
+mod verificacion;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use compartido::config::config;
+use compartido::ids::IdNodo;
+use compartido::logs::init_tracing;
+use serde::de::DeserializeOwned;
+use tracing::{error, info, warn};
+use verificacion::{verificar, Registros};
+
+/// Prefijo del archivo del registro de cada nodo, ver `server::registro_nodo`
+const PREFIJO_NODO: &str = "registro-nodo-";
+/// Prefijo del archivo del registro de commits de cada coordinador, ver `server::registro_commits`
+const PREFIJO_COORDINADOR: &str = "registro-coordinador-";
+
+/// ID_NODO del archivo de registro, si el nombre es <prefijo><ID_NODO>.jsonl
+fn id_nodo_de_archivo(nombre: &str, prefijo: &str) -> Option<IdNodo> {
+    nombre
+        .strip_prefix(prefijo)?
+        .strip_suffix(".jsonl")?
+        .parse()
+        .ok()
+}
+
+/// Lee las lineas json del registro
+fn leer_registro<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, String> {
+    let contenido = fs::read_to_string(path).map_err(|x| format!("{}: {}", path.display(), x))?;
+    contenido
+        .lines()
+        .filter(|x| !x.trim().is_empty())
+        .enumerate()
+        .map(|(numero, linea)| {
+            serde_json::from_str(linea)
+                .map_err(|x| format!("{} linea {}: {}", path.display(), numero + 1, x))
+        })
+        .collect()
+}
+
+/// Lee los registros de los nodos y de los coordinadores del directorio
+fn leer_registros(directorio: &Path) -> Result<Registros, String> {
+    let mut registros = Registros::default();
+    let archivos =
+        fs::read_dir(directorio).map_err(|x| format!("{}: {}", directorio.display(), x))?;
+    for archivo in archivos {
+        let path = archivo.map_err(|x| x.to_string())?.path();
+        let nombre = match path.file_name().and_then(|x| x.to_str()) {
+            Some(nombre) => nombre.to_string(),
+            None => continue,
+        };
+        if let Some(id_nodo) = id_nodo_de_archivo(&nombre, PREFIJO_NODO) {
+            registros.nodos.insert(id_nodo, leer_registro(&path)?);
+        } else if let Some(id_nodo) = id_nodo_de_archivo(&nombre, PREFIJO_COORDINADOR) {
+            registros
+                .coordinadores
+                .insert(id_nodo, leer_registro(&path)?);
+        }
+    }
+    Ok(registros)
+}
+
+/// Proceso que se corre luego de una prueba para verificar propiedades globales del cluster sobre los
+/// registros que dejaron los nodos y los coordinadores en servidor.directorio_datos (o en el
+/// directorio indicado): todos los nodos aplicaron los commits de cada cuenta en el mismo orden, sus
+/// saldos se derivan de los iniciales aplicando esos commits y ninguno se perdio un COMMIT difundido.
+/// Se ejecuta como `cargo run -- [DIRECTORIO]`. Termina con codigo 1 si alguna propiedad no se cumple
+/// y con 2 si no se pudieron leer los registros
+fn main() {
+    init_tracing("verificador");
+    let directorio = env::args()
+        .nth(1)
+        .unwrap_or_else(|| config().servidor.directorio_datos.clone());
+    if directorio.is_empty() {
+        error!("[VERIFICADOR] Hay que indicar el directorio de los registros o servidor.directorio_datos");
+        process::exit(2);
+    }
+    let registros = match leer_registros(Path::new(&directorio)) {
+        Ok(registros) => registros,
+        Err(err) => {
+            error!("[VERIFICADOR] No se pudieron leer los registros: {}", err);
+            process::exit(2);
+        }
+    };
+    if registros.nodos.is_empty() {
+        error!("[VERIFICADOR] No hay registros de nodos en {}", directorio);
+        process::exit(2);
+    }
+
+    let informe = verificar(&registros);
+    info!(
+        "[VERIFICADOR] {} COMMIT en los registros de los coordinadores {:?}, nodos verificados: {:?}",
+        informe.commits,
+        registros.coordinadores.keys().collect::<Vec<_>>(),
+        informe.nodos
+    );
+    for (id_nodo, saldos) in &informe.saldos_finales {
+        info!(
+            "[VERIFICADOR] Saldos finales del ID_NODO = {}: {:?}",
+            id_nodo, saldos
+        );
+    }
+    if informe.consistente() {
+        info!("[VERIFICADOR] Se cumplen todas las propiedades");
+        return;
+    }
+    for violacion in &informe.violaciones {
+        warn!("[VERIFICADOR] {:?}", violacion);
+    }
+    error!(
+        "[VERIFICADOR] {} propiedades no se cumplen",
+        informe.violaciones.len()
+    );
+    process::exit(1);
+}
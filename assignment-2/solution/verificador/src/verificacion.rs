@@ -0,0 +1,533 @@
+//! Propiedades globales que se verifican sobre los registros de una prueba: todos los nodos aplicaron
+//! los commits de cada cuenta en el mismo orden, los saldos que registraron se derivan de los iniciales
+//! aplicando esos commits, terminaron con los mismos saldos, y ninguno se perdio un COMMIT que difundio
+//! el coordinador al que estaba conectado
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use compartido::ids::{IdCuenta, IdNodo, IdTransaccion};
+use server::mensaje::CommitType;
+use server::registro_commits::{EntradaRegistro, ResultadoTransaccion};
+use server::registro_nodo::{EntradaNodo, EventoNodo};
+use server::saldo::{restar_saldo, sumar_saldo, transferir_saldo};
+
+/// Registros de una prueba, por ID_NODO
+#[derive(Debug, Default)]
+pub struct Registros {
+    /// registro de cada nodo, en el orden en que lo escribio
+    pub nodos: BTreeMap<IdNodo, Vec<EntradaNodo>>,
+    /// registro de commits de cada nodo que fue coordinador
+    pub coordinadores: BTreeMap<IdNodo, Vec<EntradaRegistro>>,
+}
+
+/// Propiedad que no se cumple
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violacion {
+    /// los nodos aplicaron dos commits de la cuenta en distinto orden: el primer nodo aplico las
+    /// transacciones en el orden indicado y el segundo al reves
+    OrdenDistinto {
+        id_cuenta: IdCuenta,
+        nodos: (IdNodo, IdNodo),
+        transacciones: (IdTransaccion, IdTransaccion),
+    },
+    /// el saldo que registro el nodo luego del commit no es el que resulta de aplicar sus registros
+    /// anteriores. None si la cuenta no existe
+    SaldoNoDerivable {
+        id_nodo: IdNodo,
+        id_transaccion: IdTransaccion,
+        id_cuenta: IdCuenta,
+        esperado: Option<u32>,
+        registrado: Option<u32>,
+    },
+    /// los nodos terminaron con distinto saldo en la cuenta
+    SaldoFinalDistinto {
+        id_cuenta: IdCuenta,
+        saldos: Vec<(IdNodo, Option<u32>)>,
+    },
+    /// el nodo no aplico el COMMIT que difundio el coordinador al que estaba conectado
+    CommitFaltante {
+        id_nodo: IdNodo,
+        id_coordinador: IdNodo,
+        secuencia: u64,
+        id_transaccion: IdTransaccion,
+    },
+}
+
+/// Resultado de la verificacion
+#[derive(Debug, Default)]
+pub struct Informe {
+    /// nodos con registro
+    pub nodos: Vec<IdNodo>,
+    /// cantidad de COMMIT en los registros de los coordinadores
+    pub commits: usize,
+    /// saldos con los que termino cada nodo, luego de aplicar su registro
+    pub saldos_finales: BTreeMap<IdNodo, BTreeMap<IdCuenta, u32>>,
+    pub violaciones: Vec<Violacion>,
+}
+
+impl Informe {
+    pub fn consistente(&self) -> bool {
+        self.violaciones.is_empty()
+    }
+}
+
+/// Verifica todas las propiedades sobre los registros
+pub fn verificar(registros: &Registros) -> Informe {
+    let mut informe = Informe {
+        nodos: registros.nodos.keys().copied().collect(),
+        commits: registros
+            .coordinadores
+            .values()
+            .flatten()
+            .filter(|x| x.resultado == ResultadoTransaccion::COMMIT)
+            .count(),
+        ..Informe::default()
+    };
+    for (id_nodo, entradas) in &registros.nodos {
+        let (saldos, violaciones) = reproducir(*id_nodo, entradas);
+        informe.saldos_finales.insert(*id_nodo, saldos);
+        informe.violaciones.extend(violaciones);
+    }
+    informe
+        .violaciones
+        .extend(saldos_finales_distintos(&informe.saldos_finales));
+    informe
+        .violaciones
+        .extend(ordenes_distintos(&registros.nodos));
+    for (id_nodo, entradas) in &registros.nodos {
+        informe.violaciones.extend(commits_faltantes(
+            *id_nodo,
+            entradas,
+            &registros.coordinadores,
+        ));
+    }
+    informe
+}
+
+/// Aplica el commit sobre las cuentas igual que el nodo: sin la cuenta no se modifica nada, y si el
+/// saldo no alcanza (o supera el maximo) el commit no se aplica
+fn aplicar(
+    cuentas: &mut HashMap<IdCuenta, u32>,
+    tipo: CommitType,
+    id_cuenta: IdCuenta,
+    id_cuenta_destino: Option<IdCuenta>,
+    cantidad: u32,
+) {
+    let saldo = match cuentas.get(&id_cuenta) {
+        Some(saldo) => *saldo,
+        None => return,
+    };
+    if tipo == CommitType::SUMA {
+        if let Ok(saldo) = sumar_saldo(saldo, cantidad) {
+            cuentas.insert(id_cuenta, saldo);
+        }
+        return;
+    }
+    let destino = id_cuenta_destino.and_then(|x| cuentas.get(&x).map(|saldo| (x, *saldo)));
+    match destino {
+        Some((id_cuenta_destino, saldo_destino)) => {
+            if let Ok((saldo, saldo_destino)) = transferir_saldo(saldo, saldo_destino, cantidad) {
+                cuentas.insert(id_cuenta, saldo);
+                cuentas.insert(id_cuenta_destino, saldo_destino);
+            }
+        }
+        None => {
+            if let Ok(saldo) = restar_saldo(saldo, cantidad) {
+                cuentas.insert(id_cuenta, saldo);
+            }
+        }
+    }
+}
+
+/// Reproduce el registro del nodo desde sus saldos iniciales, comparando despues de cada commit los
+/// saldos calculados con los registrados. Los snapshots (y los reinicios) reemplazan las cuentas.
+/// Retorna los saldos con los que termina
+fn reproducir(
+    id_nodo: IdNodo,
+    entradas: &[EntradaNodo],
+) -> (BTreeMap<IdCuenta, u32>, Vec<Violacion>) {
+    let mut cuentas: HashMap<IdCuenta, u32> = HashMap::new();
+    let mut violaciones = vec![];
+    for entrada in entradas {
+        match &entrada.evento {
+            EventoNodo::Inicio { cuentas: iniciales }
+            | EventoNodo::Snapshot {
+                cuentas: iniciales, ..
+            } => cuentas = iniciales.iter().copied().collect(),
+            EventoNodo::Alta { id_cuenta, saldo } => {
+                cuentas.entry(*id_cuenta).or_insert(*saldo);
+            }
+            EventoNodo::Baja { id_cuenta } => {
+                cuentas.remove(id_cuenta);
+            }
+            EventoNodo::Commit {
+                id_transaccion,
+                id_cuenta,
+                id_cuenta_destino,
+                tipo,
+                cantidad,
+                saldos,
+                ..
+            } => {
+                aplicar(
+                    &mut cuentas,
+                    *tipo,
+                    *id_cuenta,
+                    *id_cuenta_destino,
+                    *cantidad,
+                );
+                for id in std::iter::once(*id_cuenta).chain(*id_cuenta_destino) {
+                    let esperado = cuentas.get(&id).copied();
+                    let registrado = saldos.iter().find(|(x, _)| *x == id).map(|(_, x)| *x);
+                    if esperado != registrado {
+                        violaciones.push(Violacion::SaldoNoDerivable {
+                            id_nodo,
+                            id_transaccion: *id_transaccion,
+                            id_cuenta: id,
+                            esperado,
+                            registrado,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    (cuentas.into_iter().collect(), violaciones)
+}
+
+/// Cuentas en las que los nodos no terminaron con el mismo saldo, incluidas las que faltan en alguno
+fn saldos_finales_distintos(
+    saldos_finales: &BTreeMap<IdNodo, BTreeMap<IdCuenta, u32>>,
+) -> Vec<Violacion> {
+    let ids: BTreeSet<IdCuenta> = saldos_finales
+        .values()
+        .flat_map(|x| x.keys().copied())
+        .collect();
+    ids.into_iter()
+        .filter_map(|id_cuenta| {
+            let saldos: Vec<(IdNodo, Option<u32>)> = saldos_finales
+                .iter()
+                .map(|(id_nodo, cuentas)| (*id_nodo, cuentas.get(&id_cuenta).copied()))
+                .collect();
+            let distintos: BTreeSet<Option<u32>> = saldos.iter().map(|(_, x)| *x).collect();
+            (distintos.len() > 1).then_some(Violacion::SaldoFinalDistinto { id_cuenta, saldos })
+        })
+        .collect()
+}
+
+/// Transacciones que el nodo aplico sobre cada cuenta (la de origen y la de destino), en orden
+fn commits_por_cuenta(entradas: &[EntradaNodo]) -> HashMap<IdCuenta, Vec<IdTransaccion>> {
+    let mut por_cuenta: HashMap<IdCuenta, Vec<IdTransaccion>> = HashMap::new();
+    for entrada in entradas {
+        if let EventoNodo::Commit {
+            id_transaccion,
+            id_cuenta,
+            id_cuenta_destino,
+            ..
+        } = &entrada.evento
+        {
+            for id in std::iter::once(*id_cuenta).chain(*id_cuenta_destino) {
+                por_cuenta.entry(id).or_default().push(*id_transaccion);
+            }
+        }
+    }
+    por_cuenta
+}
+
+/// Compara de a pares los nodos: las transacciones de cada cuenta que aplicaron ambos tienen que estar
+/// en el mismo orden. Se reporta el primer par invertido de cada cuenta
+fn ordenes_distintos(nodos: &BTreeMap<IdNodo, Vec<EntradaNodo>>) -> Vec<Violacion> {
+    let commits: Vec<(IdNodo, HashMap<IdCuenta, Vec<IdTransaccion>>)> = nodos
+        .iter()
+        .map(|(id_nodo, entradas)| (*id_nodo, commits_por_cuenta(entradas)))
+        .collect();
+    let mut violaciones = vec![];
+    for (i, (nodo_a, cuentas_a)) in commits.iter().enumerate() {
+        for (nodo_b, cuentas_b) in &commits[i + 1..] {
+            let mut ids: Vec<&IdCuenta> = cuentas_a.keys().collect();
+            ids.sort();
+            for id_cuenta in ids {
+                let posiciones: HashMap<IdTransaccion, usize> = cuentas_a[id_cuenta]
+                    .iter()
+                    .enumerate()
+                    .map(|(posicion, id)| (*id, posicion))
+                    .collect();
+                let en_ambos = cuentas_b
+                    .get(id_cuenta)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|id| posiciones.get(id).map(|posicion| (*posicion, *id)));
+                let mut anterior: Option<(usize, IdTransaccion)> = None;
+                for (posicion, id) in en_ambos {
+                    if let Some((posicion_anterior, id_anterior)) = anterior {
+                        if posicion < posicion_anterior {
+                            violaciones.push(Violacion::OrdenDistinto {
+                                id_cuenta: *id_cuenta,
+                                nodos: (*nodo_a, *nodo_b),
+                                transacciones: (id, id_anterior),
+                            });
+                            break;
+                        }
+                    }
+                    anterior = Some((posicion, id));
+                }
+            }
+        }
+    }
+    violaciones
+}
+
+/// Lo que el nodo registro de un coordinador
+#[derive(Default)]
+struct TramoCoordinador {
+    /// corte del ultimo snapshot que le envio ese coordinador: los commits anteriores ya estaban
+    /// reflejados en el snapshot
+    desde: u64,
+    /// mayor secuencia que aplico de ese coordinador
+    hasta: u64,
+    /// true si luego de lo ultimo que registro de ese coordinador aplico un snapshot de otro, que ya
+    /// refleja los commits que se haya perdido
+    cubierto: bool,
+}
+
+/// COMMIT de los coordinadores que el nodo no aplico. De cada coordinador del que el nodo registro
+/// algo (un commit, un snapshot, un alta o una baja) se exigen los commits posteriores al ultimo
+/// snapshot que le envio, y si luego aplico el snapshot de otro coordinador solo hasta la mayor
+/// secuencia que aplico
+fn commits_faltantes(
+    id_nodo: IdNodo,
+    entradas: &[EntradaNodo],
+    coordinadores: &BTreeMap<IdNodo, Vec<EntradaRegistro>>,
+) -> Vec<Violacion> {
+    let mut tramos: BTreeMap<IdNodo, TramoCoordinador> = BTreeMap::new();
+    let mut aplicados: HashSet<IdTransaccion> = HashSet::new();
+    for entrada in entradas {
+        let id_coordinador = entrada.id_coordinador;
+        match &entrada.evento {
+            // al arrancar el nodo todavia puede no estar conectado al coordinador de la configuracion
+            EventoNodo::Inicio { .. } => continue,
+            EventoNodo::Snapshot { corte, .. } => {
+                for (otro, tramo) in tramos.iter_mut() {
+                    if *otro != id_coordinador {
+                        tramo.cubierto = true;
+                    }
+                }
+                tramos.entry(id_coordinador).or_default().desde = *corte;
+            }
+            EventoNodo::Commit {
+                secuencia,
+                id_transaccion,
+                ..
+            } => {
+                aplicados.insert(*id_transaccion);
+                let tramo = tramos.entry(id_coordinador).or_default();
+                tramo.hasta = tramo.hasta.max(*secuencia);
+            }
+            EventoNodo::Alta { .. } | EventoNodo::Baja { .. } => {}
+        }
+        tramos.entry(id_coordinador).or_default().cubierto = false;
+    }
+
+    let mut violaciones = vec![];
+    for (id_coordinador, tramo) in &tramos {
+        let registro = match coordinadores.get(id_coordinador) {
+            Some(registro) => registro,
+            None => continue,
+        };
+        violaciones.extend(
+            registro
+                .iter()
+                .filter(|x| x.resultado == ResultadoTransaccion::COMMIT)
+                .filter(|x| x.secuencia > tramo.desde)
+                .filter(|x| !tramo.cubierto || x.secuencia <= tramo.hasta)
+                .filter(|x| !aplicados.contains(&x.id_transaccion))
+                .map(|x| Violacion::CommitFaltante {
+                    id_nodo,
+                    id_coordinador: *id_coordinador,
+                    secuencia: x.secuencia,
+                    id_transaccion: x.id_transaccion,
+                }),
+        );
+    }
+    violaciones
+}
+
+#[cfg(test)]
+mod verificacion_test {
+    use super::*;
+
+    fn entrada(id_coordinador: u8, evento: EventoNodo) -> EntradaNodo {
+        EntradaNodo {
+            id_coordinador: IdNodo(id_coordinador),
+            evento,
+        }
+    }
+
+    fn inicio(cuentas: &[(u32, u32)]) -> EntradaNodo {
+        entrada(
+            1,
+            EventoNodo::Inicio {
+                cuentas: cuentas.iter().map(|(x, y)| (IdCuenta(*x), *y)).collect(),
+            },
+        )
+    }
+
+    fn resta(secuencia: u64, id_cuenta: u32, cantidad: u32, saldo: u32) -> EntradaNodo {
+        entrada(
+            1,
+            EventoNodo::Commit {
+                secuencia,
+                id_transaccion: IdTransaccion(100 + secuencia as u32),
+                id_cuenta: IdCuenta(id_cuenta),
+                id_cuenta_destino: None,
+                tipo: CommitType::RESTA,
+                cantidad,
+                saldos: vec![(IdCuenta(id_cuenta), saldo)],
+            },
+        )
+    }
+
+    fn commit_coordinador(secuencia: u64, id_cuenta: u32, cantidad: u32) -> EntradaRegistro {
+        EntradaRegistro {
+            secuencia,
+            id_transaccion: IdTransaccion(100 + secuencia as u32),
+            id_cuenta: IdCuenta(id_cuenta),
+            id_cuenta_destino: None,
+            tipo: CommitType::RESTA,
+            cantidad,
+            resultado: ResultadoTransaccion::COMMIT,
+        }
+    }
+
+    #[test]
+    fn registros_de_nodos_que_aplicaron_los_mismos_commits_son_consistentes() {
+        let mut registros = Registros::default();
+        for id_nodo in [1, 2] {
+            registros.nodos.insert(
+                IdNodo(id_nodo),
+                vec![
+                    inicio(&[(7, 100), (8, 50)]),
+                    resta(1, 7, 30, 70),
+                    resta(2, 8, 60, 50),
+                    resta(3, 7, 20, 50),
+                ],
+            );
+        }
+        registros.coordinadores.insert(
+            IdNodo(1),
+            vec![
+                commit_coordinador(1, 7, 30),
+                commit_coordinador(2, 8, 60),
+                commit_coordinador(3, 7, 20),
+            ],
+        );
+
+        let informe = verificar(&registros);
+        assert!(informe.consistente(), "{:?}", informe.violaciones);
+        assert_eq!(3, informe.commits);
+        assert_eq!(
+            Some(&50),
+            informe.saldos_finales[&IdNodo(2)].get(&IdCuenta(7))
+        );
+    }
+
+    #[test]
+    fn se_reportan_el_orden_distinto_los_saldos_no_derivables_y_los_commits_faltantes() {
+        let mut registros = Registros::default();
+        registros.nodos.insert(
+            IdNodo(1),
+            vec![
+                inicio(&[(7, 100)]),
+                resta(1, 7, 30, 70),
+                resta(2, 7, 20, 50),
+                resta(3, 7, 10, 40),
+            ],
+        );
+        registros.nodos.insert(
+            IdNodo(2),
+            vec![
+                inicio(&[(7, 100)]),
+                resta(2, 7, 20, 80),
+                // registra un saldo que no resulta de aplicar el commit
+                resta(1, 7, 30, 60),
+            ],
+        );
+        registros.coordinadores.insert(
+            IdNodo(1),
+            vec![
+                commit_coordinador(1, 7, 30),
+                commit_coordinador(2, 7, 20),
+                commit_coordinador(3, 7, 10),
+            ],
+        );
+
+        let violaciones = verificar(&registros).violaciones;
+        assert_eq!(
+            vec![
+                Violacion::SaldoNoDerivable {
+                    id_nodo: IdNodo(2),
+                    id_transaccion: IdTransaccion(101),
+                    id_cuenta: IdCuenta(7),
+                    esperado: Some(50),
+                    registrado: Some(60),
+                },
+                Violacion::SaldoFinalDistinto {
+                    id_cuenta: IdCuenta(7),
+                    saldos: vec![(IdNodo(1), Some(40)), (IdNodo(2), Some(50))],
+                },
+                Violacion::OrdenDistinto {
+                    id_cuenta: IdCuenta(7),
+                    nodos: (IdNodo(1), IdNodo(2)),
+                    transacciones: (IdTransaccion(101), IdTransaccion(102)),
+                },
+                Violacion::CommitFaltante {
+                    id_nodo: IdNodo(2),
+                    id_coordinador: IdNodo(1),
+                    secuencia: 3,
+                    id_transaccion: IdTransaccion(103),
+                },
+            ],
+            violaciones
+        );
+    }
+
+    #[test]
+    fn los_commits_reflejados_en_un_snapshot_no_se_exigen() {
+        let mut registros = Registros::default();
+        registros.nodos.insert(
+            IdNodo(3),
+            vec![
+                inicio(&[]),
+                entrada(
+                    1,
+                    EventoNodo::Snapshot {
+                        corte: 2,
+                        cuentas: vec![(IdCuenta(7), 50)],
+                    },
+                ),
+                resta(3, 7, 10, 40),
+                // el coordinador 1 se cae y el nodo se sincroniza con el nuevo, el 2
+                entrada(
+                    2,
+                    EventoNodo::Snapshot {
+                        corte: 0,
+                        cuentas: vec![(IdCuenta(7), 35)],
+                    },
+                ),
+            ],
+        );
+        registros.coordinadores.insert(
+            IdNodo(1),
+            (1..=4)
+                .map(|secuencia| commit_coordinador(secuencia, 7, 5))
+                .collect(),
+        );
+
+        let informe = verificar(&registros);
+        assert!(informe.consistente(), "{:?}", informe.violaciones);
+        assert_eq!(
+            Some(&35),
+            informe.saldos_finales[&IdNodo(3)].get(&IdCuenta(7))
+        );
+    }
+}
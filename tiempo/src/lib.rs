@@ -0,0 +1,5 @@
+pub mod reloj;
+pub mod reloj_virtual;
+
+pub use reloj::{Espera, Reloj, RelojReal};
+pub use reloj_virtual::RelojVirtual;
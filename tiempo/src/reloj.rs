@@ -0,0 +1,103 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+/// Espera asincronica de un reloj, se completa cuando pasa la duracion pedida
+pub type Espera = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Fuente de tiempo de los procesos. Los contenedores y dispensers del TP1 y los timeouts del bully y
+/// de la sucursal del TP2 la usan en lugar de dormir directamente, asi los tests pueden reemplazarla
+/// por un RelojVirtual y avanzar el tiempo de forma deterministica
+pub trait Reloj: Send + Sync {
+    /// Instante actual segun este reloj
+    fn ahora(&self) -> Instant;
+
+    /// Bloquea el thread actual durante la duracion indicada
+    fn dormir(&self, duracion: Duration);
+
+    /// Future que se completa luego de la duracion indicada, para esperar desde un actor sin bloquear
+    fn esperar(&self, duracion: Duration) -> Espera;
+}
+
+/// Reloj del sistema, es el que usan los procesos fuera de los tests
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RelojReal;
+
+impl Reloj for RelojReal {
+    fn ahora(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn dormir(&self, duracion: Duration) {
+        std::thread::sleep(duracion);
+    }
+
+    #[cfg(feature = "tokio")]
+    fn esperar(&self, duracion: Duration) -> Espera {
+        Box::pin(tokio::time::sleep(duracion))
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    fn esperar(&self, duracion: Duration) -> Espera {
+        Box::pin(sin_runtime::EsperaEnThread::new(duracion))
+    }
+}
+
+/// Sin el feature tokio no hay timers asincronicos disponibles, entonces la espera lanza un thread
+/// que duerme y despierta al future
+#[cfg(not(feature = "tokio"))]
+mod sin_runtime {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex, PoisonError};
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct Estado {
+        lanzada: bool,
+        completa: bool,
+        waker: Option<Waker>,
+    }
+
+    pub struct EsperaEnThread {
+        duracion: Duration,
+        estado: Arc<Mutex<Estado>>,
+    }
+
+    impl EsperaEnThread {
+        pub fn new(duracion: Duration) -> Self {
+            EsperaEnThread {
+                duracion,
+                estado: Arc::new(Mutex::new(Estado::default())),
+            }
+        }
+    }
+
+    impl Future for EsperaEnThread {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            let mut estado = self.estado.lock().unwrap_or_else(PoisonError::into_inner);
+            if estado.completa {
+                return Poll::Ready(());
+            }
+            estado.waker = Some(cx.waker().clone());
+            if !estado.lanzada {
+                estado.lanzada = true;
+                let compartido = self.estado.clone();
+                let duracion = self.duracion;
+                thread::spawn(move || {
+                    thread::sleep(duracion);
+                    let mut estado = compartido.lock().unwrap_or_else(PoisonError::into_inner);
+                    estado.completa = true;
+                    if let Some(waker) = estado.waker.take() {
+                        waker.wake();
+                    }
+                });
+            }
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::reloj::{Espera, Reloj};
+
+#[derive(Default)]
+struct Estado {
+    transcurrido: Duration,
+    dormidos: usize,
+    esperas: Vec<(Duration, Waker)>,
+}
+
+struct Interno {
+    inicio: Instant,
+    automatico: bool,
+    estado: Mutex<Estado>,
+    cvar: Condvar,
+}
+
+/// Reloj para los tests: el tiempo solo pasa cuando el test lo avanza (o, en modo automatico, cuando
+/// alguien duerme o espera), por lo que los timeouts se disparan al instante y siempre en el mismo
+/// orden. Los clones comparten el mismo tiempo, asi el test conserva un clon para avanzarlo
+#[derive(Clone)]
+pub struct RelojVirtual {
+    interno: Arc<Interno>,
+}
+
+impl RelojVirtual {
+    /// Reloj que solo avanza con `avanzar`: `dormir` bloquea y `esperar` queda pendiente hasta que el
+    /// test mueva el tiempo hasta su vencimiento
+    pub fn new() -> Self {
+        Self::crear(false)
+    }
+
+    /// Reloj que avanza solo lo que se duerme o espera, sin bloquear. Sirve cuando no hay nadie que
+    /// mueva el tiempo, como en los tests de los contenedores
+    pub fn automatico() -> Self {
+        Self::crear(true)
+    }
+
+    fn crear(automatico: bool) -> Self {
+        RelojVirtual {
+            interno: Arc::new(Interno {
+                inicio: Instant::now(),
+                automatico,
+                estado: Mutex::new(Estado::default()),
+                cvar: Condvar::new(),
+            }),
+        }
+    }
+
+    fn estado(&self) -> MutexGuard<'_, Estado> {
+        self.interno
+            .estado
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Tiempo virtual que paso desde que se creo el reloj
+    pub fn transcurrido(&self) -> Duration {
+        self.estado().transcurrido
+    }
+
+    /// Cantidad de threads dormidos y esperas pendientes, para que el test sepa cuando ya estan todos
+    /// esperando antes de avanzar el tiempo
+    pub fn pendientes(&self) -> usize {
+        let estado = self.estado();
+        estado.dormidos + estado.esperas.len()
+    }
+
+    /// Avanza el tiempo virtual, despertando a los que duermen y a las esperas que vencieron
+    pub fn avanzar(&self, duracion: Duration) {
+        let vencidas: Vec<Waker> = {
+            let mut estado = self.estado();
+            estado.transcurrido += duracion;
+            let ahora = estado.transcurrido;
+            let (vencidas, pendientes) = estado
+                .esperas
+                .drain(..)
+                .partition(|(vencimiento, _)| *vencimiento <= ahora);
+            estado.esperas = pendientes;
+            vencidas.into_iter().map(|(_, waker)| waker).collect()
+        };
+        self.interno.cvar.notify_all();
+        for waker in vencidas {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for RelojVirtual {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reloj for RelojVirtual {
+    fn ahora(&self) -> Instant {
+        self.interno.inicio + self.transcurrido()
+    }
+
+    fn dormir(&self, duracion: Duration) {
+        if self.interno.automatico {
+            self.avanzar(duracion);
+            thread::yield_now();
+            return;
+        }
+        let mut estado = self.estado();
+        let vencimiento = estado.transcurrido + duracion;
+        estado.dormidos += 1;
+        while estado.transcurrido < vencimiento {
+            estado = self
+                .interno
+                .cvar
+                .wait(estado)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        estado.dormidos -= 1;
+    }
+
+    fn esperar(&self, duracion: Duration) -> Espera {
+        if self.interno.automatico {
+            self.avanzar(duracion);
+            return Box::pin(std::future::ready(()));
+        }
+        let vencimiento = self.transcurrido() + duracion;
+        Box::pin(EsperaVirtual {
+            reloj: self.clone(),
+            vencimiento,
+        })
+    }
+}
+
+struct EsperaVirtual {
+    reloj: RelojVirtual,
+    vencimiento: Duration,
+}
+
+impl Future for EsperaVirtual {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut estado = self.reloj.estado();
+        if estado.transcurrido >= self.vencimiento {
+            return Poll::Ready(());
+        }
+        estado.esperas.push((self.vencimiento, cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod reloj_virtual_test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    use super::*;
+
+    struct ContadorDespertares(AtomicUsize);
+
+    impl Wake for ContadorDespertares {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn avanzar_mueve_el_instante_actual() {
+        let reloj = RelojVirtual::new();
+        let antes = reloj.ahora();
+        reloj.avanzar(Duration::from_secs(30));
+        assert_eq!(reloj.ahora() - antes, Duration::from_secs(30));
+        assert_eq!(reloj.transcurrido(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn dormir_en_modo_automatico_no_bloquea() {
+        let reloj = RelojVirtual::automatico();
+        let inicio = Instant::now();
+        reloj.dormir(Duration::from_secs(3600));
+        assert!(inicio.elapsed() < Duration::from_secs(1));
+        assert_eq!(reloj.transcurrido(), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn dormir_bloquea_hasta_que_se_avanza_el_tiempo() {
+        let reloj = RelojVirtual::new();
+        let reloj_thread = reloj.clone();
+        let dormido = thread::spawn(move || reloj_thread.dormir(Duration::from_secs(10)));
+        while reloj.pendientes() == 0 {
+            thread::yield_now();
+        }
+        reloj.avanzar(Duration::from_secs(5));
+        assert_eq!(reloj.pendientes(), 1);
+        reloj.avanzar(Duration::from_secs(5));
+        dormido.join().unwrap();
+        assert_eq!(reloj.pendientes(), 0);
+    }
+
+    #[test]
+    fn la_espera_se_completa_al_llegar_a_su_vencimiento() {
+        let reloj = RelojVirtual::new();
+        let contador = Arc::new(ContadorDespertares(AtomicUsize::new(0)));
+        let waker = Waker::from(contador.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut espera = reloj.esperar(Duration::from_millis(500));
+
+        assert!(espera.as_mut().poll(&mut cx).is_pending());
+        reloj.avanzar(Duration::from_millis(499));
+        assert_eq!(contador.0.load(Ordering::SeqCst), 0);
+        assert!(espera.as_mut().poll(&mut cx).is_pending());
+        reloj.avanzar(Duration::from_millis(1));
+        assert!(contador.0.load(Ordering::SeqCst) > 0);
+        assert!(espera.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn esperas_en_modo_automatico_estan_listas_de_inmediato() {
+        let reloj = RelojVirtual::automatico();
+        let waker = Waker::from(Arc::new(ContadorDespertares(AtomicUsize::new(0))));
+        let mut cx = Context::from_waker(&waker);
+        assert!(reloj
+            .esperar(Duration::from_secs(2))
+            .as_mut()
+            .poll(&mut cx)
+            .is_ready());
+        assert_eq!(reloj.transcurrido(), Duration::from_secs(2));
+    }
+}